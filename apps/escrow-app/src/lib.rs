@@ -7,14 +7,45 @@
 //! - Time-locked escrows with expiry
 //! - Multi-party escrows (2-of-2, 2-of-3)
 //! - Conditional release based on cryptographic proofs
+//! - Milestone escrows released in partial installments
 //! - Refund mechanism for expired/cancelled escrows
 
 use charms_sdk::data::{
-    charm_values, check, sum_token_amount, App, Data, Transaction, B32, TOKEN,
+    charm_values, sum_token_amount, App, Data, Transaction, B32, TOKEN,
 };
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
+/// `charms_sdk::data::check!` that also records the first failed condition,
+/// so native callers can report why the contract rejected a transaction
+macro_rules! check {
+    ($condition:expr) => {
+        if !$condition {
+            eprintln!("condition does not hold: {}", stringify!($condition));
+            FAILED_CHECK.with(|failed| {
+                failed.borrow_mut().get_or_insert(stringify!($condition));
+            });
+            return false;
+        }
+    };
+}
+
+thread_local! {
+    static FAILED_CHECK: std::cell::RefCell<Option<&'static str>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Run `app_contract` natively, returning the innermost failed condition
+/// when the transaction is rejected
+pub fn check_contract(app: &App, tx: &Transaction, x: &Data, w: &Data) -> Result<(), String> {
+    FAILED_CHECK.with(|failed| failed.borrow_mut().take());
+    if app_contract(app, tx, x, w) {
+        return Ok(());
+    }
+    Err(FAILED_CHECK
+        .with(|failed| failed.borrow_mut().take())
+        .map_or_else(|| "contract returned false".to_string(), |condition| condition.to_string()))
+}
+
 /// Escrow status
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum EscrowStatus {
@@ -127,6 +158,7 @@ fn escrow_nft_contract(app: &App, tx: &Transaction, x: &Data, w: &Data) -> bool
     match operation.as_deref() {
         Some("create") => check!(validate_escrow_creation(app, tx, w)),
         Some("release") => check!(validate_escrow_release(app, tx, w)),
+        Some("release_milestone") => check!(validate_milestone_release(app, tx, w)),
         Some("refund") => check!(validate_escrow_refund(app, tx, w)),
         Some("dispute") => check!(validate_escrow_dispute(app, tx, w)),
         Some("resolve") => check!(validate_dispute_resolution(app, tx, w)),
@@ -162,12 +194,9 @@ fn validate_escrow_creation(app: &App, tx: &Transaction, w: &Data) -> bool {
     check!(!escrow.recipient_pubkey.is_empty());
 
     // Validate escrow type requirements
-    match escrow.escrow_type {
-        EscrowType::TwoOfThree => {
-            check!(escrow.arbiter_pubkey.is_some());
-            check!(!escrow.arbiter_pubkey.as_ref().unwrap().is_empty());
-        }
-        _ => {}
+    if escrow.escrow_type == EscrowType::TwoOfThree {
+        check!(escrow.arbiter_pubkey.is_some());
+        check!(!escrow.arbiter_pubkey.as_ref().unwrap().is_empty());
     }
 
     // Verify the held tokens are actually in the escrow output
@@ -244,6 +273,56 @@ fn validate_escrow_release(app: &App, tx: &Transaction, w: &Data) -> bool {
     true
 }
 
+/// Validates a partial (milestone) release: part of the held amount goes to
+/// the recipient and the rest stays locked in a continuing escrow
+fn validate_milestone_release(app: &App, tx: &Transaction, w: &Data) -> bool {
+    let release_proof: Option<ReleaseProof> = w.value().ok();
+    check!(release_proof.is_some());
+    let proof = release_proof.unwrap();
+
+    // Get input escrow
+    let input_escrows: Vec<Escrow> = charm_values(app, tx.ins.iter().map(|(_, v)| v))
+        .filter_map(|data| data.value().ok())
+        .collect();
+    check!(input_escrows.len() == 1);
+    let input = &input_escrows[0];
+
+    // Escrow must be active, and only the depositor signs off on a milestone
+    check!(input.status == EscrowStatus::Active);
+    check!(proof.signer_pubkey == input.depositor_pubkey);
+
+    // Exactly one continuing escrow with the same terms and a smaller balance
+    let output_escrows: Vec<Escrow> = charm_values(app, tx.outs.iter())
+        .filter_map(|data| data.value().ok())
+        .collect();
+    check!(output_escrows.len() == 1);
+    let output = &output_escrows[0];
+
+    check!(output.escrow_id == input.escrow_id);
+    check!(output.depositor_pubkey == input.depositor_pubkey);
+    check!(output.recipient_pubkey == input.recipient_pubkey);
+    check!(output.arbiter_pubkey == input.arbiter_pubkey);
+    check!(output.escrow_type == input.escrow_type);
+    check!(output.held_app_id == input.held_app_id);
+    check!(output.expiry_height == input.expiry_height);
+    check!(output.status == EscrowStatus::Active);
+    check!(output.held_amount > 0);
+    check!(output.held_amount < input.held_amount);
+
+    // The remaining tokens must still be held by the continuing escrow
+    let held_app = App {
+        tag: TOKEN,
+        identity: output.held_app_id.clone(),
+        vk: app.vk.clone(),
+    };
+
+    let output_amount = sum_token_amount(&held_app, tx.outs.iter());
+    check!(output_amount.is_ok());
+    check!(output_amount.unwrap() >= output.held_amount);
+
+    true
+}
+
 /// Validates refund of escrowed assets to depositor
 fn validate_escrow_refund(app: &App, tx: &Transaction, w: &Data) -> bool {
     let refund_request: Option<RefundRequest> = w.value().ok();
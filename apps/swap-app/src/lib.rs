@@ -4,14 +4,46 @@
 //! Enables trustless cross-chain asset swaps without liquidity pools.
 
 use charms_sdk::data::{
-    charm_values, check, sum_token_amount, App, Data, Transaction, UtxoId, B32, TOKEN,
+    charm_values, sum_token_amount, App, Data, Transaction, UtxoId, B32, TOKEN,
 };
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::str::FromStr;
 
+/// `charms_sdk::data::check!` that also records the first failed condition,
+/// so native callers can report why the contract rejected a transaction
+macro_rules! check {
+    ($condition:expr) => {
+        if !$condition {
+            eprintln!("condition does not hold: {}", stringify!($condition));
+            FAILED_CHECK.with(|failed| {
+                failed.borrow_mut().get_or_insert(stringify!($condition));
+            });
+            return false;
+        }
+    };
+}
+
+thread_local! {
+    static FAILED_CHECK: std::cell::RefCell<Option<&'static str>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Run `app_contract` natively, returning the innermost failed condition
+/// when the transaction is rejected
+pub fn check_contract(app: &App, tx: &Transaction, x: &Data, w: &Data) -> Result<(), String> {
+    FAILED_CHECK.with(|failed| failed.borrow_mut().take());
+    if app_contract(app, tx, x, w) {
+        return Ok(());
+    }
+    Err(FAILED_CHECK
+        .with(|failed| failed.borrow_mut().take())
+        .map_or_else(|| "contract returned false".to_string(), |condition| condition.to_string()))
+}
+
 /// Order status enumeration
+/// Serialized as its numeric discriminant to match the spell templates (`status: 0`)
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "u8", try_from = "u8")]
 pub enum OrderStatus {
     Open = 0,
     Filled = 1,
@@ -19,6 +51,26 @@ pub enum OrderStatus {
     Expired = 3,
 }
 
+impl From<OrderStatus> for u8 {
+    fn from(status: OrderStatus) -> Self {
+        status as u8
+    }
+}
+
+impl TryFrom<u8> for OrderStatus {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(OrderStatus::Open),
+            1 => Ok(OrderStatus::Filled),
+            2 => Ok(OrderStatus::Cancelled),
+            3 => Ok(OrderStatus::Expired),
+            other => Err(format!("invalid order status: {}", other)),
+        }
+    }
+}
+
 /// Swap order NFT content
 /// This NFT represents an open order in the orderbook
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -321,6 +373,27 @@ mod tests {
         assert_eq!(order.filled_amount, 0);
     }
 
+    #[test]
+    fn test_check_contract_reports_failed_condition() {
+        let tx = Transaction {
+            ins: vec![],
+            refs: vec![],
+            outs: vec![],
+            coin_ins: None,
+            coin_outs: None,
+            prev_txs: Default::default(),
+            app_public_inputs: Default::default(),
+        };
+        let order_app = App { tag: ORDER_NFT, identity: B32([0u8; 32]), vk: B32([0u8; 32]) };
+        let create = Data::from(&"create".to_string());
+
+        let failed = check_contract(&order_app, &tx, &create, &Data::empty()).unwrap_err();
+        assert_eq!(failed, "w_str.is_some()");
+
+        let unknown = App { tag: 'x', ..order_app };
+        assert_eq!(check_contract(&unknown, &tx, &create, &Data::empty()).unwrap_err(), "contract returned false");
+    }
+
     #[test]
     fn test_hash() {
         let data = "test_utxo_id";
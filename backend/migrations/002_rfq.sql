@@ -0,0 +1,36 @@
+-- Request-for-quote workflow
+
+CREATE TABLE IF NOT EXISTS rfqs (
+    id VARCHAR(255) PRIMARY KEY,
+    taker_address VARCHAR(255) NOT NULL,
+    buy_token VARCHAR(100) NOT NULL,
+    buy_amount VARCHAR(100) NOT NULL,
+    sell_token VARCHAR(100) NOT NULL,
+    source_chain VARCHAR(50) NOT NULL,
+    dest_chain VARCHAR(50) NOT NULL,
+    status VARCHAR(50) NOT NULL DEFAULT 'open',
+    accepted_quote_id VARCHAR(255),
+    order_id VARCHAR(255),
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (order_id) REFERENCES orders(id) ON DELETE SET NULL
+);
+
+CREATE TABLE IF NOT EXISTS rfq_quotes (
+    id VARCHAR(255) PRIMARY KEY,
+    rfq_id VARCHAR(255) NOT NULL,
+    maker_address VARCHAR(255) NOT NULL,
+    maker_pubkey VARCHAR(255) NOT NULL,
+    sell_amount VARCHAR(100) NOT NULL,
+    funding_utxo VARCHAR(255) NOT NULL,
+    funding_utxo_value BIGINT,
+    signature TEXT NOT NULL,
+    status VARCHAR(50) NOT NULL DEFAULT 'pending',
+    valid_until TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (rfq_id) REFERENCES rfqs(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_rfqs_status ON rfqs(status);
+CREATE INDEX IF NOT EXISTS idx_rfq_quotes_rfq ON rfq_quotes(rfq_id);
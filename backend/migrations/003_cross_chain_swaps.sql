@@ -0,0 +1,14 @@
+-- Cross-chain swap coordinator state (one row per cross-chain order)
+
+CREATE TABLE IF NOT EXISTS cross_chain_swaps (
+    order_id VARCHAR(255) PRIMARY KEY,
+    source_chain VARCHAR(50) NOT NULL,
+    dest_chain VARCHAR(50) NOT NULL,
+    source_state VARCHAR(50) NOT NULL DEFAULT 'pending',
+    dest_state VARCHAR(50) NOT NULL DEFAULT 'pending',
+    source_txid VARCHAR(255),
+    dest_txid VARCHAR(255),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (order_id) REFERENCES orders(id) ON DELETE CASCADE
+);
@@ -0,0 +1,10 @@
+-- Free-form order tags for programmatic traders
+
+CREATE TABLE IF NOT EXISTS order_tags (
+    order_id VARCHAR(255) NOT NULL,
+    tag VARCHAR(64) NOT NULL,
+    PRIMARY KEY (order_id, tag),
+    FOREIGN KEY (order_id) REFERENCES orders(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_order_tags_tag ON order_tags(tag);
@@ -0,0 +1,23 @@
+-- Off-chain signed order intents, settled on fill
+
+CREATE TABLE IF NOT EXISTS order_intents (
+    id VARCHAR(255) PRIMARY KEY,
+    maker_address VARCHAR(255) NOT NULL,
+    maker_pubkey VARCHAR(255) NOT NULL,
+    offer_token VARCHAR(100) NOT NULL,
+    offer_amount VARCHAR(100) NOT NULL,
+    want_token VARCHAR(100) NOT NULL,
+    want_amount VARCHAR(100) NOT NULL,
+    source_chain VARCHAR(50) NOT NULL,
+    dest_chain VARCHAR(50) NOT NULL,
+    maker_utxo VARCHAR(255) NOT NULL,
+    signature TEXT NOT NULL,
+    status VARCHAR(50) NOT NULL DEFAULT 'open',
+    order_id VARCHAR(255),
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (order_id) REFERENCES orders(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_order_intents_status ON order_intents(status);
@@ -0,0 +1,14 @@
+-- Reusable escrow presets for marketplace integrations
+
+CREATE TABLE IF NOT EXISTS escrow_templates (
+    id VARCHAR(255) PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    escrow_type SMALLINT NOT NULL,
+    arbiter_pubkey VARCHAR(255),
+    expiry_blocks BIGINT NOT NULL,
+    fee_bps INTEGER NOT NULL DEFAULT 0,
+    fee_recipient VARCHAR(255),
+    depositor_fee_share_bps INTEGER NOT NULL DEFAULT 5000,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
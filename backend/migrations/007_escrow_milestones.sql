@@ -0,0 +1,14 @@
+-- Milestone schedules for escrows released in installments
+
+CREATE TABLE IF NOT EXISTS escrow_milestones (
+    escrow_id VARCHAR(255) NOT NULL,
+    idx INTEGER NOT NULL,
+    amount BIGINT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    status VARCHAR(50) NOT NULL DEFAULT 'pending',
+    remaining_amount BIGINT,
+    tx_id VARCHAR(255),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    released_at TIMESTAMPTZ,
+    PRIMARY KEY (escrow_id, idx)
+);
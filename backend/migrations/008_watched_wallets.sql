@@ -0,0 +1,12 @@
+-- Watch-only wallets registered by xpub or output descriptor
+
+CREATE TABLE IF NOT EXISTS watched_wallets (
+    id VARCHAR(255) PRIMARY KEY,
+    owner_address VARCHAR(255) NOT NULL,
+    label VARCHAR(255),
+    descriptors TEXT[] NOT NULL,
+    range_end INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_watched_wallets_owner ON watched_wallets(owner_address);
@@ -0,0 +1,13 @@
+-- Spell index: charm effects of broadcast spell transactions, keyed by txid
+
+CREATE TABLE IF NOT EXISTS spell_transactions (
+    txid VARCHAR(255) PRIMARY KEY,
+    action VARCHAR(50) NOT NULL,
+    order_id VARCHAR(255),
+    escrow_id VARCHAR(255),
+    token_id VARCHAR(255),
+    amount VARCHAR(100),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_spell_transactions_order ON spell_transactions(order_id);
@@ -0,0 +1,12 @@
+-- Funding UTXO reservations, so concurrent drafts cannot claim the same UTXO
+
+CREATE TABLE IF NOT EXISTS utxo_locks (
+    outpoint VARCHAR(255) PRIMARY KEY,
+    owner_address VARCHAR(255) NOT NULL,
+    order_id VARCHAR(255),
+    escrow_id VARCHAR(255),
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_utxo_locks_owner ON utxo_locks(owner_address);
@@ -0,0 +1,21 @@
+-- Address watch subscriptions and the deposits already notified for them
+
+CREATE TABLE IF NOT EXISTS address_subscriptions (
+    id VARCHAR(255) PRIMARY KEY,
+    owner_address VARCHAR(255) NOT NULL,
+    address VARCHAR(255) NOT NULL,
+    label VARCHAR(255),
+    webhook_url TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_address_subscriptions_owner ON address_subscriptions(owner_address);
+
+CREATE TABLE IF NOT EXISTS address_deposits (
+    subscription_id VARCHAR(255) NOT NULL,
+    outpoint VARCHAR(255) NOT NULL,
+    value BIGINT NOT NULL,
+    confirmed BOOLEAN NOT NULL DEFAULT FALSE,
+    seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (subscription_id, outpoint)
+);
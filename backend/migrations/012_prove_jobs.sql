@@ -0,0 +1,14 @@
+-- Queued spell proving jobs, worked off by background prover workers
+
+CREATE TABLE IF NOT EXISTS prove_jobs (
+    id VARCHAR(255) PRIMARY KEY,
+    status VARCHAR(50) NOT NULL DEFAULT 'queued',
+    request TEXT NOT NULL,
+    result TEXT,
+    error TEXT,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_prove_jobs_queued ON prove_jobs(created_at) WHERE status = 'queued';
@@ -0,0 +1,10 @@
+-- Proved transactions keyed by a hash of the prove request, reused when the
+-- same spell is proved again
+
+CREATE TABLE IF NOT EXISTS proof_cache (
+    cache_key VARCHAR(64) PRIMARY KEY,
+    transactions TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_proof_cache_created ON proof_cache(created_at);
@@ -0,0 +1,22 @@
+-- Versioned spell templates; exactly one version per name is active
+
+CREATE TABLE IF NOT EXISTS spell_templates (
+    name VARCHAR(100) NOT NULL,
+    version INTEGER NOT NULL,
+    body TEXT NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (name, version)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_spell_templates_active ON spell_templates(name) WHERE active;
+
+-- Template versions used to build each order's or escrow's spells
+
+CREATE TABLE IF NOT EXISTS spell_template_uses (
+    subject_id VARCHAR(255) NOT NULL,
+    template_name VARCHAR(100) NOT NULL,
+    template_version INTEGER NOT NULL,
+    used_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (subject_id, template_name, template_version)
+);
@@ -0,0 +1,4 @@
+-- Private inputs and stable error codes for queued prove jobs
+
+ALTER TABLE prove_jobs ADD COLUMN IF NOT EXISTS private_inputs TEXT;
+ALTER TABLE prove_jobs ADD COLUMN IF NOT EXISTS error_code VARCHAR(50);
@@ -0,0 +1,8 @@
+-- Charms app binaries, content-addressed by hash
+
+CREATE TABLE IF NOT EXISTS app_binaries (
+    hash VARCHAR(64) PRIMARY KEY,
+    data BYTEA NOT NULL,
+    size BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,9 @@
+-- Signed transactions returned by the prover, kept for rebroadcast
+
+CREATE TABLE IF NOT EXISTS proved_transactions (
+    subject_id VARCHAR(255) NOT NULL,
+    txid VARCHAR(64) NOT NULL,
+    tx_hex TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (subject_id, txid)
+);
@@ -0,0 +1,15 @@
+-- Audit log of every spell sent to the prover
+
+CREATE TABLE IF NOT EXISTS spells (
+    id VARCHAR(36) PRIMARY KEY,
+    order_id VARCHAR(255) NOT NULL,
+    spell TEXT NOT NULL,
+    request_hash VARCHAR(64) NOT NULL,
+    status VARCHAR(20) NOT NULL,
+    transactions TEXT,
+    error TEXT,
+    prove_ms BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_spells_order ON spells(order_id, created_at);
@@ -0,0 +1,34 @@
+-- Charm UTXO index and the block progress of its indexer
+
+CREATE TABLE IF NOT EXISTS charm_utxos (
+    utxo_id VARCHAR(100) NOT NULL,
+    app VARCHAR(300) NOT NULL,
+    tag VARCHAR(10) NOT NULL,
+    app_id VARCHAR(64) NOT NULL,
+    vk VARCHAR(64) NOT NULL,
+    address VARCHAR(100),
+    value BIGINT NOT NULL,
+    data TEXT NOT NULL,
+    block_height BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (utxo_id, app)
+);
+
+ALTER TABLE charm_utxos ADD COLUMN IF NOT EXISTS spent_height BIGINT;
+
+CREATE INDEX IF NOT EXISTS idx_charm_utxos_app_id ON charm_utxos(app_id);
+CREATE INDEX IF NOT EXISTS idx_charm_utxos_address ON charm_utxos(address);
+
+CREATE TABLE IF NOT EXISTS indexer_state (
+    name VARCHAR(50) PRIMARY KEY,
+    height BIGINT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS indexed_blocks (
+    indexer VARCHAR(50) NOT NULL,
+    height BIGINT NOT NULL,
+    hash VARCHAR(64) NOT NULL,
+    indexed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (indexer, height)
+);
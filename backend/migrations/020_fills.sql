@@ -0,0 +1,21 @@
+-- One row per fill of an order, full or partial, for trade history and
+-- market statistics
+
+CREATE TABLE IF NOT EXISTS fills (
+    id VARCHAR(36) PRIMARY KEY,
+    order_id VARCHAR(255) NOT NULL,
+    taker_address VARCHAR(255) NOT NULL,
+    -- Offer token units taken
+    amount BIGINT NOT NULL,
+    -- Want token units paid per offer token unit
+    price DOUBLE PRECISION NOT NULL,
+    txid VARCHAR(64) NOT NULL,
+    -- Network fee in sats, once known
+    fee BIGINT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (order_id) REFERENCES orders(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_fills_order ON fills(order_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_fills_created ON fills(created_at);
@@ -0,0 +1,65 @@
+-- Escrow rows hold the full escrow state served by the API instead of a thin
+-- link to an order. Legacy link rows keep what maps across; columns they never
+-- recorded are backfilled empty.
+
+ALTER TABLE escrows RENAME COLUMN token TO held_token_id;
+ALTER TABLE escrows RENAME COLUMN amount TO held_amount;
+ALTER TABLE escrows RENAME COLUMN hashlock TO release_hash;
+ALTER TABLE escrows RENAME COLUMN lock_time TO expiry_height;
+ALTER TABLE escrows RENAME COLUMN preimage TO revealed_preimage;
+
+-- Amounts were always whole base units written as strings; a row holding
+-- anything else stops the migration rather than being guessed at
+DO $$
+DECLARE
+    bad BIGINT;
+BEGIN
+    SELECT COUNT(*) INTO bad FROM escrows WHERE btrim(held_amount) !~ '^[0-9]{1,18}$';
+    IF bad > 0 THEN
+        RAISE EXCEPTION '% escrows have amounts that are not whole base units; fix them before migrating', bad;
+    END IF;
+END $$;
+
+ALTER TABLE escrows ALTER COLUMN held_amount TYPE BIGINT USING btrim(held_amount)::BIGINT;
+UPDATE escrows SET expiry_height = 0 WHERE expiry_height IS NULL;
+ALTER TABLE escrows ALTER COLUMN expiry_height SET NOT NULL;
+ALTER TABLE escrows ALTER COLUMN depositor_address DROP NOT NULL;
+ALTER TABLE escrows ALTER COLUMN recipient_address DROP NOT NULL;
+ALTER TABLE escrows ALTER COLUMN created_at TYPE TIMESTAMPTZ USING created_at AT TIME ZONE 'UTC';
+ALTER TABLE escrows ALTER COLUMN updated_at TYPE TIMESTAMPTZ USING updated_at AT TIME ZONE 'UTC';
+
+-- Link rows were written as 'pending' and never recorded an escrow on chain,
+-- so they are kept as 'void' rather than served as live escrows
+UPDATE escrows SET status = 'void'
+WHERE status NOT IN ('active', 'released', 'refunded', 'expired', 'disputed');
+ALTER TABLE escrows ALTER COLUMN status SET DEFAULT 'active';
+ALTER TABLE escrows ADD CONSTRAINT escrows_status_check
+    CHECK (status IN ('active', 'released', 'refunded', 'expired', 'disputed', 'void'));
+
+ALTER TABLE escrows
+    ADD COLUMN escrow_id VARCHAR(64) NOT NULL DEFAULT '',
+    ADD COLUMN depositor_pubkey VARCHAR(255) NOT NULL DEFAULT '',
+    ADD COLUMN recipient_pubkey VARCHAR(255) NOT NULL DEFAULT '',
+    ADD COLUMN arbiter_pubkey VARCHAR(255),
+    ADD COLUMN escrow_type VARCHAR(20) NOT NULL DEFAULT 'two_party'
+        CHECK (escrow_type IN ('two_party', 'two_of_two', 'two_of_three')),
+    ADD COLUMN created_height BIGINT NOT NULL DEFAULT 0,
+    ADD COLUMN escrow_address VARCHAR(100) NOT NULL DEFAULT '',
+    ADD COLUMN utxo_id VARCHAR(255),
+    ADD COLUMN tx_id VARCHAR(255),
+    -- JSON of the spell awaiting broadcast, dispute, fee split and deposit
+    ADD COLUMN pending_action TEXT,
+    ADD COLUMN dispute TEXT,
+    ADD COLUMN fee TEXT,
+    ADD COLUMN deposit TEXT,
+    ADD COLUMN template_id VARCHAR(255);
+
+ALTER TABLE escrows
+    ALTER COLUMN escrow_id DROP DEFAULT,
+    ALTER COLUMN depositor_pubkey DROP DEFAULT,
+    ALTER COLUMN recipient_pubkey DROP DEFAULT,
+    ALTER COLUMN escrow_type DROP DEFAULT,
+    ALTER COLUMN created_height DROP DEFAULT,
+    ALTER COLUMN escrow_address DROP DEFAULT;
+
+CREATE INDEX IF NOT EXISTS idx_escrows_address ON escrows(escrow_address);
@@ -0,0 +1,6 @@
+-- Indexes behind the filtered, paginated order listing
+
+CREATE INDEX IF NOT EXISTS idx_orders_created ON orders(created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_orders_pair ON orders(offer_token, want_token);
+CREATE INDEX IF NOT EXISTS idx_orders_chains ON orders(source_chain, dest_chain);
+CREATE INDEX IF NOT EXISTS idx_orders_expiry ON orders(expiry_height);
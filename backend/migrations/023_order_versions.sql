@@ -0,0 +1,4 @@
+-- Version bumped on every order update, so concurrent writers detect each
+-- other instead of overwriting
+
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS version BIGINT NOT NULL DEFAULT 0;
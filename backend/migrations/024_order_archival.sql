@@ -0,0 +1,6 @@
+-- Finished orders past the retention window are flagged archived and left out
+-- of listings unless asked for
+
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS archived_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_orders_unarchived ON orders(created_at DESC) WHERE archived_at IS NULL;
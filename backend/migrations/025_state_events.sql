@@ -0,0 +1,26 @@
+-- Append-only audit trail of order, escrow and transaction status changes:
+-- who moved what from which status to which, why, and the transaction behind it
+
+CREATE TABLE IF NOT EXISTS events (
+    id BIGSERIAL PRIMARY KEY,
+    subject_type VARCHAR(20) NOT NULL CHECK (subject_type IN ('order', 'escrow', 'transaction')),
+    subject_id VARCHAR(255) NOT NULL,
+    actor VARCHAR(255) NOT NULL,
+    old_status VARCHAR(50),
+    new_status VARCHAR(50) NOT NULL,
+    reason TEXT NOT NULL,
+    txid VARCHAR(64),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_events_subject ON events(subject_type, subject_id, created_at);
+
+CREATE OR REPLACE FUNCTION events_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'events is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER events_append_only
+    BEFORE UPDATE OR DELETE ON events
+    FOR EACH ROW EXECUTE FUNCTION events_append_only();
@@ -0,0 +1,33 @@
+-- Durable queue for background work. A worker claims a due job under a lease
+-- (locked_by/locked_until); a job whose worker died is claimed again once the
+-- lease runs out, and a failed one returns to the queue at a later run_at
+-- until it runs out of attempts.
+
+CREATE TABLE IF NOT EXISTS jobs (
+    id BIGSERIAL PRIMARY KEY,
+    job_type VARCHAR(50) NOT NULL,
+    payload TEXT NOT NULL DEFAULT '{}',
+    status VARCHAR(20) NOT NULL DEFAULT 'queued'
+        CHECK (status IN ('queued', 'running', 'succeeded', 'failed')),
+    run_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL DEFAULT 5,
+    locked_by VARCHAR(255),
+    locked_until TIMESTAMPTZ,
+    last_error TEXT,
+    -- At most one queued or running job per key, e.g. for recurring sweeps
+    unique_key VARCHAR(255),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_jobs_due ON jobs(job_type, run_at) WHERE status = 'queued';
+CREATE INDEX IF NOT EXISTS idx_jobs_leased ON jobs(job_type, locked_until) WHERE status = 'running';
+CREATE UNIQUE INDEX IF NOT EXISTS idx_jobs_unique_key ON jobs(unique_key)
+    WHERE unique_key IS NOT NULL AND status IN ('queued', 'running');
+
+-- Prove jobs waiting or interrupted before this table existed
+INSERT INTO jobs (job_type, payload, run_at, created_at)
+SELECT 'prove', json_build_object('prove_job_id', id)::text, created_at, created_at
+FROM prove_jobs
+WHERE status IN ('queued', 'running');
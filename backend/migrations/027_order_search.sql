@@ -0,0 +1,14 @@
+-- Trigram indexes for finding an order from any fragment of its id, maker
+-- address, tokens, tags or transaction ids
+
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX IF NOT EXISTS idx_orders_id_trgm ON orders USING GIN (id gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_orders_maker_trgm ON orders USING GIN (maker_address gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_orders_offer_token_trgm ON orders USING GIN (offer_token gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_orders_want_token_trgm ON orders USING GIN (want_token gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_orders_tx_id_trgm ON orders USING GIN (tx_id gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_orders_utxo_id_trgm ON orders USING GIN (utxo_id gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_order_tags_tag_trgm ON order_tags USING GIN (tag gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_transactions_txid_trgm ON transactions USING GIN (txid gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_fills_txid_trgm ON fills USING GIN (txid gin_trgm_ops);
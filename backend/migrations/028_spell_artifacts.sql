@@ -0,0 +1,17 @@
+-- Keep what built each order spell and how long proving took: the operation,
+-- the template version, the prove job that proved it (for queued proofs) and
+-- when proving started and finished. Queued spells are recorded before they
+-- are proved.
+
+ALTER TABLE spells ADD COLUMN IF NOT EXISTS operation VARCHAR(50);
+ALTER TABLE spells ADD COLUMN IF NOT EXISTS template_name VARCHAR(100);
+ALTER TABLE spells ADD COLUMN IF NOT EXISTS template_version INTEGER;
+ALTER TABLE spells ADD COLUMN IF NOT EXISTS prove_job_id VARCHAR(255);
+ALTER TABLE spells ADD COLUMN IF NOT EXISTS started_at TIMESTAMPTZ;
+ALTER TABLE spells ADD COLUMN IF NOT EXISTS finished_at TIMESTAMPTZ;
+
+UPDATE spells
+SET started_at = created_at - prove_ms * INTERVAL '1 millisecond', finished_at = created_at
+WHERE started_at IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_spells_prove_job ON spells(prove_job_id) WHERE prove_job_id IS NOT NULL;
@@ -0,0 +1,30 @@
+-- Wallet users and their connect sessions. A user is a wallet address proven
+-- by a BIP-322 signature; a session is a bearer token (stored as its SHA-256)
+-- opened by one user, with every wallet linked to it.
+
+CREATE TABLE IF NOT EXISTS users (
+    id VARCHAR(36) PRIMARY KEY,
+    address VARCHAR(255) NOT NULL UNIQUE,
+    pubkey VARCHAR(66),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS sessions (
+    token_hash VARCHAR(64) PRIMARY KEY,
+    user_id VARCHAR(36) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_sessions_user ON sessions(user_id);
+CREATE INDEX IF NOT EXISTS idx_sessions_expires ON sessions(expires_at);
+
+CREATE TABLE IF NOT EXISTS session_wallets (
+    id BIGSERIAL PRIMARY KEY,
+    token_hash VARCHAR(64) NOT NULL REFERENCES sessions(token_hash) ON DELETE CASCADE,
+    address VARCHAR(255) NOT NULL,
+    pubkey VARCHAR(66),
+    linked_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (token_hash, address)
+);
@@ -0,0 +1,37 @@
+-- Webhook subscriptions registered by users, and the log of every delivery
+-- attempt made to them. Deliveries are queued as `webhook` jobs, which retry
+-- with backoff; each attempt, failed or not, adds a row here.
+
+CREATE TABLE IF NOT EXISTS webhook_subscriptions (
+    id VARCHAR(36) PRIMARY KEY,
+    user_id VARCHAR(36) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    -- Key for the HMAC-SHA256 signature sent with every delivery
+    secret VARCHAR(128) NOT NULL,
+    -- JSON array of event kinds, `order.*` style prefixes, or empty for all
+    event_filters TEXT NOT NULL DEFAULT '[]',
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhook_subscriptions_user ON webhook_subscriptions(user_id);
+CREATE INDEX IF NOT EXISTS idx_webhook_subscriptions_active ON webhook_subscriptions(created_at) WHERE active;
+
+CREATE TABLE IF NOT EXISTS webhook_deliveries (
+    id BIGSERIAL PRIMARY KEY,
+    subscription_id VARCHAR(36) NOT NULL REFERENCES webhook_subscriptions(id) ON DELETE CASCADE,
+    -- Same for every attempt at one event, and sent as X-Webhook-Id
+    event_id VARCHAR(36) NOT NULL,
+    event_kind VARCHAR(100) NOT NULL,
+    subject_id VARCHAR(255) NOT NULL,
+    attempt INTEGER NOT NULL,
+    succeeded BOOLEAN NOT NULL,
+    status_code INTEGER,
+    error TEXT,
+    duration_ms BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_subscription ON webhook_deliveries(subscription_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_event ON webhook_deliveries(event_id, attempt);
@@ -0,0 +1,30 @@
+-- Order amounts become BIGINT token base units so volumes can be summed and
+-- sizes compared in SQL. Amounts were always whole base units written as
+-- strings; a row holding anything else stops the migration rather than being
+-- guessed at.
+
+DO $$
+DECLARE
+    bad BIGINT;
+BEGIN
+    SELECT COUNT(*) INTO bad FROM orders
+    WHERE btrim(offer_amount) !~ '^[0-9]{1,18}$'
+       OR btrim(want_amount) !~ '^[0-9]{1,18}$'
+       OR (filled_amount IS NOT NULL AND btrim(filled_amount) !~ '^[0-9]{0,18}$');
+    IF bad > 0 THEN
+        RAISE EXCEPTION '% orders have amounts that are not whole base units; fix them before migrating', bad;
+    END IF;
+END $$;
+
+ALTER TABLE orders ALTER COLUMN offer_amount TYPE BIGINT USING btrim(offer_amount)::BIGINT;
+ALTER TABLE orders ALTER COLUMN want_amount TYPE BIGINT USING btrim(want_amount)::BIGINT;
+
+ALTER TABLE orders ALTER COLUMN filled_amount DROP DEFAULT;
+ALTER TABLE orders ALTER COLUMN filled_amount TYPE BIGINT
+    USING COALESCE(NULLIF(btrim(filled_amount), ''), '0')::BIGINT;
+UPDATE orders SET filled_amount = 0 WHERE filled_amount IS NULL;
+ALTER TABLE orders ALTER COLUMN filled_amount SET DEFAULT 0;
+ALTER TABLE orders ALTER COLUMN filled_amount SET NOT NULL;
+
+ALTER TABLE orders ADD CONSTRAINT orders_amounts_check
+    CHECK (offer_amount >= 0 AND want_amount >= 0 AND filled_amount >= 0);
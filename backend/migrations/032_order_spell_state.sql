@@ -0,0 +1,7 @@
+-- The maker key and payout address are part of the order's on-chain state,
+-- and spells spending the order (fills, cancellation) must restate them.
+-- Orders created before this have neither; spells fall back to the maker
+-- address for both, as order creation did.
+
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS maker_pubkey TEXT;
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS dest_address TEXT;
@@ -0,0 +1,16 @@
+-- Order transactions that were proved but not yet broadcast, keyed by txid,
+-- with what broadcasting each does to its order (cancel it, or fill some of
+-- it). The order only changes once the network has accepted the transaction;
+-- the rest of an order's pending actions go when one of them is applied.
+
+CREATE TABLE IF NOT EXISTS pending_order_actions (
+    txid VARCHAR(64) PRIMARY KEY,
+    order_id VARCHAR(255) NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    action VARCHAR(20) NOT NULL,
+    actor_address VARCHAR(255) NOT NULL,
+    fill_amount BIGINT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT pending_order_actions_fill_check CHECK ((action = 'cancel') = (fill_amount IS NULL) AND (fill_amount IS NULL OR fill_amount > 0))
+);
+
+CREATE INDEX IF NOT EXISTS idx_pending_order_actions_order ON pending_order_actions(order_id);
@@ -0,0 +1,6 @@
+-- Order spells keep their private inputs (signatures, preimages) apart from
+-- the rest of the spell, sealed like prove job inputs, so the spell column
+-- and the spells API never show them. Rows written before this keep the
+-- inputs in the spell itself.
+
+ALTER TABLE spells ADD COLUMN IF NOT EXISTS private_inputs TEXT;
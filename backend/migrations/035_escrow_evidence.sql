@@ -0,0 +1,15 @@
+-- Dispute evidence submitted by an escrow's parties. Documents live in
+-- object storage; only their hash and storage reference are kept here.
+
+CREATE TABLE IF NOT EXISTS escrow_evidence (
+    id VARCHAR(255) PRIMARY KEY,
+    escrow_id VARCHAR(255) NOT NULL REFERENCES escrows(id) ON DELETE CASCADE,
+    submitter_pubkey VARCHAR(66) NOT NULL,
+    content_hash VARCHAR(64) NOT NULL,
+    storage_ref TEXT NOT NULL,
+    content_type VARCHAR(255),
+    description TEXT,
+    submitted_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_escrow_evidence_escrow ON escrow_evidence(escrow_id, submitted_at);
@@ -0,0 +1,11 @@
+-- Order and transaction times were created without a time zone, but are read
+-- as UTC instants like every later table's. Stored values were written in
+-- UTC and keep their meaning.
+
+ALTER TABLE orders ALTER COLUMN created_at TYPE TIMESTAMPTZ USING created_at AT TIME ZONE 'UTC';
+ALTER TABLE orders ALTER COLUMN updated_at TYPE TIMESTAMPTZ USING updated_at AT TIME ZONE 'UTC';
+
+ALTER TABLE transactions ALTER COLUMN signed_at TYPE TIMESTAMPTZ USING signed_at AT TIME ZONE 'UTC';
+ALTER TABLE transactions ALTER COLUMN broadcast_at TYPE TIMESTAMPTZ USING broadcast_at AT TIME ZONE 'UTC';
+ALTER TABLE transactions ALTER COLUMN confirmed_at TYPE TIMESTAMPTZ USING confirmed_at AT TIME ZONE 'UTC';
+ALTER TABLE transactions ALTER COLUMN created_at TYPE TIMESTAMPTZ USING created_at AT TIME ZONE 'UTC';
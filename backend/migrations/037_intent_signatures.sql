@@ -0,0 +1,9 @@
+-- A signed intent can be posted once: its signature, stored canonically, is
+-- unique. Earlier copies of a signature keep the oldest intent.
+
+DELETE FROM order_intents a
+USING order_intents b
+WHERE a.signature = b.signature
+  AND (a.created_at, a.id) > (b.created_at, b.id);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_order_intents_signature ON order_intents(signature);
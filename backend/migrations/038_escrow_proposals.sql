@@ -0,0 +1,37 @@
+-- Escrow proposals awaiting the other parties' signed acceptance, previously
+-- held in process memory. A proposal records the escrow built from it once
+-- created; ones never created are pruned after ESCROW_PROPOSAL_TTL_DAYS.
+
+CREATE TABLE IF NOT EXISTS escrow_proposals (
+    id VARCHAR(36) PRIMARY KEY,
+    depositor_pubkey VARCHAR(255) NOT NULL,
+    recipient_pubkey VARCHAR(255) NOT NULL,
+    arbiter_pubkey VARCHAR(255),
+    escrow_type VARCHAR(20) NOT NULL
+        CHECK (escrow_type IN ('two_party', 'two_of_two', 'two_of_three')),
+    token_id VARCHAR(255) NOT NULL,
+    amount BIGINT NOT NULL,
+    release_hash VARCHAR(64),
+    expiry_height BIGINT NOT NULL,
+    order_id VARCHAR(255),
+    -- SHA-256 of the canonical terms, which every party signs
+    terms_hash VARCHAR(64) NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'accepted', 'created')),
+    escrow_id VARCHAR(255),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_escrow_proposals_depositor ON escrow_proposals(depositor_pubkey);
+CREATE INDEX IF NOT EXISTS idx_escrow_proposals_recipient ON escrow_proposals(recipient_pubkey);
+CREATE INDEX IF NOT EXISTS idx_escrow_proposals_arbiter ON escrow_proposals(arbiter_pubkey);
+CREATE INDEX IF NOT EXISTS idx_escrow_proposals_created ON escrow_proposals(created_at) WHERE status <> 'created';
+
+CREATE TABLE IF NOT EXISTS escrow_proposal_acceptances (
+    proposal_id VARCHAR(36) NOT NULL REFERENCES escrow_proposals(id) ON DELETE CASCADE,
+    pubkey VARCHAR(255) NOT NULL,
+    signature TEXT NOT NULL,
+    accepted_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (proposal_id, pubkey)
+);
@@ -0,0 +1,7 @@
+-- Who uploaded each app binary, so uploads count against the user's quota;
+-- NULL for binaries uploaded with the admin token or before this migration
+
+ALTER TABLE app_binaries ADD COLUMN IF NOT EXISTS uploaded_by VARCHAR(36) REFERENCES users(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_app_binaries_uploaded_by ON app_binaries(uploaded_by);
+CREATE INDEX IF NOT EXISTS idx_app_binaries_created ON app_binaries(created_at);
@@ -0,0 +1,6 @@
+-- Escrow listings page through escrows newest first, keyed on the last
+-- escrow of the previous page
+
+CREATE INDEX IF NOT EXISTS idx_escrows_created ON escrows(created_at DESC, id);
+CREATE INDEX IF NOT EXISTS idx_escrows_depositor ON escrows(depositor_pubkey);
+CREATE INDEX IF NOT EXISTS idx_escrows_recipient ON escrows(recipient_pubkey);
@@ -0,0 +1,6 @@
+-- A broadcast fill can be replaced at a higher fee by its taker. The
+-- replacement spends the offer output the stuck fill spent, not the order's
+-- current one (the fill's own), so the pending action keeps the outpoint its
+-- transaction must spend.
+
+ALTER TABLE pending_order_actions ADD COLUMN IF NOT EXISTS spends VARCHAR(80);
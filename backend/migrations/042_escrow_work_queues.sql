@@ -0,0 +1,7 @@
+-- The arbiter and party listings look escrows up by key, and the dispute
+-- queue pages through disputed escrows oldest dispute first
+
+CREATE INDEX IF NOT EXISTS idx_escrows_arbiter ON escrows(arbiter_pubkey);
+CREATE INDEX IF NOT EXISTS idx_escrows_disputes
+    ON escrows(((dispute::jsonb ->> 'opened_at')::BIGINT), id)
+    WHERE dispute IS NOT NULL;
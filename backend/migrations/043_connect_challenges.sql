@@ -0,0 +1,13 @@
+-- Outstanding wallet connect challenges, one per address. A challenge is
+-- consumed by the first verification attempt on any instance, and expired
+-- rows are swept whenever a new challenge is issued.
+
+CREATE TABLE IF NOT EXISTS connect_challenges (
+    address VARCHAR(255) PRIMARY KEY,
+    nonce VARCHAR(64) NOT NULL,
+    message TEXT NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_connect_challenges_expires ON connect_challenges(expires_at);
@@ -11,6 +11,7 @@ mod routes;
 mod services;
 
 use axum::{
+    extract::DefaultBodyLimit,
     Router,
     routing::{get, post, delete},
 };
@@ -19,11 +20,26 @@ use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::sync::RwLock;
 
-use routes::{health, orders, wallet, spells, escrow};
+use routes::{health, apps, orders, wallet, watch_wallets, address_subscriptions, spells, charms, spell_templates, escrow, fees, rfq, swaps, intents, trades, events, regtest, transactions, webhooks};
+use services::app_artifacts::AppArtifacts;
 use services::bitcoin::BitcoinService;
 use services::charms::CharmsService;
+use services::chain;
+use services::chain_cache::CachedChain;
+use services::chain_tip::ChainTip;
+use services::network;
+use services::prover::{self, ProverKind};
+use services::sealing::Sealer;
+use services::events::EventBus;
+use services::fees::FeeEstimator;
+use services::indexer;
+use services::jobs::JobQueue;
+use services::mempool_monitor;
+use services::zmq::{self, ChainEvents};
+use services::sessions::SessionStore;
+use services::tokens::TokenRegistry;
+use services::webhooks::{spawn_webhook_dispatcher, spawn_webhook_workers};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -39,9 +55,13 @@ async fn main() -> anyhow::Result<()> {
 
     tracing::info!("Starting Liquid Nation API Server");
 
+    let network = network::from_env()?;
+    tracing::info!("✅ Network: {}", network::name(network));
+
     // Initialize database
-    let db_pool = db::init_db().await?;
+    let db_pool = db::init_db(network).await?;
     tracing::info!("Database initialized");
+    spell_templates::seed_builtin_templates(&db_pool).await;
 
     // Validate environment configuration
     validate_environment().await;
@@ -50,24 +70,115 @@ async fn main() -> anyhow::Result<()> {
     // Initialize services
     let bitcoin_rpc = std::env::var("BITCOIN_RPC_URL")
         .unwrap_or_else(|_| "http://127.0.0.1:48332".to_string());
-    let bitcoin_service = BitcoinService::new(&bitcoin_rpc);
-    let charms_service = CharmsService::new();
+    let bitcoin_service = BitcoinService::new(&bitcoin_rpc, network);
+    bitcoin_service.spawn_health_checks();
+    let chain_events = ChainEvents::new();
+    let chain = chain::from_env(network, &bitcoin_service);
+    // Blocks the regtest harness mines must be seen at once, ZMQ or not
+    let chain = match bitcoin_service.regtest_enabled() {
+        true => chain,
+        false => CachedChain::wrap(chain, &chain_events),
+    };
+    tracing::info!("✅ Chain backend: {:?}", chain.kind());
+    let chain_tip = Arc::new(ChainTip::new(chain.clone()));
+    if bitcoin_service.regtest_enabled() {
+        tracing::warn!("⚠️  Regtest harness: ENABLED (/api/regtest mines and funds from the node wallet)");
+    } else if std::env::var("REGTEST_HARNESS").is_ok_and(|flag| flag == "true") {
+        tracing::warn!("⚠️  REGTEST_HARNESS ignored: the network is not regtest");
+    }
+    let prover = prover::from_env();
+    if prover.kind() == ProverKind::Mock {
+        tracing::warn!("⚠️  Mock prover: ENABLED (Prover API will not be called)");
+    } else {
+        tracing::info!("✅ Prover: {:?}", prover.kind());
+    }
+    let charms_service =
+        CharmsService::new(prover.clone(), network).with_chain(chain.clone());
+    let app_artifacts = Arc::new(AppArtifacts::from_env(prover.as_ref()));
+    let event_bus = EventBus::new();
+    let sessions = SessionStore::new(db_pool.clone());
+    let sealer = Arc::new(Sealer::from_env()?);
+    if !sealer.is_enabled() {
+        tracing::warn!("⚠️  DATA_ENCRYPTION_KEYS not set: private spell inputs and preimages are stored unencrypted");
+    }
 
     // Create shared order state with database
     let order_state = Arc::new(orders::AppState {
         charms: charms_service,
-        bitcoin: bitcoin_service,
+        apps: app_artifacts.clone(),
+        bitcoin: bitcoin_service.clone(),
+        chain: chain.clone(),
+        tip: chain_tip.clone(),
+        fees: FeeEstimator::new(network),
+        events: event_bus.clone(),
+        sessions: sessions.clone(),
+        tokens: TokenRegistry::new(),
+        sealer: sealer.clone(),
+        jobs: Arc::new(JobQueue::from_env(db_pool.clone())),
         db: db_pool.clone(),
     });
 
     // Initialize escrow state with cloned services
-    let bitcoin_service_escrow = BitcoinService::new(&bitcoin_rpc);
-    let charms_service_escrow = CharmsService::new();
+    let bitcoin_service_escrow = bitcoin_service;
+    let charms_service_escrow =
+        CharmsService::new(prover, network).with_chain(chain.clone());
     let escrow_state = Arc::new(escrow::EscrowState {
         charms: Arc::new(charms_service_escrow),
+        apps: app_artifacts.clone(),
         bitcoin: Arc::new(bitcoin_service_escrow),
-        escrows: RwLock::new(Vec::new()),
+        chain: chain.clone(),
+        tip: chain_tip.clone(),
+        fees: FeeEstimator::new(network),
+        events: event_bus,
+        sealer,
+        sessions,
+        db: db_pool.clone(),
+    });
+
+    // Every service derives addresses and proves for the same network, and
+    // the node is on it too
+    network::check_agreement(
+        network,
+        &[
+            ("order spells", order_state.charms.network()),
+            ("order node client", order_state.bitcoin.network()),
+            ("escrow spells", escrow_state.charms.network()),
+            ("escrow node client", escrow_state.bitcoin.network()),
+            ("chain backend", chain.network()),
+        ],
+    )?;
+    match order_state.bitcoin.get_blockchain_info().await {
+        Ok(info) => network::check_node_chain(network, &info.chain)?,
+        Err(e) => tracing::warn!("⚠️  Bitcoin node unreachable, its network is unchecked: {}", e),
+    }
+
+    // Build the apps in the background so the first prove does not wait
+    tokio::spawn(async move {
+        app_artifacts.all().await;
     });
+    zmq::spawn_zmq_listener(chain_events.clone());
+    chain_tip.spawn_tracker(chain_events.clone());
+    escrow::spawn_expiry_monitor(escrow_state.clone(), chain_events.clone());
+    escrow::spawn_deposit_tracker(escrow_state.clone(), chain_events.clone());
+    escrow::spawn_proposal_pruner(escrow_state.clone(), order_state.jobs.clone());
+    address_subscriptions::spawn_deposit_monitor(order_state.clone(), chain_events.clone());
+    spells::spawn_prove_workers(order_state.clone());
+    spawn_webhook_dispatcher(db_pool.clone(), order_state.events.clone(), order_state.jobs.clone());
+    spawn_webhook_workers(db_pool.clone(), order_state.jobs.clone());
+    orders::spawn_order_archiver(order_state.clone());
+    intents::spawn_intent_reaper(order_state.clone());
+    mempool_monitor::spawn_mempool_monitor(
+        chain.clone(),
+        chain_events.clone(),
+        vec![order_state.clone(), escrow_state.clone()],
+    );
+    indexer::spawn_indexer(chain, db_pool.clone(), escrow_state.events.clone(), chain_events);
+
+    // Uploaded app binaries (MAX_APP_BINARY_BYTES, default 64 MiB)
+    let max_binary_bytes: usize = std::env::var("MAX_APP_BINARY_BYTES")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(64 * 1024 * 1024);
 
     // Build application routes
     let app = Router::new()
@@ -75,30 +186,141 @@ async fn main() -> anyhow::Result<()> {
         .route("/health", get(health::health_check))
         .route("/api/health", get(health::health_check))
         .route("/api/health/prover", get(health::check_prover_api))
+        .route("/api/health/rpc", get(health::rpc_metrics))
+        .route("/api/health/nodes", get(health::rpc_nodes))
+        // Regtest harness (REGTEST_HARNESS=true on regtest only)
+        .route("/api/regtest/mine", post(regtest::mine_blocks))
+        .route("/api/regtest/fund", post(regtest::fund_address))
+        .route("/api/regtest/confirm/:txid", post(regtest::confirm_transaction))
+
+        // App artifacts
+        .route("/api/apps", get(apps::list_apps))
+        .route("/api/admin/apps/:contract/refresh", post(apps::refresh_app))
+
+        // Charms indexed from chain
+        .route("/api/charms", get(charms::list_charms))
+        .route("/api/charms/:utxo_id", get(charms::get_charm_utxo))
         
         // Orders (with state)
         .route("/api/orders", get(orders::list_orders))
         .route("/api/orders", post(orders::create_order))
+        .route("/api/orders/mine", get(orders::list_my_orders))
+        .route("/api/orders/search", get(orders::search_orders))
         .route("/api/orders/:id", get(orders::get_order))
         .route("/api/orders/:id/fill", post(orders::fill_order))
         .route("/api/orders/:id/cancel", delete(orders::cancel_order))
         .route("/api/orders/:id/partial-fill", post(orders::partial_fill_order))
         .route("/api/orders/:id/broadcast", post(orders::broadcast_order))
-        .with_state(order_state)
-        
-        // Wallet
+        .route("/api/orders/:id/bump-fee", post(orders::bump_order_fee))
+        .route("/api/orders/:id/swap-status", get(swaps::get_swap_status))
+        .route("/api/orders/:id/swap-legs", post(swaps::report_leg_event))
+        .route("/api/orders/:id/spells", get(orders::get_order_spells))
+        .route("/api/orders/:id/spells/:spell_id", get(orders::get_order_spell))
+        .route("/api/orders/:id/fills", get(trades::get_order_fills))
+        .route("/api/orders/:id/spell-templates", get(spell_templates::get_order_spell_templates))
+
+        // Spell template registry (admin)
+        .route("/api/admin/spell-templates", get(spell_templates::list_spell_templates))
+        .route(
+            "/api/admin/spell-templates/:name",
+            get(spell_templates::list_spell_template_versions).post(spell_templates::add_spell_template_version),
+        )
+        .route(
+            "/api/admin/spell-templates/:name/versions/:version/activate",
+            post(spell_templates::activate_spell_template_version),
+        )
+        .route("/api/admin/spell-template-uses/:subject_id", get(spell_templates::get_spell_template_uses))
+        .route("/api/admin/events/:subject_type/:subject_id", get(events::get_state_events))
+
+        // Fees
+        .route("/api/fees", get(fees::get_fee_estimates))
+
+        // Trade history and market statistics from fills
+        .route("/api/trades", get(trades::list_trades))
+        .route("/api/trades/candles", get(trades::get_candles))
+        .route("/api/trades/stats", get(trades::get_stats))
+
+        // Request for quote
+        .route("/api/rfq", get(rfq::list_rfqs).post(rfq::create_rfq))
+        .route("/api/rfq/:id", get(rfq::get_rfq))
+        .route("/api/rfq/:id/quotes", post(rfq::submit_quote))
+        .route("/api/rfq/:id/quotes/:quote_id/accept", post(rfq::accept_quote))
+
+        // Off-chain order intents
+        .route("/api/intents", get(intents::list_intents).post(intents::create_intent))
+        .route("/api/intents/:id", get(intents::get_intent))
+        .route("/api/intents/:id/fill", post(intents::fill_intent))
+
+        // Event stream
+        .route("/api/events/ws", get(events::events_ws))
+
+        // Wallet (session connect, balances and UTXOs annotated from order state)
         .route("/api/wallet/connect", post(wallet::connect_wallet))
+        .route("/api/wallet/session", get(wallet::get_session).delete(wallet::end_session))
+        .route("/api/wallet/session/addresses", post(wallet::link_wallet))
+        .route("/api/wallet/session/addresses/:address", delete(wallet::unlink_wallet))
+        .route("/api/webhooks", get(webhooks::list_webhooks).post(webhooks::create_webhook))
+        .route(
+            "/api/webhooks/:id",
+            get(webhooks::get_webhook).patch(webhooks::update_webhook).delete(webhooks::delete_webhook),
+        )
+        .route("/api/webhooks/:id/deliveries", get(webhooks::list_webhook_deliveries))
         .route("/api/wallet/balance", get(wallet::get_balance))
+        .route("/api/wallet/fee-estimate", get(fees::get_wallet_fee_estimate))
+        .route("/api/wallet/history", get(wallet::get_history))
         .route("/api/wallet/utxos", get(wallet::get_utxos))
-        .route("/api/wallet/address", get(wallet::get_address))
-        
-        // Escrow
-        .nest("/api/escrows", escrow::router(escrow_state))
-        
-        // Spells (Charms protocol)
+        .route("/api/wallet/utxos/locks", get(wallet::list_utxo_locks))
+        .route("/api/wallet/utxos/consolidate", post(wallet::consolidate_utxos))
+        .route("/api/wallet/cpfp", post(wallet::build_cpfp))
+        .route(
+            "/api/wallet/utxos/:outpoint/lock",
+            post(wallet::lock_utxo).delete(wallet::unlock_utxo),
+        )
+        .route("/api/wallet/psbt", post(wallet::build_psbt))
+        .route("/api/wallet/psbt/finalize", post(wallet::finalize_psbt))
+        .route(
+            "/api/wallet/watch",
+            get(watch_wallets::list_watched_wallets).post(watch_wallets::register_watched_wallet),
+        )
+        .route("/api/wallet/watch/:id", delete(watch_wallets::delete_watched_wallet))
+        .route("/api/wallet/watch/:id/addresses", get(watch_wallets::get_watched_addresses))
+        .route("/api/wallet/watch/:id/utxos", get(watch_wallets::get_watched_utxos))
+        .route("/api/wallet/watch/:id/balance", get(watch_wallets::get_watched_balance))
+        .route(
+            "/api/wallet/subscriptions",
+            get(address_subscriptions::list_subscriptions).post(address_subscriptions::create_subscription),
+        )
+        .route("/api/wallet/subscriptions/:id", delete(address_subscriptions::delete_subscription))
+        .route(
+            "/api/wallet/subscriptions/:id/deposits",
+            get(address_subscriptions::list_subscription_deposits),
+        )
+
+        // Spell proving jobs
+        .route("/api/spells/check", post(spells::check_spell))
+        .route("/api/spells/decode", post(spells::decode_spell))
+        .route("/api/spells/estimate", get(spells::estimate_spell))
         .route("/api/spells/prove", post(spells::prove_spell))
+        .route(
+            "/api/spells/binaries",
+            post(spells::upload_binary).layer(DefaultBodyLimit::max(max_binary_bytes)),
+        )
+        .route("/api/spells/jobs/:id", get(spells::get_prove_job))
+        .route("/api/spells/jobs/:id/events", get(spells::prove_job_events))
         .route("/api/spells/broadcast", post(spells::broadcast_transaction))
         .route("/api/spells/status/:txid", get(spells::get_transaction_status))
+        .route("/api/tx/decode", post(transactions::decode_transaction_hex))
+        .route("/api/tx/:txid/decoded", get(transactions::get_decoded_transaction))
+        .route("/api/wallet/escrow-address", get(wallet::get_order_escrow_address))
+        .with_state(order_state)
+        
+        // Wallet
+        .route("/api/wallet/address", get(wallet::get_address))
+        
+        // Escrow
+        .nest("/api/escrows", escrow::router(escrow_state.clone()))
+        .nest("/api/disputes", escrow::disputes_router(escrow_state.clone()))
+        .merge(escrow::order_escrow_router(escrow_state))
         
         // CORS
         .layer(CorsLayer::new()
@@ -129,44 +351,27 @@ async fn validate_environment() {
     tracing::info!("=== Environment Validation ===");
     
     // Check Prover API URL
-    let api_url = std::env::var("CHARMS_PROVE_API_URL")
-        .unwrap_or_else(|_| "https://v8.charms.dev/spells/prove".to_string());
-    tracing::info!("✅ Prover API URL: {}", api_url);
-    
-    // Check mock mode
-    let mock_mode = std::env::var("MOCK_MODE")
-        .map(|v| v == "true")
-        .unwrap_or(true);
-    if mock_mode {
-        tracing::warn!("⚠️  Mock mode: ENABLED (Prover API will not be called)");
+    if std::env::var("PROVER_BACKEND").as_deref() == Ok("local") {
+        let charms_bin = std::env::var("CHARMS_BIN").unwrap_or_else(|_| "charms".to_string());
+        tracing::info!("✅ Prover backend: local ({})", charms_bin);
     } else {
-        tracing::info!("✅ Mock mode: DISABLED (Real Prover API calls enabled)");
-    }
-    
-    // Check binary path
-    if let Ok(binary_path) = std::env::var("SWAP_APP_BINARY_PATH") {
-        match tokio::fs::metadata(&binary_path).await {
-            Ok(metadata) => {
-                tracing::info!("✅ App binary found: {} ({} bytes)", binary_path, metadata.len());
-            }
-            Err(_) => {
-                tracing::warn!("⚠️  App binary not found at: {}", binary_path);
-            }
+        let api_urls = std::env::var("CHARMS_PROVE_API_URL")
+            .unwrap_or_else(|_| "https://v8.charms.dev/spells/prove".to_string());
+        tracing::info!("✅ Prover API URLs: {}", api_urls);
+        if std::env::var("PROVER_LOCAL_FALLBACK").as_deref() == Ok("true") {
+            let charms_bin = std::env::var("CHARMS_BIN").unwrap_or_else(|_| "charms".to_string());
+            tracing::info!("✅ Local prover fallback: {}", charms_bin);
         }
-    } else {
-        tracing::warn!("⚠️  SWAP_APP_BINARY_PATH not set (using defaults)");
     }
     
-    // Check VK
-    if let Ok(vk) = std::env::var("SWAP_APP_VK") {
-        let preview = if vk.len() > 16 {
-            format!("{}...", &vk[..16])
-        } else {
-            vk.clone()
-        };
-        tracing::info!("✅ App VK configured: {}", preview);
+    // Check app sources
+    let apps_dir = std::env::var("APPS_DIR").unwrap_or_else(|_| "../apps".to_string());
+    if std::env::var("APP_BUILD").as_deref() == Ok("false") {
+        tracing::warn!("⚠️  App builds disabled (using published VKs)");
+    } else if tokio::fs::metadata(&apps_dir).await.is_ok() {
+        tracing::info!("✅ App sources: {}", apps_dir);
     } else {
-        tracing::warn!("⚠️  SWAP_APP_VK not set (using default)");
+        tracing::warn!("⚠️  App sources not found at: {} (using published VKs)", apps_dir);
     }
     
     // Check Bitcoin RPC
@@ -0,0 +1,227 @@
+//! Address watch subscriptions
+//!
+//! A subscription imports `addr(<address>)` into the watch-only node wallet.
+//! The deposit monitor polls it and, for each new UTXO at a subscribed
+//! address, publishes `address.deposit` (and `address.deposit_confirmed` once
+//! it confirms) with the address as subject. Events reach the WebSocket
+//! stream and the subscriber's registered webhooks (`/api/webhooks`).
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use bitcoin::Address;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::db::{self, AddressDepositRecord, AddressSubscriptionRecord};
+use crate::routes::auth::WalletSession;
+use crate::routes::error::ApiError;
+use crate::routes::orders::AppState;
+use crate::routes::wallet::{annotate_utxos, Utxo};
+use crate::routes::watch_wallets::{node_error, watch_wallet_name};
+use crate::services::events::Event;
+use crate::services::sessions::Session;
+use crate::services::zmq::{self, ChainEvents};
+
+/// Subscriptions a session's wallets may hold at once
+const MAX_SUBSCRIPTIONS: usize = 50;
+
+/// Subscribe request
+#[derive(Debug, Deserialize)]
+pub struct SubscribeRequest {
+    pub address: String,
+    pub label: Option<String>,
+    /// No longer supported; register a webhook for `address.*` events
+    pub webhook_url: Option<String>,
+}
+
+/// Subscribe to deposits at an address
+pub async fn create_subscription(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+    Json(req): Json<SubscribeRequest>,
+) -> Result<Json<AddressSubscriptionRecord>, ApiError> {
+    let address = Address::from_str(req.address.trim())
+        .ok()
+        .and_then(|a| a.require_network(state.bitcoin.network()).ok())
+        .ok_or_else(|| ApiError::bad_request(format!("Invalid address: {}", req.address)))?
+        .to_string();
+
+    if req.webhook_url.is_some() {
+        return Err(ApiError::bad_request(
+            "webhook_url is no longer supported; register a webhook for address.* events at /api/webhooks",
+        ));
+    }
+
+    let existing = db::get_address_subscriptions(&state.db, &session.addresses).await?;
+    if existing.len() >= MAX_SUBSCRIPTIONS {
+        return Err(ApiError::conflict(format!("At most {} subscriptions per session", MAX_SUBSCRIPTIONS)));
+    }
+
+    let descriptor = state
+        .bitcoin
+        .get_descriptor_info(&format!("addr({})", address))
+        .await
+        .map_err(node_error)?
+        .descriptor;
+    let wallet_name = watch_wallet_name();
+    state.bitcoin.ensure_watch_wallet(&wallet_name).await.map_err(node_error)?;
+    state
+        .bitcoin
+        .import_descriptors(&wallet_name, &[descriptor], 0, None)
+        .await
+        .map_err(node_error)?;
+
+    let subscription = AddressSubscriptionRecord {
+        id: Uuid::new_v4().to_string(),
+        owner_address: session.address,
+        address,
+        label: req.label,
+        webhook_url: None,
+        created_at: chrono::Utc::now(),
+    };
+    db::insert_address_subscription(&state.db, &subscription).await?;
+
+    // Funds already at the address are not news; only later arrivals notify
+    let unspent = state
+        .bitcoin
+        .list_unspent_for(&wallet_name, std::slice::from_ref(&subscription.address))
+        .await
+        .map_err(node_error)?;
+    for utxo in annotate_utxos(&state, unspent).await? {
+        db::record_address_deposit(&state.db, &deposit_record(&subscription.id, &utxo)).await?;
+    }
+
+    tracing::info!("Subscription {} watching {}", subscription.id, subscription.address);
+    Ok(Json(subscription))
+}
+
+/// List the subscriptions of every wallet linked to the session
+pub async fn list_subscriptions(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+) -> Result<Json<Vec<AddressSubscriptionRecord>>, ApiError> {
+    Ok(Json(db::get_address_subscriptions(&state.db, &session.addresses).await?))
+}
+
+/// Deposits seen at a subscribed address
+pub async fn list_subscription_deposits(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<AddressDepositRecord>>, ApiError> {
+    owned_subscription(&state, &session, &id).await?;
+    Ok(Json(db::get_address_deposits(&state.db, &id).await?))
+}
+
+/// Stop watching an address
+pub async fn delete_subscription(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    owned_subscription(&state, &session, &id).await?;
+    db::delete_address_subscription(&state.db, &id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn owned_subscription(
+    state: &AppState,
+    session: &Session,
+    id: &str,
+) -> Result<AddressSubscriptionRecord, ApiError> {
+    let subscription = db::get_address_subscription(&state.db, id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Subscription not found"))?;
+
+    if !session.owns(&subscription.owner_address) {
+        return Err(ApiError::forbidden("Subscription belongs to another session"));
+    }
+    Ok(subscription)
+}
+
+fn deposit_record(subscription_id: &str, utxo: &Utxo) -> AddressDepositRecord {
+    AddressDepositRecord {
+        subscription_id: subscription_id.to_string(),
+        outpoint: format!("{}:{}", utxo.txid, utxo.vout),
+        value: utxo.value as i64,
+        confirmed: utxo.confirmations > 0,
+        seen_at: chrono::Utc::now(),
+    }
+}
+
+/// Spawn the background task that notifies subscribers of deposits, scanning
+/// on every new block and mempool transaction
+pub fn spawn_deposit_monitor(state: Arc<AppState>, chain_events: ChainEvents) {
+    let interval_secs = std::env::var("DEPOSIT_MONITOR_INTERVAL_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(30);
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_secs));
+        let mut chain = chain_events.subscribe();
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = zmq::next_event(&mut chain, zmq::is_any) => {}
+            }
+
+            if let Err(e) = scan_deposits(&state).await {
+                tracing::debug!("Deposit monitor scan failed: {}", e);
+            }
+        }
+    });
+}
+
+/// Notify each subscription of UTXOs that appeared or confirmed since the last scan
+async fn scan_deposits(state: &AppState) -> anyhow::Result<()> {
+    let subscriptions = db::get_all_address_subscriptions(&state.db).await?;
+    if subscriptions.is_empty() {
+        return Ok(());
+    }
+
+    let addresses: Vec<String> = subscriptions
+        .iter()
+        .map(|s| s.address.clone())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let unspent = state.bitcoin.list_unspent_for(&watch_wallet_name(), &addresses).await?;
+    let utxos = annotate_utxos(state, unspent).await?;
+
+    for subscription in &subscriptions {
+        for utxo in utxos.iter().filter(|u| u.address == subscription.address) {
+            let deposit = deposit_record(&subscription.id, utxo);
+            let kind = if db::record_address_deposit(&state.db, &deposit).await? {
+                "address.deposit"
+            } else if deposit.confirmed
+                && db::confirm_address_deposit(&state.db, &subscription.id, &deposit.outpoint).await?
+            {
+                "address.deposit_confirmed"
+            } else {
+                continue;
+            };
+
+            let event = Event::new(
+                kind,
+                subscription.address.clone(),
+                serde_json::json!({
+                    "subscription_id": subscription.id,
+                    "outpoint": deposit.outpoint,
+                    "value": utxo.value,
+                    "confirmations": utxo.confirmations,
+                    "charms": utxo.charms,
+                }),
+            );
+            state.events.publish(event);
+        }
+    }
+
+    Ok(())
+}
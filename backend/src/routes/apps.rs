@@ -0,0 +1,33 @@
+//! App artifact endpoints
+//!
+//! Report the verification key each app is running with and where it came
+//! from (cache, fresh build or the published key). Admins can re-resolve an
+//! app after changing its sources without restarting the server.
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use std::sync::Arc;
+
+use crate::routes::auth::AdminToken;
+use crate::routes::orders::AppState;
+use crate::services::app_artifacts::AppArtifact;
+use crate::services::spell_check::Contract;
+
+/// Swap and escrow app artifacts (without their binaries)
+pub async fn list_apps(State(state): State<Arc<AppState>>) -> Json<Vec<AppArtifact>> {
+    let artifacts = state.apps.all().await;
+    Json(artifacts.iter().map(|artifact| AppArtifact::clone(artifact)).collect())
+}
+
+/// Rebuild an app if its sources changed and serve the result from now on
+pub async fn refresh_app(
+    _admin: AdminToken,
+    State(state): State<Arc<AppState>>,
+    Path(contract): Path<Contract>,
+) -> Json<AppArtifact> {
+    let artifact = state.apps.refresh(contract).await;
+    tracing::info!("Refreshed {:?} app: vk {}", contract, artifact.vk);
+    Json(AppArtifact::clone(&artifact))
+}
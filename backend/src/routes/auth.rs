@@ -0,0 +1,139 @@
+//! Session authentication for wallet-owned actions
+//!
+//! Clients send the token from `POST /api/wallet/connect` as
+//! `Authorization: Bearer <token>`. Admin endpoints instead require the
+//! `X-Admin-Token` header to match `ADMIN_API_TOKEN`. Requests made with a
+//! session are rate limited per user, and rejected with 429 over the limit.
+
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts, Request, State},
+    http::{header::AUTHORIZATION, request::Parts, HeaderMap, Method, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::routes::error::ApiError;
+use crate::services::sessions::{Session, SessionStore};
+
+/// Extractor for the caller's wallet session; rejects with 401 when absent
+#[derive(Debug, Clone)]
+pub struct WalletSession(pub Session);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for WalletSession
+where
+    SessionStore: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        // Already authenticated, and counted, by `require_session`
+        if let Some(session) = parts.extensions.get::<Session>() {
+            return Ok(WalletSession(session.clone()));
+        }
+        let sessions = SessionStore::from_ref(state);
+        authenticate(&sessions, &parts.headers).await.map(WalletSession)
+    }
+}
+
+/// Extractor for admin endpoints; rejects with 401 on a missing or wrong
+/// token, and with 403 when `ADMIN_API_TOKEN` is unset (admin API disabled)
+#[derive(Debug, Clone, Copy)]
+pub struct AdminToken;
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AdminToken
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let expected = std::env::var("ADMIN_API_TOKEN")
+            .ok()
+            .filter(|token| !token.is_empty())
+            .ok_or_else(|| ApiError::forbidden("Admin API is disabled"))?;
+
+        let given = parts
+            .headers
+            .get("x-admin-token")
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| unauthorized("Missing admin token"))?;
+
+        // Compare digests so the check does not leak the token through timing
+        if Sha256::digest(given.trim()) != Sha256::digest(expected.as_bytes()) {
+            return Err(unauthorized("Invalid admin token"));
+        }
+        Ok(AdminToken)
+    }
+}
+
+/// Extractor for endpoints open to signed-in users and admins: the admin
+/// token when `X-Admin-Token` is sent, otherwise the wallet session
+#[derive(Debug, Clone)]
+pub enum SessionOrAdmin {
+    Session(Session),
+    Admin,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for SessionOrAdmin
+where
+    SessionStore: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        if parts.headers.contains_key("x-admin-token") {
+            AdminToken::from_request_parts(parts, state).await?;
+            return Ok(SessionOrAdmin::Admin);
+        }
+        let WalletSession(session) = WalletSession::from_request_parts(parts, state).await?;
+        Ok(SessionOrAdmin::Session(session))
+    }
+}
+
+/// Middleware requiring a session for every non-GET request on a router
+pub async fn require_session(
+    State(sessions): State<SessionStore>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    if request.method() != Method::GET {
+        let session = authenticate(&sessions, request.headers()).await?;
+        request.extensions_mut().insert(session);
+    }
+
+    Ok(next.run(request).await)
+}
+
+async fn authenticate(sessions: &SessionStore, headers: &HeaderMap) -> Result<Session, ApiError> {
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or_else(|| unauthorized("Missing session token; connect your wallet first"))?;
+
+    let session = sessions
+        .session(token.trim())
+        .await?
+        .ok_or_else(|| unauthorized("Session is invalid or has expired"))?;
+
+    if let Err(retry_after) = sessions.check_rate(&session) {
+        return Err(ApiError::new(
+            StatusCode::TOO_MANY_REQUESTS,
+            "rate_limited",
+            format!("Too many requests; retry in {} seconds", retry_after.as_secs().max(1)),
+        ));
+    }
+    Ok(session)
+}
+
+fn unauthorized(message: &str) -> ApiError {
+    ApiError::new(StatusCode::UNAUTHORIZED, "unauthorized", message)
+}
@@ -0,0 +1,238 @@
+//! Indexed charm endpoints
+//!
+//! Live charms as the chain indexer found them, so integrators can enumerate
+//! orders, escrows and token holdings from chain state rather than from our
+//! orders table. `indexed_height` tells how far the index has caught up.
+
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use bitcoin::OutPoint;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::db::{self, CharmUtxoRecord};
+use crate::routes::error::ApiError;
+use crate::routes::orders::AppState;
+use crate::services::indexer::INDEXER;
+
+/// Most charms one page lists
+const MAX_CHARMS_LIMIT: i64 = 1000;
+
+/// Charm list filters; all given filters must match
+#[derive(Debug, Deserialize)]
+pub struct ListCharmsQuery {
+    /// App identity (hex)
+    pub app_id: Option<String>,
+    /// Address holding the charm
+    pub address: Option<String>,
+    /// `t` for tokens, `n` for NFTs
+    pub tag: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// One app's charm on a live UTXO
+#[derive(Debug, Serialize)]
+pub struct Charm {
+    pub utxo_id: String,
+    /// App as `tag/identity/vk`
+    pub app: String,
+    pub tag: String,
+    pub app_id: String,
+    pub vk: String,
+    pub address: Option<String>,
+    /// Output value in sats
+    pub value: i64,
+    pub data: serde_json::Value,
+    pub block_height: i64,
+}
+
+impl From<CharmUtxoRecord> for Charm {
+    fn from(record: CharmUtxoRecord) -> Self {
+        Self {
+            data: serde_json::from_str(&record.data).unwrap_or(serde_json::Value::String(record.data)),
+            utxo_id: record.utxo_id,
+            app: record.app,
+            tag: record.tag,
+            app_id: record.app_id,
+            vk: record.vk,
+            address: record.address,
+            value: record.value,
+            block_height: record.block_height,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListCharmsResponse {
+    pub charms: Vec<Charm>,
+    /// Last block the index includes; `None` before the first block
+    pub indexed_height: Option<i64>,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+/// A live UTXO and every charm it holds
+#[derive(Debug, Serialize)]
+pub struct CharmUtxo {
+    pub utxo_id: String,
+    pub address: Option<String>,
+    pub value: i64,
+    pub block_height: i64,
+    /// Charm data keyed by app
+    pub charms: BTreeMap<String, serde_json::Value>,
+    pub indexed_height: Option<i64>,
+}
+
+/// List live charms, by app, holder or tag
+pub async fn list_charms(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ListCharmsQuery>,
+) -> Result<Json<ListCharmsResponse>, ApiError> {
+    let limit = query.limit.unwrap_or(100).clamp(1, MAX_CHARMS_LIMIT);
+    let offset = query.offset.unwrap_or(0).max(0);
+    let app_id = query.app_id.as_deref().map(|id| id.trim().to_lowercase());
+    let tag = query.tag.as_deref().map(|tag| tag.trim().to_lowercase());
+
+    let records = db::list_charm_utxos(
+        &state.db,
+        app_id.as_deref(),
+        query.address.as_deref().map(str::trim),
+        tag.as_deref(),
+        limit,
+        offset,
+    )
+    .await?;
+
+    Ok(Json(ListCharmsResponse {
+        charms: records.into_iter().map(Charm::from).collect(),
+        indexed_height: db::get_indexer_height(&state.db, INDEXER).await?,
+        limit,
+        offset,
+    }))
+}
+
+/// Charms held by one UTXO; 404 when it holds none or has been spent
+pub async fn get_charm_utxo(
+    State(state): State<Arc<AppState>>,
+    Path(utxo_id): Path<String>,
+) -> Result<Json<CharmUtxo>, ApiError> {
+    if OutPoint::from_str(&utxo_id).is_err() {
+        return Err(ApiError::bad_request(format!("Invalid UTXO id {}; expected txid:vout", utxo_id)));
+    }
+
+    let records = db::get_charm_utxo(&state.db, &utxo_id).await?;
+    let Some(first) = records.first() else {
+        return Err(ApiError::not_found(format!("No live charms at {}", utxo_id)));
+    };
+
+    Ok(Json(CharmUtxo {
+        utxo_id: first.utxo_id.clone(),
+        address: first.address.clone(),
+        value: first.value,
+        block_height: first.block_height,
+        indexed_height: db::get_indexer_height(&state.db, INDEXER).await?,
+        charms: records
+            .into_iter()
+            .map(|record| {
+                let charm = Charm::from(record);
+                (charm.app, charm.data)
+            })
+            .collect(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+
+    use crate::routes::orders::testing::test_state;
+
+    const HOLDER: &str = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+
+    fn charm(utxo_id: &str, tag: &str, app_id: &str, height: i64, data: &str) -> CharmUtxoRecord {
+        CharmUtxoRecord {
+            utxo_id: utxo_id.to_string(),
+            app: format!("{}/{}/{}", tag, app_id, "ff".repeat(32)),
+            tag: tag.to_string(),
+            app_id: app_id.to_string(),
+            vk: "ff".repeat(32),
+            address: Some(HOLDER.to_string()),
+            value: 1000,
+            data: data.to_string(),
+            block_height: height,
+            spent_height: None,
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    fn query(
+        app_id: Option<&str>,
+        tag: Option<&str>,
+        limit: Option<i64>,
+        offset: Option<i64>,
+    ) -> Query<ListCharmsQuery> {
+        Query(ListCharmsQuery {
+            app_id: app_id.map(str::to_string),
+            address: None,
+            tag: tag.map(str::to_string),
+            limit,
+            offset,
+        })
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_charms_are_served_from_the_index() {
+        let db = db::testing::fresh_db().await;
+        let state = Arc::new(test_state(db));
+
+        let token_app = "aa".repeat(32);
+        let nft_app = "bb".repeat(32);
+        let first = format!("{}:0", "01".repeat(32));
+        let second = format!("{}:1", "02".repeat(32));
+        let listed = list_charms(State(state.clone()), query(None, None, None, None)).await.unwrap().0;
+        assert!(listed.charms.is_empty());
+        assert_eq!(listed.indexed_height, None);
+
+        let created = [
+            charm(&first, "t", &token_app, 100, "500"),
+            charm(&first, "n", &nft_app, 100, r#"{"ticker":"TOAD"}"#),
+        ];
+        db::apply_charm_block(&state.db, INDEXER, 100, "hash100", &[], &created, 0).await.unwrap();
+        let created = [charm(&second, "t", &token_app, 101, "700")];
+        db::apply_charm_block(&state.db, INDEXER, 101, "hash101", &[], &created, 0).await.unwrap();
+
+        // Filters are matched case-insensitively on app id and tag
+        let tokens = list_charms(State(state.clone()), query(Some(&token_app.to_uppercase()), Some(" T "), None, None))
+            .await
+            .unwrap()
+            .0;
+        let utxos: Vec<&str> = tokens.charms.iter().map(|charm| charm.utxo_id.as_str()).collect();
+        assert_eq!(utxos, vec![first.as_str(), second.as_str()]);
+        assert_eq!(tokens.charms[0].data, serde_json::json!(500));
+        assert_eq!(tokens.indexed_height, Some(101));
+        let page = list_charms(State(state.clone()), query(None, None, Some(0), Some(2))).await.unwrap().0;
+        assert_eq!((page.limit, page.offset, page.charms.len()), (1, 2, 1));
+
+        let utxo = get_charm_utxo(State(state.clone()), Path(first.clone())).await.unwrap().0;
+        assert_eq!(utxo.charms.len(), 2);
+        let nft = &utxo.charms[&format!("n/{}/{}", nft_app, "ff".repeat(32))];
+        assert_eq!(nft["ticker"], "TOAD");
+
+        // Spent charms drop out; malformed and empty UTXOs are told apart
+        db::apply_charm_block(&state.db, INDEXER, 102, "hash102", std::slice::from_ref(&first), &[], 0).await.unwrap();
+        let err = get_charm_utxo(State(state.clone()), Path(first.clone())).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::NOT_FOUND);
+        let err = get_charm_utxo(State(state.clone()), Path("not-an-outpoint".to_string())).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        let live = list_charms(State(state), query(None, None, None, None)).await.unwrap().0;
+        assert_eq!(live.charms.len(), 1);
+        assert_eq!(live.charms[0].utxo_id, second);
+    }
+}
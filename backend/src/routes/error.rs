@@ -0,0 +1,150 @@
+//! API error type shared by route handlers
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::db::{StaleEscrow, StaleOrder};
+use crate::services::chain_tip::TipError;
+use crate::services::charms::ProverError;
+use crate::services::fee_bump::BumpError;
+use crate::services::fills::FillError;
+use crate::services::funding_check::FundingMismatch;
+use crate::services::network::AddressError;
+use crate::services::spell_schema::SpellLintError;
+
+/// Error returned by handlers, rendered as `{ success: false, error, code }`
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self { status, code, message: message.into() }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "bad_request", message)
+    }
+
+    /// Error for a request that leaves out a field the handler needs
+    pub fn missing(field: &str) -> Self {
+        Self::bad_request(format!("{} is required", field))
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::FORBIDDEN, "forbidden", message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, "not_found", message)
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::CONFLICT, "conflict", message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "internal_error", message)
+    }
+}
+
+impl ApiError {
+    /// Error for a spell template that does not build, e.g. because it
+    /// uses variables the builder does not supply
+    pub fn spell_build_failed(e: &anyhow::Error) -> Self {
+        let code = if e.is::<SpellLintError>() { "unresolved_template" } else { "invalid_template" };
+        Self::new(StatusCode::UNPROCESSABLE_ENTITY, code, format!("Failed to build spell: {}", e))
+    }
+
+    /// Error for an app binary the prover needs but that was not built
+    pub fn app_unavailable(e: &anyhow::Error) -> Self {
+        Self::new(StatusCode::SERVICE_UNAVAILABLE, "app_unavailable", format!("{:#}", e))
+    }
+
+    /// Error for a failed prove: prover failures get their own codes, so
+    /// clients can tell a bad spell from an underfunded one or a prover outage
+    pub fn prove_failed(e: &anyhow::Error) -> Self {
+        if let Some(err) = e.downcast_ref::<FundingMismatch>() {
+            return Self::new(StatusCode::UNPROCESSABLE_ENTITY, err.code(), err.to_string());
+        }
+        let Some(err) = e.downcast_ref::<ProverError>() else {
+            return Self::new(StatusCode::BAD_GATEWAY, "prover_error", e.to_string());
+        };
+        let status = match err {
+            ProverError::Transient(_) | ProverError::CircuitOpen { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            ProverError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            ProverError::InvalidSpell(_)
+            | ProverError::ContractCheckFailed(_)
+            | ProverError::InsufficientFunding(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ProverError::Rejected { .. } | ProverError::InvalidResponse(_) | ProverError::Local(_) => {
+                StatusCode::BAD_GATEWAY
+            }
+        };
+        Self::new(status, err.code(), e.to_string())
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        if let Some(err) = e.downcast_ref::<StaleOrder>() {
+            return Self::new(StatusCode::CONFLICT, "stale_order", err.to_string());
+        }
+        if let Some(err) = e.downcast_ref::<StaleEscrow>() {
+            return Self::new(StatusCode::CONFLICT, "stale_escrow", err.to_string());
+        }
+        tracing::error!("Internal error: {}", e);
+        Self::internal(e.to_string())
+    }
+}
+
+impl From<BumpError> for ApiError {
+    fn from(e: BumpError) -> Self {
+        match e {
+            BumpError::Chain(e) => e.into(),
+            e => Self::conflict(e.to_string()),
+        }
+    }
+}
+
+impl From<FillError> for ApiError {
+    fn from(e: FillError) -> Self {
+        match e {
+            FillError::NotFillable(_) | FillError::ExceedsRemaining { .. } => Self::conflict(e.to_string()),
+            e => Self::bad_request(e.to_string()),
+        }
+    }
+}
+
+impl From<TipError> for ApiError {
+    fn from(e: TipError) -> Self {
+        tracing::error!("{}", e);
+        Self::new(StatusCode::SERVICE_UNAVAILABLE, "chain_tip_unavailable", e.to_string())
+    }
+}
+
+impl From<AddressError> for ApiError {
+    fn from(e: AddressError) -> Self {
+        let code = match e {
+            AddressError::Invalid { .. } => "invalid_address",
+            AddressError::WrongNetwork { .. } => "wrong_network",
+        };
+        Self::new(StatusCode::BAD_REQUEST, code, e.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = serde_json::json!({
+            "success": false,
+            "error": self.message,
+            "code": self.code,
+        });
+        (self.status, Json(body)).into_response()
+    }
+}
@@ -3,24 +3,86 @@
 //! Handles escrow creation, release, refund, and dispute operations
 
 use axum::{
-    extract::{Path, State},
+    async_trait,
+    extract::{FromRef, Path, Query, State},
     http::StatusCode,
+    middleware,
     response::Json,
     routing::{get, post},
     Router,
 };
+use bitcoin::{Network, OutPoint};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
 use uuid::Uuid;
 
+use crate::db::{self, DbPool};
+use crate::routes::auth::{require_session, WalletSession};
+use crate::routes::error::ApiError;
+use crate::routes::network_json::{BitcoinAddresses, NetworkJson};
+use crate::routes::escrow_templates::{
+    create_template, delete_template, get_template, instantiate_template, list_templates,
+    update_template,
+};
+use crate::routes::orders::{
+    signing_payloads, BroadcastResponse, InputToSign, SigningInstructions, SpellData, UnsignedTransaction,
+    DEFAULT_TOKEN_VK,
+};
+use crate::routes::spell_templates::{
+    record_template_use, spell_template, CREATE_ESCROW, DISPUTE_ESCROW, REFUND_ESCROW, RELEASE_ESCROW,
+    RELEASE_MILESTONE, RESOLVE_DISPUTE,
+};
+use crate::routes::spells::prove_cached;
+use crate::routes::wallet::lock_funding_utxo;
+use crate::routes::watch_wallets::watch_wallet_name;
+use crate::routes::wallet_formats::{NetworkState, WalletFormat};
+use crate::services::addresses;
+use crate::services::app_artifacts::AppArtifacts;
+use crate::services::chain::{BroadcastRejection, ChainBackend, ChainKind};
+use crate::services::chain_tip::ChainTip;
+use crate::services::charms::{EscrowSpellData, EscrowWitness, ProvedTransaction, SpellProveRequest};
+use crate::services::escrow_script::{escrow_script_tree, EscrowKeys, EscrowScriptTree};
+use crate::services::events::{Event, EventBus};
+use crate::services::fees::{FeeEstimator, FeeTier};
+use crate::services::jobs::JobQueue;
+use crate::services::mempool_monitor::{ExternalSpend, SpendWatcher, WatchedOutpoint};
+use crate::services::sealing::Sealer;
+use crate::services::sessions::{Session, SessionStore};
+use crate::services::signatures::verify_signature;
+use crate::services::spell_check::Contract;
+use crate::services::zmq::{self, ChainEvents};
 use crate::services::{BitcoinService, CharmsService};
 
 /// Application state for escrow routes
 pub struct EscrowState {
     pub charms: Arc<CharmsService>,
+    pub apps: Arc<AppArtifacts>,
+    /// Bitcoin node, for its watch-only wallets
     pub bitcoin: Arc<BitcoinService>,
-    pub escrows: RwLock<Vec<EscrowRecord>>,
+    /// Chain state, broadcasts and fee estimates
+    pub chain: Arc<dyn ChainBackend>,
+    /// Best-block height expiries are set from
+    pub tip: Arc<ChainTip>,
+    pub fees: FeeEstimator,
+    pub events: EventBus,
+    /// Seals revealed preimages kept with escrows
+    pub sealer: Arc<Sealer>,
+    pub sessions: SessionStore,
+    pub db: DbPool,
+}
+
+impl FromRef<Arc<EscrowState>> for SessionStore {
+    fn from_ref(state: &Arc<EscrowState>) -> Self {
+        state.sessions.clone()
+    }
+}
+
+impl NetworkState for Arc<EscrowState> {
+    fn network(&self) -> Network {
+        self.bitcoin.network()
+    }
 }
 
 /// Escrow status
@@ -31,16 +93,96 @@ pub enum EscrowStatus {
     Refunded,
     Expired,
     Disputed,
+    /// Legacy order link that never recorded an escrow on chain; nothing
+    /// can be done with it
+    Void,
+}
+
+/// Spell transaction awaiting broadcast; the escrow status only changes once
+/// it is accepted by the network
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PendingAction {
+    Release,
+    Refund,
+    /// Release of the milestone with this index
+    Milestone(u32),
+    /// Recording a dispute on-chain; the escrow charm moves to a new UTXO
+    Dispute,
 }
 
 /// Escrow type
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[allow(clippy::enum_variant_names)]
 pub enum EscrowType {
     TwoParty,
     TwoOfTwo,
     TwoOfThree,
 }
 
+impl EscrowStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EscrowStatus::Active => "active",
+            EscrowStatus::Released => "released",
+            EscrowStatus::Refunded => "refunded",
+            EscrowStatus::Expired => "expired",
+            EscrowStatus::Disputed => "disputed",
+            EscrowStatus::Void => "void",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "active" => Some(EscrowStatus::Active),
+            "released" => Some(EscrowStatus::Released),
+            "refunded" => Some(EscrowStatus::Refunded),
+            "expired" => Some(EscrowStatus::Expired),
+            "disputed" => Some(EscrowStatus::Disputed),
+            "void" => Some(EscrowStatus::Void),
+            _ => None,
+        }
+    }
+}
+
+impl EscrowType {
+    /// Numeric discriminant used by the escrow app contract
+    pub fn as_u8(&self) -> u8 {
+        match self {
+            EscrowType::TwoParty => 0,
+            EscrowType::TwoOfTwo => 1,
+            EscrowType::TwoOfThree => 2,
+        }
+    }
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(EscrowType::TwoParty),
+            1 => Some(EscrowType::TwoOfTwo),
+            2 => Some(EscrowType::TwoOfThree),
+            _ => None,
+        }
+    }
+
+    /// Name stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EscrowType::TwoParty => "two_party",
+            EscrowType::TwoOfTwo => "two_of_two",
+            EscrowType::TwoOfThree => "two_of_three",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "two_party" => Some(EscrowType::TwoParty),
+            "two_of_two" => Some(EscrowType::TwoOfTwo),
+            "two_of_three" => Some(EscrowType::TwoOfThree),
+            _ => None,
+        }
+    }
+}
+
 /// Escrow record in database
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EscrowRecord {
@@ -56,9 +198,278 @@ pub struct EscrowRecord {
     pub expiry_height: u64,
     pub status: EscrowStatus,
     pub created_at: u64,
+    /// Block height recorded in the escrow charm at creation
+    pub created_height: u64,
     pub order_id: Option<String>,
     pub utxo_id: Option<String>,
+    /// Taproot address the escrow charm is locked at
+    pub escrow_address: String,
+    pub tx_id: Option<String>,
+    pub pending_action: Option<PendingAction>,
+    pub dispute: Option<DisputeInfo>,
+    /// Hashlock preimage (hex) once revealed through a claim
+    pub revealed_preimage: Option<String>,
+    /// Template the escrow was instantiated from
+    pub template_id: Option<String>,
+    pub fee: Option<EscrowFee>,
+    /// Installments the escrow is released in; empty for a single release
+    pub milestones: Vec<EscrowMilestone>,
+    /// Escrow UTXO as seen at the escrow address by the deposit tracker
+    #[serde(default)]
+    pub deposit: Option<EscrowDeposit>,
+    /// Depositor's address for change
+    #[serde(default)]
+    pub depositor_address: Option<String>,
+    /// Recipient's address, when the escrow settles an order
+    #[serde(default)]
+    pub recipient_address: Option<String>,
+}
+
+impl From<&EscrowRecord> for db::EscrowRecord {
+    fn from(escrow: &EscrowRecord) -> Self {
+        let created_at = chrono::DateTime::from_timestamp(escrow.created_at as i64, 0).unwrap_or_default();
+        Self {
+            id: escrow.id.clone(),
+            escrow_id: escrow.escrow_id.clone(),
+            order_id: escrow.order_id.clone(),
+            depositor_pubkey: escrow.depositor_pubkey.clone(),
+            recipient_pubkey: escrow.recipient_pubkey.clone(),
+            arbiter_pubkey: escrow.arbiter_pubkey.clone(),
+            escrow_type: escrow.escrow_type.as_str().to_string(),
+            held_token_id: escrow.held_token_id.clone(),
+            held_amount: escrow.held_amount as i64,
+            release_hash: escrow.release_hash.clone(),
+            expiry_height: escrow.expiry_height as i64,
+            status: escrow.status.as_str().to_string(),
+            created_height: escrow.created_height as i64,
+            utxo_id: escrow.utxo_id.clone(),
+            escrow_address: escrow.escrow_address.clone(),
+            tx_id: escrow.tx_id.clone(),
+            pending_action: json_column(&escrow.pending_action),
+            dispute: json_column(&escrow.dispute),
+            revealed_preimage: escrow.revealed_preimage.clone(),
+            template_id: escrow.template_id.clone(),
+            fee: json_column(&escrow.fee),
+            deposit: json_column(&escrow.deposit),
+            depositor_address: escrow.depositor_address.clone(),
+            recipient_address: escrow.recipient_address.clone(),
+            created_at,
+            updated_at: chrono::Utc::now(),
+        }
+    }
+}
+
+/// Persist an escrow's move from `from` to its current status, with the state
+/// event recording who made it and why. Fails if the escrow is no longer in
+/// `from`.
+async fn record_escrow_transition(
+    state: &EscrowState,
+    escrow: &EscrowRecord,
+    from: EscrowStatus,
+    by: &db::Transition<'_>,
+) -> anyhow::Result<()> {
+    db::transition_escrow(&state.db, &escrow_row(state, escrow)?, from.as_str(), by).await
+}
+
+/// Where sealed preimages are stored, bound into their encryption
+const PREIMAGE_PURPOSE: &str = "escrows.revealed_preimage";
+
+/// An escrow as its database row, with the revealed preimage sealed
+fn escrow_row(state: &EscrowState, escrow: &EscrowRecord) -> anyhow::Result<db::EscrowRecord> {
+    let mut row = db::EscrowRecord::from(escrow);
+    row.revealed_preimage = state.sealer.seal_optional(PREIMAGE_PURPOSE, row.revealed_preimage.as_deref())?;
+    Ok(row)
+}
+
+/// `EscrowRecord::from_db` for a row stored with `escrow_row`
+fn escrow_from_row(
+    state: &EscrowState,
+    mut row: db::EscrowRecord,
+    milestones: Vec<db::EscrowMilestoneRecord>,
+) -> anyhow::Result<EscrowRecord> {
+    row.revealed_preimage = state.sealer.open_optional(PREIMAGE_PURPOSE, row.revealed_preimage.as_deref())?;
+    EscrowRecord::from_db(row, milestones)
+}
+
+/// Load an escrow with its milestones
+pub(crate) async fn load_escrow(state: &EscrowState, id: &str) -> Result<EscrowRecord, ApiError> {
+    let record = db::get_escrow(&state.db, id).await?.ok_or_else(|| ApiError::not_found("Escrow not found"))?;
+    let milestones = db::get_escrow_milestones(&state.db, id).await?;
+    Ok(escrow_from_row(state, record, milestones)?)
+}
+
+/// Load every escrow, or those in `status`, newest first
+async fn load_escrows(state: &EscrowState, status: Option<EscrowStatus>) -> anyhow::Result<Vec<EscrowRecord>> {
+    let records = db::get_escrows(&state.db, status.map(|s| s.as_str())).await?;
+    escrows_from_rows(state, records).await
+}
+
+/// Escrows from their rows, with their milestones
+async fn escrows_from_rows(state: &EscrowState, records: Vec<db::EscrowRecord>) -> anyhow::Result<Vec<EscrowRecord>> {
+    let ids: Vec<String> = records.iter().map(|r| r.id.clone()).collect();
+    let mut milestones = db::get_milestones_for_escrows(&state.db, &ids).await?;
+    records
+        .into_iter()
+        .map(|record| {
+            let milestones = milestones.remove(&record.id).unwrap_or_default();
+            escrow_from_row(state, record, milestones)
+        })
+        .collect()
+}
+
+/// One page of the escrows matching `filter`, newest first
+async fn escrow_page(state: &EscrowState, mut filter: db::EscrowFilter, page: PageQuery) -> Result<EscrowPage, ApiError> {
+    let limit = page.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    if let Some(cursor) = &page.cursor {
+        if db::get_escrow(&state.db, cursor).await?.is_none() {
+            return Err(ApiError::bad_request("Invalid cursor"));
+        }
+    }
+    filter.after_id = page.cursor;
+    // One extra to tell whether another page follows
+    filter.limit = Some(limit as i64 + 1);
+
+    let mut records = db::query_escrows(&state.db, &filter).await?;
+    let more = records.len() > limit as usize;
+    records.truncate(limit as usize);
+    let escrows = escrows_from_rows(state, records).await?;
+    let next_cursor = if more { escrows.last().map(|e| e.id.clone()) } else { None };
+
+    Ok(EscrowPage { escrows, limit, next_cursor })
+}
+
+/// Write back an escrow change that leaves its status as it is. Fails with
+/// `StaleEscrow` if the escrow moved to another status since it was loaded.
+pub(crate) async fn save_escrow(state: &EscrowState, escrow: &EscrowRecord) -> anyhow::Result<()> {
+    if db::update_escrow(&state.db, &escrow_row(state, escrow)?).await? {
+        return Ok(());
+    }
+    match db::get_escrow(&state.db, &escrow.id).await? {
+        Some(_) => Err(db::StaleEscrow { id: escrow.id.clone(), expected: escrow.status.as_str().to_string() }.into()),
+        None => anyhow::bail!("Escrow {} not found", escrow.id),
+    }
+}
+
+impl EscrowRecord {
+    /// Rebuild an escrow from its database row and milestone rows
+    pub fn from_db(record: db::EscrowRecord, milestones: Vec<db::EscrowMilestoneRecord>) -> anyhow::Result<Self> {
+        let milestones = milestones
+            .into_iter()
+            .map(|m| {
+                let status = match m.status.as_str() {
+                    "pending" => MilestoneStatus::Pending,
+                    "released" => MilestoneStatus::Released,
+                    other => anyhow::bail!("Unknown milestone status {}", other),
+                };
+                Ok(EscrowMilestone {
+                    index: m.idx as u32,
+                    amount: m.amount as u64,
+                    description: m.description,
+                    status,
+                    tx_id: m.tx_id,
+                    released_at: m.released_at.map(|at| at.timestamp() as u64),
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            escrow_type: EscrowType::parse(&record.escrow_type)
+                .ok_or_else(|| anyhow::anyhow!("Unknown escrow type {}", record.escrow_type))?,
+            status: EscrowStatus::parse(&record.status)
+                .ok_or_else(|| anyhow::anyhow!("Unknown escrow status {}", record.status))?,
+            pending_action: from_json_column(record.pending_action.as_deref())?,
+            dispute: from_json_column(record.dispute.as_deref())?,
+            fee: from_json_column(record.fee.as_deref())?,
+            deposit: from_json_column(record.deposit.as_deref())?,
+            id: record.id,
+            escrow_id: record.escrow_id,
+            depositor_pubkey: record.depositor_pubkey,
+            recipient_pubkey: record.recipient_pubkey,
+            arbiter_pubkey: record.arbiter_pubkey,
+            held_token_id: record.held_token_id,
+            held_amount: record.held_amount as u64,
+            release_hash: record.release_hash,
+            expiry_height: record.expiry_height as u64,
+            created_at: record.created_at.timestamp() as u64,
+            created_height: record.created_height as u64,
+            order_id: record.order_id,
+            utxo_id: record.utxo_id,
+            escrow_address: record.escrow_address,
+            tx_id: record.tx_id,
+            revealed_preimage: record.revealed_preimage,
+            template_id: record.template_id,
+            milestones,
+            depositor_address: record.depositor_address,
+            recipient_address: record.recipient_address,
+        })
+    }
+}
+
+/// JSON text for an optional structured column
+fn json_column<T: Serialize>(value: &Option<T>) -> Option<String> {
+    value.as_ref().and_then(|value| serde_json::to_string(value).ok())
+}
+
+fn from_json_column<T: serde::de::DeserializeOwned>(json: Option<&str>) -> anyhow::Result<Option<T>> {
+    Ok(json.map(serde_json::from_str).transpose()?)
+}
+
+/// UTXO credited to an escrow once it appears at the escrow address
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EscrowDeposit {
+    pub outpoint: String,
+    /// Sats
+    pub value: u64,
+    pub confirmed: bool,
+}
+
+/// Milestone status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MilestoneStatus {
+    Pending,
+    Released,
+}
+
+/// One installment of a milestone escrow
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EscrowMilestone {
+    pub index: u32,
+    pub amount: u64,
+    pub description: String,
+    pub status: MilestoneStatus,
     pub tx_id: Option<String>,
+    pub released_at: Option<u64>,
+}
+
+/// Milestone definition supplied at escrow creation
+#[derive(Debug, Clone, Deserialize)]
+pub struct MilestoneSpec {
+    pub amount: u64,
+    #[serde(default)]
+    pub description: String,
+}
+
+/// Marketplace fee owed on an escrow, split between the parties
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EscrowFee {
+    pub fee_bps: u32,
+    pub amount: u64,
+    pub recipient: String,
+    pub depositor_share: u64,
+    pub recipient_share: u64,
+}
+
+/// Dispute details recorded when an escrow is disputed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DisputeInfo {
+    pub reason: String,
+    pub initiator_pubkey: String,
+    pub evidence_hash: Option<String>,
+    pub opened_at: u64,
+    pub resolved_at: Option<u64>,
+    /// "depositor" or "recipient" once resolved
+    pub winner: Option<String>,
 }
 
 /// Create escrow request
@@ -73,21 +484,105 @@ pub struct CreateEscrowRequest {
     pub release_hash: Option<String>,
     pub expiry_height: u64,
     pub order_id: Option<String>,
+    /// UTXO holding the tokens to escrow (also determines the escrow identity)
+    #[serde(default)]
+    pub funding_utxo: Option<OutPoint>,
+    #[serde(default)]
+    pub funding_utxo_value: Option<u64>,
+    /// Depositor's address for change
+    #[serde(default)]
+    pub depositor_address: Option<String>,
+    /// Optional installments; their amounts must add up to `amount`
+    #[serde(default)]
+    pub milestones: Vec<MilestoneSpec>,
+}
+
+impl BitcoinAddresses for CreateEscrowRequest {
+    fn bitcoin_addresses(&self) -> Vec<(&'static str, &str)> {
+        self.depositor_address.as_deref().map(|address| ("depositor_address", address)).into_iter().collect()
+    }
+}
+
+/// Escrow spell response with unsigned transactions to sign and broadcast
+#[derive(Debug, Serialize)]
+pub struct EscrowSpellResponse {
+    pub escrow: EscrowRecord,
+    pub spell: SpellData,
+    pub unsigned_txs: Vec<UnsignedTransaction>,
+    pub signing_instructions: SigningInstructions,
 }
 
 /// Release escrow request
 #[derive(Debug, Deserialize)]
 pub struct ReleaseEscrowRequest {
     pub preimage: Option<String>,
+    /// Signature over `release:<id>:<recipient_address>` (see `escrow_action_message`)
     pub signature: String,
     pub signer_pubkey: String,
+    /// Address receiving the released tokens
+    pub recipient_address: String,
+    /// UTXO paying the transaction fee
+    pub funding_utxo: OutPoint,
+    #[serde(default)]
+    pub funding_utxo_value: Option<u64>,
+    /// Signer's address for change
+    pub change_address: String,
+}
+
+impl BitcoinAddresses for ReleaseEscrowRequest {
+    fn bitcoin_addresses(&self) -> Vec<(&'static str, &str)> {
+        vec![("recipient_address", self.recipient_address.as_str()), ("change_address", self.change_address.as_str())]
+    }
+}
+
+/// HTLC claim request (recipient reveals the hashlock preimage)
+#[derive(Debug, Deserialize)]
+pub struct ClaimEscrowRequest {
+    /// Hex-encoded preimage of `release_hash`
+    pub preimage: String,
+    pub claimer_pubkey: String,
+    /// Signature over the release payload (see `escrow_action_message`)
+    pub signature: String,
+    /// Address receiving the claimed tokens
+    pub recipient_address: String,
+    /// UTXO paying the transaction fee
+    pub funding_utxo: OutPoint,
+    #[serde(default)]
+    pub funding_utxo_value: Option<u64>,
+    /// Claimer's address for change
+    pub change_address: String,
+}
+
+impl BitcoinAddresses for ClaimEscrowRequest {
+    fn bitcoin_addresses(&self) -> Vec<(&'static str, &str)> {
+        vec![("recipient_address", self.recipient_address.as_str()), ("change_address", self.change_address.as_str())]
+    }
 }
 
 /// Refund escrow request
 #[derive(Debug, Deserialize)]
 pub struct RefundEscrowRequest {
     pub reason: String,
+    /// Depositor's signature over `refund:<id>:<depositor_address>:<reason>`
     pub signature: String,
+    /// Address receiving the refunded tokens
+    pub depositor_address: String,
+    /// UTXO paying the transaction fee
+    pub funding_utxo: OutPoint,
+    #[serde(default)]
+    pub funding_utxo_value: Option<u64>,
+}
+
+impl BitcoinAddresses for RefundEscrowRequest {
+    fn bitcoin_addresses(&self) -> Vec<(&'static str, &str)> {
+        vec![("depositor_address", self.depositor_address.as_str())]
+    }
+}
+
+/// Broadcast a signed escrow transaction
+#[derive(Debug, Deserialize)]
+pub struct BroadcastEscrowRequest {
+    pub signed_tx_hex: String,
 }
 
 /// Dispute escrow request
@@ -96,306 +591,3020 @@ pub struct DisputeEscrowRequest {
     pub reason: String,
     pub evidence_hash: Option<String>,
     pub initiator_pubkey: String,
+    /// Initiator's signature over `dispute:<id>:<reason>`
+    pub signature: String,
+    /// Funds the dispute-escrow spell; without it the dispute is only
+    /// recorded here
+    #[serde(default)]
+    pub funding_utxo: Option<OutPoint>,
+    #[serde(default)]
+    pub funding_utxo_value: Option<u64>,
+    /// Initiator's address for change from the funding UTXO
+    #[serde(default)]
+    pub change_address: Option<String>,
 }
 
-/// Resolve dispute request
-#[derive(Debug, Deserialize)]
-pub struct ResolveDisputeRequest {
-    pub winner: String, // "depositor" or "recipient"
-    pub arbiter_signature: String,
+impl BitcoinAddresses for DisputeEscrowRequest {
+    fn bitcoin_addresses(&self) -> Vec<(&'static str, &str)> {
+        self.change_address.as_deref().map(|address| ("change_address", address)).into_iter().collect()
+    }
 }
 
-/// API response wrapper
+/// Dispute response; the spell and transactions are set when a funding UTXO
+/// was given
 #[derive(Debug, Serialize)]
-pub struct EscrowResponse<T> {
-    pub success: bool,
-    pub data: Option<T>,
-    pub error: Option<String>,
+pub struct DisputeEscrowResponse {
+    pub escrow: EscrowRecord,
+    pub spell: Option<SpellData>,
+    pub unsigned_txs: Vec<UnsignedTransaction>,
+    pub signing_instructions: Option<SigningInstructions>,
 }
 
-impl<T> EscrowResponse<T> {
-    pub fn success(data: T) -> Self {
-        Self {
-            success: true,
-            data: Some(data),
-            error: None,
-        }
-    }
+/// Dispute evidence document. The content lives in object storage; only its
+/// hash and storage reference are kept here.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvidenceRecord {
+    pub id: String,
+    pub escrow_id: String,
+    pub submitter_pubkey: String,
+    /// SHA-256 of the document, hex encoded
+    pub content_hash: String,
+    /// Object storage reference (e.g. `s3://bucket/key` or `ipfs://cid`)
+    pub storage_ref: String,
+    pub content_type: Option<String>,
+    pub description: Option<String>,
+    pub submitted_at: u64,
+}
 
-    pub fn error(msg: impl Into<String>) -> Self {
+impl From<db::EscrowEvidenceRecord> for EvidenceRecord {
+    fn from(record: db::EscrowEvidenceRecord) -> Self {
         Self {
-            success: false,
-            data: None,
-            error: Some(msg.into()),
+            id: record.id,
+            escrow_id: record.escrow_id,
+            submitter_pubkey: record.submitter_pubkey,
+            content_hash: record.content_hash,
+            storage_ref: record.storage_ref,
+            content_type: record.content_type,
+            description: record.description,
+            submitted_at: record.submitted_at.timestamp() as u64,
         }
     }
 }
 
-/// Create the escrow router
-pub fn router(state: Arc<EscrowState>) -> Router {
-    Router::new()
-        .route("/", get(list_escrows).post(create_escrow))
-        .route("/{id}", get(get_escrow))
-        .route("/{id}/release", post(release_escrow))
-        .route("/{id}/refund", post(refund_escrow))
-        .route("/{id}/dispute", post(dispute_escrow))
-        .route("/{id}/resolve", post(resolve_dispute))
-        .route("/by-depositor/{pubkey}", get(get_escrows_by_depositor))
-        .route("/by-recipient/{pubkey}", get(get_escrows_by_recipient))
-        .with_state(state)
+/// Submit evidence request; the submitter must be one of the session's keys
+#[derive(Debug, Deserialize)]
+pub struct SubmitEvidenceRequest {
+    pub submitter_pubkey: String,
+    pub content_hash: String,
+    pub storage_ref: String,
+    pub content_type: Option<String>,
+    pub description: Option<String>,
 }
 
-/// List all escrows
-async fn list_escrows(
-    State(state): State<Arc<EscrowState>>,
-) -> Json<EscrowResponse<Vec<EscrowRecord>>> {
-    let escrows = state.escrows.read().await;
-    Json(EscrowResponse::success(escrows.clone()))
+/// Query parameters for listing escrows
+#[derive(Debug, Deserialize)]
+pub struct ListEscrowsQuery {
+    pub status: Option<String>,
+    pub token_id: Option<String>,
+    pub depositor: Option<String>,
+    pub recipient: Option<String>,
+    /// Unix timestamp bounds on `created_at` (inclusive)
+    pub created_after: Option<u64>,
+    pub created_before: Option<u64>,
+    pub limit: Option<u32>,
+    /// ID of the last escrow of the previous page
+    pub cursor: Option<String>,
 }
 
-/// Get escrow by ID
-async fn get_escrow(
-    State(state): State<Arc<EscrowState>>,
-    Path(id): Path<String>,
-) -> Result<Json<EscrowResponse<EscrowRecord>>, StatusCode> {
-    let escrows = state.escrows.read().await;
-    
-    if let Some(escrow) = escrows.iter().find(|e| e.id == id) {
-        Ok(Json(EscrowResponse::success(escrow.clone())))
-    } else {
-        Ok(Json(EscrowResponse::error("Escrow not found")))
-    }
+/// List escrows response
+#[derive(Debug, Serialize)]
+pub struct ListEscrowsResponse {
+    pub escrows: Vec<EscrowRecord>,
+    /// Escrows matching all filters
+    pub total: u64,
+    /// Per-status counts for the non-status filters
+    pub counts: BTreeMap<String, u64>,
+    pub limit: u32,
+    pub next_cursor: Option<String>,
 }
 
-/// Create a new escrow
-async fn create_escrow(
-    State(state): State<Arc<EscrowState>>,
-    Json(req): Json<CreateEscrowRequest>,
-) -> Result<Json<EscrowResponse<EscrowRecord>>, StatusCode> {
-    // Validate escrow type requirements
-    if req.escrow_type == EscrowType::TwoOfThree && req.arbiter_pubkey.is_none() {
-        return Ok(Json(EscrowResponse::error(
-            "2-of-3 escrow requires arbiter pubkey",
-        )));
-    }
-
-    // Generate unique escrow ID
-    let id = Uuid::new_v4().to_string();
-    let escrow_id = format!("escrow_{}", &id[..8]);
+/// Page of an escrow listing
+#[derive(Debug, Deserialize)]
+pub struct PageQuery {
+    pub limit: Option<u32>,
+    /// ID of the last escrow of the previous page
+    pub cursor: Option<String>,
+}
 
-    // Create escrow record
-    let escrow = EscrowRecord {
-        id: id.clone(),
-        escrow_id,
-        depositor_pubkey: req.depositor_pubkey,
-        recipient_pubkey: req.recipient_pubkey,
-        arbiter_pubkey: req.arbiter_pubkey,
-        escrow_type: req.escrow_type,
-        held_token_id: req.token_id,
-        held_amount: req.amount,
-        release_hash: req.release_hash,
-        expiry_height: req.expiry_height,
-        status: EscrowStatus::Active,
-        created_at: chrono::Utc::now().timestamp() as u64,
-        order_id: req.order_id,
-        utxo_id: None,
-        tx_id: None,
-    };
+/// One page of escrows, newest first
+#[derive(Debug, Serialize)]
+pub struct EscrowPage {
+    pub escrows: Vec<EscrowRecord>,
+    pub limit: u32,
+    pub next_cursor: Option<String>,
+}
 
-    // Store escrow
-    let mut escrows = state.escrows.write().await;
-    escrows.push(escrow.clone());
+/// Default and maximum page size for escrow listings
+const DEFAULT_PAGE_SIZE: u32 = 20;
+const MAX_PAGE_SIZE: u32 = 100;
 
-    // TODO: Build and broadcast create-escrow spell
-    // let spell = state.charms.build_create_escrow_spell(&escrow)?;
-    // let tx_id = state.bitcoin.broadcast_spell(&spell)?;
+/// Create an escrow for an existing order. Amount, token, depositor, and
+/// expiry come from the order.
+#[derive(Debug, Deserialize)]
+pub struct CreateOrderEscrowRequest {
+    /// Maker's public key (the order only records the maker's address)
+    pub depositor_pubkey: String,
+    pub recipient_pubkey: String,
+    pub recipient_address: String,
+    pub arbiter_pubkey: Option<String>,
+    /// Hashlock; required for cross-chain orders
+    pub release_hash: Option<String>,
+    /// Overrides the order's expiry height
+    pub expiry_height: Option<u64>,
+    pub funding_utxo: Option<OutPoint>,
+    #[serde(default)]
+    pub funding_utxo_value: Option<u64>,
+}
 
-    Ok(Json(EscrowResponse::success(escrow)))
+impl BitcoinAddresses for CreateOrderEscrowRequest {
+    fn bitcoin_addresses(&self) -> Vec<(&'static str, &str)> {
+        vec![("recipient_address", self.recipient_address.as_str())]
+    }
 }
 
-/// Release escrow to recipient
-async fn release_escrow(
-    State(state): State<Arc<EscrowState>>,
-    Path(id): Path<String>,
-    Json(req): Json<ReleaseEscrowRequest>,
-) -> Result<Json<EscrowResponse<EscrowRecord>>, StatusCode> {
-    let mut escrows = state.escrows.write().await;
-    
-    if let Some(escrow) = escrows.iter_mut().find(|e| e.id == id) {
-        // Validate escrow is active
-        if escrow.status != EscrowStatus::Active {
-            return Ok(Json(EscrowResponse::error(
-                "Escrow is not active",
-            )));
-        }
+/// Escrow linked to an order
+#[derive(Debug, Serialize)]
+pub struct OrderEscrowResponse {
+    /// Escrow as stored when it was created for the order
+    pub link: EscrowRecord,
+    /// Current escrow state; always present now that escrows are stored,
+    /// kept for clients reading it
+    pub escrow: Option<EscrowRecord>,
+}
 
-        // Validate release hash if present
-        if escrow.release_hash.is_some() && req.preimage.is_none() {
-            return Ok(Json(EscrowResponse::error(
-                "Preimage required for hash-locked escrow",
-            )));
-        }
+/// Escrow terms agreed by all parties before the escrow is created on-chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EscrowTerms {
+    pub depositor_pubkey: String,
+    pub recipient_pubkey: String,
+    pub arbiter_pubkey: Option<String>,
+    pub escrow_type: EscrowType,
+    pub token_id: String,
+    pub amount: u64,
+    pub release_hash: Option<String>,
+    pub expiry_height: u64,
+    pub order_id: Option<String>,
+}
 
-        // Validate signer is authorized
-        let is_authorized = req.signer_pubkey == escrow.depositor_pubkey
-            || req.signer_pubkey == escrow.recipient_pubkey
-            || escrow.arbiter_pubkey.as_ref().map(|a| a == &req.signer_pubkey).unwrap_or(false);
+impl EscrowTerms {
+    /// SHA-256 (hex) of the canonical terms string; this is what parties sign
+    pub fn hash(&self) -> String {
+        let canonical = format!(
+            "{}:{}:{}:{}:{}:{}:{}:{}:{}",
+            self.depositor_pubkey,
+            self.recipient_pubkey,
+            self.arbiter_pubkey.as_deref().unwrap_or(""),
+            self.escrow_type.as_u8(),
+            self.token_id,
+            self.amount,
+            self.release_hash.as_deref().unwrap_or(""),
+            self.expiry_height,
+            self.order_id.as_deref().unwrap_or(""),
+        );
+        hex::encode(Sha256::digest(canonical.as_bytes()))
+    }
 
-        if !is_authorized {
-            return Ok(Json(EscrowResponse::error(
-                "Signer not authorized to release escrow",
-            )));
-        }
+    /// Parties other than the depositor who must accept
+    fn required_acceptors(&self) -> Vec<&String> {
+        std::iter::once(&self.recipient_pubkey)
+            .chain(self.arbiter_pubkey.as_ref())
+            .collect()
+    }
+}
 
-        // Update escrow status
-        escrow.status = EscrowStatus::Released;
+/// Proposal status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProposalStatus {
+    /// Waiting for recipient/arbiter acceptance
+    Pending,
+    /// All parties accepted; the depositor can create the escrow
+    Accepted,
+    /// Escrow creation spell built
+    Created,
+}
 
-        // TODO: Build and broadcast release-escrow spell
+/// A party's signed acceptance of the terms
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Acceptance {
+    pub pubkey: String,
+    pub signature: String,
+    pub accepted_at: u64,
+}
 
-        Ok(Json(EscrowResponse::success(escrow.clone())))
-    } else {
-        Ok(Json(EscrowResponse::error("Escrow not found")))
-    }
+/// Escrow proposed by a depositor, awaiting acceptance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EscrowProposal {
+    pub id: String,
+    pub terms: EscrowTerms,
+    pub terms_hash: String,
+    pub status: ProposalStatus,
+    pub acceptances: Vec<Acceptance>,
+    pub escrow_id: Option<String>,
+    pub created_at: u64,
 }
 
-/// Refund escrow to depositor
-async fn refund_escrow(
-    State(state): State<Arc<EscrowState>>,
-    Path(id): Path<String>,
-    Json(_req): Json<RefundEscrowRequest>,
-) -> Result<Json<EscrowResponse<EscrowRecord>>, StatusCode> {
-    let mut escrows = state.escrows.write().await;
-    
-    if let Some(escrow) = escrows.iter_mut().find(|e| e.id == id) {
-        // Validate escrow is active or expired
-        if escrow.status != EscrowStatus::Active && escrow.status != EscrowStatus::Expired {
-            return Ok(Json(EscrowResponse::error(
-                "Escrow cannot be refunded in current state",
-            )));
+impl ProposalStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProposalStatus::Pending => "pending",
+            ProposalStatus::Accepted => "accepted",
+            ProposalStatus::Created => "created",
         }
+    }
 
-        // Update escrow status
-        escrow.status = EscrowStatus::Refunded;
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(ProposalStatus::Pending),
+            "accepted" => Some(ProposalStatus::Accepted),
+            "created" => Some(ProposalStatus::Created),
+            _ => None,
+        }
+    }
+}
 
-        // TODO: Build and broadcast refund-escrow spell
+impl From<&EscrowProposal> for db::EscrowProposalRecord {
+    fn from(proposal: &EscrowProposal) -> Self {
+        let created_at = chrono::DateTime::from_timestamp(proposal.created_at as i64, 0).unwrap_or_default();
+        Self {
+            id: proposal.id.clone(),
+            depositor_pubkey: proposal.terms.depositor_pubkey.clone(),
+            recipient_pubkey: proposal.terms.recipient_pubkey.clone(),
+            arbiter_pubkey: proposal.terms.arbiter_pubkey.clone(),
+            escrow_type: proposal.terms.escrow_type.as_str().to_string(),
+            token_id: proposal.terms.token_id.clone(),
+            amount: proposal.terms.amount as i64,
+            release_hash: proposal.terms.release_hash.clone(),
+            expiry_height: proposal.terms.expiry_height as i64,
+            order_id: proposal.terms.order_id.clone(),
+            terms_hash: proposal.terms_hash.clone(),
+            status: proposal.status.as_str().to_string(),
+            escrow_id: proposal.escrow_id.clone(),
+            created_at,
+            updated_at: chrono::Utc::now(),
+        }
+    }
+}
 
-        Ok(Json(EscrowResponse::success(escrow.clone())))
-    } else {
-        Ok(Json(EscrowResponse::error("Escrow not found")))
+impl EscrowProposal {
+    /// Rebuild a proposal from its database row and acceptance rows
+    pub fn from_db(
+        record: db::EscrowProposalRecord,
+        acceptances: Vec<db::EscrowProposalAcceptanceRecord>,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            terms: EscrowTerms {
+                depositor_pubkey: record.depositor_pubkey,
+                recipient_pubkey: record.recipient_pubkey,
+                arbiter_pubkey: record.arbiter_pubkey,
+                escrow_type: EscrowType::parse(&record.escrow_type)
+                    .ok_or_else(|| anyhow::anyhow!("Unknown escrow type {}", record.escrow_type))?,
+                token_id: record.token_id,
+                amount: record.amount as u64,
+                release_hash: record.release_hash,
+                expiry_height: record.expiry_height as u64,
+                order_id: record.order_id,
+            },
+            status: ProposalStatus::parse(&record.status)
+                .ok_or_else(|| anyhow::anyhow!("Unknown proposal status {}", record.status))?,
+            acceptances: acceptances
+                .into_iter()
+                .map(|a| Acceptance {
+                    pubkey: a.pubkey,
+                    signature: a.signature,
+                    accepted_at: a.accepted_at.timestamp() as u64,
+                })
+                .collect(),
+            id: record.id,
+            terms_hash: record.terms_hash,
+            escrow_id: record.escrow_id,
+            created_at: record.created_at.timestamp() as u64,
+        })
     }
 }
 
-/// Initiate dispute on escrow
-async fn dispute_escrow(
-    State(state): State<Arc<EscrowState>>,
-    Path(id): Path<String>,
-    Json(req): Json<DisputeEscrowRequest>,
+/// Load a proposal with its acceptances
+async fn load_proposal(state: &EscrowState, id: &str) -> anyhow::Result<Option<EscrowProposal>> {
+    let Some(record) = db::get_escrow_proposal(&state.db, id).await? else {
+        return Ok(None);
+    };
+    let mut acceptances = db::get_acceptances_for_proposals(&state.db, std::slice::from_ref(&record.id)).await?;
+    let acceptances = acceptances.remove(&record.id).unwrap_or_default();
+    EscrowProposal::from_db(record, acceptances).map(Some)
+}
+
+/// Propose escrow request
+#[derive(Debug, Deserialize)]
+pub struct ProposeEscrowRequest {
+    #[serde(flatten)]
+    pub terms: EscrowTerms,
+    /// Depositor's signature over `propose:<terms_hash>`
+    pub signature: String,
+}
+
+/// Accept proposal request
+#[derive(Debug, Deserialize)]
+pub struct AcceptProposalRequest {
+    pub signer_pubkey: String,
+    /// Signature over `accept:<terms_hash>`
+    pub signature: String,
+}
+
+/// Create the escrow for an accepted proposal
+#[derive(Debug, Deserialize)]
+pub struct CreateFromProposalRequest {
+    pub funding_utxo: OutPoint,
+    #[serde(default)]
+    pub funding_utxo_value: Option<u64>,
+    pub depositor_address: Option<String>,
+}
+
+impl BitcoinAddresses for CreateFromProposalRequest {
+    fn bitcoin_addresses(&self) -> Vec<(&'static str, &str)> {
+        self.depositor_address.as_deref().map(|address| ("depositor_address", address)).into_iter().collect()
+    }
+}
+
+/// Proposal listing query
+#[derive(Debug, Deserialize)]
+pub struct ListProposalsQuery {
+    /// Only proposals involving this party
+    pub pubkey: Option<String>,
+    /// Newest first, default 100, at most 500
+    pub limit: Option<i64>,
+}
+
+/// Dispute listing query
+#[derive(Debug, Deserialize)]
+pub struct ListDisputesQuery {
+    /// "open" (default), "resolved", or "all"
+    pub status: Option<String>,
+    /// Only disputes assigned to this arbiter
+    pub arbiter: Option<String>,
+    pub limit: Option<u32>,
+    /// ID of the escrow of the last dispute of the previous page
+    pub cursor: Option<String>,
+}
+
+/// Dispute with the evidence an arbiter needs to resolve it
+#[derive(Debug, Serialize)]
+pub struct DisputeSummary {
+    pub escrow: EscrowRecord,
+    pub evidence: Vec<EvidenceRecord>,
+    pub resolve_endpoint: String,
+}
+
+/// One page of disputes, oldest first
+#[derive(Debug, Serialize)]
+pub struct DisputePage {
+    pub disputes: Vec<DisputeSummary>,
+    pub limit: u32,
+    pub next_cursor: Option<String>,
+}
+
+/// Resolve dispute request
+#[derive(Debug, Deserialize)]
+pub struct ResolveDisputeRequest {
+    pub winner: String, // "depositor" or "recipient"
+    /// Arbiter's signature over `resolve:<id>:<winner>:<winner_address>`
+    pub arbiter_signature: String,
+    pub winner_address: String,
+    pub funding_utxo: OutPoint,
+    pub funding_utxo_value: Option<u64>,
+    /// Arbiter's address for change from the funding UTXO
+    pub change_address: String,
+}
+
+impl BitcoinAddresses for ResolveDisputeRequest {
+    fn bitcoin_addresses(&self) -> Vec<(&'static str, &str)> {
+        vec![("winner_address", self.winner_address.as_str()), ("change_address", self.change_address.as_str())]
+    }
+}
+
+/// Release milestone request (depositor approves one installment)
+#[derive(Debug, Deserialize)]
+pub struct ReleaseMilestoneRequest {
+    /// Depositor's signature over `release_milestone:<id>:<index>:<recipient_address>`
+    pub signature: String,
+    pub recipient_address: String,
+    pub funding_utxo: OutPoint,
+    pub funding_utxo_value: Option<u64>,
+    pub change_address: String,
+}
+
+impl BitcoinAddresses for ReleaseMilestoneRequest {
+    fn bitcoin_addresses(&self) -> Vec<(&'static str, &str)> {
+        vec![("recipient_address", self.recipient_address.as_str()), ("change_address", self.change_address.as_str())]
+    }
+}
+
+/// API response wrapper
+#[derive(Debug, Serialize)]
+pub struct EscrowResponse<T> {
+    pub success: bool,
+    pub data: Option<T>,
+    pub error: Option<String>,
+    /// Machine-readable error code, when the failure has one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<&'static str>,
+}
+
+impl EscrowResponse<EscrowSpellResponse> {
+    /// Attach the requests for the browser wallet named by `?wallet=`
+    pub(crate) fn for_wallet(mut self, wallet: WalletFormat) -> Self {
+        if let Some(data) = self.data.as_mut() {
+            wallet.apply(&mut data.unsigned_txs);
+        }
+        self
+    }
+}
+
+impl<T> EscrowResponse<T> {
+    pub fn success(data: T) -> Self {
+        Self {
+            success: true,
+            data: Some(data),
+            error: None,
+            code: None,
+        }
+    }
+
+    pub fn error(msg: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            data: None,
+            error: Some(msg.into()),
+            code: None,
+        }
+    }
+}
+
+impl<T> From<ApiError> for EscrowResponse<T> {
+    fn from(e: ApiError) -> Self {
+        Self {
+            code: Some(e.code),
+            ..Self::error(e.message)
+        }
+    }
+}
+
+impl<T> From<Result<T, ApiError>> for EscrowResponse<T> {
+    fn from(result: Result<T, ApiError>) -> Self {
+        match result {
+            Ok(data) => Self::success(data),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+/// Create the escrow router
+pub fn router(state: Arc<EscrowState>) -> Router {
+    Router::new()
+        .route("/", get(list_escrows).post(create_escrow))
+        .route("/templates", get(list_templates).post(create_template))
+        .route(
+            "/templates/:id",
+            get(get_template).put(update_template).delete(delete_template),
+        )
+        .route("/templates/:id/instantiate", post(instantiate_template))
+        .route("/proposals", get(list_proposals).post(propose_escrow))
+        .route("/proposals/:id", get(get_proposal))
+        .route("/proposals/:id/accept", post(accept_proposal))
+        .route("/proposals/:id/create", post(create_from_proposal))
+        .route("/:id", get(get_escrow))
+        .route("/:id/script", get(get_escrow_script))
+        .route("/:id/release", post(release_escrow))
+        .route("/:id/claim", post(claim_escrow))
+        .route("/:id/refund", post(refund_escrow))
+        .route("/:id/broadcast", post(broadcast_escrow))
+        .route("/:id/dispute", post(dispute_escrow))
+        .route("/:id/resolve", post(resolve_dispute))
+        .route("/:id/evidence", get(list_evidence).post(submit_evidence))
+        .route("/:id/milestones", get(list_milestones))
+        .route("/:id/milestones/:index/release", post(release_milestone))
+        .route("/mine", get(list_my_escrows))
+        .route("/by-depositor/:pubkey", get(get_escrows_by_depositor))
+        .route("/by-recipient/:pubkey", get(get_escrows_by_recipient))
+        .route("/by-arbiter/:pubkey", get(get_escrows_by_arbiter))
+        .route_layer(middleware::from_fn_with_state(state.sessions.clone(), require_session))
+        .with_state(state)
+}
+
+/// Create the order-escrow linkage routes (`/api/orders/:id/escrow`)
+pub fn order_escrow_router(state: Arc<EscrowState>) -> Router {
+    Router::new()
+        .route(
+            "/api/orders/:id/escrow",
+            get(get_order_escrow).post(create_order_escrow),
+        )
+        .route_layer(middleware::from_fn_with_state(state.sessions.clone(), require_session))
+        .with_state(state)
+}
+
+/// Create the dispute work-queue router for arbiters
+pub fn disputes_router(state: Arc<EscrowState>) -> Router {
+    Router::new()
+        .route("/", get(list_disputes))
+        .with_state(state)
+}
+
+/// List escrows with optional filters, newest first
+async fn list_escrows(
+    State(state): State<Arc<EscrowState>>,
+    Query(params): Query<ListEscrowsQuery>,
+) -> Result<Json<EscrowResponse<ListEscrowsResponse>>, ApiError> {
+    let limit = params.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let timestamp = |secs: u64| chrono::DateTime::from_timestamp(secs as i64, 0).unwrap_or_default();
+
+    if let Some(cursor) = &params.cursor {
+        if db::get_escrow(&state.db, cursor).await?.is_none() {
+            return Ok(Json(EscrowResponse::error("Invalid cursor")));
+        }
+    }
+
+    let filter = db::EscrowFilter {
+        status: params.status.as_deref().map(str::to_lowercase),
+        held_token_id: params.token_id,
+        depositor_pubkey: params.depositor,
+        recipient_pubkey: params.recipient,
+        created_after: params.created_after.map(timestamp),
+        created_before: params.created_before.map(timestamp),
+        after_id: params.cursor,
+        // One extra to tell whether another page follows
+        limit: Some(limit as i64 + 1),
+        ..Default::default()
+    };
+
+    // Counts leave out the status filter, so they can drive status tabs
+    let counts: BTreeMap<String, u64> = db::count_escrows_by_status(&state.db, &filter)
+        .await?
+        .into_iter()
+        .map(|(status, count)| (status, count as u64))
+        .collect();
+    let total = match &filter.status {
+        Some(status) => counts.get(status).copied().unwrap_or(0),
+        None => counts.values().sum(),
+    };
+
+    let mut records = db::query_escrows(&state.db, &filter).await?;
+    let more = records.len() > limit as usize;
+    records.truncate(limit as usize);
+    let escrows = escrows_from_rows(&state, records).await?;
+    let next_cursor = if more { escrows.last().map(|e| e.id.clone()) } else { None };
+
+    Ok(Json(EscrowResponse::success(ListEscrowsResponse {
+        total,
+        escrows,
+        counts,
+        limit,
+        next_cursor,
+    })))
+}
+
+/// Get escrow by ID
+async fn get_escrow(
+    State(state): State<Arc<EscrowState>>,
+    Path(id): Path<String>,
 ) -> Result<Json<EscrowResponse<EscrowRecord>>, StatusCode> {
-    let mut escrows = state.escrows.write().await;
-    
-    if let Some(escrow) = escrows.iter_mut().find(|e| e.id == id) {
-        // Validate escrow type supports disputes
-        if escrow.escrow_type != EscrowType::TwoOfThree {
+    Ok(Json(match load_escrow(&state, &id).await {
+        Ok(escrow) => EscrowResponse::success(escrow),
+        Err(e) => EscrowResponse::from(e),
+    }))
+}
+
+/// Get the script tree an escrow is locked at, for spending it directly
+async fn get_escrow_script(
+    State(state): State<Arc<EscrowState>>,
+    Path(id): Path<String>,
+) -> Json<EscrowResponse<EscrowScriptTree>> {
+    let escrow = match load_escrow(&state, &id).await {
+        Ok(escrow) => escrow,
+        Err(e) => return Json(EscrowResponse::from(e)),
+    };
+
+    match escrow_tree(
+        &escrow.escrow_id,
+        &escrow.depositor_pubkey,
+        &escrow.recipient_pubkey,
+        escrow.arbiter_pubkey.as_deref(),
+        escrow.release_hash.as_deref(),
+        escrow.expiry_height,
+        state.bitcoin.network(),
+    ) {
+        // Escrows created before their outputs carried a script tree
+        Ok(tree) if tree.address != escrow.escrow_address => {
+            Json(EscrowResponse::error("Escrow output has no script tree"))
+        }
+        Ok(tree) => Json(EscrowResponse::success(tree)),
+        Err(e) => Json(EscrowResponse::error(format!("Invalid escrow terms: {}", e))),
+    }
+}
+
+/// Create a new escrow - builds the create-escrow spell and calls the prover
+async fn create_escrow(
+    State(state): State<Arc<EscrowState>>,
+    wallet: WalletFormat,
+    NetworkJson(req): NetworkJson<CreateEscrowRequest>,
+) -> Result<Json<EscrowResponse<EscrowSpellResponse>>, ApiError> {
+    Ok(Json(build_escrow(&state, req).await?.for_wallet(wallet)))
+}
+
+/// Build and prove the create-escrow spell, storing the new escrow. A
+/// request missing its funding value is an error; the rest fail in the body.
+pub(crate) async fn build_escrow(
+    state: &EscrowState,
+    req: CreateEscrowRequest,
+) -> Result<EscrowResponse<EscrowSpellResponse>, ApiError> {
+    let funding_utxo_value = req.funding_utxo_value.ok_or_else(|| ApiError::missing("funding_utxo_value"))?;
+
+    // Validate escrow type requirements
+    if req.escrow_type == EscrowType::TwoOfThree && req.arbiter_pubkey.is_none() {
+        return Ok(EscrowResponse::error(
+            "2-of-3 escrow requires arbiter pubkey",
+        ));
+    }
+
+    if !req.milestones.is_empty() {
+        if req.release_hash.is_some() {
+            return Ok(EscrowResponse::error("Milestone escrows cannot be hash-locked"));
+        }
+        if req.milestones.iter().any(|m| m.amount == 0) {
+            return Ok(EscrowResponse::error("Milestone amounts must be positive"));
+        }
+        let total = req.milestones.iter().try_fold(0u64, |acc, m| acc.checked_add(m.amount));
+        if total != Some(req.amount) {
+            return Ok(EscrowResponse::error("Milestone amounts must add up to the escrow amount"));
+        }
+    }
+
+    let funding_utxo = match req.funding_utxo {
+        Some(utxo) => utxo,
+        None => {
+            return Ok(EscrowResponse::error(
+                "funding_utxo is required to build the escrow spell",
+            ));
+        }
+    };
+
+    // Generate unique escrow ID; the on-chain identity is the hash of the funding UTXO
+    let id = Uuid::new_v4().to_string();
+    let escrow_id = hex::encode(Sha256::digest(funding_utxo.to_string().as_bytes()));
+    let change_address = req.depositor_address.clone().unwrap_or_else(|| req.depositor_pubkey.clone());
+
+    let escrow_address = match escrow_tree(
+        &escrow_id,
+        &req.depositor_pubkey,
+        &req.recipient_pubkey,
+        req.arbiter_pubkey.as_deref(),
+        req.release_hash.as_deref(),
+        req.expiry_height,
+        state.bitcoin.network(),
+    ) {
+        Ok(tree) => tree.address,
+        Err(e) => return Ok(EscrowResponse::error(format!("Invalid escrow terms: {}", e))),
+    };
+
+    let current_height = match state.tip.height().await {
+        Ok(height) => height,
+        Err(e) => {
+            tracing::error!("Refusing to create escrow {}: {}", escrow_id, e);
+            return Ok(EscrowResponse::error(format!("Cannot set escrow heights: {}", e)));
+        }
+    };
+    // An escrow already past its expiry could be refunded as soon as it is funded
+    if req.expiry_height <= current_height {
+        return Err(ApiError::bad_request(format!(
+            "expiry_height {} must be above the current block height {}",
+            req.expiry_height, current_height
+        )));
+    }
+
+    let spell_data = EscrowSpellData {
+        escrow_id: escrow_id.clone(),
+        depositor_pubkey: req.depositor_pubkey.clone(),
+        recipient_pubkey: req.recipient_pubkey.clone(),
+        arbiter_pubkey: req.arbiter_pubkey.clone(),
+        escrow_type: req.escrow_type.as_u8(),
+        token_id: req.token_id.clone(),
+        token_vk: DEFAULT_TOKEN_VK.to_string(),
+        amount: req.amount,
+        release_hash: req.release_hash.clone(),
+        expiry_height: req.expiry_height,
+        created_at: current_height,
+        order_id: req.order_id.clone(),
+        escrow_utxo: funding_utxo.to_string(),
+        escrow_address: escrow_address.clone(),
+    };
+
+    let template = spell_template(&state.db, CREATE_ESCROW).await;
+
+    let escrow_vk = state.apps.vk(Contract::Escrow).await;
+    let spell_built = match state.charms.build_create_escrow_spell(
+        &template.body,
+        &spell_data,
+        &escrow_vk,
+        current_height,
+    ) {
+        Ok(spell) => spell,
+        Err(e) => {
+            tracing::error!("Failed to build escrow spell: {}", e);
+            return Ok(EscrowResponse::error(format!("Failed to build spell: {}", e)));
+        }
+    };
+
+    let app_binary = match state.apps.app_binary(&spell_built).await {
+        Ok(binary) => binary,
+        Err(e) => {
+            tracing::error!("Escrow app binary unavailable: {:#}", e);
+            return Ok(EscrowResponse::error(format!("{:#}", e)));
+        }
+    };
+
+    if let Err(e) = state.charms.validate_spell(&spell_built) {
+        tracing::warn!("Spell validation warning: {}", e);
+    }
+
+    // Claim the funding UTXO so a concurrent draft cannot spend it too
+    if let Err(e) = lock_funding_utxo(&state.db, &funding_utxo, &change_address, None, Some(&id)).await {
+        return Ok(EscrowResponse::error(e.message));
+    }
+
+    let proved_txs = match prove_escrow_spell(
+        state,
+        &spell_built,
+        &funding_utxo,
+        funding_utxo_value,
+        &change_address,
+        &id,
+    ).await {
+        Ok(txs) => txs,
+        Err(e) => return Ok(EscrowResponse::from(e)),
+    };
+    record_template_use(&state.db, &id, &template).await;
+
+    // The escrow NFT lands in the first output of the spell transaction
+    let spell_txid = proved_txs.last().map(|tx| tx.txid.clone());
+
+    let escrow = EscrowRecord {
+        id: id.clone(),
+        escrow_id,
+        depositor_pubkey: req.depositor_pubkey,
+        recipient_pubkey: req.recipient_pubkey,
+        arbiter_pubkey: req.arbiter_pubkey,
+        escrow_type: req.escrow_type,
+        held_token_id: req.token_id,
+        held_amount: req.amount,
+        release_hash: req.release_hash,
+        expiry_height: req.expiry_height,
+        status: EscrowStatus::Active,
+        created_at: chrono::Utc::now().timestamp() as u64,
+        created_height: current_height,
+        order_id: req.order_id,
+        utxo_id: spell_txid.as_ref().map(|txid| format!("{}:0", txid)),
+        escrow_address,
+        tx_id: spell_txid,
+        pending_action: None,
+        dispute: None,
+        revealed_preimage: None,
+        deposit: None,
+        template_id: None,
+        fee: None,
+        depositor_address: req.depositor_address,
+        recipient_address: None,
+        milestones: req.milestones.iter().enumerate().map(|(index, spec)| EscrowMilestone {
+            index: index as u32,
+            amount: spec.amount,
+            description: spec.description.clone(),
+            status: MilestoneStatus::Pending,
+            tx_id: None,
+            released_at: None,
+        }).collect(),
+    };
+
+    let now = chrono::Utc::now();
+    let milestones: Vec<db::EscrowMilestoneRecord> = escrow.milestones.iter().map(|m| db::EscrowMilestoneRecord {
+        escrow_id: id.clone(),
+        idx: m.index as i32,
+        amount: m.amount as i64,
+        description: m.description.clone(),
+        status: "pending".to_string(),
+        remaining_amount: None,
+        tx_id: None,
+        created_at: now,
+        released_at: None,
+    }).collect();
+
+    let by = db::Transition::new(&escrow.depositor_pubkey, "escrow created");
+    if let Err(e) = db::create_escrow(&state.db, &escrow_row(state, &escrow)?, &milestones, &by).await {
+        tracing::error!("Failed to store escrow {}: {}", id, e);
+        return Ok(EscrowResponse::error("Failed to store escrow"));
+    }
+
+    // The deposit tracker credits the escrow once its charm lands there
+    track_escrow_address(state, &escrow.escrow_address).await;
+
+    let unsigned_txs = unsigned_from_proved(state, proved_txs, &change_address).await;
+
+    Ok(EscrowResponse::success(EscrowSpellResponse {
+        escrow,
+        spell: SpellData {
+            spell_yaml: template.body,
+            spell_yaml_built: spell_built,
+            app_binary,
+            prev_txs: vec![],
+        },
+        unsigned_txs,
+        signing_instructions: SigningInstructions {
+            message: "Please sign the transaction to lock your tokens in escrow".to_string(),
+            steps: vec![
+                "1. Review the escrow terms and amount".to_string(),
+                "2. Sign with your Bitcoin wallet".to_string(),
+                "3. Submit the signed transaction to broadcast".to_string(),
+            ],
+            broadcast_endpoint: format!("/api/escrows/{}/broadcast", id),
+        },
+    }))
+}
+
+/// Propose an escrow; nothing is built until the other parties accept
+async fn propose_escrow(
+    State(state): State<Arc<EscrowState>>,
+    Json(req): Json<ProposeEscrowRequest>,
+) -> Result<Json<EscrowResponse<EscrowProposal>>, ApiError> {
+    let terms = req.terms;
+
+    if terms.escrow_type == EscrowType::TwoOfThree && terms.arbiter_pubkey.is_none() {
+        return Ok(Json(EscrowResponse::error(
+            "2-of-3 escrow requires arbiter pubkey",
+        )));
+    }
+
+    let terms_hash = terms.hash();
+    let message = escrow_action_message("propose", &terms_hash, &[]);
+    if let Err(e) = verify_signature(&terms.depositor_pubkey, &message, &req.signature) {
+        return Ok(Json(EscrowResponse::error(format!("Invalid proposal signature: {}", e))));
+    }
+
+    let proposal = EscrowProposal {
+        id: Uuid::new_v4().to_string(),
+        terms,
+        terms_hash,
+        status: ProposalStatus::Pending,
+        acceptances: Vec::new(),
+        escrow_id: None,
+        created_at: chrono::Utc::now().timestamp() as u64,
+    };
+
+    db::insert_escrow_proposal(&state.db, &(&proposal).into()).await?;
+    tracing::info!("Escrow proposal {} created", proposal.id);
+
+    state.events.publish(Event::new(
+        "escrow.proposed",
+        proposal.id.clone(),
+        serde_json::json!({
+            "recipient_pubkey": proposal.terms.recipient_pubkey,
+            "arbiter_pubkey": proposal.terms.arbiter_pubkey,
+            "terms_hash": proposal.terms_hash,
+        }),
+    ));
+
+    Ok(Json(EscrowResponse::success(proposal)))
+}
+
+/// List proposals, newest first, optionally only those involving a party
+async fn list_proposals(
+    State(state): State<Arc<EscrowState>>,
+    Query(query): Query<ListProposalsQuery>,
+) -> Result<Json<EscrowResponse<Vec<EscrowProposal>>>, ApiError> {
+    let limit = query.limit.unwrap_or(100).clamp(1, 500);
+    let records = db::get_escrow_proposals(&state.db, query.pubkey.as_deref(), limit).await?;
+    let ids: Vec<String> = records.iter().map(|r| r.id.clone()).collect();
+    let mut acceptances = db::get_acceptances_for_proposals(&state.db, &ids).await?;
+    let proposals = records
+        .into_iter()
+        .map(|record| {
+            let acceptances = acceptances.remove(&record.id).unwrap_or_default();
+            EscrowProposal::from_db(record, acceptances)
+        })
+        .collect::<anyhow::Result<_>>()?;
+    Ok(Json(EscrowResponse::success(proposals)))
+}
+
+/// Get a proposal by ID
+async fn get_proposal(
+    State(state): State<Arc<EscrowState>>,
+    Path(id): Path<String>,
+) -> Result<Json<EscrowResponse<EscrowProposal>>, ApiError> {
+    match load_proposal(&state, &id).await? {
+        Some(proposal) => Ok(Json(EscrowResponse::success(proposal))),
+        None => Ok(Json(EscrowResponse::error("Proposal not found"))),
+    }
+}
+
+/// Accept a proposal's terms (recipient or arbiter)
+async fn accept_proposal(
+    State(state): State<Arc<EscrowState>>,
+    Path(id): Path<String>,
+    Json(req): Json<AcceptProposalRequest>,
+) -> Result<Json<EscrowResponse<EscrowProposal>>, ApiError> {
+    let proposal = match load_proposal(&state, &id).await? {
+        Some(proposal) => proposal,
+        None => return Ok(Json(EscrowResponse::error("Proposal not found"))),
+    };
+
+    if proposal.status != ProposalStatus::Pending {
+        return Ok(Json(EscrowResponse::error("Proposal is no longer pending")));
+    }
+
+    if !proposal.terms.required_acceptors().contains(&&req.signer_pubkey) {
+        return Ok(Json(EscrowResponse::error(
+            "Only the recipient or arbiter can accept the proposal",
+        )));
+    }
+
+    if proposal.acceptances.iter().any(|a| a.pubkey == req.signer_pubkey) {
+        return Ok(Json(EscrowResponse::error("Proposal already accepted by this party")));
+    }
+
+    let message = escrow_action_message("accept", &proposal.terms_hash, &[]);
+    if let Err(e) = verify_signature(&req.signer_pubkey, &message, &req.signature) {
+        return Ok(Json(EscrowResponse::error(format!("Invalid acceptance signature: {}", e))));
+    }
+
+    let required: Vec<String> = proposal.terms.required_acceptors().into_iter().cloned().collect();
+    let acceptance = db::EscrowProposalAcceptanceRecord {
+        proposal_id: proposal.id.clone(),
+        pubkey: req.signer_pubkey,
+        signature: req.signature,
+        accepted_at: chrono::Utc::now(),
+    };
+    // Another acceptance may have landed since the proposal was loaded
+    let all_accepted = match db::accept_escrow_proposal(&state.db, &acceptance, &required).await? {
+        Some(all_accepted) => all_accepted,
+        None => return Ok(Json(EscrowResponse::error("Proposal changed; reload it and retry"))),
+    };
+    if all_accepted {
+        state.events.publish(Event::new(
+            "escrow.proposal_accepted",
+            proposal.id.clone(),
+            serde_json::json!({ "depositor_pubkey": proposal.terms.depositor_pubkey }),
+        ));
+    }
+
+    match load_proposal(&state, &id).await? {
+        Some(proposal) => Ok(Json(EscrowResponse::success(proposal))),
+        None => Ok(Json(EscrowResponse::error("Proposal not found"))),
+    }
+}
+
+/// Build the creation spell for a fully accepted proposal
+async fn create_from_proposal(
+    State(state): State<Arc<EscrowState>>,
+    Path(id): Path<String>,
+    wallet: WalletFormat,
+    NetworkJson(req): NetworkJson<CreateFromProposalRequest>,
+) -> Result<Json<EscrowResponse<EscrowSpellResponse>>, ApiError> {
+    let terms = match load_proposal(&state, &id).await? {
+        Some(proposal) if proposal.status == ProposalStatus::Accepted => proposal.terms,
+        Some(_) => {
             return Ok(Json(EscrowResponse::error(
-                "Only 2-of-3 escrows can be disputed",
+                "Proposal must be accepted by all parties before creation",
             )));
         }
+        None => return Ok(Json(EscrowResponse::error("Proposal not found"))),
+    };
+
+    let response = build_escrow(&state, CreateEscrowRequest {
+        depositor_pubkey: terms.depositor_pubkey,
+        recipient_pubkey: terms.recipient_pubkey,
+        arbiter_pubkey: terms.arbiter_pubkey,
+        escrow_type: terms.escrow_type,
+        token_id: terms.token_id,
+        amount: terms.amount,
+        release_hash: terms.release_hash,
+        expiry_height: terms.expiry_height,
+        order_id: terms.order_id,
+        funding_utxo: Some(req.funding_utxo),
+        funding_utxo_value: req.funding_utxo_value,
+        depositor_address: req.depositor_address,
+        milestones: vec![],
+    }).await?;
+
+    if let Some(data) = &response.data {
+        db::mark_escrow_proposal_created(&state.db, &id, &data.escrow.id).await?;
+    }
+
+    Ok(Json(response.for_wallet(wallet)))
+}
+
+/// Create an escrow derived from an existing order
+async fn create_order_escrow(
+    State(state): State<Arc<EscrowState>>,
+    Path(order_id): Path<String>,
+    wallet: WalletFormat,
+    NetworkJson(req): NetworkJson<CreateOrderEscrowRequest>,
+) -> Result<Json<EscrowResponse<EscrowSpellResponse>>, ApiError> {
+    let order = match db::get_order_by_id(&state.db, &order_id).await {
+        Ok(Some(order)) => order,
+        Ok(None) => return Ok(Json(EscrowResponse::error("Order not found"))),
+        Err(e) => return Err(e.into()),
+    };
+
+    if matches!(order.status.as_str(), "filled" | "cancelled" | "expired") {
+        return Ok(Json(EscrowResponse::error(format!(
+            "Order is {} and cannot be escrowed",
+            order.status
+        ))));
+    }
+
+    match db::get_escrow_by_order(&state.db, &order_id).await {
+        Ok(Some(_)) => return Ok(Json(EscrowResponse::error("Order already has an escrow"))),
+        Ok(None) => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    if order.source_chain != order.dest_chain && req.release_hash.is_none() {
+        return Ok(Json(EscrowResponse::error(
+            "Cross-chain orders require a release_hash",
+        )));
+    }
+
+    // Escrow whatever is still unfilled
+    let amount = order.offer_amount.saturating_sub(order.filled_amount).max(0) as u64;
+    if amount == 0 {
+        return Ok(Json(EscrowResponse::error("Order has no unfilled amount to escrow")));
+    }
+
+    let expiry_height = match req.expiry_height.or(order.expiry_height.map(|h| h as u64)) {
+        Some(height) => height,
+        None => return Ok(Json(EscrowResponse::error(
+            "Order has no expiry height; expiry_height is required",
+        ))),
+    };
+
+    let escrow_type = if req.arbiter_pubkey.is_some() {
+        EscrowType::TwoOfThree
+    } else {
+        EscrowType::TwoParty
+    };
+
+    let mut response = build_escrow(&state, CreateEscrowRequest {
+        depositor_pubkey: req.depositor_pubkey,
+        recipient_pubkey: req.recipient_pubkey,
+        arbiter_pubkey: req.arbiter_pubkey,
+        escrow_type,
+        token_id: order.offer_token.clone(),
+        amount,
+        release_hash: req.release_hash.clone(),
+        expiry_height,
+        order_id: Some(order_id.clone()),
+        // Funded from the order's own funding UTXO unless another is given
+        funding_utxo: req.funding_utxo.or_else(|| order.utxo_id.as_deref().and_then(|utxo| utxo.parse().ok())),
+        funding_utxo_value: req.funding_utxo_value,
+        depositor_address: Some(order.maker_address.clone()),
+        milestones: vec![],
+    }).await?;
+
+    let escrow = match response.data.as_mut() {
+        Some(data) => &mut data.escrow,
+        None => return Ok(Json(response)),
+    };
+    escrow.recipient_address = Some(req.recipient_address);
+    save_escrow(&state, escrow).await?;
+    tracing::info!("Escrow {} created for order {}", escrow.id, order_id);
+
+    Ok(Json(response.for_wallet(wallet)))
+}
+
+/// Get the escrow created for an order
+async fn get_order_escrow(
+    State(state): State<Arc<EscrowState>>,
+    Path(order_id): Path<String>,
+) -> Result<Json<EscrowResponse<OrderEscrowResponse>>, StatusCode> {
+    let link = match db::get_escrow_by_order(&state.db, &order_id).await {
+        Ok(Some(link)) => link,
+        Ok(None) => return Ok(Json(EscrowResponse::error("Order has no escrow"))),
+        Err(e) => {
+            tracing::error!("Failed to fetch escrow for order {}: {}", order_id, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let link = match load_escrow(&state, &link.id).await {
+        Ok(link) => link,
+        Err(e) => {
+            tracing::error!("Escrow for order {} is unreadable: {}", order_id, e.message);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    let escrow = Some(link.clone());
+
+    Ok(Json(EscrowResponse::success(OrderEscrowResponse { link, escrow })))
+}
+
+/// Release escrow to recipient - builds the release spell against the escrow UTXO
+async fn release_escrow(
+    State(state): State<Arc<EscrowState>>,
+    Path(id): Path<String>,
+    wallet: WalletFormat,
+    NetworkJson(req): NetworkJson<ReleaseEscrowRequest>,
+) -> Result<Json<EscrowResponse<EscrowSpellResponse>>, ApiError> {
+    Ok(Json(build_release(&state, &id, &req).await?.for_wallet(wallet)))
+}
+
+/// Validate a release and build its spell, marking the release pending broadcast
+async fn build_release(
+    state: &EscrowState,
+    id: &str,
+    req: &ReleaseEscrowRequest,
+) -> Result<EscrowResponse<EscrowSpellResponse>, ApiError> {
+    let funding_utxo_value = req.funding_utxo_value.ok_or_else(|| ApiError::missing("funding_utxo_value"))?;
+
+    let escrow = match load_escrow(state, id).await {
+        Ok(escrow) => escrow,
+        Err(e) => return Ok(EscrowResponse::from(e)),
+    };
+
+    // Validate escrow is active
+    if escrow.status != EscrowStatus::Active {
+        return Ok(EscrowResponse::error(
+            "Escrow is not active",
+        ));
+    }
+
+    // Validate release hash if present
+    if escrow.release_hash.is_some() && req.preimage.is_none() {
+        return Ok(EscrowResponse::error(
+            "Preimage required for hash-locked escrow",
+        ));
+    }
+
+    // Validate signer is authorized
+    let is_authorized = req.signer_pubkey == escrow.depositor_pubkey
+        || req.signer_pubkey == escrow.recipient_pubkey
+        || escrow.arbiter_pubkey.as_ref().map(|a| a == &req.signer_pubkey).unwrap_or(false);
+
+    if !is_authorized {
+        return Ok(EscrowResponse::error(
+            "Signer not authorized to release escrow",
+        ));
+    }
+
+    let message = escrow_action_message("release", &escrow.id, &[&req.recipient_address]);
+    if let Err(e) = verify_signature(&req.signer_pubkey, &message, &req.signature) {
+        return Ok(EscrowResponse::error(format!("Invalid release signature: {}", e)));
+    }
+
+    let spell_data = match spell_data_for(&escrow) {
+        Some(data) => data,
+        None => return Ok(EscrowResponse::error("Escrow has no on-chain UTXO yet")),
+    };
+
+    let witness = EscrowWitness {
+        signature: req.signature.clone(),
+        signer_pubkey: Some(req.signer_pubkey.clone()),
+        preimage: req.preimage.clone(),
+        reason: None,
+    };
+
+    let template = spell_template(&state.db, RELEASE_ESCROW).await;
+
+    let escrow_vk = state.apps.vk(Contract::Escrow).await;
+    let spell_built = match state.charms.build_release_escrow_spell(
+        &template.body,
+        &spell_data,
+        &escrow_vk,
+        &req.recipient_address,
+        &witness,
+    ) {
+        Ok(spell) => spell,
+        Err(e) => {
+            tracing::error!("Failed to build release spell: {}", e);
+            return Ok(EscrowResponse::error(format!("Failed to build spell: {}", e)));
+        }
+    };
+
+    let app_binary = match state.apps.app_binary(&spell_built).await {
+        Ok(binary) => binary,
+        Err(e) => {
+            tracing::error!("Escrow app binary unavailable: {:#}", e);
+            return Ok(EscrowResponse::error(format!("{:#}", e)));
+        }
+    };
+
+    if let Err(e) = lock_funding_utxo(&state.db, &req.funding_utxo, &req.change_address, None, Some(id)).await {
+        return Ok(EscrowResponse::error(e.message));
+    }
+
+    let proved_txs = match prove_escrow_spell(
+        state,
+        &spell_built,
+        &req.funding_utxo,
+        funding_utxo_value,
+        &req.change_address,
+        id,
+    ).await {
+        Ok(txs) => txs,
+        Err(e) => return Ok(EscrowResponse::from(e)),
+    };
+    record_template_use(&state.db, id, &template).await;
+
+    let escrow = match set_pending_action(state, id, PendingAction::Release, |_| {}).await {
+        Ok(escrow) => escrow,
+        Err(e) => return Ok(EscrowResponse::from(e)),
+    };
+
+    Ok(EscrowResponse::success(EscrowSpellResponse {
+        escrow,
+        spell: SpellData {
+            spell_yaml: template.body,
+            spell_yaml_built: spell_built,
+            app_binary,
+            prev_txs: vec![],
+        },
+        unsigned_txs: unsigned_from_proved(state, proved_txs, &req.change_address).await,
+        signing_instructions: SigningInstructions {
+            message: "Please sign the transaction to release the escrowed tokens".to_string(),
+            steps: vec![
+                "1. Review the recipient address and amount".to_string(),
+                "2. Sign with your Bitcoin wallet".to_string(),
+                "3. Submit the signed transaction to broadcast".to_string(),
+            ],
+            broadcast_endpoint: format!("/api/escrows/{}/broadcast", id),
+        },
+    }))
+}
+
+/// Claim a hash-locked escrow by revealing the preimage
+async fn claim_escrow(
+    State(state): State<Arc<EscrowState>>,
+    Path(id): Path<String>,
+    wallet: WalletFormat,
+    NetworkJson(req): NetworkJson<ClaimEscrowRequest>,
+) -> Result<Json<EscrowResponse<EscrowSpellResponse>>, ApiError> {
+    let escrow = match load_escrow(&state, &id).await {
+        Ok(escrow) => escrow,
+        Err(e) => return Ok(Json(EscrowResponse::from(e))),
+    };
+
+    let release_hash = match &escrow.release_hash {
+        Some(hash) => hash.to_lowercase(),
+        None => return Ok(Json(EscrowResponse::error("Escrow is not hash-locked"))),
+    };
+
+    if req.claimer_pubkey != escrow.recipient_pubkey {
+        return Ok(Json(EscrowResponse::error(
+            "Only the recipient can claim a hash-locked escrow",
+        )));
+    }
+
+    let preimage = match hex::decode(&req.preimage) {
+        Ok(bytes) => bytes,
+        Err(_) => return Ok(Json(EscrowResponse::error("Preimage must be hex encoded"))),
+    };
+
+    if hex::encode(Sha256::digest(&preimage)) != release_hash {
+        return Ok(Json(EscrowResponse::error(
+            "Preimage does not match release hash",
+        )));
+    }
+
+    // The hashlock release is a release spell carrying the preimage
+    let release = ReleaseEscrowRequest {
+        preimage: Some(req.preimage.clone()),
+        signature: req.signature,
+        signer_pubkey: req.claimer_pubkey,
+        recipient_address: req.recipient_address,
+        funding_utxo: req.funding_utxo,
+        funding_utxo_value: req.funding_utxo_value,
+        change_address: req.change_address,
+    };
+
+    let mut response = build_release(&state, &id, &release).await?;
+    if !response.success {
+        return Ok(Json(response));
+    }
+
+    // Record the preimage so the counterpart leg can be claimed with it
+    if let Some(data) = response.data.as_mut() {
+        data.escrow.revealed_preimage = Some(req.preimage.clone());
+        save_escrow(&state, &data.escrow).await?;
+    }
+
+    // The preimage itself stays out of the event: events are fanned out to
+    // sockets and webhook jobs stored in the clear. The parties read it from
+    // the escrow.
+    state.events.publish(Event::new(
+        "escrow.preimage_revealed",
+        id.clone(),
+        serde_json::json!({
+            "order_id": escrow.order_id,
+            "release_hash": release_hash,
+        }),
+    ));
+    tracing::info!("Preimage revealed for escrow {}", id);
+
+    Ok(Json(response.for_wallet(wallet)))
+}
+
+/// Refund escrow to depositor - builds the refund spell against the escrow UTXO
+async fn refund_escrow(
+    State(state): State<Arc<EscrowState>>,
+    Path(id): Path<String>,
+    wallet: WalletFormat,
+    NetworkJson(req): NetworkJson<RefundEscrowRequest>,
+) -> Result<Json<EscrowResponse<EscrowSpellResponse>>, ApiError> {
+    let funding_utxo_value = req.funding_utxo_value.ok_or_else(|| ApiError::missing("funding_utxo_value"))?;
+    let escrow = match load_escrow(&state, &id).await {
+        Ok(escrow) => escrow,
+        Err(e) => return Ok(Json(EscrowResponse::from(e))),
+    };
+
+    // Validate escrow is active or expired
+    if escrow.status != EscrowStatus::Active && escrow.status != EscrowStatus::Expired {
+        return Ok(Json(EscrowResponse::error(
+            "Escrow cannot be refunded in current state",
+        )));
+    }
+
+    // Only the depositor can request a refund
+    let message = escrow_action_message("refund", &escrow.id, &[&req.depositor_address, &req.reason]);
+    if let Err(e) = verify_signature(&escrow.depositor_pubkey, &message, &req.signature) {
+        return Ok(Json(EscrowResponse::error(format!("Invalid refund signature: {}", e))));
+    }
+
+    let spell_data = match spell_data_for(&escrow) {
+        Some(data) => data,
+        None => return Ok(Json(EscrowResponse::error("Escrow has no on-chain UTXO yet"))),
+    };
+
+    let witness = EscrowWitness {
+        signature: req.signature.clone(),
+        reason: Some(req.reason.clone()),
+        ..Default::default()
+    };
+
+    let template = spell_template(&state.db, REFUND_ESCROW).await;
+
+    let escrow_vk = state.apps.vk(Contract::Escrow).await;
+    let spell_built = match state.charms.build_refund_escrow_spell(
+        &template.body,
+        &spell_data,
+        &escrow_vk,
+        &req.depositor_address,
+        &witness,
+    ) {
+        Ok(spell) => spell,
+        Err(e) => {
+            tracing::error!("Failed to build refund spell: {}", e);
+            return Ok(Json(EscrowResponse::error(format!("Failed to build spell: {}", e))));
+        }
+    };
+
+    let app_binary = match state.apps.app_binary(&spell_built).await {
+        Ok(binary) => binary,
+        Err(e) => {
+            tracing::error!("Escrow app binary unavailable: {:#}", e);
+            return Ok(Json(EscrowResponse::error(format!("{:#}", e))));
+        }
+    };
+
+    if let Err(e) = lock_funding_utxo(&state.db, &req.funding_utxo, &req.depositor_address, None, Some(&id)).await {
+        return Ok(Json(EscrowResponse::error(e.message)));
+    }
+
+    let proved_txs = match prove_escrow_spell(
+        &state,
+        &spell_built,
+        &req.funding_utxo,
+        funding_utxo_value,
+        &req.depositor_address,
+        &id,
+    ).await {
+        Ok(txs) => txs,
+        Err(e) => return Ok(Json(EscrowResponse::from(e))),
+    };
+    record_template_use(&state.db, &id, &template).await;
+
+    let escrow = match set_pending_action(&state, &id, PendingAction::Refund, |_| {}).await {
+        Ok(escrow) => escrow,
+        Err(e) => return Ok(Json(EscrowResponse::from(e))),
+    };
+
+    Ok(Json(EscrowResponse::success(EscrowSpellResponse {
+        escrow,
+        spell: SpellData {
+            spell_yaml: template.body,
+            spell_yaml_built: spell_built,
+            app_binary,
+            prev_txs: vec![],
+        },
+        unsigned_txs: unsigned_from_proved(&state, proved_txs, &req.depositor_address).await,
+        signing_instructions: SigningInstructions {
+            message: "Please sign the transaction to refund the escrowed tokens".to_string(),
+            steps: vec![
+                "1. Review the refund amount".to_string(),
+                "2. Sign with your Bitcoin wallet".to_string(),
+                "3. Submit the signed transaction to broadcast".to_string(),
+            ],
+            broadcast_endpoint: format!("/api/escrows/{}/broadcast", id),
+        },
+    }).for_wallet(wallet)))
+}
+
+/// List the milestones of an escrow
+async fn list_milestones(
+    State(state): State<Arc<EscrowState>>,
+    Path(id): Path<String>,
+) -> Json<EscrowResponse<Vec<EscrowMilestone>>> {
+    match load_escrow(&state, &id).await {
+        Ok(escrow) => Json(EscrowResponse::success(escrow.milestones)),
+        Err(e) => Json(EscrowResponse::from(e)),
+    }
+}
+
+/// Release one milestone - builds a partial-release spell paying the milestone
+/// amount and re-locking the rest, or a full release for the last one
+async fn release_milestone(
+    State(state): State<Arc<EscrowState>>,
+    Path((id, index)): Path<(String, u32)>,
+    wallet: WalletFormat,
+    NetworkJson(req): NetworkJson<ReleaseMilestoneRequest>,
+) -> Result<Json<EscrowResponse<EscrowSpellResponse>>, ApiError> {
+    let funding_utxo_value = req.funding_utxo_value.ok_or_else(|| ApiError::missing("funding_utxo_value"))?;
+    let escrow = match load_escrow(&state, &id).await {
+        Ok(escrow) => escrow,
+        Err(e) => return Ok(Json(EscrowResponse::from(e))),
+    };
+
+    if escrow.status != EscrowStatus::Active {
+        return Ok(Json(EscrowResponse::error("Escrow is not active")));
+    }
+
+    // Each partial release spends the current escrow UTXO, so they go one at a time
+    if escrow.pending_action.is_some() {
+        return Ok(Json(EscrowResponse::error(
+            "Another escrow transaction is awaiting broadcast",
+        )));
+    }
+
+    let milestone = match escrow.milestones.iter().find(|m| m.index == index) {
+        Some(milestone) => milestone.clone(),
+        None => return Ok(Json(EscrowResponse::error("Milestone not found"))),
+    };
+    if milestone.status != MilestoneStatus::Pending {
+        return Ok(Json(EscrowResponse::error("Milestone already released")));
+    }
+
+    // Only the depositor signs off on a milestone
+    let message = escrow_action_message(
+        "release_milestone",
+        &escrow.id,
+        &[&index.to_string(), &req.recipient_address],
+    );
+    if let Err(e) = verify_signature(&escrow.depositor_pubkey, &message, &req.signature) {
+        return Ok(Json(EscrowResponse::error(format!("Invalid milestone signature: {}", e))));
+    }
+
+    let spell_data = match spell_data_for(&escrow) {
+        Some(data) => data,
+        None => return Ok(Json(EscrowResponse::error("Escrow has no on-chain UTXO yet"))),
+    };
+
+    let witness = EscrowWitness {
+        signature: req.signature.clone(),
+        signer_pubkey: Some(escrow.depositor_pubkey.clone()),
+        ..Default::default()
+    };
+
+    let is_final = milestone.amount >= escrow.held_amount;
+    let template = spell_template(&state.db, if is_final { RELEASE_ESCROW } else { RELEASE_MILESTONE }).await;
+    let escrow_vk = state.apps.vk(Contract::Escrow).await;
+    let built = if is_final {
+        state.charms.build_release_escrow_spell(
+            &template.body,
+            &spell_data,
+            &escrow_vk,
+            &req.recipient_address,
+            &witness,
+        )
+    } else {
+        state.charms.build_release_milestone_spell(
+            &template.body,
+            &spell_data,
+            &escrow_vk,
+            &req.recipient_address,
+            milestone.amount,
+            &witness,
+        )
+    };
+
+    let spell_built = match built {
+        Ok(spell) => spell,
+        Err(e) => {
+            tracing::error!("Failed to build milestone release spell: {}", e);
+            return Ok(Json(EscrowResponse::error(format!("Failed to build spell: {}", e))));
+        }
+    };
+
+    let app_binary = match state.apps.app_binary(&spell_built).await {
+        Ok(binary) => binary,
+        Err(e) => {
+            tracing::error!("Escrow app binary unavailable: {:#}", e);
+            return Ok(Json(EscrowResponse::error(format!("{:#}", e))));
+        }
+    };
+
+    if let Err(e) = lock_funding_utxo(&state.db, &req.funding_utxo, &req.change_address, None, Some(&id)).await {
+        return Ok(Json(EscrowResponse::error(e.message)));
+    }
+
+    let proved_txs = match prove_escrow_spell(
+        &state,
+        &spell_built,
+        &req.funding_utxo,
+        funding_utxo_value,
+        &req.change_address,
+        &id,
+    ).await {
+        Ok(txs) => txs,
+        Err(e) => return Ok(Json(EscrowResponse::from(e))),
+    };
+    record_template_use(&state.db, &id, &template).await;
+
+    let escrow = match set_pending_action(&state, &id, PendingAction::Milestone(index), |_| {}).await {
+        Ok(escrow) => escrow,
+        Err(e) => return Ok(Json(EscrowResponse::from(e))),
+    };
+
+    Ok(Json(EscrowResponse::success(EscrowSpellResponse {
+        escrow,
+        spell: SpellData {
+            spell_yaml: template.body,
+            spell_yaml_built: spell_built,
+            app_binary,
+            prev_txs: vec![],
+        },
+        unsigned_txs: unsigned_from_proved(&state, proved_txs, &req.change_address).await,
+        signing_instructions: SigningInstructions {
+            message: format!("Please sign the transaction to release milestone {}", index),
+            steps: vec![
+                "1. Review the milestone amount and recipient".to_string(),
+                "2. Sign with your Bitcoin wallet".to_string(),
+                "3. Submit the signed transaction to broadcast".to_string(),
+            ],
+            broadcast_endpoint: format!("/api/escrows/{}/broadcast", id),
+        },
+    }).for_wallet(wallet)))
+}
+
+/// Broadcast a signed escrow transaction and apply its pending action
+async fn broadcast_escrow(
+    State(state): State<Arc<EscrowState>>,
+    Path(id): Path<String>,
+    Json(req): Json<BroadcastEscrowRequest>,
+) -> Result<Json<EscrowResponse<BroadcastResponse>>, StatusCode> {
+    // A pending action spends the escrow charm; creation spends funding only
+    let mut escrow = match load_escrow(&state, &id).await {
+        Ok(escrow) => escrow,
+        Err(e) => return Ok(Json(EscrowResponse::from(e))),
+    };
+    let expected_spend = escrow.pending_action.and(escrow.utxo_id.clone());
+
+    let proved = match db::get_proved_transactions(&state.db, &id).await {
+        Ok(proved) => proved,
+        Err(e) => {
+            return Ok(Json(EscrowResponse::error(format!("Failed to load proved transactions: {}", e))));
+        }
+    };
+    if let Err(e) = state.charms.prover().check_signed(&req.signed_tx_hex, &proved, expected_spend.as_deref()) {
+        tracing::warn!("Rejected broadcast for escrow {}: {}", id, e);
+        return Ok(Json(EscrowResponse::error(format!("{} for escrow {}", e, id))));
+    }
+
+    let txid = match state.fees.broadcast(state.chain.as_ref(), &req.signed_tx_hex).await {
+        Ok(txid) => txid,
+        Err(e) => {
+            tracing::error!("Escrow broadcast failed: {}", e);
+            return Ok(Json(EscrowResponse {
+                code: BroadcastRejection::of(&e).map(|rejection| rejection.code),
+                ..EscrowResponse::error(format!("Failed to broadcast: {}", e))
+            }));
+        }
+    };
+
+    // Only now that the network accepted the transaction does the status change
+    let from = escrow.status;
+    let mut milestone_released = None;
+    let mut moved_amount = escrow.held_amount;
+    let (action, message) = match escrow.pending_action.take() {
+        Some(PendingAction::Milestone(index)) => {
+            let amount = match escrow.milestones.iter_mut().find(|m| m.index == index) {
+                Some(milestone) => {
+                    milestone.status = MilestoneStatus::Released;
+                    milestone.tx_id = Some(txid.clone());
+                    milestone.released_at = Some(chrono::Utc::now().timestamp() as u64);
+                    milestone.amount
+                }
+                None => 0,
+            };
+            escrow.held_amount = escrow.held_amount.saturating_sub(amount);
+            moved_amount = amount;
+            milestone_released = Some((index, escrow.held_amount));
+
+            if escrow.held_amount == 0 {
+                escrow.status = EscrowStatus::Released;
+                ("milestone_released", "Final milestone released to recipient")
+            } else {
+                // The continuing escrow charm sits in the first output
+                escrow.utxo_id = Some(format!("{}:0", txid));
+                ("milestone_released", "Milestone released to recipient")
+            }
+        }
+        Some(PendingAction::Release) => {
+            escrow.status = EscrowStatus::Released;
+            ("escrow_released", "Escrow released to recipient")
+        }
+        Some(PendingAction::Refund) => {
+            escrow.status = EscrowStatus::Refunded;
+            ("escrow_refunded", "Escrow refunded to depositor")
+        }
+        Some(PendingAction::Dispute) => {
+            // The disputed escrow charm sits in the first output
+            escrow.utxo_id = Some(format!("{}:0", txid));
+            ("escrow_disputed", "Dispute recorded on-chain")
+        }
+        None => {
+            // Creation: the escrow charm sits in the first output
+            escrow.utxo_id = Some(format!("{}:0", txid));
+            ("escrow_created", "Tokens are now locked in escrow")
+        }
+    };
+    let spell_record = db::SpellTransactionRecord {
+        txid: txid.clone(),
+        action: action.to_string(),
+        order_id: escrow.order_id.clone(),
+        escrow_id: Some(escrow.id.clone()),
+        token_id: Some(escrow.held_token_id.clone()),
+        amount: Some(moved_amount.to_string()),
+        created_at: chrono::Utc::now(),
+    };
+    escrow.tx_id = Some(txid.clone());
+    if let Some(dispute) = escrow.dispute.as_mut() {
+        if dispute.winner.is_some() && dispute.resolved_at.is_none() {
+            dispute.resolved_at = Some(chrono::Utc::now().timestamp() as u64);
+        }
+    }
+    tracing::info!("Escrow {} broadcast: {}", id, txid);
+
+    if escrow.status != from {
+        let by = db::Transition::new("api", action).with_txid(&txid);
+        if let Err(e) = record_escrow_transition(&state, &escrow, from, &by).await {
+            tracing::error!("Failed to record escrow {} moving to {}: {}", id, escrow.status.as_str(), e);
+        }
+    } else if let Err(e) = save_escrow(&state, &escrow).await {
+        tracing::error!("Failed to record broadcast {} of escrow {}: {}", txid, id, e);
+    }
+
+    if let Some((index, remaining)) = milestone_released {
+        if let Err(e) = db::release_escrow_milestone(&state.db, &id, index as i32, &txid, remaining as i64).await {
+            tracing::error!("Failed to record milestone {} release for escrow {}: {}", index, id, e);
+        }
+    }
+    if let Err(e) = db::record_spell_transaction(&state.db, &spell_record).await {
+        tracing::error!("Failed to index escrow spell {}: {}", txid, e);
+    }
+    if let Err(e) = db::release_escrow_utxo_locks(&state.db, &id).await {
+        tracing::warn!("Failed to release UTXO locks for escrow {}: {}", id, e);
+    }
+
+    Ok(Json(EscrowResponse::success(BroadcastResponse {
+        txid,
+        status: "confirmed".to_string(),
+        message: message.to_string(),
+        rejection: None,
+    })))
+}
+
+/// Initiate dispute on escrow. With a funding UTXO the dispute-escrow spell
+/// is built and proved so the disputed state is also recorded on-chain.
+async fn dispute_escrow(
+    State(state): State<Arc<EscrowState>>,
+    Path(id): Path<String>,
+    NetworkJson(req): NetworkJson<DisputeEscrowRequest>,
+) -> Result<Json<EscrowResponse<DisputeEscrowResponse>>, ApiError> {
+    let mut escrow = match load_escrow(&state, &id).await {
+        Ok(escrow) => escrow,
+        Err(e) => return Ok(Json(EscrowResponse::from(e))),
+    };
+
+    // Validate escrow type supports disputes
+    if escrow.escrow_type != EscrowType::TwoOfThree {
+        return Ok(Json(EscrowResponse::error(
+            "Only 2-of-3 escrows can be disputed",
+        )));
+    }
+
+    // Validate escrow is active
+    if escrow.status != EscrowStatus::Active {
+        return Ok(Json(EscrowResponse::error(
+            "Escrow is not active",
+        )));
+    }
+
+    // Validate initiator is party to escrow
+    if req.initiator_pubkey != escrow.depositor_pubkey
+        && req.initiator_pubkey != escrow.recipient_pubkey
+    {
+        return Ok(Json(EscrowResponse::error(
+            "Only depositor or recipient can initiate dispute",
+        )));
+    }
+
+    let message = escrow_action_message("dispute", &escrow.id, &[&req.reason]);
+    if let Err(e) = verify_signature(&req.initiator_pubkey, &message, &req.signature) {
+        return Ok(Json(EscrowResponse::error(format!("Invalid dispute signature: {}", e))));
+    }
+
+    let on_chain = match (&req.funding_utxo, &req.change_address) {
+        (Some(funding_utxo), Some(change_address)) => {
+            let funding_utxo_value = req.funding_utxo_value.ok_or_else(|| ApiError::missing("funding_utxo_value"))?;
+            let spell_data = match spell_data_for(&escrow) {
+                Some(data) => data,
+                None => return Ok(Json(EscrowResponse::error("Escrow has no on-chain UTXO yet"))),
+            };
+            let witness = EscrowWitness {
+                signature: req.signature.clone(),
+                signer_pubkey: Some(req.initiator_pubkey.clone()),
+                reason: Some(req.reason.clone()),
+                ..Default::default()
+            };
+
+            let template = spell_template(&state.db, DISPUTE_ESCROW).await;
+            let escrow_vk = state.apps.vk(Contract::Escrow).await;
+            let spell_built = match state.charms.build_dispute_escrow_spell(
+                &template.body,
+                &spell_data,
+                &escrow_vk,
+                req.evidence_hash.as_deref(),
+                &witness,
+            ) {
+                Ok(spell) => spell,
+                Err(e) => {
+                    tracing::error!("Failed to build dispute spell: {}", e);
+                    return Ok(Json(EscrowResponse::error(format!("Failed to build spell: {}", e))));
+                }
+            };
+
+            let app_binary = match state.apps.app_binary(&spell_built).await {
+                Ok(binary) => binary,
+                Err(e) => {
+                    tracing::error!("Escrow app binary unavailable: {:#}", e);
+                    return Ok(Json(EscrowResponse::error(format!("{:#}", e))));
+                }
+            };
+
+            if let Err(e) = lock_funding_utxo(&state.db, funding_utxo, change_address, None, Some(&id)).await {
+                return Ok(Json(EscrowResponse::error(e.message)));
+            }
+
+            let proved_txs = match prove_escrow_spell(
+                &state,
+                &spell_built,
+                funding_utxo,
+                funding_utxo_value,
+                change_address,
+                &id,
+            ).await {
+                Ok(txs) => txs,
+                Err(e) => return Ok(Json(EscrowResponse::from(e))),
+            };
+            record_template_use(&state.db, &id, &template).await;
+
+            Some((
+                SpellData {
+                    spell_yaml: template.body,
+                    spell_yaml_built: spell_built,
+                    app_binary,
+                    prev_txs: vec![],
+                },
+                unsigned_from_proved(&state, proved_txs, change_address).await,
+            ))
+        }
+        (None, None) => None,
+        _ => {
+            return Ok(Json(EscrowResponse::error(
+                "funding_utxo and change_address must be given together",
+            )));
+        }
+    };
+
+    escrow.status = EscrowStatus::Disputed;
+    escrow.dispute = Some(DisputeInfo {
+        reason: req.reason,
+        initiator_pubkey: req.initiator_pubkey,
+        evidence_hash: req.evidence_hash,
+        opened_at: chrono::Utc::now().timestamp() as u64,
+        resolved_at: None,
+        winner: None,
+    });
+    if on_chain.is_some() {
+        escrow.pending_action = Some(PendingAction::Dispute);
+    }
+
+    if let Some(dispute) = &escrow.dispute {
+        let by = db::Transition::new(&dispute.initiator_pubkey, &dispute.reason);
+        if let Err(e) = record_escrow_transition(&state, &escrow, EscrowStatus::Active, &by).await {
+            tracing::warn!("Dispute of escrow {} not recorded: {}", id, e);
+            if let Err(e) = db::release_escrow_utxo_locks(&state.db, &id).await {
+                tracing::warn!("Failed to release UTXO locks for escrow {}: {}", id, e);
+            }
+            return Ok(Json(EscrowResponse::error(format!("Failed to record dispute: {}", e))));
+        }
+    }
 
-        // Validate escrow is active
-        if escrow.status != EscrowStatus::Active {
-            return Ok(Json(EscrowResponse::error(
-                "Escrow is not active",
-            )));
-        }
+    state.events.publish(Event::new(
+        "escrow.disputed",
+        escrow.id.clone(),
+        serde_json::json!({ "arbiter_pubkey": escrow.arbiter_pubkey }),
+    ));
+
+    let (spell, unsigned_txs) = match on_chain {
+        Some((spell, unsigned_txs)) => (Some(spell), unsigned_txs),
+        None => (None, Vec::new()),
+    };
+    let signing_instructions = spell.as_ref().map(|_| SigningInstructions {
+        message: "Please sign the transaction to record the dispute on-chain".to_string(),
+        steps: vec![
+            "1. Review the dispute reason".to_string(),
+            "2. Sign with your Bitcoin wallet".to_string(),
+            "3. Submit the signed transaction to broadcast".to_string(),
+        ],
+        broadcast_endpoint: format!("/api/escrows/{}/broadcast", id),
+    });
+
+    Ok(Json(EscrowResponse::success(DisputeEscrowResponse {
+        escrow,
+        spell,
+        unsigned_txs,
+        signing_instructions,
+    })))
+}
+
+/// Resolve dispute (arbiter only) - builds the resolve spell paying the winner
+async fn resolve_dispute(
+    State(state): State<Arc<EscrowState>>,
+    Path(id): Path<String>,
+    wallet: WalletFormat,
+    NetworkJson(req): NetworkJson<ResolveDisputeRequest>,
+) -> Result<Json<EscrowResponse<EscrowSpellResponse>>, ApiError> {
+    let funding_utxo_value = req.funding_utxo_value.ok_or_else(|| ApiError::missing("funding_utxo_value"))?;
+    let escrow = match load_escrow(&state, &id).await {
+        Ok(escrow) => escrow,
+        Err(e) => return Ok(Json(EscrowResponse::from(e))),
+    };
+
+    // Validate escrow is disputed
+    if escrow.status != EscrowStatus::Disputed {
+        return Ok(Json(EscrowResponse::error(
+            "Escrow is not in disputed state",
+        )));
+    }
 
-        // Validate initiator is party to escrow
-        if req.initiator_pubkey != escrow.depositor_pubkey
-            && req.initiator_pubkey != escrow.recipient_pubkey
-        {
+    // The winner's payout settles the escrow the same way a release or refund would
+    let action = match req.winner.as_str() {
+        "depositor" => PendingAction::Refund,
+        "recipient" => PendingAction::Release,
+        _ => {
             return Ok(Json(EscrowResponse::error(
-                "Only depositor or recipient can initiate dispute",
+                "Winner must be 'depositor' or 'recipient'",
             )));
         }
+    };
+
+    // Only the arbiter named at creation can resolve
+    let arbiter_pubkey = match escrow.arbiter_pubkey.as_deref() {
+        Some(pubkey) => pubkey,
+        None => return Ok(Json(EscrowResponse::error("Escrow has no arbiter"))),
+    };
+    let message = escrow_action_message("resolve", &escrow.id, &[&req.winner, &req.winner_address]);
+    if let Err(e) = verify_signature(arbiter_pubkey, &message, &req.arbiter_signature) {
+        return Ok(Json(EscrowResponse::error(format!("Invalid arbiter signature: {}", e))));
+    }
 
-        // Update escrow status
-        escrow.status = EscrowStatus::Disputed;
+    let spell_data = match spell_data_for(&escrow) {
+        Some(data) => data,
+        None => return Ok(Json(EscrowResponse::error("Escrow has no on-chain UTXO yet"))),
+    };
 
-        // TODO: Build and broadcast dispute-escrow spell
+    let witness = EscrowWitness {
+        signature: req.arbiter_signature.clone(),
+        signer_pubkey: Some(arbiter_pubkey.to_string()),
+        ..Default::default()
+    };
 
-        Ok(Json(EscrowResponse::success(escrow.clone())))
-    } else {
-        Ok(Json(EscrowResponse::error("Escrow not found")))
+    let template = spell_template(&state.db, RESOLVE_DISPUTE).await;
+
+    let escrow_vk = state.apps.vk(Contract::Escrow).await;
+    let spell_built = match state.charms.build_resolve_dispute_spell(
+        &template.body,
+        &spell_data,
+        &escrow_vk,
+        &req.winner_address,
+        &witness,
+    ) {
+        Ok(spell) => spell,
+        Err(e) => {
+            tracing::error!("Failed to build resolve-dispute spell: {}", e);
+            return Ok(Json(EscrowResponse::error(format!("Failed to build spell: {}", e))));
+        }
+    };
+
+    let app_binary = match state.apps.app_binary(&spell_built).await {
+        Ok(binary) => binary,
+        Err(e) => {
+            tracing::error!("Escrow app binary unavailable: {:#}", e);
+            return Ok(Json(EscrowResponse::error(format!("{:#}", e))));
+        }
+    };
+
+    if let Err(e) = lock_funding_utxo(&state.db, &req.funding_utxo, &req.change_address, None, Some(&id)).await {
+        return Ok(Json(EscrowResponse::error(e.message)));
     }
+
+    let proved_txs = match prove_escrow_spell(
+        &state,
+        &spell_built,
+        &req.funding_utxo,
+        funding_utxo_value,
+        &req.change_address,
+        &id,
+    ).await {
+        Ok(txs) => txs,
+        Err(e) => return Ok(Json(EscrowResponse::from(e))),
+    };
+    record_template_use(&state.db, &id, &template).await;
+
+    // Record the ruling; the escrow is settled once the spell is broadcast
+    let winner = req.winner.clone();
+    let escrow = match set_pending_action(&state, &id, action, |escrow| {
+        if let Some(dispute) = escrow.dispute.as_mut() {
+            dispute.winner = Some(winner);
+        }
+    }).await {
+        Ok(escrow) => escrow,
+        Err(e) => return Ok(Json(EscrowResponse::from(e))),
+    };
+
+    Ok(Json(EscrowResponse::success(EscrowSpellResponse {
+        escrow,
+        spell: SpellData {
+            spell_yaml: template.body,
+            spell_yaml_built: spell_built,
+            app_binary,
+            prev_txs: vec![],
+        },
+        unsigned_txs: unsigned_from_proved(&state, proved_txs, &req.change_address).await,
+        signing_instructions: SigningInstructions {
+            message: format!("Please sign the transaction to pay the escrow to the {}", req.winner),
+            steps: vec![
+                "1. Review the ruling and payout address".to_string(),
+                "2. Sign with your Bitcoin wallet".to_string(),
+                "3. Submit the signed transaction to broadcast".to_string(),
+            ],
+            broadcast_endpoint: format!("/api/escrows/{}/broadcast", id),
+        },
+    }).for_wallet(wallet)))
 }
 
-/// Resolve dispute (arbiter only)
-async fn resolve_dispute(
+/// Submit dispute evidence (depositor or recipient, from a session holding
+/// their key)
+async fn submit_evidence(
     State(state): State<Arc<EscrowState>>,
+    WalletSession(session): WalletSession,
     Path(id): Path<String>,
-    Json(req): Json<ResolveDisputeRequest>,
-) -> Result<Json<EscrowResponse<EscrowRecord>>, StatusCode> {
-    let mut escrows = state.escrows.write().await;
-    
-    if let Some(escrow) = escrows.iter_mut().find(|e| e.id == id) {
-        // Validate escrow is disputed
-        if escrow.status != EscrowStatus::Disputed {
-            return Ok(Json(EscrowResponse::error(
-                "Escrow is not in disputed state",
-            )));
-        }
+    Json(req): Json<SubmitEvidenceRequest>,
+) -> Result<Json<EscrowResponse<EvidenceRecord>>, StatusCode> {
+    let escrow = match load_escrow(&state, &id).await {
+        Ok(escrow) => escrow,
+        Err(e) => return Ok(Json(EscrowResponse::from(e))),
+    };
 
-        // Determine winner
-        let winner = match req.winner.as_str() {
-            "depositor" => {
-                escrow.status = EscrowStatus::Refunded;
-                &escrow.depositor_pubkey
-            }
-            "recipient" => {
-                escrow.status = EscrowStatus::Released;
-                &escrow.recipient_pubkey
-            }
-            _ => {
-                return Ok(Json(EscrowResponse::error(
-                    "Winner must be 'depositor' or 'recipient'",
-                )));
-            }
-        };
+    if !session.pubkeys.contains(&req.submitter_pubkey) {
+        return Ok(Json(EscrowResponse::from(ApiError::forbidden(
+            "submitter_pubkey is not a key of this session's wallets",
+        ))));
+    }
+    // Only the two parties to the escrow can submit evidence
+    if req.submitter_pubkey != escrow.depositor_pubkey
+        && req.submitter_pubkey != escrow.recipient_pubkey
+    {
+        return Ok(Json(EscrowResponse::error(
+            "Only depositor or recipient can submit evidence",
+        )));
+    }
+
+    if escrow.status != EscrowStatus::Active && escrow.status != EscrowStatus::Disputed {
+        return Ok(Json(EscrowResponse::error(
+            "Evidence can only be submitted while the escrow is active or disputed",
+        )));
+    }
 
-        let _ = winner; // Use to resolve the dispute
+    let content_hash = req.content_hash.to_lowercase();
+    if content_hash.len() != 64 || hex::decode(&content_hash).is_err() {
+        return Ok(Json(EscrowResponse::error(
+            "content_hash must be a hex-encoded SHA-256 digest",
+        )));
+    }
+
+    if req.storage_ref.is_empty() {
+        return Ok(Json(EscrowResponse::error("storage_ref is required")));
+    }
 
-        // TODO: Build and broadcast resolve-dispute spell
+    let record = db::EscrowEvidenceRecord {
+        id: Uuid::new_v4().to_string(),
+        escrow_id: id.clone(),
+        submitter_pubkey: req.submitter_pubkey,
+        content_hash,
+        storage_ref: req.storage_ref,
+        content_type: req.content_type,
+        description: req.description,
+        submitted_at: chrono::Utc::now(),
+    };
+    if let Err(e) = db::insert_escrow_evidence(&state.db, &record).await {
+        tracing::error!("Failed to store evidence for escrow {}: {}", id, e);
+        return Ok(Json(EscrowResponse::error("Failed to store evidence")));
+    }
+    let record = EvidenceRecord::from(record);
+    tracing::info!("Evidence {} submitted for escrow {}", record.id, id);
 
-        Ok(Json(EscrowResponse::success(escrow.clone())))
-    } else {
-        Ok(Json(EscrowResponse::error("Escrow not found")))
+    state.events.publish(Event::new(
+        "escrow.evidence_submitted",
+        id,
+        serde_json::json!({
+            "evidence_id": record.id,
+            "submitter_pubkey": record.submitter_pubkey,
+            "content_hash": record.content_hash,
+        }),
+    ));
+
+    Ok(Json(EscrowResponse::success(record)))
+}
+
+/// The part of an escrow's evidence a session may see: all of it when it
+/// holds the arbiter's key, otherwise what its keys submitted
+fn visible_evidence(
+    escrow: &EscrowRecord,
+    session: &Session,
+    evidence: Vec<db::EscrowEvidenceRecord>,
+) -> Vec<EvidenceRecord> {
+    let is_arbiter = escrow.arbiter_pubkey.as_ref().is_some_and(|key| session.pubkeys.contains(key));
+    evidence
+        .into_iter()
+        .filter(|e| is_arbiter || session.pubkeys.contains(&e.submitter_pubkey))
+        .map(EvidenceRecord::from)
+        .collect()
+}
+
+/// List evidence for an escrow. The arbiter sees all evidence; each party
+/// sees only what they submitted.
+async fn list_evidence(
+    State(state): State<Arc<EscrowState>>,
+    WalletSession(session): WalletSession,
+    Path(id): Path<String>,
+) -> Result<Json<EscrowResponse<Vec<EvidenceRecord>>>, StatusCode> {
+    let escrow = match load_escrow(&state, &id).await {
+        Ok(escrow) => escrow,
+        Err(e) => return Ok(Json(EscrowResponse::from(e))),
+    };
+
+    let ours = |key: &str| session.pubkeys.iter().any(|k| k == key);
+    if !ours(&escrow.depositor_pubkey) && !ours(&escrow.recipient_pubkey) && !escrow.arbiter_pubkey.as_deref().is_some_and(ours) {
+        return Ok(Json(EscrowResponse::from(ApiError::forbidden(
+            "Not authorized to view evidence for this escrow",
+        ))));
     }
+
+    let evidence = match db::get_evidence_for_escrows(&state.db, std::slice::from_ref(&id)).await {
+        Ok(mut evidence) => evidence.remove(&id).unwrap_or_default(),
+        Err(e) => return Ok(Json(EscrowResponse::from(ApiError::from(e)))),
+    };
+
+    Ok(Json(EscrowResponse::success(visible_evidence(&escrow, &session, evidence))))
+}
+
+/// Escrows in which any public key declared by the session's wallets is the
+/// depositor, recipient or arbiter
+async fn list_my_escrows(
+    State(state): State<Arc<EscrowState>>,
+    WalletSession(session): WalletSession,
+    Query(page): Query<PageQuery>,
+) -> Json<EscrowResponse<EscrowPage>> {
+    let filter = db::EscrowFilter { party_pubkeys: Some(session.pubkeys), ..Default::default() };
+    Json(escrow_page(&state, filter, page).await.into())
 }
 
 /// Get escrows by depositor
 async fn get_escrows_by_depositor(
     State(state): State<Arc<EscrowState>>,
     Path(pubkey): Path<String>,
-) -> Json<EscrowResponse<Vec<EscrowRecord>>> {
-    let escrows = state.escrows.read().await;
-    let filtered: Vec<EscrowRecord> = escrows
-        .iter()
-        .filter(|e| e.depositor_pubkey == pubkey)
-        .cloned()
-        .collect();
-    Json(EscrowResponse::success(filtered))
+    Query(page): Query<PageQuery>,
+) -> Json<EscrowResponse<EscrowPage>> {
+    let filter = db::EscrowFilter { depositor_pubkey: Some(pubkey), ..Default::default() };
+    Json(escrow_page(&state, filter, page).await.into())
 }
 
 /// Get escrows by recipient
 async fn get_escrows_by_recipient(
     State(state): State<Arc<EscrowState>>,
     Path(pubkey): Path<String>,
-) -> Json<EscrowResponse<Vec<EscrowRecord>>> {
-    let escrows = state.escrows.read().await;
-    let filtered: Vec<EscrowRecord> = escrows
+    Query(page): Query<PageQuery>,
+) -> Json<EscrowResponse<EscrowPage>> {
+    let filter = db::EscrowFilter { recipient_pubkey: Some(pubkey), ..Default::default() };
+    Json(escrow_page(&state, filter, page).await.into())
+}
+
+/// Get escrows by arbiter
+async fn get_escrows_by_arbiter(
+    State(state): State<Arc<EscrowState>>,
+    Path(pubkey): Path<String>,
+    Query(page): Query<PageQuery>,
+) -> Json<EscrowResponse<EscrowPage>> {
+    let filter = db::EscrowFilter { arbiter_pubkey: Some(pubkey), ..Default::default() };
+    Json(escrow_page(&state, filter, page).await.into())
+}
+
+/// List disputes with the evidence the session may see, oldest first
+async fn list_disputes(
+    State(state): State<Arc<EscrowState>>,
+    WalletSession(session): WalletSession,
+    Query(query): Query<ListDisputesQuery>,
+) -> Json<EscrowResponse<DisputePage>> {
+    Json(dispute_page(&state, &session, query).await.into())
+}
+
+/// One page of the disputes `query` asks for, oldest first
+async fn dispute_page(state: &EscrowState, session: &Session, query: ListDisputesQuery) -> Result<DisputePage, ApiError> {
+    let dispute = match query.status.as_deref().unwrap_or("open") {
+        "open" => db::DisputeState::Open,
+        "resolved" => db::DisputeState::Resolved,
+        "all" => db::DisputeState::Any,
+        _ => return Err(ApiError::bad_request("status must be 'open', 'resolved', or 'all'")),
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    if let Some(cursor) = &query.cursor {
+        if db::get_escrow(&state.db, cursor).await?.is_none_or(|e| e.dispute.is_none()) {
+            return Err(ApiError::bad_request("Invalid cursor"));
+        }
+    }
+
+    let filter = db::EscrowFilter {
+        arbiter_pubkey: query.arbiter,
+        dispute: Some(dispute),
+        after_id: query.cursor,
+        // One extra to tell whether another page follows
+        limit: Some(limit as i64 + 1),
+        ..Default::default()
+    };
+    let mut records = db::query_disputes(&state.db, &filter).await?;
+    let more = records.len() > limit as usize;
+    records.truncate(limit as usize);
+    let escrows = escrows_from_rows(state, records).await?;
+
+    let ids: Vec<String> = escrows.iter().map(|e| e.id.clone()).collect();
+    let mut evidence = db::get_evidence_for_escrows(&state.db, &ids).await?;
+    let next_cursor = if more { escrows.last().map(|e| e.id.clone()) } else { None };
+    let disputes = escrows
+        .into_iter()
+        .map(|e| DisputeSummary {
+            evidence: visible_evidence(&e, session, evidence.remove(&e.id).unwrap_or_default()),
+            resolve_endpoint: format!("/api/escrows/{}/resolve", e.id),
+            escrow: e,
+        })
+        .collect();
+
+    Ok(DisputePage { disputes, limit, next_cursor })
+}
+
+/// Prove an escrow spell. On failure the escrow's UTXO locks are released so
+/// the caller can retry, and the error is returned for the response.
+async fn prove_escrow_spell(
+    state: &EscrowState,
+    spell_built: &str,
+    funding_utxo: &OutPoint,
+    funding_utxo_value: u64,
+    change_address: &str,
+    escrow_id: &str,
+) -> Result<Vec<ProvedTransaction>, ApiError> {
+    let binaries = state.apps.binaries_for_spell(spell_built).await;
+
+    let fee_rate = state.fees.fee_rate(state.chain.as_ref(), FeeTier::Normal).await;
+
+    let prove_request = SpellProveRequest {
+        spell: spell_built.to_string(),
+        binaries,
+        prev_txs: vec![],
+        funding_utxo: *funding_utxo,
+        funding_utxo_value,
+        change_address: change_address.to_string(),
+        fee_rate,
+        chain: state.charms.chain().to_string(),
+        replaces: None,
+    };
+
+    match prove_cached(&state.charms, &state.db, &state.events, prove_request, escrow_id).await {
+        Ok(txs) => Ok(txs),
+        Err(e) => {
+            tracing::error!("Proving escrow {} spell failed: {:#}", escrow_id, e);
+            if let Err(e) = db::release_escrow_utxo_locks(&state.db, escrow_id).await {
+                tracing::warn!("Failed to release UTXO locks for escrow {}: {}", escrow_id, e);
+            }
+            Err(ApiError::prove_failed(&e))
+        }
+    }
+}
+
+/// Convert proved transactions into signing payloads for a single signer
+async fn unsigned_from_proved(
+    state: &EscrowState,
+    txs: Vec<ProvedTransaction>,
+    signer: &str,
+) -> Vec<UnsignedTransaction> {
+    signing_payloads(state.chain.as_ref(), &state.sessions, txs, vec![InputToSign::new(0, signer)]).await
+}
+
+/// Canonical payload a party signs to authorize an escrow action:
+/// `liquid-nation:escrow:<action>:<escrow id>[:<field>...]`
+pub fn escrow_action_message(action: &str, escrow_id: &str, fields: &[&str]) -> String {
+    let mut message = format!("liquid-nation:escrow:{}:{}", action, escrow_id);
+    for field in fields {
+        message.push(':');
+        message.push_str(field);
+    }
+    message
+}
+
+/// Spell data describing the escrow's current on-chain state
+fn spell_data_for(escrow: &EscrowRecord) -> Option<EscrowSpellData> {
+    let escrow_utxo = escrow.utxo_id.clone()?;
+
+    Some(EscrowSpellData {
+        escrow_id: escrow.escrow_id.clone(),
+        depositor_pubkey: escrow.depositor_pubkey.clone(),
+        recipient_pubkey: escrow.recipient_pubkey.clone(),
+        arbiter_pubkey: escrow.arbiter_pubkey.clone(),
+        escrow_type: escrow.escrow_type.as_u8(),
+        token_id: escrow.held_token_id.clone(),
+        token_vk: DEFAULT_TOKEN_VK.to_string(),
+        amount: escrow.held_amount,
+        release_hash: escrow.release_hash.clone(),
+        expiry_height: escrow.expiry_height,
+        created_at: escrow.created_height,
+        order_id: escrow.order_id.clone(),
+        escrow_utxo,
+        escrow_address: escrow.escrow_address.clone(),
+    })
+}
+
+/// Script tree the escrow charm is locked at: cooperative and arbitrated
+/// release, hashlock and timeout refund leaves
+fn escrow_tree(
+    escrow_id: &str,
+    depositor_pubkey: &str,
+    recipient_pubkey: &str,
+    arbiter_pubkey: Option<&str>,
+    release_hash: Option<&str>,
+    expiry_height: u64,
+    network: Network,
+) -> anyhow::Result<EscrowScriptTree> {
+    let keys = EscrowKeys {
+        depositor: addresses::parse_xonly_key(depositor_pubkey)?,
+        recipient: addresses::parse_xonly_key(recipient_pubkey)?,
+        arbiter: arbiter_pubkey.map(addresses::parse_xonly_key).transpose()?,
+    };
+    escrow_script_tree(escrow_id, &keys, release_hash, expiry_height, network)
+}
+
+/// Import the escrow address into the node's watch-only wallet so deposits
+/// to it show up; Esplora looks addresses up directly and needs no import
+async fn track_escrow_address(state: &EscrowState, address: &str) {
+    if state.chain.kind() != ChainKind::Node {
+        return;
+    }
+    let import = async {
+        let descriptor = state
+            .bitcoin
+            .get_descriptor_info(&format!("addr({})", address))
+            .await?
+            .descriptor;
+        let wallet = watch_wallet_name();
+        state.bitcoin.ensure_watch_wallet(&wallet).await?;
+        state.bitcoin.import_descriptors(&wallet, &[descriptor], 0, None).await
+    };
+    if let Err(e) = import.await {
+        tracing::warn!("Could not watch escrow address {}: {:#}", address, e);
+    }
+}
+
+/// Spawn the background task that credits escrows with the UTXO at their
+/// address, checking on every new block and, at most once per
+/// `ESCROW_DEPOSIT_DEBOUNCE_SECS`, on mempool transactions
+pub fn spawn_deposit_tracker(state: Arc<EscrowState>, chain_events: ChainEvents) {
+    let interval_secs = std::env::var("ESCROW_DEPOSIT_INTERVAL_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(30);
+    let debounce_secs = std::env::var("ESCROW_DEPOSIT_DEBOUNCE_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(5);
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_secs));
+        let mut blocks = chain_events.subscribe();
+        let mut transactions = chain_events.subscribe();
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = zmq::next_event(&mut blocks, zmq::is_block) => {}
+                _ = zmq::next_event(&mut transactions, zmq::is_any) => {
+                    // A busy mempool announces transactions far faster than
+                    // deposits need checking: one pass covers every
+                    // transaction announced while waiting
+                    tokio::time::sleep(std::time::Duration::from_secs(debounce_secs)).await;
+                    transactions = transactions.resubscribe();
+                }
+            }
+
+            if let Err(e) = credit_deposits(&state).await {
+                tracing::debug!("Escrow deposit tracker failed: {:#}", e);
+            }
+        }
+    });
+}
+
+/// Credit each waiting escrow with the UTXO at its address: the spell output
+/// it expects, or any when it expects none. Publishes `escrow.deposit` when
+/// the UTXO appears and `escrow.deposit_confirmed` when it confirms.
+async fn credit_deposits(state: &EscrowState) -> anyhow::Result<()> {
+    let records = db::get_escrows_awaiting_deposit(&state.db).await?;
+    let mut waiting = escrows_from_rows(state, records).await?;
+    let addresses: Vec<String> = waiting
         .iter()
-        .filter(|e| e.recipient_pubkey == pubkey)
-        .cloned()
+        .map(|e| e.escrow_address.clone())
+        .collect::<std::collections::HashSet<_>>()
+        .into_iter()
         .collect();
-    Json(EscrowResponse::success(filtered))
+    if addresses.is_empty() {
+        return Ok(());
+    }
+
+    let unspent = match state.chain.kind() {
+        ChainKind::Node => state.bitcoin.list_unspent_for(&watch_wallet_name(), &addresses).await?,
+        ChainKind::Esplora | ChainKind::Mock => state.chain.list_unspent(&addresses, 0).await?,
+    };
+
+    let mut events = Vec::new();
+    for escrow in &mut waiting {
+        let Some(utxo) = unspent.iter().filter(|u| u.address == escrow.escrow_address).find(|u| {
+            let outpoint = format!("{}:{}", u.txid, u.vout);
+            escrow.utxo_id.as_ref().is_none_or(|expected| *expected == outpoint)
+        }) else {
+            continue;
+        };
+
+        let confirmed = utxo.confirmations > 0;
+        let kind = match &escrow.deposit {
+            None => "escrow.deposit",
+            Some(_) if confirmed => "escrow.deposit_confirmed",
+            Some(_) => continue,
+        };
+        let deposit = EscrowDeposit {
+            outpoint: format!("{}:{}", utxo.txid, utxo.vout),
+            value: (utxo.amount * 100_000_000.0).round() as u64,
+            confirmed,
+        };
+        let event = Event::new(
+            kind,
+            escrow.id.clone(),
+            serde_json::json!({
+                "address": escrow.escrow_address,
+                "outpoint": deposit.outpoint,
+                "value": deposit.value,
+                "confirmations": utxo.confirmations,
+            }),
+        );
+        escrow.utxo_id = Some(deposit.outpoint.clone());
+        escrow.tx_id = Some(utxo.txid.clone());
+        escrow.deposit = Some(deposit);
+
+        // An escrow that moved on while the UTXOs were listed is left for
+        // the next pass to look at again
+        if let Err(e) = save_escrow(state, escrow).await {
+            if e.downcast_ref::<db::StaleEscrow>().is_none() {
+                return Err(e);
+            }
+            tracing::debug!("Escrow deposit not credited: {}", e);
+            continue;
+        }
+        tracing::info!("Escrow {} credited with {} ({})", escrow.id, escrow.utxo_id.as_deref().unwrap_or_default(), kind);
+        events.push(event);
+    }
+
+    for event in events {
+        state.events.publish(event);
+    }
+    Ok(())
+}
+
+/// Record the spell awaiting broadcast along with any other change `update`
+/// makes, returning the updated escrow
+async fn set_pending_action(
+    state: &EscrowState,
+    id: &str,
+    action: PendingAction,
+    update: impl FnOnce(&mut EscrowRecord),
+) -> Result<EscrowRecord, ApiError> {
+    let mut escrow = load_escrow(state, id).await?;
+    escrow.pending_action = Some(action);
+    update(&mut escrow);
+    save_escrow(state, &escrow).await?;
+    Ok(escrow)
+}
+
+/// Spawn the recurring job that deletes proposals never turned into an
+/// escrow once they are `ESCROW_PROPOSAL_TTL_DAYS` old
+pub fn spawn_proposal_pruner(state: Arc<EscrowState>, jobs: Arc<JobQueue>) {
+    let ttl_days: i64 = std::env::var("ESCROW_PROPOSAL_TTL_DAYS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(7);
+
+    jobs.spawn_recurring("prune_escrow_proposals", std::time::Duration::from_secs(3600), move || {
+        let state = state.clone();
+        async move {
+            let before = chrono::Utc::now() - chrono::Duration::days(ttl_days);
+            let pruned = db::prune_escrow_proposals(&state.db, before).await?;
+            if pruned > 0 {
+                tracing::info!("Pruned {} stale escrow proposals", pruned);
+            }
+            Ok(())
+        }
+    });
+}
+
+/// Spawn the background task that expires escrows once the chain passes their
+/// `expiry_height`, checking on every new block
+pub fn spawn_expiry_monitor(state: Arc<EscrowState>, chain_events: ChainEvents) {
+    let interval_secs = std::env::var("ESCROW_MONITOR_INTERVAL_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(60);
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_secs));
+        let mut blocks = chain_events.subscribe();
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = zmq::next_event(&mut blocks, zmq::is_block) => {}
+            }
+
+            match state.tip.height().await {
+                Ok(height) => expire_escrows(&state, height).await,
+                Err(e) => tracing::debug!("Escrow monitor could not fetch block height: {}", e),
+            }
+        }
+    });
 }
 
+/// Held escrow charms, which only the escrow's own spells may spend. An
+/// unexpected spend is reported; the escrow keeps its status, since only its
+/// parties know what happened.
+#[async_trait]
+impl SpendWatcher for EscrowState {
+    async fn watched(&self) -> anyhow::Result<Vec<WatchedOutpoint>> {
+        let held: Vec<(String, String)> = load_escrows(self, None)
+            .await?
+            .iter()
+            .filter(|e| matches!(e.status, EscrowStatus::Active | EscrowStatus::Disputed))
+            .filter_map(|e| Some((e.id.clone(), e.utxo_id.clone()?)))
+            .collect();
+        let ids: Vec<String> = held.iter().map(|(id, _)| id.clone()).collect();
+        let proved = db::get_proved_txids(&self.db, &ids).await?;
+
+        Ok(held
+            .iter()
+            .filter_map(|(id, utxo)| {
+                let expected = proved.get(id).map(Vec::as_slice).unwrap_or_default();
+                WatchedOutpoint::new(utxo, id, expected, ExternalSpend::ExternallySpent)
+            })
+            .collect())
+    }
+
+    async fn spent_externally(&self, watched: &WatchedOutpoint, txid: &bitcoin::Txid) -> anyhow::Result<()> {
+        self.events.publish(Event::new(
+            "escrow.externally_spent",
+            watched.subject_id.clone(),
+            serde_json::json!({ "outpoint": watched.outpoint.to_string(), "txid": txid.to_string() }),
+        ));
+        Ok(())
+    }
+}
+
+/// Mark active escrows past their expiry height as expired and notify the depositor
+async fn expire_escrows(state: &EscrowState, height: u64) {
+    let escrows = match load_escrows(state, Some(EscrowStatus::Active)).await {
+        Ok(escrows) => escrows,
+        Err(e) => {
+            tracing::warn!("Escrow monitor could not load escrows: {}", e);
+            return;
+        }
+    };
+
+    let reason = format!("expiry height reached at block {}", height);
+    let by = db::Transition::new("expiry_monitor", &reason);
+    for mut escrow in escrows.into_iter().filter(|e| height >= e.expiry_height) {
+        escrow.status = EscrowStatus::Expired;
+        // Skipped if a party moved it first
+        if let Err(e) = record_escrow_transition(state, &escrow, EscrowStatus::Active, &by).await {
+            tracing::warn!("Escrow {} not expired: {}", escrow.id, e);
+            continue;
+        }
+        tracing::info!("Escrow {} expired at height {}", escrow.id, height);
+
+        state.events.publish(Event::new(
+            "escrow.expired",
+            escrow.id.clone(),
+            serde_json::json!({
+                "depositor_pubkey": escrow.depositor_pubkey,
+                "expiry_height": escrow.expiry_height,
+                "height": height,
+                "refund_endpoint": format!("/api/escrows/{}/refund", escrow.id),
+            }),
+        ));
+    }
+}
+
+/// Escrow state for handler tests
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::*;
+
+    use crate::services::mock_chain::MockChain;
+    use crate::services::prover::{MockProver, ProverBackend};
+
+    /// Escrow state over the mock prover and chain, as the server builds it
+    /// on startup
+    pub fn test_state(db: DbPool) -> EscrowState {
+        let network = Network::Testnet4;
+        let prover: Arc<dyn ProverBackend> = Arc::new(MockProver);
+        let chain: Arc<dyn ChainBackend> = Arc::new(MockChain::new(network));
+        EscrowState {
+            charms: Arc::new(CharmsService::new(prover.clone(), network).with_chain(chain.clone())),
+            apps: Arc::new(AppArtifacts::from_env(prover.as_ref())),
+            bitcoin: Arc::new(BitcoinService::new("http://127.0.0.1:1", network)),
+            tip: Arc::new(ChainTip::new(chain.clone())),
+            chain,
+            fees: FeeEstimator::new(network),
+            events: EventBus::new(),
+            sealer: Arc::new(Sealer::new(vec![("k1".to_string(), [1; 32])])),
+            sessions: SessionStore::new(db.clone()),
+            db,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::hashes::Hash;
+    use bitcoin::secp256k1::{Keypair, Message, Secp256k1, SecretKey};
+
+    use crate::routes::escrow::testing::test_state;
+    use crate::services::signatures::message_digest;
+
+    const ADDRESS: &str = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+
+    fn xonly(byte: u8) -> String {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[byte; 32]).unwrap());
+        hex::encode(keypair.x_only_public_key().0.serialize())
+    }
+
+    /// Schnorr signature by `xonly(byte)` over an escrow action message
+    fn sign(byte: u8, message: &str) -> String {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[byte; 32]).unwrap());
+        let msg = Message::from_digest(message_digest(message));
+        hex::encode(secp.sign_schnorr_no_aux_rand(&msg, &keypair).serialize())
+    }
+
+    fn create_request() -> CreateEscrowRequest {
+        CreateEscrowRequest {
+            depositor_pubkey: xonly(1),
+            recipient_pubkey: xonly(2),
+            arbiter_pubkey: None,
+            escrow_type: EscrowType::TwoParty,
+            token_id: "TOAD".to_string(),
+            amount: 1000,
+            release_hash: None,
+            expiry_height: 200_000,
+            order_id: None,
+            funding_utxo: Some(OutPoint::new(bitcoin::Txid::from_byte_array([3; 32]), 0)),
+            funding_utxo_value: Some(20_000),
+            depositor_address: Some(ADDRESS.to_string()),
+            milestones: vec![
+                MilestoneSpec { amount: 400, description: "design".to_string() },
+                MilestoneSpec { amount: 600, description: "build".to_string() },
+            ],
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_escrow_survives_restart() {
+        let db = db::testing::fresh_db().await;
+
+        let state = test_state(db);
+        let created = build_escrow(&state, create_request()).await.unwrap();
+        assert!(created.success, "{:?}", created.error);
+        let id = created.data.unwrap().escrow.id;
+        set_pending_action(&state, &id, PendingAction::Milestone(0), |escrow| {
+            escrow.revealed_preimage = Some("00".repeat(32));
+        })
+        .await
+        .unwrap();
+
+        // A restarted server finds the escrow, its milestones and the
+        // pending spell where they were left
+        let state = Arc::new(test_state(db::testing::reconnect(&state.db).await));
+        let escrow = load_escrow(&state, &id).await.unwrap();
+        assert_eq!(escrow.status, EscrowStatus::Active);
+        assert_eq!(escrow.held_amount, 1000);
+        assert_eq!(escrow.depositor_pubkey, xonly(1));
+        assert_eq!(escrow.pending_action, Some(PendingAction::Milestone(0)));
+        assert_eq!(escrow.revealed_preimage, Some("00".repeat(32)));
+        // Stored sealed
+        let row = db::get_escrow(&state.db, &id).await.unwrap().unwrap();
+        assert!(row.revealed_preimage.unwrap().starts_with("sealed:v1:k1:"));
+        let amounts: Vec<u64> = escrow.milestones.iter().map(|m| m.amount).collect();
+        assert_eq!(amounts, vec![400, 600]);
+
+        let page = PageQuery { limit: None, cursor: None };
+        let listed = get_escrows_by_depositor(State(state.clone()), Path(xonly(1)), Query(page)).await.0;
+        let listed = listed.data.unwrap().escrows;
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, id);
+
+        let events = db::get_state_events(&state.db, db::Subject::Escrow, &id).await.unwrap();
+        assert_eq!(events.len(), 1);
+
+        assert_eq!(load_escrow(&state, "missing").await.unwrap_err().status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_escrow_transition_needs_current_status() {
+        let db = db::testing::fresh_db().await;
+
+        let state = test_state(db);
+        let created = build_escrow(&state, create_request()).await.unwrap();
+        let mut escrow = load_escrow(&state, &created.data.unwrap().escrow.id).await.unwrap();
+        let by = db::Transition::new("test", "released");
+
+        // Moving from a status the escrow is not in changes nothing
+        escrow.status = EscrowStatus::Released;
+        assert!(record_escrow_transition(&state, &escrow, EscrowStatus::Disputed, &by).await.is_err());
+        assert_eq!(load_escrow(&state, &escrow.id).await.unwrap().status, EscrowStatus::Active);
+
+        record_escrow_transition(&state, &escrow, EscrowStatus::Active, &by).await.unwrap();
+        assert_eq!(load_escrow(&state, &escrow.id).await.unwrap().status, EscrowStatus::Released);
+        // A second writer that still saw it active loses
+        assert!(record_escrow_transition(&state, &escrow, EscrowStatus::Active, &by).await.is_err());
+        assert_eq!(db::get_state_events(&state.db, db::Subject::Escrow, &escrow.id).await.unwrap().len(), 2);
+
+        // An escrow without a row cannot be moved
+        escrow.id = "missing".to_string();
+        assert!(record_escrow_transition(&state, &escrow, EscrowStatus::Released, &by).await.is_err());
+    }
+
+    fn session(pubkeys: &[String]) -> WalletSession {
+        WalletSession(Session {
+            token: "token".to_string(),
+            user_id: "user".to_string(),
+            address: ADDRESS.to_string(),
+            addresses: vec![ADDRESS.to_string()],
+            pubkeys: pubkeys.to_vec(),
+            expires_at: chrono::Utc::now() + chrono::Duration::hours(1),
+        })
+    }
+
+    fn evidence_request(submitter: &str) -> SubmitEvidenceRequest {
+        SubmitEvidenceRequest {
+            submitter_pubkey: submitter.to_string(),
+            content_hash: "ab".repeat(32),
+            storage_ref: format!("ipfs://{}", submitter),
+            content_type: None,
+            description: None,
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_evidence_needs_a_party_session_and_persists() {
+        let db = db::testing::fresh_db().await;
+
+        let state = Arc::new(test_state(db));
+        let request = CreateEscrowRequest {
+            escrow_type: EscrowType::TwoOfThree,
+            arbiter_pubkey: Some(xonly(3)),
+            ..create_request()
+        };
+        let id = build_escrow(&state, request).await.unwrap().data.unwrap().escrow.id;
+        let submit = |as_session: WalletSession, submitter: String| {
+            let state = state.clone();
+            let id = id.clone();
+            async move {
+                submit_evidence(State(state), as_session, Path(id), Json(evidence_request(&submitter)))
+                    .await
+                    .unwrap()
+                    .0
+            }
+        };
+
+        // A key the session does not hold, even a party's, is refused
+        let refused = submit(session(&[xonly(1)]), xonly(2)).await;
+        assert!(!refused.success);
+        // So is a session key that is not a party
+        assert!(!submit(session(&[xonly(9)]), xonly(9)).await.success);
+        assert!(submit(session(&[xonly(1)]), xonly(1)).await.success);
+        assert!(submit(session(&[xonly(2)]), xonly(2)).await.success);
+
+        // Stored, so a restarted server still lists it
+        let state = Arc::new(test_state(db::testing::reconnect(&state.db).await));
+        let list = |as_session: WalletSession| {
+            let state = state.clone();
+            let id = id.clone();
+            async move { list_evidence(State(state), as_session, Path(id)).await.unwrap().0 }
+        };
+        let submitters = |response: EscrowResponse<Vec<EvidenceRecord>>| -> Vec<String> {
+            response.data.unwrap().into_iter().map(|e| e.submitter_pubkey).collect()
+        };
+        assert_eq!(submitters(list(session(&[xonly(1)])).await), vec![xonly(1)]);
+        assert_eq!(submitters(list(session(&[xonly(3)])).await).len(), 2);
+        assert!(!list(session(&[xonly(9)])).await.success);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_proposals_persist_through_acceptance() {
+        let db = db::testing::fresh_db().await;
+
+        let state = Arc::new(test_state(db));
+        let terms = EscrowTerms {
+            depositor_pubkey: xonly(1),
+            recipient_pubkey: xonly(2),
+            arbiter_pubkey: Some(xonly(3)),
+            escrow_type: EscrowType::TwoOfThree,
+            token_id: "TOAD".to_string(),
+            amount: 1000,
+            release_hash: None,
+            expiry_height: 200_000,
+            order_id: None,
+        };
+        let terms_hash = terms.hash();
+        let signature = sign(1, &escrow_action_message("propose", &terms_hash, &[]));
+        let proposal = propose_escrow(State(state.clone()), Json(ProposeEscrowRequest { terms, signature }))
+            .await
+            .unwrap()
+            .0
+            .data
+            .unwrap();
+        let accept = |byte: u8| {
+            let state = state.clone();
+            let id = proposal.id.clone();
+            let req = AcceptProposalRequest {
+                signer_pubkey: xonly(byte),
+                signature: sign(byte, &escrow_action_message("accept", &terms_hash, &[])),
+            };
+            async move { accept_proposal(State(state), Path(id), Json(req)).await.unwrap().0 }
+        };
+
+        let accepted = accept(2).await.data.unwrap();
+        assert_eq!(accepted.status, ProposalStatus::Pending);
+        assert!(!accept(2).await.success, "a party accepts once");
+        assert!(!accept(9).await.success, "only the recipient or arbiter accepts");
+
+        // Stored, so a restarted server picks up where it left off
+        let state = Arc::new(test_state(db::testing::reconnect(&state.db).await));
+        let req = AcceptProposalRequest {
+            signer_pubkey: xonly(3),
+            signature: sign(3, &escrow_action_message("accept", &terms_hash, &[])),
+        };
+        let accepted = accept_proposal(State(state.clone()), Path(proposal.id.clone()), Json(req))
+            .await
+            .unwrap()
+            .0
+            .data
+            .unwrap();
+        assert_eq!(accepted.status, ProposalStatus::Accepted);
+        assert_eq!(accepted.acceptances.len(), 2);
+
+        let query = ListProposalsQuery { pubkey: Some(xonly(3)), limit: None };
+        let listed = list_proposals(State(state.clone()), Query(query)).await.unwrap().0.data.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].terms.arbiter_pubkey, Some(xonly(3)));
+        let query = ListProposalsQuery { pubkey: Some(xonly(9)), limit: None };
+        assert!(list_proposals(State(state.clone()), Query(query)).await.unwrap().0.data.unwrap().is_empty());
+
+        // Only proposals never turned into an escrow are pruned
+        let later = chrono::Utc::now() + chrono::Duration::minutes(1);
+        assert!(db::mark_escrow_proposal_created(&state.db, &proposal.id, "escrow-1").await.unwrap());
+        assert_eq!(db::prune_escrow_proposals(&state.db, later).await.unwrap(), 0);
+        let created = get_proposal(State(state.clone()), Path(proposal.id.clone())).await.unwrap().0.data.unwrap();
+        assert_eq!(created.status, ProposalStatus::Created);
+        assert_eq!(created.escrow_id.as_deref(), Some("escrow-1"));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_list_escrows_pages_in_sql() {
+        let db = db::testing::fresh_db().await;
+
+        let state = Arc::new(test_state(db));
+        let mut created = Vec::new();
+        for (vout, depositor) in [1, 1, 4].into_iter().enumerate() {
+            let request = CreateEscrowRequest {
+                depositor_pubkey: xonly(depositor),
+                funding_utxo: Some(OutPoint::new(bitcoin::Txid::from_byte_array([3; 32]), vout as u32)),
+                ..create_request()
+            };
+            created.push(build_escrow(&state, request).await.unwrap().data.unwrap().escrow.id);
+        }
+        let list = |limit: u32, cursor: Option<String>, status: Option<&str>, depositor: Option<String>| {
+            let state = state.clone();
+            let params = ListEscrowsQuery {
+                status: status.map(str::to_string),
+                token_id: Some("TOAD".to_string()),
+                depositor,
+                recipient: None,
+                created_after: None,
+                created_before: None,
+                limit: Some(limit),
+                cursor,
+            };
+            async move { list_escrows(State(state), Query(params)).await.unwrap().0 }
+        };
+
+        let first = list(2, None, None, None).await.data.unwrap();
+        assert_eq!(first.escrows.len(), 2);
+        assert_eq!(first.total, 3);
+        assert_eq!(first.counts.get("active"), Some(&3));
+        let second = list(2, first.next_cursor.clone(), None, None).await.data.unwrap();
+        assert_eq!(second.escrows.len(), 1);
+        assert_eq!(second.next_cursor, None);
+        let mut seen: Vec<String> = first.escrows.iter().chain(&second.escrows).map(|e| e.id.clone()).collect();
+        seen.sort();
+        created.sort();
+        assert_eq!(seen, created);
+
+        let mine = list(10, None, None, Some(xonly(1))).await.data.unwrap();
+        assert_eq!(mine.total, 2);
+        assert!(mine.escrows.iter().all(|e| e.depositor_pubkey == xonly(1)));
+
+        // Counts ignore the status filter; the total does not
+        let released = list(10, None, Some("released"), None).await.data.unwrap();
+        assert!(released.escrows.is_empty());
+        assert_eq!(released.total, 0);
+        assert_eq!(released.counts.get("active"), Some(&3));
+
+        assert!(!list(2, Some("missing".to_string()), None, None).await.success);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_work_queues_page_in_sql() {
+        let db = db::testing::fresh_db().await;
+
+        let state = Arc::new(test_state(db));
+        let mut created = Vec::new();
+        for (vout, arbiter) in [3, 3, 5].into_iter().enumerate() {
+            let request = CreateEscrowRequest {
+                escrow_type: EscrowType::TwoOfThree,
+                arbiter_pubkey: Some(xonly(arbiter)),
+                funding_utxo: Some(OutPoint::new(bitcoin::Txid::from_byte_array([4; 32]), vout as u32)),
+                ..create_request()
+            };
+            created.push(build_escrow(&state, request).await.unwrap().data.unwrap().escrow.id);
+        }
+
+        // The first escrow is disputed after the second; the second's
+        // dispute is then resolved
+        for (id, opened_at) in [(&created[1], 100), (&created[0], 200)] {
+            let mut escrow = load_escrow(&state, id).await.unwrap();
+            escrow.status = EscrowStatus::Disputed;
+            escrow.dispute = Some(DisputeInfo {
+                reason: "late".to_string(),
+                initiator_pubkey: xonly(1),
+                evidence_hash: None,
+                opened_at,
+                resolved_at: None,
+                winner: None,
+            });
+            let by = db::Transition::new("depositor", "dispute");
+            record_escrow_transition(&state, &escrow, EscrowStatus::Active, &by).await.unwrap();
+        }
+        let mut resolved = load_escrow(&state, &created[1]).await.unwrap();
+        resolved.dispute.as_mut().unwrap().resolved_at = Some(300);
+        save_escrow(&state, &resolved).await.unwrap();
+
+        let page = |limit: u32, cursor: Option<String>| PageQuery { limit: Some(limit), cursor };
+        let ids = |page: EscrowPage| -> Vec<String> { page.escrows.into_iter().map(|e| e.id).collect() };
+
+        // A page at a time
+        let first = get_escrows_by_arbiter(State(state.clone()), Path(xonly(3)), Query(page(1, None))).await.0;
+        let first = first.data.unwrap();
+        assert!(first.next_cursor.is_some());
+        let second = get_escrows_by_arbiter(State(state.clone()), Path(xonly(3)), Query(page(1, first.next_cursor.clone())))
+            .await
+            .0
+            .data
+            .unwrap();
+        assert_eq!(second.next_cursor, None);
+        let mut seen = [ids(first), ids(second)].concat();
+        seen.sort();
+        let mut arbitrated = created[..2].to_vec();
+        arbitrated.sort();
+        assert_eq!(seen, arbitrated);
+        let bad_cursor = get_escrows_by_arbiter(State(state.clone()), Path(xonly(3)), Query(page(1, Some("missing".to_string()))));
+        assert!(!bad_cursor.await.0.success);
+
+        // Any key of the session, in any role
+        let mine = list_my_escrows(State(state.clone()), session(&[xonly(9), xonly(5)]), Query(page(10, None))).await.0;
+        assert_eq!(ids(mine.data.unwrap()), vec![created[2].clone()]);
+        let mine = list_my_escrows(State(state.clone()), session(&[xonly(1)]), Query(page(10, None))).await.0;
+        assert_eq!(mine.data.unwrap().escrows.len(), 3);
+        let none = list_my_escrows(State(state.clone()), session(&[]), Query(page(10, None))).await.0;
+        assert!(none.data.unwrap().escrows.is_empty());
+
+        let disputes = |status: &str, limit: u32, cursor: Option<String>| {
+            let state = state.clone();
+            let query = ListDisputesQuery {
+                status: Some(status.to_string()),
+                arbiter: Some(xonly(3)),
+                limit: Some(limit),
+                cursor,
+            };
+            async move { list_disputes(State(state), session(&[xonly(3)]), Query(query)).await.0 }
+        };
+        let dispute_ids = |page: DisputePage| -> Vec<String> { page.disputes.into_iter().map(|d| d.escrow.id).collect() };
+        assert_eq!(dispute_ids(disputes("open", 10, None).await.data.unwrap()), vec![created[0].clone()]);
+        assert_eq!(dispute_ids(disputes("resolved", 10, None).await.data.unwrap()), vec![created[1].clone()]);
+
+        // Oldest dispute first, a page at a time
+        let first = disputes("all", 1, None).await.data.unwrap();
+        assert_eq!(first.next_cursor.as_deref(), Some(created[1].as_str()));
+        let second = disputes("all", 1, first.next_cursor.clone()).await.data.unwrap();
+        assert_eq!(second.next_cursor, None);
+        assert_eq!([dispute_ids(first), dispute_ids(second)].concat(), vec![created[1].clone(), created[0].clone()]);
+
+        // Only a disputed escrow continues the queue
+        assert!(!disputes("all", 1, Some(created[2].clone())).await.success);
+        assert!(!disputes("pending", 10, None).await.success);
+    }
+
+    #[tokio::test]
+    async fn test_escrow_requires_funding_value() {
+        // Rejected before anything is looked up, so the database is never reached
+        let state = test_state(DbPool::connect_lazy("postgres://localhost/unused").unwrap());
+        let err = build_escrow(&state, CreateEscrowRequest { funding_utxo_value: None, ..create_request() })
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        assert_eq!(err.message, "funding_utxo_value is required");
+    }
+
+    #[tokio::test]
+    async fn test_escrow_expiry_must_be_ahead_of_the_tip() {
+        // Rejected against the mock chain's tip before the database is reached
+        let state = test_state(DbPool::connect_lazy("postgres://localhost/unused").unwrap());
+        let tip = state.tip.height().await.unwrap();
+        for expiry_height in [tip - 1, tip] {
+            let err = build_escrow(&state, CreateEscrowRequest { expiry_height, ..create_request() })
+                .await
+                .unwrap_err();
+            assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        }
+    }
+}
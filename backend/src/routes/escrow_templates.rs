@@ -0,0 +1,418 @@
+//! Escrow template (preset) routes
+//!
+//! Templates fix an escrow's type, arbiter, relative expiry, and marketplace
+//! fee split so integrators can create standardized escrows in one call.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use bitcoin::OutPoint;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::db::{self, EscrowTemplateRecord};
+use crate::routes::error::ApiError;
+use crate::routes::escrow::{
+    build_escrow, save_escrow, CreateEscrowRequest, EscrowFee, EscrowResponse, EscrowSpellResponse,
+    EscrowState, EscrowType, MilestoneSpec,
+};
+use crate::routes::wallet_formats::WalletFormat;
+use crate::services::chain_tip::{self, MAX_EXPIRY_BLOCKS};
+
+/// Basis-point denominator
+const BPS: u64 = 10_000;
+
+/// Escrow template
+#[derive(Debug, Clone, Serialize)]
+pub struct EscrowTemplate {
+    pub id: String,
+    pub name: String,
+    pub escrow_type: EscrowType,
+    pub arbiter_pubkey: Option<String>,
+    /// Escrows expire this many blocks after creation
+    pub expiry_blocks: u64,
+    /// Marketplace fee in basis points of the escrowed amount
+    pub fee_bps: u32,
+    pub fee_recipient: Option<String>,
+    /// Share of the fee paid by the depositor, in basis points (rest by recipient)
+    pub depositor_fee_share_bps: u32,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl TryFrom<EscrowTemplateRecord> for EscrowTemplate {
+    type Error = String;
+
+    fn try_from(record: EscrowTemplateRecord) -> Result<Self, Self::Error> {
+        let escrow_type = EscrowType::from_u8(record.escrow_type as u8)
+            .ok_or_else(|| format!("Unknown escrow type {}", record.escrow_type))?;
+
+        Ok(Self {
+            id: record.id,
+            name: record.name,
+            escrow_type,
+            arbiter_pubkey: record.arbiter_pubkey,
+            expiry_blocks: record.expiry_blocks as u64,
+            fee_bps: record.fee_bps as u32,
+            fee_recipient: record.fee_recipient,
+            depositor_fee_share_bps: record.depositor_fee_share_bps as u32,
+            created_at: record.created_at.to_rfc3339(),
+            updated_at: record.updated_at.to_rfc3339(),
+        })
+    }
+}
+
+/// Create or update template request
+#[derive(Debug, Deserialize)]
+pub struct TemplateRequest {
+    pub name: String,
+    pub escrow_type: EscrowType,
+    pub arbiter_pubkey: Option<String>,
+    pub expiry_blocks: u64,
+    #[serde(default)]
+    pub fee_bps: u32,
+    pub fee_recipient: Option<String>,
+    #[serde(default = "default_depositor_fee_share")]
+    pub depositor_fee_share_bps: u32,
+}
+
+fn default_depositor_fee_share() -> u32 {
+    5000
+}
+
+impl TemplateRequest {
+    fn validate(&self) -> Result<(), &'static str> {
+        if self.name.trim().is_empty() {
+            return Err("Template name is required");
+        }
+        if self.escrow_type == EscrowType::TwoOfThree && self.arbiter_pubkey.is_none() {
+            return Err("2-of-3 templates require an arbiter pubkey");
+        }
+        if self.expiry_blocks == 0 || self.expiry_blocks > MAX_EXPIRY_BLOCKS {
+            return Err("expiry_blocks must be positive and at most a year of blocks");
+        }
+        if self.fee_bps as u64 > BPS || self.depositor_fee_share_bps as u64 > BPS {
+            return Err("Basis-point values must be at most 10000");
+        }
+        if self.fee_bps > 0 && self.fee_recipient.is_none() {
+            return Err("A fee recipient is required when fee_bps is set");
+        }
+        Ok(())
+    }
+
+    fn into_record(self, id: String, now: chrono::DateTime<chrono::Utc>) -> EscrowTemplateRecord {
+        EscrowTemplateRecord {
+            id,
+            name: self.name.trim().to_string(),
+            escrow_type: self.escrow_type.as_u8() as i16,
+            arbiter_pubkey: self.arbiter_pubkey,
+            expiry_blocks: self.expiry_blocks as i64,
+            fee_bps: self.fee_bps as i32,
+            fee_recipient: self.fee_recipient,
+            depositor_fee_share_bps: self.depositor_fee_share_bps as i32,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// Instantiate an escrow from a template
+#[derive(Debug, Deserialize)]
+pub struct InstantiateTemplateRequest {
+    pub depositor_pubkey: String,
+    pub recipient_pubkey: String,
+    pub token_id: String,
+    pub amount: u64,
+    pub release_hash: Option<String>,
+    pub order_id: Option<String>,
+    pub funding_utxo: OutPoint,
+    #[serde(default)]
+    pub funding_utxo_value: Option<u64>,
+    pub depositor_address: Option<String>,
+    #[serde(default)]
+    pub milestones: Vec<MilestoneSpec>,
+}
+
+/// List all templates
+pub async fn list_templates(
+    State(state): State<Arc<EscrowState>>,
+) -> Result<Json<EscrowResponse<Vec<EscrowTemplate>>>, StatusCode> {
+    let records = db::get_escrow_templates(&state.db).await.map_err(internal)?;
+    let templates = records
+        .into_iter()
+        .filter_map(|r| EscrowTemplate::try_from(r).ok())
+        .collect();
+
+    Ok(Json(EscrowResponse::success(templates)))
+}
+
+/// Get a template by ID
+pub async fn get_template(
+    State(state): State<Arc<EscrowState>>,
+    Path(id): Path<String>,
+) -> Result<Json<EscrowResponse<EscrowTemplate>>, StatusCode> {
+    Ok(Json(match load_template(&state, &id).await? {
+        Some(template) => EscrowResponse::success(template),
+        None => EscrowResponse::error("Template not found"),
+    }))
+}
+
+/// Create a template
+pub async fn create_template(
+    State(state): State<Arc<EscrowState>>,
+    Json(req): Json<TemplateRequest>,
+) -> Result<Json<EscrowResponse<EscrowTemplate>>, StatusCode> {
+    if let Err(e) = req.validate() {
+        return Ok(Json(EscrowResponse::error(e)));
+    }
+
+    let record = req.into_record(Uuid::new_v4().to_string(), chrono::Utc::now());
+    db::insert_escrow_template(&state.db, &record).await.map_err(internal)?;
+    tracing::info!("Escrow template {} created", record.id);
+
+    Ok(Json(match EscrowTemplate::try_from(record) {
+        Ok(template) => EscrowResponse::success(template),
+        Err(e) => EscrowResponse::error(e),
+    }))
+}
+
+/// Replace a template's settings
+pub async fn update_template(
+    State(state): State<Arc<EscrowState>>,
+    Path(id): Path<String>,
+    Json(req): Json<TemplateRequest>,
+) -> Result<Json<EscrowResponse<EscrowTemplate>>, StatusCode> {
+    if let Err(e) = req.validate() {
+        return Ok(Json(EscrowResponse::error(e)));
+    }
+
+    let record = req.into_record(id.clone(), chrono::Utc::now());
+    if !db::update_escrow_template(&state.db, &record).await.map_err(internal)? {
+        return Ok(Json(EscrowResponse::error("Template not found")));
+    }
+
+    Ok(Json(match load_template(&state, &id).await? {
+        Some(template) => EscrowResponse::success(template),
+        None => EscrowResponse::error("Template not found"),
+    }))
+}
+
+/// Delete a template
+pub async fn delete_template(
+    State(state): State<Arc<EscrowState>>,
+    Path(id): Path<String>,
+) -> Result<Json<EscrowResponse<String>>, StatusCode> {
+    Ok(Json(if db::delete_escrow_template(&state.db, &id).await.map_err(internal)? {
+        EscrowResponse::success(id)
+    } else {
+        EscrowResponse::error("Template not found")
+    }))
+}
+
+/// Create an escrow from a template in one call
+pub async fn instantiate_template(
+    State(state): State<Arc<EscrowState>>,
+    Path(id): Path<String>,
+    wallet: WalletFormat,
+    Json(req): Json<InstantiateTemplateRequest>,
+) -> Result<Json<EscrowResponse<EscrowSpellResponse>>, ApiError> {
+    let template = match load_template(&state, &id).await.map_err(|_| ApiError::internal("Failed to load escrow template"))? {
+        Some(template) => template,
+        None => return Ok(Json(EscrowResponse::error("Template not found"))),
+    };
+
+    let current_height = match state.tip.height().await {
+        Ok(height) => height,
+        Err(e) => {
+            tracing::error!("Refusing to instantiate escrow template {}: {}", id, e);
+            return Ok(Json(EscrowResponse::error(format!("Cannot set escrow expiry: {}", e))));
+        }
+    };
+    let expiry_height = match chain_tip::expiry_height(current_height, template.expiry_blocks) {
+        Ok(height) => height,
+        Err(e) => return Ok(Json(EscrowResponse::error(format!("Cannot set escrow expiry: {}", e)))),
+    };
+
+    let mut response = build_escrow(&state, CreateEscrowRequest {
+        depositor_pubkey: req.depositor_pubkey,
+        recipient_pubkey: req.recipient_pubkey,
+        arbiter_pubkey: template.arbiter_pubkey.clone(),
+        escrow_type: template.escrow_type,
+        token_id: req.token_id,
+        amount: req.amount,
+        release_hash: req.release_hash,
+        expiry_height,
+        order_id: req.order_id,
+        funding_utxo: Some(req.funding_utxo),
+        funding_utxo_value: req.funding_utxo_value,
+        depositor_address: req.depositor_address,
+        milestones: req.milestones,
+    }).await?;
+
+    let data = match response.data.as_mut() {
+        Some(data) => data,
+        None => return Ok(Json(response)),
+    };
+
+    let fee = template.fee_recipient.as_ref().map(|recipient| {
+        let (amount, depositor_share, recipient_share) =
+            fee_split(req.amount, template.fee_bps, template.depositor_fee_share_bps);
+        EscrowFee {
+            fee_bps: template.fee_bps,
+            amount,
+            recipient: recipient.clone(),
+            depositor_share,
+            recipient_share,
+        }
+    });
+
+    data.escrow.template_id = Some(template.id);
+    data.escrow.fee = fee;
+    save_escrow(&state, &data.escrow).await?;
+
+    Ok(Json(response.for_wallet(wallet)))
+}
+
+/// Split a fee on `amount` into (total, depositor share, recipient share)
+fn fee_split(amount: u64, fee_bps: u32, depositor_share_bps: u32) -> (u64, u64, u64) {
+    let fee = (amount as u128 * fee_bps as u128 / BPS as u128) as u64;
+    let depositor_share = fee * depositor_share_bps as u64 / BPS;
+    (fee, depositor_share, fee - depositor_share)
+}
+
+async fn load_template(state: &EscrowState, id: &str) -> Result<Option<EscrowTemplate>, StatusCode> {
+    let record = db::get_escrow_template(&state.db, id).await.map_err(internal)?;
+    Ok(record.and_then(|r| EscrowTemplate::try_from(r).ok()))
+}
+
+fn internal(e: anyhow::Error) -> StatusCode {
+    tracing::error!("Escrow template query failed: {}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::hashes::Hash;
+    use bitcoin::secp256k1::{Keypair, Secp256k1, SecretKey};
+    use bitcoin::Network;
+
+    use crate::routes::escrow::{load_escrow, testing::test_state};
+
+    const ADDRESS: &str = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+
+    fn xonly(byte: u8) -> String {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[byte; 32]).unwrap());
+        hex::encode(keypair.x_only_public_key().0.serialize())
+    }
+
+    /// Two-party template charging 2.5%, split evenly
+    fn template_request(name: &str) -> TemplateRequest {
+        TemplateRequest {
+            name: name.to_string(),
+            escrow_type: EscrowType::TwoParty,
+            arbiter_pubkey: None,
+            expiry_blocks: 144,
+            fee_bps: 250,
+            fee_recipient: Some(ADDRESS.to_string()),
+            depositor_fee_share_bps: 5000,
+        }
+    }
+
+    fn instantiate_request() -> InstantiateTemplateRequest {
+        InstantiateTemplateRequest {
+            depositor_pubkey: xonly(1),
+            recipient_pubkey: xonly(2),
+            token_id: "TOAD".to_string(),
+            amount: 10_000,
+            release_hash: None,
+            order_id: None,
+            funding_utxo: OutPoint::new(bitcoin::Txid::from_byte_array([3; 32]), 0),
+            funding_utxo_value: Some(20_000),
+            depositor_address: Some(ADDRESS.to_string()),
+            milestones: vec![],
+        }
+    }
+
+    #[test]
+    fn test_fee_split() {
+        assert_eq!(fee_split(10_000, 250, 5000), (250, 125, 125));
+        // The recipient takes the odd unit
+        assert_eq!(fee_split(10_000, 251, 5000), (251, 125, 126));
+        assert_eq!(fee_split(10_000, 250, 10_000), (250, 250, 0));
+        assert_eq!(fee_split(u64::MAX, 10_000, 0), (u64::MAX, 0, u64::MAX));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_templates_are_stored_and_instantiated() {
+        let db = db::testing::fresh_db().await;
+        let state = Arc::new(test_state(db));
+
+        // An invalid template is refused and nothing is stored
+        let mut invalid = template_request("arbitrated");
+        invalid.escrow_type = EscrowType::TwoOfThree;
+        let refused = create_template(State(state.clone()), Json(invalid)).await.unwrap().0;
+        assert_eq!(refused.error.as_deref(), Some("2-of-3 templates require an arbiter pubkey"));
+        assert!(list_templates(State(state.clone())).await.unwrap().0.data.unwrap().is_empty());
+
+        let created = create_template(State(state.clone()), Json(template_request(" freelance ")))
+            .await
+            .unwrap()
+            .0
+            .data
+            .unwrap();
+        assert_eq!(created.name, "freelance");
+        let id = created.id.clone();
+        let stored = get_template(State(state.clone()), Path(id.clone())).await.unwrap().0.data.unwrap();
+
+        let mut changed = template_request("freelance");
+        changed.expiry_blocks = 1008;
+        let updated = update_template(State(state.clone()), Path(id.clone()), Json(changed))
+            .await
+            .unwrap()
+            .0
+            .data
+            .unwrap();
+        assert_eq!(updated.expiry_blocks, 1008);
+        assert_eq!(updated.created_at, stored.created_at);
+        let fetched = get_template(State(state.clone()), Path(id.clone())).await.unwrap().0.data.unwrap();
+        assert_eq!(fetched.expiry_blocks, 1008);
+        let missing = update_template(State(state.clone()), Path("missing".to_string()), Json(template_request("x")))
+            .await
+            .unwrap()
+            .0;
+        assert_eq!(missing.error.as_deref(), Some("Template not found"));
+
+        // An instance takes the template's expiry and fee split, and is
+        // stored with them
+        let wallet = || WalletFormat::raw(Network::Testnet4);
+        let request = Json(instantiate_request());
+        let instance = instantiate_template(State(state.clone()), Path(id.clone()), wallet(), request).await.unwrap().0;
+        assert!(instance.success, "{:?}", instance.error);
+        let escrow = instance.data.unwrap().escrow;
+        let tip = state.tip.height().await.unwrap();
+        assert_eq!(escrow.expiry_height, tip + 1008);
+        let stored = load_escrow(&state, &escrow.id).await.unwrap();
+        assert_eq!(stored.template_id.as_deref(), Some(id.as_str()));
+        let fee = stored.fee.unwrap();
+        assert_eq!((fee.fee_bps, fee.amount, fee.depositor_share, fee.recipient_share), (250, 250, 125, 125));
+        assert_eq!(fee.recipient, ADDRESS);
+
+        let missing = Path("missing".to_string());
+        let unknown = instantiate_template(State(state.clone()), missing, wallet(), Json(instantiate_request()))
+            .await
+            .unwrap()
+            .0;
+        assert_eq!(unknown.error.as_deref(), Some("Template not found"));
+
+        assert!(delete_template(State(state.clone()), Path(id.clone())).await.unwrap().0.success);
+        let gone = delete_template(State(state.clone()), Path(id.clone())).await.unwrap().0;
+        assert_eq!(gone.error.as_deref(), Some("Template not found"));
+        assert!(get_template(State(state), Path(id)).await.unwrap().0.data.is_none());
+    }
+}
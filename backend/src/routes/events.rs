@@ -0,0 +1,77 @@
+//! Event stream endpoints, and the recorded status history of orders,
+//! escrows and transactions
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    response::Response,
+    Json,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::db::{self, StateEventRecord, Subject};
+use crate::routes::auth::AdminToken;
+use crate::routes::error::ApiError;
+use crate::routes::orders::AppState;
+
+/// Event stream filters
+#[derive(Debug, Deserialize)]
+pub struct EventsQuery {
+    /// Only events about this order, escrow or address
+    pub subject: Option<String>,
+}
+
+/// Stream events to a WebSocket client as JSON messages
+pub async fn events_ws(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<EventsQuery>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let receiver = state.events.subscribe();
+    ws.on_upgrade(move |socket| stream_events(socket, receiver, query.subject))
+}
+
+/// Every status change of an order, escrow or transaction, oldest first (admin)
+pub async fn get_state_events(
+    _admin: AdminToken,
+    State(state): State<Arc<AppState>>,
+    Path((subject_type, subject_id)): Path<(String, String)>,
+) -> Result<Json<Vec<StateEventRecord>>, ApiError> {
+    let subject = Subject::parse(&subject_type).ok_or_else(|| {
+        ApiError::bad_request(format!("Unknown subject type {}; use order, escrow or transaction", subject_type))
+    })?;
+    Ok(Json(db::get_state_events(&state.db, subject, &subject_id).await?))
+}
+
+async fn stream_events(
+    mut socket: WebSocket,
+    mut receiver: tokio::sync::broadcast::Receiver<crate::services::events::Event>,
+    subject: Option<String>,
+) {
+    loop {
+        let event = match receiver.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(skipped)) => {
+                tracing::warn!("WebSocket subscriber lagged, skipped {} events", skipped);
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+        if subject.as_ref().is_some_and(|s| *s != event.subject_id) {
+            continue;
+        }
+
+        let json = match serde_json::to_string(&event) {
+            Ok(json) => json,
+            Err(_) => continue,
+        };
+
+        if socket.send(Message::Text(json)).await.is_err() {
+            break;
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Fee estimation endpoints
+
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::routes::error::ApiError;
+use crate::routes::orders::AppState;
+use crate::services::fees::{
+    fee_for, FeeEstimates, FeeSource, FeeTier, COMMIT_TX_VBYTES, CREATE_ORDER_SPELL_VBYTES,
+    FILL_ORDER_SPELL_VBYTES,
+};
+
+/// Longest confirmation target the node estimates for
+const MAX_TARGET_BLOCKS: u16 = 1008;
+/// Standardness limit on transaction size
+const MAX_VBYTES: u64 = 100_000;
+
+/// Get current fee rate tiers (sat/vB)
+pub async fn get_fee_estimates(State(state): State<Arc<AppState>>) -> Json<FeeEstimates> {
+    Json(state.fees.estimate(state.chain.as_ref()).await)
+}
+
+/// Wallet fee estimate query
+#[derive(Debug, Deserialize)]
+pub struct WalletFeeQuery {
+    /// Size of a custom transaction to price, in vbytes
+    pub vbytes: Option<u64>,
+    /// Confirmation target in blocks (default: the normal tier's)
+    pub target: Option<u16>,
+}
+
+/// All-in cost of a commit + spell transaction pair
+#[derive(Debug, Serialize)]
+pub struct SpellPairCost {
+    pub commit_vbytes: u64,
+    pub spell_vbytes: u64,
+    pub fee_sats: u64,
+}
+
+impl SpellPairCost {
+    fn new(rate: f64, spell_vbytes: u64) -> Self {
+        Self {
+            commit_vbytes: COMMIT_TX_VBYTES,
+            spell_vbytes,
+            fee_sats: fee_for(rate, COMMIT_TX_VBYTES + spell_vbytes),
+        }
+    }
+}
+
+/// Fee estimate for wallet actions at one confirmation target
+#[derive(Debug, Serialize)]
+pub struct WalletFeeEstimate {
+    pub target_blocks: u16,
+    /// sat/vB
+    pub fee_rate: f64,
+    pub source: FeeSource,
+    pub create_order: SpellPairCost,
+    pub fill_order: SpellPairCost,
+    /// Fee for the requested `vbytes`, when given
+    pub custom_fee_sats: Option<u64>,
+}
+
+/// Price order creation and filling (commit + spell transactions) and an
+/// optional custom transaction size at a confirmation target
+pub async fn get_wallet_fee_estimate(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<WalletFeeQuery>,
+) -> Result<Json<WalletFeeEstimate>, ApiError> {
+    let target = query.target.unwrap_or(FeeTier::Normal.target_blocks());
+    if !(1..=MAX_TARGET_BLOCKS).contains(&target) {
+        return Err(ApiError::bad_request(format!("target must be between 1 and {} blocks", MAX_TARGET_BLOCKS)));
+    }
+    if query.vbytes.is_some_and(|v| v == 0 || v > MAX_VBYTES) {
+        return Err(ApiError::bad_request(format!("vbytes must be between 1 and {}", MAX_VBYTES)));
+    }
+
+    let (fee_rate, source) = state.fees.rate_for_target(state.chain.as_ref(), target).await;
+
+    Ok(Json(WalletFeeEstimate {
+        target_blocks: target,
+        fee_rate,
+        source,
+        create_order: SpellPairCost::new(fee_rate, CREATE_ORDER_SPELL_VBYTES),
+        fill_order: SpellPairCost::new(fee_rate, FILL_ORDER_SPELL_VBYTES),
+        custom_fee_sats: query.vbytes.map(|v| fee_for(fee_rate, v)),
+    }))
+}
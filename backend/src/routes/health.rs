@@ -1,39 +1,78 @@
 //! Health check endpoints
 
-use axum::Json;
+use axum::{extract::State, Json};
+use futures_util::future::join_all;
 use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
 use std::time::Instant;
 
+use crate::routes::orders::AppState;
+use crate::services::bitcoin::RpcMethodStats;
+use crate::services::chain::ChainKind;
+use crate::services::chain_tip::TipStatus;
+use crate::services::network;
+use crate::services::prover::ProverKind;
+use crate::services::prover_pool::EndpointStats;
+use crate::services::rpc_nodes::NodeHealth;
+
 #[derive(Serialize)]
 pub struct HealthResponse {
     pub status: String,
     pub version: String,
     pub timestamp: String,
     pub prover_api: ProverApiHealth,
+    /// Backend spells are proved with
+    pub prover: ProverKind,
     pub mock_mode: bool,
+    /// Bitcoin network the server runs on
+    pub network: &'static str,
+    /// Where chain state comes from
+    pub chain: ChainKind,
+    /// Best-block height expiries are set from
+    pub chain_tip: TipStatus,
 }
 
-/// Prover API health status
+/// Prover API health status; the top-level fields describe the endpoint
+/// prove requests are currently routed to
 #[derive(Serialize)]
 pub struct ProverApiHealth {
     pub url: String,
     pub reachable: bool,
     pub latency_ms: Option<u64>,
     pub error: Option<String>,
+    /// Every configured hosted endpoint, healthiest first
+    pub endpoints: Vec<ProverEndpointHealth>,
+    /// Local charms CLI proving takes over with when every endpoint is down
+    pub fallback: Option<LocalProverHealth>,
 }
 
-/// Overall system health check endpoint
-pub async fn health_check() -> Json<HealthResponse> {
-    let mock_mode = std::env::var("MOCK_MODE")
-        .map(|v| v == "true")
-        .unwrap_or(true);
+/// Whether a local charms binary can be run
+#[derive(Serialize)]
+pub struct LocalProverHealth {
+    pub url: String,
+    pub reachable: bool,
+    pub version: Option<String>,
+    pub error: Option<String>,
+}
 
-    let api_url = std::env::var("CHARMS_PROVE_API_URL")
-        .unwrap_or_else(|_| "https://v8.charms.dev/spells/prove".to_string());
+/// Reachability and prove statistics of one prover endpoint
+#[derive(Serialize)]
+pub struct ProverEndpointHealth {
+    pub url: String,
+    pub reachable: bool,
+    pub latency_ms: Option<u64>,
+    pub error: Option<String>,
+    pub stats: EndpointStats,
+}
 
-    let prover_health = check_prover_api_internal(&api_url).await;
+/// Overall system health check endpoint
+pub async fn health_check(State(state): State<Arc<AppState>>) -> Json<HealthResponse> {
+    let prover = state.charms.prover().kind();
+    let prover_health = check_prover_endpoints(&state).await;
 
-    let status = if prover_health.reachable || mock_mode {
+    let chain_tip = state.tip.status();
+    let status = if prover_health.reachable && !chain_tip.stale {
         "healthy"
     } else {
         "degraded"
@@ -44,20 +83,102 @@ pub async fn health_check() -> Json<HealthResponse> {
         version: env!("CARGO_PKG_VERSION").to_string(),
         timestamp: chrono::Utc::now().to_rfc3339(),
         prover_api: prover_health,
-        mock_mode,
+        prover,
+        mock_mode: prover == ProverKind::Mock,
+        network: network::name(state.charms.network()),
+        chain: state.chain.kind(),
+        chain_tip,
     })
 }
 
 /// Check only the Prover API status
-pub async fn check_prover_api() -> Json<ProverApiHealth> {
-    let api_url = std::env::var("CHARMS_PROVE_API_URL")
-        .unwrap_or_else(|_| "https://v8.charms.dev/spells/prove".to_string());
+pub async fn check_prover_api(State(state): State<Arc<AppState>>) -> Json<ProverApiHealth> {
+    Json(check_prover_endpoints(&state).await)
+}
+
+/// Bitcoin node RPC calls made since startup, by method
+pub async fn rpc_metrics(State(state): State<Arc<AppState>>) -> Json<BTreeMap<String, RpcMethodStats>> {
+    Json(state.bitcoin.metrics())
+}
+
+/// Reachability and height of each configured Bitcoin node, in the order
+/// calls try them
+pub async fn rpc_nodes(State(state): State<Arc<AppState>>) -> Json<Vec<NodeHealth>> {
+    Json(state.bitcoin.node_health())
+}
+
+/// Probe every prover endpoint and merge in its prove statistics; a local
+/// prover is checked by running its binary, and the mock prover is always up
+async fn check_prover_endpoints(state: &AppState) -> ProverApiHealth {
+    let prover = state.charms.prover();
+    if prover.kind() == ProverKind::Mock {
+        return ProverApiHealth {
+            url: "mock".to_string(),
+            reachable: true,
+            latency_ms: None,
+            error: None,
+            endpoints: Vec::new(),
+            fallback: None,
+        };
+    }
+    if let (ProverKind::Local, Some(local)) = (prover.kind(), prover.local()) {
+        let started = Instant::now();
+        let version = local.version().await;
+        return ProverApiHealth {
+            url: local.url(),
+            reachable: version.is_ok(),
+            latency_ms: version.is_ok().then(|| started.elapsed().as_millis() as u64),
+            error: version.err().map(|e| e.to_string()),
+            endpoints: Vec::new(),
+            fallback: None,
+        };
+    }
+
+    let endpoints = prover.endpoints();
+    let probes = join_all(endpoints.iter().map(|(url, _)| check_prover_api_internal(url))).await;
+    let fallback = match prover.local() {
+        Some(local) => {
+            let version = local.version().await;
+            Some(LocalProverHealth {
+                url: local.url(),
+                reachable: version.is_ok(),
+                error: version.as_ref().err().map(|e| e.to_string()),
+                version: version.ok(),
+            })
+        }
+        None => None,
+    };
 
-    Json(check_prover_api_internal(&api_url).await)
+    let endpoints: Vec<ProverEndpointHealth> = endpoints
+        .into_iter()
+        .zip(probes)
+        .map(|((url, stats), probe)| ProverEndpointHealth {
+            url,
+            reachable: probe.reachable,
+            latency_ms: probe.latency_ms,
+            error: probe.error,
+            stats,
+        })
+        .collect();
+
+    // The ranking already prefers healthy endpoints; skip unreachable ones
+    let preferred = endpoints
+        .iter()
+        .find(|e| e.reachable && e.stats.circuit != "open")
+        .or(endpoints.first());
+
+    ProverApiHealth {
+        url: preferred.map(|e| e.url.clone()).unwrap_or_default(),
+        reachable: preferred.is_some_and(|e| e.reachable),
+        latency_ms: preferred.and_then(|e| e.latency_ms),
+        error: preferred.and_then(|e| e.error.clone()),
+        endpoints,
+        fallback,
+    }
 }
 
 /// Internal function to check Prover API reachability
-async fn check_prover_api_internal(api_url: &str) -> ProverApiHealth {
+async fn check_prover_api_internal(api_url: &str) -> Probe {
     let start = Instant::now();
 
     tracing::debug!("🏥 Health check: Testing Prover API at {}", api_url);
@@ -70,8 +191,7 @@ async fn check_prover_api_internal(api_url: &str) -> ProverApiHealth {
         Ok(c) => c,
         Err(e) => {
             tracing::error!("❌ Failed to build HTTP client for health check: {}", e);
-            return ProverApiHealth {
-                url: api_url.to_string(),
+            return Probe {
                 reachable: false,
                 latency_ms: None,
                 error: Some(format!("Client build error: {}", e)),
@@ -87,8 +207,7 @@ async fn check_prover_api_internal(api_url: &str) -> ProverApiHealth {
 
             tracing::debug!("✅ Prover API reachable (status: {}, latency: {}ms)", status, latency);
 
-            ProverApiHealth {
-                url: api_url.to_string(),
+            Probe {
                 reachable: true,
                 latency_ms: Some(latency),
                 error: None,
@@ -97,8 +216,7 @@ async fn check_prover_api_internal(api_url: &str) -> ProverApiHealth {
         Err(e) => {
             tracing::warn!("⚠️  Prover API unreachable: {}", e);
 
-            ProverApiHealth {
-                url: api_url.to_string(),
+            Probe {
                 reachable: false,
                 latency_ms: None,
                 error: Some(e.to_string()),
@@ -107,3 +225,9 @@ async fn check_prover_api_internal(api_url: &str) -> ProverApiHealth {
     }
 }
 
+
+struct Probe {
+    reachable: bool,
+    latency_ms: Option<u64>,
+    error: Option<String>,
+}
@@ -0,0 +1,632 @@
+//! Off-chain order intent endpoints
+//!
+//! Makers post orders signed with their key without putting them on-chain.
+//! When a taker commits, a single spell swaps the maker's and taker's tokens,
+//! creating and filling the order in one transaction.
+//!
+//! A committed intent is settling until its transaction is broadcast. One
+//! still settling after `INTENT_SETTLE_TIMEOUT_SECS` (default 15 minutes)
+//! has its order cancelled and its UTXOs released, and is open again.
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use bitcoin::OutPoint;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::db::{self, OrderIntentRecord, OrderRecord};
+use crate::routes::error::ApiError;
+use crate::routes::network_json::{BitcoinAddresses, NetworkJson};
+use crate::routes::orders::{
+    chain_to_id, normalize_chain, parse_amount, prove_order_spell, release_order_locks, signing_payloads,
+    verify_party, AppState, InputToSign, Order, SigningInstructions, SpellData, UnsignedTransaction,
+    DEFAULT_TOKEN_ID, DEFAULT_TOKEN_VK,
+};
+use crate::routes::spell_templates::{record_template_use, spell_template, TRANSFER_TOKEN};
+use crate::routes::wallet::{lock_funding_utxo, parse_outpoint};
+use crate::routes::wallet_formats::WalletFormat;
+use crate::services::charms::{FillSpellData, OrderSpellData};
+use crate::services::addresses::key_controls_address;
+use crate::services::signatures::{canonical_signature, verify_signature};
+
+/// Create intent request
+#[derive(Debug, Deserialize)]
+pub struct CreateIntentRequest {
+    pub maker_address: String,
+    pub maker_pubkey: String,
+    pub offer_token: String,
+    pub offer_amount: String,
+    pub want_token: String,
+    pub want_amount: String,
+    pub source_chain: String,
+    pub dest_chain: String,
+    /// UTXO holding the offered tokens
+    pub maker_utxo: OutPoint,
+    /// Unix timestamp after which the intent can no longer be filled
+    pub expires_at: i64,
+    /// Maker's signature over `intent_message`
+    pub signature: String,
+}
+
+impl BitcoinAddresses for CreateIntentRequest {
+    fn bitcoin_addresses(&self) -> Vec<(&'static str, &str)> {
+        vec![("maker_address", self.maker_address.as_str())]
+    }
+}
+
+/// Fill intent request (taker commits)
+#[derive(Debug, Deserialize)]
+pub struct FillIntentRequest {
+    pub taker_address: String,
+    #[serde(default)]
+    pub taker_pubkey: Option<String>,
+    pub taker_utxo: OutPoint,
+    #[serde(default)]
+    pub taker_utxo_value: Option<u64>,
+    /// Taker's signature over `intent_fill_message`: hex by `taker_pubkey`,
+    /// or BIP-322 by `taker_address` when no pubkey is given
+    pub signature: String,
+}
+
+impl BitcoinAddresses for FillIntentRequest {
+    fn bitcoin_addresses(&self) -> Vec<(&'static str, &str)> {
+        vec![("taker_address", self.taker_address.as_str())]
+    }
+}
+
+/// Fill intent response
+#[derive(Debug, Serialize)]
+pub struct FillIntentResponse {
+    pub order: Order,
+    pub spell: SpellData,
+    pub unsigned_txs: Vec<UnsignedTransaction>,
+    pub signing_instructions: SigningInstructions,
+}
+
+/// Canonical message a maker signs to authorize an intent: every term the
+/// intent is stored with, chains as they are stored
+pub fn intent_message(req: &CreateIntentRequest) -> String {
+    format!(
+        "liquid-nation:intent:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}",
+        req.maker_address,
+        req.maker_pubkey,
+        req.offer_token,
+        req.offer_amount,
+        req.want_token,
+        req.want_amount,
+        normalize_chain(&req.source_chain),
+        normalize_chain(&req.dest_chain),
+        req.maker_utxo,
+        req.expires_at,
+    )
+}
+
+/// Canonical message a taker signs to commit to an intent
+pub fn intent_fill_message(intent_id: &str, req: &FillIntentRequest) -> String {
+    format!("liquid-nation:intent:fill:{}:{}:{}", intent_id, req.taker_address, req.taker_utxo)
+}
+
+/// Check the maker signed the intent's terms with `maker_pubkey`, a key
+/// `maker_address` pays to
+pub fn verify_intent(req: &CreateIntentRequest) -> Result<(), ApiError> {
+    if req.signature.is_empty() {
+        return Err(ApiError::bad_request("Intent must be signed by the maker"));
+    }
+    if !key_controls_address(&req.maker_pubkey, &req.maker_address) {
+        return Err(ApiError::forbidden("maker_pubkey is not the key of maker_address"));
+    }
+    verify_signature(&req.maker_pubkey, &intent_message(req), &req.signature)
+        .map_err(|e| ApiError::forbidden(format!("Invalid intent signature: {}", e)))
+}
+
+/// Post a new signed order intent
+pub async fn create_intent(
+    State(state): State<Arc<AppState>>,
+    NetworkJson(req): NetworkJson<CreateIntentRequest>,
+) -> Result<Json<OrderIntentRecord>, ApiError> {
+    verify_intent(&req)?;
+    // Stored canonically, so a re-encoded copy of a posted signature is
+    // recognized as the same intent
+    let signature = canonical_signature(&req.maker_pubkey, &req.signature)
+        .map_err(|e| ApiError::forbidden(format!("Invalid intent signature: {}", e)))?;
+
+    parse_amount("offer_amount", &req.offer_amount)?;
+    parse_amount("want_amount", &req.want_amount)?;
+
+    let now = chrono::Utc::now();
+    let expires_at = chrono::DateTime::from_timestamp(req.expires_at, 0)
+        .filter(|t| *t > now)
+        .ok_or_else(|| ApiError::bad_request("expires_at must be a future unix timestamp"))?;
+
+    let intent = OrderIntentRecord {
+        id: Uuid::new_v4().to_string(),
+        maker_address: req.maker_address,
+        maker_pubkey: req.maker_pubkey,
+        offer_token: req.offer_token,
+        offer_amount: req.offer_amount,
+        want_token: req.want_token,
+        want_amount: req.want_amount,
+        source_chain: normalize_chain(&req.source_chain),
+        dest_chain: normalize_chain(&req.dest_chain),
+        maker_utxo: req.maker_utxo.to_string(),
+        signature,
+        status: "open".to_string(),
+        order_id: None,
+        expires_at,
+        created_at: now,
+        updated_at: now,
+    };
+
+    if !db::insert_intent(&state.db, &intent).await? {
+        return Err(ApiError::conflict("This signed intent has already been posted"));
+    }
+    tracing::info!("Intent {} posted by {}", intent.id, intent.maker_address);
+
+    Ok(Json(intent))
+}
+
+/// List open intents
+pub async fn list_intents(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<OrderIntentRecord>>, ApiError> {
+    Ok(Json(db::get_open_intents(&state.db).await?))
+}
+
+/// Get an intent by ID
+pub async fn get_intent(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<OrderIntentRecord>, ApiError> {
+    let intent = db::get_intent_by_id(&state.db, &id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Intent not found"))?;
+
+    Ok(Json(intent))
+}
+
+/// Fill an intent: compose the maker's and taker's transfers into one spell. The
+/// intent is settling until its order's transaction is broadcast.
+pub async fn fill_intent(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    wallet: WalletFormat,
+    NetworkJson(req): NetworkJson<FillIntentRequest>,
+) -> Result<Json<FillIntentResponse>, ApiError> {
+    let taker_utxo_value = req.taker_utxo_value.ok_or_else(|| ApiError::missing("taker_utxo_value"))?;
+    if let Some(pubkey) = req.taker_pubkey.as_deref() {
+        if !key_controls_address(pubkey, &req.taker_address) {
+            return Err(ApiError::forbidden("taker_pubkey is not the key of taker_address"));
+        }
+    }
+    verify_party(req.taker_pubkey.as_deref(), &req.taker_address, &intent_fill_message(&id, &req), &req.signature)
+        .map_err(|e| ApiError::forbidden(format!("Invalid fill signature: {}", e)))?;
+    let intent = db::get_intent_by_id(&state.db, &id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Intent not found"))?;
+
+    let now = chrono::Utc::now();
+    if intent.status != "open" || intent.expires_at <= now {
+        return Err(ApiError::conflict("Intent is no longer open"));
+    }
+
+    let order_id = Uuid::new_v4().to_string();
+    let offer_amount = parse_amount("offer_amount", &intent.offer_amount)?;
+    let want_amount = parse_amount("want_amount", &intent.want_amount)?;
+
+    let order_spell_data = OrderSpellData {
+        maker_address: intent.maker_address.clone(),
+        maker_pubkey: intent.maker_pubkey.clone(),
+        offer_token_id: DEFAULT_TOKEN_ID.to_string(),
+        offer_token_vk: DEFAULT_TOKEN_VK.to_string(),
+        offer_amount: offer_amount.to_string(),
+        want_token_id: intent.want_token.to_lowercase(),
+        want_amount: want_amount.to_string(),
+        expiry_height: 0,
+        allow_partial: false,
+        funding_utxo: intent.maker_utxo.clone(),
+        escrow_address: "".to_string(),
+        dest_chain: chain_to_id(&intent.dest_chain),
+        dest_address: intent.maker_address.clone(),
+    };
+
+    let fill_spell_data = FillSpellData {
+        order_utxo: intent.maker_utxo.clone(),
+        taker_utxo: req.taker_utxo.to_string(),
+        taker_pubkey: req.taker_pubkey.clone().unwrap_or_else(|| req.taker_address.clone()),
+        taker_address: req.taker_address.clone(),
+        maker_address: intent.maker_address.clone(),
+        offer_amount: offer_amount.to_string(),
+        want_amount: want_amount.to_string(),
+    };
+
+    let template = spell_template(&state.db, TRANSFER_TOKEN).await;
+    let spell_built = state.charms.build_settle_intent_spell(
+        &template.body,
+        &fill_spell_data,
+        &order_spell_data,
+    )
+    .map_err(|e| ApiError::spell_build_failed(&e))?;
+    let app_binary = state.apps.app_binary(&spell_built).await.map_err(|e| ApiError::app_unavailable(&e))?;
+
+    // Claim both UTXOs so concurrent drafts cannot spend them too
+    lock_funding_utxo(&state.db, &parse_outpoint(&intent.maker_utxo)?, &intent.maker_address, Some(&order_id), None).await?;
+    lock_funding_utxo(&state.db, &req.taker_utxo, &req.taker_address, Some(&order_id), None).await?;
+
+    // The taker funds the settlement transaction
+    let proved_txs = prove_order_spell(
+        &state,
+        &template,
+        &spell_built,
+        &req.taker_utxo,
+        taker_utxo_value,
+        &req.taker_address,
+        &order_id,
+    )
+    .await?;
+
+    let record = OrderRecord {
+        id: order_id.clone(),
+        maker_address: intent.maker_address.clone(),
+        offer_token: intent.offer_token.clone(),
+        offer_amount,
+        want_token: intent.want_token.clone(),
+        want_amount,
+        source_chain: intent.source_chain.clone(),
+        dest_chain: intent.dest_chain.clone(),
+        status: "pendingsignature".to_string(),
+        allow_partial: false,
+        filled_amount: offer_amount,
+        expiry_height: None,
+        utxo_id: Some(intent.maker_utxo.clone()),
+        tx_id: None,
+        created_at: now,
+        updated_at: now,
+        version: 0,
+        archived_at: None,
+        maker_pubkey: Some(intent.maker_pubkey.clone()),
+        dest_address: Some(intent.maker_address.clone()),
+    };
+
+    db::settle_intent(&state.db, &id, &record)
+        .await
+        .map_err(|e| ApiError::conflict(e.to_string()))?;
+    tracing::info!("Intent {} settling as order {}", id, order_id);
+    record_template_use(&state.db, &order_id, &template).await;
+
+    // Maker's input (0) is authorized by the signed intent; both parties sign
+    let mut unsigned_txs = signing_payloads(
+        state.chain.as_ref(),
+        &state.sessions,
+        proved_txs,
+        vec![
+            InputToSign::new(0, &intent.maker_address),
+            InputToSign::new(1, &req.taker_address),
+        ],
+    )
+    .await;
+    wallet.apply(&mut unsigned_txs);
+
+    Ok(Json(FillIntentResponse {
+        order: Order::from(record),
+        spell: SpellData {
+            spell_yaml: template.body,
+            spell_yaml_built: spell_built,
+            app_binary,
+            prev_txs: vec![],
+        },
+        unsigned_txs,
+        signing_instructions: SigningInstructions {
+            message: "Sign to settle the maker's intent in a single transaction".to_string(),
+            steps: vec![
+                "1. Taker signs their input providing the wanted tokens".to_string(),
+                "2. Maker co-signs their input holding the offered tokens".to_string(),
+                "3. Submit the fully signed transaction to broadcast".to_string(),
+            ],
+            broadcast_endpoint: format!("/api/orders/{}/broadcast", order_id),
+        },
+    }))
+}
+
+/// Finish the settling intent an order was created from, if any: `settled`
+/// once its transaction is broadcast, `cancelled` if the maker drops the
+/// order before that
+pub(crate) async fn finish_intent(state: &AppState, order_id: &str, status: &str) {
+    let intent = match db::get_intent_by_order(&state.db, order_id).await {
+        Ok(Some(intent)) => intent,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::error!("Failed to load the intent of order {}: {}", order_id, e);
+            return;
+        }
+    };
+    match db::update_intent_status(&state.db, &intent.id, "settling", status).await {
+        Ok(true) => tracing::info!("Intent {} {}", intent.id, status),
+        Ok(false) => {}
+        Err(e) => tracing::error!("Failed to mark intent {} {}: {}", intent.id, status, e),
+    }
+}
+
+/// Reopen intents left settling past `INTENT_SETTLE_TIMEOUT_SECS`: their
+/// order was never signed and broadcast, so it is cancelled and the UTXOs
+/// it held are released
+pub fn spawn_intent_reaper(state: Arc<AppState>) {
+    let timeout_secs: i64 = std::env::var("INTENT_SETTLE_TIMEOUT_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(900);
+
+    let jobs = state.jobs.clone();
+    jobs.spawn_recurring("reopen_stalled_intents", std::time::Duration::from_secs(60), move || {
+        let state = state.clone();
+        async move {
+            let before = chrono::Utc::now() - chrono::Duration::seconds(timeout_secs);
+            for intent in db::get_stalled_intents(&state.db, before).await? {
+                reopen_intent(&state, &intent).await?;
+            }
+            Ok(())
+        }
+    });
+}
+
+/// Cancel a stalled intent's unsigned order and reopen the intent; an order
+/// that moved on meanwhile is left to finish the intent itself
+async fn reopen_intent(state: &AppState, intent: &OrderIntentRecord) -> anyhow::Result<()> {
+    if let Some(order_id) = &intent.order_id {
+        if let Some(order) = db::get_order_by_id(&state.db, order_id).await? {
+            if order.status != "pendingsignature" {
+                return Ok(());
+            }
+            let by = db::Transition::new("intent_reaper", "intent settlement timed out");
+            if db::transition_order(&state.db, order_id, order.version, db::OrderChange::status("cancelled"), &by)
+                .await
+                .is_err()
+            {
+                // Changed since it was read, e.g. broadcast just now
+                return Ok(());
+            }
+            release_order_locks(state, order_id).await;
+        }
+    }
+
+    if db::reopen_intent(&state.db, &intent.id).await? {
+        tracing::info!("Intent {} was not settled in time and is open again", intent.id);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+    use bitcoin::CompressedPublicKey;
+    use crate::routes::orders::testing::test_state;
+    use crate::services::signatures::message_digest;
+    use crate::services::spell_schema::Spell;
+
+    fn signed_request() -> CreateIntentRequest {
+        let secp = Secp256k1::new();
+        let secret = SecretKey::from_slice(&[7u8; 32]).unwrap();
+        let pubkey = CompressedPublicKey(PublicKey::from_secret_key(&secp, &secret));
+        let mut req = CreateIntentRequest {
+            maker_address: bitcoin::Address::p2wpkh(&pubkey, bitcoin::Network::Testnet4).to_string(),
+            maker_pubkey: hex::encode(pubkey.to_bytes()),
+            offer_token: "TOAD".to_string(),
+            offer_amount: "1000".to_string(),
+            want_token: "BTC".to_string(),
+            want_amount: "10000".to_string(),
+            source_chain: "bitcoin".to_string(),
+            dest_chain: "bitcoin".to_string(),
+            maker_utxo: format!("{}:0", "aa".repeat(32)).parse().unwrap(),
+            expires_at: 1_900_000_000,
+            signature: String::new(),
+        };
+        let msg = Message::from_digest(message_digest(&intent_message(&req)));
+        req.signature = hex::encode(secp.sign_ecdsa(&msg, &secret).serialize_der());
+        req
+    }
+
+    fn taker_secret() -> SecretKey {
+        SecretKey::from_slice(&[9u8; 32]).unwrap()
+    }
+
+    /// P2WPKH address of the taker's key
+    fn taker_address() -> String {
+        let pubkey = CompressedPublicKey(PublicKey::from_secret_key(&Secp256k1::new(), &taker_secret()));
+        bitcoin::Address::p2wpkh(&pubkey, bitcoin::Network::Testnet4).to_string()
+    }
+
+    /// A fill of `intent_id` signed by the taker's key
+    fn signed_fill(intent_id: &str, taker_address: &str, taker_utxo: OutPoint) -> FillIntentRequest {
+        let secp = Secp256k1::new();
+        let secret = taker_secret();
+        let mut req = FillIntentRequest {
+            taker_address: taker_address.to_string(),
+            taker_pubkey: Some(hex::encode(PublicKey::from_secret_key(&secp, &secret).serialize())),
+            taker_utxo,
+            taker_utxo_value: Some(20_000),
+            signature: String::new(),
+        };
+        let msg = Message::from_digest(message_digest(&intent_fill_message(intent_id, &req)));
+        req.signature = hex::encode(secp.sign_ecdsa(&msg, &secret).serialize_der());
+        req
+    }
+
+    #[test]
+    fn test_verify_intent() {
+        let req = signed_request();
+        assert!(verify_intent(&req).is_ok());
+        assert_eq!(
+            intent_message(&req),
+            format!(
+                "liquid-nation:intent:{}:{}:TOAD:1000:BTC:10000:bitcoin:bitcoin:{}:0:1900000000",
+                req.maker_address,
+                req.maker_pubkey,
+                "aa".repeat(32)
+            )
+        );
+
+        // Any change to the terms breaks the signature, chains included;
+        // another spelling of the same chain does not
+        let mut tampered = signed_request();
+        tampered.want_amount = "1".to_string();
+        assert_eq!(verify_intent(&tampered).unwrap_err().status, axum::http::StatusCode::FORBIDDEN);
+        let mut rechained = signed_request();
+        rechained.dest_chain = "ethereum".to_string();
+        assert_eq!(verify_intent(&rechained).unwrap_err().status, axum::http::StatusCode::FORBIDDEN);
+        let mut respelled = signed_request();
+        respelled.source_chain = "BTC".to_string();
+        assert!(verify_intent(&respelled).is_ok());
+
+        // The key must be the maker address's
+        let mut other_address = signed_request();
+        other_address.maker_address = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx".to_string();
+        assert_eq!(verify_intent(&other_address).unwrap_err().status, axum::http::StatusCode::FORBIDDEN);
+
+        // So does signing with another key
+        let mut other_key = signed_request();
+        let secp = Secp256k1::new();
+        let other = SecretKey::from_slice(&[8u8; 32]).unwrap();
+        other_key.maker_pubkey = hex::encode(PublicKey::from_secret_key(&secp, &other).serialize());
+        assert_eq!(verify_intent(&other_key).unwrap_err().status, axum::http::StatusCode::FORBIDDEN);
+
+        let mut unsigned = signed_request();
+        unsigned.signature.clear();
+        assert_eq!(verify_intent(&unsigned).unwrap_err().status, axum::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_create_intent_stores_only_signed_intents() {
+        let db = db::testing::fresh_db().await;
+
+        let state = Arc::new(test_state(db));
+        let mut tampered = signed_request();
+        tampered.offer_amount = "999".to_string();
+        let err = create_intent(State(state.clone()), NetworkJson(tampered)).await.unwrap_err();
+        assert_eq!(err.status, axum::http::StatusCode::FORBIDDEN);
+        assert!(list_intents(State(state.clone())).await.unwrap().0.is_empty());
+
+        let intent = create_intent(State(state.clone()), NetworkJson(signed_request())).await.unwrap().0;
+        let listed = list_intents(State(state.clone())).await.unwrap().0;
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, intent.id);
+        assert_eq!(listed[0].signature, intent.signature);
+
+        // The same signature, even re-encoded, cannot post a second intent
+        let err = create_intent(State(state.clone()), NetworkJson(signed_request())).await.unwrap_err();
+        assert_eq!(err.status, axum::http::StatusCode::CONFLICT);
+        let mut reencoded = signed_request();
+        let der = hex::decode(&reencoded.signature).unwrap();
+        let sig = bitcoin::secp256k1::ecdsa::Signature::from_der(&der).unwrap();
+        reencoded.signature = hex::encode(sig.serialize_compact());
+        let err = create_intent(State(state.clone()), NetworkJson(reencoded)).await.unwrap_err();
+        assert_eq!(err.status, axum::http::StatusCode::CONFLICT);
+        assert_eq!(list_intents(State(state)).await.unwrap().0.len(), 1);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_fill_intent_settles_in_one_composed_spell() {
+        let db = db::testing::fresh_db().await;
+
+        let state = Arc::new(test_state(db));
+        let maker = signed_request();
+        let maker_utxo = maker.maker_utxo.to_string();
+        let intent = create_intent(State(state.clone()), NetworkJson(maker)).await.unwrap().0;
+
+        let taker_address = taker_address();
+        let taker_address = taker_address.as_str();
+        let taker_utxo: OutPoint = format!("{}:1", "bb".repeat(32)).parse().unwrap();
+        // The taker must sign a commitment to this intent
+        let misdirected = signed_fill("other-intent", taker_address, taker_utxo);
+        let wallet = WalletFormat::raw(bitcoin::Network::Testnet4);
+        let err = fill_intent(State(state.clone()), Path(intent.id.clone()), wallet, NetworkJson(misdirected))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, axum::http::StatusCode::FORBIDDEN);
+
+        // and sign with the key of the taker address
+        let borrowed = signed_fill(&intent.id, "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7", taker_utxo);
+        let wallet = WalletFormat::raw(bitcoin::Network::Testnet4);
+        let err = fill_intent(State(state.clone()), Path(intent.id.clone()), wallet, NetworkJson(borrowed))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, axum::http::StatusCode::FORBIDDEN);
+        assert_eq!(err.message, "taker_pubkey is not the key of taker_address");
+        let unchanged = db::get_intent_by_id(&state.db, &intent.id).await.unwrap().unwrap();
+        assert_eq!(unchanged.status, "open");
+
+        let fill = signed_fill(&intent.id, taker_address, taker_utxo);
+        let wallet = WalletFormat::raw(bitcoin::Network::Testnet4);
+        let filled = fill_intent(State(state.clone()), Path(intent.id.clone()), wallet, NetworkJson(fill))
+            .await
+            .unwrap()
+            .0;
+
+        // Both legs are proved as one spell, maker first
+        let spell = Spell::parse(&filled.spell.spell_yaml_built).unwrap();
+        assert_eq!(spell.apps.len(), 2);
+        let ins: Vec<String> = spell.ins.iter().map(|input| input.utxo_id.clone()).collect();
+        assert_eq!(ins, vec![maker_utxo, taker_utxo.to_string()]);
+        let outs: Vec<&str> = spell.outs.iter().filter_map(|output| output.address.as_deref()).collect();
+        assert_eq!(outs, vec![taker_address, intent.maker_address.as_str()]);
+        assert!(!filled.unsigned_txs.is_empty());
+
+        // The order exists, awaiting signatures, and the intent is settling
+        // into it
+        assert_eq!(filled.order.filled_amount, "1000");
+        let intent = db::get_intent_by_id(&state.db, &intent.id).await.unwrap().unwrap();
+        assert_eq!(intent.status, "settling");
+        assert_eq!(intent.order_id.as_deref(), Some(filled.order.id.as_str()));
+        let spells = db::get_order_spells(&state.db, &filled.order.id).await.unwrap();
+        assert_eq!(spells.len(), 1);
+        assert_eq!(spells[0].status, "proved");
+
+        // An intent settles once
+        let again = signed_fill(&intent.id, taker_address, taker_utxo);
+        let wallet = WalletFormat::raw(bitcoin::Network::Testnet4);
+        let err = fill_intent(State(state), Path(intent.id), wallet, NetworkJson(again)).await.unwrap_err();
+        assert_eq!(err.status, axum::http::StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_stalled_intents_reopen() {
+        let db = db::testing::fresh_db().await;
+
+        let state = Arc::new(test_state(db));
+        let intent = create_intent(State(state.clone()), NetworkJson(signed_request())).await.unwrap().0;
+        let taker_utxo: OutPoint = format!("{}:1", "bb".repeat(32)).parse().unwrap();
+        let taker_address = taker_address();
+        let fill = signed_fill(&intent.id, &taker_address, taker_utxo);
+        let wallet = WalletFormat::raw(bitcoin::Network::Testnet4);
+        let filled = fill_intent(State(state.clone()), Path(intent.id.clone()), wallet, NetworkJson(fill))
+            .await
+            .unwrap()
+            .0;
+
+        // Not stalled yet
+        let before = chrono::Utc::now() - chrono::Duration::minutes(15);
+        assert!(db::get_stalled_intents(&state.db, before).await.unwrap().is_empty());
+
+        // Settling past the timeout: the order is cancelled, its UTXOs are
+        // free and the intent is open to another taker
+        let stalled = db::get_stalled_intents(&state.db, chrono::Utc::now()).await.unwrap();
+        assert_eq!(stalled.len(), 1);
+        reopen_intent(&state, &stalled[0]).await.unwrap();
+
+        let order = db::get_order_by_id(&state.db, &filled.order.id).await.unwrap().unwrap();
+        assert_eq!(order.status, "cancelled");
+        assert!(db::get_active_utxo_locks(&state.db).await.unwrap().is_empty());
+        let reopened = db::get_intent_by_id(&state.db, &intent.id).await.unwrap().unwrap();
+        assert_eq!(reopened.status, "open");
+        assert_eq!(reopened.order_id, None);
+
+        let again = signed_fill(&intent.id, &taker_address, taker_utxo);
+        let wallet = WalletFormat::raw(bitcoin::Network::Testnet4);
+        assert!(fill_intent(State(state), Path(intent.id), wallet, NetworkJson(again)).await.is_ok());
+    }
+}
@@ -1,8 +1,27 @@
 //! API Route handlers
 
 pub mod health;
+pub mod apps;
 pub mod orders;
 pub mod wallet;
+pub mod wallet_formats;
+pub mod network_json;
+pub mod watch_wallets;
+pub mod address_subscriptions;
 pub mod spells;
+pub mod charms;
+pub mod spell_templates;
 pub mod escrow;
+pub mod escrow_templates;
+pub mod fees;
+pub mod rfq;
+pub mod intents;
+pub mod trades;
+pub mod swaps;
+pub mod transactions;
+pub mod events;
+pub mod webhooks;
+pub mod regtest;
+pub mod error;
+pub mod auth;
 
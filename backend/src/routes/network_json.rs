@@ -0,0 +1,47 @@
+//! Request bodies checked against the server's network
+//!
+//! Handlers that take Bitcoin addresses extract their body as
+//! `NetworkJson<T>`. Every address the request names must be valid on the
+//! network the server runs on, so e.g. a mainnet address sent to a testnet4
+//! deployment is refused with `400 wrong_network` before anything is built,
+//! proved or locked, instead of failing once the transaction is broadcast.
+
+use axum::{
+    async_trait,
+    extract::{FromRequest, Request},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::de::DeserializeOwned;
+
+use crate::routes::error::ApiError;
+use crate::routes::wallet_formats::NetworkState;
+use crate::services::network;
+
+/// Request body naming Bitcoin addresses
+pub trait BitcoinAddresses {
+    /// `(field, address)` of every Bitcoin address given
+    fn bitcoin_addresses(&self) -> Vec<(&'static str, &str)>;
+}
+
+/// JSON body whose addresses are all on the server's network
+#[derive(Debug)]
+pub struct NetworkJson<T>(pub T);
+
+#[async_trait]
+impl<S, T> FromRequest<S> for NetworkJson<T>
+where
+    S: NetworkState + Send + Sync,
+    T: DeserializeOwned + BitcoinAddresses,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(body) = Json::<T>::from_request(req, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        network::check_fields(state.network(), &body.bitcoin_addresses())
+            .map_err(|e| ApiError::from(e).into_response())?;
+        Ok(NetworkJson(body))
+    }
+}
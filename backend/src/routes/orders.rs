@@ -3,24 +3,85 @@
 //! Handles order creation, filling, cancellation with full Charms integration
 
 use axum::{
-    extract::{Path, Query, State},
+    async_trait,
+    extract::{FromRef, Path, Query, State},
     Json,
 };
+use bitcoin::consensus::encode::deserialize_hex;
+use bitcoin::{Network, OutPoint, Transaction};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
 use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::db::{self, DbPool, OrderRecord};
-use crate::services::charms::{CharmsService, OrderSpellData, FillSpellData, SpellProveRequest};
+use crate::routes::auth::{AdminToken, WalletSession};
+use crate::routes::error::ApiError;
+use crate::routes::intents::finish_intent;
+use crate::routes::network_json::{BitcoinAddresses, NetworkJson};
+use crate::routes::spell_templates::{
+    record_template_use, spell_template, SpellTemplate, CANCEL_ORDER, CREATE_ORDER, FILL_ORDER, PARTIAL_FILL,
+};
+use crate::routes::spells::{open_spell, prove_cached, seal_spell};
+use crate::routes::wallet::{lock_funding_utxo, parse_outpoint};
+use crate::routes::wallet_formats::{NetworkState, WalletFormat, WalletRequest};
+use crate::services::addresses;
+use crate::services::app_artifacts::AppArtifacts;
+use crate::services::charms::{
+    CancelSpellData, CharmsService, FillSpellData, OrderSpellData, PartialFillSpellData, ProvedTransaction,
+    SpellProveRequest,
+};
 use crate::services::bitcoin::BitcoinService;
+use crate::services::broadcast_check::BroadcastMismatch;
+use crate::services::chain::{BroadcastRejection, ChainBackend};
+use crate::services::chain_tip::{self, ChainTip};
+use crate::services::events::{Event, EventBus};
+use crate::services::fee_bump::{load_stuck, replacement_rate, BumpError, PaidFee, MAX_BUMP_FEE_RATE};
+use crate::services::fees::{FeeEstimator, FeeTier};
+use crate::services::fills::plan_fill;
+use crate::services::jobs::JobQueue;
+use crate::services::mempool_monitor::{ExternalSpend, SpendWatcher, WatchedOutpoint};
+use crate::services::psbt::{build_psbts, BuiltPsbt};
+use crate::services::sealing::Sealer;
+use crate::services::signatures::{verify_bip322, verify_signature, SignatureError};
+use crate::services::sessions::SessionStore;
+use crate::services::spell_check::Contract;
+use crate::services::tokens::TokenRegistry;
 
 /// Application state shared across handlers
 pub struct AppState {
     pub charms: CharmsService,
+    pub apps: Arc<AppArtifacts>,
+    /// Bitcoin node, for its watch-only wallets
     pub bitcoin: BitcoinService,
+    /// Chain state, broadcasts and fee estimates
+    pub chain: Arc<dyn ChainBackend>,
+    /// Best-block height expiries are set from
+    pub tip: Arc<ChainTip>,
+    pub fees: FeeEstimator,
+    pub events: EventBus,
+    pub sessions: SessionStore,
+    pub tokens: TokenRegistry,
+    /// Seals private spell inputs kept in prove jobs and order spells
+    pub sealer: Arc<Sealer>,
+    /// Durable queue background work runs from
+    pub jobs: Arc<JobQueue>,
     pub db: DbPool,
 }
 
+impl FromRef<Arc<AppState>> for SessionStore {
+    fn from_ref(state: &Arc<AppState>) -> Self {
+        state.sessions.clone()
+    }
+}
+
+impl NetworkState for Arc<AppState> {
+    fn network(&self) -> Network {
+        self.bitcoin.network()
+    }
+}
+
 /// Order status
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -31,6 +92,11 @@ pub enum OrderStatus {
     Expired,
     PartiallyFilled,
     PendingSignature,
+    /// The order's funding UTXO was double-spent before it confirmed
+    Conflicting,
+    /// The order's UTXO was spent by a transaction this backend did not build
+    #[serde(rename = "externally_spent")]
+    ExternallySpent,
 }
 
 /// Chain identifier - using String for flexibility
@@ -48,6 +114,16 @@ pub fn normalize_chain(chain: &str) -> String {
     }
 }
 
+/// Parse a token amount in whole base units, as stored on orders
+pub fn parse_amount(field: &str, amount: &str) -> Result<i64, ApiError> {
+    amount
+        .trim()
+        .parse::<i64>()
+        .ok()
+        .filter(|&amount| amount > 0)
+        .ok_or_else(|| ApiError::bad_request(format!("{} must be a positive whole number of base units", field)))
+}
+
 /// Map chain string to numeric ID for spell
 pub fn chain_to_id(chain: &str) -> u8 {
     match chain.to_lowercase().as_str() {
@@ -78,6 +154,44 @@ pub struct Order {
     pub created_at: String,
     pub updated_at: String,
     pub utxo_id: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// When the order was archived, if it has been
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived_at: Option<String>,
+}
+
+impl From<OrderRecord> for Order {
+    fn from(record: OrderRecord) -> Self {
+        Order {
+            id: record.id,
+            maker_address: record.maker_address,
+            offer_token: record.offer_token,
+            offer_amount: record.offer_amount.to_string(),
+            want_token: record.want_token,
+            want_amount: record.want_amount.to_string(),
+            source_chain: record.source_chain,
+            dest_chain: record.dest_chain,
+            status: match record.status.as_str() {
+                "open" => OrderStatus::Open,
+                "filled" => OrderStatus::Filled,
+                "cancelled" => OrderStatus::Cancelled,
+                "expired" => OrderStatus::Expired,
+                "partiallyfilled" => OrderStatus::PartiallyFilled,
+                "conflicting" => OrderStatus::Conflicting,
+                "externally_spent" => OrderStatus::ExternallySpent,
+                _ => OrderStatus::PendingSignature,
+            },
+            allow_partial: record.allow_partial,
+            filled_amount: record.filled_amount.to_string(),
+            expiry_height: record.expiry_height.unwrap_or(0) as u64,
+            created_at: record.created_at.to_rfc3339(),
+            updated_at: record.updated_at.to_rfc3339(),
+            utxo_id: record.utxo_id,
+            tags: Vec::new(),
+            archived_at: record.archived_at.map(|at| at.to_rfc3339()),
+        }
+    }
 }
 
 /// Create order request
@@ -94,11 +208,36 @@ pub struct CreateOrderRequest {
     pub dest_chain: Chain,
     pub allow_partial: bool,
     pub expiry_blocks: u64,
-    pub funding_utxo: String,
+    /// Required; `""` and `"pending"` are refused like a missing one
+    #[serde(default, deserialize_with = "placeholder_outpoint")]
+    pub funding_utxo: Option<OutPoint>,
     #[serde(default)]
     pub funding_utxo_value: Option<u64>,
     #[serde(default)]
     pub dest_address: Option<String>,
+    /// Free-form labels (e.g. "otc", campaign ids)
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// An outpoint, with `""` and `"pending"` (sent before a wallet has picked a
+/// UTXO) meaning none
+fn placeholder_outpoint<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<OutPoint>, D::Error> {
+    match String::deserialize(deserializer)?.as_str() {
+        "" | "pending" => Ok(None),
+        outpoint => OutPoint::from_str(outpoint).map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+impl BitcoinAddresses for CreateOrderRequest {
+    fn bitcoin_addresses(&self) -> Vec<(&'static str, &str)> {
+        let mut addresses = vec![("maker_address", self.maker_address.as_str())];
+        // Cross-chain orders pay out on the other chain
+        if let Some(dest) = self.dest_address.as_deref().filter(|_| normalize_chain(&self.dest_chain) == "bitcoin") {
+            addresses.push(("dest_address", dest));
+        }
+        addresses
+    }
 }
 
 /// Create order response with spell and unsigned transactions
@@ -125,6 +264,13 @@ pub struct UnsignedTransaction {
     pub hex: String,
     pub txid: String,
     pub inputs_to_sign: Vec<InputToSign>,
+    /// PSBT with prevouts, key origins and a readable summary, when the
+    /// transaction could be decoded
+    #[serde(default)]
+    pub psbt: Option<BuiltPsbt>,
+    /// Arguments for the browser wallet named by `?wallet=`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wallet_request: Option<WalletRequest>,
 }
 
 /// Input that needs signing
@@ -133,6 +279,24 @@ pub struct InputToSign {
     pub index: u32,
     pub address: String,
     pub sighash_type: String,
+    /// BIP-32 path of the signing key, when its origin was registered at connect
+    #[serde(default)]
+    pub derivation_path: Option<String>,
+    #[serde(default)]
+    pub master_fingerprint: Option<String>,
+}
+
+impl InputToSign {
+    /// Input signed with the default sighash by the wallet holding `address`
+    pub fn new(index: u32, address: &str) -> Self {
+        Self {
+            index,
+            address: address.to_string(),
+            sighash_type: "SIGHASH_DEFAULT".to_string(),
+            derivation_path: None,
+            master_fingerprint: None,
+        }
+    }
 }
 
 /// Instructions for wallet signing
@@ -149,10 +313,37 @@ pub struct FillOrderRequest {
     pub taker_address: String,
     #[serde(default)]
     pub taker_pubkey: Option<String>,
-    pub taker_utxo: String,
+    /// Holds the wanted tokens and pays the fee
+    pub taker_utxo: OutPoint,
+    pub taker_utxo_value: u64,
+    /// Offer base units to take; a fill takes all that is left when absent,
+    /// a partial fill requires it
     #[serde(default)]
-    pub taker_utxo_value: Option<u64>,
     pub fill_amount: Option<String>,
+    /// Signature over `liquid-nation:order:fill:<order id>:<taker address>:
+    /// <offer units taken>:<taker utxo>`: hex by `taker_pubkey` when given,
+    /// BIP-322 by the taker address otherwise
+    pub signature: String,
+}
+
+impl BitcoinAddresses for FillOrderRequest {
+    fn bitcoin_addresses(&self) -> Vec<(&'static str, &str)> {
+        vec![("taker_address", self.taker_address.as_str())]
+    }
+}
+
+/// Cancel order request: the maker's authorization and, for an order already
+/// on chain, the UTXO paying for the cancellation
+#[derive(Debug, Deserialize)]
+pub struct CancelOrderRequest {
+    /// Signature over `liquid-nation:order:cancel:<order id>`: hex by the
+    /// order's `maker_pubkey` when it has one, BIP-322 by the maker address
+    /// otherwise
+    pub signature: String,
+    #[serde(default)]
+    pub funding_utxo: Option<OutPoint>,
+    #[serde(default)]
+    pub funding_utxo_value: Option<u64>,
 }
 
 /// Fill order response
@@ -167,16 +358,55 @@ pub struct FillOrderResponse {
 /// Query parameters for listing orders
 #[derive(Debug, Deserialize)]
 pub struct ListOrdersQuery {
+    /// One status or a comma-separated list
     pub status: Option<String>,
     pub offer_token: Option<String>,
     pub want_token: Option<String>,
     pub maker_address: Option<String>,
     pub source_chain: Option<String>,
     pub dest_chain: Option<String>,
+    pub tag: Option<String>,
+    pub min_expiry_height: Option<u64>,
+    pub max_expiry_height: Option<u64>,
+    /// Also list archived orders (finished and past the retention window)
+    #[serde(default)]
+    pub include_archived: bool,
     pub limit: Option<u32>,
     pub offset: Option<u32>,
 }
 
+impl ListOrdersQuery {
+    fn filter(&self, limit: u32, offset: u32) -> db::OrderFilter {
+        let trimmed = |value: &Option<String>| {
+            value.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(str::to_string)
+        };
+        db::OrderFilter {
+            statuses: self
+                .status
+                .as_deref()
+                .map(|status| {
+                    status
+                        .split(',')
+                        .map(|s| s.trim().to_lowercase())
+                        .filter(|s| !s.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            maker_addresses: trimmed(&self.maker_address).into_iter().collect(),
+            offer_token: trimmed(&self.offer_token),
+            want_token: trimmed(&self.want_token),
+            source_chain: trimmed(&self.source_chain).map(|chain| normalize_chain(&chain)),
+            dest_chain: trimmed(&self.dest_chain).map(|chain| normalize_chain(&chain)),
+            tag: trimmed(&self.tag).map(|tag| tag.to_lowercase()),
+            min_expiry_height: self.min_expiry_height.map(|h| h as i64),
+            max_expiry_height: self.max_expiry_height.map(|h| h as i64),
+            include_archived: self.include_archived,
+            limit: Some(limit as i64),
+            offset: offset as i64,
+        }
+    }
+}
+
 /// List orders response
 #[derive(Debug, Serialize)]
 pub struct ListOrdersResponse {
@@ -190,7 +420,6 @@ pub struct ListOrdersResponse {
 #[derive(Debug, Deserialize)]
 pub struct BroadcastRequest {
     pub signed_tx_hex: String,
-    pub order_id: String,
 }
 
 /// Broadcast response
@@ -199,25 +428,125 @@ pub struct BroadcastResponse {
     pub txid: String,
     pub status: String,
     pub message: String,
+    /// Why the mempool refused the transaction, when it did
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rejection: Option<BroadcastRejection>,
 }
 
-// ============ App Configuration ============
-// Built with: charms app build && charms app vk
+/// Fee bump request
+#[derive(Debug, Default, Deserialize)]
+pub struct BumpFeeRequest {
+    /// sat/vB the replacement must reach; defaults to the current estimate
+    /// for `fee_tier`
+    #[serde(default)]
+    pub fee_rate: Option<f64>,
+    /// Tier to estimate the rate for when `fee_rate` is not given (default:
+    /// normal)
+    #[serde(default)]
+    pub fee_tier: Option<FeeTier>,
+}
+
+/// Fee bump response: the replacement, ready for signing
+#[derive(Debug, Serialize)]
+pub struct BumpFeeResponse {
+    pub order: Order,
+    /// Transaction the replacement conflicts with
+    pub replaced_txid: String,
+    /// What the stuck transaction pays
+    pub paid: PaidFee,
+    /// sat/vB the replacement was proved at
+    pub fee_rate: f64,
+    pub unsigned_txs: Vec<UnsignedTransaction>,
+    pub signing_instructions: SigningInstructions,
+}
+
+/// A spell built for an order and its prove outcome
+#[derive(Debug, Serialize)]
+pub struct OrderSpell {
+    pub id: String,
+    /// What the spell does, e.g. create-order or bump-fee
+    pub operation: Option<String>,
+    pub template_name: Option<String>,
+    pub template_version: Option<i32>,
+    pub spell: String,
+    pub request_hash: String,
+    /// queued, proved or failed
+    pub status: String,
+    /// Prove job proving it, for spells queued through `/api/spells/prove`
+    pub prove_job_id: Option<String>,
+    pub transactions: Vec<ProvedTransaction>,
+    pub error: Option<String>,
+    pub prove_ms: i64,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    pub created_at: String,
+}
 
-const DEFAULT_APP_ID: &str = "liquid-swap";
-const DEFAULT_APP_VK: &str = "857ee181813511526321296bb0183b7496e1cdc0801552495464e9ec44c37718";
-const DEFAULT_TOKEN_ID: &str = "toad-token";
-const DEFAULT_TOKEN_VK: &str = "857ee181813511526321296bb0183b7496e1cdc0801552495464e9ec44c37718";
+impl From<db::SpellRecord> for OrderSpell {
+    fn from(record: db::SpellRecord) -> Self {
+        Self {
+            transactions: record
+                .transactions
+                .as_deref()
+                .and_then(|json| serde_json::from_str(json).ok())
+                .unwrap_or_default(),
+            id: record.id,
+            operation: record.operation,
+            template_name: record.template_name,
+            template_version: record.template_version,
+            spell: record.spell,
+            request_hash: record.request_hash,
+            status: record.status,
+            prove_job_id: record.prove_job_id,
+            error: record.error,
+            prove_ms: record.prove_ms,
+            started_at: record.started_at.map(|t| t.to_rfc3339()),
+            finished_at: record.finished_at.map(|t| t.to_rfc3339()),
+            created_at: record.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Largest page of orders one listing returns
+const MAX_ORDERS_LIMIT: u32 = 100;
+/// Maximum number of tags per order
+const MAX_TAGS: usize = 10;
+/// Maximum length of a single tag
+const MAX_TAG_LEN: usize = 64;
+
+/// Normalize and validate order tags (lowercased, trimmed, deduplicated)
+pub fn normalize_tags(tags: &[String]) -> Result<Vec<String>, String> {
+    let mut normalized: Vec<String> = Vec::new();
+
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if tag.is_empty() {
+            continue;
+        }
+        if tag.len() > MAX_TAG_LEN {
+            return Err(format!("Tag '{}' exceeds {} characters", tag, MAX_TAG_LEN));
+        }
+        if !tag.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':' | '.')) {
+            return Err(format!("Tag '{}' contains invalid characters", tag));
+        }
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+
+    if normalized.len() > MAX_TAGS {
+        return Err(format!("At most {} tags are allowed", MAX_TAGS));
+    }
 
-// Path to the compiled WASM binary
-const APP_WASM_PATH: &str = "target/wasm32-wasip1/release/liquid-swap-app.wasm";
+    Ok(normalized)
+}
 
-// ============ Spell Templates ============
+// ============ App Configuration ============
+// The swap app's VK and binary come from `AppState::apps`
 
-const CREATE_ORDER_SPELL: &str = include_str!("../../../apps/swap-app/spells/create-order.yaml");
-const FILL_ORDER_SPELL: &str = include_str!("../../../apps/swap-app/spells/fill-order.yaml");
-const CANCEL_ORDER_SPELL: &str = include_str!("../../../apps/swap-app/spells/cancel-order.yaml");
-const PARTIAL_FILL_SPELL: &str = include_str!("../../../apps/swap-app/spells/partial-fill.yaml");
+pub(crate) const DEFAULT_APP_ID: &str = "liquid-swap";
+pub(crate) const DEFAULT_TOKEN_ID: &str = "toad-token";
+pub(crate) const DEFAULT_TOKEN_VK: &str = "857ee181813511526321296bb0183b7496e1cdc0801552495464e9ec44c37718";
 
 // ============ Route Handlers ============
 
@@ -226,56 +555,124 @@ pub async fn list_orders(
     State(state): State<Arc<AppState>>,
     Query(params): Query<ListOrdersQuery>,
 ) -> Json<ListOrdersResponse> {
-    // Fetch orders from database
-    let db_orders = match db::get_all_orders(&state.db).await {
-        Ok(orders) => orders,
+    let limit = params.limit.unwrap_or(20).clamp(1, MAX_ORDERS_LIMIT);
+    let offset = params.offset.unwrap_or(0);
+    let filter = params.filter(limit, offset);
+
+    let (db_orders, total) = match tokio::try_join!(
+        db::query_orders(&state.db, &filter),
+        db::count_orders(&state.db, &filter),
+    ) {
+        Ok(fetched) => fetched,
         Err(e) => {
             tracing::error!("Failed to fetch orders: {}", e);
-            Vec::new()
+            (Vec::new(), 0)
         }
     };
 
+    let ids: Vec<String> = db_orders.iter().map(|o| o.id.clone()).collect();
+    let mut tags = db::get_tags_for_orders(&state.db, &ids).await.unwrap_or_else(|e| {
+        tracing::error!("Failed to fetch order tags: {}", e);
+        Default::default()
+    });
+
     // Convert database records to API response format
     let orders: Vec<Order> = db_orders
         .into_iter()
-        .map(|record| Order {
-            id: record.id,
-            maker_address: record.maker_address,
-            offer_token: record.offer_token,
-            offer_amount: record.offer_amount,
-            want_token: record.want_token,
-            want_amount: record.want_amount,
-            source_chain: record.source_chain,
-            dest_chain: record.dest_chain,
-            status: match record.status.as_str() {
-                "open" => OrderStatus::Open,
-                "filled" => OrderStatus::Filled,
-                "cancelled" => OrderStatus::Cancelled,
-                "expired" => OrderStatus::Expired,
-                "partiallyfilled" => OrderStatus::PartiallyFilled,
-                _ => OrderStatus::PendingSignature,
-            },
-            allow_partial: record.allow_partial,
-            filled_amount: record.filled_amount.unwrap_or_else(|| "0".to_string()),
-            expiry_height: record.expiry_height.unwrap_or(0) as u64,
-            created_at: record.created_at.to_rfc3339(),
-            updated_at: record.updated_at.to_rfc3339(),
-            utxo_id: record.utxo_id,
+        .map(|record| {
+            let order_tags = tags.remove(&record.id).unwrap_or_default();
+            Order { tags: order_tags, ..Order::from(record) }
         })
         .collect();
 
-    let total = orders.len() as u64;
-    let limit = params.limit.unwrap_or(20);
-    let offset = params.offset.unwrap_or(0);
-
     Json(ListOrdersResponse {
-        total,
+        total: total as u64,
         orders,
         limit,
         offset,
     })
 }
 
+/// Query parameters for listing the session's orders
+#[derive(Debug, Deserialize)]
+pub struct ListMyOrdersQuery {
+    #[serde(default)]
+    pub include_archived: bool,
+}
+
+/// List the orders made by any wallet linked to the session
+pub async fn list_my_orders(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+    Query(params): Query<ListMyOrdersQuery>,
+) -> Result<Json<Vec<Order>>, ApiError> {
+    // An empty maker list would match every order
+    if session.addresses.is_empty() {
+        return Ok(Json(Vec::new()));
+    }
+    let filter = db::OrderFilter {
+        maker_addresses: session.addresses.clone(),
+        include_archived: params.include_archived,
+        ..Default::default()
+    };
+    let records = db::query_orders(&state.db, &filter).await?;
+
+    let ids: Vec<String> = records.iter().map(|o| o.id.clone()).collect();
+    let mut tags = db::get_tags_for_orders(&state.db, &ids).await?;
+
+    let orders = records
+        .into_iter()
+        .map(|record| {
+            let order_tags = tags.remove(&record.id).unwrap_or_default();
+            Order { tags: order_tags, ..Order::from(record) }
+        })
+        .collect();
+
+    Ok(Json(orders))
+}
+
+/// Order search query: any fragment of an order's id, maker address, token
+/// (app id, or a registered symbol or name), UTXO, tag or transaction id
+#[derive(Debug, Deserialize)]
+pub struct SearchOrdersQuery {
+    pub q: String,
+    pub limit: Option<u32>,
+}
+
+/// Shortest fragment searched for; trigram indexes need three characters
+const MIN_SEARCH_LEN: usize = 3;
+
+/// Find orders, archived ones included, from a pasted fragment (admin)
+pub async fn search_orders(
+    _admin: AdminToken,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SearchOrdersQuery>,
+) -> Result<Json<Vec<Order>>, ApiError> {
+    let fragment = query.q.trim();
+    if fragment.chars().count() < MIN_SEARCH_LEN {
+        return Err(ApiError::bad_request(format!(
+            "Search for at least {} characters",
+            MIN_SEARCH_LEN
+        )));
+    }
+    let limit = query.limit.unwrap_or(20).clamp(1, MAX_ORDERS_LIMIT);
+    let token_ids = state.tokens.app_ids_matching(fragment);
+
+    let records = db::search_orders(&state.db, fragment, &token_ids, limit as i64).await?;
+    let ids: Vec<String> = records.iter().map(|o| o.id.clone()).collect();
+    let mut tags = db::get_tags_for_orders(&state.db, &ids).await?;
+
+    Ok(Json(
+        records
+            .into_iter()
+            .map(|record| {
+                let order_tags = tags.remove(&record.id).unwrap_or_default();
+                Order { tags: order_tags, ..Order::from(record) }
+            })
+            .collect(),
+    ))
+}
+
 /// Get a specific order by ID
 pub async fn get_order(
     State(state): State<Arc<AppState>>,
@@ -284,30 +681,12 @@ pub async fn get_order(
     // Fetch from database
     match db::get_order_by_id(&state.db, &id).await {
         Ok(Some(record)) => {
-            Json(Some(Order {
-                id: record.id,
-                maker_address: record.maker_address,
-                offer_token: record.offer_token,
-                offer_amount: record.offer_amount,
-                want_token: record.want_token,
-                want_amount: record.want_amount,
-                source_chain: record.source_chain,
-                dest_chain: record.dest_chain,
-                status: match record.status.as_str() {
-                    "open" => OrderStatus::Open,
-                    "filled" => OrderStatus::Filled,
-                    "cancelled" => OrderStatus::Cancelled,
-                    "expired" => OrderStatus::Expired,
-                    "partiallyfilled" => OrderStatus::PartiallyFilled,
-                    _ => OrderStatus::PendingSignature,
-                },
-                allow_partial: record.allow_partial,
-                filled_amount: record.filled_amount.unwrap_or_else(|| "0".to_string()),
-                expiry_height: record.expiry_height.unwrap_or(0) as u64,
-                created_at: record.created_at.to_rfc3339(),
-                updated_at: record.updated_at.to_rfc3339(),
-                utxo_id: record.utxo_id,
-            }))
+            let tags = db::get_tags_for_orders(&state.db, std::slice::from_ref(&record.id))
+                .await
+                .unwrap_or_default()
+                .remove(&record.id)
+                .unwrap_or_default();
+            Json(Some(Order { tags, ..Order::from(record) }))
         }
         Ok(None) => Json(None),
         Err(e) => {
@@ -317,35 +696,69 @@ pub async fn get_order(
     }
 }
 
+/// Spells built and proved for an order, oldest first (admin)
+pub async fn get_order_spells(
+    _admin: AdminToken,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<OrderSpell>>, ApiError> {
+    if db::get_order_by_id(&state.db, &id).await?.is_none() {
+        return Err(ApiError::not_found(format!("Order {} not found", id)));
+    }
+
+    let spells = db::get_order_spells(&state.db, &id).await?;
+    Ok(Json(spells.into_iter().map(OrderSpell::from).collect()))
+}
+
+/// One spell built for an order (admin)
+pub async fn get_order_spell(
+    _admin: AdminToken,
+    State(state): State<Arc<AppState>>,
+    Path((id, spell_id)): Path<(String, String)>,
+) -> Result<Json<OrderSpell>, ApiError> {
+    let spell = db::get_spell(&state.db, &spell_id)
+        .await?
+        .filter(|spell| spell.order_id == id)
+        .ok_or_else(|| ApiError::not_found(format!("Spell {} not found for order {}", spell_id, id)))?;
+
+    Ok(Json(spell.into()))
+}
+
 /// Create a new order - builds spell and calls prover
 pub async fn create_order(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateOrderRequest>,
-) -> Json<CreateOrderResponse> {
+    wallet: WalletFormat,
+    NetworkJson(req): NetworkJson<CreateOrderRequest>,
+) -> Result<Json<CreateOrderResponse>, ApiError> {
     let order_id = Uuid::new_v4().to_string();
     let now = chrono::Utc::now();
+    let offer_amount = parse_amount("offer_amount", &req.offer_amount)?;
+    let want_amount = parse_amount("want_amount", &req.want_amount)?;
     
-    // Validate funding UTXO
-    if req.funding_utxo.is_empty() || req.funding_utxo == "pending" {
-        tracing::warn!("Invalid funding UTXO: {}. Using mock mode.", req.funding_utxo);
-        // In real mode, we need a valid UTXO. For now, fall back to mock mode
-        // TODO: Get actual UTXO from wallet
-    }
+    let tags = normalize_tags(&req.tags).map_err(ApiError::bad_request)?;
+    
+    let funding_utxo = req
+        .funding_utxo
+        .ok_or_else(|| ApiError::bad_request("funding_utxo is required"))?;
+    let funding_utxo_value = req.funding_utxo_value.ok_or_else(|| ApiError::missing("funding_utxo_value"))?;
     
     // Get current block height for expiry calculation
-    let current_height = match state.bitcoin.get_blockchain_info().await {
-        Ok(info) => info.blocks,
-        Err(_) => 850000, // Fallback
-    };
+    let current_height = state.tip.height().await?;
     
-    let expiry_height = current_height + req.expiry_blocks;
+    let expiry_height = chain_tip::expiry_height(current_height, req.expiry_blocks).map_err(ApiError::bad_request)?;
     
     // Normalize chains
     let source_chain = normalize_chain(&req.source_chain);
     let dest_chain = normalize_chain(&req.dest_chain);
     
-    // Generate escrow address (in production, this would be derived from the contract)
-    let escrow_address = format!("tb1q_escrow_{}", &order_id[..8]);
+    // The offer is locked at a taproot address committed to this order
+    let escrow_address = order_escrow_address(
+        req.maker_pubkey.as_deref(),
+        &req.maker_address,
+        &order_id,
+        state.bitcoin.network(),
+    )
+        .map_err(|e| ApiError::bad_request(format!("Cannot derive escrow address: {}", e)))?;
     
     // Prepare spell data
     let order_spell_data = OrderSpellData {
@@ -353,112 +766,64 @@ pub async fn create_order(
         maker_pubkey: req.maker_pubkey.clone().unwrap_or_else(|| req.maker_address.clone()),
         offer_token_id: DEFAULT_TOKEN_ID.to_string(),
         offer_token_vk: DEFAULT_TOKEN_VK.to_string(),
-        offer_amount: req.offer_amount.clone(),
+        offer_amount: offer_amount.to_string(),
         want_token_id: req.want_token.clone().to_lowercase(),
-        want_amount: req.want_amount.clone(),
+        want_amount: want_amount.to_string(),
         expiry_height,
         allow_partial: req.allow_partial,
-        funding_utxo: req.funding_utxo.clone(),
+        funding_utxo: funding_utxo.to_string(),
         escrow_address: escrow_address.clone(),
         dest_chain: chain_to_id(&dest_chain),
         dest_address: req.dest_address.clone().unwrap_or_else(|| req.maker_address.clone()),
     };
     
     // Build the spell with variables substituted
+    let template = spell_template(&state.db, CREATE_ORDER).await;
+    let swap_vk = state.apps.vk(Contract::Swap).await;
     let spell_built = state.charms.build_create_order_spell(
-        CREATE_ORDER_SPELL,
+        &template.body,
         &order_spell_data,
         DEFAULT_APP_ID,
-        DEFAULT_APP_VK,
-    ).unwrap_or_else(|e| {
-        tracing::error!("Failed to build spell: {}", e);
-        CREATE_ORDER_SPELL.to_string()
-    });
+        &swap_vk,
+    ).map_err(|e| ApiError::spell_build_failed(&e))?;
+    let app_binary = state.apps.app_binary(&spell_built).await.map_err(|e| ApiError::app_unavailable(&e))?;
     
     // Validate the spell
     if let Err(e) = state.charms.validate_spell(&spell_built) {
         tracing::warn!("Spell validation warning: {}", e);
     }
     
+    // Claim the funding UTXO so a concurrent draft cannot spend it too
+    lock_funding_utxo(&state.db, &funding_utxo, &req.maker_address, Some(&order_id), None).await?;
+    
     // Call the Charms Prover API
-    let proved_txs = if !state.charms.is_mock_mode() {
-        // Load app binary if path is set
-        let mut binaries = std::collections::BTreeMap::new();
-        if let Ok(binary_path) = std::env::var("SWAP_APP_BINARY_PATH") {
-            if let Ok(binary_data) = tokio::fs::read(&binary_path).await {
-                let app_vk = std::env::var("SWAP_APP_VK")
-                    .unwrap_or_else(|_| DEFAULT_APP_VK.to_string());
-                binaries.insert(app_vk, binary_data);
-                tracing::info!("Loaded app binary from: {}", binary_path);
-            } else {
-                tracing::warn!("Failed to load app binary from: {}", binary_path);
-            }
-        }
-        
-        let prove_request = SpellProveRequest {
-            spell: spell_built.clone(),
-            binaries,
-            prev_txs: vec![],
-            funding_utxo: req.funding_utxo.clone(),
-            funding_utxo_value: req.funding_utxo_value.unwrap_or(10000),
-            change_address: req.maker_address.clone(),
-            fee_rate: 10.0,
-            chain: "testnet4".to_string(),
-        };
-        
-        match state.charms.prove_spell(prove_request).await {
-            Ok(txs) => {
-                if txs.is_empty() {
-                    tracing::warn!("Prover API returned empty transactions, falling back to mock");
-                    // Fallback to mock transaction
-                    vec![crate::services::charms::ProvedTransaction {
-                        hex: format!("0200000001...mock_fallback_{}...", order_id),
-                        txid: format!("mock_fallback_{}", order_id),
-                    }]
-                } else {
-                    txs
-                }
-            }
-            Err(e) => {
-                tracing::error!("Prover API error: {}. Falling back to mock transaction.", e);
-                // Fallback to mock transaction when API fails
-                vec![crate::services::charms::ProvedTransaction {
-                    hex: format!("0200000001...mock_fallback_{}...", order_id),
-                    txid: format!("mock_fallback_{}", order_id),
-                }]
-            }
-        }
-    } else {
-        // Mock mode - generate mock transaction
-        vec![crate::services::charms::ProvedTransaction {
-            hex: "0200000001...mock...".to_string(),
-            txid: format!("mock_{}", order_id),
-        }]
-    };
+    let proved_txs = prove_order_spell(
+        &state,
+        &template,
+        &spell_built,
+        &funding_utxo,
+        funding_utxo_value,
+        &req.maker_address,
+        &order_id,
+    ).await?;
     
     // Create unsigned transactions for signing
-    let unsigned_txs: Vec<UnsignedTransaction> = proved_txs.iter().map(|tx| {
-        UnsignedTransaction {
-            hex: tx.hex.clone(),
-            txid: tx.txid.clone(),
-            inputs_to_sign: vec![
-                InputToSign {
-                    index: 0,
-                    address: req.maker_address.clone(),
-                    sighash_type: "SIGHASH_DEFAULT".to_string(),
-                }
-            ],
-        }
-    }).collect();
+    let mut unsigned_txs = signing_payloads(
+        state.chain.as_ref(),
+        &state.sessions,
+        proved_txs,
+        vec![InputToSign::new(0, &req.maker_address)],
+    ).await;
+    wallet.apply(&mut unsigned_txs);
     
     // Create the order record
     let order = Order {
         id: order_id.clone(),
         maker_address: req.maker_address.clone(),
         offer_token: req.offer_token.clone(),
-        offer_amount: req.offer_amount.clone(),
+        offer_amount: offer_amount.to_string(),
         want_token: req.want_token.clone(),
-        want_amount: req.want_amount.clone(),
+        want_amount: want_amount.to_string(),
         source_chain: source_chain.clone(),
         dest_chain: dest_chain.clone(),
         status: OrderStatus::PendingSignature,
@@ -467,7 +832,9 @@ pub async fn create_order(
         expiry_height,
         created_at: now.to_rfc3339(),
         updated_at: now.to_rfc3339(),
-        utxo_id: Some(req.funding_utxo.clone()),
+        utxo_id: Some(funding_utxo.to_string()),
+        tags: tags.clone(),
+        archived_at: None,
     };
 
     // Store order in database
@@ -475,33 +842,54 @@ pub async fn create_order(
         id: order_id.clone(),
         maker_address: req.maker_address.clone(),
         offer_token: req.offer_token.clone(),
-        offer_amount: req.offer_amount.clone(),
+        offer_amount,
         want_token: req.want_token,
-        want_amount: req.want_amount,
+        want_amount,
         source_chain,
         dest_chain,
         status: "pendingsignature".to_string(),
         allow_partial: req.allow_partial,
-        filled_amount: Some("0".to_string()),
+        filled_amount: 0,
         expiry_height: Some(expiry_height as i64),
-        utxo_id: Some(req.funding_utxo),
+        utxo_id: Some(funding_utxo.to_string()),
         tx_id: None,
         created_at: now,
         updated_at: now,
+        version: 0,
+        archived_at: None,
+        maker_pubkey: req.maker_pubkey.clone(),
+        dest_address: req.dest_address.clone(),
     };
 
-    if let Err(e) = db::insert_order(&state.db, &db_record).await {
-        tracing::error!("Failed to insert order into database: {}", e);
-    } else {
-        tracing::info!("Order {} saved to database", order_id);
+    // Without its row the maker would be handed transactions for an order
+    // nobody can find
+    let by = db::Transition::new(&req.maker_address, "order created");
+    if let Err(e) = db::create_order(&state.db, &db_record, &tags, &by).await {
+        tracing::error!("Failed to store order {}: {}", order_id, e);
+        return Err(ApiError::internal("Failed to store order"));
+    }
+    tracing::info!("Order {} saved to database", order_id);
+
+    record_template_use(&state.db, &order_id, &template).await;
+
+    // Cross-chain orders get a coordinator tracking both legs
+    if db_record.source_chain != db_record.dest_chain {
+        if let Err(e) = db::insert_cross_chain_swap(
+            &state.db,
+            &order_id,
+            &db_record.source_chain,
+            &db_record.dest_chain,
+        ).await {
+            tracing::error!("Failed to start swap coordinator: {}", e);
+        }
     }
     
-    Json(CreateOrderResponse {
+    Ok(Json(CreateOrderResponse {
         order,
         spell: SpellData {
-            spell_yaml: CREATE_ORDER_SPELL.to_string(),
+            spell_yaml: template.body,
             spell_yaml_built: spell_built,
-            app_binary: "".to_string(), // TODO: Load compiled app binary
+            app_binary,
             prev_txs: vec![],
         },
         unsigned_txs,
@@ -514,119 +902,129 @@ pub async fn create_order(
             ],
             broadcast_endpoint: format!("/api/orders/{}/broadcast", order_id),
         },
-    })
+    }))
+}
+
+/// Turn proved transactions into signing payloads: raw hex for software
+/// wallets plus, when the transactions decode, a PSBT carrying prevouts,
+/// registered key origins and a readable summary for hardware wallets
+pub(crate) async fn signing_payloads(
+    chain: &dyn ChainBackend,
+    sessions: &SessionStore,
+    txs: Vec<ProvedTransaction>,
+    mut inputs_to_sign: Vec<InputToSign>,
+) -> Vec<UnsignedTransaction> {
+    let mut key_origins = HashMap::new();
+    for input in &mut inputs_to_sign {
+        if let Some(origin) = sessions.key_origin(&input.address).await {
+            input.derivation_path = Some(origin.derivation_path.clone());
+            input.master_fingerprint = Some(origin.master_fingerprint.clone());
+            key_origins.insert(input.index as usize, origin);
+        }
+    }
+
+    let hexes: Vec<String> = txs.iter().map(|tx| tx.hex.clone()).collect();
+    let psbts = match build_psbts(chain, &hexes, &[], &key_origins, chain.network()).await {
+        Ok(psbts) => psbts.into_iter().map(Some).collect(),
+        Err(e) => {
+            // Mock prover output is not a real transaction
+            tracing::debug!("Signing payloads without PSBTs: {:#}", e);
+            vec![None; txs.len()]
+        }
+    };
+
+    txs.into_iter()
+        .zip(psbts)
+        .map(|(tx, psbt)| UnsignedTransaction {
+            hex: tx.hex,
+            txid: tx.txid,
+            inputs_to_sign: inputs_to_sign.clone(),
+            psbt,
+            wallet_request: None,
+        })
+        .collect()
+}
+
+/// Taproot address holding an order's offer, derived from the maker's key
+/// (or taproot address) and the order id
+pub(crate) fn order_escrow_address(
+    maker_pubkey: Option<&str>,
+    maker_address: &str,
+    order_id: &str,
+    network: Network,
+) -> anyhow::Result<String> {
+    let maker_key = addresses::resolve_party_key(maker_pubkey, maker_address)?;
+    Ok(addresses::order_escrow_address(maker_key, order_id, network)?.address)
 }
 
-/// Fill an order (atomic swap)
+/// Fill an order (atomic swap), taking all that is left unless
+/// `fill_amount` says less
 pub async fn fill_order(
     State(state): State<Arc<AppState>>,
+    wallet: WalletFormat,
     Path(id): Path<String>,
-    Json(req): Json<FillOrderRequest>,
-) -> Json<FillOrderResponse> {
-    let now = chrono::Utc::now();
-    
-    // TODO: Lookup order from database
-    let existing_order = Order {
-        id: id.clone(),
-        maker_address: "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx".to_string(),
-        offer_token: "TOAD".to_string(),
-        offer_amount: "1000".to_string(),
-        want_token: "BTC".to_string(),
-        want_amount: "10000".to_string(),
-        source_chain: "bitcoin".to_string(),
-        dest_chain: "bitcoin".to_string(),
-        status: OrderStatus::Open,
-        allow_partial: true,
-        filled_amount: "0".to_string(),
-        expiry_height: 850000,
-        created_at: now.to_rfc3339(),
-        updated_at: now.to_rfc3339(),
-        utxo_id: Some("abc123:0".to_string()),
-    };
-    
-    // Prepare fill spell data
-    let order_spell_data = OrderSpellData {
-        maker_address: existing_order.maker_address.clone(),
-        maker_pubkey: existing_order.maker_address.clone(),
-        offer_token_id: DEFAULT_TOKEN_ID.to_string(),
-        offer_token_vk: DEFAULT_TOKEN_VK.to_string(),
-        offer_amount: existing_order.offer_amount.clone(),
-        want_token_id: existing_order.want_token.clone().to_lowercase(),
-        want_amount: existing_order.want_amount.clone(),
-        expiry_height: existing_order.expiry_height,
-        allow_partial: existing_order.allow_partial,
-        funding_utxo: existing_order.utxo_id.clone().unwrap_or_default(),
-        escrow_address: "".to_string(),
-        dest_chain: chain_to_id(&existing_order.dest_chain),
-        dest_address: existing_order.maker_address.clone(),
-    };
-    
-    let fill_spell_data = FillSpellData {
-        order_utxo: existing_order.utxo_id.clone().unwrap_or_default(),
-        taker_utxo: req.taker_utxo.clone(),
-        taker_pubkey: req.taker_pubkey.clone().unwrap_or_else(|| req.taker_address.clone()),
-        taker_address: req.taker_address.clone(),
-        maker_address: existing_order.maker_address.clone(),
-        offer_amount: existing_order.offer_amount.clone(),
-        want_amount: existing_order.want_amount.clone(),
-        fill_amount: req.fill_amount.clone(),
-    };
-    
-    // Build the fill spell
-    let spell_built = state.charms.build_fill_order_spell(
-        FILL_ORDER_SPELL,
-        &fill_spell_data,
-        &order_spell_data,
-        DEFAULT_APP_ID,
-        DEFAULT_APP_VK,
-    ).unwrap_or_else(|e| {
-        tracing::error!("Failed to build fill spell: {}", e);
-        FILL_ORDER_SPELL.to_string()
-    });
-    
-    // Call prover (mock for now)
-    let unsigned_txs = vec![
-        UnsignedTransaction {
-            hex: "0200000001...mock_fill...".to_string(),
-            txid: format!("mock_fill_{}", id),
-            inputs_to_sign: vec![
-                InputToSign {
-                    index: 0,
-                    address: req.taker_address.clone(),
-                    sighash_type: "SIGHASH_DEFAULT".to_string(),
-                }
-            ],
+    NetworkJson(req): NetworkJson<FillOrderRequest>,
+) -> Result<Json<FillOrderResponse>, ApiError> {
+    let amount = req
+        .fill_amount
+        .as_deref()
+        .map(|amount| parse_amount("fill_amount", amount))
+        .transpose()?;
+    fill_stored_order(&state, wallet, &id, &req, amount).await.map(Json)
+}
+
+/// Build and prove a fill of a stored order: a whole untouched order through
+/// the fill-order spell, anything else through a partial fill
+async fn fill_stored_order(
+    state: &AppState,
+    wallet: WalletFormat,
+    id: &str,
+    req: &FillOrderRequest,
+    amount: Option<i64>,
+) -> Result<FillOrderResponse, ApiError> {
+    // The key signs for the taker and goes into the spell as theirs, so it
+    // must be the key of the taker address
+    if let Some(pubkey) = req.taker_pubkey.as_deref() {
+        if !addresses::key_controls_address(pubkey, &req.taker_address) {
+            return Err(ApiError::forbidden("taker_pubkey is not the key of taker_address"));
         }
-    ];
-    
-    let order = Order {
-        id: id.clone(),
-        maker_address: existing_order.maker_address,
-        offer_token: existing_order.offer_token,
-        offer_amount: existing_order.offer_amount.clone(),
-        want_token: existing_order.want_token,
-        want_amount: existing_order.want_amount,
-        source_chain: existing_order.source_chain,
-        dest_chain: existing_order.dest_chain,
-        status: OrderStatus::PendingSignature,
-        allow_partial: existing_order.allow_partial,
-        filled_amount: existing_order.offer_amount, // Full fill
-        expiry_height: existing_order.expiry_height,
-        created_at: existing_order.created_at,
-        updated_at: now.to_rfc3339(),
-        utxo_id: None,
-    };
+    }
 
-    Json(FillOrderResponse {
-        order,
-        spell: SpellData {
-            spell_yaml: FILL_ORDER_SPELL.to_string(),
-            spell_yaml_built: spell_built,
-            app_binary: "".to_string(),
-            prev_txs: vec![],
-        },
-        unsigned_txs,
-        signing_instructions: SigningInstructions {
+    let record = db::get_order_by_id(&state.db, id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Order not found"))?;
+    let plan = plan_fill(&record, amount)?;
+
+    let amount = plan.amount.to_string();
+    let taker_utxo = req.taker_utxo.to_string();
+    let message = order_action_message("fill", id, &[&req.taker_address, &amount, &taker_utxo]);
+    verify_party(req.taker_pubkey.as_deref(), &req.taker_address, &message, &req.signature)
+        .map_err(|e| ApiError::forbidden(format!("Invalid fill signature: {}", e)))?;
+
+    let order_data = order_spell_data(&record, state.bitcoin.network())?;
+    let order_utxo = order_output(&record)?;
+    let taker_pubkey = req.taker_pubkey.clone().unwrap_or_else(|| req.taker_address.clone());
+    let swap_vk = state.apps.vk(Contract::Swap).await;
+
+    let (template, spell_built, signing_instructions) = if plan.takes_whole_order() {
+        let template = spell_template(&state.db, FILL_ORDER).await;
+        let fill_spell_data = FillSpellData {
+            order_utxo,
+            taker_utxo: req.taker_utxo.to_string(),
+            taker_pubkey,
+            taker_address: req.taker_address.clone(),
+            maker_address: record.maker_address.clone(),
+            offer_amount: record.offer_amount.to_string(),
+            want_amount: record.want_amount.to_string(),
+        };
+        let spell_built = state.charms.build_fill_order_spell(
+            &template.body,
+            &fill_spell_data,
+            &order_data,
+            DEFAULT_APP_ID,
+            &swap_vk,
+        ).map_err(|e| ApiError::spell_build_failed(&e))?;
+        let instructions = SigningInstructions {
             message: "Sign to complete the atomic swap".to_string(),
             steps: vec![
                 "1. You will receive the offered tokens".to_string(),
@@ -634,198 +1032,1202 @@ pub async fn fill_order(
                 "3. Sign the transaction to execute".to_string(),
             ],
             broadcast_endpoint: format!("/api/orders/{}/broadcast", id),
+        };
+        (template, spell_built, instructions)
+    } else {
+        let template = spell_template(&state.db, PARTIAL_FILL).await;
+        let partial_spell_data = PartialFillSpellData {
+            order_utxo,
+            taker_utxo: req.taker_utxo.to_string(),
+            taker_pubkey,
+            taker_address: req.taker_address.clone(),
+            fill_amount: plan.amount as u64,
+            fill_want_amount: plan.want_amount as u64,
+            current_filled: record.filled_amount as u64,
+            current_remaining: plan.remaining as u64,
+        };
+        let spell_built = state.charms.build_partial_fill_spell(
+            &template.body,
+            &partial_spell_data,
+            &order_data,
+            DEFAULT_APP_ID,
+            &swap_vk,
+        ).map_err(|e| ApiError::spell_build_failed(&e))?;
+        let instructions = SigningInstructions {
+            message: "Sign to partially fill this order".to_string(),
+            steps: vec![
+                "1. You will receive a portion of the offered tokens".to_string(),
+                "2. A portion of your tokens will be sent to the maker".to_string(),
+                "3. The remaining order will stay open".to_string(),
+            ],
+            broadcast_endpoint: format!("/api/orders/{}/broadcast", id),
+        };
+        (template, spell_built, instructions)
+    };
+    let app_binary = state.apps.app_binary(&spell_built).await.map_err(|e| ApiError::app_unavailable(&e))?;
+    record_template_use(&state.db, id, &template).await;
+
+    lock_funding_utxo(&state.db, &req.taker_utxo, &req.taker_address, Some(id), None).await?;
+    let proved_txs = prove_order_spell(
+        state,
+        &template,
+        &spell_built,
+        &req.taker_utxo,
+        req.taker_utxo_value,
+        &req.taker_address,
+        id,
+    ).await?;
+    let action = if plan.completes_order() { "fill" } else { "partial_fill" };
+    record_pending_action(state, id, action, &req.taker_address, Some(plan.amount), &proved_txs).await?;
+
+    // The taker's UTXO is the spell's second input, after the order's
+    let mut unsigned_txs = signing_payloads(
+        state.chain.as_ref(),
+        &state.sessions,
+        proved_txs,
+        vec![InputToSign::new(1, &req.taker_address)],
+    ).await;
+    wallet.apply(&mut unsigned_txs);
+
+    Ok(FillOrderResponse {
+        order: Order::from(record),
+        spell: SpellData {
+            spell_yaml: template.body,
+            spell_yaml_built: spell_built,
+            app_binary,
+            prev_txs: vec![],
         },
+        unsigned_txs,
+        signing_instructions,
     })
 }
 
-/// Cancel an order
-pub async fn cancel_order(
-    State(state): State<Arc<AppState>>,
-    Path(id): Path<String>,
-) -> Json<FillOrderResponse> {
+/// Record a broadcast fill of a stored order, planned again against the
+/// order as it is now: its filled amount, status and transaction, the fill
+/// and the transaction record are written together or not at all
+async fn record_fill(
+    state: &AppState,
+    order: &OrderRecord,
+    action: &db::PendingOrderAction,
+    txid: &str,
+    signed_tx_hex: &str,
+) -> anyhow::Result<i64> {
+    let plan = plan_fill(order, action.fill_amount)?;
     let now = chrono::Utc::now();
-    
-    // Update order status to cancelled in database
-    if let Err(e) = db::update_order_status(&state.db, &id, "cancelled").await {
-        tracing::error!("Failed to update order status: {}", e);
+
+    let fill = db::FillRecord {
+        id: Uuid::new_v4().to_string(),
+        order_id: order.id.clone(),
+        taker_address: action.actor_address.clone(),
+        amount: plan.amount,
+        price: plan.price,
+        txid: txid.to_string(),
+        fee: None,
+        created_at: now,
+        updated_at: now,
+    };
+    let transaction = db::TransactionRecord {
+        id: Uuid::new_v4().to_string(),
+        order_id: order.id.clone(),
+        tx_type: action.action.clone(),
+        tx_hex: Some(signed_tx_hex.to_string()),
+        txid: Some(txid.to_string()),
+        status: "broadcast".to_string(),
+        signed_at: Some(now),
+        broadcast_at: Some(now),
+        confirmed_at: None,
+        created_at: now,
+    };
+    let by = db::Transition::new(&action.actor_address, &action.action).with_txid(txid);
+    db::execute_fill(&state.db, plan.status, plan.filled_amount, order.version, &fill, &transaction, &by).await
+}
+
+/// Move a fill whose transaction was replaced at a higher fee over to the
+/// broadcast replacement, recording the replacement transaction
+async fn replace_fill(
+    state: &AppState,
+    order: &OrderRecord,
+    action: &db::PendingOrderAction,
+    txid: &str,
+    signed_tx_hex: &str,
+) -> anyhow::Result<i64> {
+    let replaced = order.tx_id.as_deref().ok_or_else(|| anyhow::anyhow!("Order {} has no fill to replace", order.id))?;
+    let now = chrono::Utc::now();
+    let transaction = db::TransactionRecord {
+        id: Uuid::new_v4().to_string(),
+        order_id: order.id.clone(),
+        tx_type: "fill_replacement".to_string(),
+        tx_hex: Some(signed_tx_hex.to_string()),
+        txid: Some(txid.to_string()),
+        status: "broadcast".to_string(),
+        signed_at: Some(now),
+        broadcast_at: Some(now),
+        confirmed_at: None,
+        created_at: now,
+    };
+    let reason = format!("fill {} replaced", replaced);
+    let by = db::Transition::new(&action.actor_address, &reason).with_txid(txid);
+    db::replace_fill_transaction(&state.db, &order.status, order.version, replaced, &transaction, &by).await
+}
+
+/// Cancel an order (maker only). An order never broadcast is cancelled at
+/// once; one on chain gets a cancel spell returning the offer to the maker,
+/// and is cancelled when that transaction is broadcast.
+pub async fn cancel_order(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+    wallet: WalletFormat,
+    Path(id): Path<String>,
+    Json(req): Json<CancelOrderRequest>,
+) -> Result<Json<FillOrderResponse>, ApiError> {
+    let record = db::get_order_by_id(&state.db, &id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Order not found"))?;
+
+    if !session.owns(&record.maker_address) {
+        return Err(ApiError::forbidden("Only the maker can cancel this order"));
     }
-    
-    // Build cancel spell
-    let spell_built = CANCEL_ORDER_SPELL.to_string();
-    
-    let unsigned_txs = vec![
-        UnsignedTransaction {
-            hex: "0200000001...mock_cancel...".to_string(),
-            txid: format!("mock_cancel_{}", id),
-            inputs_to_sign: vec![
-                InputToSign {
-                    index: 0,
-                    address: "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx".to_string(),
-                    sighash_type: "SIGHASH_DEFAULT".to_string(),
-                }
-            ],
-        }
-    ];
-    
-    Json(FillOrderResponse {
-        order: Order {
-            id: id.clone(),
-            maker_address: "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx".to_string(),
-            offer_token: "TOAD".to_string(),
-            offer_amount: "1000".to_string(),
-            want_token: "BTC".to_string(),
-            want_amount: "10000".to_string(),
-            source_chain: "bitcoin".to_string(),
-            dest_chain: "bitcoin".to_string(),
-            status: OrderStatus::PendingSignature,
-            allow_partial: true,
-            filled_amount: "0".to_string(),
-            expiry_height: 850000,
-            created_at: now.to_rfc3339(),
-            updated_at: now.to_rfc3339(),
-            utxo_id: None,
-        },
+    let message = order_action_message("cancel", &id, &[]);
+    verify_party(record.maker_pubkey.as_deref(), &record.maker_address, &message, &req.signature)
+        .map_err(|e| ApiError::forbidden(format!("Invalid cancel signature: {}", e)))?;
+
+    let template = spell_template(&state.db, CANCEL_ORDER).await;
+    let signing_instructions = SigningInstructions {
+        message: "Sign to cancel your order and unlock your tokens".to_string(),
+        steps: vec![
+            "1. Your escrowed tokens will be returned".to_string(),
+            "2. Sign the transaction to cancel".to_string(),
+        ],
+        broadcast_endpoint: format!("/api/orders/{}/broadcast", id),
+    };
+
+    // Nothing was locked on chain, so there is nothing to spend back
+    if record.status == "pendingsignature" {
+        let by = db::Transition::new(&session.address, "cancelled by maker");
+        db::transition_order(&state.db, &id, record.version, db::OrderChange::status("cancelled"), &by).await?;
+        release_order_locks(&state, &id).await;
+        finish_intent(&state, &id, "cancelled").await;
+
+        return Ok(Json(FillOrderResponse {
+            order: Order { status: OrderStatus::Cancelled, ..Order::from(record) },
+            spell: SpellData {
+                spell_yaml: template.body,
+                spell_yaml_built: String::new(),
+                app_binary: String::new(),
+                prev_txs: vec![],
+            },
+            unsigned_txs: vec![],
+            signing_instructions,
+        }));
+    }
+    if !matches!(record.status.as_str(), "open" | "partiallyfilled") {
+        return Err(ApiError::conflict(format!("Order is {} and cannot be cancelled", record.status)));
+    }
+    let funding_utxo = req
+        .funding_utxo
+        .ok_or_else(|| ApiError::bad_request("funding_utxo is required to cancel an order on chain"))?;
+    let funding_utxo_value = req
+        .funding_utxo_value
+        .ok_or_else(|| ApiError::bad_request("funding_utxo_value is required to cancel an order on chain"))?;
+
+    let order_data = order_spell_data(&record, state.bitcoin.network())?;
+    let cancel_data = CancelSpellData {
+        order_utxo: order_output(&record)?,
+        maker_signature: req.signature.clone(),
+        filled_amount: record.filled_amount.to_string(),
+        remaining_amount: (record.offer_amount - record.filled_amount).to_string(),
+    };
+    let swap_vk = state.apps.vk(Contract::Swap).await;
+    let spell_built = state.charms.build_cancel_order_spell(
+        &template.body,
+        &cancel_data,
+        &order_data,
+        DEFAULT_APP_ID,
+        &swap_vk,
+    ).map_err(|e| ApiError::spell_build_failed(&e))?;
+    let app_binary = state.apps.app_binary(&spell_built).await.map_err(|e| ApiError::app_unavailable(&e))?;
+    record_template_use(&state.db, &id, &template).await;
+
+    lock_funding_utxo(&state.db, &funding_utxo, &record.maker_address, Some(&id), None).await?;
+    let proved_txs = prove_order_spell(
+        &state,
+        &template,
+        &spell_built,
+        &funding_utxo,
+        funding_utxo_value,
+        &record.maker_address,
+        &id,
+    ).await?;
+    record_pending_action(&state, &id, "cancel", &record.maker_address, None, &proved_txs).await?;
+
+    let mut unsigned_txs = signing_payloads(
+        state.chain.as_ref(),
+        &state.sessions,
+        proved_txs,
+        vec![InputToSign::new(0, &record.maker_address)],
+    ).await;
+    wallet.apply(&mut unsigned_txs);
+
+    Ok(Json(FillOrderResponse {
+        order: Order::from(record),
         spell: SpellData {
-            spell_yaml: CANCEL_ORDER_SPELL.to_string(),
+            spell_yaml: template.body,
             spell_yaml_built: spell_built,
-            app_binary: "".to_string(),
+            app_binary,
             prev_txs: vec![],
         },
         unsigned_txs,
-        signing_instructions: SigningInstructions {
-            message: "Sign to cancel your order and unlock your tokens".to_string(),
-            steps: vec![
-                "1. Your escrowed tokens will be returned".to_string(),
-                "2. Sign the transaction to cancel".to_string(),
-            ],
-            broadcast_endpoint: format!("/api/orders/{}/broadcast", id),
-        },
+        signing_instructions,
+    }))
+}
+
+/// Canonical payload a party signs to authorize an order action:
+/// `liquid-nation:order:<action>:<order id>[:<field>...]`
+pub fn order_action_message(action: &str, order_id: &str, fields: &[&str]) -> String {
+    let mut message = format!("liquid-nation:order:{}:{}", action, order_id);
+    for field in fields {
+        message.push(':');
+        message.push_str(field);
+    }
+    message
+}
+
+/// Check a party's signature over `message`: hex by `pubkey` when one is
+/// known, a BIP-322 signature by `address` otherwise
+pub(crate) fn verify_party(pubkey: Option<&str>, address: &str, message: &str, signature: &str) -> Result<(), SignatureError> {
+    match pubkey {
+        Some(pubkey) => verify_signature(pubkey, message, signature),
+        None => verify_bip322(address, message, signature),
+    }
+}
+
+/// Spell data describing a stored order's on-chain state, as its create
+/// spell wrote it
+fn order_spell_data(record: &OrderRecord, network: Network) -> Result<OrderSpellData, ApiError> {
+    let escrow_address = order_escrow_address(record.maker_pubkey.as_deref(), &record.maker_address, &record.id, network)
+        .map_err(|e| ApiError::internal(format!("Cannot derive escrow address: {}", e)))?;
+
+    Ok(OrderSpellData {
+        maker_address: record.maker_address.clone(),
+        maker_pubkey: record.maker_pubkey.clone().unwrap_or_else(|| record.maker_address.clone()),
+        offer_token_id: DEFAULT_TOKEN_ID.to_string(),
+        offer_token_vk: DEFAULT_TOKEN_VK.to_string(),
+        offer_amount: record.offer_amount.to_string(),
+        want_token_id: record.want_token.to_lowercase(),
+        want_amount: record.want_amount.to_string(),
+        expiry_height: record.expiry_height.unwrap_or(0) as u64,
+        allow_partial: record.allow_partial,
+        funding_utxo: record.utxo_id.clone().unwrap_or_default(),
+        escrow_address,
+        dest_chain: chain_to_id(&record.dest_chain),
+        dest_address: record.dest_address.clone().unwrap_or_else(|| record.maker_address.clone()),
     })
 }
 
-/// Partially fill an order
+/// The order's offer output: the first output of its latest transaction
+fn order_output(record: &OrderRecord) -> Result<String, ApiError> {
+    record
+        .tx_id
+        .as_deref()
+        .map(|txid| format!("{}:0", txid))
+        .ok_or_else(|| ApiError::conflict("Order has no broadcast transaction"))
+}
+
+/// Txid of a proved transaction as computed from its hex, which signing
+/// keeps; the prover's own id when the hex does not decode
+pub(crate) fn proved_txid(tx: &ProvedTransaction) -> String {
+    deserialize_hex::<Transaction>(&tx.hex)
+        .map(|decoded| decoded.compute_txid().to_string())
+        .unwrap_or_else(|_| tx.txid.clone())
+}
+
+/// Remember what broadcasting each proved transaction does to the order, so
+/// the broadcast endpoint applies it once the transaction is relayed
+pub(crate) async fn record_pending_action(
+    state: &AppState,
+    order_id: &str,
+    action: &str,
+    actor_address: &str,
+    fill_amount: Option<i64>,
+    txs: &[ProvedTransaction],
+) -> Result<(), ApiError> {
+    for tx in txs {
+        let pending = db::PendingOrderAction {
+            txid: proved_txid(tx),
+            order_id: order_id.to_string(),
+            action: action.to_string(),
+            actor_address: actor_address.to_string(),
+            fill_amount,
+            spends: None,
+            created_at: chrono::Utc::now(),
+        };
+        db::insert_pending_order_action(&state.db, &pending).await?;
+    }
+    Ok(())
+}
+
+/// Partially fill an order: `fill_amount` offer units of what is left
 pub async fn partial_fill_order(
-    State(_state): State<Arc<AppState>>,
+    State(state): State<Arc<AppState>>,
+    wallet: WalletFormat,
     Path(id): Path<String>,
-    Json(req): Json<FillOrderRequest>,
-) -> Json<FillOrderResponse> {
-    let fill_amount = req.fill_amount.clone().unwrap_or("500".to_string());
-    let now = chrono::Utc::now();
-    
-    // Build partial fill spell
-    let spell_built = PARTIAL_FILL_SPELL.to_string();
-    
-    let unsigned_txs = vec![
-        UnsignedTransaction {
-            hex: "0200000001...mock_partial...".to_string(),
-            txid: format!("mock_partial_{}", id),
-            inputs_to_sign: vec![
-                InputToSign {
-                    index: 0,
-                    address: req.taker_address.clone(),
-                    sighash_type: "SIGHASH_DEFAULT".to_string(),
-                }
-            ],
-        }
-    ];
-    
-    Json(FillOrderResponse {
-        order: Order {
-            id: id.clone(),
-            maker_address: "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx".to_string(),
-            offer_token: "TOAD".to_string(),
-            offer_amount: "1000".to_string(),
-            want_token: "BTC".to_string(),
-            want_amount: "10000".to_string(),
-            source_chain: "bitcoin".to_string(),
-            dest_chain: "bitcoin".to_string(),
-            status: OrderStatus::PartiallyFilled,
-            allow_partial: true,
-            filled_amount: fill_amount,
-            expiry_height: 850000,
-            created_at: now.to_rfc3339(),
-            updated_at: now.to_rfc3339(),
-            utxo_id: Some("abc123:1".to_string()),
-        },
-        spell: SpellData {
-            spell_yaml: PARTIAL_FILL_SPELL.to_string(),
-            spell_yaml_built: spell_built,
-            app_binary: "".to_string(),
-            prev_txs: vec![],
-        },
-        unsigned_txs,
-        signing_instructions: SigningInstructions {
-            message: "Sign to partially fill this order".to_string(),
-            steps: vec![
-                "1. You will receive a portion of the offered tokens".to_string(),
-                "2. A portion of your tokens will be sent to the maker".to_string(),
-                "3. The remaining order will stay open".to_string(),
-            ],
-            broadcast_endpoint: format!("/api/orders/{}/broadcast", id),
-        },
-    })
+    NetworkJson(req): NetworkJson<FillOrderRequest>,
+) -> Result<Json<FillOrderResponse>, ApiError> {
+    let amount = req
+        .fill_amount
+        .as_deref()
+        .ok_or_else(|| ApiError::bad_request("fill_amount is required for a partial fill"))?;
+    let amount = parse_amount("fill_amount", amount)?;
+    fill_stored_order(&state, wallet, &id, &req, Some(amount)).await.map(Json)
 }
 
-/// Broadcast a signed transaction
+/// Broadcast a signed transaction. A cancellation or fill takes effect on
+/// the order once relayed; any other order transaction opens it.
 pub async fn broadcast_order(
     State(state): State<Arc<AppState>>,
     Path(id): Path<String>,
     Json(req): Json<BroadcastRequest>,
 ) -> Json<BroadcastResponse> {
     tracing::info!("Broadcasting transaction for order {}", id);
-    
-    // Check if we're in mock mode (transaction hex starts with mock indicator)
-    let is_mock = req.signed_tx_hex.contains("mock") 
-        || req.signed_tx_hex.len() < 100 
-        || state.charms.is_mock_mode();
-    
-    if is_mock {
-        // In mock mode, simulate successful broadcast
-        let mock_txid = format!("mock_broadcast_{}", uuid::Uuid::new_v4());
-        tracing::info!("Mock mode: simulating broadcast with txid {}", mock_txid);
-        
-        // Update order status in database
-        if let Err(e) = db::update_order_status(&state.db, &id, "open").await {
-            tracing::error!("Failed to update order status: {}", e);
-        }
-        if let Err(e) = db::update_order_tx_id(&state.db, &id, &mock_txid).await {
-            tracing::error!("Failed to update order tx_id: {}", e);
-        }
-        
-        return Json(BroadcastResponse {
-            txid: mock_txid,
-            status: "confirmed".to_string(),
-            message: "Transaction simulated successfully (mock mode). In production, tokens would be locked in escrow.".to_string(),
-        });
-    }
-    
-    // Send to Bitcoin network (real mode)
-    match state.bitcoin.send_raw_transaction(&req.signed_tx_hex).await {
+    let rejected = |message: String| {
+        Json(BroadcastResponse {
+            txid: "".to_string(),
+            status: "rejected".to_string(),
+            message,
+            rejection: None,
+        })
+    };
+    let record = match db::get_order_by_id(&state.db, &id).await {
+        Ok(Some(record)) => record,
+        Ok(None) => return rejected("Order not found".to_string()),
+        Err(e) => return rejected(format!("Failed to load order: {}", e)),
+    };
+
+    let pending = match verify_order_broadcast(&state, &record, &req.signed_tx_hex).await {
+        Ok(pending) => pending,
+        Err(reason) => {
+            tracing::warn!("Rejected broadcast for order {}: {}", id, reason);
+            return rejected(reason);
+        }
+    };
+
+    match state.fees.broadcast(state.chain.as_ref(), &req.signed_tx_hex).await {
         Ok(txid) => {
             tracing::info!("Transaction broadcast successful: {}", txid);
-            
-            // Update order status in database
-            if let Err(e) = db::update_order_status(&state.db, &id, "open").await {
-                tracing::error!("Failed to update order status: {}", e);
-            }
-            if let Err(e) = db::update_order_tx_id(&state.db, &id, &txid).await {
-                tracing::error!("Failed to update order tx_id: {}", e);
-            }
-            
+
+            let message = match &pending {
+                Some(action) => {
+                    apply_order_action(&state, &record, action, &txid, &req.signed_tx_hex).await;
+                    format!("Transaction broadcast successfully. The {} is on its way.", action.action.replace('_', " "))
+                }
+                None => {
+                    record_order_broadcast(&state, &record, &txid).await;
+                    finish_intent(&state, &id, "settled").await;
+                    crate::routes::swaps::mark_source_locked(&state, &id, &txid).await;
+                    index_order_spell(&state, &record, &txid, "order_created", record.offer_amount).await;
+                    "Transaction broadcast successfully. Tokens are now locked in escrow.".to_string()
+                }
+            };
+            release_order_locks(&state, &id).await;
+
             Json(BroadcastResponse {
                 txid,
                 status: "confirmed".to_string(),
-                message: "Transaction broadcast successfully. Tokens are now locked in escrow.".to_string(),
+                message,
+                rejection: None,
             })
         }
         Err(e) => {
             tracing::error!("Broadcast failed: {}", e);
-            
+            let rejection = BroadcastRejection::of(&e).cloned();
+
             Json(BroadcastResponse {
                 txid: "".to_string(),
-                status: "failed".to_string(),
+                status: if rejection.is_some() { "rejected" } else { "failed" }.to_string(),
                 message: format!("Failed to broadcast: {}", e),
+                rejection,
             })
         }
     }
 }
+
+/// Apply a relayed cancellation, fill or fill replacement to its order. The transaction is
+/// already on the network, so a failure here is logged for support rather
+/// than returned.
+async fn apply_order_action(
+    state: &AppState,
+    order: &OrderRecord,
+    action: &db::PendingOrderAction,
+    txid: &str,
+    signed_tx_hex: &str,
+) {
+    let applied = match action.action.as_str() {
+        "cancel" => {
+            let by = db::Transition::new(&action.actor_address, "cancelled by maker").with_txid(txid);
+            let remaining = order.offer_amount - order.filled_amount;
+            index_order_spell(state, order, txid, "order_cancelled", remaining).await;
+            db::execute_cancel(&state.db, &order.id, order.version, txid, &by).await
+        }
+        "fill" | "partial_fill" => {
+            let amount = action.fill_amount.unwrap_or_default();
+            index_order_spell(state, order, txid, "order_filled", amount).await;
+            record_fill(state, order, action, txid, signed_tx_hex).await
+        }
+        "fill_replacement" => {
+            let amount = action.fill_amount.unwrap_or_default();
+            index_order_spell(state, order, txid, "order_filled", amount).await;
+            replace_fill(state, order, action, txid, signed_tx_hex).await
+        }
+        other => Err(anyhow::anyhow!("Unknown order action {}", other)),
+    };
+    if let Err(e) = applied {
+        tracing::error!("Failed to apply {} {} to order {}: {:#}", action.action, txid, order.id, e);
+    }
+}
+
+/// Archive finished orders older than `ORDER_ARCHIVE_AFTER_DAYS` (default
+/// 30) as a recurring job, every `ORDER_ARCHIVE_INTERVAL_SECS` (default 3600)
+pub fn spawn_order_archiver(state: Arc<AppState>) {
+    let retention_days: i64 = std::env::var("ORDER_ARCHIVE_AFTER_DAYS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(30);
+    let interval_secs = std::env::var("ORDER_ARCHIVE_INTERVAL_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(3600);
+
+    let jobs = state.jobs.clone();
+    jobs.spawn_recurring("archive_orders", std::time::Duration::from_secs(interval_secs), move || {
+        let state = state.clone();
+        async move {
+            let before = chrono::Utc::now() - chrono::Duration::days(retention_days);
+            let archived = db::archive_finished_orders(&state.db, before).await?;
+            if archived > 0 {
+                tracing::info!("Archived {} finished orders", archived);
+            }
+            Ok(())
+        }
+    });
+}
+
+/// Mark an order open with its broadcast transaction (filled, for an
+/// intent settled in the same transaction), if it is still at the version
+/// read before broadcasting. An order that moved on meanwhile (e.g. flagged
+/// by the spend watcher) keeps the newer state.
+async fn record_order_broadcast(state: &AppState, order: &OrderRecord, txid: &str) {
+    let status = if order.filled_amount >= order.offer_amount { "filled" } else { "open" };
+    let change = db::OrderChange {
+        status,
+        tx_id: Some(txid),
+        filled_amount: None,
+    };
+    let by = db::Transition::new("api", "order transaction broadcast").with_txid(txid);
+    if let Err(e) = db::transition_order(&state.db, &order.id, order.version, change, &by).await {
+        tracing::error!("Failed to record broadcast {} of order {}: {}", txid, order.id, e);
+    }
+}
+
+/// Replace an order's unconfirmed transaction with one paying more (RBF).
+/// The spell proof commits to the whole transaction, so instead of a node
+/// `bumpfee` the transaction's spell is proved again at the higher rate,
+/// spending the same funding UTXO. The maker replaces the order's own
+/// transactions; a stuck fill is the taker's to replace, funded by their
+/// UTXO. The payer signs the replacement and broadcasts it through the
+/// order's broadcast endpoint as before.
+pub async fn bump_order_fee(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+    wallet: WalletFormat,
+    Path(id): Path<String>,
+    body: Option<Json<BumpFeeRequest>>,
+) -> Result<Json<BumpFeeResponse>, ApiError> {
+    let req = body.map(|Json(b)| b).unwrap_or_default();
+    let record = db::get_order_by_id(&state.db, &id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Order not found"))?;
+
+    let txid = record
+        .tx_id
+        .clone()
+        .ok_or_else(|| ApiError::conflict("Order has no broadcast transaction to replace"))?;
+    // Only the latest fill can be stuck: a later one spends its output
+    let fill = db::get_order_fills(&state.db, &id).await?.into_iter().find(|fill| fill.txid == txid);
+    let payer = match &fill {
+        Some(fill) if !session.owns(&fill.taker_address) => {
+            return Err(ApiError::forbidden("Only the taker can bump this fill's fee"));
+        }
+        Some(fill) => fill.taker_address.clone(),
+        None if !session.owns(&record.maker_address) => {
+            return Err(ApiError::forbidden("Only the maker can bump this order's fee"));
+        }
+        None => record.maker_address.clone(),
+    };
+    let spells = db::get_order_spells(&state.db, &id).await?;
+    let spell = match &fill {
+        Some(_) => spells.into_iter().find(|spell| spell_proves(spell, &txid)),
+        None => spells.into_iter().rev().find(|spell| spell.status == "proved"),
+    }
+    .ok_or_else(|| ApiError::conflict("Order has no proved spell to rebuild"))?;
+    let spell_yaml = open_spell(&state.sealer, &spell)?;
+
+    let floor = match req.fee_rate {
+        Some(rate) if rate > 0.0 && rate <= MAX_BUMP_FEE_RATE => rate,
+        Some(_) => {
+            return Err(ApiError::bad_request(format!("fee_rate must be between 0 and {}", MAX_BUMP_FEE_RATE)));
+        }
+        None => {
+            let tier = req.fee_tier.unwrap_or(FeeTier::Normal);
+            state.fees.fee_rate(state.chain.as_ref(), tier).await
+        }
+    };
+
+    let stuck = load_stuck(state.chain.as_ref(), &txid).await?;
+    // A fill spends the order's offer output first and the taker's UTXO second
+    let (funding_utxo, funding_input) = match &fill {
+        Some(_) => {
+            let input = stuck
+                .tx
+                .input
+                .get(1)
+                .ok_or_else(|| ApiError::conflict(format!("Fill {} has no taker input", txid)))?;
+            (input.previous_output, 1)
+        }
+        None => {
+            let utxo = record
+                .utxo_id
+                .as_deref()
+                .map(parse_outpoint)
+                .transpose()?
+                .ok_or_else(|| ApiError::conflict("Order has no funding UTXO"))?;
+            (utxo, 0)
+        }
+    };
+    let funding_value = stuck
+        .spent_value(&funding_utxo)
+        .ok_or_else(|| ApiError::conflict(format!("Transaction {} does not spend the order's funding UTXO", txid)))?;
+    let fee_rate = replacement_rate(stuck.paid.fee_rate, floor).ok_or_else(|| BumpError::AlreadyPays {
+        txid: txid.clone(),
+        paid: stuck.paid.fee_rate,
+        target: floor,
+    })?;
+
+    lock_funding_utxo(&state.db, &funding_utxo, &payer, Some(&id), None).await?;
+    let origin = SpellOrigin {
+        operation: "bump-fee",
+        template_name: spell.template_name.as_deref(),
+        template_version: spell.template_version,
+    };
+    let proved_txs = prove_at_rate(
+        &state,
+        origin,
+        &spell_yaml,
+        &funding_utxo,
+        funding_value,
+        &payer,
+        &id,
+        fee_rate,
+        Some(&txid),
+    ).await?;
+    tracing::info!("Rebuilt order {} transaction {} at {} sat/vB", id, txid, fee_rate);
+
+    // The replacement moves the fill over once relayed, spending the offer
+    // output the stuck fill spent
+    if let Some(fill) = &fill {
+        let spends = stuck.tx.input[0].previous_output.to_string();
+        for tx in &proved_txs {
+            let pending = db::PendingOrderAction {
+                txid: proved_txid(tx),
+                order_id: id.clone(),
+                action: "fill_replacement".to_string(),
+                actor_address: payer.clone(),
+                fill_amount: Some(fill.amount),
+                spends: Some(spends.clone()),
+                created_at: chrono::Utc::now(),
+            };
+            db::insert_pending_order_action(&state.db, &pending).await?;
+        }
+    }
+
+    let mut unsigned_txs = signing_payloads(
+        state.chain.as_ref(),
+        &state.sessions,
+        proved_txs,
+        vec![InputToSign::new(funding_input, &payer)],
+    ).await;
+    wallet.apply(&mut unsigned_txs);
+
+    let message = match fill {
+        Some(_) => "Sign the replacement to speed up your fill's transaction",
+        None => "Sign the replacement to speed up your order's transaction",
+    };
+    Ok(Json(BumpFeeResponse {
+        order: Order::from(record),
+        replaced_txid: txid,
+        paid: stuck.paid,
+        fee_rate,
+        unsigned_txs,
+        signing_instructions: SigningInstructions {
+            message: message.to_string(),
+            steps: vec![
+                "1. The replacement spends the same UTXO at a higher fee".to_string(),
+                "2. Sign it with your Bitcoin wallet".to_string(),
+                "3. Submit it to broadcast; the stuck transaction is dropped".to_string(),
+            ],
+            broadcast_endpoint: format!("/api/orders/{}/broadcast", id),
+        },
+    }))
+}
+
+/// Whether `txid` is one of the transactions proved from `spell`
+fn spell_proves(spell: &db::SpellRecord, txid: &str) -> bool {
+    spell
+        .transactions
+        .as_deref()
+        .and_then(|txs| serde_json::from_str::<Vec<ProvedTransaction>>(txs).ok())
+        .is_some_and(|txs| txs.iter().any(|tx| proved_txid(tx) == txid))
+}
+
+/// Check a signed transaction is a spell transaction proved for the order
+/// and spends the UTXO it acts on: the offer output for a cancellation or
+/// fill, the funding UTXO otherwise. Returns what it does to the order, if
+/// it is a cancellation or fill.
+async fn verify_order_broadcast(
+    state: &AppState,
+    order: &OrderRecord,
+    signed_tx_hex: &str,
+) -> Result<Option<db::PendingOrderAction>, String> {
+    let txid = deserialize_hex::<Transaction>(signed_tx_hex.trim())
+        .map(|tx| tx.compute_txid().to_string())
+        .map_err(|e| BroadcastMismatch::Undecodable(e.to_string()).to_string())?;
+    let pending = db::get_pending_order_action(&state.db, &order.id, &txid)
+        .await
+        .map_err(|e| format!("Failed to load pending order actions: {}", e))?;
+    let proved = db::get_proved_transactions(&state.db, &order.id)
+        .await
+        .map_err(|e| format!("Failed to load proved transactions: {}", e))?;
+
+    let expected_spend = match &pending {
+        Some(action) => action.spends.clone().or_else(|| order_output(order).ok()),
+        None => order.utxo_id.clone(),
+    };
+    state
+        .charms
+        .prover()
+        .check_signed(signed_tx_hex, &proved, expected_spend.as_deref())
+        .map_err(|e| format!("{} for order {}", e, order.id))?;
+    Ok(pending)
+}
+
+/// Live orders' UTXOs: the funding UTXO, which only the order's own
+/// transactions may spend, and once the order is open its offer output
+#[async_trait]
+impl SpendWatcher for AppState {
+    async fn watched(&self) -> anyhow::Result<Vec<WatchedOutpoint>> {
+        let orders = db::get_live_orders(&self.db).await?;
+        let ids: Vec<String> = orders.iter().map(|order| order.id.clone()).collect();
+        let proved = db::get_proved_txids(&self.db, &ids).await?;
+
+        let mut watched = Vec::new();
+        for order in &orders {
+            let expected = proved.get(&order.id).map(Vec::as_slice).unwrap_or_default();
+            if let Some(utxo) = order.utxo_id.as_deref() {
+                watched.extend(WatchedOutpoint::new(utxo, &order.id, expected, ExternalSpend::Conflicting));
+            }
+            if let Some(txid) = order.tx_id.as_deref().filter(|_| order.status != "pendingsignature") {
+                // The offer sits in the order transaction's first output
+                let offer = format!("{}:0", txid);
+                watched.extend(WatchedOutpoint::new(&offer, &order.id, expected, ExternalSpend::ExternallySpent));
+            }
+        }
+        Ok(watched)
+    }
+
+    async fn spent_externally(&self, watched: &WatchedOutpoint, txid: &bitcoin::Txid) -> anyhow::Result<()> {
+        let status = match watched.kind {
+            ExternalSpend::Conflicting => "conflicting",
+            ExternalSpend::ExternallySpent => "externally_spent",
+        };
+        // Only a live order is flagged; one cancelled or filled meanwhile stays so
+        let Some(order) = db::get_order_by_id(&self.db, &watched.subject_id).await? else {
+            return Ok(());
+        };
+        if !matches!(order.status.as_str(), "pendingsignature" | "open" | "partiallyfilled") {
+            return Ok(());
+        }
+        let txid = txid.to_string();
+        let reason = format!("{} spent by another transaction", watched.outpoint);
+        let by = db::Transition::new("spend_watcher", &reason).with_txid(&txid);
+        db::transition_order(&self.db, &order.id, order.version, db::OrderChange::status(status), &by).await?;
+        release_order_locks(self, &watched.subject_id).await;
+        self.events.publish(Event::new(
+            format!("order.{}", status),
+            watched.subject_id.clone(),
+            serde_json::json!({ "outpoint": watched.outpoint.to_string(), "txid": txid.to_string() }),
+        ));
+        Ok(())
+    }
+}
+
+/// Free the funding UTXO locks held by an order's draft spells
+pub(crate) async fn release_order_locks(state: &AppState, order_id: &str) {
+    if let Err(e) = db::release_order_utxo_locks(&state.db, order_id).await {
+        tracing::warn!("Failed to release UTXO locks for order {}: {}", order_id, e);
+    }
+}
+
+/// Record an order spell in the spell index for wallet history
+async fn index_order_spell(state: &AppState, order: &OrderRecord, txid: &str, action: &str, amount: i64) {
+    let record = db::SpellTransactionRecord {
+        txid: txid.to_string(),
+        action: action.to_string(),
+        order_id: Some(order.id.clone()),
+        escrow_id: None,
+        token_id: Some(order.offer_token.clone()),
+        amount: Some(amount.to_string()),
+        created_at: chrono::Utc::now(),
+    };
+    if let Err(e) = db::record_spell_transaction(&state.db, &record).await {
+        tracing::error!("Failed to index order spell {}: {}", txid, e);
+    }
+}
+
+/// What an order spell was built for, kept with its record
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SpellOrigin<'a> {
+    pub operation: &'a str,
+    pub template_name: Option<&'a str>,
+    pub template_version: Option<i32>,
+}
+
+impl<'a> From<&'a SpellTemplate> for SpellOrigin<'a> {
+    fn from(template: &'a SpellTemplate) -> Self {
+        Self {
+            operation: &template.name,
+            template_name: Some(&template.name),
+            template_version: Some(template.version),
+        }
+    }
+}
+
+/// Keep the spell proved for an order, with its outcome, for support
+async fn record_order_spell(
+    state: &AppState,
+    order_id: &str,
+    origin: SpellOrigin<'_>,
+    spell: &str,
+    request_hash: String,
+    proved: &anyhow::Result<Vec<ProvedTransaction>>,
+    started_at: chrono::DateTime<chrono::Utc>,
+) {
+    let (status, transactions, error) = match proved {
+        Ok(txs) => ("proved", serde_json::to_string(txs).ok(), None),
+        Err(e) => ("failed", None, Some(format!("{:#}", e))),
+    };
+    let (spell, private_inputs) = match seal_spell(&state.sealer, spell) {
+        Ok(sealed) => sealed,
+        Err(e) => {
+            tracing::warn!("Failed to seal spell for order {}: {}", order_id, e);
+            return;
+        }
+    };
+    let finished_at = chrono::Utc::now();
+    let record = db::SpellRecord {
+        id: Uuid::new_v4().to_string(),
+        order_id: order_id.to_string(),
+        spell,
+        private_inputs,
+        request_hash,
+        status: status.to_string(),
+        transactions,
+        error,
+        prove_ms: (finished_at - started_at).num_milliseconds(),
+        created_at: finished_at,
+        operation: Some(origin.operation.to_string()),
+        template_name: origin.template_name.map(str::to_string),
+        template_version: origin.template_version,
+        prove_job_id: None,
+        started_at: Some(started_at),
+        finished_at: Some(finished_at),
+    };
+    if let Err(e) = db::insert_spell(&state.db, &record).await {
+        tracing::warn!("Failed to record spell for order {}: {}", order_id, e);
+    }
+}
+
+/// Prove a built order spell. On failure the order's UTXO locks are
+/// released so the draft can be retried.
+pub(crate) async fn prove_order_spell(
+    state: &AppState,
+    template: &SpellTemplate,
+    spell_built: &str,
+    funding_utxo: &OutPoint,
+    funding_utxo_value: u64,
+    change_address: &str,
+    order_id: &str,
+) -> Result<Vec<ProvedTransaction>, ApiError> {
+    let fee_rate = state.fees.fee_rate(state.chain.as_ref(), FeeTier::Normal).await;
+    prove_at_rate(
+        state,
+        template.into(),
+        spell_built,
+        funding_utxo,
+        funding_utxo_value,
+        change_address,
+        order_id,
+        fee_rate,
+        None,
+    )
+    .await
+}
+
+/// `prove_order_spell` at a given fee rate (sat/vB), replacing the unconfirmed
+/// transaction `replaces` if given
+#[allow(clippy::too_many_arguments)]
+async fn prove_at_rate(
+    state: &AppState,
+    origin: SpellOrigin<'_>,
+    spell_built: &str,
+    funding_utxo: &OutPoint,
+    funding_utxo_value: u64,
+    change_address: &str,
+    order_id: &str,
+    fee_rate: f64,
+    replaces: Option<&str>,
+) -> Result<Vec<ProvedTransaction>, ApiError> {
+    let binaries = state.apps.binaries_for_spell(spell_built).await;
+
+    let prove_request = SpellProveRequest {
+        spell: spell_built.to_string(),
+        binaries,
+        prev_txs: vec![],
+        funding_utxo: *funding_utxo,
+        funding_utxo_value,
+        change_address: change_address.to_string(),
+        fee_rate,
+        chain: state.charms.chain().to_string(),
+        replaces: replaces.map(str::to_string),
+    };
+
+    let request_hash = prove_request.cache_key();
+    let started_at = chrono::Utc::now();
+    let proved = prove_cached(&state.charms, &state.db, &state.events, prove_request, order_id).await;
+    record_order_spell(state, order_id, origin, spell_built, request_hash, &proved, started_at).await;
+
+    match proved {
+        Ok(txs) => Ok(txs),
+        Err(e) => {
+            tracing::error!("Proving order {} spell failed: {:#}", order_id, e);
+            release_order_locks(state, order_id).await;
+            Err(ApiError::prove_failed(&e))
+        }
+    }
+}
+
+/// Order state for handler tests
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::*;
+    use bitcoin::Network;
+
+    use crate::services::mock_chain::MockChain;
+    use crate::services::prover::{MockProver, ProverBackend};
+
+    /// Order state over the mock prover and chain, as the server builds it
+    /// on startup
+    pub fn test_state(db: DbPool) -> AppState {
+        let network = Network::Testnet4;
+        let prover: Arc<dyn ProverBackend> = Arc::new(MockProver);
+        let chain: Arc<dyn ChainBackend> = Arc::new(MockChain::new(network));
+        AppState {
+            charms: CharmsService::new(prover.clone(), network).with_chain(chain.clone()),
+            apps: Arc::new(AppArtifacts::from_env(prover.as_ref())),
+            bitcoin: BitcoinService::new("http://127.0.0.1:1", network),
+            tip: Arc::new(ChainTip::new(chain.clone())),
+            chain,
+            fees: FeeEstimator::new(network),
+            events: EventBus::new(),
+            sessions: SessionStore::new(db.clone()),
+            tokens: TokenRegistry::new(),
+            sealer: Arc::new(Sealer::new(vec![("k1".to_string(), [1; 32])])),
+            jobs: Arc::new(JobQueue::from_env(db.clone())),
+            db,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use bitcoin::secp256k1::{Keypair, Message, PublicKey, Secp256k1, SecretKey};
+
+    use crate::routes::orders::testing::test_state;
+    use crate::services::sessions::Session;
+    use crate::services::signatures::message_digest;
+
+    /// Someone other than the taker
+    const OTHER_ADDRESS: &str = "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7";
+
+    fn taker_keypair() -> Keypair {
+        Keypair::from_secret_key(&Secp256k1::new(), &SecretKey::from_slice(&[6u8; 32]).unwrap())
+    }
+
+    /// Taproot address of the taker's key
+    fn taker_address() -> String {
+        let secp = Secp256k1::new();
+        bitcoin::Address::p2tr(&secp, taker_keypair().x_only_public_key().0, None, Network::Testnet4).to_string()
+    }
+
+    /// Open partially fillable order of 1000 TOAD for 10000 BTC, on chain
+    fn open_order() -> OrderRecord {
+        let secp = Secp256k1::new();
+        let maker = SecretKey::from_slice(&[5u8; 32]).unwrap();
+        let now = chrono::Utc::now();
+        OrderRecord {
+            id: Uuid::new_v4().to_string(),
+            maker_address: "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx".to_string(),
+            offer_token: "TOAD".to_string(),
+            offer_amount: 1000,
+            want_token: "BTC".to_string(),
+            want_amount: 10_000,
+            source_chain: "bitcoin".to_string(),
+            dest_chain: "bitcoin".to_string(),
+            status: "open".to_string(),
+            allow_partial: true,
+            filled_amount: 0,
+            expiry_height: None,
+            utxo_id: None,
+            tx_id: Some("dd".repeat(32)),
+            created_at: now,
+            updated_at: now,
+            version: 0,
+            archived_at: None,
+            maker_pubkey: Some(hex::encode(PublicKey::from_secret_key(&secp, &maker).serialize())),
+            dest_address: None,
+        }
+    }
+
+    /// Fill of `amount` (all that is left when `None`) for `taker_address`,
+    /// signed by the taker's key over `signed_amount`
+    fn fill_request_for(taker_address: &str, order_id: &str, amount: Option<&str>, signed_amount: &str) -> FillOrderRequest {
+        let secp = Secp256k1::new();
+        let keypair = taker_keypair();
+        let taker_utxo: OutPoint = format!("{}:1", "ee".repeat(32)).parse().unwrap();
+        let message = order_action_message("fill", order_id, &[taker_address, signed_amount, &taker_utxo.to_string()]);
+        let msg = Message::from_digest(message_digest(&message));
+        FillOrderRequest {
+            taker_address: taker_address.to_string(),
+            taker_pubkey: Some(hex::encode(keypair.x_only_public_key().0.serialize())),
+            taker_utxo,
+            taker_utxo_value: 20_000,
+            fill_amount: amount.map(str::to_string),
+            signature: hex::encode(secp.sign_schnorr_no_aux_rand(&msg, &keypair).serialize()),
+        }
+    }
+
+    /// Fill of `amount` (all that is left when `None`) signed by the taker
+    /// over `signed_amount`
+    fn fill_request(order_id: &str, amount: Option<&str>, signed_amount: &str) -> FillOrderRequest {
+        fill_request_for(&taker_address(), order_id, amount, signed_amount)
+    }
+
+    async fn pending_actions(db: &DbPool, order_id: &str) -> Vec<db::PendingOrderAction> {
+        let txids: Vec<String> = sqlx::query_scalar("SELECT txid FROM pending_order_actions WHERE order_id = $1")
+            .bind(order_id)
+            .fetch_all(db)
+            .await
+            .unwrap();
+        let mut actions = Vec::new();
+        for txid in txids {
+            actions.push(db::get_pending_order_action(db, order_id, &txid).await.unwrap().unwrap());
+        }
+        actions
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_fills_need_the_taker_signature_and_apply_on_broadcast() {
+        let db = db::testing::fresh_db().await;
+
+        let state = Arc::new(test_state(db));
+        let order = open_order();
+        db::insert_order(&state.db, &order).await.unwrap();
+        let wallet = || WalletFormat::raw(Network::Testnet4);
+
+        // A signature over other terms proves nothing
+        let forged = fill_request(&order.id, Some("400"), "500");
+        let err = partial_fill_order(State(state.clone()), wallet(), Path(order.id.clone()), NetworkJson(forged))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, StatusCode::FORBIDDEN);
+        assert!(pending_actions(&state.db, &order.id).await.is_empty());
+
+        // A signed partial fill is proved and waits for its broadcast
+        let partial = fill_request(&order.id, Some("400"), "400");
+        let drafted = partial_fill_order(State(state.clone()), wallet(), Path(order.id.clone()), NetworkJson(partial))
+            .await
+            .unwrap()
+            .0;
+        assert!(!drafted.unsigned_txs.is_empty());
+        let actions = pending_actions(&state.db, &order.id).await;
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].action, "partial_fill");
+        assert_eq!(actions[0].fill_amount, Some(400));
+        assert_eq!(actions[0].actor_address, taker_address());
+
+        let stored = db::get_order_by_id(&state.db, &order.id).await.unwrap().unwrap();
+        assert_eq!(stored.filled_amount, 0);
+        // As the broadcast endpoint does once the transaction is relayed
+        apply_order_action(&state, &stored, &actions[0], &actions[0].txid, "00").await;
+        release_order_locks(&state, &order.id).await;
+        let stored = db::get_order_by_id(&state.db, &order.id).await.unwrap().unwrap();
+        assert_eq!(stored.status, "partiallyfilled");
+        assert_eq!(stored.filled_amount, 400);
+
+        // The rest is taken by a fill without an amount
+        let rest = fill_request(&order.id, None, "600");
+        let drafted = fill_order(State(state.clone()), wallet(), Path(order.id.clone()), NetworkJson(rest))
+            .await
+            .unwrap()
+            .0;
+        assert_eq!(drafted.order.filled_amount, "400");
+        let action = pending_actions(&state.db, &order.id)
+            .await
+            .into_iter()
+            .find(|action| action.action == "fill")
+            .unwrap();
+        assert_eq!(action.fill_amount, Some(600));
+        apply_order_action(&state, &stored, &action, &action.txid, "00").await;
+
+        let stored = db::get_order_by_id(&state.db, &order.id).await.unwrap().unwrap();
+        assert_eq!(stored.status, "filled");
+        assert_eq!(stored.filled_amount, 1000);
+        let fills = db::get_order_fills(&state.db, &order.id).await.unwrap();
+        let mut amounts: Vec<i64> = fills.iter().map(|fill| fill.amount).collect();
+        amounts.sort();
+        assert_eq!(amounts, vec![400, 600]);
+        assert!(fills.iter().all(|fill| fill.taker_address == taker_address()));
+
+        // Nothing is left to fill
+        let more = fill_request(&order.id, None, "0");
+        let err = fill_order(State(state), wallet(), Path(order.id.clone()), NetworkJson(more)).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::CONFLICT);
+    }
+
+    fn session(address: &str) -> WalletSession {
+        WalletSession(Session {
+            token: "token".to_string(),
+            user_id: "user".to_string(),
+            address: address.to_string(),
+            addresses: vec![address.to_string()],
+            pubkeys: vec![],
+            expires_at: chrono::Utc::now() + chrono::Duration::hours(1),
+        })
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_stuck_fills_are_replaced_by_their_taker() {
+        let db = db::testing::fresh_db().await;
+
+        let state = Arc::new(test_state(db));
+        let order = open_order();
+        db::insert_order(&state.db, &order).await.unwrap();
+        let offer = order_output(&order).unwrap();
+        let wallet = || WalletFormat::raw(Network::Testnet4);
+
+        let partial = fill_request(&order.id, Some("400"), "400");
+        let drafted = partial_fill_order(State(state.clone()), wallet(), Path(order.id.clone()), NetworkJson(partial))
+            .await
+            .unwrap()
+            .0;
+        assert!(!drafted.unsigned_txs.is_empty());
+        let fill = pending_actions(&state.db, &order.id).await.remove(0);
+        let stored = db::get_order_by_id(&state.db, &order.id).await.unwrap().unwrap();
+        apply_order_action(&state, &stored, &fill, &fill.txid, "00").await;
+        let filled = db::get_order_by_id(&state.db, &order.id).await.unwrap().unwrap();
+        assert_eq!(filled.tx_id.as_deref(), Some(fill.txid.as_str()));
+
+        // The fill is the taker's to bump, not the maker's
+        let err = bump_order_fee(
+            State(state.clone()),
+            session(&order.maker_address),
+            wallet(),
+            Path(order.id.clone()),
+            None,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.status, StatusCode::FORBIDDEN);
+        assert_eq!(err.message, "Only the taker can bump this fill's fee");
+
+        // Its replacement spends the offer output the fill spent, and moves
+        // the fill over once relayed
+        let replacement = db::PendingOrderAction {
+            txid: "ab".repeat(32),
+            order_id: order.id.clone(),
+            action: "fill_replacement".to_string(),
+            actor_address: taker_address(),
+            fill_amount: Some(400),
+            spends: Some(offer),
+            created_at: chrono::Utc::now(),
+        };
+        db::insert_pending_order_action(&state.db, &replacement).await.unwrap();
+        apply_order_action(&state, &filled, &replacement, &replacement.txid, "00").await;
+
+        let stored = db::get_order_by_id(&state.db, &order.id).await.unwrap().unwrap();
+        assert_eq!(stored.status, "partiallyfilled");
+        assert_eq!(stored.filled_amount, 400);
+        assert_eq!(stored.tx_id.as_deref(), Some(replacement.txid.as_str()));
+        let fills = db::get_order_fills(&state.db, &order.id).await.unwrap();
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].txid, replacement.txid);
+        assert!(pending_actions(&state.db, &order.id).await.is_empty());
+
+        // A stale replacement, for a fill no longer current, changes nothing
+        apply_order_action(&state, &filled, &replacement, &"cd".repeat(32), "00").await;
+        let after = db::get_order_by_id(&state.db, &order.id).await.unwrap().unwrap();
+        assert_eq!(after.version, stored.version);
+    }
+
+    #[tokio::test]
+    async fn test_fills_need_the_key_of_the_taker_address() {
+        // Rejected before the order is looked up, so the database is never reached
+        let state = Arc::new(test_state(DbPool::connect_lazy("postgres://localhost/unused").unwrap()));
+        let order_id = Uuid::new_v4().to_string();
+
+        // A valid signature by a key that does not control the address
+        let borrowed = fill_request_for(OTHER_ADDRESS, &order_id, None, "1000");
+        let err = fill_order(State(state), WalletFormat::raw(Network::Testnet4), Path(order_id), NetworkJson(borrowed))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, StatusCode::FORBIDDEN);
+        assert_eq!(err.message, "taker_pubkey is not the key of taker_address");
+    }
+
+    #[tokio::test]
+    async fn test_create_order_rejects_invalid_tags() {
+        // Rejected before anything is looked up, so the database is never reached
+        let state = Arc::new(test_state(DbPool::connect_lazy("postgres://localhost/unused").unwrap()));
+        let mut req: CreateOrderRequest = serde_json::from_value(serde_json::json!({
+            "maker_address": "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx",
+            "offer_token": "TOAD",
+            "offer_amount": "1000",
+            "want_token": "BTC",
+            "want_amount": "10000",
+            "source_chain": "bitcoin",
+            "dest_chain": "bitcoin",
+            "allow_partial": false,
+            "expiry_blocks": 144,
+        }))
+        .unwrap();
+        req.tags = vec!["otc".to_string(), "no spaces!".to_string()];
+
+        let err = create_order(State(state), WalletFormat::raw(Network::Testnet4), NetworkJson(req))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        assert_eq!(err.message, "Tag 'no spaces!' contains invalid characters");
+    }
+}
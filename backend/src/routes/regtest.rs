@@ -0,0 +1,137 @@
+//! Regtest harness endpoints
+//!
+//! With `REGTEST_HARNESS=true` on a regtest node, integration tests drive the
+//! chain through `/api/regtest`: mine blocks, fund an address from the node's
+//! wallet and confirm a broadcast transaction straight away. Every endpoint
+//! answers 403 otherwise.
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::routes::error::ApiError;
+use crate::routes::orders::AppState;
+use crate::services::network;
+
+/// Most blocks one request may mine
+const MAX_BLOCKS: u32 = 1000;
+
+/// Mine blocks request
+#[derive(Debug, Deserialize)]
+pub struct MineRequest {
+    /// Default 1
+    #[serde(default = "default_blocks")]
+    pub blocks: u32,
+    /// Coinbase address; defaults to the harness wallet
+    #[serde(default)]
+    pub address: Option<String>,
+}
+
+fn default_blocks() -> u32 {
+    1
+}
+
+#[derive(Debug, Serialize)]
+pub struct MineResponse {
+    pub block_hashes: Vec<String>,
+    pub height: u64,
+}
+
+/// Fund an address request
+#[derive(Debug, Deserialize)]
+pub struct FundRequest {
+    pub address: String,
+    pub amount_sats: u64,
+    /// Mine the funding transaction right away (default true)
+    #[serde(default = "default_confirm")]
+    pub confirm: bool,
+}
+
+fn default_confirm() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize)]
+pub struct FundResponse {
+    pub txid: String,
+    /// Block the funding was mined in, when confirmed
+    pub block_hash: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConfirmResponse {
+    pub txid: String,
+    pub block_hash: String,
+}
+
+fn require_harness(state: &AppState) -> Result<(), ApiError> {
+    if !state.bitcoin.regtest_enabled() {
+        return Err(ApiError::forbidden("Regtest harness is disabled"));
+    }
+    Ok(())
+}
+
+fn check_address(state: &AppState, address: &str) -> Result<(), ApiError> {
+    network::check_address(address, state.bitcoin.network())
+        .map(|_| ())
+        .map_err(|e| ApiError::bad_request(e.to_string()))
+}
+
+/// Mine blocks
+pub async fn mine_blocks(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<MineRequest>,
+) -> Result<Json<MineResponse>, ApiError> {
+    require_harness(&state)?;
+    if req.blocks == 0 || req.blocks > MAX_BLOCKS {
+        return Err(ApiError::bad_request(format!("blocks must be between 1 and {}", MAX_BLOCKS)));
+    }
+    if let Some(address) = &req.address {
+        check_address(&state, address)?;
+    }
+
+    let block_hashes = state.bitcoin.generate_to_address(req.blocks, req.address.as_deref()).await?;
+    let height = state.bitcoin.get_blockchain_info().await?.blocks;
+    Ok(Json(MineResponse { block_hashes, height }))
+}
+
+/// Send coins to an address from the node's harness wallet
+pub async fn fund_address(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<FundRequest>,
+) -> Result<Json<FundResponse>, ApiError> {
+    require_harness(&state)?;
+    check_address(&state, &req.address)?;
+    if req.amount_sats == 0 {
+        return Err(ApiError::bad_request("amount_sats must be positive"));
+    }
+
+    let txid = state.bitcoin.fund_address(&req.address, req.amount_sats).await?;
+    let block_hash = match req.confirm {
+        true => Some(state.bitcoin.confirm_transaction(&txid).await?),
+        false => None,
+    };
+    tracing::info!("Regtest harness funded {} with {} sats in {}", req.address, req.amount_sats, txid);
+    Ok(Json(FundResponse { txid, block_hash }))
+}
+
+/// Mine a mempool transaction into a block of its own
+pub async fn confirm_transaction(
+    State(state): State<Arc<AppState>>,
+    Path(txid): Path<String>,
+) -> Result<Json<ConfirmResponse>, ApiError> {
+    require_harness(&state)?;
+    match state.chain.tx_status(&txid).await? {
+        None => return Err(ApiError::not_found(format!("Transaction {} is not in the mempool", txid))),
+        Some(status) if status.is_confirmed() => {
+            return Err(ApiError::conflict(format!("Transaction {} is already confirmed", txid)));
+        }
+        Some(_) => {}
+    }
+
+    let block_hash = state.bitcoin.confirm_transaction(&txid).await?;
+    Ok(Json(ConfirmResponse { txid, block_hash }))
+}
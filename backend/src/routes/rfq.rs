@@ -0,0 +1,533 @@
+//! Request-for-quote endpoints
+//!
+//! Takers post an RFQ, makers respond with signed quotes, and accepting a quote
+//! materializes an order together with its fill spell. Used for OTC-sized trades
+//! that should not sit on the public order book.
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use bitcoin::OutPoint;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::db::{self, OrderRecord, RfqQuoteRecord, RfqRecord};
+use crate::routes::auth::WalletSession;
+use crate::routes::error::ApiError;
+use crate::routes::network_json::{BitcoinAddresses, NetworkJson};
+use crate::routes::orders::{
+    chain_to_id, normalize_chain, order_escrow_address, parse_amount, prove_order_spell, proved_txid,
+    record_pending_action, signing_payloads, AppState, InputToSign, Order, SigningInstructions, SpellData, UnsignedTransaction, DEFAULT_APP_ID,
+    DEFAULT_TOKEN_ID, DEFAULT_TOKEN_VK,
+};
+use crate::routes::spell_templates::{record_template_use, spell_template, CREATE_ORDER, FILL_ORDER};
+use crate::routes::wallet::{lock_funding_utxo, parse_outpoint};
+use crate::routes::wallet_formats::WalletFormat;
+use crate::services::chain_tip;
+use crate::services::charms::{FillSpellData, OrderSpellData};
+use crate::services::signatures::verify_signature;
+use crate::services::spell_check::Contract;
+
+/// Default RFQ lifetime when the taker does not specify one
+const DEFAULT_RFQ_TTL_SECS: i64 = 15 * 60;
+/// Shortest and longest RFQ lifetime a taker may ask for
+const MIN_RFQ_TTL_SECS: i64 = 60;
+const MAX_RFQ_TTL_SECS: i64 = 24 * 60 * 60;
+/// Longest a maker's quote may stay valid
+const MAX_QUOTE_VALID_SECS: i64 = 60 * 60;
+
+/// Create RFQ request (from the taker's perspective)
+#[derive(Debug, Deserialize)]
+pub struct CreateRfqRequest {
+    pub taker_address: String,
+    /// Token the taker wants to receive
+    pub buy_token: String,
+    pub buy_amount: String,
+    /// Token the taker will pay with
+    pub sell_token: String,
+    pub source_chain: String,
+    pub dest_chain: String,
+    #[serde(default)]
+    pub ttl_secs: Option<i64>,
+}
+
+impl BitcoinAddresses for CreateRfqRequest {
+    fn bitcoin_addresses(&self) -> Vec<(&'static str, &str)> {
+        vec![("taker_address", self.taker_address.as_str())]
+    }
+}
+
+/// Maker quote submission
+#[derive(Debug, Deserialize)]
+pub struct SubmitQuoteRequest {
+    pub maker_address: String,
+    pub maker_pubkey: String,
+    /// Amount of `sell_token` the maker asks for in exchange
+    pub sell_amount: String,
+    pub funding_utxo: OutPoint,
+    pub funding_utxo_value: u64,
+    /// Maker's hex signature with `maker_pubkey` over `quote_message`
+    pub signature: String,
+    /// Between 1 and `MAX_QUOTE_VALID_SECS`
+    pub valid_for_secs: i64,
+}
+
+impl BitcoinAddresses for SubmitQuoteRequest {
+    fn bitcoin_addresses(&self) -> Vec<(&'static str, &str)> {
+        vec![("maker_address", self.maker_address.as_str())]
+    }
+}
+
+/// Accept quote request (from the taker)
+#[derive(Debug, Deserialize)]
+pub struct AcceptQuoteRequest {
+    pub taker_utxo: OutPoint,
+    #[serde(default)]
+    pub taker_pubkey: Option<String>,
+    pub taker_utxo_value: u64,
+    #[serde(default)]
+    pub expiry_blocks: Option<u64>,
+}
+
+/// RFQ with its quotes
+#[derive(Debug, Serialize)]
+pub struct RfqResponse {
+    pub rfq: RfqRecord,
+    pub quotes: Vec<RfqQuoteRecord>,
+}
+
+/// Result of accepting a quote
+#[derive(Debug, Serialize)]
+pub struct AcceptQuoteResponse {
+    pub order: Order,
+    pub create_spell: SpellData,
+    pub fill_spell: SpellData,
+    pub unsigned_txs: Vec<UnsignedTransaction>,
+    pub signing_instructions: SigningInstructions,
+}
+
+/// Create a new RFQ
+pub async fn create_rfq(
+    State(state): State<Arc<AppState>>,
+    NetworkJson(req): NetworkJson<CreateRfqRequest>,
+) -> Result<Json<RfqRecord>, ApiError> {
+    if req.buy_amount.parse::<u64>().map(|a| a == 0).unwrap_or(true) {
+        return Err(ApiError::bad_request("buy_amount must be a positive integer"));
+    }
+
+    let now = chrono::Utc::now();
+    let ttl = req.ttl_secs.unwrap_or(DEFAULT_RFQ_TTL_SECS);
+    if !(MIN_RFQ_TTL_SECS..=MAX_RFQ_TTL_SECS).contains(&ttl) {
+        return Err(ApiError::bad_request(format!(
+            "ttl_secs must be between {} and {}",
+            MIN_RFQ_TTL_SECS, MAX_RFQ_TTL_SECS
+        )));
+    }
+
+    let rfq = RfqRecord {
+        id: Uuid::new_v4().to_string(),
+        taker_address: req.taker_address,
+        buy_token: req.buy_token,
+        buy_amount: req.buy_amount,
+        sell_token: req.sell_token,
+        source_chain: normalize_chain(&req.source_chain),
+        dest_chain: normalize_chain(&req.dest_chain),
+        status: "open".to_string(),
+        accepted_quote_id: None,
+        order_id: None,
+        expires_at: now + chrono::Duration::seconds(ttl),
+        created_at: now,
+        updated_at: now,
+    };
+
+    db::insert_rfq(&state.db, &rfq).await?;
+    tracing::info!("RFQ {} created by {}", rfq.id, rfq.taker_address);
+
+    Ok(Json(rfq))
+}
+
+/// List open RFQs for makers to quote on
+pub async fn list_rfqs(State(state): State<Arc<AppState>>) -> Result<Json<Vec<RfqRecord>>, ApiError> {
+    Ok(Json(db::get_open_rfqs(&state.db).await?))
+}
+
+/// Get an RFQ with all of its quotes
+pub async fn get_rfq(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<RfqResponse>, ApiError> {
+    let rfq = db::get_rfq_by_id(&state.db, &id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("RFQ not found"))?;
+    let quotes = db::get_quotes_for_rfq(&state.db, &id).await?;
+
+    Ok(Json(RfqResponse { rfq, quotes }))
+}
+
+/// Canonical message a maker signs to commit to a quote:
+/// `liquid-nation:rfq-quote:<rfq id>:` followed by the maker address, sell
+/// amount, funding UTXO, its value and `valid_for_secs`, colon-separated
+pub fn quote_message(rfq_id: &str, req: &SubmitQuoteRequest) -> String {
+    format!(
+        "liquid-nation:rfq-quote:{}:{}:{}:{}:{}:{}",
+        rfq_id, req.maker_address, req.sell_amount, req.funding_utxo, req.funding_utxo_value, req.valid_for_secs,
+    )
+}
+
+/// Check a quote's terms are in bounds and signed by the maker
+pub fn verify_quote(rfq_id: &str, req: &SubmitQuoteRequest) -> Result<(), ApiError> {
+    if req.signature.is_empty() {
+        return Err(ApiError::bad_request("Quote must be signed by the maker"));
+    }
+    if req.sell_amount.parse::<u64>().map(|a| a == 0).unwrap_or(true) {
+        return Err(ApiError::bad_request("sell_amount must be a positive integer"));
+    }
+    if !(1..=MAX_QUOTE_VALID_SECS).contains(&req.valid_for_secs) {
+        return Err(ApiError::bad_request(format!(
+            "valid_for_secs must be between 1 and {}",
+            MAX_QUOTE_VALID_SECS
+        )));
+    }
+    verify_signature(&req.maker_pubkey, &quote_message(rfq_id, req), &req.signature)
+        .map_err(|e| ApiError::forbidden(format!("Invalid quote signature: {}", e)))
+}
+
+/// Submit a maker quote for an open RFQ
+pub async fn submit_quote(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    NetworkJson(req): NetworkJson<SubmitQuoteRequest>,
+) -> Result<Json<RfqQuoteRecord>, ApiError> {
+    let rfq = db::get_rfq_by_id(&state.db, &id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("RFQ not found"))?;
+
+    let now = chrono::Utc::now();
+    if rfq.status != "open" || rfq.expires_at <= now {
+        return Err(ApiError::conflict("RFQ is no longer accepting quotes"));
+    }
+    verify_quote(&id, &req)?;
+
+    let quote = RfqQuoteRecord {
+        id: Uuid::new_v4().to_string(),
+        rfq_id: id,
+        maker_address: req.maker_address,
+        maker_pubkey: req.maker_pubkey,
+        sell_amount: req.sell_amount,
+        funding_utxo: req.funding_utxo.to_string(),
+        funding_utxo_value: Some(req.funding_utxo_value as i64),
+        signature: req.signature,
+        status: "pending".to_string(),
+        valid_until: now + chrono::Duration::seconds(req.valid_for_secs),
+        created_at: now,
+    };
+
+    db::insert_rfq_quote(&state.db, &quote).await?;
+    tracing::info!("Quote {} submitted for RFQ {}", quote.id, quote.rfq_id);
+
+    Ok(Json(quote))
+}
+
+/// Accept a quote (the RFQ's taker only), materializing the order and its
+/// fill spell. The fill takes effect when its transaction is broadcast after
+/// the order's.
+pub async fn accept_quote(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+    Path((id, quote_id)): Path<(String, String)>,
+    wallet: WalletFormat,
+    Json(req): Json<AcceptQuoteRequest>,
+) -> Result<Json<AcceptQuoteResponse>, ApiError> {
+    let rfq = db::get_rfq_by_id(&state.db, &id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("RFQ not found"))?;
+    if !session.owns(&rfq.taker_address) {
+        return Err(ApiError::forbidden("Only the RFQ's taker can accept its quotes"));
+    }
+
+    let now = chrono::Utc::now();
+    if rfq.status != "open" || rfq.expires_at <= now {
+        return Err(ApiError::conflict("RFQ is no longer open"));
+    }
+
+    let quote = db::get_quotes_for_rfq(&state.db, &id)
+        .await?
+        .into_iter()
+        .find(|q| q.id == quote_id)
+        .ok_or_else(|| ApiError::not_found("Quote not found"))?;
+
+    if quote.status != "pending" || quote.valid_until <= now {
+        return Err(ApiError::conflict("Quote has expired"));
+    }
+    let funding_utxo_value = quote
+        .funding_utxo_value
+        .ok_or_else(|| ApiError::conflict("Quote does not state its funding UTXO value"))?;
+
+    let order_id = Uuid::new_v4().to_string();
+
+    let offer_amount = parse_amount("buy_amount", &rfq.buy_amount)?;
+    let want_amount = parse_amount("sell_amount", &quote.sell_amount)?;
+    let current_height = state.tip.height().await?;
+    let expiry_height = chain_tip::expiry_height(current_height, req.expiry_blocks.unwrap_or(144)).map_err(ApiError::bad_request)?;
+
+    let network = state.bitcoin.network();
+    let escrow_address = order_escrow_address(Some(&quote.maker_pubkey), &quote.maker_address, &order_id, network)
+        .map_err(|e| ApiError::bad_request(format!("Cannot derive escrow address: {}", e)))?;
+
+    // The maker offers what the taker wants to buy
+    let order_spell_data = OrderSpellData {
+        maker_address: quote.maker_address.clone(),
+        maker_pubkey: quote.maker_pubkey.clone(),
+        offer_token_id: DEFAULT_TOKEN_ID.to_string(),
+        offer_token_vk: DEFAULT_TOKEN_VK.to_string(),
+        offer_amount: offer_amount.to_string(),
+        want_token_id: rfq.sell_token.to_lowercase(),
+        want_amount: want_amount.to_string(),
+        expiry_height,
+        allow_partial: false,
+        funding_utxo: quote.funding_utxo.clone(),
+        escrow_address,
+        dest_chain: chain_to_id(&rfq.dest_chain),
+        dest_address: quote.maker_address.clone(),
+    };
+
+    let swap_vk = state.apps.vk(Contract::Swap).await;
+    let create_template = spell_template(&state.db, CREATE_ORDER).await;
+    let create_spell = state
+        .charms
+        .build_create_order_spell(&create_template.body, &order_spell_data, DEFAULT_APP_ID, &swap_vk)
+        .map_err(|e| ApiError::spell_build_failed(&e))?;
+    // The fill spell runs the same app
+    let app_binary = state.apps.app_binary(&create_spell).await.map_err(|e| ApiError::app_unavailable(&e))?;
+
+    // Claim both funding UTXOs so concurrent drafts cannot spend them too
+    let maker_utxo = parse_outpoint(&quote.funding_utxo)?;
+    lock_funding_utxo(&state.db, &maker_utxo, &quote.maker_address, Some(&order_id), None).await?;
+    lock_funding_utxo(&state.db, &req.taker_utxo, &rfq.taker_address, Some(&order_id), None).await?;
+
+    let create_txs = prove_order_spell(
+        &state,
+        &create_template,
+        &create_spell,
+        &maker_utxo,
+        funding_utxo_value as u64,
+        &quote.maker_address,
+        &order_id,
+    )
+    .await?;
+
+    // The order NFT lands in the first output of the last (spell) transaction
+    let order_utxo = create_txs
+        .last()
+        .map(|tx| format!("{}:0", proved_txid(tx)))
+        .unwrap_or_default();
+
+    let fill_spell_data = FillSpellData {
+        order_utxo,
+        taker_utxo: req.taker_utxo.to_string(),
+        taker_pubkey: req.taker_pubkey.clone().unwrap_or_else(|| rfq.taker_address.clone()),
+        taker_address: rfq.taker_address.clone(),
+        maker_address: quote.maker_address.clone(),
+        offer_amount: offer_amount.to_string(),
+        want_amount: want_amount.to_string(),
+    };
+
+    let fill_template = spell_template(&state.db, FILL_ORDER).await;
+    let fill_spell = state.charms.build_fill_order_spell(
+        &fill_template.body,
+        &fill_spell_data,
+        &order_spell_data,
+        DEFAULT_APP_ID,
+        &swap_vk,
+    )
+    .map_err(|e| ApiError::spell_build_failed(&e))?;
+
+    let fill_txs = prove_order_spell(
+        &state,
+        &fill_template,
+        &fill_spell,
+        &req.taker_utxo,
+        req.taker_utxo_value,
+        &rfq.taker_address,
+        &order_id,
+    )
+    .await?;
+
+    let record = OrderRecord {
+        id: order_id.clone(),
+        maker_address: quote.maker_address.clone(),
+        offer_token: rfq.buy_token.clone(),
+        offer_amount,
+        want_token: rfq.sell_token.clone(),
+        want_amount,
+        source_chain: rfq.source_chain.clone(),
+        dest_chain: rfq.dest_chain.clone(),
+        status: "pendingsignature".to_string(),
+        allow_partial: false,
+        filled_amount: 0,
+        expiry_height: Some(expiry_height as i64),
+        utxo_id: Some(quote.funding_utxo.clone()),
+        tx_id: None,
+        created_at: now,
+        updated_at: now,
+        version: 0,
+        archived_at: None,
+        maker_pubkey: Some(quote.maker_pubkey.clone()),
+        dest_address: Some(quote.maker_address.clone()),
+    };
+
+    db::accept_rfq_quote(&state.db, &id, &quote_id, &record)
+        .await
+        .map_err(|e| ApiError::conflict(e.to_string()))?;
+    tracing::info!("RFQ {} accepted quote {} as order {}", id, quote_id, order_id);
+    record_template_use(&state.db, &order_id, &create_template).await;
+    record_template_use(&state.db, &order_id, &fill_template).await;
+    record_pending_action(&state, &order_id, "fill", &rfq.taker_address, Some(offer_amount), &fill_txs).await?;
+
+    let mut unsigned_txs = signing_payloads(
+        state.chain.as_ref(),
+        &state.sessions,
+        create_txs,
+        vec![InputToSign::new(0, &quote.maker_address)],
+    )
+    .await;
+    unsigned_txs.extend(
+        signing_payloads(
+            state.chain.as_ref(),
+            &state.sessions,
+            fill_txs,
+            vec![InputToSign::new(1, &rfq.taker_address)],
+        )
+        .await,
+    );
+    wallet.apply(&mut unsigned_txs);
+
+    Ok(Json(AcceptQuoteResponse {
+        order: Order::from(record),
+        create_spell: SpellData {
+            spell_yaml: create_template.body,
+            spell_yaml_built: create_spell,
+            app_binary: app_binary.clone(),
+            prev_txs: vec![],
+        },
+        fill_spell: SpellData {
+            spell_yaml: fill_template.body,
+            spell_yaml_built: fill_spell,
+            app_binary,
+            prev_txs: vec![],
+        },
+        unsigned_txs,
+        signing_instructions: SigningInstructions {
+            message: "Maker and taker sign to settle the quoted trade".to_string(),
+            steps: vec![
+                "1. Maker signs the order creation transaction".to_string(),
+                "2. Taker signs the fill transaction".to_string(),
+                "3. Submit both signed transactions to broadcast".to_string(),
+            ],
+            broadcast_endpoint: format!("/api/orders/{}/broadcast", order_id),
+        },
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routes::orders::testing::test_state;
+    use crate::services::signatures::message_digest;
+    use axum::http::StatusCode;
+    use bitcoin::secp256k1::{Keypair, Message, Secp256k1, SecretKey};
+
+    /// Quote for `rfq-1` signed with an x-only (Schnorr) maker key
+    fn signed_quote() -> SubmitQuoteRequest {
+        signed_quote_for("rfq-1")
+    }
+
+    fn signed_quote_for(rfq_id: &str) -> SubmitQuoteRequest {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[9u8; 32]).unwrap());
+        let mut req = SubmitQuoteRequest {
+            maker_address: "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx".to_string(),
+            maker_pubkey: hex::encode(keypair.x_only_public_key().0.serialize()),
+            sell_amount: "25000".to_string(),
+            funding_utxo: format!("{}:1", "cc".repeat(32)).parse().unwrap(),
+            funding_utxo_value: 20_000,
+            signature: String::new(),
+            valid_for_secs: 300,
+        };
+        let msg = Message::from_digest(message_digest(&quote_message(rfq_id, &req)));
+        req.signature = hex::encode(secp.sign_schnorr_no_aux_rand(&msg, &keypair).serialize());
+        req
+    }
+
+    #[test]
+    fn test_verify_quote() {
+        let quote = signed_quote();
+        assert!(verify_quote("rfq-1", &quote).is_ok());
+        // Signed for one RFQ only
+        assert_eq!(verify_quote("rfq-2", &quote).unwrap_err().status, StatusCode::FORBIDDEN);
+
+        let mut cheaper = signed_quote();
+        cheaper.sell_amount = "1".to_string();
+        assert_eq!(verify_quote("rfq-1", &cheaper).unwrap_err().status, StatusCode::FORBIDDEN);
+
+        let mut underfunded = signed_quote();
+        underfunded.funding_utxo_value = 1;
+        assert_eq!(verify_quote("rfq-1", &underfunded).unwrap_err().status, StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn test_quote_bounds() {
+        for valid_for_secs in [0, -1, MAX_QUOTE_VALID_SECS + 1] {
+            let mut quote = signed_quote();
+            quote.valid_for_secs = valid_for_secs;
+            assert_eq!(verify_quote("rfq-1", &quote).unwrap_err().status, StatusCode::BAD_REQUEST);
+        }
+
+        let mut free = signed_quote();
+        free.sell_amount = "0".to_string();
+        assert_eq!(verify_quote("rfq-1", &free).unwrap_err().status, StatusCode::BAD_REQUEST);
+
+        let mut unsigned = signed_quote();
+        unsigned.signature.clear();
+        assert_eq!(verify_quote("rfq-1", &unsigned).unwrap_err().status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_submit_quote_stores_only_signed_quotes() {
+        let db = db::testing::fresh_db().await;
+
+        let state = Arc::new(test_state(db));
+        let request = CreateRfqRequest {
+            taker_address: "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7".to_string(),
+            buy_token: "TOAD".to_string(),
+            buy_amount: "1000".to_string(),
+            sell_token: "BTC".to_string(),
+            source_chain: "bitcoin".to_string(),
+            dest_chain: "bitcoin".to_string(),
+            ttl_secs: None,
+        };
+        let rfq = create_rfq(State(state.clone()), NetworkJson(request)).await.unwrap().0;
+
+        // Signed for another RFQ, or over other terms
+        let elsewhere = signed_quote_for("rfq-other");
+        let err = submit_quote(State(state.clone()), Path(rfq.id.clone()), NetworkJson(elsewhere)).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::FORBIDDEN);
+        let mut cheaper = signed_quote_for(&rfq.id);
+        cheaper.sell_amount = "1".to_string();
+        let err = submit_quote(State(state.clone()), Path(rfq.id.clone()), NetworkJson(cheaper)).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::FORBIDDEN);
+        assert!(get_rfq(State(state.clone()), Path(rfq.id.clone())).await.unwrap().0.quotes.is_empty());
+
+        let quote = submit_quote(State(state.clone()), Path(rfq.id.clone()), NetworkJson(signed_quote_for(&rfq.id)))
+            .await
+            .unwrap()
+            .0;
+        let quotes = get_rfq(State(state), Path(rfq.id)).await.unwrap().0.quotes;
+        assert_eq!(quotes.len(), 1);
+        assert_eq!(quotes[0].id, quote.id);
+        assert_eq!(quotes[0].status, "pending");
+    }
+}
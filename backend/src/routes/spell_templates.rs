@@ -0,0 +1,213 @@
+//! Spell template registry
+//!
+//! Templates are stored in `spell_templates` with a version number per change.
+//! Handlers build spells from the active version of a template and record the
+//! version they used against the order or escrow. The YAML files under
+//! `apps/*/spells` are compiled in, seeded as version 1 on startup and used as
+//! a fallback (version 0) when the database cannot be read. Admins add
+//! versions and roll back through `/api/admin/spell-templates`.
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::db::{self, DbPool, SpellTemplateRecord, SpellTemplateUseRecord};
+use crate::routes::auth::AdminToken;
+use crate::routes::error::ApiError;
+use crate::routes::orders::AppState;
+
+pub(crate) const CREATE_ORDER: &str = "create-order";
+pub(crate) const FILL_ORDER: &str = "fill-order";
+pub(crate) const CANCEL_ORDER: &str = "cancel-order";
+pub(crate) const PARTIAL_FILL: &str = "partial-fill";
+pub(crate) const TRANSFER_TOKEN: &str = "transfer-token";
+pub(crate) const CREATE_ESCROW: &str = "create-escrow";
+pub(crate) const RELEASE_ESCROW: &str = "release-escrow";
+pub(crate) const REFUND_ESCROW: &str = "refund-escrow";
+pub(crate) const DISPUTE_ESCROW: &str = "dispute-escrow";
+pub(crate) const RESOLVE_DISPUTE: &str = "resolve-dispute";
+pub(crate) const RELEASE_MILESTONE: &str = "release-milestone";
+
+/// Compiled-in templates, seeded as version 1
+const BUILTIN_TEMPLATES: &[(&str, &str)] = &[
+    (CREATE_ORDER, include_str!("../../../apps/swap-app/spells/create-order.yaml")),
+    (FILL_ORDER, include_str!("../../../apps/swap-app/spells/fill-order.yaml")),
+    (CANCEL_ORDER, include_str!("../../../apps/swap-app/spells/cancel-order.yaml")),
+    (PARTIAL_FILL, include_str!("../../../apps/swap-app/spells/partial-fill.yaml")),
+    (TRANSFER_TOKEN, include_str!("../../../apps/swap-app/spells/transfer-token.yaml")),
+    (CREATE_ESCROW, include_str!("../../../apps/escrow-app/spells/create-escrow.yaml")),
+    (RELEASE_ESCROW, include_str!("../../../apps/escrow-app/spells/release-escrow.yaml")),
+    (REFUND_ESCROW, include_str!("../../../apps/escrow-app/spells/refund-escrow.yaml")),
+    (DISPUTE_ESCROW, include_str!("../../../apps/escrow-app/spells/dispute-escrow.yaml")),
+    (RESOLVE_DISPUTE, include_str!("../../../apps/escrow-app/spells/resolve-dispute.yaml")),
+    (RELEASE_MILESTONE, include_str!("../../../apps/escrow-app/spells/release-milestone.yaml")),
+];
+
+/// A template version as used to build a spell
+#[derive(Debug, Clone)]
+pub(crate) struct SpellTemplate {
+    pub name: String,
+    /// 0 for the compiled-in fallback
+    pub version: i32,
+    pub body: String,
+}
+
+/// Add a template version request
+#[derive(Debug, Deserialize)]
+pub struct AddSpellTemplateRequest {
+    /// Spell YAML with `${variable}` placeholders
+    pub body: String,
+    /// Make the new version active right away (default true)
+    #[serde(default = "default_activate")]
+    pub activate: bool,
+}
+
+fn default_activate() -> bool {
+    true
+}
+
+/// Store the compiled-in templates as version 1 of any template not yet in
+/// the registry
+pub async fn seed_builtin_templates(pool: &DbPool) {
+    for (name, body) in BUILTIN_TEMPLATES {
+        match db::seed_spell_template(pool, name, body).await {
+            Ok(true) => tracing::info!("Seeded spell template {} v1", name),
+            Ok(false) => {}
+            Err(e) => tracing::warn!("Failed to seed spell template {}: {}", name, e),
+        }
+    }
+}
+
+/// Active version of a template, or the compiled-in one if the registry
+/// cannot be read
+pub(crate) async fn spell_template(pool: &DbPool, name: &str) -> SpellTemplate {
+    match db::get_active_spell_template(pool, name).await {
+        Ok(Some(record)) => {
+            return SpellTemplate {
+                name: record.name,
+                version: record.version,
+                body: record.body,
+            }
+        }
+        Ok(None) => tracing::warn!("No active spell template {}; using the built-in one", name),
+        Err(e) => tracing::warn!("Failed to load spell template {}: {}; using the built-in one", name, e),
+    }
+
+    SpellTemplate {
+        name: name.to_string(),
+        version: 0,
+        body: builtin_template(name).unwrap_or_default().to_string(),
+    }
+}
+
+/// Record the template version that built a spell for an order or escrow
+pub(crate) async fn record_template_use(pool: &DbPool, subject_id: &str, template: &SpellTemplate) {
+    if let Err(e) = db::record_spell_template_use(pool, subject_id, &template.name, template.version).await {
+        tracing::warn!(
+            "Failed to record spell template {} v{} for {}: {}",
+            template.name,
+            template.version,
+            subject_id,
+            e
+        );
+    }
+}
+
+fn builtin_template(name: &str) -> Option<&'static str> {
+    BUILTIN_TEMPLATES
+        .iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|(_, body)| *body)
+}
+
+/// Active version of every template (admin)
+pub async fn list_spell_templates(
+    _admin: AdminToken,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<SpellTemplateRecord>>, ApiError> {
+    Ok(Json(db::get_active_spell_templates(&state.db).await?))
+}
+
+/// Every version of a template, newest first (admin)
+pub async fn list_spell_template_versions(
+    _admin: AdminToken,
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<Json<Vec<SpellTemplateRecord>>, ApiError> {
+    known_template(&name)?;
+    Ok(Json(db::get_spell_template_versions(&state.db, &name).await?))
+}
+
+/// Add a new version of a template (admin)
+pub async fn add_spell_template_version(
+    _admin: AdminToken,
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Json(req): Json<AddSpellTemplateRequest>,
+) -> Result<Json<SpellTemplateRecord>, ApiError> {
+    known_template(&name)?;
+    validate_template(&req.body)?;
+
+    let template = db::insert_spell_template_version(&state.db, &name, &req.body, req.activate).await?;
+    tracing::info!(
+        "Added spell template {} v{}{}",
+        name,
+        template.version,
+        if template.active { " (active)" } else { "" }
+    );
+    Ok(Json(template))
+}
+
+/// Make an existing version the active one, e.g. to roll back (admin)
+pub async fn activate_spell_template_version(
+    _admin: AdminToken,
+    State(state): State<Arc<AppState>>,
+    Path((name, version)): Path<(String, i32)>,
+) -> Result<Json<Vec<SpellTemplateRecord>>, ApiError> {
+    known_template(&name)?;
+    if !db::activate_spell_template(&state.db, &name, version).await? {
+        return Err(ApiError::not_found(format!("Spell template {} has no version {}", name, version)));
+    }
+    tracing::info!("Activated spell template {} v{}", name, version);
+
+    Ok(Json(db::get_spell_template_versions(&state.db, &name).await?))
+}
+
+/// Template versions used for an order's spells
+pub async fn get_order_spell_templates(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<SpellTemplateUseRecord>>, ApiError> {
+    Ok(Json(db::get_spell_template_uses(&state.db, &id).await?))
+}
+
+/// Template versions used for any order's or escrow's spells (admin)
+pub async fn get_spell_template_uses(
+    _admin: AdminToken,
+    State(state): State<Arc<AppState>>,
+    Path(subject_id): Path<String>,
+) -> Result<Json<Vec<SpellTemplateUseRecord>>, ApiError> {
+    Ok(Json(db::get_spell_template_uses(&state.db, &subject_id).await?))
+}
+
+/// Only templates a handler builds spells from can be versioned
+fn known_template(name: &str) -> Result<(), ApiError> {
+    builtin_template(name)
+        .map(|_| ())
+        .ok_or_else(|| ApiError::not_found(format!("Unknown spell template {}", name)))
+}
+
+/// A template must be a YAML mapping declaring its apps
+fn validate_template(body: &str) -> Result<(), ApiError> {
+    let value: serde_yaml::Value = serde_yaml::from_str(body)
+        .map_err(|e| ApiError::bad_request(format!("Template is not valid YAML: {}", e)))?;
+
+    let has_apps = value.as_mapping().is_some_and(|m| m.contains_key("apps"));
+    if !has_apps {
+        return Err(ApiError::bad_request("Template must be a YAML mapping with an `apps` section"));
+    }
+    Ok(())
+}
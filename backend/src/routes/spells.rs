@@ -1,51 +1,242 @@
 //! Charms spell and transaction endpoints
+//!
+//! Proving takes minutes, so `POST /api/spells/prove` only queues a job and
+//! returns its id. Large app binaries are uploaded once to
+//! `POST /api/spells/binaries` (signed-in users, within a per-user quota, or
+//! the admin token) and referenced by hash; ones no unfinished job needs are
+//! pruned after a day. Background workers claim queued jobs from the database and
+//! call the prover; clients poll `GET /api/spells/jobs/:id` or follow
+//! `GET /api/spells/jobs/:id/events` (server-sent events) until the job
+//! succeeds or fails. `POST /api/spells/check` runs our contracts natively
+//! first, so a spell that would be rejected fails without waiting on a proof,
+//! `POST /api/spells/decode` reads the spell back out of a transaction, and
+//! `GET /api/spells/estimate` quotes an operation's proving time and fee
+//! before the user starts it.
 
+use anyhow::Context;
 use axum::{
-    extract::Path,
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::sse::{Event as SseEvent, KeepAlive, Sse},
     Json,
 };
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use bitcoin::{BlockHash, OutPoint, Txid};
+use futures_util::stream::{self, Stream};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+
+use crate::db::{self, DbPool, ProveJobRecord};
+use crate::routes::auth::SessionOrAdmin;
+use crate::routes::error::ApiError;
+use crate::routes::network_json::{BitcoinAddresses, NetworkJson};
+use crate::routes::orders::AppState;
+use crate::services::chain::{BroadcastRejection, ChainBackend};
+use crate::services::charms::{CharmsService, ProveProgress, ProvedTransaction, SpellProveRequest};
+use crate::services::events::{Event, EventBus};
+use crate::services::fees::{fee_for, FeeSource, FeeTier, SpellOperation, COMMIT_TX_VBYTES};
+use crate::services::spell_check::{self, Contract, ContractKeys, SpellCheck};
+use crate::services::sealing::Sealer;
+use crate::services::spell_decode::{decode_spell_hex, DecodedOutput, DecodedSpell};
+use crate::services::spell_schema::{join_private_inputs, split_private_inputs};
+use crate::services::spv::{spv_proof, SpvProof};
+
+/// Succeeded prove jobs the proving time estimate looks back over
+const PROVE_HISTORY_JOBS: i64 = 50;
+/// Proving time quoted before any job has succeeded
+const DEFAULT_PROVE_SECS: u64 = 300;
+/// Longest confirmation target the node estimates for
+const MAX_ESTIMATE_TARGET_BLOCKS: u16 = 1008;
+
+/// Leading bytes of every WebAssembly module
+const WASM_MAGIC: &[u8] = b"\0asm";
+
+/// Bytes of app binaries a user may have stored at once
+/// (`APP_BINARY_QUOTA_BYTES`, default 256 MiB)
+fn app_binary_quota() -> i64 {
+    std::env::var("APP_BINARY_QUOTA_BYTES")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(256 * 1024 * 1024)
+}
+
+/// How long an uploaded binary is kept unless an unfinished prove job
+/// references it (`APP_BINARY_TTL_SECS`, default one day)
+fn app_binary_ttl() -> chrono::Duration {
+    let secs = std::env::var("APP_BINARY_TTL_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(86_400);
+    chrono::Duration::seconds(secs)
+}
+
+/// Where sealed private inputs are stored, bound into their encryption
+const PRIVATE_INPUTS_PURPOSE: &str = "prove_jobs.private_inputs";
+const SPELL_PRIVATE_INPUTS_PURPOSE: &str = "spells.private_inputs";
+/// Queue job type the prove workers run
+const PROVE_JOB: &str = "prove";
+
+/// Payload of a queued `prove` job
+#[derive(Debug, Serialize, Deserialize)]
+struct ProveJobPayload {
+    prove_job_id: String,
+}
 
 /// Prove spell request
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ProveSpellRequest {
     pub spell_yaml: String,
+    /// App binary (base64); the built swap app binary is used when empty
+    #[serde(default)]
     pub app_binary: String,
+    /// Verification key of `app_binary` (default: the swap app's)
+    #[serde(default)]
+    pub app_vk: Option<String>,
+    /// Binaries uploaded through `POST /api/spells/binaries`, as
+    /// verification key -> SHA-256 hash, so large binaries stay out of the
+    /// request
+    #[serde(default)]
+    pub binary_refs: BTreeMap<String, String>,
+    /// Raw previous transactions (hex); any the spell spends that are missing
+    /// are fetched from the node
+    #[serde(default)]
     pub prev_txs: Vec<String>,
-    pub funding_utxo: String,
+    pub funding_utxo: OutPoint,
     pub funding_utxo_value: u64,
     pub change_address: String,
-    pub fee_rate: f64,
+    /// sat/vB; defaults to the current estimate for `fee_tier`
+    #[serde(default)]
+    pub fee_rate: Option<f64>,
+    /// Tier to estimate the fee rate for when `fee_rate` is not given
+    /// (default: normal)
+    #[serde(default)]
+    pub fee_tier: Option<FeeTier>,
+    /// Order the spell is for; the spell and its outcome are then kept with
+    /// the order's spells
+    #[serde(default)]
+    pub order_id: Option<String>,
+}
+
+impl BitcoinAddresses for ProveSpellRequest {
+    fn bitcoin_addresses(&self) -> Vec<(&'static str, &str)> {
+        vec![("change_address", self.change_address.as_str())]
+    }
 }
 
-/// Prove spell response
+/// Stored app binary
 #[derive(Debug, Serialize)]
-pub struct ProveSpellResponse {
-    pub success: bool,
-    pub transactions: Vec<TransactionData>,
-    pub total_fee: u64,
+pub struct UploadedBinary {
+    /// SHA-256 (hex) to pass in `binary_refs`
+    pub hash: String,
+    pub size: usize,
+}
+
+/// Queued prove job
+#[derive(Debug, Serialize)]
+pub struct ProveJobAccepted {
+    pub job_id: String,
+    pub status: String,
+    pub poll_url: String,
+    pub events_url: String,
+}
+
+/// Prove job state as returned to clients
+#[derive(Debug, Serialize)]
+pub struct ProveJob {
+    pub id: String,
+    pub status: String,
+    /// Proved transactions, once the job succeeded
+    pub transactions: Option<Vec<ProvedTransaction>>,
     pub error: Option<String>,
+    /// Error code of a failed job, as API errors carry it
+    pub error_code: Option<String>,
+    pub attempts: i32,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
-/// Transaction data
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TransactionData {
-    pub txid: String,
-    pub hex: String,
-    pub inputs_to_sign: Vec<InputToSign>,
+impl ProveJob {
+    fn is_finished(&self) -> bool {
+        self.status == "succeeded" || self.status == "failed"
+    }
 }
 
-/// Input that needs signing
-#[derive(Debug, Serialize, Deserialize)]
-pub struct InputToSign {
-    pub index: u32,
-    pub sighash_type: String,
-    pub script_pubkey: String,
+impl From<ProveJobRecord> for ProveJob {
+    fn from(record: ProveJobRecord) -> Self {
+        Self {
+            transactions: record.result.as_deref().and_then(|r| serde_json::from_str(r).ok()),
+            id: record.id,
+            status: record.status,
+            error: record.error,
+            error_code: record.error_code,
+            attempts: record.attempts,
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+        }
+    }
+}
+
+/// Spell dry-run request
+#[derive(Debug, Deserialize)]
+pub struct CheckSpellRequest {
+    pub spell_yaml: String,
+    /// Contract to run for apps with our verification key; required while the
+    /// swap and escrow apps share one
+    #[serde(default)]
+    pub contract: Option<Contract>,
+}
+
+/// Spell cost estimate query
+#[derive(Debug, Deserialize)]
+pub struct SpellEstimateQuery {
+    pub operation: SpellOperation,
+    /// Confirmation target in blocks (default: the normal tier's)
+    pub target: Option<u16>,
+}
+
+/// Expected proving time, from recently succeeded prove jobs
+#[derive(Debug, Serialize)]
+pub struct ProvingTimeEstimate {
+    pub median_secs: u64,
+    pub p90_secs: u64,
+    /// Jobs the estimate is based on; 0 when it is the default
+    pub samples: usize,
+}
+
+/// What an operation costs before the user starts it
+#[derive(Debug, Serialize)]
+pub struct SpellEstimate {
+    pub operation: SpellOperation,
+    pub proving: ProvingTimeEstimate,
+    pub commit_vbytes: u64,
+    pub spell_vbytes: u64,
+    pub target_blocks: u16,
+    /// sat/vB
+    pub fee_rate: f64,
+    pub fee_source: FeeSource,
+    /// Fee for the commit and spell transactions together
+    pub fee_sats: u64,
+}
+
+/// Spell decode request
+#[derive(Debug, Deserialize)]
+pub struct DecodeSpellRequest {
+    pub tx_hex: String,
 }
 
 /// Broadcast transaction request
 #[derive(Debug, Deserialize)]
 pub struct BroadcastRequest {
+    /// Prove job whose transactions were signed
+    pub job_id: String,
     pub signed_txs: Vec<String>,
 }
 
@@ -55,145 +246,797 @@ pub struct BroadcastResponse {
     pub success: bool,
     pub txids: Vec<String>,
     pub error: Option<String>,
+    /// Why the mempool refused the first transaction that failed, when it did
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rejection: Option<BroadcastRejection>,
 }
 
 /// Transaction status
 #[derive(Debug, Serialize)]
 pub struct TransactionStatus {
     pub txid: String,
+    /// mempool or confirmed
+    pub status: String,
     pub confirmed: bool,
     pub confirmations: u32,
     pub block_height: Option<u64>,
     pub block_hash: Option<String>,
+    /// Charms in the transaction's outputs, when it carries a spell
+    pub charms_created: Vec<DecodedOutput>,
+    /// Charms held by the UTXOs the spell spent
+    pub charms_consumed: Vec<ConsumedCharms>,
+    /// Proof of inclusion in its block, once confirmed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spv_proof: Option<SpvProof>,
 }
 
-/// Prove a spell and generate transactions
+/// Charms a spent UTXO held
+#[derive(Debug, Serialize)]
+pub struct ConsumedCharms {
+    pub utxo_id: String,
+    pub charms: BTreeMap<String, serde_json::Value>,
+}
+
+/// Queue a spell for proving; returns the job id immediately
 pub async fn prove_spell(
-    Json(req): Json<ProveSpellRequest>,
-) -> Json<ProveSpellResponse> {
-    // TODO: Call Charms prover API
-    // For mock mode, use local prover
-    
-    let charms_api_url = std::env::var("CHARMS_PROVE_API_URL")
-        .unwrap_or_else(|_| "https://v8.charms.dev/spells/prove".to_string());
-    
-    let mock_mode = std::env::var("MOCK_MODE")
-        .map(|v| v == "true")
-        .unwrap_or(true);  // Default to mock for development
-
-    if mock_mode {
-        // Mock response for development
-        return Json(ProveSpellResponse {
-            success: true,
-            transactions: vec![
-                TransactionData {
-                    txid: "mock_commit_txid".to_string(),
-                    hex: "0200000001...".to_string(),
-                    inputs_to_sign: vec![
-                        InputToSign {
-                            index: 0,
-                            sighash_type: "SIGHASH_ALL".to_string(),
-                            script_pubkey: "0014...".to_string(),
-                        },
-                    ],
-                },
-                TransactionData {
-                    txid: "mock_spell_txid".to_string(),
-                    hex: "0200000001...".to_string(),
-                    inputs_to_sign: vec![],  // Spell tx uses commit output
-                },
-            ],
-            total_fee: 500,
+    State(state): State<Arc<AppState>>,
+    NetworkJson(mut req): NetworkJson<ProveSpellRequest>,
+) -> Result<(StatusCode, Json<ProveJobAccepted>), ApiError> {
+    state
+        .charms
+        .validate_spell(&req.spell_yaml)
+        .map_err(|e| ApiError::bad_request(format!("Invalid spell: {}", e)))?;
+    if !req.app_binary.is_empty() && BASE64.decode(&req.app_binary).is_err() {
+        return Err(ApiError::bad_request("app_binary must be base64"));
+    }
+    if !req.binary_refs.is_empty() {
+        let hashes: Vec<String> = req.binary_refs.values().cloned().collect();
+        let existing = db::existing_app_binaries(&state.db, &hashes).await?;
+        let missing: Vec<&str> = hashes
+            .iter()
+            .filter(|hash| !existing.contains(hash))
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            return Err(ApiError::bad_request(format!("No uploaded binary with hash {}", missing.join(", "))));
+        }
+    }
+
+    if let Some(order_id) = &req.order_id {
+        if db::get_order_by_id(&state.db, order_id).await?.is_none() {
+            return Err(ApiError::not_found(format!("Order {} not found", order_id)));
+        }
+    }
+
+    // Fail now rather than after the job has waited for a prover
+    state
+        .charms
+        .check_funding_utxo(&req.funding_utxo, req.funding_utxo_value)
+        .await
+        .map_err(|e| ApiError::prove_failed(&e))?;
+
+    // Private inputs are stored sealed, apart from the rest of the request
+    let (spell_yaml, private_inputs) = split_private_inputs(&req.spell_yaml)
+        .map_err(|e| ApiError::bad_request(format!("Invalid spell: {}", e)))?;
+    req.spell_yaml = spell_yaml;
+    let sealed_inputs = state.sealer.seal_optional(PRIVATE_INPUTS_PURPOSE, private_inputs.as_deref())?;
+
+    let now = chrono::Utc::now();
+    let job = ProveJobRecord {
+        id: Uuid::new_v4().to_string(),
+        status: "queued".to_string(),
+        request: serde_json::to_string(&req).map_err(anyhow::Error::from)?,
+        private_inputs: sealed_inputs,
+        result: None,
+        error: None,
+        error_code: None,
+        attempts: 0,
+        created_at: now,
+        updated_at: now,
+    };
+    let queued = state.jobs.new_job(PROVE_JOB, &ProveJobPayload { prove_job_id: job.id.clone() })?;
+    db::insert_prove_job(&state.db, &job, &queued).await?;
+    tracing::info!("Queued prove job {}", job.id);
+    if let Some(order_id) = &req.order_id {
+        let spell = db::SpellRecord {
+            id: Uuid::new_v4().to_string(),
+            order_id: order_id.clone(),
+            spell: req.spell_yaml.clone(),
+            private_inputs: state.sealer.seal_optional(SPELL_PRIVATE_INPUTS_PURPOSE, private_inputs.as_deref())?,
+            request_hash: String::new(),
+            status: "queued".to_string(),
+            transactions: None,
             error: None,
-        });
+            prove_ms: 0,
+            created_at: now,
+            operation: Some("prove".to_string()),
+            template_name: None,
+            template_version: None,
+            prove_job_id: Some(job.id.clone()),
+            started_at: None,
+            finished_at: None,
+        };
+        if let Err(e) = db::insert_spell(&state.db, &spell).await {
+            tracing::warn!("Failed to record spell of prove job {} for order {}: {}", job.id, order_id, e);
+        }
+    }
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(ProveJobAccepted {
+            poll_url: format!("/api/spells/jobs/{}", job.id),
+            events_url: format!("/api/spells/jobs/{}/events", job.id),
+            job_id: job.id,
+            status: job.status,
+        }),
+    ))
+}
+
+/// Store an app binary sent as the raw request body, to be referenced by hash
+/// in prove requests. Counts against the user's quota; admin uploads do not.
+pub async fn upload_binary(
+    State(state): State<Arc<AppState>>,
+    caller: SessionOrAdmin,
+    body: Bytes,
+) -> Result<(StatusCode, Json<UploadedBinary>), ApiError> {
+    if !body.starts_with(WASM_MAGIC) {
+        return Err(ApiError::bad_request("Body must be a WebAssembly app binary"));
+    }
+
+    let uploaded_by = match &caller {
+        SessionOrAdmin::Session(session) => Some(session.user_id.as_str()),
+        SessionOrAdmin::Admin => None,
+    };
+    let quota = app_binary_quota();
+    let hash = hex::encode(Sha256::digest(&body));
+    let created = db::store_app_binary(&state.db, &hash, &body, uploaded_by, Some(quota))
+        .await?
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "quota_exceeded",
+                format!("Uploaded binaries would exceed your {} byte quota", quota),
+            )
+        })?;
+    if created {
+        tracing::info!("Stored app binary {} ({} bytes)", hash, body.len());
     }
 
-    // Call Charms prover API
-    let client = reqwest::Client::new();
-    
-    match client
-        .post(&charms_api_url)
-        .json(&serde_json::json!({
-            "spell": req.spell_yaml,
-            "binaries": {
-                // TODO: Include app binary with correct VK
+    Ok((
+        if created { StatusCode::CREATED } else { StatusCode::OK },
+        Json(UploadedBinary { hash, size: body.len() }),
+    ))
+}
+
+/// Get a prove job's status and, once done, its transactions
+pub async fn get_prove_job(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<ProveJob>, ApiError> {
+    let job = db::get_prove_job(&state.db, &id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Prove job not found"))?;
+
+    Ok(Json(job.into()))
+}
+
+/// Stream a prove job's state as server-sent events until it finishes, with
+/// `progress` events (queued, proving, finalizing) while it is being proved
+pub async fn prove_job_events(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Sse<impl Stream<Item = Result<SseEvent, Infallible>>>, ApiError> {
+    // Subscribe before reading the job so no transition is missed
+    let receiver = state.events.subscribe();
+    let job: ProveJob = db::get_prove_job(&state.db, &id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Prove job not found"))?
+        .into();
+
+    let initial = Some(job);
+    let events = stream::unfold((state, id, receiver, initial, false), |(state, id, mut receiver, mut next, done)| async move {
+        if done {
+            return None;
+        }
+
+        let (event, finished) = match next.take() {
+            Some(job) => job_state_event(job),
+            None => loop {
+                match receiver.recv().await {
+                    Ok(event) if event.subject_id != id => continue,
+                    Ok(event) if event.kind.starts_with("prove_job.") => {
+                        if let Ok(Some(record)) = db::get_prove_job(&state.db, &id).await {
+                            break job_state_event(record.into());
+                        }
+                    }
+                    Ok(event) if event.kind.starts_with("prove.") => {
+                        let progress = SseEvent::default().event("progress").json_data(&event.data).unwrap_or_default();
+                        break (progress, false);
+                    }
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
             },
-            "prev_txs": req.prev_txs,
-            "funding_utxo": req.funding_utxo,
-            "funding_utxo_value": req.funding_utxo_value,
-            "change_address": req.change_address,
-            "fee_rate": req.fee_rate,
-            "chain": "Bitcoin"
-        }))
-        .send()
+        };
+        Some((Ok(event), (state, id, receiver, None, finished)))
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// SSE event named after the job's status, and whether the job is finished
+fn job_state_event(job: ProveJob) -> (SseEvent, bool) {
+    let event = SseEvent::default()
+        .event(job.status.clone())
+        .json_data(&job)
+        .unwrap_or_default();
+    (event, job.is_finished())
+}
+
+/// Spawn the background workers (`PROVE_WORKERS`, default 2) that prove
+/// queued jobs
+pub fn spawn_prove_workers(state: Arc<AppState>) {
+    let workers: usize = std::env::var("PROVE_WORKERS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(2);
+
+    let pruner = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(proof_cache_ttl().to_std().unwrap_or(Duration::from_secs(3600)));
+        loop {
+            interval.tick().await;
+            match db::prune_proof_cache(&pruner.db, chrono::Utc::now() - proof_cache_ttl()).await {
+                Ok(0) => {}
+                Ok(n) => tracing::debug!("Pruned {} expired cached proofs", n),
+                Err(e) => tracing::warn!("Failed to prune proof cache: {}", e),
+            }
+            match db::prune_app_binaries(&pruner.db, chrono::Utc::now() - app_binary_ttl()).await {
+                Ok(0) => {}
+                Ok(n) => tracing::info!("Pruned {} unused app binaries", n),
+                Err(e) => tracing::warn!("Failed to prune app binaries: {}", e),
+            }
+        }
+    });
+
+    let jobs = state.jobs.clone();
+    jobs.spawn_workers(PROVE_JOB, workers, move |queued| {
+        let state = state.clone();
+        async move {
+            let payload: ProveJobPayload =
+                serde_json::from_str(&queued.payload).context("Unreadable prove job payload")?;
+            match db::start_prove_job(&state.db, &payload.prove_job_id).await? {
+                Some(job) => run_prove_job(&state, job).await,
+                // Finished by an earlier claim that lost its lease
+                None => Ok(()),
+            }
+        }
+    });
+}
+
+/// Prove a job and record its outcome. A failed proof is an outcome; only
+/// failing to record it fails the queued job, which is then retried.
+async fn run_prove_job(state: &AppState, job: ProveJobRecord) -> anyhow::Result<()> {
+    tracing::info!("Proving job {} (attempt {})", job.id, job.attempts);
+    publish_job_event(state, &job.id, "running");
+    let started_at = chrono::Utc::now();
+
+    let outcome = match stored_request(state, &job) {
+        Ok(req) => prove_request(state, req, &job.id).await,
+        Err(e) => Err(e),
+    };
+
+    let (result, error) = match outcome {
+        Ok(txs) => (serde_json::to_string(&txs).ok(), None),
+        Err(e) => {
+            tracing::warn!("Prove job {} failed: {:#}", job.id, e);
+            (None, Some((format!("{:#}", e), ApiError::prove_failed(&e).code)))
+        }
+    };
+    record_job_spell(state, &job.id, result.as_deref(), error.as_ref().map(|(message, _)| message.as_str()), started_at)
+        .await;
+
+    let error_ref = error.as_ref().map(|(message, code)| (message.as_str(), *code));
+    db::finish_prove_job(&state.db, &job.id, result.as_deref(), error_ref)
         .await
-    {
-        Ok(response) => {
-            if response.status().is_success() {
-                // TODO: Parse response and extract transactions
-                Json(ProveSpellResponse {
-                    success: true,
-                    transactions: vec![],
-                    total_fee: 0,
-                    error: None,
-                })
-            } else {
-                let error = response.text().await.unwrap_or_default();
-                Json(ProveSpellResponse {
-                    success: false,
-                    transactions: vec![],
-                    total_fee: 0,
-                    error: Some(error),
-                })
+        .with_context(|| format!("Failed to record prove job {} result", job.id))?;
+    publish_job_event(state, &job.id, if error.is_some() { "failed" } else { "succeeded" });
+    Ok(())
+}
+
+/// Write a prove job's outcome and timing to the order spell it was queued
+/// for, if any
+async fn record_job_spell(
+    state: &AppState,
+    job_id: &str,
+    transactions: Option<&str>,
+    error: Option<&str>,
+    started_at: chrono::DateTime<chrono::Utc>,
+) {
+    let mut spell = match db::get_spell_by_prove_job(&state.db, job_id).await {
+        Ok(Some(spell)) => spell,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::warn!("Failed to load the order spell of prove job {}: {}", job_id, e);
+            return;
+        }
+    };
+    let finished_at = chrono::Utc::now();
+    spell.status = if error.is_some() { "failed" } else { "proved" }.to_string();
+    spell.transactions = transactions.map(str::to_string);
+    spell.error = error.map(str::to_string);
+    spell.prove_ms = (finished_at - started_at).num_milliseconds();
+    spell.started_at = Some(started_at);
+    spell.finished_at = Some(finished_at);
+    if let Err(e) = db::update_spell(&state.db, &spell).await {
+        tracing::warn!("Failed to record the outcome of spell {}: {}", spell.id, e);
+    }
+}
+
+/// A built spell split for its `spells` row: the spell without its private
+/// inputs, and those inputs sealed
+pub(crate) fn seal_spell(sealer: &Sealer, spell_yaml: &str) -> anyhow::Result<(String, Option<String>)> {
+    let (spell_yaml, private_inputs) = split_private_inputs(spell_yaml)?;
+    let private_inputs = sealer.seal_optional(SPELL_PRIVATE_INPUTS_PURPOSE, private_inputs.as_deref())?;
+    Ok((spell_yaml, private_inputs))
+}
+
+/// A stored spell with its private inputs opened and put back, as proved
+pub(crate) fn open_spell(sealer: &Sealer, spell: &db::SpellRecord) -> anyhow::Result<String> {
+    match sealer.open_optional(SPELL_PRIVATE_INPUTS_PURPOSE, spell.private_inputs.as_deref())? {
+        Some(private_inputs) => join_private_inputs(&spell.spell, &private_inputs),
+        None => Ok(spell.spell.clone()),
+    }
+}
+
+/// A job's request with its private inputs opened and put back in the spell
+fn stored_request(state: &AppState, job: &ProveJobRecord) -> anyhow::Result<ProveSpellRequest> {
+    let mut req: ProveSpellRequest =
+        serde_json::from_str(&job.request).context("Stored request is unreadable")?;
+    if let Some(private_inputs) = state.sealer.open_optional(PRIVATE_INPUTS_PURPOSE, job.private_inputs.as_deref())? {
+        req.spell_yaml = join_private_inputs(&req.spell_yaml, &private_inputs)?;
+    }
+    Ok(req)
+}
+
+async fn prove_request(state: &AppState, req: ProveSpellRequest, job_id: &str) -> anyhow::Result<Vec<ProvedTransaction>> {
+    // Binaries for every app the spell references, plus any sent by the caller
+    let mut binaries = state.apps.binaries_for_spell(&req.spell_yaml).await;
+    if !req.app_binary.is_empty() {
+        let vk = match req.app_vk {
+            Some(vk) => vk,
+            None => state.apps.vk(Contract::Swap).await,
+        };
+        binaries.insert(vk, BASE64.decode(&req.app_binary)?);
+    }
+    for (vk, hash) in req.binary_refs {
+        let binary = db::get_app_binary(&state.db, &hash)
+            .await?
+            .with_context(|| format!("Uploaded binary {} no longer exists", hash))?;
+        binaries.insert(vk, binary);
+    }
+
+    let fee_rate = match req.fee_rate {
+        Some(rate) => rate,
+        None => {
+            let tier = req.fee_tier.unwrap_or(FeeTier::Normal);
+            state.fees.fee_rate(state.chain.as_ref(), tier).await
+        }
+    };
+
+    let request = SpellProveRequest {
+        spell: req.spell_yaml,
+        binaries,
+        prev_txs: req.prev_txs,
+        funding_utxo: req.funding_utxo,
+        funding_utxo_value: req.funding_utxo_value,
+        change_address: req.change_address,
+        fee_rate,
+        chain: state.charms.chain().to_string(),
+        replaces: None,
+    };
+    prove_cached(&state.charms, &state.db, &state.events, request, job_id).await
+}
+
+/// How long an identical prove request reuses a cached proof
+/// (`PROOF_CACHE_TTL_SECS`, default one hour)
+fn proof_cache_ttl() -> chrono::Duration {
+    let secs = std::env::var("PROOF_CACHE_TTL_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(3600);
+    chrono::Duration::seconds(secs)
+}
+
+/// Prove through the proof cache, so retried or duplicated requests (e.g. a
+/// refreshed page) reuse an earlier proof instead of running the prover
+/// again; the prover keys placeholder proofs apart. Progress is published as
+/// `prove.<stage>` events about `subject_id` (the order, escrow or job).
+pub(crate) async fn prove_cached(
+    charms: &CharmsService,
+    pool: &DbPool,
+    events: &EventBus,
+    request: SpellProveRequest,
+    subject_id: &str,
+) -> anyhow::Result<Vec<ProvedTransaction>> {
+    let progress = |update: ProveProgress| {
+        events.publish(Event::new(
+            format!("prove.{}", update.stage.as_str()),
+            subject_id,
+            serde_json::to_value(&update).unwrap_or_default(),
+        ));
+    };
+    let cache_key = charms.prover().cache_key(&request);
+    let txs = match cached_proof(pool, &cache_key).await {
+        Some(txs) => txs,
+        None => {
+            let txs = charms.prove_spell_with_progress(request, &progress).await?;
+            match serde_json::to_string(&txs) {
+                Ok(json) => {
+                    if let Err(e) = db::store_cached_proof(pool, &cache_key, &json).await {
+                        tracing::warn!("Failed to cache proof {}: {}", cache_key, e);
+                    }
+                }
+                Err(e) => tracing::warn!("Failed to serialize proof {}: {}", cache_key, e),
             }
+            txs
+        }
+    };
+
+    // Broadcasts for the subject are checked against these
+    for tx in &txs {
+        if let Err(e) = db::record_proved_transaction(pool, subject_id, &tx.txid, &tx.hex).await {
+            tracing::warn!("Failed to record proved transaction {} for {}: {}", tx.txid, subject_id, e);
+        }
+    }
+    Ok(txs)
+}
+
+async fn cached_proof(pool: &DbPool, cache_key: &str) -> Option<Vec<ProvedTransaction>> {
+    match db::get_cached_proof(pool, cache_key, chrono::Utc::now() - proof_cache_ttl()).await {
+        Ok(Some(cached)) => match serde_json::from_str(&cached) {
+            Ok(txs) => {
+                tracing::info!("Reusing cached proof {}", cache_key);
+                Some(txs)
+            }
+            Err(e) => {
+                tracing::warn!("Ignoring unreadable cached proof {}: {}", cache_key, e);
+                None
+            }
+        },
+        Ok(None) => None,
+        Err(e) => {
+            tracing::warn!("Proof cache lookup failed: {}", e);
+            None
         }
-        Err(e) => Json(ProveSpellResponse {
-            success: false,
-            transactions: vec![],
-            total_fee: 0,
-            error: Some(e.to_string()),
-        }),
     }
 }
 
-/// Broadcast signed transactions
+fn publish_job_event(state: &AppState, id: &str, status: &str) {
+    state.events.publish(Event::new(
+        format!("prove_job.{}", status),
+        id,
+        serde_json::json!({ "status": status }),
+    ));
+}
+
+/// Run the swap/escrow contracts natively against a built spell, reporting
+/// the failed condition of any app that would reject it
+pub async fn check_spell(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<CheckSpellRequest>,
+) -> Result<Json<SpellCheck>, ApiError> {
+    let keys = ContractKeys {
+        swap_vk: state.apps.vk(Contract::Swap).await,
+        escrow_vk: state.apps.vk(Contract::Escrow).await,
+    };
+
+    spell_check::check_spell(&req.spell_yaml, &keys, req.contract)
+        .map(Json)
+        .map_err(|e| ApiError::bad_request(format!("{:#}", e)))
+}
+
+/// Read the spell embedded in a raw transaction, e.g. so a wallet can show
+/// what it is about to sign
+pub async fn decode_spell(Json(req): Json<DecodeSpellRequest>) -> Result<Json<DecodedSpell>, ApiError> {
+    match decode_spell_hex(&req.tx_hex) {
+        Ok(Some(spell)) => Ok(Json(spell)),
+        Ok(None) => Err(ApiError::bad_request("Transaction carries no spell")),
+        Err(e) => Err(ApiError::bad_request(format!("{:#}", e))),
+    }
+}
+
+/// Quote proving time, transaction sizes and fee for an operation
+pub async fn estimate_spell(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SpellEstimateQuery>,
+) -> Result<Json<SpellEstimate>, ApiError> {
+    let target = query.target.unwrap_or(FeeTier::Normal.target_blocks());
+    if !(1..=MAX_ESTIMATE_TARGET_BLOCKS).contains(&target) {
+        return Err(ApiError::bad_request(format!(
+            "target must be between 1 and {} blocks",
+            MAX_ESTIMATE_TARGET_BLOCKS
+        )));
+    }
+
+    let durations = db::recent_prove_durations(&state.db, PROVE_HISTORY_JOBS)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!("Failed to read prove job history: {}", e);
+            Vec::new()
+        });
+    let (fee_rate, fee_source) = state.fees.rate_for_target(state.chain.as_ref(), target).await;
+    let spell_vbytes = query.operation.spell_vbytes();
+
+    Ok(Json(SpellEstimate {
+        operation: query.operation,
+        proving: proving_time_estimate(durations),
+        commit_vbytes: COMMIT_TX_VBYTES,
+        spell_vbytes,
+        target_blocks: target,
+        fee_rate,
+        fee_source,
+        fee_sats: fee_for(fee_rate, COMMIT_TX_VBYTES + spell_vbytes),
+    }))
+}
+
+/// Median and 90th percentile of job durations, or the default with no history
+fn proving_time_estimate(mut durations: Vec<f64>) -> ProvingTimeEstimate {
+    durations.retain(|secs| secs.is_finite() && *secs >= 0.0);
+    if durations.is_empty() {
+        return ProvingTimeEstimate {
+            median_secs: DEFAULT_PROVE_SECS,
+            p90_secs: DEFAULT_PROVE_SECS,
+            samples: 0,
+        };
+    }
+
+    durations.sort_by(f64::total_cmp);
+    let percentile = |p: usize| durations[(durations.len() - 1) * p / 100].ceil() as u64;
+    ProvingTimeEstimate {
+        median_secs: percentile(50),
+        p90_secs: percentile(90),
+        samples: durations.len(),
+    }
+}
+
+/// Broadcast the signed transactions of a prove job, in order. Each must be a
+/// transaction the job proved, with its spell witness intact.
 pub async fn broadcast_transaction(
+    State(state): State<Arc<AppState>>,
     Json(req): Json<BroadcastRequest>,
 ) -> Json<BroadcastResponse> {
-    // TODO: Broadcast via Bitcoin Core RPC
-    
-    let mock_mode = std::env::var("MOCK_MODE")
-        .map(|v| v == "true")
-        .unwrap_or(true);
-
-    if mock_mode {
-        return Json(BroadcastResponse {
-            success: true,
-            txids: req.signed_txs.iter().map(|_| format!("mock_txid_{}", uuid::Uuid::new_v4())).collect(),
-            error: None,
-        });
+    let failed = |txids: Vec<String>, error: String| {
+        Json(BroadcastResponse {
+            success: false,
+            txids,
+            error: Some(error),
+            rejection: None,
+        })
+    };
+
+    let proved = match db::get_proved_transactions(&state.db, &req.job_id).await {
+        Ok(proved) => proved,
+        Err(e) => return failed(vec![], format!("Failed to load proved transactions: {}", e)),
+    };
+    // Check every transaction before relaying any of them
+    for signed in &req.signed_txs {
+        if let Err(e) = state.charms.prover().check_signed(signed, &proved, None) {
+            tracing::warn!("Rejected broadcast for prove job {}: {}", req.job_id, e);
+            return failed(vec![], format!("{} for prove job {}", e, req.job_id));
+        }
+    }
+
+    let mut txids = Vec::with_capacity(req.signed_txs.len());
+    for signed in &req.signed_txs {
+        match state.fees.broadcast(state.chain.as_ref(), signed).await {
+            Ok(txid) => txids.push(txid),
+            Err(e) => {
+                return Json(BroadcastResponse {
+                    success: false,
+                    error: Some(format!("Failed to broadcast: {}", e)),
+                    rejection: BroadcastRejection::of(&e).cloned(),
+                    txids,
+                })
+            }
+        }
     }
 
-    // TODO: Call Bitcoin Core sendrawtransaction
     Json(BroadcastResponse {
-        success: false,
-        txids: vec![],
-        error: Some("Not implemented".to_string()),
+        success: true,
+        txids,
+        error: None,
+        rejection: None,
     })
 }
 
-/// Get transaction status
+/// Mempool or confirmation state of a transaction, with the charms it
+/// created and consumed
 pub async fn get_transaction_status(
+    State(state): State<Arc<AppState>>,
     Path(txid): Path<String>,
-) -> Json<TransactionStatus> {
-    // TODO: Query Bitcoin Core for transaction status
-    
-    Json(TransactionStatus {
+) -> Result<Json<TransactionStatus>, ApiError> {
+    let Ok(parsed_txid) = Txid::from_str(&txid) else {
+        return Err(ApiError::bad_request(format!("Invalid txid {}", txid)));
+    };
+
+    let not_found = |e: String| ApiError::not_found(format!("Transaction {} not found: {}", txid, e));
+    let status = match state.chain.tx_status(&txid).await {
+        Ok(Some(status)) => status,
+        Ok(None) => return Err(not_found("unknown to the chain backend".to_string())),
+        Err(e) => return Err(not_found(e.to_string())),
+    };
+    let hex = state.chain.raw_transaction(&txid).await.map_err(|e| not_found(e.to_string()))?;
+
+    let spell = decode_spell_hex(&hex).unwrap_or_else(|e| {
+        tracing::warn!("Failed to decode spell in {}: {:#}", txid, e);
+        None
+    });
+    let charms_consumed = match &spell {
+        Some(spell) => consumed_charms(state.chain.as_ref(), spell).await,
+        None => vec![],
+    };
+
+    let spv_proof = match (&status.block_hash, status.is_confirmed()) {
+        (Some(block_hash), true) => {
+            inclusion_proof(state.chain.as_ref(), &parsed_txid, block_hash, status.block_height).await
+        }
+        _ => None,
+    };
+
+    Ok(Json(TransactionStatus {
         txid,
-        confirmed: false,
-        confirmations: 0,
-        block_height: None,
-        block_hash: None,
-    })
+        status: if status.is_confirmed() { "confirmed" } else { "mempool" }.to_string(),
+        confirmed: status.is_confirmed(),
+        confirmations: status.confirmations,
+        block_height: status.block_height,
+        block_hash: status.block_hash,
+        charms_created: spell
+            .map(|spell| spell.outs.into_iter().filter(|out| !out.charms.is_empty()).collect())
+            .unwrap_or_default(),
+        charms_consumed,
+        spv_proof,
+    }))
+}
+
+/// Checked merkle proof of a mined transaction; `None` when the backend
+/// cannot provide one
+async fn inclusion_proof(
+    chain: &dyn ChainBackend,
+    txid: &Txid,
+    block_hash: &str,
+    block_height: Option<u64>,
+) -> Option<SpvProof> {
+    let proof = async {
+        let merkle_block = chain.merkle_block(&txid.to_string(), block_hash).await?;
+        spv_proof(txid, &BlockHash::from_str(block_hash)?, block_height, &merkle_block)
+    };
+    proof
+        .await
+        .inspect_err(|e| tracing::warn!("No inclusion proof for {}: {:#}", txid, e))
+        .ok()
+}
+
+/// Charms held by the spell's inputs, read from the spells that created them
+async fn consumed_charms(chain: &dyn ChainBackend, spell: &DecodedSpell) -> Vec<ConsumedCharms> {
+    let mut consumed = Vec::new();
+    for utxo_id in &spell.ins {
+        let Ok(outpoint) = OutPoint::from_str(utxo_id) else {
+            continue;
+        };
+        let prev_spell = match chain.raw_transaction(&outpoint.txid.to_string()).await {
+            Ok(hex) => decode_spell_hex(&hex).ok().flatten(),
+            Err(e) => {
+                tracing::debug!("Could not fetch previous transaction {}: {}", outpoint.txid, e);
+                None
+            }
+        };
+        if let Some(charms) = prev_spell.as_ref().and_then(|prev| prev.charms_at(outpoint.vout)) {
+            consumed.push(ConsumedCharms {
+                utxo_id: utxo_id.clone(),
+                charms: charms.clone(),
+            });
+        }
+    }
+    consumed
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::sessions::Session;
+    use crate::services::spell_schema::Spell;
+
+    #[test]
+    fn test_spell_private_inputs_sealed() {
+        let sealer = Sealer::new(vec![("k1".to_string(), [1; 32])]);
+        let built = "version: 8\napps:\n  $E: n/abc/def\nprivate_inputs:\n  $E:\n    preimage: cafe\nins: []\nouts: []\n";
+        let (spell, private_inputs) = seal_spell(&sealer, built).unwrap();
+        assert!(!spell.contains("cafe"));
+        assert!(!private_inputs.as_deref().unwrap().contains("cafe"));
+
+        let now = chrono::Utc::now();
+        let record = db::SpellRecord {
+            id: "spell-1".to_string(),
+            order_id: "order-1".to_string(),
+            spell,
+            private_inputs,
+            request_hash: String::new(),
+            status: "proved".to_string(),
+            transactions: None,
+            error: None,
+            prove_ms: 0,
+            created_at: now,
+            operation: None,
+            template_name: None,
+            template_version: None,
+            prove_job_id: None,
+            started_at: None,
+            finished_at: None,
+        };
+        let opened = Spell::parse(&open_spell(&sealer, &record).unwrap()).unwrap();
+        assert_eq!(opened.private_inputs["$E"]["preimage"], "cafe");
+
+        // Rows stored before the inputs were split out read as they are
+        let legacy = db::SpellRecord { spell: built.to_string(), private_inputs: None, ..record };
+        assert_eq!(open_spell(&sealer, &legacy).unwrap(), built);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_binary_uploads_are_quota_limited_and_pruned() {
+        let db = db::testing::fresh_db().await;
+
+        let user = db::upsert_user(&db, "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx", None).await.unwrap();
+        let session = Session {
+            token: "token".to_string(),
+            user_id: user.id.clone(),
+            address: user.address.clone(),
+            addresses: vec![user.address.clone()],
+            pubkeys: vec![],
+            expires_at: chrono::Utc::now() + chrono::Duration::hours(1),
+        };
+        let state = Arc::new(crate::routes::orders::testing::test_state(db));
+        let binary = |tag: u8| Bytes::from([WASM_MAGIC, &[tag; 96]].concat());
+        let upload = |caller: SessionOrAdmin, body: Bytes| {
+            let state = state.clone();
+            async move { upload_binary(State(state), caller, body).await }
+        };
+
+        let (status, Json(used)) = upload(SessionOrAdmin::Session(session.clone()), binary(1)).await.unwrap();
+        assert_eq!(status, StatusCode::CREATED);
+        let (status, _) = upload(SessionOrAdmin::Session(session.clone()), binary(1)).await.unwrap();
+        assert_eq!(status, StatusCode::OK);
+        let (status, Json(unused)) = upload(SessionOrAdmin::Admin, binary(2)).await.unwrap();
+        assert_eq!(status, StatusCode::CREATED);
+
+        // 100 bytes stored against the user, so another 100 exceeds 150
+        let other = binary(3);
+        let hash = hex::encode(Sha256::digest(&other));
+        assert_eq!(db::store_app_binary(&state.db, &hash, &other, Some(&user.id), Some(150)).await.unwrap(), None);
+        // Admin uploads count against nobody
+        assert_eq!(db::store_app_binary(&state.db, &hash, &other, None, Some(150)).await.unwrap(), Some(true));
+
+        // A queued job still needs its binary; the others go
+        let now = chrono::Utc::now();
+        let job = ProveJobRecord {
+            id: Uuid::new_v4().to_string(),
+            status: "queued".to_string(),
+            request: serde_json::json!({ "binary_refs": { "vk": used.hash } }).to_string(),
+            private_inputs: None,
+            result: None,
+            error: None,
+            error_code: None,
+            attempts: 0,
+            created_at: now,
+            updated_at: now,
+        };
+        let queued = state.jobs.new_job(PROVE_JOB, &ProveJobPayload { prove_job_id: job.id.clone() }).unwrap();
+        db::insert_prove_job(&state.db, &job, &queued).await.unwrap();
+
+        let later = now + chrono::Duration::minutes(1);
+        assert_eq!(db::prune_app_binaries(&state.db, later).await.unwrap(), 2);
+        let left = db::existing_app_binaries(&state.db, &[used.hash.clone(), unused.hash, hash]).await.unwrap();
+        assert_eq!(left, vec![used.hash]);
+    }
+}
@@ -0,0 +1,337 @@
+//! Cross-chain swap status endpoints
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use bitcoin::consensus::encode::deserialize_hex;
+use bitcoin::Transaction;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::db::{self, CrossChainSwapRecord, OrderRecord};
+use crate::routes::auth::WalletSession;
+use crate::routes::error::ApiError;
+use crate::routes::orders::AppState;
+use crate::services::coordinator::{combined_phase, LegEvent, LegState, SwapLeg, SwapPhase};
+
+/// Status of a single leg
+#[derive(Debug, Serialize)]
+pub struct LegStatus {
+    pub chain: String,
+    pub state: LegState,
+    pub txid: Option<String>,
+}
+
+/// Combined cross-chain swap status
+#[derive(Debug, Serialize)]
+pub struct SwapStatusResponse {
+    pub order_id: String,
+    pub phase: SwapPhase,
+    pub source: LegStatus,
+    pub dest: LegStatus,
+    pub updated_at: String,
+}
+
+/// Leg event report from the maker or a taker
+#[derive(Debug, Deserialize)]
+pub struct LegEventRequest {
+    pub leg: SwapLeg,
+    pub event: LegEvent,
+    pub txid: Option<String>,
+}
+
+impl From<CrossChainSwapRecord> for SwapStatusResponse {
+    fn from(record: CrossChainSwapRecord) -> Self {
+        let source_state = LegState::parse(&record.source_state).unwrap_or(LegState::Pending);
+        let dest_state = LegState::parse(&record.dest_state).unwrap_or(LegState::Pending);
+
+        SwapStatusResponse {
+            order_id: record.order_id,
+            phase: combined_phase(source_state, dest_state),
+            source: LegStatus {
+                chain: record.source_chain,
+                state: source_state,
+                txid: record.source_txid,
+            },
+            dest: LegStatus {
+                chain: record.dest_chain,
+                state: dest_state,
+                txid: record.dest_txid,
+            },
+            updated_at: record.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Get combined status of both legs of a cross-chain swap
+pub async fn get_swap_status(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<SwapStatusResponse>, ApiError> {
+    let record = db::get_cross_chain_swap(&state.db, &id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Order is not a cross-chain swap"))?;
+
+    Ok(Json(SwapStatusResponse::from(record)))
+}
+
+/// Report a lock/reveal/claim/refund event on one leg
+///
+/// Only the maker or a taker of the order may report, and the reported
+/// transaction is checked on chain first: a lock on the source leg must be
+/// the order's own transaction, and a reveal, claim or refund must spend the
+/// leg's lock transaction. Legs on chains the server cannot read are refused,
+/// since nothing could back the report.
+pub async fn report_leg_event(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+    Path(id): Path<String>,
+    Json(req): Json<LegEventRequest>,
+) -> Result<Json<SwapStatusResponse>, ApiError> {
+    let record = db::get_cross_chain_swap(&state.db, &id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Order is not a cross-chain swap"))?;
+    let order = db::get_order_by_id(&state.db, &id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Order not found"))?;
+
+    let is_party = session.owns(&order.maker_address)
+        || db::get_order_fills(&state.db, &id)
+            .await?
+            .iter()
+            .any(|fill| session.owns(&fill.taker_address));
+    if !is_party {
+        return Err(ApiError::forbidden("Only the maker or a taker of the order can report swap legs"));
+    }
+
+    let txid = req.txid.as_deref().map(str::trim).filter(|t| !t.is_empty()).ok_or_else(|| ApiError::missing("txid"))?;
+
+    let (chain, current, lock_txid) = match req.leg {
+        SwapLeg::Source => (&record.source_chain, &record.source_state, record.source_txid.as_deref()),
+        SwapLeg::Dest => (&record.dest_chain, &record.dest_state, record.dest_txid.as_deref()),
+    };
+    let current = LegState::parse(current).unwrap_or(LegState::Pending);
+
+    let next = current.apply(req.event).ok_or_else(|| {
+        ApiError::conflict(format!("Cannot apply {:?} to a leg in state {}", req.event, current.as_str()))
+    })?;
+
+    if chain != "bitcoin" {
+        return Err(ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "unverifiable_leg",
+            format!("Transactions on {} cannot be checked by this server, so the leg cannot be reported", chain),
+        ));
+    }
+    verify_leg_transaction(&state, &order, req.leg, req.event, txid, lock_txid).await?;
+
+    // A lock records the leg's transaction; later events keep it, so the
+    // spends of later reports are checked against the lock
+    let recorded_txid = (req.event == LegEvent::Lock).then_some(txid);
+    if !db::update_swap_leg(&state.db, &id, req.leg, current, next, recorded_txid).await? {
+        return Err(ApiError::conflict("The leg changed while the report was checked; fetch its status and retry"));
+    }
+    tracing::info!("Swap {} {:?} leg: {} -> {} ({})", id, req.leg, current.as_str(), next.as_str(), txid);
+
+    let updated = db::get_cross_chain_swap(&state.db, &id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Order is not a cross-chain swap"))?;
+
+    Ok(Json(SwapStatusResponse::from(updated)))
+}
+
+/// Check a reported Bitcoin leg transaction against the chain
+async fn verify_leg_transaction(
+    state: &AppState,
+    order: &OrderRecord,
+    leg: SwapLeg,
+    event: LegEvent,
+    txid: &str,
+    lock_txid: Option<&str>,
+) -> Result<(), ApiError> {
+    let status = state.chain.tx_status(txid).await.map_err(|e| {
+        tracing::error!("Failed to look up reported swap transaction {}: {}", txid, e);
+        ApiError::internal("Failed to look up the reported transaction")
+    })?;
+    if status.is_none() {
+        return Err(ApiError::bad_request(format!("Transaction {} is neither mined nor in the mempool", txid)));
+    }
+
+    match event {
+        LegEvent::Lock => {
+            if leg == SwapLeg::Source && order.tx_id.as_deref() != Some(txid) {
+                return Err(ApiError::bad_request("The source leg is locked by the order's own transaction"));
+            }
+        }
+        LegEvent::Reveal | LegEvent::Claim | LegEvent::Refund => {
+            let lock_txid = lock_txid.ok_or_else(|| ApiError::conflict("The leg has no recorded lock transaction"))?;
+            let hex = state.chain.raw_transaction(txid).await.map_err(|e| {
+                tracing::error!("Failed to fetch reported swap transaction {}: {}", txid, e);
+                ApiError::internal("Failed to fetch the reported transaction")
+            })?;
+            let tx: Transaction = deserialize_hex(hex.trim())
+                .map_err(|_| ApiError::internal("The reported transaction does not decode"))?;
+            let spends_lock = tx.input.iter().any(|input| input.previous_output.txid.to_string() == lock_txid);
+            if !spends_lock {
+                return Err(ApiError::bad_request(format!(
+                    "Transaction {} does not spend the leg's lock transaction {}",
+                    txid, lock_txid
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Record a lock event on the source leg after the order transaction is broadcast
+pub(crate) async fn mark_source_locked(state: &AppState, order_id: &str, txid: &str) {
+    let record = match db::get_cross_chain_swap(&state.db, order_id).await {
+        Ok(Some(record)) => record,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::error!("Failed to load swap coordinator state: {}", e);
+            return;
+        }
+    };
+
+    let current = LegState::parse(&record.source_state).unwrap_or(LegState::Pending);
+    if let Some(next) = current.apply(LegEvent::Lock) {
+        if let Err(e) = db::update_swap_leg(&state.db, order_id, SwapLeg::Source, current, next, Some(txid)).await {
+            tracing::error!("Failed to update source leg: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::consensus::encode::serialize_hex;
+    use bitcoin::hashes::Hash;
+    use bitcoin::transaction::Version;
+    use bitcoin::{Amount, OutPoint, ScriptBuf, Sequence, TxIn, TxOut, Txid, Witness};
+
+    use crate::routes::orders::testing::test_state;
+    use crate::services::sessions::Session;
+
+    const MAKER: &str = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+    const STRANGER: &str = "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7";
+
+    fn session(address: &str) -> WalletSession {
+        WalletSession(Session {
+            token: "token".to_string(),
+            user_id: "user".to_string(),
+            address: address.to_string(),
+            addresses: vec![address.to_string()],
+            pubkeys: vec![],
+            expires_at: chrono::Utc::now() + chrono::Duration::hours(1),
+        })
+    }
+
+    /// Broadcast a transaction spending `outpoint` to the mock chain
+    async fn spend(state: &AppState, outpoint: OutPoint) -> String {
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: outpoint,
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::MAX,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(10_000),
+                script_pubkey: ScriptBuf::new(),
+            }],
+        };
+        state.chain.broadcast(&serialize_hex(&tx)).await.unwrap()
+    }
+
+    fn report(leg: SwapLeg, event: LegEvent, txid: Option<&str>) -> Json<LegEventRequest> {
+        Json(LegEventRequest { leg, event, txid: txid.map(str::to_string) })
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_leg_reports_are_checked_against_the_chain() {
+        let db = db::testing::fresh_db().await;
+        let state = Arc::new(test_state(db));
+
+        let lock = spend(&state, OutPoint::new(Txid::from_byte_array([1; 32]), 0)).await;
+        let unrelated = spend(&state, OutPoint::new(Txid::from_byte_array([2; 32]), 0)).await;
+        let now = chrono::Utc::now();
+        let order = OrderRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            maker_address: MAKER.to_string(),
+            offer_token: "TOAD".to_string(),
+            offer_amount: 1000,
+            want_token: "ETH".to_string(),
+            want_amount: 10_000,
+            source_chain: "bitcoin".to_string(),
+            dest_chain: "ethereum".to_string(),
+            status: "open".to_string(),
+            allow_partial: false,
+            filled_amount: 0,
+            expiry_height: None,
+            utxo_id: None,
+            tx_id: Some(lock.clone()),
+            created_at: now,
+            updated_at: now,
+            version: 0,
+            archived_at: None,
+            maker_pubkey: None,
+            dest_address: None,
+        };
+        db::insert_order(&state.db, &order).await.unwrap();
+        let id = || Path(order.id.clone());
+
+        let err = get_swap_status(State(state.clone()), id()).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::NOT_FOUND);
+        db::insert_cross_chain_swap(&state.db, &order.id, "bitcoin", "ethereum").await.unwrap();
+        let status = get_swap_status(State(state.clone()), id()).await.unwrap().0;
+        assert_eq!(status.phase, SwapPhase::AwaitingLocks);
+
+        // Only the order's parties report, and only transactions the chain backs
+        let lock_report = || report(SwapLeg::Source, LegEvent::Lock, Some(&lock));
+        let err = report_leg_event(State(state.clone()), session(STRANGER), id(), lock_report()).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::FORBIDDEN);
+        let rejected = [
+            (None, StatusCode::BAD_REQUEST),
+            (Some("ab".repeat(32)), StatusCode::BAD_REQUEST),
+            (Some(unrelated.clone()), StatusCode::BAD_REQUEST),
+        ];
+        for (txid, status) in rejected {
+            let request = report(SwapLeg::Source, LegEvent::Lock, txid.as_deref());
+            let err = report_leg_event(State(state.clone()), session(MAKER), id(), request).await.unwrap_err();
+            assert_eq!(err.status, status, "{}", err.message);
+        }
+
+        let locked = report_leg_event(State(state.clone()), session(MAKER), id(), lock_report()).await.unwrap().0;
+        assert_eq!(locked.source.state, LegState::Locked);
+        assert_eq!(locked.source.txid.as_deref(), Some(lock.as_str()));
+        assert_eq!(locked.phase, SwapPhase::PartiallyLocked);
+
+        // A leg moves forward only, and a chain the server cannot read is not
+        // taken on trust
+        let err = report_leg_event(State(state.clone()), session(MAKER), id(), lock_report()).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::CONFLICT);
+        let dest_lock = report(SwapLeg::Dest, LegEvent::Lock, Some(&unrelated));
+        let err = report_leg_event(State(state.clone()), session(MAKER), id(), dest_lock).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        // A claim must spend the lock, which stays the leg's recorded transaction
+        let claim = report(SwapLeg::Source, LegEvent::Claim, Some(&unrelated));
+        let err = report_leg_event(State(state.clone()), session(MAKER), id(), claim).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        let claim_txid = spend(&state, OutPoint::new(lock.parse().unwrap(), 0)).await;
+        let claim = report(SwapLeg::Source, LegEvent::Claim, Some(&claim_txid));
+        let claimed = report_leg_event(State(state.clone()), session(MAKER), id(), claim).await.unwrap().0;
+        assert_eq!(claimed.source.state, LegState::Claimed);
+        assert_eq!(claimed.source.txid.as_deref(), Some(lock.as_str()));
+        assert_eq!(claimed.dest.state, LegState::Pending);
+        assert_eq!(claimed.phase, SwapPhase::Claiming);
+    }
+}
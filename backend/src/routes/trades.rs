@@ -0,0 +1,155 @@
+//! Trade history, price candles and market statistics from recorded fills
+
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::db::{self, FillCandleRecord, FillRecord, FillStatsRecord};
+use crate::routes::error::ApiError;
+use crate::routes::orders::AppState;
+
+/// Most fills returned by one trade history request
+const MAX_TRADES_LIMIT: i64 = 500;
+/// Most candles returned by one request
+const MAX_CANDLES: i64 = 1000;
+/// Window market statistics cover
+const STATS_WINDOW_HOURS: i64 = 24;
+
+/// A fill of an order
+#[derive(Debug, Serialize)]
+pub struct Trade {
+    pub id: String,
+    pub order_id: String,
+    pub taker_address: String,
+    /// Offer token units taken
+    pub amount: i64,
+    /// Want token units paid per offer token unit
+    pub price: f64,
+    pub txid: String,
+    /// Network fee in sats, once known
+    pub fee: Option<i64>,
+    pub created_at: String,
+}
+
+impl From<FillRecord> for Trade {
+    fn from(record: FillRecord) -> Self {
+        Self {
+            id: record.id,
+            order_id: record.order_id,
+            taker_address: record.taker_address,
+            amount: record.amount,
+            price: record.price,
+            txid: record.txid,
+            fee: record.fee,
+            created_at: record.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Token pair a market query is about
+#[derive(Debug, Deserialize)]
+pub struct TradesQuery {
+    pub offer_token: String,
+    pub want_token: String,
+    pub limit: Option<i64>,
+}
+
+/// Candle query: pair, bucket size (`1m`, `5m`, `15m`, `1h`, `4h` or `1d`)
+/// and how many buckets back to go
+#[derive(Debug, Deserialize)]
+pub struct CandlesQuery {
+    pub offer_token: String,
+    pub want_token: String,
+    pub interval: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// Statistics query
+#[derive(Debug, Deserialize)]
+pub struct StatsQuery {
+    pub offer_token: String,
+    pub want_token: String,
+}
+
+/// Price candles of a pair
+#[derive(Debug, Serialize)]
+pub struct CandlesResponse {
+    pub interval: String,
+    pub candles: Vec<FillCandleRecord>,
+}
+
+/// Pair statistics over the last `window_hours`
+#[derive(Debug, Serialize)]
+pub struct StatsResponse {
+    pub window_hours: i64,
+    #[serde(flatten)]
+    pub stats: FillStatsRecord,
+}
+
+/// Bucket size in seconds for a candle interval name
+fn interval_secs(interval: &str) -> Option<i64> {
+    match interval {
+        "1m" => Some(60),
+        "5m" => Some(5 * 60),
+        "15m" => Some(15 * 60),
+        "1h" => Some(60 * 60),
+        "4h" => Some(4 * 60 * 60),
+        "1d" => Some(24 * 60 * 60),
+        _ => None,
+    }
+}
+
+/// Fills of one order, oldest first
+pub async fn get_order_fills(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<Trade>>, ApiError> {
+    if db::get_order_by_id(&state.db, &id).await?.is_none() {
+        return Err(ApiError::not_found(format!("Order {} not found", id)));
+    }
+
+    let fills = db::get_order_fills(&state.db, &id).await?;
+    Ok(Json(fills.into_iter().map(Trade::from).collect()))
+}
+
+/// Recent fills of a pair, newest first
+pub async fn list_trades(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TradesQuery>,
+) -> Result<Json<Vec<Trade>>, ApiError> {
+    let limit = query.limit.unwrap_or(100).clamp(1, MAX_TRADES_LIMIT);
+    let fills = db::get_pair_fills(&state.db, &query.offer_token, &query.want_token, limit).await?;
+    Ok(Json(fills.into_iter().map(Trade::from).collect()))
+}
+
+/// OHLC candles of a pair's fill prices, oldest first
+pub async fn get_candles(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<CandlesQuery>,
+) -> Result<Json<CandlesResponse>, ApiError> {
+    let interval = query.interval.unwrap_or_else(|| "1h".to_string());
+    let secs = interval_secs(&interval).ok_or_else(|| {
+        ApiError::bad_request(format!("Unknown interval {}; use 1m, 5m, 15m, 1h, 4h or 1d", interval))
+    })?;
+    let limit = query.limit.unwrap_or(100).clamp(1, MAX_CANDLES);
+    let since = chrono::Utc::now() - chrono::Duration::seconds(secs * limit);
+
+    let candles = db::get_fill_candles(&state.db, &query.offer_token, &query.want_token, secs, since).await?;
+    Ok(Json(CandlesResponse { interval, candles }))
+}
+
+/// Trade count, volume, price range and fees of a pair over the last day
+pub async fn get_stats(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<StatsQuery>,
+) -> Result<Json<StatsResponse>, ApiError> {
+    let since = chrono::Utc::now() - chrono::Duration::hours(STATS_WINDOW_HOURS);
+    let stats = db::get_fill_stats(&state.db, &query.offer_token, &query.want_token, since).await?;
+    Ok(Json(StatsResponse {
+        window_hours: STATS_WINDOW_HOURS,
+        stats,
+    }))
+}
@@ -0,0 +1,295 @@
+//! Raw transaction decoding
+//!
+//! Support tooling and wallet verification screens need a readable view of a
+//! transaction: the outputs it spends and their values, the outputs it
+//! creates, the fee it pays, and the charms its spell moves. Transactions are
+//! decoded here rather than by the node so the same view works with either
+//! chain backend and for transactions not yet broadcast.
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use bitcoin::consensus::encode::deserialize_hex;
+use bitcoin::{Address, Transaction, Txid};
+use futures_util::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::routes::error::ApiError;
+use crate::routes::orders::AppState;
+use crate::services::chain::{ChainBackend, TxStatus};
+use crate::services::spell_decode::{decode_spell_tx, DecodedSpell};
+
+/// Decode-from-hex request
+#[derive(Debug, Deserialize)]
+pub struct DecodeTransactionRequest {
+    pub tx_hex: String,
+}
+
+/// A transaction with its spent outputs and charms
+#[derive(Debug, Serialize)]
+pub struct DecodedTransaction {
+    pub txid: String,
+    pub wtxid: String,
+    pub version: i32,
+    pub lock_time: u32,
+    pub size: usize,
+    pub vsize: usize,
+    pub weight: u64,
+    pub inputs: Vec<DecodedInput>,
+    pub outputs: Vec<DecodedTxOutput>,
+    /// Sats; `None` when a spent output could not be looked up
+    pub fee: Option<u64>,
+    /// sat/vB
+    pub fee_rate: Option<f64>,
+    /// Spell the transaction carries, if any
+    pub spell: Option<DecodedSpell>,
+    /// `None` when the chain backend does not know the transaction
+    pub status: Option<TxStatus>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DecodedInput {
+    pub txid: String,
+    pub vout: u32,
+    pub sequence: u32,
+    pub witness_items: usize,
+    /// Sats of the spent output
+    pub value: Option<u64>,
+    pub address: Option<String>,
+    /// Charms the spent output held
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub charms: Option<BTreeMap<String, serde_json::Value>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DecodedTxOutput {
+    pub vout: u32,
+    /// Sats
+    pub value: u64,
+    pub address: Option<String>,
+    pub script_pubkey: String,
+    /// Charms the spell puts in this output
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub charms: Option<BTreeMap<String, serde_json::Value>>,
+}
+
+/// Decode a transaction the chain backend knows
+pub async fn get_decoded_transaction(
+    State(state): State<Arc<AppState>>,
+    Path(txid): Path<String>,
+) -> Result<Json<DecodedTransaction>, ApiError> {
+    if Txid::from_str(&txid).is_err() {
+        return Err(ApiError::bad_request(format!("Invalid txid {}", txid)));
+    }
+    let hex = state
+        .chain
+        .raw_transaction(&txid)
+        .await
+        .map_err(|e| ApiError::not_found(format!("Transaction {} not found: {}", txid, e)))?;
+    let tx: Transaction = deserialize_hex(&hex).map_err(|e| ApiError::internal(format!("Invalid transaction: {}", e)))?;
+
+    Ok(Json(decode_transaction(state.chain.as_ref(), tx).await))
+}
+
+/// Decode a raw transaction, e.g. one about to be signed or broadcast
+pub async fn decode_transaction_hex(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<DecodeTransactionRequest>,
+) -> Result<Json<DecodedTransaction>, ApiError> {
+    let tx: Transaction = deserialize_hex(req.tx_hex.trim())
+        .map_err(|e| ApiError::bad_request(format!("Invalid transaction hex: {}", e)))?;
+
+    Ok(Json(decode_transaction(state.chain.as_ref(), tx).await))
+}
+
+async fn decode_transaction(chain: &dyn ChainBackend, tx: Transaction) -> DecodedTransaction {
+    let network = chain.network();
+    let txid = tx.compute_txid();
+
+    let prev_txs = previous_transactions(chain, &tx).await;
+    let prev_spells: HashMap<Txid, DecodedSpell> = prev_txs
+        .iter()
+        .filter_map(|(txid, prev)| Some((*txid, decode_spell_tx(prev).ok().flatten()?)))
+        .collect();
+
+    let inputs: Vec<DecodedInput> = tx
+        .input
+        .iter()
+        .map(|input| {
+            let outpoint = input.previous_output;
+            let spent = prev_txs
+                .get(&outpoint.txid)
+                .and_then(|prev| prev.output.get(outpoint.vout as usize));
+            DecodedInput {
+                txid: outpoint.txid.to_string(),
+                vout: outpoint.vout,
+                sequence: input.sequence.0,
+                witness_items: input.witness.len(),
+                value: spent.map(|out| out.value.to_sat()),
+                address: spent.and_then(|out| Address::from_script(&out.script_pubkey, network).ok()).map(|a| a.to_string()),
+                charms: prev_spells
+                    .get(&outpoint.txid)
+                    .and_then(|spell| spell.charms_at(outpoint.vout))
+                    .cloned(),
+            }
+        })
+        .collect();
+
+    let spell = decode_spell_tx(&tx).unwrap_or_else(|e| {
+        tracing::debug!("Failed to decode spell in {}: {:#}", txid, e);
+        None
+    });
+    let outputs = tx
+        .output
+        .iter()
+        .enumerate()
+        .map(|(vout, out)| DecodedTxOutput {
+            vout: vout as u32,
+            value: out.value.to_sat(),
+            address: Address::from_script(&out.script_pubkey, network).ok().map(|a| a.to_string()),
+            script_pubkey: out.script_pubkey.to_hex_string(),
+            charms: spell.as_ref().and_then(|spell| spell.charms_at(vout as u32)).cloned(),
+        })
+        .collect();
+
+    // Coinbase transactions spend nothing and pay no fee
+    let fee = match tx.is_coinbase() {
+        true => None,
+        false => inputs
+            .iter()
+            .map(|input| input.value)
+            .sum::<Option<u64>>()
+            .and_then(|spent| spent.checked_sub(tx.output.iter().map(|out| out.value.to_sat()).sum())),
+    };
+    let vsize = tx.vsize();
+    let status = chain.tx_status(&txid.to_string()).await.ok().flatten();
+
+    DecodedTransaction {
+        txid: txid.to_string(),
+        wtxid: tx.compute_wtxid().to_string(),
+        version: tx.version.0,
+        lock_time: tx.lock_time.to_consensus_u32(),
+        size: tx.total_size(),
+        vsize,
+        weight: tx.weight().to_wu(),
+        inputs,
+        outputs,
+        fee,
+        fee_rate: fee.map(|fee| fee as f64 / vsize as f64),
+        spell,
+        status,
+    }
+}
+
+/// Transactions whose outputs `tx` spends, skipping any the chain backend
+/// cannot find
+async fn previous_transactions(chain: &dyn ChainBackend, tx: &Transaction) -> HashMap<Txid, Transaction> {
+    if tx.is_coinbase() {
+        return HashMap::new();
+    }
+    let txids: Vec<Txid> = tx
+        .input
+        .iter()
+        .map(|input| input.previous_output.txid)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+
+    let hex_txids: Vec<String> = txids.iter().map(Txid::to_string).collect();
+    let fetched = join_all(hex_txids.iter().map(|txid| chain.raw_transaction(txid))).await;
+    txids
+        .into_iter()
+        .zip(fetched)
+        .filter_map(|(txid, hex)| match hex.and_then(|hex| Ok(deserialize_hex::<Transaction>(&hex)?)) {
+            Ok(prev) => Some((txid, prev)),
+            Err(e) => {
+                tracing::debug!("Could not fetch previous transaction {}: {:#}", txid, e);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::consensus::encode::serialize_hex;
+    use bitcoin::hashes::Hash;
+    use bitcoin::transaction::Version;
+    use bitcoin::{Amount, Network, OutPoint, ScriptBuf, Sequence, TxIn, TxOut, Witness};
+
+    use crate::db::DbPool;
+    use crate::routes::orders::testing::test_state;
+
+    const ADDRESS: &str = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+
+    fn spending(outpoints: &[OutPoint], value: u64) -> Transaction {
+        let script = Address::from_str(ADDRESS).unwrap().require_network(Network::Testnet4).unwrap().script_pubkey();
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: outpoints
+                .iter()
+                .map(|outpoint| TxIn {
+                    previous_output: *outpoint,
+                    script_sig: ScriptBuf::new(),
+                    sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                    witness: Witness::new(),
+                })
+                .collect(),
+            output: vec![TxOut {
+                value: Amount::from_sat(value),
+                script_pubkey: script,
+            }],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transactions_decode_with_their_spent_outputs() {
+        // Decoding reads the chain backend only, so the database is never reached
+        let state = Arc::new(test_state(DbPool::connect_lazy("postgres://localhost/unused").unwrap()));
+
+        let parent = spending(&[OutPoint::new(Txid::from_byte_array([1; 32]), 0)], 50_000);
+        let parent_txid = state.chain.broadcast(&serialize_hex(&parent)).await.unwrap();
+        let child = spending(&[OutPoint::new(parent.compute_txid(), 0)], 49_000);
+
+        // Not yet broadcast: the spent output is looked up, the status is not known
+        let request = DecodeTransactionRequest { tx_hex: format!(" {} ", serialize_hex(&child)) };
+        let decoded = decode_transaction_hex(State(state.clone()), Json(request)).await.unwrap().0;
+        assert_eq!(decoded.txid, child.compute_txid().to_string());
+        assert_eq!(decoded.inputs[0].txid, parent_txid);
+        assert_eq!(decoded.inputs[0].value, Some(50_000));
+        assert_eq!(decoded.inputs[0].address.as_deref(), Some(ADDRESS));
+        assert_eq!(decoded.outputs[0].address.as_deref(), Some(ADDRESS));
+        assert_eq!(decoded.fee, Some(1000));
+        assert_eq!(decoded.fee_rate, Some(1000.0 / decoded.vsize as f64));
+        assert!(decoded.spell.is_none());
+        assert!(decoded.status.is_none());
+
+        let child_txid = state.chain.broadcast(&serialize_hex(&child)).await.unwrap();
+        let decoded = get_decoded_transaction(State(state.clone()), Path(child_txid)).await.unwrap().0;
+        assert_eq!(decoded.status.unwrap().confirmations, 0);
+
+        // A fee cannot be known while any spent output is unknown
+        let unknown = OutPoint::new(Txid::from_byte_array([9; 32]), 0);
+        let orphan = spending(&[OutPoint::new(parent.compute_txid(), 0), unknown], 1000);
+        let request = DecodeTransactionRequest { tx_hex: serialize_hex(&orphan) };
+        let decoded = decode_transaction_hex(State(state.clone()), Json(request)).await.unwrap().0;
+        assert_eq!(decoded.inputs[1].value, None);
+        assert_eq!(decoded.fee, None);
+
+        let err = get_decoded_transaction(State(state.clone()), Path("not-a-txid".to_string())).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        let err = get_decoded_transaction(State(state.clone()), Path("ab".repeat(32))).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::NOT_FOUND);
+        let request = DecodeTransactionRequest { tx_hex: "zz".to_string() };
+        let err = decode_transaction_hex(State(state), Json(request)).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+    }
+}
@@ -1,21 +1,62 @@
 //! Wallet management endpoints
 
-use axum::Json;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use bitcoin::consensus::encode::serialize_hex;
+use bitcoin::{Address, OutPoint, ScriptBuf, TxOut, Txid};
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::db::{self, OrderRecord, UtxoLockRecord};
+use crate::routes::auth::WalletSession;
+use crate::routes::error::ApiError;
+use crate::routes::orders::{signing_payloads, AppState, InputToSign, UnsignedTransaction, DEFAULT_APP_ID};
+use crate::routes::wallet_formats::WalletFormat;
+use crate::services::addresses::{self, TaprootDerivation};
+use crate::services::bitcoin::UnspentOutput;
+use crate::services::chain::ChainKind;
+use crate::services::charms::ProvedTransaction;
+use crate::services::consolidation::{build_consolidation, SweepInput, MAX_SWEEP_INPUTS};
+use crate::services::fee_bump::{build_cpfp_child, load_stuck, BumpError, PaidFee, MAX_BUMP_FEE_RATE};
+use crate::services::fees::FeeTier;
+use crate::services::network;
+use crate::services::psbt::{build_psbts, finalize_psbts, BuiltPsbt, FinalizedPsbt, KeyOrigin};
+use crate::services::sessions::{Challenge, Session};
+use crate::services::signatures::{verify_bip322, verify_signature};
+use crate::services::spell_decode::decode_spell_tx;
+use crate::services::tokens::TokenRegistry;
 
 /// UTXO representation
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Utxo {
     pub txid: String,
     pub vout: u32,
+    pub address: String,
     pub value: u64,
     pub script_pubkey: String,
     pub confirmations: u32,
     pub charms: Option<Vec<CharmData>>,
+    /// Order or escrow whose pending spell spends this UTXO
+    pub reserved_by: Option<String>,
+    /// Set while the UTXO is locked against use by other drafts
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+/// UTXO list filters
+#[derive(Debug, Deserialize)]
+pub struct UtxoQuery {
+    pub address: Option<String>,
+    pub min_conf: Option<u32>,
 }
 
 /// Charm data on a UTXO
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CharmData {
     pub app_id: String,
     pub app_tag: String,  // "token" or "nft"
@@ -25,24 +66,92 @@ pub struct CharmData {
 /// Wallet balance
 #[derive(Debug, Serialize)]
 pub struct WalletBalance {
+    /// Addresses the balance covers; empty means the whole node wallet
+    pub addresses: Vec<String>,
     pub btc_balance: u64,
+    pub unconfirmed_btc: u64,
     pub tokens: Vec<TokenBalance>,
+    pub nfts: Vec<NftHolding>,
 }
 
 /// Token balance
 #[derive(Debug, Serialize)]
 pub struct TokenBalance {
-    pub token_id: String,
-    pub ticker: String,
-    pub amount: String,
+    pub app_id: String,
+    pub symbol: String,
+    pub name: Option<String>,
+    pub decimals: u8,
+    pub amount: u64,
+    pub utxo_count: usize,
+}
+
+/// NFT charm held in the wallet
+#[derive(Debug, Serialize)]
+pub struct NftHolding {
+    pub app_id: String,
+    pub utxo: String,
+    pub data: serde_json::Value,
+}
+
+/// Balance filters
+#[derive(Debug, Deserialize)]
+pub struct BalanceQuery {
+    /// Address to report on; defaults to every wallet linked to the session
+    pub address: Option<String>,
+}
+
+/// Transactions scanned from the node wallet when building history
+const HISTORY_SCAN_LIMIT: u32 = 1000;
+const DEFAULT_HISTORY_LIMIT: u32 = 50;
+const MAX_HISTORY_LIMIT: u32 = 200;
+
+/// History pagination
+#[derive(Debug, Deserialize)]
+pub struct HistoryQuery {
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+/// Transaction in the wallet history
+#[derive(Debug, Serialize)]
+pub struct HistoryEntry {
+    pub txid: String,
+    /// Net BTC change across the session's wallets in sats, excluding fees;
+    /// `None` when the node wallet has no record of the transaction
+    pub btc_change: Option<i64>,
+    pub confirmations: Option<i64>,
+    pub time: DateTime<Utc>,
+    pub charm_effects: Vec<CharmEffect>,
+}
+
+/// What a transaction did to charms, from the spell index
+#[derive(Debug, Serialize)]
+pub struct CharmEffect {
+    pub action: String,
+    pub order_id: Option<String>,
+    pub escrow_id: Option<String>,
+    pub token_id: Option<String>,
+    pub symbol: Option<String>,
+    pub amount: Option<String>,
+    pub description: String,
 }
 
 /// Connect wallet request
 #[derive(Debug, Deserialize)]
 pub struct ConnectWalletRequest {
     pub address: String,
+    /// BIP-322 signature (base64) over the issued challenge message
     pub signature: Option<String>,
+    /// Challenge message that was signed; must match the one issued
     pub message: Option<String>,
+    /// Hardware wallets: origin of the address's key, so signing payloads
+    /// include derivation paths
+    pub key_origin: Option<KeyOrigin>,
+    /// Public key (hex) behind the address, used to find the wallet's escrows
+    pub pubkey: Option<String>,
+    /// BIP-340 signature (hex) by `pubkey` over the same challenge message;
+    /// required with `pubkey`, so only a key the wallet holds is bound
+    pub pubkey_signature: Option<String>,
 }
 
 /// Connect wallet response
@@ -51,60 +160,929 @@ pub struct ConnectWalletResponse {
     pub connected: bool,
     pub address: String,
     pub network: String,
+    /// Challenge to sign, returned when no signature was supplied
+    pub challenge: Option<Challenge>,
+    /// Bearer token for authenticated requests once connected
+    pub session_token: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
 }
 
-/// Connect wallet endpoint
+/// Connect wallet endpoint.
+///
+/// Called first with just the address to receive a challenge, then again with
+/// the wallet's BIP-322 signature over the challenge message to open a session.
 pub async fn connect_wallet(
+    State(state): State<Arc<AppState>>,
     Json(req): Json<ConnectWalletRequest>,
-) -> Json<ConnectWalletResponse> {
-    // TODO: Verify signature if provided
-    // TODO: Determine network from address
-    
-    Json(ConnectWalletResponse {
+) -> Result<Json<ConnectWalletResponse>, ApiError> {
+    let network = state.bitcoin.network();
+    network::check_address(&req.address, network).map_err(|e| ApiError::bad_request(e.to_string()))?;
+
+    let signature = match req.signature.as_deref() {
+        Some(signature) if !signature.is_empty() => signature,
+        _ => {
+            let challenge = state.sessions.issue_challenge(&req.address).await?.ok_or_else(|| {
+                ApiError::new(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "rate_limited",
+                    "Too many wallets are connecting right now; retry shortly",
+                )
+            })?;
+            return Ok(Json(ConnectWalletResponse {
+                connected: false,
+                address: req.address,
+                network: network::name(network).to_string(),
+                challenge: Some(challenge),
+                session_token: None,
+                expires_at: None,
+            }));
+        }
+    };
+
+    verify_challenge(&state, &req, signature).await?;
+
+    let mut session = state.sessions.create_session(&req.address).await?;
+    if let Some(pubkey) = req.pubkey.as_deref() {
+        session = state
+            .sessions
+            .link_address(&session.token, &req.address, Some(pubkey))
+            .await?
+            .unwrap_or(session);
+    }
+    tracing::info!("Wallet {} connected", req.address);
+
+    Ok(Json(ConnectWalletResponse {
         connected: true,
-        address: req.address,
-        network: "testnet4".to_string(),
+        address: session.address,
+        network: network::name(network).to_string(),
+        challenge: None,
+        session_token: Some(session.token),
+        expires_at: Some(session.expires_at),
+    }))
+}
+
+/// Linked wallets of the current session
+pub async fn get_session(WalletSession(session): WalletSession) -> Json<Session> {
+    Json(session)
+}
+
+/// End the current session; its token stops working at once
+pub async fn end_session(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+) -> Result<StatusCode, ApiError> {
+    state.sessions.end_session(&session.token).await?;
+    tracing::info!("Wallet {} disconnected", session.address);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Link another wallet to the current session. The address must first get a
+/// challenge from `POST /api/wallet/connect` and sign it like a normal connect.
+pub async fn link_wallet(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+    Json(req): Json<ConnectWalletRequest>,
+) -> Result<Json<Session>, ApiError> {
+    network::check_address(&req.address, state.bitcoin.network()).map_err(|e| ApiError::bad_request(e.to_string()))?;
+    let signature = req
+        .signature
+        .as_deref()
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| ApiError::bad_request("A signature over the address's challenge is required"))?;
+
+    verify_challenge(&state, &req, signature).await?;
+
+    let session = state
+        .sessions
+        .link_address(&session.token, &req.address, req.pubkey.as_deref())
+        .await?
+        .ok_or_else(|| ApiError::new(StatusCode::UNAUTHORIZED, "unauthorized", "Session has expired"))?;
+    tracing::info!("Wallet {} linked to session of {}", req.address, session.address);
+
+    Ok(Json(session))
+}
+
+/// Unlink a wallet from the current session
+pub async fn unlink_wallet(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+    Path(address): Path<String>,
+) -> Result<Json<Session>, ApiError> {
+    if address == session.address {
+        return Err(ApiError::bad_request("The wallet the session was opened with cannot be unlinked"));
+    }
+
+    let session = state
+        .sessions
+        .unlink_address(&session.token, &address)
+        .await?
+        .ok_or_else(|| ApiError::new(StatusCode::UNAUTHORIZED, "unauthorized", "Session has expired"))?;
+
+    Ok(Json(session))
+}
+
+/// Verify a wallet's BIP-322 signature over its outstanding challenge, and
+/// its public key's signature over the same message when it declares one,
+/// then record the key origin it declared
+async fn verify_challenge(
+    state: &AppState,
+    req: &ConnectWalletRequest,
+    signature: &str,
+) -> Result<(), ApiError> {
+    if let Some(pubkey) = req.pubkey.as_deref() {
+        addresses::parse_xonly_key(pubkey)
+            .map_err(|e| ApiError::bad_request(format!("Invalid public key: {:#}", e)))?;
+    }
+
+    // Challenges are single use: a failed attempt has to request a new one
+    let challenge = state
+        .sessions
+        .take_challenge(&req.address)
+        .await?
+        .ok_or_else(|| ApiError::bad_request("No pending challenge for this address; request a new one"))?;
+
+    if req.message.as_deref().is_some_and(|m| m != challenge.message) {
+        return Err(ApiError::bad_request("Signed message does not match the issued challenge"));
+    }
+
+    if let Err(e) = verify_bip322(&req.address, &challenge.message, signature) {
+        return Err(ApiError::new(
+            StatusCode::UNAUTHORIZED,
+            "invalid_signature",
+            format!("Wallet signature rejected: {}", e),
+        ));
+    }
+    if let Some(pubkey) = req.pubkey.as_deref() {
+        check_pubkey_signature(pubkey, req.pubkey_signature.as_deref(), &challenge.message)?;
+    }
+
+    if let Some(origin) = req.key_origin.clone() {
+        origin
+            .parse()
+            .map_err(|e| ApiError::bad_request(format!("Invalid key origin: {:#}", e)))?;
+        state.sessions.set_key_origin(&req.address, origin).await;
+    }
+
+    Ok(())
+}
+
+/// Check that a declared public key signed the challenge message
+fn check_pubkey_signature(pubkey: &str, signature: Option<&str>, message: &str) -> Result<(), ApiError> {
+    let signature = signature
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| ApiError::missing("pubkey_signature"))?;
+    verify_signature(pubkey, message, signature).map_err(|e| {
+        ApiError::new(
+            StatusCode::UNAUTHORIZED,
+            "invalid_signature",
+            format!("Public key signature rejected: {}", e),
+        )
     })
 }
 
-/// Get wallet balance
-pub async fn get_balance() -> Json<WalletBalance> {
-    // TODO: Query Bitcoin Core for balance
-    // TODO: Parse UTXOs for charm tokens
-    
-    Json(WalletBalance {
-        btc_balance: 50000,  // sats
-        tokens: vec![
+/// Get wallet balance: BTC plus the charm tokens and NFTs on its UTXOs
+pub async fn get_balance(
+    State(state): State<Arc<AppState>>,
+    session: Option<WalletSession>,
+    Query(query): Query<BalanceQuery>,
+) -> Result<Json<WalletBalance>, ApiError> {
+    let addresses: Vec<String> = match (query.address, session) {
+        (Some(address), _) => vec![address],
+        (None, Some(WalletSession(session))) => session.addresses,
+        (None, None) => return Err(no_wallet("get a balance")),
+    };
+
+    let utxos = match state.chain.list_unspent(&addresses, 0).await {
+        Ok(unspent) => {
+            let unspent = unspent.into_iter().filter(|u| addresses.contains(&u.address)).collect();
+            annotate_utxos(&state, unspent).await?
+        }
+        Err(e) => {
+            return Err(ApiError::new(
+                StatusCode::BAD_GATEWAY,
+                "node_unavailable",
+                format!("Failed to list unspent outputs: {}", e),
+            ));
+        }
+    };
+
+    Ok(Json(summarize_balance(&state.tokens, addresses, &utxos)))
+}
+
+/// Total BTC and group charms by app id across annotated UTXOs
+fn summarize_balance(registry: &TokenRegistry, addresses: Vec<String>, utxos: &[Utxo]) -> WalletBalance {
+    let mut btc_balance = 0;
+    let mut unconfirmed_btc = 0;
+    let mut token_totals: BTreeMap<String, (u64, usize)> = BTreeMap::new();
+    let mut nfts = Vec::new();
+
+    for utxo in utxos {
+        if utxo.confirmations > 0 {
+            btc_balance += utxo.value;
+        } else {
+            unconfirmed_btc += utxo.value;
+        }
+
+        for charm in utxo.charms.iter().flatten() {
+            match charm.app_tag.as_str() {
+                "token" => {
+                    let amount = charm.data.get("amount").and_then(|a| a.as_u64()).unwrap_or(0);
+                    let entry = token_totals.entry(charm.app_id.clone()).or_default();
+                    entry.0 += amount;
+                    entry.1 += 1;
+                }
+                _ => nfts.push(NftHolding {
+                    app_id: charm.app_id.clone(),
+                    utxo: format!("{}:{}", utxo.txid, utxo.vout),
+                    data: charm.data.clone(),
+                }),
+            }
+        }
+    }
+
+    let tokens = token_totals
+        .into_iter()
+        .map(|(app_id, (amount, utxo_count))| {
+            let info = registry.lookup(&app_id);
             TokenBalance {
-                token_id: "abc123".to_string(),
-                ticker: "TOAD".to_string(),
-                amount: "10000".to_string(),
-            },
-        ],
-    })
+                app_id,
+                symbol: info.symbol,
+                name: info.name,
+                decimals: info.decimals,
+                amount,
+                utxo_count,
+            }
+        })
+        .collect();
+
+    WalletBalance {
+        addresses,
+        btc_balance,
+        unconfirmed_btc,
+        tokens,
+        nfts,
+    }
+}
+
+/// Transaction history of the session's wallets: node wallet transactions
+/// touching their addresses (with the node as chain backend), joined with
+/// the spell index for charm effects
+pub async fn get_history(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<Vec<HistoryEntry>>, ApiError> {
+    let limit = query.limit.unwrap_or(DEFAULT_HISTORY_LIMIT).clamp(1, MAX_HISTORY_LIMIT) as usize;
+    let offset = query.offset.unwrap_or(0) as usize;
+
+    let mut entries: HashMap<String, HistoryEntry> = HashMap::new();
+    // Only the node keeps a wallet; other chain backends have the spell index alone
+    if state.chain.kind() == ChainKind::Node {
+        let txs = state.bitcoin.list_transactions(HISTORY_SCAN_LIMIT, 0).await.map_err(|e| {
+            ApiError::new(
+                StatusCode::BAD_GATEWAY,
+                "node_unavailable",
+                format!("Failed to list transactions: {}", e),
+            )
+        })?;
+        for tx in txs.into_iter().filter(|t| t.address.as_deref().is_some_and(|a| session.owns(a))) {
+            let entry = entries.entry(tx.txid.clone()).or_insert_with(|| HistoryEntry {
+                txid: tx.txid.clone(),
+                btc_change: Some(0),
+                confirmations: Some(tx.confirmations),
+                time: DateTime::from_timestamp(tx.time, 0).unwrap_or_default(),
+                charm_effects: Vec::new(),
+            });
+            let sats = (tx.amount * 100_000_000.0).round() as i64;
+            entry.btc_change = entry.btc_change.map(|change| change + sats);
+        }
+    }
+
+    // Spells on transactions the node saw, plus order spells of the session's
+    // makers the node may not know
+    let txids: Vec<String> = entries.keys().cloned().collect();
+    let mut spells = db::get_spell_transactions(&state.db, &txids).await?;
+    spells.extend(db::get_spell_transactions_for_makers(&state.db, &session.addresses).await?);
+
+    for spell in spells {
+        let entry = entries.entry(spell.txid.clone()).or_insert_with(|| HistoryEntry {
+            txid: spell.txid.clone(),
+            btc_change: None,
+            confirmations: None,
+            time: spell.created_at,
+            charm_effects: Vec::new(),
+        });
+        if entry.charm_effects.is_empty() {
+            entry.charm_effects.push(charm_effect(&state.tokens, spell));
+        }
+    }
+
+    let mut history: Vec<HistoryEntry> = entries.into_values().collect();
+    history.sort_by(|a, b| b.time.cmp(&a.time).then_with(|| a.txid.cmp(&b.txid)));
+
+    Ok(Json(history.into_iter().skip(offset).take(limit).collect()))
+}
+
+/// Describe a spell index record for display
+fn charm_effect(registry: &TokenRegistry, spell: db::SpellTransactionRecord) -> CharmEffect {
+    let symbol = spell.token_id.as_deref().map(|id| registry.lookup(id).symbol);
+    let amount = match (&spell.amount, &symbol) {
+        (Some(amount), Some(symbol)) => format!("{} {}", amount, symbol),
+        (Some(amount), None) => amount.clone(),
+        _ => "tokens".to_string(),
+    };
+    let escrow = spell.escrow_id.as_deref().unwrap_or("");
+
+    let description = match spell.action.as_str() {
+        "order_created" => format!(
+            "Created order {}, locking {}",
+            spell.order_id.as_deref().unwrap_or(""),
+            amount
+        ),
+        "order_filled" => format!(
+            "Filled {} of order {}",
+            amount,
+            spell.order_id.as_deref().unwrap_or("")
+        ),
+        "order_cancelled" => format!(
+            "Cancelled order {}, unlocking {}",
+            spell.order_id.as_deref().unwrap_or(""),
+            amount
+        ),
+        "escrow_created" => format!("Locked {} in escrow {}", amount, escrow),
+        "escrow_released" => format!("Escrow {} released {} to the recipient", escrow, amount),
+        "escrow_refunded" => format!("Escrow {} refunded {} to the depositor", escrow, amount),
+        "milestone_released" => format!("Escrow {} released a milestone of {}", escrow, amount),
+        other => other.replace('_', " "),
+    };
+
+    CharmEffect {
+        action: spell.action,
+        order_id: spell.order_id,
+        escrow_id: spell.escrow_id,
+        token_id: spell.token_id,
+        symbol,
+        amount: spell.amount,
+        description,
+    }
+}
+
+/// Get wallet UTXOs from the chain backend, annotated with the charms they carry and
+/// any pending order that has claimed them. With a session and no address
+/// filter, UTXOs of all the session's linked wallets are returned.
+pub async fn get_utxos(
+    State(state): State<Arc<AppState>>,
+    session: Option<WalletSession>,
+    Query(query): Query<UtxoQuery>,
+) -> Result<Json<Vec<Utxo>>, ApiError> {
+    let addresses: Vec<String> = match (query.address, session) {
+        (Some(address), _) => vec![address],
+        (None, Some(WalletSession(session))) => session.addresses,
+        (None, None) => return Err(no_wallet("list UTXOs")),
+    };
+    let unspent = match state.chain.list_unspent(&addresses, query.min_conf.unwrap_or(0)).await {
+        Ok(unspent) => unspent,
+        Err(e) => {
+            return Err(ApiError::new(
+                StatusCode::BAD_GATEWAY,
+                "node_unavailable",
+                format!("Failed to list unspent outputs: {}", e),
+            ));
+        }
+    };
+
+    // The node wallet may hold other addresses; only the requested ones count
+    let unspent = unspent.into_iter().filter(|u| addresses.contains(&u.address)).collect();
+
+    Ok(Json(annotate_utxos(&state, unspent).await?))
 }
 
-/// Get wallet UTXOs
-pub async fn get_utxos() -> Json<Vec<Utxo>> {
-    // TODO: Query Bitcoin Core for UTXOs
-    // TODO: Parse charm data from UTXOs
-    
-    Json(vec![
-        Utxo {
-            txid: "abc123def456".to_string(),
-            vout: 0,
-            value: 10000,
-            script_pubkey: "0014...".to_string(),
-            confirmations: 6,
-            charms: Some(vec![
+/// Refusal for wallet queries made with neither a session nor an address
+fn no_wallet(action: &str) -> ApiError {
+    ApiError::new(
+        StatusCode::UNAUTHORIZED,
+        "unauthorized",
+        format!("Connect a wallet or pass ?address= to {}", action),
+    )
+}
+
+/// Convert node UTXOs to API UTXOs with charm and reservation annotations
+pub(crate) async fn annotate_utxos(
+    state: &AppState,
+    unspent: Vec<UnspentOutput>,
+) -> anyhow::Result<Vec<Utxo>> {
+    let orders = db::get_live_orders(&state.db).await?;
+    let (charms, reserved) = index_order_utxos(&orders);
+    let locks: HashMap<String, UtxoLockRecord> = db::get_active_utxo_locks(&state.db)
+        .await?
+        .into_iter()
+        .map(|lock| (lock.outpoint.clone(), lock))
+        .collect();
+
+    let utxos = unspent
+        .into_iter()
+        .map(|u| {
+            let outpoint = format!("{}:{}", u.txid, u.vout);
+            let lock = locks.get(&outpoint);
+            Utxo {
+                charms: charms.get(&outpoint).map(|c| c.to_vec()),
+                reserved_by: reserved
+                    .get(&outpoint)
+                    .cloned()
+                    .or_else(|| lock.and_then(|l| l.order_id.clone().or_else(|| l.escrow_id.clone()))),
+                locked_until: lock.map(|l| l.expires_at),
+                value: (u.amount * 100_000_000.0).round() as u64,
+                txid: u.txid,
+                vout: u.vout,
+                address: u.address,
+                script_pubkey: u.script_pub_key,
+                confirmations: u.confirmations,
+            }
+        })
+        .collect();
+
+    Ok(utxos)
+}
+
+/// Map outpoints to the charms placed on them by order spells, and to the
+/// pending orders that reserve them as funding.
+///
+/// Charms are recognized from the spells this backend built; UTXOs charmed
+/// elsewhere are not annotated.
+fn index_order_utxos(
+    orders: &[OrderRecord],
+) -> (HashMap<String, Vec<CharmData>>, HashMap<String, String>) {
+    let mut charms = HashMap::new();
+    let mut reserved = HashMap::new();
+
+    for order in orders {
+        if order.status == "pendingsignature" {
+            if let Some(utxo) = &order.utxo_id {
+                reserved.insert(utxo.clone(), order.id.clone());
+            }
+            continue;
+        }
+
+        // The create-order spell puts the order NFT and offered tokens in output 0
+        if let Some(txid) = &order.tx_id {
+            charms.insert(format!("{}:0", txid), vec![
                 CharmData {
-                    app_id: "toad_token".to_string(),
+                    app_id: DEFAULT_APP_ID.to_string(),
+                    app_tag: "nft".to_string(),
+                    data: serde_json::json!({
+                        "order_id": order.id,
+                        "offer_app_id": order.offer_token,
+                        "offer_amount": order.offer_amount,
+                        "want_app_id": order.want_token,
+                        "want_amount": order.want_amount,
+                        "filled_amount": order.filled_amount,
+                    }),
+                },
+                CharmData {
+                    app_id: order.offer_token.clone(),
                     app_tag: "token".to_string(),
-                    data: serde_json::json!({ "amount": 5000 }),
+                    data: serde_json::json!({ "amount": order.offer_amount }),
                 },
-            ]),
+            ]);
+        }
+    }
+
+    (charms, reserved)
+}
+
+/// Only UTXOs below this value are swept unless the request says otherwise
+const DEFAULT_SWEEP_MAX_VALUE: u64 = 100_000;
+/// Upper bound on a requested consolidation fee rate (sat/vB)
+const MAX_SWEEP_FEE_RATE: f64 = 1000.0;
+
+/// UTXO consolidation request
+#[derive(Debug, Default, Deserialize)]
+pub struct ConsolidateRequest {
+    /// Address receiving the swept output (default: the session's primary wallet)
+    pub destination: Option<String>,
+    /// sat/vB; defaults to the economy tier
+    pub fee_rate: Option<f64>,
+    /// Sweep only UTXOs worth less than this many sats (default 100000)
+    pub max_utxo_value: Option<u64>,
+    /// Minimum confirmations of swept UTXOs (default 1)
+    pub min_conf: Option<u32>,
+}
+
+/// Consolidation transaction ready for signing
+#[derive(Debug, Serialize)]
+pub struct ConsolidateResponse {
+    /// Outpoints swept, in input order
+    pub utxos: Vec<String>,
+    pub input_value: u64,
+    pub output_value: u64,
+    pub fee: u64,
+    pub fee_rate: f64,
+    pub vbytes: u64,
+    pub unsigned_tx: UnsignedTransaction,
+}
+
+/// Build a transaction sweeping the session wallets' small UTXOs into one
+/// output. Charm-bearing, reserved and locked UTXOs are left alone.
+pub async fn consolidate_utxos(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+    wallet: WalletFormat,
+    body: Option<Json<ConsolidateRequest>>,
+) -> Result<Json<ConsolidateResponse>, ApiError> {
+    let req = body.map(|Json(req)| req).unwrap_or_default();
+
+    let destination = req.destination.as_deref().unwrap_or(&session.address);
+    let destination = Address::from_str(destination)
+        .ok()
+        .and_then(|a| a.require_network(state.bitcoin.network()).ok())
+        .ok_or_else(|| ApiError::bad_request(format!("Invalid destination address: {}", destination)))?;
+
+    let fee_rate = match req.fee_rate {
+        Some(rate) if rate > 0.0 && rate <= MAX_SWEEP_FEE_RATE => rate,
+        Some(_) => {
+            return Err(ApiError::bad_request(format!("fee_rate must be between 0 and {}", MAX_SWEEP_FEE_RATE)));
+        }
+        None => state.fees.fee_rate(state.chain.as_ref(), FeeTier::Economy).await,
+    };
+    let max_value = req.max_utxo_value.unwrap_or(DEFAULT_SWEEP_MAX_VALUE);
+
+    let unspent = state
+        .chain
+        .list_unspent(&session.addresses, req.min_conf.unwrap_or(1))
+        .await
+        .map_err(|e| {
+            ApiError::new(StatusCode::BAD_GATEWAY, "node_unavailable", format!("Failed to list unspent outputs: {}", e))
+        })?
+        .into_iter()
+        .filter(|u| u.spendable && session.owns(&u.address))
+        .collect();
+
+    let mut utxos: Vec<Utxo> = annotate_utxos(&state, unspent)
+        .await?
+        .into_iter()
+        .filter(|u| u.value < max_value && u.charms.is_none() && u.reserved_by.is_none() && u.locked_until.is_none())
+        .collect();
+    // Smallest first: those are the ones fragmenting the wallet
+    utxos.sort_by_key(|u| u.value);
+    utxos.truncate(MAX_SWEEP_INPUTS);
+
+    let inputs = utxos
+        .iter()
+        .map(|u| {
+            Ok(SweepInput {
+                outpoint: OutPoint::new(Txid::from_str(&u.txid)?, u.vout),
+                value: u.value,
+                script_pubkey: ScriptBuf::from_hex(&u.script_pubkey)?,
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let built = build_consolidation(&inputs, destination.script_pubkey(), fee_rate)
+        .map_err(|e| ApiError::bad_request(format!("Cannot consolidate: {}", e)))?;
+
+    let proved = ProvedTransaction {
+        hex: serialize_hex(&built.tx),
+        txid: built.tx.compute_txid().to_string(),
+    };
+    let inputs_to_sign = utxos
+        .iter()
+        .enumerate()
+        .map(|(index, u)| InputToSign::new(index as u32, &u.address))
+        .collect();
+    let mut unsigned_txs = signing_payloads(state.chain.as_ref(), &state.sessions, vec![proved], inputs_to_sign).await;
+    wallet.apply(&mut unsigned_txs);
+    let unsigned_tx = unsigned_txs.remove(0);
+
+    Ok(Json(ConsolidateResponse {
+        utxos: utxos.iter().map(|u| format!("{}:{}", u.txid, u.vout)).collect(),
+        input_value: built.input_value,
+        output_value: built.output_value,
+        fee: built.fee,
+        fee_rate,
+        vbytes: built.vbytes,
+        unsigned_tx,
+    }))
+}
+
+/// CPFP request
+#[derive(Debug, Deserialize)]
+pub struct CpfpRequest {
+    /// Unconfirmed parent transaction
+    pub txid: Txid,
+    /// Parent output to spend (default: the first paying one of the
+    /// session's wallets, usually the change)
+    #[serde(default)]
+    pub vout: Option<u32>,
+    /// Address receiving the child's output (default: the session's primary wallet)
+    #[serde(default)]
+    pub destination: Option<String>,
+    /// sat/vB parent and child should reach together; defaults to the
+    /// current estimate for `fee_tier`
+    #[serde(default)]
+    pub fee_rate: Option<f64>,
+    /// Tier to estimate the rate for when `fee_rate` is not given (default:
+    /// normal)
+    #[serde(default)]
+    pub fee_tier: Option<FeeTier>,
+}
+
+/// CPFP child transaction ready for signing
+#[derive(Debug, Serialize)]
+pub struct CpfpResponse {
+    pub parent_txid: String,
+    /// What the parent pays on its own
+    pub parent: PaidFee,
+    /// Parent output the child spends
+    pub utxo: String,
+    pub input_value: u64,
+    pub output_value: u64,
+    pub fee: u64,
+    pub vbytes: u64,
+    /// sat/vB of parent and child together
+    pub package_fee_rate: f64,
+    pub unsigned_tx: UnsignedTransaction,
+}
+
+/// Build a child-pays-for-parent transaction for an unconfirmed transaction
+/// that cannot be replaced, e.g. a spell transaction the counterparty has
+/// already signed. The child spends one of the parent's outputs held by the
+/// session's wallets and pays enough for both to reach the fee rate.
+pub async fn build_cpfp(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+    wallet: WalletFormat,
+    Json(req): Json<CpfpRequest>,
+) -> Result<Json<CpfpResponse>, ApiError> {
+    let destination = req.destination.as_deref().unwrap_or(&session.address);
+    let destination = Address::from_str(destination)
+        .ok()
+        .and_then(|a| a.require_network(state.bitcoin.network()).ok())
+        .ok_or_else(|| ApiError::bad_request(format!("Invalid destination address: {}", destination)))?;
+
+    let fee_rate = match req.fee_rate {
+        Some(rate) if rate > 0.0 && rate <= MAX_BUMP_FEE_RATE => rate,
+        Some(_) => {
+            return Err(ApiError::bad_request(format!("fee_rate must be between 0 and {}", MAX_BUMP_FEE_RATE)));
+        }
+        None => {
+            let tier = req.fee_tier.unwrap_or(FeeTier::Normal);
+            state.fees.fee_rate(state.chain.as_ref(), tier).await
+        }
+    };
+
+    let txid = req.txid.to_string();
+    let parent = load_stuck(state.chain.as_ref(), &txid).await?;
+    if parent.paid.fee_rate >= fee_rate {
+        return Err(BumpError::AlreadyPays {
+            txid,
+            paid: parent.paid.fee_rate,
+            target: fee_rate,
+        }
+        .into());
+    }
+
+    let network = state.bitcoin.network();
+    let owner = |out: &TxOut| {
+        Address::from_script(&out.script_pubkey, network)
+            .ok()
+            .map(|address| address.to_string())
+            .filter(|address| session.owns(address))
+    };
+    let vout = match req.vout {
+        Some(vout) => vout,
+        None => parent
+            .tx
+            .output
+            .iter()
+            .position(|out| owner(out).is_some())
+            .ok_or_else(|| ApiError::bad_request("The transaction pays none of this session's wallets"))?
+            as u32,
+    };
+    let output = parent
+        .tx
+        .output
+        .get(vout as usize)
+        .ok_or_else(|| ApiError::bad_request(format!("Transaction has no output {}", vout)))?;
+    let address = owner(output).ok_or_else(|| ApiError::forbidden("Output does not belong to this session's wallets"))?;
+
+    // A plain child would burn charms the output carries
+    if let Ok(Some(spell)) = decode_spell_tx(&parent.tx) {
+        if spell.charms_at(vout).is_some() {
+            return Err(ApiError::bad_request(format!("Output {} carries charms", vout)));
+        }
+    }
+    if state.chain.tx_out(&txid, vout).await?.is_none() {
+        return Err(ApiError::conflict(format!("Output {}:{} is already spent", req.txid, vout)));
+    }
+
+    let child = build_cpfp_child(
+        &parent,
+        vout,
+        output.value.to_sat(),
+        &output.script_pubkey,
+        destination.script_pubkey(),
+        fee_rate,
+    )
+    .map_err(|e| ApiError::bad_request(format!("Cannot build CPFP child: {}", e)))?;
+
+    let proved = ProvedTransaction {
+        hex: serialize_hex(&child.tx),
+        txid: child.tx.compute_txid().to_string(),
+    };
+    let inputs_to_sign = vec![InputToSign::new(0, &address)];
+    let mut unsigned_txs = signing_payloads(state.chain.as_ref(), &state.sessions, vec![proved], inputs_to_sign).await;
+    wallet.apply(&mut unsigned_txs);
+    let unsigned_tx = unsigned_txs.remove(0);
+
+    Ok(Json(CpfpResponse {
+        utxo: format!("{}:{}", req.txid, vout),
+        parent_txid: txid,
+        parent: parent.paid,
+        input_value: child.input_value,
+        output_value: child.output_value,
+        fee: child.fee,
+        vbytes: child.vbytes,
+        package_fee_rate: child.package_fee_rate,
+        unsigned_tx,
+    }))
+}
+
+/// PSBT request: prover transactions in spending order (commit, then spell)
+#[derive(Debug, Deserialize)]
+pub struct PsbtRequest {
+    pub txs: Vec<String>,
+    /// Raw previous transactions, for inputs the node cannot look up
+    #[serde(default)]
+    pub prev_txs: Vec<String>,
+    /// Key origins by input index, for hardware signers
+    #[serde(default)]
+    pub key_origins: HashMap<usize, KeyOrigin>,
+}
+
+/// PSBT response
+#[derive(Debug, Serialize)]
+pub struct PsbtResponse {
+    pub psbts: Vec<BuiltPsbt>,
+}
+
+/// Convert prover transactions into PSBTs with UTXO metadata for wallet signing
+pub async fn build_psbt(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<PsbtRequest>,
+) -> Result<Json<PsbtResponse>, ApiError> {
+    if req.txs.is_empty() {
+        return Err(ApiError::bad_request("At least one transaction is required"));
+    }
+
+    let psbts = build_psbts(state.chain.as_ref(), &req.txs, &req.prev_txs, &req.key_origins, state.chain.network())
+        .await
+        .map_err(|e| ApiError::bad_request(format!("Failed to build PSBT: {:#}", e)))?;
+
+    Ok(Json(PsbtResponse { psbts }))
+}
+
+/// Finalize request: partially signed copies of one transaction, one per
+/// co-signer (e.g. maker and taker of a fill)
+#[derive(Debug, Deserialize)]
+pub struct FinalizePsbtRequest {
+    pub psbts: Vec<String>,
+}
+
+/// Combine co-signers' PSBTs and, once every input is signed, extract the
+/// transaction for `/api/spells/broadcast`
+pub async fn finalize_psbt(Json(req): Json<FinalizePsbtRequest>) -> Result<Json<FinalizedPsbt>, ApiError> {
+    let finalized =
+        finalize_psbts(&req.psbts).map_err(|e| ApiError::bad_request(format!("Failed to finalize PSBT: {:#}", e)))?;
+    Ok(Json(finalized))
+}
+
+/// How long a draft spell holds its funding UTXO before the lock lapses
+const DRAFT_LOCK_SECS: i64 = 30 * 60;
+/// Upper bound on a manual lock
+const MAX_LOCK_SECS: i64 = 24 * 60 * 60;
+
+/// Manual UTXO lock request
+#[derive(Debug, Default, Deserialize)]
+pub struct LockUtxoRequest {
+    /// Lock lifetime in seconds (default 30 minutes, at most 24 hours)
+    pub ttl_secs: Option<i64>,
+}
+
+/// Lock one of the session's UTXOs so drafts cannot claim it
+pub async fn lock_utxo(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+    Path(outpoint): Path<String>,
+    body: Option<Json<LockUtxoRequest>>,
+) -> Result<Json<UtxoLockRecord>, ApiError> {
+    let outpoint = parse_outpoint(&outpoint)?;
+    let ttl = body.and_then(|Json(b)| b.ttl_secs).unwrap_or(DRAFT_LOCK_SECS);
+    if !(1..=MAX_LOCK_SECS).contains(&ttl) {
+        return Err(ApiError::bad_request(format!("ttl_secs must be between 1 and {}", MAX_LOCK_SECS)));
+    }
+
+    let owner = match state.chain.tx_out(&outpoint.txid.to_string(), outpoint.vout).await {
+        Ok(Some(out)) => match out.script_pub_key.address {
+            Some(address) if session.owns(&address) => address,
+            _ => return Err(ApiError::forbidden("UTXO does not belong to this session's wallets")),
         },
-    ])
+        Ok(None) => return Err(ApiError::not_found("UTXO is spent or unknown")),
+        Err(e) => return Err(e.into()),
+    };
+
+    let now = Utc::now();
+    let lock = UtxoLockRecord {
+        outpoint: outpoint.to_string(),
+        owner_address: owner,
+        order_id: None,
+        escrow_id: None,
+        expires_at: now + chrono::Duration::seconds(ttl),
+        created_at: now,
+    };
+    if !db::lock_utxo(&state.db, &lock).await? {
+        return Err(ApiError::conflict("UTXO is already locked"));
+    }
+
+    Ok(Json(lock))
+}
+
+/// Release a lock held by one of the session's wallets
+pub async fn unlock_utxo(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+    Path(outpoint): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    for address in &session.addresses {
+        if db::unlock_utxo(&state.db, &outpoint, address).await? {
+            return Ok(StatusCode::NO_CONTENT);
+        }
+    }
+    Err(ApiError::not_found("No lock on this UTXO for this session"))
+}
+
+/// Active locks held by the session's wallets
+pub async fn list_utxo_locks(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+) -> Result<Json<Vec<UtxoLockRecord>>, ApiError> {
+    let locks = db::get_active_utxo_locks(&state.db)
+        .await?
+        .into_iter()
+        .filter(|lock| session.owns(&lock.owner_address))
+        .collect();
+    Ok(Json(locks))
+}
+
+/// Lock the UTXO funding a draft spell for an order or escrow; a UTXO
+/// already claimed by another draft is a conflict.
+pub(crate) async fn lock_funding_utxo(
+    pool: &db::DbPool,
+    outpoint: &OutPoint,
+    owner: &str,
+    order_id: Option<&str>,
+    escrow_id: Option<&str>,
+) -> Result<(), ApiError> {
+    let now = Utc::now();
+    let lock = UtxoLockRecord {
+        outpoint: outpoint.to_string(),
+        owner_address: owner.to_string(),
+        order_id: order_id.map(str::to_string),
+        escrow_id: escrow_id.map(str::to_string),
+        expires_at: now + chrono::Duration::seconds(DRAFT_LOCK_SECS),
+        created_at: now,
+    };
+    if !db::lock_utxo(pool, &lock).await? {
+        return Err(ApiError::conflict("Funding UTXO is already claimed by another draft"));
+    }
+    Ok(())
+}
+
+pub(crate) fn parse_outpoint(outpoint: &str) -> Result<OutPoint, ApiError> {
+    OutPoint::from_str(outpoint).map_err(|_| ApiError::bad_request("Outpoint must be <txid>:<vout>"))
+}
+
+/// Escrow address derivation query
+#[derive(Debug, Deserialize)]
+pub struct EscrowAddressQuery {
+    pub order_id: String,
+    pub maker_address: String,
+    pub maker_pubkey: Option<String>,
+}
+
+/// Derive the taproot address an order's offer is locked at, with the
+/// internal key and leaf scripts a signer needs to spend it
+pub async fn get_order_escrow_address(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<EscrowAddressQuery>,
+) -> Result<Json<TaprootDerivation>, ApiError> {
+    let derive = || {
+        let maker_key = addresses::resolve_party_key(query.maker_pubkey.as_deref(), &query.maker_address)?;
+        addresses::order_escrow_address(maker_key, &query.order_id, state.bitcoin.network())
+    };
+
+    derive()
+        .map(Json)
+        .map_err(|e| ApiError::bad_request(format!("Cannot derive escrow address: {}", e)))
 }
 
 /// Get new wallet address
@@ -113,3 +1091,29 @@ pub async fn get_address() -> Json<String> {
     Json("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx".to_string())
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::secp256k1::{Keypair, Message, Secp256k1, SecretKey};
+    use crate::services::signatures::message_digest;
+
+    #[test]
+    fn test_pubkey_must_sign_the_challenge() {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[7; 32]).unwrap());
+        let pubkey = hex::encode(keypair.x_only_public_key().0.serialize());
+        let sign = |message: &str| {
+            let signature = secp.sign_schnorr_no_aux_rand(&Message::from_digest(message_digest(message)), &keypair);
+            hex::encode(signature.serialize())
+        };
+        let challenge = "liquid-nation:connect:tb1q:abc";
+
+        assert!(check_pubkey_signature(&pubkey, Some(&sign(challenge)), challenge).is_ok());
+        assert_eq!(check_pubkey_signature(&pubkey, None, challenge).unwrap_err().status, StatusCode::BAD_REQUEST);
+        // A signature over anything else, or by another key, binds nothing
+        let other = check_pubkey_signature(&pubkey, Some(&sign("liquid-nation:connect:tb1q:old")), challenge);
+        assert_eq!(other.unwrap_err().status, StatusCode::UNAUTHORIZED);
+        let stranger = hex::encode([2; 32]);
+        assert!(check_pubkey_signature(&stranger, Some(&sign(challenge)), challenge).is_err());
+    }
+}
@@ -0,0 +1,230 @@
+//! Browser wallet signing payloads
+//!
+//! Signing endpoints accept `?wallet=unisat|xverse`. Each unsigned transaction
+//! that has a PSBT then also carries `wallet_request`, the arguments that
+//! wallet's signing call takes, so the frontend can pass them through as-is:
+//!
+//! - Unisat: `signPsbt(psbtHex, options)`
+//! - Xverse: `signTransaction({ payload })`, on the server's network
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Query},
+    http::request::Parts,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use bitcoin::Network;
+use serde::{Deserialize, Serialize};
+
+use crate::routes::error::ApiError;
+use crate::routes::orders::{InputToSign, UnsignedTransaction};
+
+/// Wallet whose signing call shape the client wants, on the network the
+/// server runs on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WalletFormat {
+    kind: WalletKind,
+    network: Network,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum WalletKind {
+    /// Only the generic payload (hex, inputs to sign, PSBT)
+    #[default]
+    Raw,
+    Unisat,
+    Xverse,
+}
+
+#[derive(Debug, Deserialize)]
+struct WalletFormatQuery {
+    #[serde(default)]
+    wallet: WalletKind,
+}
+
+/// Router state that knows the network the server runs on
+pub trait NetworkState {
+    fn network(&self) -> Network;
+}
+
+#[async_trait]
+impl<S: NetworkState + Send + Sync> FromRequestParts<S> for WalletFormat {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        Query::<WalletFormatQuery>::try_from_uri(&parts.uri)
+            .map(|Query(query)| WalletFormat {
+                kind: query.wallet,
+                network: state.network(),
+            })
+            .map_err(|_| ApiError::bad_request("Unsupported wallet; expected unisat or xverse"))
+    }
+}
+
+impl WalletFormat {
+    /// The generic payload only, as for a request without `?wallet=`
+    #[cfg(test)]
+    pub fn raw(network: Network) -> Self {
+        Self { kind: WalletKind::Raw, network }
+    }
+
+    /// Attach the wallet-specific request to each transaction with a PSBT
+    pub fn apply(self, txs: &mut [UnsignedTransaction]) {
+        for tx in txs {
+            tx.wallet_request = self.request_for(tx);
+        }
+    }
+
+    fn request_for(self, tx: &UnsignedTransaction) -> Option<WalletRequest> {
+        let psbt_base64 = &tx.psbt.as_ref()?.psbt_base64;
+        match self.kind {
+            WalletKind::Raw => None,
+            WalletKind::Unisat => {
+                let psbt_hex = hex::encode(BASE64.decode(psbt_base64).ok()?);
+                Some(WalletRequest::Unisat(unisat_request(psbt_hex, &tx.inputs_to_sign)))
+            }
+            WalletKind::Xverse => Some(WalletRequest::Xverse(xverse_request(
+                psbt_base64.clone(),
+                &tx.inputs_to_sign,
+                self.network,
+            ))),
+        }
+    }
+}
+
+/// Arguments for a browser wallet's signing call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum WalletRequest {
+    Unisat(UnisatSignPsbt),
+    Xverse(XverseSignTransaction),
+}
+
+/// Unisat `signPsbt(psbtHex, options)`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnisatSignPsbt {
+    pub psbt_hex: String,
+    pub options: UnisatSignOptions,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnisatSignOptions {
+    /// Leave finalization to the backend broadcast
+    pub auto_finalized: bool,
+    pub to_sign_inputs: Vec<UnisatInput>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnisatInput {
+    pub index: u32,
+    pub address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sighash_types: Option<Vec<u8>>,
+}
+
+/// Xverse `signTransaction({ payload })`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct XverseSignTransaction {
+    pub payload: XversePayload,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct XversePayload {
+    pub network: XverseNetwork,
+    pub message: String,
+    pub psbt_base64: String,
+    pub broadcast: bool,
+    pub inputs_to_sign: Vec<XverseInputs>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct XverseNetwork {
+    #[serde(rename = "type")]
+    pub network_type: String,
+}
+
+/// Inputs one address signs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct XverseInputs {
+    pub address: String,
+    pub signing_indexes: Vec<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sig_hash: Option<u8>,
+}
+
+fn unisat_request(psbt_hex: String, inputs: &[InputToSign]) -> UnisatSignPsbt {
+    UnisatSignPsbt {
+        psbt_hex,
+        options: UnisatSignOptions {
+            auto_finalized: false,
+            to_sign_inputs: inputs
+                .iter()
+                .map(|input| UnisatInput {
+                    index: input.index,
+                    address: input.address.clone(),
+                    sighash_types: explicit_sighash(&input.sighash_type).map(|s| vec![s]),
+                })
+                .collect(),
+        },
+    }
+}
+
+fn xverse_request(psbt_base64: String, inputs: &[InputToSign], network: Network) -> XverseSignTransaction {
+    let mut grouped: Vec<XverseInputs> = Vec::new();
+    for input in inputs {
+        let sig_hash = explicit_sighash(&input.sighash_type);
+        match grouped.iter_mut().find(|g| g.address == input.address && g.sig_hash == sig_hash) {
+            Some(group) => group.signing_indexes.push(input.index),
+            None => grouped.push(XverseInputs {
+                address: input.address.clone(),
+                signing_indexes: vec![input.index],
+                sig_hash,
+            }),
+        }
+    }
+
+    XverseSignTransaction {
+        payload: XversePayload {
+            network: XverseNetwork {
+                network_type: xverse_network(network).to_string(),
+            },
+            message: "Sign Liquid Nation transaction".to_string(),
+            psbt_base64,
+            broadcast: false,
+            inputs_to_sign: grouped,
+        },
+    }
+}
+
+/// Sighash byte for a named type; `None` for the default, which wallets
+/// apply on their own
+fn explicit_sighash(name: &str) -> Option<u8> {
+    let (base, anyone_can_pay) = match name.strip_suffix("|ANYONECANPAY") {
+        Some(base) => (base, 0x80),
+        None => (name, 0),
+    };
+    let base = match base {
+        "SIGHASH_ALL" => 0x01,
+        "SIGHASH_NONE" => 0x02,
+        "SIGHASH_SINGLE" => 0x03,
+        _ => return None,
+    };
+    Some(base | anyone_can_pay)
+}
+
+fn xverse_network(network: Network) -> &'static str {
+    match network {
+        Network::Bitcoin => "Mainnet",
+        Network::Testnet4 => "Testnet4",
+        Network::Signet => "Signet",
+        Network::Regtest => "Regtest",
+        _ => "Testnet",
+    }
+}
@@ -0,0 +1,314 @@
+//! Watch-only wallet endpoints
+//!
+//! Users register an xpub or public output descriptor; its addresses are
+//! imported into a watch-only node wallet (`WATCH_WALLET_NAME`) so balances and
+//! UTXOs can be served without the node holding any keys.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::db::{self, WatchedWalletRecord};
+use crate::routes::auth::WalletSession;
+use crate::routes::error::ApiError;
+use crate::routes::orders::AppState;
+use crate::routes::wallet::{annotate_utxos, Utxo};
+use crate::services::sessions::Session;
+
+/// Addresses derived per descriptor when no range is given
+const DEFAULT_RANGE: u32 = 100;
+/// Upper bound on the derivation range a user can request
+const MAX_RANGE: u32 = 1000;
+
+/// Register watch-only wallet request; exactly one of `xpub` or `descriptor`
+#[derive(Debug, Deserialize)]
+pub struct WatchWalletRequest {
+    pub xpub: Option<String>,
+    pub descriptor: Option<String>,
+    pub label: Option<String>,
+    /// Last derivation index to track (default 100)
+    pub range: Option<u32>,
+    /// Unix time to rescan the chain from; omit to only track new activity
+    pub birth_time: Option<i64>,
+}
+
+/// Balance of a watch-only wallet
+#[derive(Debug, Serialize)]
+pub struct WatchedWalletBalance {
+    pub wallet_id: String,
+    pub confirmed: u64,
+    pub unconfirmed: u64,
+    pub utxo_count: usize,
+}
+
+/// Register an xpub or descriptor to watch
+pub async fn register_watched_wallet(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+    Json(req): Json<WatchWalletRequest>,
+) -> Result<Json<WatchedWalletRecord>, ApiError> {
+    let raw_descriptors = match (req.xpub.as_deref(), req.descriptor.as_deref()) {
+        (Some(xpub), None) => xpub_descriptors(xpub.trim())?,
+        (None, Some(descriptor)) => vec![descriptor.trim().to_string()],
+        _ => return Err(ApiError::bad_request("Provide exactly one of xpub or descriptor")),
+    };
+
+    let range_end = req.range.unwrap_or(DEFAULT_RANGE);
+    if range_end > MAX_RANGE {
+        return Err(ApiError::bad_request(format!("range must be at most {}", MAX_RANGE)));
+    }
+
+    // Canonicalize through the node, which also rejects private keys
+    let mut descriptors = Vec::with_capacity(raw_descriptors.len());
+    for raw in &raw_descriptors {
+        let info = state
+            .bitcoin
+            .get_descriptor_info(raw)
+            .await
+            .map_err(|e| ApiError::bad_request(format!("Invalid descriptor: {}", e)))?;
+
+        if info.has_private_keys {
+            return Err(ApiError::bad_request("Descriptors must not contain private keys"));
+        }
+        descriptors.push(info.descriptor);
+    }
+
+    let wallet_name = watch_wallet_name();
+    state.bitcoin.ensure_watch_wallet(&wallet_name).await.map_err(node_error)?;
+    state
+        .bitcoin
+        .import_descriptors(&wallet_name, &descriptors, range_end, req.birth_time)
+        .await
+        .map_err(node_error)?;
+
+    let record = WatchedWalletRecord {
+        id: Uuid::new_v4().to_string(),
+        owner_address: session.address,
+        label: req.label,
+        descriptors,
+        range_end: range_end as i32,
+        created_at: chrono::Utc::now(),
+    };
+    db::insert_watched_wallet(&state.db, &record).await?;
+    tracing::info!("Watching wallet {} for {}", record.id, record.owner_address);
+
+    Ok(Json(record))
+}
+
+/// List the watch-only wallets of every wallet linked to the session
+pub async fn list_watched_wallets(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+) -> Result<Json<Vec<WatchedWalletRecord>>, ApiError> {
+    Ok(Json(db::get_watched_wallets(&state.db, &session.addresses).await?))
+}
+
+/// Stop watching a wallet
+pub async fn delete_watched_wallet(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    owned_wallet(&state, &session, &id).await?;
+    db::delete_watched_wallet(&state.db, &id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Addresses derived from a watch-only wallet's descriptors
+pub async fn get_watched_addresses(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<String>>, ApiError> {
+    let wallet = owned_wallet(&state, &session, &id).await?;
+    Ok(Json(derive_wallet_addresses(&state, &wallet).await?))
+}
+
+/// UTXOs held by a watch-only wallet
+pub async fn get_watched_utxos(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<Utxo>>, ApiError> {
+    let wallet = owned_wallet(&state, &session, &id).await?;
+    Ok(Json(watched_utxos(&state, &wallet).await?))
+}
+
+/// Confirmed and unconfirmed balance of a watch-only wallet
+pub async fn get_watched_balance(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+    Path(id): Path<String>,
+) -> Result<Json<WatchedWalletBalance>, ApiError> {
+    let wallet = owned_wallet(&state, &session, &id).await?;
+    let utxos = watched_utxos(&state, &wallet).await?;
+
+    let (confirmed, unconfirmed) = utxos.iter().fold((0, 0), |(c, u), utxo| {
+        if utxo.confirmations > 0 {
+            (c + utxo.value, u)
+        } else {
+            (c, u + utxo.value)
+        }
+    });
+
+    Ok(Json(WatchedWalletBalance {
+        wallet_id: wallet.id,
+        confirmed,
+        unconfirmed,
+        utxo_count: utxos.len(),
+    }))
+}
+
+async fn owned_wallet(state: &AppState, session: &Session, id: &str) -> Result<WatchedWalletRecord, ApiError> {
+    let wallet = db::get_watched_wallet(&state.db, id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Watched wallet not found"))?;
+
+    if !session.owns(&wallet.owner_address) {
+        return Err(ApiError::forbidden("Watched wallet belongs to another session"));
+    }
+    Ok(wallet)
+}
+
+async fn derive_wallet_addresses(state: &AppState, wallet: &WatchedWalletRecord) -> Result<Vec<String>, ApiError> {
+    let mut addresses = Vec::new();
+    for descriptor in &wallet.descriptors {
+        let derived = state
+            .bitcoin
+            .derive_addresses(descriptor, 0, wallet.range_end as u32)
+            .await
+            .map_err(node_error)?;
+        addresses.extend(derived);
+    }
+    Ok(addresses)
+}
+
+async fn watched_utxos(state: &AppState, wallet: &WatchedWalletRecord) -> Result<Vec<Utxo>, ApiError> {
+    let addresses = derive_wallet_addresses(state, wallet).await?;
+    let unspent = state
+        .bitcoin
+        .list_unspent_for(&watch_wallet_name(), &addresses)
+        .await
+        .map_err(node_error)?;
+
+    Ok(annotate_utxos(state, unspent).await?)
+}
+
+/// Receive and change descriptors (native segwit) for a bare extended public
+/// key. SLIP-132 variants (ypub/zpub) are not understood by the node and must
+/// be registered as explicit descriptors instead.
+fn xpub_descriptors(xpub: &str) -> Result<Vec<String>, ApiError> {
+    if !xpub.starts_with("xpub") && !xpub.starts_with("tpub") {
+        return Err(ApiError::bad_request(
+            "Only xpub/tpub keys are accepted; register other key types as a descriptor",
+        ));
+    }
+
+    Ok((0..2).map(|chain| format!("wpkh({}/{}/*)", xpub, chain)).collect())
+}
+
+pub(crate) fn watch_wallet_name() -> String {
+    std::env::var("WATCH_WALLET_NAME").unwrap_or_else(|_| "liquid-nation-watch".to_string())
+}
+
+pub(crate) fn node_error(e: anyhow::Error) -> ApiError {
+    ApiError::new(StatusCode::BAD_GATEWAY, "node_unavailable", e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::routes::orders::testing::test_state;
+
+    const OWNER: &str = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+    const LINKED: &str = "tb1q0ht9tyks4vh7p5p904t340cr9nvahy7u3re7zg";
+    const STRANGER: &str = "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7";
+    const TPUB: &str =
+        "tpubD6NzVbkrYhZ4XgiXtGrdW5XDAPFCL9h7we1vwNCpn8tGbBcgfVYjXyhWo4E1xkh56hjod1RhGjxbaTLV3X4FyWuejifB9jusQ46QzG87VKp";
+
+    fn session(addresses: &[&str]) -> WalletSession {
+        WalletSession(Session {
+            token: "token".to_string(),
+            user_id: "user".to_string(),
+            address: addresses[0].to_string(),
+            addresses: addresses.iter().map(|a| a.to_string()).collect(),
+            pubkeys: vec![],
+            expires_at: chrono::Utc::now() + chrono::Duration::hours(1),
+        })
+    }
+
+    fn request(xpub: Option<&str>, descriptor: Option<&str>, range: Option<u32>) -> Json<WatchWalletRequest> {
+        Json(WatchWalletRequest {
+            xpub: xpub.map(str::to_string),
+            descriptor: descriptor.map(str::to_string),
+            label: None,
+            range,
+            birth_time: None,
+        })
+    }
+
+    #[test]
+    fn test_xpub_descriptors() {
+        let descriptors = xpub_descriptors(TPUB).unwrap();
+        assert_eq!(descriptors, vec![format!("wpkh({}/0/*)", TPUB), format!("wpkh({}/1/*)", TPUB)]);
+        // SLIP-132 keys must come as descriptors
+        assert!(xpub_descriptors(&TPUB.replacen("tpub", "vpub", 1)).is_err());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_watched_wallets_belong_to_their_session() {
+        let db = db::testing::fresh_db().await;
+        let state = Arc::new(test_state(db));
+
+        // Requests are checked before the node is asked anything
+        let invalid = [
+            request(Some(TPUB), Some("wpkh(...)"), None),
+            request(None, None, None),
+            request(Some(TPUB), None, Some(MAX_RANGE + 1)),
+        ];
+        for bad in invalid {
+            let err = register_watched_wallet(State(state.clone()), session(&[OWNER]), bad).await.unwrap_err();
+            assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        }
+
+        let wallet = WatchedWalletRecord {
+            id: Uuid::new_v4().to_string(),
+            owner_address: LINKED.to_string(),
+            label: Some("cold storage".to_string()),
+            descriptors: xpub_descriptors(TPUB).unwrap(),
+            range_end: 100,
+            created_at: chrono::Utc::now(),
+        };
+        db::insert_watched_wallet(&state.db, &wallet).await.unwrap();
+        let id = || Path(wallet.id.clone());
+
+        // Listed for every session the registering wallet is linked to
+        let listed = list_watched_wallets(State(state.clone()), session(&[OWNER, LINKED])).await.unwrap().0;
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].descriptors, wallet.descriptors);
+        assert!(list_watched_wallets(State(state.clone()), session(&[STRANGER])).await.unwrap().0.is_empty());
+
+        let err = get_watched_addresses(State(state.clone()), session(&[STRANGER]), id()).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::FORBIDDEN);
+        let err = delete_watched_wallet(State(state.clone()), session(&[STRANGER]), id()).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::FORBIDDEN);
+        let missing = Path("missing".to_string());
+        let err = get_watched_balance(State(state.clone()), session(&[OWNER]), missing).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::NOT_FOUND);
+
+        // Addresses come from the node, which is down here
+        let err = get_watched_utxos(State(state.clone()), session(&[OWNER, LINKED]), id()).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_GATEWAY);
+
+        let deleted = delete_watched_wallet(State(state.clone()), session(&[OWNER, LINKED]), id()).await.unwrap();
+        assert_eq!(deleted, StatusCode::NO_CONTENT);
+        assert!(db::get_watched_wallet(&state.db, &wallet.id).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,350 @@
+//! Webhook registration
+//!
+//! A signed-in user registers URLs to receive events about their own orders,
+//! escrows and addresses, optionally narrowed to kinds or `prefix.*`
+//! patterns. URLs resolving to loopback, private or link-local addresses are
+//! refused. Deliveries are signed with the webhook's
+//! secret, which is shown only when the webhook is created or the secret is
+//! rotated; see `services::webhooks` for the delivery format.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::db::{self, WebhookDeliveryRecord, WebhookSubscriptionRecord};
+use crate::routes::auth::WalletSession;
+use crate::routes::error::ApiError;
+use crate::routes::orders::AppState;
+use crate::services::sessions::Session;
+use crate::services::webhooks::{check_webhook_url, parse_filters};
+
+/// Webhooks a user may register at once
+const MAX_WEBHOOKS: usize = 20;
+
+/// Delivery attempts listed when no limit is given, and the most allowed
+const DEFAULT_DELIVERIES_LIMIT: u32 = 50;
+const MAX_DELIVERIES_LIMIT: u32 = 500;
+
+/// Register webhook request
+#[derive(Debug, Deserialize)]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    /// Event kinds or `prefix.*` patterns; empty for every event
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+/// Update webhook request; omitted fields are left as they are
+#[derive(Debug, Deserialize)]
+pub struct UpdateWebhookRequest {
+    pub url: Option<String>,
+    pub events: Option<Vec<String>>,
+    pub active: Option<bool>,
+    /// Replace the signing secret and return the new one
+    #[serde(default)]
+    pub rotate_secret: bool,
+}
+
+/// Delivery log query
+#[derive(Debug, Deserialize)]
+pub struct DeliveriesQuery {
+    pub limit: Option<u32>,
+}
+
+/// Registered webhook
+#[derive(Debug, Serialize)]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    pub events: Vec<String>,
+    pub active: bool,
+    /// Signing secret, only returned on creation and rotation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl Webhook {
+    fn from_record(record: WebhookSubscriptionRecord, show_secret: bool) -> Self {
+        Self {
+            events: parse_filters(&record.event_filters),
+            secret: show_secret.then_some(record.secret),
+            id: record.id,
+            url: record.url,
+            active: record.active,
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+        }
+    }
+}
+
+/// Register a webhook for the session's user
+pub async fn create_webhook(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+    Json(req): Json<CreateWebhookRequest>,
+) -> Result<Json<Webhook>, ApiError> {
+    let url = validate_url(&req.url).await?;
+    let events = validate_filters(req.events)?;
+
+    let existing = db::get_user_webhook_subscriptions(&state.db, &session.user_id).await?;
+    if existing.len() >= MAX_WEBHOOKS {
+        return Err(ApiError::conflict(format!("At most {} webhooks per user", MAX_WEBHOOKS)));
+    }
+
+    let now = chrono::Utc::now();
+    let subscription = WebhookSubscriptionRecord {
+        id: Uuid::new_v4().to_string(),
+        user_id: session.user_id,
+        url,
+        secret: new_secret(),
+        event_filters: serde_json::to_string(&events).map_err(anyhow::Error::from)?,
+        active: true,
+        created_at: now,
+        updated_at: now,
+    };
+    db::insert_webhook_subscription(&state.db, &subscription).await?;
+
+    tracing::info!("Webhook {} registered for {}", subscription.id, subscription.url);
+    Ok(Json(Webhook::from_record(subscription, true)))
+}
+
+/// List the session user's webhooks
+pub async fn list_webhooks(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+) -> Result<Json<Vec<Webhook>>, ApiError> {
+    let subscriptions = db::get_user_webhook_subscriptions(&state.db, &session.user_id).await?;
+    Ok(Json(
+        subscriptions
+            .into_iter()
+            .map(|s| Webhook::from_record(s, false))
+            .collect(),
+    ))
+}
+
+/// Get one of the session user's webhooks
+pub async fn get_webhook(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+    Path(id): Path<String>,
+) -> Result<Json<Webhook>, ApiError> {
+    let subscription = owned_webhook(&state, &session, &id).await?;
+    Ok(Json(Webhook::from_record(subscription, false)))
+}
+
+/// Change a webhook's URL, filters or active flag, or rotate its secret
+pub async fn update_webhook(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+    Path(id): Path<String>,
+    Json(req): Json<UpdateWebhookRequest>,
+) -> Result<Json<Webhook>, ApiError> {
+    let mut subscription = owned_webhook(&state, &session, &id).await?;
+
+    if let Some(url) = &req.url {
+        subscription.url = validate_url(url).await?;
+    }
+    if let Some(events) = req.events {
+        let events = validate_filters(events)?;
+        subscription.event_filters = serde_json::to_string(&events).map_err(anyhow::Error::from)?;
+    }
+    if let Some(active) = req.active {
+        subscription.active = active;
+    }
+    if req.rotate_secret {
+        subscription.secret = new_secret();
+    }
+
+    if !db::update_webhook_subscription(&state.db, &subscription).await? {
+        return Err(ApiError::not_found("Webhook not found"));
+    }
+    subscription.updated_at = chrono::Utc::now();
+    Ok(Json(Webhook::from_record(subscription, req.rotate_secret)))
+}
+
+/// Remove a webhook and its delivery log
+pub async fn delete_webhook(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    if !db::delete_webhook_subscription(&state.db, &id, &session.user_id).await? {
+        return Err(ApiError::not_found("Webhook not found"));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Recent delivery attempts to a webhook, newest first
+pub async fn list_webhook_deliveries(
+    State(state): State<Arc<AppState>>,
+    WalletSession(session): WalletSession,
+    Path(id): Path<String>,
+    Query(query): Query<DeliveriesQuery>,
+) -> Result<Json<Vec<WebhookDeliveryRecord>>, ApiError> {
+    owned_webhook(&state, &session, &id).await?;
+    let limit = query.limit.unwrap_or(DEFAULT_DELIVERIES_LIMIT).min(MAX_DELIVERIES_LIMIT);
+    Ok(Json(db::get_webhook_deliveries(&state.db, &id, limit as i64).await?))
+}
+
+async fn owned_webhook(state: &AppState, session: &Session, id: &str) -> Result<WebhookSubscriptionRecord, ApiError> {
+    db::get_webhook_subscription(&state.db, id)
+        .await?
+        .filter(|s| s.user_id == session.user_id)
+        .ok_or_else(|| ApiError::not_found("Webhook not found"))
+}
+
+/// An http(s) URL whose host resolves only to public addresses
+async fn validate_url(url: &str) -> Result<String, ApiError> {
+    let url = url.trim();
+    if let Err(e) = check_webhook_url(url).await {
+        return Err(ApiError::bad_request(format!("Invalid webhook url: {:#}", e)));
+    }
+    Ok(url.to_string())
+}
+
+fn validate_filters(events: Vec<String>) -> Result<Vec<String>, ApiError> {
+    let events: Vec<String> = events.into_iter().map(|e| e.trim().to_string()).collect();
+    if let Some(bad) = events.iter().find(|e| e.is_empty() || e.starts_with('.') || e.contains(char::is_whitespace)) {
+        return Err(ApiError::bad_request(format!("Invalid event filter: {:?}", bad)));
+    }
+    Ok(events)
+}
+
+/// 256-bit random signing secret
+fn new_secret() -> String {
+    format!("whsec_{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::routes::orders::testing::test_state;
+
+    const OWNER: &str = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+    const STRANGER: &str = "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7";
+
+    async fn session(state: &AppState, address: &str) -> WalletSession {
+        let user = db::upsert_user(&state.db, address, None).await.unwrap();
+        WalletSession(Session {
+            token: "token".to_string(),
+            user_id: user.id,
+            address: address.to_string(),
+            addresses: vec![address.to_string()],
+            pubkeys: vec![],
+            expires_at: chrono::Utc::now() + chrono::Duration::hours(1),
+        })
+    }
+
+    fn create_request(url: &str, events: &[&str]) -> Json<CreateWebhookRequest> {
+        Json(CreateWebhookRequest {
+            url: url.to_string(),
+            events: events.iter().map(|e| e.to_string()).collect(),
+        })
+    }
+
+    fn update_request() -> UpdateWebhookRequest {
+        UpdateWebhookRequest { url: None, events: None, active: None, rotate_secret: false }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_webhooks_belong_to_their_user() {
+        let db = db::testing::fresh_db().await;
+        let state = Arc::new(test_state(db));
+        let owner = || session(&state, OWNER);
+        let stranger = || session(&state, STRANGER);
+
+        // Internal URLs and malformed filters are refused
+        let request = create_request("http://10.0.0.5/hook", &[]);
+        let err = create_webhook(State(state.clone()), owner().await, request).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        let request = create_request("https://93.184.216.34/hook", &[" "]);
+        let err = create_webhook(State(state.clone()), owner().await, request).await.unwrap_err();
+        assert_eq!(err.message, "Invalid event filter: \"\"");
+
+        // The secret is shown once, on creation
+        let request = create_request(" https://93.184.216.34/hook ", &["order.*"]);
+        let created = create_webhook(State(state.clone()), owner().await, request).await.unwrap().0;
+        assert_eq!(created.url, "https://93.184.216.34/hook");
+        assert_eq!(created.events, vec!["order.*"]);
+        let secret = created.secret.clone().unwrap();
+        assert!(secret.starts_with("whsec_"));
+        let listed = list_webhooks(State(state.clone()), owner().await).await.unwrap().0;
+        assert_eq!(listed.len(), 1);
+        assert!(listed[0].secret.is_none());
+
+        // Another user can neither see nor change it
+        assert!(list_webhooks(State(state.clone()), stranger().await).await.unwrap().0.is_empty());
+        let id = || Path(created.id.clone());
+        let err = get_webhook(State(state.clone()), stranger().await, id()).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::NOT_FOUND);
+        let request = Json(update_request());
+        let err = update_webhook(State(state.clone()), stranger().await, id(), request).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::NOT_FOUND);
+        let query = || Query(DeliveriesQuery { limit: None });
+        let err = list_webhook_deliveries(State(state.clone()), stranger().await, id(), query()).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::NOT_FOUND);
+        let err = delete_webhook(State(state.clone()), stranger().await, id()).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::NOT_FOUND);
+
+        // Its owner pauses it and rotates the secret; a new URL is checked too
+        let err = update_webhook(
+            State(state.clone()),
+            owner().await,
+            id(),
+            Json(UpdateWebhookRequest { url: Some("http://169.254.169.254/".to_string()), ..update_request() }),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        let rotated = update_webhook(
+            State(state.clone()),
+            owner().await,
+            id(),
+            Json(UpdateWebhookRequest { active: Some(false), rotate_secret: true, ..update_request() }),
+        )
+        .await
+        .unwrap()
+        .0;
+        assert!(!rotated.active);
+        assert_ne!(rotated.secret.unwrap(), secret);
+        let fetched = get_webhook(State(state.clone()), owner().await, id()).await.unwrap().0;
+        assert_eq!(fetched.url, created.url);
+        assert!(!fetched.active);
+        let deliveries = list_webhook_deliveries(State(state.clone()), owner().await, id(), query()).await.unwrap();
+        assert!(deliveries.0.is_empty());
+
+        assert_eq!(delete_webhook(State(state.clone()), owner().await, id()).await.unwrap(), StatusCode::NO_CONTENT);
+        let err = get_webhook(State(state.clone()), owner().await, id()).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_webhooks_per_user_are_capped() {
+        let db = db::testing::fresh_db().await;
+        let state = Arc::new(test_state(db));
+
+        for _ in 0..MAX_WEBHOOKS {
+            let request = create_request("https://93.184.216.34/hook", &[]);
+            let created = create_webhook(State(state.clone()), session(&state, OWNER).await, request).await.unwrap();
+            assert!(created.active);
+        }
+        let request = create_request("https://93.184.216.34/hook", &[]);
+        let err = create_webhook(State(state.clone()), session(&state, OWNER).await, request).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::CONFLICT);
+
+        // The cap is per user
+        let request = create_request("https://93.184.216.34/hook", &[]);
+        let created = create_webhook(State(state.clone()), session(&state, STRANGER).await, request).await.unwrap();
+        assert!(created.active);
+    }
+}
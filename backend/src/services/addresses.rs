@@ -0,0 +1,182 @@
+//! Taproot address derivation for order and escrow outputs
+//!
+//! Each address commits to the order or escrow id in a script leaf, so every
+//! order gets its own deterministic address that only the intended keys can
+//! spend:
+//!
+//! - Orders: internal key is the maker's key (key-path spend with the tweak),
+//!   plus a leaf `<H(id)> OP_DROP <maker> OP_CHECKSIG`.
+//!
+//! Escrow outputs carry their release and timeout rules in the script tree
+//! itself; see `escrow_script`.
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+use bitcoin::opcodes::all::{OP_CHECKSIG, OP_DROP};
+use bitcoin::script::Builder;
+use bitcoin::secp256k1::{PublicKey, Secp256k1, XOnlyPublicKey};
+use bitcoin::taproot::TaprootBuilder;
+use bitcoin::{Address, CompressedPublicKey, Network, ScriptBuf};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// BIP-341 "nothing up my sleeve" x-only key with no known private key
+pub(crate) const NUMS_INTERNAL_KEY: &str = "50929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0";
+
+/// A derived taproot address with what a signer needs to spend it
+#[derive(Debug, Clone, Serialize)]
+pub struct TaprootDerivation {
+    pub address: String,
+    pub internal_key: String,
+    pub merkle_root: String,
+    /// Leaf scripts (hex), in the order the keys were given
+    pub leaf_scripts: Vec<String>,
+}
+
+/// Parse a hex public key, compressed (33 bytes) or x-only (32 bytes)
+pub fn parse_xonly_key(pubkey_hex: &str) -> Result<XOnlyPublicKey> {
+    let bytes = hex::decode(pubkey_hex.trim()).context("Public key is not hex")?;
+    match bytes.len() {
+        32 => Ok(XOnlyPublicKey::from_slice(&bytes)?),
+        33 => Ok(PublicKey::from_slice(&bytes)?.x_only_public_key().0),
+        n => bail!("Public key must be 32 or 33 bytes, got {}", n),
+    }
+}
+
+/// Key a party signs with: the given pubkey, or else the output key of their
+/// taproot address
+pub fn resolve_party_key(pubkey: Option<&str>, address: &str) -> Result<XOnlyPublicKey> {
+    if let Some(key) = pubkey.and_then(|p| parse_xonly_key(p).ok()) {
+        return Ok(key);
+    }
+
+    let script = Address::from_str(address)
+        .map(|a| a.assume_checked().script_pubkey())
+        .map_err(|_| anyhow!("A public key is required for non-taproot address {}", address))?;
+    if !script.is_p2tr() {
+        bail!("A public key is required for non-taproot address {}", address);
+    }
+    Ok(XOnlyPublicKey::from_slice(&script.as_bytes()[2..])?)
+}
+
+/// Whether `address` pays to `pubkey_hex`: the P2WPKH address of the
+/// compressed key, or a taproot address whose output key is the key itself
+/// or the key tweaked with no script tree (BIP-86)
+pub fn key_controls_address(pubkey_hex: &str, address: &str) -> bool {
+    let Ok(script) = Address::from_str(address).map(|a| a.assume_checked().script_pubkey()) else {
+        return false;
+    };
+
+    if script.is_p2wpkh() {
+        return hex::decode(pubkey_hex.trim())
+            .ok()
+            .and_then(|bytes| CompressedPublicKey::from_slice(&bytes).ok())
+            .is_some_and(|key| ScriptBuf::new_p2wpkh(&key.wpubkey_hash()) == script);
+    }
+    if script.is_p2tr() {
+        let Ok(key) = parse_xonly_key(pubkey_hex) else {
+            return false;
+        };
+        let secp = Secp256k1::verification_only();
+        return script.as_bytes()[2..] == key.serialize() || ScriptBuf::new_p2tr(&secp, key, None) == script;
+    }
+    false
+}
+
+/// Address for an order's escrowed offer, spendable by the maker
+pub fn order_escrow_address(maker: XOnlyPublicKey, order_id: &str, network: Network) -> Result<TaprootDerivation> {
+    let leaf = commitment_leaf("order", order_id, maker);
+    derive(maker, vec![leaf], network)
+}
+
+/// `sha256("liquid-nation:<kind>:<id>")`, committing a leaf to one order or
+/// escrow
+pub(crate) fn commitment(kind: &str, id: &str) -> [u8; 32] {
+    Sha256::digest(format!("liquid-nation:{}:{}", kind, id)).into()
+}
+
+/// `<sha256("liquid-nation:<kind>:<id>")> OP_DROP <key> OP_CHECKSIG`
+fn commitment_leaf(kind: &str, id: &str, key: XOnlyPublicKey) -> ScriptBuf {
+    Builder::new()
+        .push_slice(commitment(kind, id))
+        .push_opcode(OP_DROP)
+        .push_x_only_key(&key)
+        .push_opcode(OP_CHECKSIG)
+        .into_script()
+}
+
+fn derive(internal: XOnlyPublicKey, leaves: Vec<ScriptBuf>, network: Network) -> Result<TaprootDerivation> {
+    let secp = Secp256k1::verification_only();
+    let spend_info = TaprootBuilder::with_huffman_tree(leaves.iter().map(|leaf| (1, leaf.clone())))?
+        .finalize(&secp, internal)
+        .map_err(|_| anyhow!("Incomplete taproot tree"))?;
+
+    let merkle_root = spend_info
+        .merkle_root()
+        .context("Taproot tree has no leaves")?;
+
+    Ok(TaprootDerivation {
+        address: Address::p2tr_tweaked(spend_info.output_key(), network).to_string(),
+        internal_key: internal.to_string(),
+        merkle_root: merkle_root.to_string(),
+        leaf_scripts: leaves.iter().map(|leaf| leaf.to_hex_string()).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::secp256k1::SecretKey;
+
+    fn key(byte: u8) -> XOnlyPublicKey {
+        let secp = Secp256k1::new();
+        let secret = SecretKey::from_slice(&[byte; 32]).unwrap();
+        PublicKey::from_secret_key(&secp, &secret).x_only_public_key().0
+    }
+
+    #[test]
+    fn test_order_escrow_address_is_deterministic() {
+        let a = order_escrow_address(key(1), "order-1", Network::Testnet4).unwrap();
+        let b = order_escrow_address(key(1), "order-1", Network::Testnet4).unwrap();
+        let other = order_escrow_address(key(1), "order-2", Network::Testnet4).unwrap();
+
+        assert_eq!(a.address, b.address);
+        assert_ne!(a.address, other.address);
+        assert!(a.address.starts_with("tb1p"));
+        assert_eq!(a.internal_key, key(1).to_string());
+    }
+
+    #[test]
+    fn test_party_keys() {
+        // Compressed and x-only encodings resolve to the same key
+        let secp = Secp256k1::new();
+        let compressed = PublicKey::from_secret_key(&secp, &SecretKey::from_slice(&[1; 32]).unwrap());
+        let parsed = parse_xonly_key(&hex::encode(compressed.serialize())).unwrap();
+        assert_eq!(parsed, key(1));
+        assert_eq!(parse_xonly_key(&key(1).to_string()).unwrap(), key(1));
+
+        // Taproot addresses stand in for a missing key; others are rejected
+        let p2tr = order_escrow_address(key(1), "x", Network::Testnet4).unwrap().address;
+        assert!(resolve_party_key(None, &p2tr).is_ok());
+        assert!(resolve_party_key(None, "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx").is_err());
+    }
+
+    #[test]
+    fn test_key_controls_address() {
+        let secp = Secp256k1::new();
+        let compressed = CompressedPublicKey(PublicKey::from_secret_key(&secp, &SecretKey::from_slice(&[1; 32]).unwrap()));
+        let compressed_hex = hex::encode(compressed.to_bytes());
+
+        let p2wpkh = Address::p2wpkh(&compressed, Network::Testnet4).to_string();
+        assert!(key_controls_address(&compressed_hex, &p2wpkh));
+        let bip86 = Address::p2tr(&secp, key(1), None, Network::Testnet4).to_string();
+        assert!(key_controls_address(&compressed_hex, &bip86));
+        assert!(key_controls_address(&key(1).to_string(), &bip86));
+
+        // Another key's addresses, and P2WPKH for an x-only key, do not match
+        assert!(!key_controls_address(&key(2).to_string(), &bip86));
+        assert!(!key_controls_address(&key(1).to_string(), &p2wpkh));
+        assert!(!key_controls_address(&compressed_hex, "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx"));
+    }
+}
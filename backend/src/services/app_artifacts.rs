@@ -0,0 +1,410 @@
+//! Swap and escrow app builds
+//!
+//! Builds each app's WASM with `charms app build`, derives its verification key
+//! with `charms app vk`, and caches both under `APP_CACHE_DIR` keyed by a hash
+//! of the app's sources and the charms version, so a restart only rebuilds
+//! apps that changed. Apps are found under `APPS_DIR` (default `../apps`).
+//!
+//! When an app cannot be built (mock mode, `APP_BUILD=false`, or a failing
+//! build) its published verification key is used and no binary is sent to
+//! the prover. Spell drafts that hand the app binary to the client fail
+//! instead when the prover needs one.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tokio::process::Command;
+use tokio::sync::Mutex;
+
+use crate::services::prover::ProverBackend;
+use crate::services::spell_check::Contract;
+use crate::services::spell_schema::Spell;
+
+/// Verification key of the published swap app build
+pub const PUBLISHED_SWAP_VK: &str = "857ee181813511526321296bb0183b7496e1cdc0801552495464e9ec44c37718";
+/// Verification key of the published escrow app build
+pub const PUBLISHED_ESCROW_VK: &str = "857ee181813511526321296bb0183b7496e1cdc0801552495464e9ec44c37718";
+
+/// Where an app's verification key (and binary) came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtifactSource {
+    /// Built earlier from the same sources and read from the cache
+    Cache,
+    /// Built on this resolution
+    Build,
+    /// Not built; the published verification key without a binary
+    Published,
+}
+
+/// A resolved app: its verification key and, when built, its WASM
+#[derive(Debug, Clone, Serialize)]
+pub struct AppArtifact {
+    pub contract: Contract,
+    pub vk: String,
+    pub source: ArtifactSource,
+    /// Hash of the app sources and charms version the binary was built from
+    pub source_hash: Option<String>,
+    pub binary_size: Option<usize>,
+    /// Why the app could not be built
+    pub error: Option<String>,
+    #[serde(skip)]
+    pub binary: Option<Arc<Vec<u8>>>,
+}
+
+impl AppArtifact {
+    fn published(contract: Contract, error: Option<String>) -> Self {
+        let vk = match contract {
+            Contract::Swap => PUBLISHED_SWAP_VK,
+            Contract::Escrow => PUBLISHED_ESCROW_VK,
+        };
+        Self {
+            contract,
+            vk: vk.to_string(),
+            source: ArtifactSource::Published,
+            source_hash: None,
+            binary_size: None,
+            error,
+            binary: None,
+        }
+    }
+}
+
+/// Builds, caches and serves the app artifacts
+pub struct AppArtifacts {
+    charms_bin: String,
+    apps_dir: PathBuf,
+    cache_dir: PathBuf,
+    build_enabled: bool,
+    /// Whether the prover checks spells against app binaries
+    needs_binaries: bool,
+    build_timeout: Duration,
+    /// Resolved artifacts; held across a build so each app builds once
+    artifacts: Mutex<HashMap<Contract, Arc<AppArtifact>>>,
+}
+
+impl AppArtifacts {
+    /// Configure from the environment; nothing is built for a prover that
+    /// takes no app binaries
+    pub fn from_env(prover: &dyn ProverBackend) -> Self {
+        Self {
+            charms_bin: std::env::var("CHARMS_BIN").unwrap_or_else(|_| "charms".to_string()),
+            apps_dir: std::env::var("APPS_DIR").unwrap_or_else(|_| "../apps".to_string()).into(),
+            cache_dir: std::env::var("APP_CACHE_DIR")
+                .unwrap_or_else(|_| "./target/app-artifacts".to_string())
+                .into(),
+            needs_binaries: prover.proves_spells(),
+            build_enabled: prover.proves_spells() && std::env::var("APP_BUILD").map(|v| v != "false").unwrap_or(true),
+            build_timeout: Duration::from_secs(
+                std::env::var("APP_BUILD_TIMEOUT_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(900),
+            ),
+            artifacts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The app's artifact, building it on first use
+    pub async fn artifact(&self, contract: Contract) -> Arc<AppArtifact> {
+        let mut artifacts = self.artifacts.lock().await;
+        if let Some(artifact) = artifacts.get(&contract) {
+            return artifact.clone();
+        }
+
+        let artifact = Arc::new(self.resolve(contract).await);
+        artifacts.insert(contract, artifact.clone());
+        artifact
+    }
+
+    /// Resolve the app again, rebuilding it if its sources changed
+    pub async fn refresh(&self, contract: Contract) -> Arc<AppArtifact> {
+        let mut artifacts = self.artifacts.lock().await;
+        let artifact = Arc::new(self.resolve(contract).await);
+        artifacts.insert(contract, artifact.clone());
+        artifact
+    }
+
+    /// Every app's artifact, building any not yet resolved
+    pub async fn all(&self) -> Vec<Arc<AppArtifact>> {
+        vec![self.artifact(Contract::Swap).await, self.artifact(Contract::Escrow).await]
+    }
+
+    /// Verification key spells should reference for the app
+    pub async fn vk(&self, contract: Contract) -> String {
+        self.artifact(contract).await.vk.clone()
+    }
+
+    /// Binaries of the apps a spell references, keyed by verification key.
+    /// Apps that were not built (or are not ours) are left out.
+    pub async fn binaries_for_spell(&self, spell_yaml: &str) -> BTreeMap<String, Vec<u8>> {
+        let Ok(spell) = Spell::parse(spell_yaml) else {
+            return BTreeMap::new();
+        };
+        let vks: HashSet<&str> = spell.app_vks().into_values().collect();
+
+        let mut binaries = BTreeMap::new();
+        for artifact in self.all().await {
+            if let Some(binary) = artifact.binary.as_ref().filter(|_| vks.contains(artifact.vk.as_str())) {
+                binaries.insert(artifact.vk.clone(), Vec::clone(binary));
+            }
+        }
+        binaries
+    }
+
+    /// Base64 WASM of the app of ours a spell runs, for clients proving it
+    /// themselves. Empty when the spell runs none of our apps or the prover
+    /// takes no binaries; an error when the app was not built.
+    pub async fn app_binary(&self, spell_yaml: &str) -> Result<String> {
+        let spell = Spell::parse(spell_yaml)?;
+        let vks: HashSet<&str> = spell.app_vks().into_values().collect();
+
+        for artifact in self.all().await {
+            if !vks.contains(artifact.vk.as_str()) {
+                continue;
+            }
+            match &artifact.binary {
+                Some(binary) => return Ok(BASE64.encode(binary.as_slice())),
+                None if self.needs_binaries => bail!(
+                    "the {:?} app binary is not available: {}",
+                    artifact.contract,
+                    artifact.error.as_deref().unwrap_or("app builds are turned off")
+                ),
+                None => {}
+            }
+        }
+        Ok(String::new())
+    }
+
+    async fn resolve(&self, contract: Contract) -> AppArtifact {
+        if !self.build_enabled {
+            return AppArtifact::published(contract, None);
+        }
+
+        match self.build(contract).await {
+            Ok(artifact) => {
+                tracing::info!(
+                    "{:?} app ready from {:?}: vk {} ({} bytes)",
+                    contract,
+                    artifact.source,
+                    artifact.vk,
+                    artifact.binary_size.unwrap_or(0)
+                );
+                artifact
+            }
+            Err(e) => {
+                tracing::warn!("Failed to build the {:?} app, using its published VK: {:#}", contract, e);
+                AppArtifact::published(contract, Some(format!("{:#}", e)))
+            }
+        }
+    }
+
+    async fn build(&self, contract: Contract) -> Result<AppArtifact> {
+        let name = app_dir_name(contract);
+        let app_dir = self.apps_dir.join(name);
+        let version = self.charms(None, ["--version"]).await?;
+
+        let hash = {
+            let app_dir = app_dir.clone();
+            tokio::task::spawn_blocking(move || source_hash(&app_dir, &version)).await??
+        };
+        let wasm_path = self.cache_dir.join(format!("{}-{}.wasm", name, hash));
+        let vk_path = self.cache_dir.join(format!("{}-{}.vk", name, hash));
+
+        let cached = match (tokio::fs::read(&wasm_path).await, tokio::fs::read_to_string(&vk_path).await) {
+            (Ok(binary), Ok(vk)) => Some((binary, vk.trim().to_string())),
+            _ => None,
+        };
+        let (binary, vk, source) = match cached {
+            Some((binary, vk)) => (binary, vk, ArtifactSource::Cache),
+            None => {
+                let (binary, vk) = self.build_uncached(&app_dir).await?;
+                tokio::fs::create_dir_all(&self.cache_dir)
+                    .await
+                    .context("Failed to create the app cache directory")?;
+                tokio::fs::write(&wasm_path, &binary).await?;
+                tokio::fs::write(&vk_path, &vk).await?;
+                (binary, vk, ArtifactSource::Build)
+            }
+        };
+
+        Ok(AppArtifact {
+            contract,
+            vk,
+            source,
+            source_hash: Some(hash),
+            binary_size: Some(binary.len()),
+            error: None,
+            binary: Some(Arc::new(binary)),
+        })
+    }
+
+    /// Run `charms app build` and `charms app vk` in the app's directory
+    async fn build_uncached(&self, app_dir: &Path) -> Result<(Vec<u8>, String)> {
+        tracing::info!("Building app in {}", app_dir.display());
+        let output = self.charms(Some(app_dir), ["app", "build"]).await?;
+        let wasm = output
+            .lines()
+            .map(str::trim)
+            .rfind(|line| !line.is_empty())
+            .ok_or_else(|| anyhow!("charms app build did not print the WASM path"))?;
+        let wasm_path = app_dir.join(wasm);
+
+        let vk = self
+            .charms(Some(app_dir), [OsStr::new("app"), OsStr::new("vk"), wasm_path.as_os_str()])
+            .await?;
+        if vk.len() != 64 || !vk.chars().all(|c| c.is_ascii_hexdigit()) {
+            bail!("charms app vk returned {:?}, not a 32-byte hex key", vk);
+        }
+
+        let binary = tokio::fs::read(&wasm_path)
+            .await
+            .with_context(|| format!("Failed to read built WASM {}", wasm_path.display()))?;
+        Ok((binary, vk))
+    }
+
+    /// Run the charms CLI, returning its trimmed stdout
+    async fn charms<I, S>(&self, dir: Option<&Path>, args: I) -> Result<String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let mut command = Command::new(&self.charms_bin);
+        command.args(args).kill_on_drop(true);
+        if let Some(dir) = dir {
+            command.current_dir(dir);
+        }
+
+        let output = tokio::time::timeout(self.build_timeout, command.output())
+            .await
+            .map_err(|_| anyhow!("{} timed out after {:?}", self.charms_bin, self.build_timeout))?
+            .with_context(|| format!("Failed to run {}", self.charms_bin))?;
+        if !output.status.success() {
+            bail!(
+                "{} exited with {}: {}",
+                self.charms_bin,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+fn app_dir_name(contract: Contract) -> &'static str {
+    match contract {
+        Contract::Swap => "swap-app",
+        Contract::Escrow => "escrow-app",
+    }
+}
+
+/// Hex SHA-256 over `salt` and the app's manifest, lock file and `src/` tree
+fn source_hash(app_dir: &Path, salt: &str) -> Result<String> {
+    let mut files = Vec::new();
+    for name in ["Cargo.toml", "Cargo.lock"] {
+        let path = app_dir.join(name);
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+    collect_files(&app_dir.join("src"), &mut files)
+        .with_context(|| format!("Failed to read app sources in {}", app_dir.display()))?;
+    files.sort();
+
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    for path in files {
+        let contents = std::fs::read(&path)?;
+        let relative = path.strip_prefix(app_dir).unwrap_or(&path);
+        hasher.update(relative.to_string_lossy().as_bytes());
+        hasher.update((contents.len() as u64).to_le_bytes());
+        hasher.update(&contents);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_hash_tracks_sources_and_charms_version() {
+        let dir = std::env::temp_dir().join(format!("app-hash-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("src/bin")).unwrap();
+        std::fs::write(dir.join("Cargo.toml"), "[package]\nname = \"app\"\n").unwrap();
+        std::fs::write(dir.join("src/lib.rs"), "pub fn a() {}\n").unwrap();
+        std::fs::write(dir.join("src/bin/main.rs"), "fn main() {}\n").unwrap();
+
+        let hash = source_hash(&dir, "charms 0.10.0").unwrap();
+        assert_eq!(hash, source_hash(&dir, "charms 0.10.0").unwrap());
+        assert_ne!(hash, source_hash(&dir, "charms 0.10.1").unwrap());
+
+        // Build output does not count, sources do
+        std::fs::create_dir_all(dir.join("target")).unwrap();
+        std::fs::write(dir.join("target/app.wasm"), "wasm").unwrap();
+        assert_eq!(hash, source_hash(&dir, "charms 0.10.0").unwrap());
+        std::fs::write(dir.join("src/bin/main.rs"), "fn main() { }\n").unwrap();
+        assert_ne!(hash, source_hash(&dir, "charms 0.10.0").unwrap());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_app_binary_is_required_for_real_proofs() {
+        let built = AppArtifact {
+            contract: Contract::Swap,
+            vk: "aa".repeat(32),
+            source: ArtifactSource::Build,
+            source_hash: None,
+            binary_size: Some(4),
+            error: None,
+            binary: Some(Arc::new(b"wasm".to_vec())),
+        };
+        let unbuilt = AppArtifact {
+            vk: "bb".repeat(32),
+            ..AppArtifact::published(Contract::Escrow, Some("charms not found".to_string()))
+        };
+        let apps = |needs_binaries| AppArtifacts {
+            charms_bin: "charms".to_string(),
+            apps_dir: PathBuf::new(),
+            cache_dir: PathBuf::new(),
+            build_enabled: false,
+            needs_binaries,
+            build_timeout: Duration::from_secs(1),
+            artifacts: Mutex::new(HashMap::from([
+                (Contract::Swap, Arc::new(built.clone())),
+                (Contract::Escrow, Arc::new(unbuilt.clone())),
+            ])),
+        };
+        let spell = |vk: &str| format!("version: 8\napps:\n  $APP: n/{}/{}\nins: []\nouts: []\n", "cc".repeat(32), vk);
+
+        let prover = apps(true);
+        assert_eq!(prover.app_binary(&spell(&built.vk)).await.unwrap(), BASE64.encode(b"wasm"));
+        let err = prover.app_binary(&spell(&unbuilt.vk)).await.unwrap_err();
+        assert!(err.to_string().contains("charms not found"), "{}", err);
+        // Spells running none of our apps need no binary of ours
+        assert_eq!(prover.app_binary(&spell(&"dd".repeat(32))).await.unwrap(), "");
+
+        // A mock prover takes no binaries
+        assert_eq!(apps(false).app_binary(&spell(&unbuilt.vk)).await.unwrap(), "");
+    }
+}
@@ -1,16 +1,230 @@
 //! Bitcoin Core RPC client service
+//!
+//! Authenticates with `BITCOIN_RPC_USER` / `BITCOIN_RPC_PASSWORD`, or with the
+//! node's cookie file when `BITCOIN_RPC_COOKIE_FILE` is set. Core writes a new
+//! cookie on every restart, so the file is re-read whenever it changes.
+//!
+//! Clones of a client share one pooled HTTP client and one set of per-method
+//! call metrics. Calls time out after `BITCOIN_RPC_TIMEOUT_SECS` (default 30);
+//! read-only calls that fail transiently — node unreachable, timed out, its
+//! work queue full or still warming up — are retried with backoff per
+//! `BITCOIN_RPC_MAX_ATTEMPTS`, `BITCOIN_RPC_RETRY_BASE_MS` and
+//! `BITCOIN_RPC_RETRY_MAX_MS`.
+//!
+//! Address UTXOs come from the node's wallets, or from scanning the UTXO set
+//! with `scantxoutset` when no wallet is loaded or `NODE_UTXO_SOURCE=scan`.
+//! Scans need no wallet but only see confirmed outputs.
+//!
+//! `BITCOIN_RPC_URL` may list several nodes; calls prefer the most synced
+//! reachable one and move on to the next when a node is down (see
+//! `rpc_nodes`).
+//!
+//! On regtest, `REGTEST_HARNESS=true` enables helpers that mine blocks, fund
+//! addresses from a node wallet and confirm a transaction at once, so the
+//! whole create → sign → broadcast → confirm flow can run in integration
+//! tests without external infrastructure.
 
-use anyhow::Result;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{Context, Result};
+use bitcoin::consensus::encode::deserialize_hex;
+use bitcoin::{Block, Network};
+use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::services::fee_bump::MAX_BUMP_FEE_RATE;
+use crate::services::fees::sat_per_vb_to_btc_per_kvb;
+use crate::services::network;
+use crate::services::retry::RetryPolicy;
+use crate::services::rpc_nodes::{NodeHealth, RpcNode, RpcNodes, DEFAULT_RPC_URL};
 
 /// Bitcoin service (alias for RPC client)
 pub type BitcoinService = BitcoinRpcClient;
 
-/// Bitcoin Core RPC client
+/// Methods that only read node state, safe to send again after a failure
+const IDEMPOTENT_METHODS: &[&str] = &[
+    "deriveaddresses",
+    "estimatesmartfee",
+    "getbalance",
+    "getblock",
+    "getblockchaininfo",
+    "getblockhash",
+    "getdescriptorinfo",
+    "getrawtransaction",
+    "gettransaction",
+    "gettxout",
+    "gettxoutproof",
+    "gettxspendingprevout",
+    "listtransactions",
+    "listunspent",
+    "listwallets",
+    "testmempoolaccept",
+];
+
+/// Node wallet the regtest harness mines to and funds addresses from
+const REGTEST_WALLET: &str = "liquid-nation-regtest";
+
+/// Blocks before a coinbase output can be spent
+const COINBASE_MATURITY: u32 = 100;
+
+/// Scanning the whole UTXO set takes minutes on mainnet
+const UTXO_SCAN_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Bitcoin Core RPC client; clones share its connections and metrics
+#[derive(Clone)]
 pub struct BitcoinRpcClient {
-    url: String,
-    user: String,
-    password: String,
+    nodes: Arc<RpcNodes>,
+    auth: Arc<RpcAuth>,
+    /// Network the node is expected to be on
+    network: Network,
+    /// Pooled HTTP connections to the node
+    client: reqwest::Client,
+    retry: RetryPolicy,
+    metrics: Arc<RpcMetrics>,
+    /// Look address UTXOs up in the UTXO set instead of the node's wallets
+    scan_utxos: bool,
+    /// `BITCOIN_MAX_FEE_RATE` (sat/vB): the node refuses to relay
+    /// transactions paying more
+    max_fee_rate: f64,
+    /// `REGTEST_HARNESS`: allow mining and funding helpers on regtest
+    regtest_harness: bool,
+}
+
+/// A failure worth retrying: the node could not be reached, timed out, was
+/// busy or is still starting
+#[derive(Debug, Error)]
+#[error("{0}")]
+struct TransientRpcError(String);
+
+/// The request never reached the node, so any call may go to another one
+#[derive(Debug, Error)]
+#[error("{0}")]
+struct NodeUnreachable(String);
+
+fn is_transient(e: &anyhow::Error) -> bool {
+    e.is::<TransientRpcError>() || e.is::<NodeUnreachable>()
+}
+
+/// Calls made for one RPC method
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RpcMethodStats {
+    pub calls: u64,
+    /// Calls that failed, after any retries, or returned an RPC error
+    pub failures: u64,
+    /// Attempts repeated after a transient failure
+    pub retries: u64,
+    /// Including retries
+    pub avg_latency_ms: u64,
+    pub max_latency_ms: u64,
+    #[serde(skip)]
+    total_latency_ms: u64,
+}
+
+/// Per-method call statistics
+#[derive(Debug, Default)]
+struct RpcMetrics {
+    methods: Mutex<BTreeMap<String, RpcMethodStats>>,
+}
+
+impl RpcMetrics {
+    fn record(&self, method: &str, latency: Duration, ok: bool, retries: u32) {
+        let latency_ms = latency.as_millis() as u64;
+        let mut methods = self.methods.lock().unwrap_or_else(|e| e.into_inner());
+        let stats = methods.entry(method.to_string()).or_default();
+        stats.calls += 1;
+        stats.failures += u64::from(!ok);
+        stats.retries += u64::from(retries);
+        stats.total_latency_ms += latency_ms;
+        stats.avg_latency_ms = stats.total_latency_ms / stats.calls;
+        stats.max_latency_ms = stats.max_latency_ms.max(latency_ms);
+    }
+
+    fn snapshot(&self) -> BTreeMap<String, RpcMethodStats> {
+        self.methods.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+/// How RPC calls authenticate
+enum RpcAuth {
+    UserPass { user: String, password: String },
+    Cookie(CookieFile),
+}
+
+/// Core's `.cookie` file, with the credentials last read from it
+struct CookieFile {
+    path: PathBuf,
+    /// Modification time and `(user, password)` of the last read
+    cached: Mutex<Option<(SystemTime, (String, String))>>,
+}
+
+impl CookieFile {
+    fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// Current credentials, re-reading the file when it has changed
+    fn credentials(&self) -> Result<(String, String)> {
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|meta| meta.modified())
+            .with_context(|| format!("Cannot read RPC cookie file {}", self.path.display()))?;
+
+        let mut cached = self.cached.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((read_at, credentials)) = cached.as_ref() {
+            if *read_at == modified {
+                return Ok(credentials.clone());
+            }
+        }
+
+        let contents = std::fs::read_to_string(&self.path)
+            .with_context(|| format!("Cannot read RPC cookie file {}", self.path.display()))?;
+        let credentials = parse_cookie(&contents)
+            .with_context(|| format!("Invalid RPC cookie file {}", self.path.display()))?;
+        *cached = Some((modified, credentials.clone()));
+        Ok(credentials)
+    }
+
+    /// Drop the cached credentials so the next call reads the file again
+    fn invalidate(&self) {
+        *self.cached.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+/// `(user, password)` of a cookie file's `user:password` line
+fn parse_cookie(contents: &str) -> Result<(String, String)> {
+    let (user, password) = contents
+        .trim()
+        .split_once(':')
+        .context("Expected user:password")?;
+    if user.is_empty() || password.is_empty() {
+        anyhow::bail!("Expected user:password");
+    }
+    Ok((user.to_string(), password.to_string()))
+}
+
+impl RpcAuth {
+    fn from_env() -> Self {
+        if let Some(path) = std::env::var("BITCOIN_RPC_COOKIE_FILE").ok().filter(|p| !p.is_empty()) {
+            return RpcAuth::Cookie(CookieFile::new(path));
+        }
+        RpcAuth::UserPass {
+            user: std::env::var("BITCOIN_RPC_USER").unwrap_or_else(|_| "charms".to_string()),
+            password: std::env::var("BITCOIN_RPC_PASSWORD").unwrap_or_else(|_| "charms".to_string()),
+        }
+    }
+
+    fn credentials(&self) -> Result<(String, String)> {
+        match self {
+            RpcAuth::UserPass { user, password } => Ok((user.clone(), password.clone())),
+            RpcAuth::Cookie(cookie) => cookie.credentials(),
+        }
+    }
 }
 
 /// UTXO from listunspent
@@ -18,38 +232,230 @@ pub struct BitcoinRpcClient {
 pub struct UnspentOutput {
     pub txid: String,
     pub vout: u32,
+    #[serde(default)]
     pub address: String,
+    #[serde(rename = "scriptPubKey")]
     pub script_pub_key: String,
     pub amount: f64,
     pub confirmations: u32,
     pub spendable: bool,
 }
 
+/// Wallet transaction entry from listtransactions (one per address touched)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WalletTransaction {
+    pub txid: String,
+    #[serde(default)]
+    pub address: Option<String>,
+    /// `send`, `receive`, `generate`, ...
+    pub category: String,
+    /// BTC, negative for sends
+    pub amount: f64,
+    #[serde(default)]
+    pub fee: Option<f64>,
+    pub confirmations: i64,
+    pub time: i64,
+}
+
+/// Unspent output from gettxout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TxOutInfo {
+    pub confirmations: u32,
+    /// BTC
+    pub value: f64,
+    #[serde(rename = "scriptPubKey")]
+    pub script_pub_key: ScriptPubKeyInfo,
+}
+
+/// Output script details
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptPubKeyInfo {
+    pub hex: String,
+    #[serde(default)]
+    pub address: Option<String>,
+}
+
 /// Block info
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BlockchainInfo {
     pub chain: String,
     pub blocks: u64,
     pub headers: u64,
+    #[serde(rename = "bestblockhash")]
     pub best_block_hash: String,
 }
 
+/// Result of getdescriptorinfo
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DescriptorInfo {
+    /// Canonical public descriptor with checksum
+    pub descriptor: String,
+    pub checksum: String,
+    #[serde(rename = "isrange")]
+    pub is_range: bool,
+    #[serde(rename = "issolvable")]
+    pub is_solvable: bool,
+    #[serde(rename = "hasprivatekeys")]
+    pub has_private_keys: bool,
+}
+
+/// Result of estimatesmartfee
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SmartFeeEstimate {
+    /// Fee rate in BTC/kvB (absent when the node lacks data)
+    pub feerate: Option<f64>,
+    pub errors: Option<Vec<String>>,
+    pub blocks: u32,
+}
+
+/// Entry of gettxspendingprevout
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SpendingPrevout {
+    pub txid: String,
+    pub vout: u32,
+    /// Mempool transaction spending the output, if any
+    #[serde(rename = "spendingtxid", default)]
+    pub spending_txid: Option<String>,
+}
+
+/// Result of scantxoutset
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UtxoScan {
+    pub success: bool,
+    /// Height of the chainstate that was scanned
+    pub height: u64,
+    pub unspents: Vec<ScannedUtxo>,
+}
+
+/// Output found by scantxoutset
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScannedUtxo {
+    pub txid: String,
+    pub vout: u32,
+    #[serde(rename = "scriptPubKey")]
+    pub script_pub_key: String,
+    /// BTC
+    pub amount: f64,
+    /// Height of the block that created it
+    pub height: u64,
+}
+
+/// Entry of testmempoolaccept
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MempoolAcceptResult {
+    pub txid: String,
+    pub allowed: bool,
+    /// Why the transaction would be rejected, e.g. `min relay fee not met`
+    #[serde(rename = "reject-reason", default)]
+    pub reject_reason: Option<String>,
+}
+
 impl BitcoinRpcClient {
-    /// Create a new Bitcoin RPC client with explicit URL
-    pub fn new(url: &str) -> Self {
-        let user = std::env::var("BITCOIN_RPC_USER")
-            .unwrap_or_else(|_| "charms".to_string());
-        let password = std::env::var("BITCOIN_RPC_PASSWORD")
-            .unwrap_or_else(|_| "charms".to_string());
+    /// Create a new Bitcoin RPC client for the node at `url` (or several,
+    /// comma-separated) on `network`
+    pub fn new(url: &str, network: Network) -> Self {
+        let timeout_secs = std::env::var("BITCOIN_RPC_TIMEOUT_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(30);
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(timeout_secs))
+            .connect_timeout(Duration::from_secs(5))
+            .build()
+            .expect("Failed to build Bitcoin RPC HTTP client");
+        let retry = RetryPolicy::from_env(
+            "BITCOIN_RPC",
+            RetryPolicy {
+                max_attempts: 3,
+                base_delay: Duration::from_millis(250),
+                max_delay: Duration::from_secs(2),
+            },
+        );
 
-        Self { url: url.to_string(), user, password }
+        Self {
+            nodes: Arc::new(RpcNodes::new(url)),
+            auth: Arc::new(RpcAuth::from_env()),
+            network,
+            client,
+            retry,
+            metrics: Arc::new(RpcMetrics::default()),
+            scan_utxos: std::env::var("NODE_UTXO_SOURCE").is_ok_and(|source| source == "scan"),
+            max_fee_rate: std::env::var("BITCOIN_MAX_FEE_RATE")
+                .ok()
+                .and_then(|r| r.parse().ok())
+                .unwrap_or(MAX_BUMP_FEE_RATE),
+            regtest_harness: std::env::var("REGTEST_HARNESS").is_ok_and(|flag| flag == "true"),
+        }
     }
 
     /// Create a new Bitcoin RPC client from environment
     pub fn from_env() -> Result<Self> {
-        let url = std::env::var("BITCOIN_RPC_URL")
-            .unwrap_or_else(|_| "http://127.0.0.1:48332".to_string());
-        Ok(Self::new(&url))
+        let url = std::env::var("BITCOIN_RPC_URL").unwrap_or_else(|_| DEFAULT_RPC_URL.to_string());
+        Ok(Self::new(&url, network::from_env()?))
+    }
+
+    /// Network the node is expected to be on
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    /// Whether address UTXOs always come from scanning the UTXO set
+    /// (`NODE_UTXO_SOURCE=scan`) rather than from the node's wallets
+    pub fn scans_utxos(&self) -> bool {
+        self.scan_utxos
+    }
+
+    /// Calls made so far by this client and its clones, by method
+    pub fn metrics(&self) -> BTreeMap<String, RpcMethodStats> {
+        self.metrics.snapshot()
+    }
+
+    /// Reachability and height of each configured node
+    pub fn node_health(&self) -> Vec<NodeHealth> {
+        self.nodes.health()
+    }
+
+    /// Check every node's height in the background when several are
+    /// configured, so calls go to the most synced one
+    pub fn spawn_health_checks(&self) {
+        if self.nodes.len() < 2 {
+            return;
+        }
+        let interval_secs = std::env::var("BITCOIN_RPC_HEALTH_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(30);
+
+        let client = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+            loop {
+                interval.tick().await;
+                for node in client.nodes.all() {
+                    client.check_node(node).await;
+                }
+            }
+        });
+    }
+
+    async fn check_node(&self, node: &RpcNode) {
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": "liquid-nation",
+            "method": "getblockchaininfo",
+            "params": []
+        });
+        let info = self
+            .post(&node.url, "getblockchaininfo", &request)
+            .await
+            .and_then(|response| rpc_result::<BlockchainInfo>(&response));
+        match info {
+            Ok(info) => node.record_height(info.blocks),
+            Err(e) => {
+                tracing::debug!("Bitcoin node {} failed its health check: {:#}", node.url, e);
+                node.record_failure(&format!("{:#}", e));
+            }
+        }
     }
 
     /// Make an RPC call
@@ -58,32 +464,162 @@ impl BitcoinRpcClient {
         method: &str,
         params: serde_json::Value,
     ) -> Result<T> {
-        let client = reqwest::Client::new();
-        
-        let response = client
-            .post(&self.url)
-            .basic_auth(&self.user, Some(&self.password))
-            .json(&serde_json::json!({
-                "jsonrpc": "2.0",
-                "id": "liquid-nation",
-                "method": method,
-                "params": params
-            }))
+        self.rpc_call_at("", method, params).await
+    }
+
+    /// Make an RPC call against a specific loaded wallet
+    async fn wallet_rpc_call<T: for<'de> Deserialize<'de>>(
+        &self,
+        wallet: &str,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<T> {
+        self.rpc_call_at(&format!("/wallet/{}", wallet), method, params).await
+    }
+
+    /// Make an RPC call at `path` under the node URL
+    async fn rpc_call_at<T: for<'de> Deserialize<'de>>(
+        &self,
+        path: &str,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<T> {
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": "liquid-nation",
+            "method": method,
+            "params": params
+        });
+        let response = self.send(path, method, &request).await?;
+        rpc_result(&response)
+    }
+
+    /// Make the same RPC call once per entry of `params`, all in one round
+    /// trip. Results come back in `params` order and fail one by one, so a
+    /// missing transaction does not sink the rest of the batch.
+    async fn rpc_batch<T: for<'de> Deserialize<'de>>(
+        &self,
+        method: &str,
+        params: &[serde_json::Value],
+    ) -> Result<Vec<Result<T>>> {
+        if params.is_empty() {
+            return Ok(Vec::new());
+        }
+        let requests: Vec<serde_json::Value> = params
+            .iter()
+            .enumerate()
+            .map(|(id, params)| {
+                serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "method": method,
+                    "params": params
+                })
+            })
+            .collect();
+        let response = self.send("", method, &serde_json::Value::Array(requests)).await?;
+        batch_results(response, params.len())
+    }
+
+    /// Send a JSON-RPC request (or batch) to `path` under the node URL and
+    /// return the response body, retrying idempotent methods that fail
+    /// transiently
+    async fn send(&self, path: &str, method: &str, request: &serde_json::Value) -> Result<serde_json::Value> {
+        let started = Instant::now();
+        let retryable = IDEMPOTENT_METHODS.contains(&method);
+        let mut attempt = 1;
+        let result = loop {
+            let result = self.send_to_nodes(path, method, request, retryable).await;
+            match &result {
+                Err(e) if retryable && is_transient(e) && attempt < self.retry.max_attempts => {
+                    let delay = self.retry.delay(attempt);
+                    tracing::debug!("RPC {} failed ({}); retrying in {:?}", method, e, delay);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                _ => break result,
+            }
+        };
+
+        let ok = matches!(&result, Ok(response) if response.get("error").is_none_or(serde_json::Value::is_null));
+        self.metrics.record(method, started.elapsed(), ok, attempt - 1);
+        result
+    }
+
+    /// One attempt at a request, going down the node ranking while nodes
+    /// cannot be reached. Non-idempotent calls only move on when the request
+    /// never reached the node, so a broadcast is not sent twice.
+    async fn send_to_nodes(
+        &self,
+        path: &str,
+        method: &str,
+        request: &serde_json::Value,
+        retryable: bool,
+    ) -> Result<serde_json::Value> {
+        let mut last_error = None;
+        for node in self.nodes.ranked() {
+            let url = format!("{}{}", node.url, path);
+            match self.post(&url, method, request).await {
+                Ok(response) => {
+                    node.record_success();
+                    return Ok(response);
+                }
+                Err(e) if e.is::<NodeUnreachable>() || (retryable && e.is::<TransientRpcError>()) => {
+                    if self.nodes.len() > 1 {
+                        tracing::warn!("Bitcoin node {} failed {}: {:#}; trying the next", node.url, method, e);
+                    }
+                    node.record_failure(&format!("{:#}", e));
+                    last_error = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No Bitcoin RPC node configured")))
+    }
+
+    /// One attempt at a JSON-RPC request
+    async fn post(&self, url: &str, method: &str, request: &serde_json::Value) -> Result<serde_json::Value> {
+        let (user, password) = self.auth.credentials()?;
+
+        let mut builder = self.client.post(url).basic_auth(&user, Some(&password)).json(request);
+        if method == "scantxoutset" {
+            builder = builder.timeout(UTXO_SCAN_TIMEOUT);
+        }
+        let response = builder
             .send()
-            .await?;
+            .await
+            .map_err(|e| match (e.is_connect(), e.is_timeout() || e.is_request()) {
+                (true, _) => anyhow::Error::new(NodeUnreachable(format!("RPC {} failed: {}", method, e))),
+                (false, true) => anyhow::Error::new(TransientRpcError(format!("RPC {} failed: {}", method, e))),
+                (false, false) => e.into(),
+            })?;
 
-        let result: serde_json::Value = response.json().await?;
-        
-        if let Some(error) = result.get("error") {
-            if !error.is_null() {
-                anyhow::bail!("RPC error: {}", error);
+        match response.status() {
+            StatusCode::UNAUTHORIZED => {
+                // A rotated cookie may have the same modification time on
+                // coarse filesystems; read it afresh on the next attempt
+                if let RpcAuth::Cookie(cookie) = self.auth.as_ref() {
+                    cookie.invalidate();
+                    return Err(TransientRpcError(format!("RPC authentication failed for {}", method)).into());
+                }
+                anyhow::bail!("RPC authentication failed for {}", method);
+            }
+            // Core's work queue is full
+            StatusCode::SERVICE_UNAVAILABLE => {
+                return Err(TransientRpcError(format!("RPC {} failed: node is busy", method)).into());
             }
+            _ => {}
         }
 
-        let result_value = result.get("result")
-            .ok_or_else(|| anyhow::anyhow!("No result in response"))?;
-        
-        Ok(serde_json::from_value(result_value.clone())?)
+        let response: serde_json::Value = response.json().await.map_err(|e| match e.is_timeout() {
+            true => anyhow::Error::new(TransientRpcError(format!("RPC {} timed out: {}", method, e))),
+            false => e.into(),
+        })?;
+        // RPC_IN_WARMUP: the node is still loading its block index
+        if response["error"]["code"].as_i64() == Some(-28) {
+            return Err(TransientRpcError(format!("RPC {} failed: {}", method, response["error"])).into());
+        }
+        Ok(response)
     }
 
     /// Get blockchain info
@@ -91,15 +627,6 @@ impl BitcoinRpcClient {
         self.rpc_call("getblockchaininfo", serde_json::json!([])).await
     }
 
-    /// Get new address
-    pub async fn get_new_address(&self, label: Option<&str>) -> Result<String> {
-        let params = match label {
-            Some(l) => serde_json::json!([l]),
-            None => serde_json::json!([]),
-        };
-        self.rpc_call("getnewaddress", params).await
-    }
-
     /// List unspent outputs
     pub async fn list_unspent(
         &self,
@@ -113,25 +640,158 @@ impl BitcoinRpcClient {
         self.rpc_call("listunspent", params).await
     }
 
-    /// Get wallet balance
-    pub async fn get_balance(&self) -> Result<f64> {
-        self.rpc_call("getbalance", serde_json::json!([])).await
+    /// Search the UTXO set for outputs matching `descriptors`, without a
+    /// wallet. Only confirmed outputs are in the UTXO set, and the node runs
+    /// one scan at a time.
+    pub async fn scan_tx_out_set(&self, descriptors: &[String]) -> Result<UtxoScan> {
+        self.rpc_call("scantxoutset", serde_json::json!(["start", descriptors])).await
+    }
+
+    /// Validate a descriptor and get its canonical form with checksum
+    pub async fn get_descriptor_info(&self, descriptor: &str) -> Result<DescriptorInfo> {
+        self.rpc_call("getdescriptorinfo", serde_json::json!([descriptor])).await
+    }
+
+    /// Derive the addresses of a ranged descriptor over `[start, end]`
+    pub async fn derive_addresses(&self, descriptor: &str, start: u32, end: u32) -> Result<Vec<String>> {
+        self.rpc_call("deriveaddresses", serde_json::json!([descriptor, [start, end]])).await
+    }
+
+    /// Load a blank watch-only descriptor wallet, creating it on first use
+    pub async fn ensure_watch_wallet(&self, wallet: &str) -> Result<()> {
+        let loaded: Vec<String> = self.rpc_call("listwallets", serde_json::json!([])).await?;
+        if loaded.iter().any(|w| w == wallet) {
+            return Ok(());
+        }
+
+        if self.rpc_call::<serde_json::Value>("loadwallet", serde_json::json!([wallet])).await.is_ok() {
+            return Ok(());
+        }
+
+        // name, disable_private_keys, blank, passphrase, avoid_reuse, descriptors
+        self.rpc_call::<serde_json::Value>(
+            "createwallet",
+            serde_json::json!([wallet, true, true, "", false, true]),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Import public descriptors into a watch-only wallet.
+    ///
+    /// `birth_time` is the unix time to rescan from; `None` only tracks new
+    /// activity.
+    pub async fn import_descriptors(
+        &self,
+        wallet: &str,
+        descriptors: &[String],
+        range_end: u32,
+        birth_time: Option<i64>,
+    ) -> Result<()> {
+        let timestamp = birth_time.map_or(serde_json::json!("now"), |t| serde_json::json!(t));
+        let requests: Vec<serde_json::Value> = descriptors
+            .iter()
+            .map(|desc| {
+                let mut request = serde_json::json!({
+                    "desc": desc,
+                    "timestamp": timestamp,
+                    "active": false,
+                });
+                // Core rejects a range on un-ranged descriptors such as `addr(...)`
+                if desc.contains('*') {
+                    request["range"] = serde_json::json!([0, range_end]);
+                }
+                request
+            })
+            .collect();
+
+        let results: Vec<serde_json::Value> = self
+            .wallet_rpc_call(wallet, "importdescriptors", serde_json::json!([requests]))
+            .await?;
+        if let Some(failed) = results.iter().find(|r| r.get("success") != Some(&serde_json::Value::Bool(true))) {
+            anyhow::bail!("importdescriptors failed: {}", failed);
+        }
+        Ok(())
+    }
+
+    /// List unspent outputs of a wallet at the given addresses, including unconfirmed
+    pub async fn list_unspent_for(&self, wallet: &str, addresses: &[String]) -> Result<Vec<UnspentOutput>> {
+        self.wallet_rpc_call(wallet, "listunspent", serde_json::json!([0, 9999999, addresses])).await
+    }
+
+    /// Most recent wallet transactions, including watch-only addresses
+    pub async fn list_transactions(&self, count: u32, skip: u32) -> Result<Vec<WalletTransaction>> {
+        self.rpc_call("listtransactions", serde_json::json!(["*", count, skip, true])).await
+    }
+
+    /// Look up an unspent output; `None` when it is spent or unknown
+    pub async fn get_tx_out(&self, txid: &str, vout: u32) -> Result<Option<TxOutInfo>> {
+        self.rpc_call("gettxout", serde_json::json!([txid, vout, true])).await
+    }
+
+    /// Mempool transactions spending `outpoints` (`(txid, vout)`)
+    pub async fn get_tx_spending_prevout(&self, outpoints: &[(String, u32)]) -> Result<Vec<SpendingPrevout>> {
+        let outputs: Vec<serde_json::Value> = outpoints
+            .iter()
+            .map(|(txid, vout)| serde_json::json!({ "txid": txid, "vout": vout }))
+            .collect();
+        self.rpc_call("gettxspendingprevout", serde_json::json!([outputs])).await
+    }
+
+    /// BIP37 merkle block (hex) proving `txid` is in block `block_hash`
+    pub async fn get_tx_out_proof(&self, txid: &str, block_hash: &str) -> Result<String> {
+        self.rpc_call("gettxoutproof", serde_json::json!([[txid], block_hash])).await
+    }
+
+    /// Estimate fee rate for confirmation within `conf_target` blocks
+    pub async fn estimate_smart_fee(&self, conf_target: u16) -> Result<SmartFeeEstimate> {
+        self.rpc_call("estimatesmartfee", serde_json::json!([conf_target])).await
     }
 
     /// Send raw transaction
     pub async fn send_raw_transaction(&self, hex: &str) -> Result<String> {
-        self.rpc_call("sendrawtransaction", serde_json::json!([hex])).await
+        self.rpc_call("sendrawtransaction", serde_json::json!([hex, self.max_fee_rate_btc_kvb()]))
+            .await
+    }
+
+    /// Check whether the mempool would accept a raw transaction, without
+    /// relaying it
+    pub async fn test_mempool_accept(&self, hex: &str) -> Result<MempoolAcceptResult> {
+        let results: Vec<MempoolAcceptResult> =
+            self.rpc_call("testmempoolaccept", serde_json::json!([[hex], self.max_fee_rate_btc_kvb()])).await?;
+        results.into_iter().next().context("testmempoolaccept returned no result")
     }
 
-    /// Get transaction
-    pub async fn get_transaction(&self, txid: &str) -> Result<serde_json::Value> {
-        self.rpc_call("gettransaction", serde_json::json!([txid])).await
+    /// `maxfeerate` argument of `sendrawtransaction` and `testmempoolaccept`
+    fn max_fee_rate_btc_kvb(&self) -> f64 {
+        sat_per_vb_to_btc_per_kvb(self.max_fee_rate)
     }
 
     /// Get raw transaction
     pub async fn get_raw_transaction(&self, txid: &str, verbose: bool) -> Result<serde_json::Value> {
         self.rpc_call("getrawtransaction", serde_json::json!([txid, verbose])).await
     }
+
+    /// Raw transactions for each of `txids`, in one round trip
+    pub async fn get_raw_transactions(
+        &self,
+        txids: &[String],
+        verbose: bool,
+    ) -> Result<Vec<Result<serde_json::Value>>> {
+        let params: Vec<serde_json::Value> = txids.iter().map(|txid| serde_json::json!([txid, verbose])).collect();
+        self.rpc_batch("getrawtransaction", &params).await
+    }
+
+    /// Hash of the block at `height` in the active chain
+    pub async fn get_block_hash(&self, height: u64) -> Result<String> {
+        self.rpc_call("getblockhash", serde_json::json!([height])).await
+    }
+
+    /// Block with all its transactions
+    pub async fn get_block(&self, hash: &str) -> Result<Block> {
+        let hex: String = self.rpc_call("getblock", serde_json::json!([hash, 0])).await?;
+        Ok(deserialize_hex(&hex)?)
+    }
 }
 
 impl Default for BitcoinRpcClient {
@@ -140,3 +800,213 @@ impl Default for BitcoinRpcClient {
     }
 }
 
+/// Regtest harness helpers; all fail unless `regtest_enabled`
+impl BitcoinRpcClient {
+    /// Whether the harness is on: `REGTEST_HARNESS=true` on a regtest node
+    pub fn regtest_enabled(&self) -> bool {
+        self.regtest_harness && self.network == Network::Regtest
+    }
+
+    fn require_regtest(&self) -> Result<()> {
+        if !self.regtest_enabled() {
+            anyhow::bail!("The regtest harness needs REGTEST_HARNESS=true on a regtest node");
+        }
+        Ok(())
+    }
+
+    /// Mine `blocks` blocks paying their coinbases to `address`, or to the
+    /// harness wallet; returns their hashes
+    pub async fn generate_to_address(&self, blocks: u32, address: Option<&str>) -> Result<Vec<String>> {
+        self.require_regtest()?;
+        let address = match address {
+            Some(address) => address.to_string(),
+            None => {
+                self.ensure_regtest_wallet().await?;
+                self.regtest_address().await?
+            }
+        };
+        self.rpc_call("generatetoaddress", serde_json::json!([blocks, address])).await
+    }
+
+    /// Send `sats` to `address` from the harness wallet, first mining mature
+    /// coinbases to it when it cannot cover them; returns the txid
+    pub async fn fund_address(&self, address: &str, sats: u64) -> Result<String> {
+        self.require_regtest()?;
+        self.ensure_regtest_wallet().await?;
+
+        let amount = sats as f64 / 100_000_000.0;
+        let balance: f64 = self.wallet_rpc_call(REGTEST_WALLET, "getbalance", serde_json::json!([])).await?;
+        // Leave room for the fee
+        if balance < amount + 0.001 {
+            self.generate_to_address(COINBASE_MATURITY + 1, None).await?;
+        }
+
+        // Regtest nodes have no fee estimates, so pay an explicit rate
+        self.wallet_rpc_call(
+            REGTEST_WALLET,
+            "sendtoaddress",
+            serde_json::json!({ "address": address, "amount": amount, "fee_rate": 1 }),
+        )
+        .await
+    }
+
+    /// Mine a block holding `txid`, which must be in the mempool, and
+    /// nothing else; returns the block hash
+    pub async fn confirm_transaction(&self, txid: &str) -> Result<String> {
+        self.require_regtest()?;
+        self.ensure_regtest_wallet().await?;
+        let miner = self.regtest_address().await?;
+        let block: serde_json::Value = self.rpc_call("generateblock", serde_json::json!([miner, [txid]])).await?;
+        block["hash"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| anyhow::anyhow!("generateblock did not return a block hash"))
+    }
+
+    /// Load the harness wallet, creating it (with keys) on first use
+    async fn ensure_regtest_wallet(&self) -> Result<()> {
+        let loaded: Vec<String> = self.rpc_call("listwallets", serde_json::json!([])).await?;
+        if loaded.iter().any(|w| w == REGTEST_WALLET) {
+            return Ok(());
+        }
+
+        if self.rpc_call::<serde_json::Value>("loadwallet", serde_json::json!([REGTEST_WALLET])).await.is_ok() {
+            return Ok(());
+        }
+
+        // name, disable_private_keys, blank, passphrase, avoid_reuse, descriptors
+        self.rpc_call::<serde_json::Value>(
+            "createwallet",
+            serde_json::json!([REGTEST_WALLET, false, false, "", false, true]),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Fresh address of the harness wallet
+    async fn regtest_address(&self) -> Result<String> {
+        self.wallet_rpc_call(REGTEST_WALLET, "getnewaddress", serde_json::json!([])).await
+    }
+}
+
+/// Result of a single JSON-RPC response
+fn rpc_result<T: for<'de> Deserialize<'de>>(response: &serde_json::Value) -> Result<T> {
+    if let Some(error) = response.get("error") {
+        if !error.is_null() {
+            anyhow::bail!("RPC error: {}", error);
+        }
+    }
+
+    let result_value = response.get("result")
+        .ok_or_else(|| anyhow::anyhow!("No result in response"))?;
+
+    Ok(serde_json::from_value(result_value.clone())?)
+}
+
+/// Results of a batch of `len` requests numbered from 0, in request order;
+/// the node may answer them in any order
+fn batch_results<T: for<'de> Deserialize<'de>>(response: serde_json::Value, len: usize) -> Result<Vec<Result<T>>> {
+    let serde_json::Value::Array(responses) = response else {
+        // A malformed batch gets a single error response
+        return Err(rpc_result::<serde_json::Value>(&response)
+            .err()
+            .unwrap_or_else(|| anyhow::anyhow!("Expected an array of responses")));
+    };
+
+    let mut by_id: Vec<Option<serde_json::Value>> = vec![None; len];
+    for response in responses {
+        let Some(slot) = response["id"].as_u64().and_then(|id| by_id.get_mut(id as usize)) else {
+            continue;
+        };
+        *slot = Some(response);
+    }
+    Ok(by_id
+        .into_iter()
+        .enumerate()
+        .map(|(id, response)| match response {
+            Some(response) => rpc_result(&response),
+            None => Err(anyhow::anyhow!("No response to request {} of the batch", id)),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_cookie_file_reloads_on_rotation() {
+        assert_eq!(
+            parse_cookie("__cookie__:abc123\n").unwrap(),
+            ("__cookie__".to_string(), "abc123".to_string())
+        );
+        assert!(parse_cookie("no-separator").is_err());
+        assert!(parse_cookie("__cookie__:").is_err());
+
+        let path = std::env::temp_dir().join(format!("liquid-nation-cookie-{}", std::process::id()));
+        std::fs::write(&path, "__cookie__:first").unwrap();
+        let cookie = CookieFile::new(&path);
+        assert_eq!(cookie.credentials().unwrap().1, "first");
+
+        // Core restarting writes a new cookie
+        std::fs::write(&path, "__cookie__:second").unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(5)).unwrap();
+        assert_eq!(cookie.credentials().unwrap().1, "second");
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(cookie.credentials().is_err());
+    }
+
+    #[test]
+    fn test_batch_results_in_request_order() {
+        let response = serde_json::json!([
+            { "id": 2, "result": "cc", "error": null },
+            { "id": 0, "result": "aa", "error": null },
+            { "id": 1, "result": null, "error": { "code": -5, "message": "No such mempool or blockchain transaction" } },
+        ]);
+        let results: Vec<Result<String>> = batch_results(response, 4).unwrap();
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].as_ref().unwrap(), "aa");
+        assert!(results[1].as_ref().unwrap_err().to_string().contains("\"code\":-5"));
+        assert_eq!(results[2].as_ref().unwrap(), "cc");
+        assert!(results[3].is_err());
+
+        // A rejected batch is a single error object
+        let response = serde_json::json!({ "id": null, "result": null, "error": { "code": -32700, "message": "Parse error" } });
+        assert!(batch_results::<String>(response, 1).unwrap_err().to_string().contains("Parse error"));
+    }
+
+    #[test]
+    fn test_rpc_metrics_per_method() {
+        let metrics = RpcMetrics::default();
+        metrics.record("getblock", Duration::from_millis(30), true, 0);
+        metrics.record("getblock", Duration::from_millis(10), false, 2);
+        metrics.record("gettxout", Duration::from_millis(5), true, 0);
+
+        let snapshot = metrics.snapshot();
+        let getblock = &snapshot["getblock"];
+        assert_eq!((getblock.calls, getblock.failures, getblock.retries), (2, 1, 2));
+        assert_eq!((getblock.avg_latency_ms, getblock.max_latency_ms), (20, 30));
+        assert_eq!(snapshot["gettxout"].calls, 1);
+
+        // Wallet changes are never sent twice
+        assert!(!IDEMPOTENT_METHODS.contains(&"sendrawtransaction"));
+        assert!(!IDEMPOTENT_METHODS.contains(&"getnewaddress"));
+    }
+
+    #[tokio::test]
+    async fn test_regtest_harness_needs_regtest() {
+        let harness = |network| BitcoinRpcClient {
+            regtest_harness: true,
+            ..BitcoinRpcClient::new("http://127.0.0.1:1", network)
+        };
+        assert!(harness(Network::Regtest).regtest_enabled());
+        // Never mines or spends on a real network, whatever the flag says
+        let testnet = harness(Network::Testnet4);
+        assert!(!testnet.regtest_enabled());
+        assert!(testnet.generate_to_address(1, None).await.is_err());
+        assert!(testnet.fund_address("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx", 1000).await.is_err());
+    }
+}
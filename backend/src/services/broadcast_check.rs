@@ -0,0 +1,141 @@
+//! Checks on signed transactions before they are relayed
+//!
+//! Clients sign the transactions we proved for an order or escrow and send
+//! them back for broadcast. Signing only adds witnesses, so the signed
+//! transaction must have the txid of one we proved, keep the spell witness
+//! the prover attached and spend the UTXO the action is about.
+
+use std::str::FromStr;
+
+use bitcoin::consensus::encode::deserialize_hex;
+use bitcoin::{OutPoint, Transaction};
+use thiserror::Error;
+
+/// Why a signed transaction may not be broadcast
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum BroadcastMismatch {
+    #[error("Signed transaction does not decode: {0}")]
+    Undecodable(String),
+    #[error("No proved spell transaction is on record")]
+    NothingProved,
+    #[error("Transaction {0} is not the spell transaction that was proved")]
+    NotProved(String),
+    #[error("Transaction {txid} changes the spell witness of input {input}")]
+    SpellWitnessChanged { txid: String, input: usize },
+    #[error("Transaction {txid} does not spend {outpoint}")]
+    MissingInput { txid: String, outpoint: String },
+}
+
+/// Check a signed transaction against the transactions proved for its order
+/// or escrow (hex, any order) and the UTXO it has to spend, if any
+pub fn verify_signed_spell_tx(
+    signed_hex: &str,
+    proved_hexes: &[String],
+    expected_spend: Option<&str>,
+) -> Result<Transaction, BroadcastMismatch> {
+    let signed: Transaction = deserialize_hex(signed_hex.trim())
+        .map_err(|e| BroadcastMismatch::Undecodable(e.to_string()))?;
+    let txid = signed.compute_txid();
+
+    let proved: Vec<Transaction> = proved_hexes
+        .iter()
+        .filter_map(|hex| deserialize_hex(hex).ok())
+        .collect();
+    if proved.is_empty() {
+        return Err(BroadcastMismatch::NothingProved);
+    }
+    let original = proved
+        .iter()
+        .find(|tx| tx.compute_txid() == txid)
+        .ok_or_else(|| BroadcastMismatch::NotProved(txid.to_string()))?;
+
+    // Witnesses the prover already filled in carry the spell and its proof
+    for (input, (proved_in, signed_in)) in original.input.iter().zip(&signed.input).enumerate() {
+        if !proved_in.witness.is_empty() && proved_in.witness != signed_in.witness {
+            return Err(BroadcastMismatch::SpellWitnessChanged {
+                txid: txid.to_string(),
+                input,
+            });
+        }
+    }
+
+    if let Some(expected) = expected_spend {
+        let spends = OutPoint::from_str(expected)
+            .is_ok_and(|outpoint| signed.input.iter().any(|input| input.previous_output == outpoint));
+        if !spends {
+            return Err(BroadcastMismatch::MissingInput {
+                txid: txid.to_string(),
+                outpoint: expected.to_string(),
+            });
+        }
+    }
+
+    Ok(signed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::consensus::encode::serialize_hex;
+    use bitcoin::transaction::Version;
+    use bitcoin::{Amount, ScriptBuf, Sequence, TxIn, TxOut, Witness};
+
+    fn input(outpoint: &str, witness: &[&[u8]]) -> TxIn {
+        TxIn {
+            previous_output: OutPoint::from_str(outpoint).unwrap(),
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::MAX,
+            witness: Witness::from_slice(witness),
+        }
+    }
+
+    #[test]
+    fn test_verify_signed_spell_tx() {
+        let order_utxo = format!("{}:0", "aa".repeat(32));
+        let commit_utxo = format!("{}:0", "bb".repeat(32));
+        let proved = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![input(&order_utxo, &[]), input(&commit_utxo, &[b"spell", b"proof"])],
+            output: vec![TxOut {
+                value: Amount::from_sat(1000),
+                script_pubkey: ScriptBuf::new(),
+            }],
+        };
+        let proved_hexes = vec![serialize_hex(&proved)];
+
+        let mut signed = proved.clone();
+        signed.input[0].witness = Witness::from_slice(&[b"signature"]);
+        assert!(verify_signed_spell_tx(&serialize_hex(&signed), &proved_hexes, Some(&order_utxo)).is_ok());
+
+        // Spending some other UTXO than the order's
+        let other = format!("{}:1", "cc".repeat(32));
+        assert!(matches!(
+            verify_signed_spell_tx(&serialize_hex(&signed), &proved_hexes, Some(&other)),
+            Err(BroadcastMismatch::MissingInput { .. })
+        ));
+
+        // Swapping out the spell witness
+        let mut tampered = signed.clone();
+        tampered.input[1].witness = Witness::from_slice(&[b"other spell"]);
+        assert!(matches!(
+            verify_signed_spell_tx(&serialize_hex(&tampered), &proved_hexes, None),
+            Err(BroadcastMismatch::SpellWitnessChanged { input: 1, .. })
+        ));
+
+        // Redirecting the output changes the txid
+        let mut redirected = signed;
+        redirected.output[0].value = Amount::from_sat(999);
+        assert!(matches!(
+            verify_signed_spell_tx(&serialize_hex(&redirected), &proved_hexes, None),
+            Err(BroadcastMismatch::NotProved(_))
+        ));
+
+        assert_eq!(verify_signed_spell_tx(&serialize_hex(&proved), &[], None), Err(BroadcastMismatch::NothingProved));
+        assert!(matches!(
+            verify_signed_spell_tx("zz", &proved_hexes, None),
+            Err(BroadcastMismatch::Undecodable(_))
+        ));
+    }
+}
@@ -0,0 +1,462 @@
+//! Chain backends
+//!
+//! Everything the backend needs to know about the chain itself — the tip,
+//! address UTXOs, transactions and their status, fee estimates, blocks for
+//! the indexer — and broadcasting goes through a `ChainBackend`: either the
+//! Bitcoin Core node (`BitcoinRpcClient`) or an Esplora HTTP API such as
+//! mempool.space, for deployments without a full node. Watch-only wallets
+//! and descriptor imports have no Esplora equivalent and stay on the node.
+//!
+//! Chosen once at startup with `CHAIN_BACKEND` (`node`, `esplora` or `mock`;
+//! default `mock` in mock mode, `node` otherwise); `ESPLORA_URL` overrides
+//! the default mempool.space API for the network. The node finds address UTXOs without a wallet by scanning its
+//! UTXO set.
+//!
+//! A broadcast the network refuses fails with a `BroadcastRejection` carrying
+//! the node's reason; the node backend runs `testmempoolaccept` first, so
+//! nothing is relayed unless the mempool will take it. It also passes an
+//! explicit `maxfeerate` (`BITCOIN_MAX_FEE_RATE` sat/vB, default 1000).
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::Result;
+use axum::async_trait;
+use bitcoin::{Address, Block, Network, OutPoint, Txid};
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::services::bitcoin::{BitcoinRpcClient, TxOutInfo, UnspentOutput, UtxoScan};
+use crate::services::esplora::EsploraClient;
+use crate::services::fees::btc_per_kvb_to_sat_per_vb;
+use crate::services::mock_chain::MockChain;
+
+/// Kind of chain backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChainKind {
+    /// Bitcoin Core over JSON-RPC
+    Node,
+    /// Esplora-compatible HTTP API
+    Esplora,
+    /// In-process simulation for mock mode
+    Mock,
+}
+
+/// Where a transaction is on the chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TxStatus {
+    /// 0 while in the mempool
+    pub confirmations: u32,
+    pub block_hash: Option<String>,
+    pub block_height: Option<u64>,
+}
+
+impl TxStatus {
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmations > 0
+    }
+}
+
+/// Why the mempool refused a transaction
+#[derive(Debug, Clone, PartialEq, Eq, Error, Serialize)]
+#[error("Transaction rejected: {reason}")]
+pub struct BroadcastRejection {
+    /// Stable class of the reason clients can branch on
+    pub code: &'static str,
+    /// The node's reject reason, e.g. `min relay fee not met`
+    pub reason: String,
+}
+
+impl BroadcastRejection {
+    /// Classify a node reject reason
+    pub fn new(reason: &str) -> Self {
+        let code = if reason.starts_with("max-fee-exceeded") || reason.starts_with("absurdly-high-fee") {
+            "fee_too_high"
+        } else if reason.contains("fee") {
+            "insufficient_fee"
+        } else if reason.contains("missing") {
+            "missing_inputs"
+        } else if reason.contains("mempool-conflict") || reason.contains("replacement") {
+            "conflict"
+        } else if reason.starts_with("bad-") || reason.starts_with("mandatory-script-verify-flag") {
+            "invalid"
+        } else if reason.starts_with("non-mandatory-script-verify-flag")
+            || reason.starts_with("scriptpubkey")
+            || reason.starts_with("scriptsig")
+            || reason.starts_with("dust")
+            || reason.starts_with("tx-size")
+            || reason.starts_with("version")
+            || reason.starts_with("multi-op-return")
+            || reason.starts_with("bare-multisig")
+        {
+            "non_standard"
+        } else {
+            "rejected"
+        };
+        Self { code, reason: reason.to_string() }
+    }
+
+    /// A transaction refused before relaying for paying `rate` sat/vB,
+    /// above `max_rate`
+    pub fn fee_too_high(rate: f64, max_rate: f64) -> Self {
+        Self {
+            code: "fee_too_high",
+            reason: format!("fee rate {:.2} sat/vB exceeds the maximum of {:.2} sat/vB", rate, max_rate),
+        }
+    }
+
+    /// The rejection behind a failed broadcast, if that is why it failed
+    pub fn of(e: &anyhow::Error) -> Option<&Self> {
+        e.downcast_ref()
+    }
+}
+
+/// Reads chain state and relays transactions
+#[async_trait]
+pub trait ChainBackend: Send + Sync {
+    fn kind(&self) -> ChainKind;
+
+    /// Network the backend is expected to be on
+    fn network(&self) -> Network;
+
+    /// Height of the best block
+    async fn tip_height(&self) -> Result<u64>;
+
+    /// Unspent outputs at `addresses` with at least `min_conf`
+    /// confirmations. No addresses lists nothing, never the node wallet's
+    /// own UTXOs.
+    async fn list_unspent(&self, addresses: &[String], min_conf: u32) -> Result<Vec<UnspentOutput>>;
+
+    /// An unspent output; `None` when it is spent or unknown
+    async fn tx_out(&self, txid: &str, vout: u32) -> Result<Option<TxOutInfo>>;
+
+    /// Relay a signed transaction, returning its txid
+    async fn broadcast(&self, tx_hex: &str) -> Result<String>;
+
+    /// Raw transaction hex
+    async fn raw_transaction(&self, txid: &str) -> Result<String>;
+
+    /// Raw transaction hex of each of `txids`, in order. The node fetches
+    /// them all in one batched call.
+    async fn raw_transactions(&self, txids: &[String]) -> Result<Vec<String>> {
+        let mut hexes = Vec::with_capacity(txids.len());
+        for txid in txids {
+            hexes.push(self.raw_transaction(txid).await?);
+        }
+        Ok(hexes)
+    }
+
+    /// Confirmation state of a transaction; `None` when neither mined nor in
+    /// the mempool
+    async fn tx_status(&self, txid: &str) -> Result<Option<TxStatus>>;
+
+    /// `tx_status` of each of `txids`, in order, each failing on its own.
+    /// The node looks them all up in one batched call.
+    async fn tx_statuses(&self, txids: &[String]) -> Result<Vec<Result<Option<TxStatus>>>> {
+        let mut statuses = Vec::with_capacity(txids.len());
+        for txid in txids {
+            statuses.push(self.tx_status(txid).await);
+        }
+        Ok(statuses)
+    }
+
+    /// BIP37 merkle block (hex) proving the mined `txid` is in block
+    /// `block_hash`
+    async fn merkle_block(&self, txid: &str, block_hash: &str) -> Result<String>;
+
+    /// Transactions spending `outpoints` (`txid:vout`), by outpoint; unspent
+    /// ones are left out. The node only sees spends still in its mempool,
+    /// Esplora mined ones too.
+    async fn spenders(&self, outpoints: &[OutPoint]) -> Result<HashMap<OutPoint, Txid>>;
+
+    /// Fee rate in sat/vB to confirm within `conf_target` blocks; `None` when
+    /// the backend has no estimate
+    async fn fee_rate(&self, conf_target: u16) -> Result<Option<f64>>;
+
+    /// Hash of the block at `height` in the best chain
+    async fn block_hash(&self, height: u64) -> Result<String>;
+
+    /// Block with all its transactions
+    async fn block(&self, hash: &str) -> Result<Block>;
+}
+
+/// Backend selected by `CHAIN_BACKEND`, on `network`; the `node` backend
+/// shares `node`'s connections. Unset, mock mode (`MOCK_MODE`, default
+/// `true`) simulates the chain.
+pub fn from_env(network: Network, node: &BitcoinRpcClient) -> Arc<dyn ChainBackend> {
+    let mock_mode = std::env::var("MOCK_MODE").map(|v| v == "true").unwrap_or(true);
+    match std::env::var("CHAIN_BACKEND").as_deref() {
+        Ok("esplora") => Arc::new(EsploraClient::from_env(network)),
+        Ok("mock") => Arc::new(MockChain::new(network)),
+        Err(_) if mock_mode => Arc::new(MockChain::new(network)),
+        Ok(other) if other != "node" => {
+            tracing::warn!("Unknown CHAIN_BACKEND {:?}, using the Bitcoin node", other);
+            Arc::new(node.clone())
+        }
+        _ => Arc::new(node.clone()),
+    }
+}
+
+#[async_trait]
+impl ChainBackend for BitcoinRpcClient {
+    fn kind(&self) -> ChainKind {
+        ChainKind::Node
+    }
+
+    fn network(&self) -> Network {
+        BitcoinRpcClient::network(self)
+    }
+
+    async fn tip_height(&self) -> Result<u64> {
+        Ok(self.get_blockchain_info().await?.blocks)
+    }
+
+    async fn list_unspent(&self, addresses: &[String], min_conf: u32) -> Result<Vec<UnspentOutput>> {
+        if addresses.is_empty() {
+            return Ok(Vec::new());
+        }
+        if self.scans_utxos() {
+            return scan_unspent(self, addresses, min_conf).await;
+        }
+        let unspent = match BitcoinRpcClient::list_unspent(self, Some(min_conf), None).await {
+            Ok(unspent) => unspent,
+            Err(e) if is_walletless(&e) => {
+                tracing::debug!("No node wallet to list UTXOs from ({}); scanning the UTXO set", e);
+                return scan_unspent(self, addresses, min_conf).await;
+            }
+            Err(e) => return Err(e),
+        };
+        Ok(unspent
+            .into_iter()
+            .filter(|utxo| addresses.contains(&utxo.address))
+            .collect())
+    }
+
+    async fn tx_out(&self, txid: &str, vout: u32) -> Result<Option<TxOutInfo>> {
+        self.get_tx_out(txid, vout).await
+    }
+
+    async fn broadcast(&self, tx_hex: &str) -> Result<String> {
+        let preflight = self.test_mempool_accept(tx_hex).await?;
+        match preflight.reject_reason {
+            // Relaying it again is harmless and returns the txid
+            Some(reason) if !preflight.allowed && !reason.starts_with("txn-already") => {
+                Err(BroadcastRejection::new(&reason).into())
+            }
+            _ => self.send_raw_transaction(tx_hex).await,
+        }
+    }
+
+    async fn raw_transaction(&self, txid: &str) -> Result<String> {
+        let raw = self.get_raw_transaction(txid, false).await?;
+        raw.as_str()
+            .map(str::to_string)
+            .ok_or_else(|| anyhow::anyhow!("getrawtransaction did not return hex for {}", txid))
+    }
+
+    async fn raw_transactions(&self, txids: &[String]) -> Result<Vec<String>> {
+        let raws = self.get_raw_transactions(txids, false).await?;
+        txids
+            .iter()
+            .zip(raws)
+            .map(|(txid, raw)| {
+                raw?.as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| anyhow::anyhow!("getrawtransaction did not return hex for {}", txid))
+            })
+            .collect()
+    }
+
+    async fn tx_status(&self, txid: &str) -> Result<Option<TxStatus>> {
+        let raw = match self.get_raw_transaction(txid, true).await {
+            Ok(raw) => raw,
+            Err(e) if e.to_string().contains("\"code\":-5") => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let tip = match raw["confirmations"].as_u64() {
+            Some(confirmations) if confirmations > 0 => self.tip_height().await?,
+            _ => 0,
+        };
+        Ok(Some(node_tx_status(&raw, tip)))
+    }
+
+    async fn tx_statuses(&self, txids: &[String]) -> Result<Vec<Result<Option<TxStatus>>>> {
+        let raws = self.get_raw_transactions(txids, true).await?;
+        let tip = self.tip_height().await?;
+        Ok(raws
+            .into_iter()
+            .map(|raw| match raw {
+                Ok(raw) => Ok(Some(node_tx_status(&raw, tip))),
+                Err(e) if e.to_string().contains("\"code\":-5") => Ok(None),
+                Err(e) => Err(e),
+            })
+            .collect())
+    }
+
+    async fn merkle_block(&self, txid: &str, block_hash: &str) -> Result<String> {
+        self.get_tx_out_proof(txid, block_hash).await
+    }
+
+    async fn spenders(&self, outpoints: &[OutPoint]) -> Result<HashMap<OutPoint, Txid>> {
+        if outpoints.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let queried: Vec<(String, u32)> = outpoints.iter().map(|o| (o.txid.to_string(), o.vout)).collect();
+        let spending = self.get_tx_spending_prevout(&queried).await?;
+        Ok(spending
+            .into_iter()
+            .filter_map(|entry| {
+                let outpoint = OutPoint::new(Txid::from_str(&entry.txid).ok()?, entry.vout);
+                Some((outpoint, Txid::from_str(&entry.spending_txid?).ok()?))
+            })
+            .collect())
+    }
+
+    async fn fee_rate(&self, conf_target: u16) -> Result<Option<f64>> {
+        let estimate = self.estimate_smart_fee(conf_target).await?;
+        Ok(estimate.feerate.map(btc_per_kvb_to_sat_per_vb))
+    }
+
+    async fn block_hash(&self, height: u64) -> Result<String> {
+        self.get_block_hash(height).await
+    }
+
+    async fn block(&self, hash: &str) -> Result<Block> {
+        self.get_block(hash).await
+    }
+}
+
+/// Whether a wallet call failed because the node has no single wallet to
+/// use: none loaded, several loaded, or built without wallet support
+fn is_walletless(e: &anyhow::Error) -> bool {
+    let message = e.to_string();
+    ["\"code\":-18", "\"code\":-19", "\"code\":-32601"]
+        .iter()
+        .any(|code| message.contains(code))
+}
+
+/// Confirmed UTXOs at `addresses`, found by scanning the node's UTXO set
+async fn scan_unspent(node: &BitcoinRpcClient, addresses: &[String], min_conf: u32) -> Result<Vec<UnspentOutput>> {
+    // Script of each address, to attribute the outputs found; addresses
+    // for another network would fail the whole scan
+    let scripts: HashMap<String, String> = addresses
+        .iter()
+        .filter_map(|address| {
+            let parsed = Address::from_str(address).ok()?.require_network(node.network()).ok()?;
+            Some((parsed.script_pubkey().to_hex_string(), address.clone()))
+        })
+        .collect();
+    if scripts.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let descriptors: Vec<String> = scripts.values().map(|address| format!("addr({})", address)).collect();
+    let scan = node.scan_tx_out_set(&descriptors).await?;
+    if !scan.success {
+        anyhow::bail!("UTXO set scan did not complete");
+    }
+    Ok(scanned_unspent(scan, &scripts, min_conf))
+}
+
+/// `scan`'s outputs with at least `min_conf` confirmations, attributed to
+/// addresses by script
+fn scanned_unspent(scan: UtxoScan, scripts: &HashMap<String, String>, min_conf: u32) -> Vec<UnspentOutput> {
+    scan.unspents
+        .into_iter()
+        .filter_map(|utxo| {
+            let address = scripts.get(&utxo.script_pub_key)?.clone();
+            let confirmations = (scan.height + 1).saturating_sub(utxo.height) as u32;
+            (confirmations >= min_conf).then_some(UnspentOutput {
+                txid: utxo.txid,
+                vout: utxo.vout,
+                address,
+                script_pub_key: utxo.script_pub_key,
+                amount: utxo.amount,
+                confirmations,
+                spendable: true,
+            })
+        })
+        .collect()
+}
+
+/// Status of a verbose `getrawtransaction` result with the tip at `tip`
+fn node_tx_status(raw: &serde_json::Value, tip: u64) -> TxStatus {
+    let confirmations = raw["confirmations"].as_u64().unwrap_or(0) as u32;
+    let block_height = match confirmations {
+        0 => None,
+        _ => Some((tip + 1).saturating_sub(confirmations as u64)),
+    };
+    TxStatus {
+        confirmations,
+        block_hash: raw["blockhash"].as_str().map(str::to_string),
+        block_height,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::bitcoin::ScannedUtxo;
+
+    #[test]
+    fn test_broadcast_rejection_codes() {
+        let code = |reason| BroadcastRejection::new(reason).code;
+        assert_eq!(code("min relay fee not met"), "insufficient_fee");
+        assert_eq!(code("mempool min fee not met"), "insufficient_fee");
+        assert_eq!(code("bad-txns-inputs-missingorspent"), "missing_inputs");
+        assert_eq!(code("txn-mempool-conflict"), "conflict");
+        assert_eq!(code("bad-txns-in-belowout"), "invalid");
+        assert_eq!(code("mandatory-script-verify-flag-failed (Invalid Schnorr signature)"), "invalid");
+        assert_eq!(code("dust"), "non_standard");
+        assert_eq!(code("non-mandatory-script-verify-flag (Witness program hash mismatch)"), "non_standard");
+        assert_eq!(code("non-final"), "rejected");
+        assert_eq!(code("too-long-mempool-chain"), "rejected");
+
+        let e: anyhow::Error = BroadcastRejection::new("dust").into();
+        assert_eq!(BroadcastRejection::of(&e).map(|r| r.code), Some("non_standard"));
+        assert_eq!(BroadcastRejection::of(&anyhow::anyhow!("timeout")), None);
+    }
+
+    #[test]
+    fn test_scanned_unspent_attributes_outputs() {
+        let address = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+        let script = Address::from_str(address)
+            .unwrap()
+            .assume_checked()
+            .script_pubkey()
+            .to_hex_string();
+        let scripts = HashMap::from([(script.clone(), address.to_string())]);
+        let utxo = |vout, script: &str, height| ScannedUtxo {
+            txid: "aa".repeat(32),
+            vout,
+            script_pub_key: script.to_string(),
+            amount: 0.0001,
+            height,
+        };
+        let scan = UtxoScan {
+            success: true,
+            height: 100,
+            unspents: vec![utxo(0, &script, 100), utxo(1, &script, 91), utxo(2, "0014ff", 50)],
+        };
+
+        let unspent = scanned_unspent(scan, &scripts, 2);
+        // Mined at the tip has one confirmation; unknown scripts are dropped
+        assert_eq!(unspent.len(), 1);
+        assert_eq!((unspent[0].vout, unspent[0].confirmations), (1, 10));
+        assert_eq!(unspent[0].address, address);
+
+        assert!(is_walletless(&anyhow::anyhow!(
+            "RPC error: {{\"code\":-18,\"message\":\"No wallet is loaded.\"}}"
+        )));
+        assert!(!is_walletless(&anyhow::anyhow!("RPC error: {{\"code\":-5}}")));
+    }
+
+    #[tokio::test]
+    async fn test_no_addresses_lists_nothing() {
+        // Nothing listens here: an empty list must not reach the node wallet
+        let node = BitcoinRpcClient::new("http://127.0.0.1:1", Network::Regtest);
+        assert!(ChainBackend::list_unspent(&node, &[], 0).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,247 @@
+//! Short-lived cache of UTXO and confirmation lookups
+//!
+//! Pages showing the state of many orders look up the same outputs and
+//! transactions over and over. `CachedChain` wraps a chain backend and keeps
+//! its `tx_out` and `tx_status` answers for `CHAIN_CACHE_TTL_SECS` (default
+//! 10; 0 turns the cache off). A new block clears the cache, a mempool
+//! announcement drops that transaction's status, and a broadcast drops the
+//! outputs it spends, so answers do not lag changes this backend hears of.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use axum::async_trait;
+use bitcoin::consensus::encode::deserialize_hex;
+use bitcoin::{Block, Network, OutPoint, Transaction, Txid};
+
+use crate::services::bitcoin::{TxOutInfo, UnspentOutput};
+use crate::services::chain::{ChainBackend, ChainKind, TxStatus};
+use crate::services::zmq::{ChainEvent, ChainEvents};
+
+/// Entries kept before expired ones are swept out
+const SWEEP_THRESHOLD: usize = 10_000;
+
+/// Values that expire `ttl` after they were stored
+struct TtlCache<K, V> {
+    ttl: Duration,
+    entries: Mutex<HashMap<K, (Instant, V)>>,
+}
+
+impl<K: Eq + Hash, V: Clone> TtlCache<K, V> {
+    fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get(&self, key: &K) -> Option<V> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(key)
+            .filter(|(stored, _)| stored.elapsed() < self.ttl)
+            .map(|(_, value)| value.clone())
+    }
+
+    fn insert(&self, key: K, value: V) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= SWEEP_THRESHOLD {
+            entries.retain(|_, (stored, _)| stored.elapsed() < self.ttl);
+        }
+        entries.insert(key, (Instant::now(), value));
+    }
+
+    fn remove(&self, key: &K) {
+        self.entries.lock().unwrap().remove(key);
+    }
+
+    fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+/// Chain backend answering repeated UTXO and status lookups from memory
+pub struct CachedChain {
+    inner: Arc<dyn ChainBackend>,
+    tx_outs: TtlCache<(String, u32), Option<TxOutInfo>>,
+    statuses: TtlCache<String, Option<TxStatus>>,
+}
+
+impl CachedChain {
+    /// Wrap `inner` with a cache invalidated by `chain_events`; `inner`
+    /// itself when `CHAIN_CACHE_TTL_SECS` is 0
+    pub fn wrap(inner: Arc<dyn ChainBackend>, chain_events: &ChainEvents) -> Arc<dyn ChainBackend> {
+        let ttl_secs = std::env::var("CHAIN_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(10);
+        if ttl_secs == 0 {
+            return inner;
+        }
+
+        let cached = Arc::new(Self::new(inner, Duration::from_secs(ttl_secs)));
+        let mut events = chain_events.subscribe();
+        let invalidated = cached.clone();
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => invalidated.invalidate(&event),
+                    // Missed announcements may have changed anything
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => invalidated.clear(),
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        cached
+    }
+
+    fn new(inner: Arc<dyn ChainBackend>, ttl: Duration) -> Self {
+        Self {
+            inner,
+            tx_outs: TtlCache::new(ttl),
+            statuses: TtlCache::new(ttl),
+        }
+    }
+
+    fn invalidate(&self, event: &ChainEvent) {
+        match event {
+            ChainEvent::Block(_) => self.clear(),
+            ChainEvent::Transaction(txid) => self.statuses.remove(&txid.to_string()),
+        }
+    }
+
+    fn clear(&self) {
+        self.tx_outs.clear();
+        self.statuses.clear();
+    }
+}
+
+#[async_trait]
+impl ChainBackend for CachedChain {
+    fn kind(&self) -> ChainKind {
+        self.inner.kind()
+    }
+
+    fn network(&self) -> Network {
+        self.inner.network()
+    }
+
+    async fn tip_height(&self) -> Result<u64> {
+        self.inner.tip_height().await
+    }
+
+    async fn list_unspent(&self, addresses: &[String], min_conf: u32) -> Result<Vec<UnspentOutput>> {
+        self.inner.list_unspent(addresses, min_conf).await
+    }
+
+    async fn tx_out(&self, txid: &str, vout: u32) -> Result<Option<TxOutInfo>> {
+        let key = (txid.to_string(), vout);
+        if let Some(tx_out) = self.tx_outs.get(&key) {
+            return Ok(tx_out);
+        }
+        let tx_out = self.inner.tx_out(txid, vout).await?;
+        self.tx_outs.insert(key, tx_out.clone());
+        Ok(tx_out)
+    }
+
+    async fn broadcast(&self, tx_hex: &str) -> Result<String> {
+        let txid = self.inner.broadcast(tx_hex).await?;
+        self.statuses.remove(&txid);
+        if let Ok(tx) = deserialize_hex::<Transaction>(tx_hex.trim()) {
+            for input in &tx.input {
+                let spent = input.previous_output;
+                self.tx_outs.remove(&(spent.txid.to_string(), spent.vout));
+            }
+        }
+        Ok(txid)
+    }
+
+    async fn raw_transaction(&self, txid: &str) -> Result<String> {
+        self.inner.raw_transaction(txid).await
+    }
+
+    async fn raw_transactions(&self, txids: &[String]) -> Result<Vec<String>> {
+        self.inner.raw_transactions(txids).await
+    }
+
+    async fn tx_status(&self, txid: &str) -> Result<Option<TxStatus>> {
+        if let Some(status) = self.statuses.get(&txid.to_string()) {
+            return Ok(status);
+        }
+        let status = self.inner.tx_status(txid).await?;
+        self.statuses.insert(txid.to_string(), status.clone());
+        Ok(status)
+    }
+
+    async fn tx_statuses(&self, txids: &[String]) -> Result<Vec<Result<Option<TxStatus>>>> {
+        let cached: Vec<Option<Option<TxStatus>>> = txids.iter().map(|txid| self.statuses.get(txid)).collect();
+        let missing: Vec<String> = txids
+            .iter()
+            .zip(&cached)
+            .filter(|(_, status)| status.is_none())
+            .map(|(txid, _)| txid.clone())
+            .collect();
+
+        let mut fetched = self.inner.tx_statuses(&missing).await?.into_iter();
+        Ok(txids
+            .iter()
+            .zip(cached)
+            .map(|(txid, status)| match status {
+                Some(status) => Ok(status),
+                None => {
+                    let status = fetched.next().unwrap_or_else(|| Err(anyhow::anyhow!("No status for {}", txid)))?;
+                    self.statuses.insert(txid.clone(), status.clone());
+                    Ok(status)
+                }
+            })
+            .collect())
+    }
+
+    async fn merkle_block(&self, txid: &str, block_hash: &str) -> Result<String> {
+        self.inner.merkle_block(txid, block_hash).await
+    }
+
+    async fn spenders(&self, outpoints: &[OutPoint]) -> Result<HashMap<OutPoint, Txid>> {
+        self.inner.spenders(outpoints).await
+    }
+
+    async fn fee_rate(&self, conf_target: u16) -> Result<Option<f64>> {
+        self.inner.fee_rate(conf_target).await
+    }
+
+    async fn block_hash(&self, height: u64) -> Result<String> {
+        self.inner.block_hash(height).await
+    }
+
+    async fn block(&self, hash: &str) -> Result<Block> {
+        self.inner.block(hash).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ttl_cache_expiry_and_invalidation() {
+        let cache = TtlCache::new(Duration::from_millis(50));
+        cache.insert("a", Some(1));
+        cache.insert("b", None);
+        assert_eq!(cache.get(&"a"), Some(Some(1)));
+        // A cached "unknown" is still a hit
+        assert_eq!(cache.get(&"b"), Some(None));
+
+        cache.remove(&"a");
+        assert_eq!(cache.get(&"a"), None);
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(cache.get(&"b"), None);
+
+        cache.insert("c", Some(3));
+        cache.clear();
+        assert_eq!(cache.get(&"c"), None);
+    }
+}
@@ -0,0 +1,202 @@
+//! Best-block height for expiry calculations
+//!
+//! Order and escrow expiries are block heights, so a wrong tip silently
+//! shortens or stretches every deadline set from it. `ChainTip` keeps the
+//! height last read from the chain backend, refreshed on each block
+//! announcement and every `CHAIN_TIP_POLL_SECS` (default 30). A height not
+//! confirmed for `CHAIN_TIP_MAX_AGE_SECS` (default 180) is stale and is
+//! re-read before use; if that fails the tip is reported unavailable rather
+//! than guessed.
+
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::services::chain::ChainBackend;
+use crate::services::zmq::{self, ChainEvents};
+
+/// Longest expiry that may be requested, about a year of blocks
+pub const MAX_EXPIRY_BLOCKS: u64 = 52_560;
+
+/// Height `blocks` blocks past the tip, refusing zero or more than
+/// `MAX_EXPIRY_BLOCKS` so a client value cannot wrap the height
+pub fn expiry_height(tip: u64, blocks: u64) -> Result<u64, String> {
+    if blocks == 0 || blocks > MAX_EXPIRY_BLOCKS {
+        return Err(format!("expiry_blocks must be between 1 and {}", MAX_EXPIRY_BLOCKS));
+    }
+    tip.checked_add(blocks)
+        .ok_or_else(|| format!("Expiry {} blocks past height {} is out of range", blocks, tip))
+}
+
+/// Why no trustworthy tip height is known
+#[derive(Debug, Error)]
+pub enum TipError {
+    #[error("Chain tip is unknown: {0}")]
+    Unknown(String),
+    #[error("Chain tip {height} was last confirmed {age_secs}s ago: {error}")]
+    Stale { height: u64, age_secs: u64, error: String },
+}
+
+/// Tip height and how long ago the chain backend last reported it
+#[derive(Debug, Clone, Serialize)]
+pub struct TipStatus {
+    pub height: Option<u64>,
+    pub age_secs: Option<u64>,
+    pub stale: bool,
+    pub last_error: Option<String>,
+}
+
+#[derive(Default)]
+struct TipState {
+    /// Height and when it was read
+    tip: Option<(u64, Instant)>,
+    last_error: Option<String>,
+}
+
+/// Cached best-block height of the chain backend
+pub struct ChainTip {
+    chain: Arc<dyn ChainBackend>,
+    max_age: Duration,
+    state: RwLock<TipState>,
+}
+
+impl ChainTip {
+    pub fn new(chain: Arc<dyn ChainBackend>) -> Self {
+        let max_age_secs = std::env::var("CHAIN_TIP_MAX_AGE_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(180);
+        Self::with_max_age(chain, Duration::from_secs(max_age_secs))
+    }
+
+    fn with_max_age(chain: Arc<dyn ChainBackend>, max_age: Duration) -> Self {
+        Self {
+            chain,
+            max_age,
+            state: RwLock::new(TipState::default()),
+        }
+    }
+
+    /// Keep the tip current on block announcements and a polling interval
+    pub fn spawn_tracker(self: &Arc<Self>, chain_events: ChainEvents) {
+        let interval_secs = std::env::var("CHAIN_TIP_POLL_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(30);
+
+        let tip = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+            let mut blocks = chain_events.subscribe();
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = zmq::next_event(&mut blocks, zmq::is_block) => {}
+                }
+                if let Err(e) = tip.refresh().await {
+                    tracing::warn!("Could not refresh chain tip: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Current tip height, re-read from the chain backend when stale
+    pub async fn height(&self) -> Result<u64, TipError> {
+        if let Some(height) = self.fresh_height() {
+            return Ok(height);
+        }
+        self.refresh().await
+    }
+
+    /// Read the tip from the chain backend
+    async fn refresh(&self) -> Result<u64, TipError> {
+        match self.chain.tip_height().await {
+            Ok(height) => {
+                self.record(height);
+                Ok(height)
+            }
+            Err(e) => Err(self.record_failure(format!("{:#}", e))),
+        }
+    }
+
+    fn record(&self, height: u64) {
+        let mut state = self.state.write().unwrap();
+        state.tip = Some((height, Instant::now()));
+        state.last_error = None;
+    }
+
+    fn record_failure(&self, error: String) -> TipError {
+        let mut state = self.state.write().unwrap();
+        state.last_error = Some(error.clone());
+        match state.tip {
+            Some((height, read_at)) => TipError::Stale {
+                height,
+                age_secs: read_at.elapsed().as_secs(),
+                error,
+            },
+            None => TipError::Unknown(error),
+        }
+    }
+
+    fn fresh_height(&self) -> Option<u64> {
+        self.fresh_height_of(&self.state.read().unwrap())
+    }
+
+    /// Tip as last read, for health reporting
+    pub fn status(&self) -> TipStatus {
+        let state = self.state.read().unwrap();
+        TipStatus {
+            height: state.tip.map(|(height, _)| height),
+            age_secs: state.tip.map(|(_, read_at)| read_at.elapsed().as_secs()),
+            stale: self.fresh_height_of(&state).is_none(),
+            last_error: state.last_error.clone(),
+        }
+    }
+
+    fn fresh_height_of(&self, state: &TipState) -> Option<u64> {
+        state
+            .tip
+            .filter(|(_, read_at)| read_at.elapsed() < self.max_age)
+            .map(|(height, _)| height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::esplora::EsploraClient;
+    use bitcoin::Network;
+
+    #[tokio::test]
+    async fn test_stale_tip_is_not_used() {
+        // Nothing listens here, so every refresh fails
+        let chain = Arc::new(EsploraClient::new("http://127.0.0.1:1", Network::Regtest));
+        let tip = ChainTip::with_max_age(chain.clone(), Duration::from_secs(60));
+        assert!(matches!(tip.height().await, Err(TipError::Unknown(_))));
+
+        tip.record(870_000);
+        assert_eq!(tip.height().await.unwrap(), 870_000);
+        assert!(!tip.status().stale);
+
+        let tip = ChainTip::with_max_age(chain, Duration::ZERO);
+        tip.record(870_000);
+        match tip.height().await {
+            Err(TipError::Stale { height, .. }) => assert_eq!(height, 870_000),
+            other => panic!("expected a stale tip, got {:?}", other),
+        }
+        let status = tip.status();
+        assert!(status.stale && status.last_error.is_some());
+    }
+
+    #[test]
+    fn test_expiry_height_is_bounded() {
+        assert_eq!(expiry_height(870_000, 144), Ok(870_144));
+        assert_eq!(expiry_height(870_000, MAX_EXPIRY_BLOCKS), Ok(870_000 + MAX_EXPIRY_BLOCKS));
+        assert!(expiry_height(870_000, 0).is_err());
+        assert!(expiry_height(870_000, MAX_EXPIRY_BLOCKS + 1).is_err());
+        assert!(expiry_height(870_000, u64::MAX).is_err());
+        assert!(expiry_height(u64::MAX, 1).is_err());
+    }
+}
@@ -2,15 +2,183 @@
 //!
 //! Handles spell building, proving, and transaction management
 
-use anyhow::Result;
-use serde::{Deserialize, Serialize, Serializer};
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use bitcoin::consensus::encode::deserialize_hex;
+use bitcoin::{Network, OutPoint, Transaction, Txid};
+use serde::{Deserialize, Serialize};
 use serde_yaml;
-use std::collections::BTreeMap;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+use crate::services::chain::ChainBackend;
+use crate::services::funding_check::verify_funding_utxo;
+use crate::services::network;
+use crate::services::prove_check::verify_proved_txs;
+use crate::services::prover::ProverBackend;
+use crate::services::spell_schema::{lint_built_spell, Spell};
 
 /// Charms prover service
 pub struct CharmsService {
-    api_url: String,
-    mock_mode: bool,
+    /// Where spells are proved, chosen at startup
+    prover: Arc<dyn ProverBackend>,
+    /// Network spells are proved for
+    network: Network,
+    /// Chain the previous transactions of spell inputs are fetched from
+    chain: Option<Arc<dyn ChainBackend>>,
+    /// `FUNDING_MIN_CONFIRMATIONS`: confirmations a funding UTXO needs
+    funding_min_conf: u32,
+}
+
+/// Failure of a call to the prover API
+#[derive(Debug, Error)]
+pub enum ProverError {
+    /// Connection failures, 5xx and 429; worth retrying
+    #[error("Prover API unavailable: {0}")]
+    Transient(String),
+    /// The prover did not answer in time; retried like other transient errors
+    #[error("Prover timed out: {0}")]
+    Timeout(String),
+    /// The spell does not parse or is inconsistent with its transaction
+    #[error("Prover rejected the spell as invalid: {0}")]
+    InvalidSpell(String),
+    /// An app contract does not accept the spell
+    #[error("App contract check failed: {0}")]
+    ContractCheckFailed(String),
+    /// The funding UTXO cannot cover the outputs and fees
+    #[error("Funding UTXO value is insufficient: {0}")]
+    InsufficientFunding(String),
+    /// Any other rejection, e.g. an authentication or routing error
+    #[error("Prover API rejected the spell ({status}): {body}")]
+    Rejected { status: u16, body: String },
+    #[error("Prover API returned an invalid response: {0}")]
+    InvalidResponse(String),
+    #[error("Local prover failed: {0}")]
+    Local(String),
+    #[error("All prover endpoints are down after repeated failures; retry in {retry_in_secs}s")]
+    CircuitOpen { retry_in_secs: u64 },
+}
+
+impl ProverError {
+    /// Classify a rejection by the prover from its status and message, which
+    /// the hosted API and the charms CLI word the same way
+    pub fn rejected(status: u16, body: String) -> Self {
+        let message = body.to_lowercase();
+        if message.contains("insufficient") || message.contains("funding utxo value") || message.contains("not enough funds") {
+            ProverError::InsufficientFunding(body)
+        } else if message.contains("contract") || message.contains("app check") || message.contains("not satisfied") {
+            ProverError::ContractCheckFailed(body)
+        } else if matches!(status, 400 | 422) {
+            ProverError::InvalidSpell(body)
+        } else {
+            ProverError::Rejected { status, body }
+        }
+    }
+
+    pub fn is_transient(&self) -> bool {
+        matches!(self, ProverError::Transient(_) | ProverError::Timeout(_))
+    }
+
+    /// The prover could not be reached at all, as opposed to rejecting the spell
+    pub fn is_unavailable(&self) -> bool {
+        matches!(
+            self,
+            ProverError::Transient(_) | ProverError::Timeout(_) | ProverError::CircuitOpen { .. }
+        )
+    }
+
+    /// Stable code clients can branch on
+    pub fn code(&self) -> &'static str {
+        match self {
+            ProverError::Transient(_) | ProverError::CircuitOpen { .. } => "prover_unavailable",
+            ProverError::Timeout(_) => "prover_timeout",
+            ProverError::InvalidSpell(_) => "invalid_spell",
+            ProverError::ContractCheckFailed(_) => "contract_check_failed",
+            ProverError::InsufficientFunding(_) => "insufficient_funding",
+            ProverError::Rejected { .. } | ProverError::InvalidResponse(_) | ProverError::Local(_) => "prover_error",
+        }
+    }
+}
+
+/// How often progress is re-reported while a prover is working
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Stage of a prove request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProveStage {
+    /// Gathering inputs, or waiting to retry after every prover failed
+    Queued,
+    /// A prover is working on the spell
+    Proving,
+    /// The proof is back and its transactions are being returned
+    Finalizing,
+}
+
+impl ProveStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProveStage::Queued => "queued",
+            ProveStage::Proving => "proving",
+            ProveStage::Finalizing => "finalizing",
+        }
+    }
+}
+
+/// Progress update for a prove request
+#[derive(Debug, Clone, Serialize)]
+pub struct ProveProgress {
+    pub stage: ProveStage,
+    /// Endpoint URL (or `local:<bin>`) handling the request
+    pub prover: Option<String>,
+    pub attempt: u32,
+    /// Seconds since proving started
+    pub elapsed_secs: u64,
+}
+
+/// Receives progress updates while a spell is proved
+pub type ProgressFn<'a> = &'a (dyn Fn(ProveProgress) + Send + Sync);
+
+/// Stamps progress updates with the time since the request started
+pub struct ProgressReporter<'a> {
+    sink: ProgressFn<'a>,
+    started: Instant,
+}
+
+impl<'a> ProgressReporter<'a> {
+    pub fn new(sink: ProgressFn<'a>) -> Self {
+        Self {
+            sink,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn report(&self, stage: ProveStage, prover: Option<&str>, attempt: u32) {
+        (self.sink)(ProveProgress {
+            stage,
+            prover: prover.map(str::to_string),
+            attempt,
+            elapsed_secs: self.started.elapsed().as_secs(),
+        });
+    }
+}
+
+/// Await `work`, calling `beat` now and then every `PROGRESS_INTERVAL` until it
+/// finishes, so a long proof visibly keeps advancing
+pub(crate) async fn with_heartbeat<T>(work: impl Future<Output = T>, beat: impl Fn()) -> T {
+    tokio::pin!(work);
+    let mut ticker = tokio::time::interval(PROGRESS_INTERVAL);
+    loop {
+        tokio::select! {
+            output = &mut work => return output,
+            _ = ticker.tick() => beat(),
+        }
+    }
 }
 
 /// Spell prove request - sent to Charms Prover API
@@ -18,13 +186,79 @@ pub struct CharmsService {
 pub struct SpellProveRequest {
     #[serde(serialize_with = "serialize_spell")]
     pub spell: String, // YAML string that will be parsed to JSON object
+    /// App binaries keyed by verification key, sent base64-encoded
+    #[serde(serialize_with = "serialize_binaries")]
     pub binaries: BTreeMap<String, Vec<u8>>,
     pub prev_txs: Vec<String>,
-    pub funding_utxo: String,
+    pub funding_utxo: OutPoint,
     pub funding_utxo_value: u64,
     pub change_address: String,
     pub fee_rate: f64,
     pub chain: String,
+    /// Unconfirmed transaction this one replaces, which already spends the
+    /// funding UTXO
+    #[serde(skip)]
+    pub replaces: Option<String>,
+}
+
+impl SpellProveRequest {
+    /// Hex SHA-256 over everything that determines the proof: the spell
+    /// (parsed, so formatting and key order do not matter), app binary
+    /// hashes, previous transactions and funding parameters
+    pub fn cache_key(&self) -> String {
+        let spell = serde_yaml::from_str::<serde_yaml::Value>(&self.spell)
+            .ok()
+            .and_then(|value| serde_json::to_value(value).ok())
+            .map_or_else(|| self.spell.clone(), |value| value.to_string());
+        let binaries: BTreeMap<&str, String> = self
+            .binaries
+            .iter()
+            .map(|(vk, binary)| (vk.as_str(), hex::encode(Sha256::digest(binary))))
+            .collect();
+
+        let canonical = serde_json::json!({
+            "spell": spell,
+            "binaries": binaries,
+            "prev_txs": self.prev_txs,
+            "funding_utxo": self.funding_utxo,
+            "funding_utxo_value": self.funding_utxo_value,
+            "change_address": self.change_address,
+            "fee_rate": self.fee_rate,
+            "chain": self.chain,
+        });
+        hex::encode(Sha256::digest(canonical.to_string()))
+    }
+
+    /// Fail unless every app the prover has to run has a binary
+    pub fn check_binaries(&self) -> Result<()> {
+        let missing = Spell::parse(&self.spell)?.apps_missing_binaries(&self.binaries);
+        if !missing.is_empty() {
+            anyhow::bail!("No app binary for {}; build the app or send its binary", missing.join(", "));
+        }
+        Ok(())
+    }
+
+    /// Txids of the spell's inputs and references whose transactions are not
+    /// already in `prev_txs`, in spell order
+    pub fn missing_prev_txids(&self) -> Result<Vec<Txid>> {
+        let spell = Spell::parse(&self.spell)?;
+        let mut known: HashSet<Txid> = self
+            .prev_txs
+            .iter()
+            .filter_map(|hex| deserialize_hex::<Transaction>(hex).ok())
+            .map(|tx| tx.compute_txid())
+            .collect();
+
+        let mut missing = Vec::new();
+        for input in spell.ins.iter().chain(&spell.refs) {
+            let outpoint = OutPoint::from_str(&input.utxo_id)
+                .with_context(|| format!("Invalid utxo_id {}", input.utxo_id))?;
+            if known.insert(outpoint.txid) {
+                missing.push(outpoint.txid);
+            }
+        }
+        Ok(missing)
+    }
 }
 
 /// Custom serializer to convert YAML string to JSON object
@@ -40,6 +274,15 @@ where
     yaml_value.serialize(serializer)
 }
 
+/// Serialize binaries as base64 strings rather than arrays of numbers, which
+/// are several times larger
+fn serialize_binaries<S>(binaries: &BTreeMap<String, Vec<u8>>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.collect_map(binaries.iter().map(|(vk, binary)| (vk, BASE64.encode(binary))))
+}
+
 /// Transaction from prove response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProvedTransaction {
@@ -75,23 +318,97 @@ pub struct FillSpellData {
     pub maker_address: String,
     pub offer_amount: String,
     pub want_amount: String,
-    pub fill_amount: Option<String>,
+}
+
+/// Partial fill data for spell building, amounts in offer base units except
+/// `fill_want_amount`
+#[derive(Debug, Clone)]
+pub struct PartialFillSpellData {
+    pub order_utxo: String,
+    pub taker_utxo: String,
+    pub taker_pubkey: String,
+    pub taker_address: String,
+    pub fill_amount: u64,
+    /// Want units the taker pays for the fill
+    pub fill_want_amount: u64,
+    /// Filled amount before the fill
+    pub current_filled: u64,
+    /// Offer units still in the order before the fill
+    pub current_remaining: u64,
+}
+
+/// Cancel order data for spell building
+#[derive(Debug, Clone)]
+pub struct CancelSpellData {
+    pub order_utxo: String,
+    pub maker_signature: String,
+    pub filled_amount: String,
+    pub remaining_amount: String,
+}
+
+/// Escrow data for spell building
+#[derive(Debug, Clone)]
+pub struct EscrowSpellData {
+    pub escrow_id: String,
+    pub depositor_pubkey: String,
+    pub recipient_pubkey: String,
+    pub arbiter_pubkey: Option<String>,
+    pub escrow_type: u8,
+    pub token_id: String,
+    pub token_vk: String,
+    pub amount: u64,
+    pub release_hash: Option<String>,
+    pub expiry_height: u64,
+    pub created_at: u64,
+    pub order_id: Option<String>,
+    /// Funding UTXO at creation, the escrow's own UTXO afterwards
+    pub escrow_utxo: String,
+    pub escrow_address: String,
+}
+
+/// Private inputs authorizing an escrow release or refund
+#[derive(Debug, Clone, Default)]
+pub struct EscrowWitness {
+    pub signature: String,
+    pub signer_pubkey: Option<String>,
+    pub preimage: Option<String>,
+    pub reason: Option<String>,
 }
 
 impl CharmsService {
-    /// Create a new Charms service
-    pub fn new() -> Self {
-        let api_url = std::env::var("CHARMS_PROVE_API_URL")
-            .unwrap_or_else(|_| "https://v8.charms.dev/spells/prove".to_string());
-        
-        let mock_mode = std::env::var("MOCK_MODE")
-            .map(|v| v == "true")
-            .unwrap_or(true);
+    /// Create a Charms service proving with `prover` for `network`
+    pub fn new(prover: Arc<dyn ProverBackend>, network: Network) -> Self {
+        let funding_min_conf = std::env::var("FUNDING_MIN_CONFIRMATIONS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(1);
+        Self { prover, network, chain: None, funding_min_conf }
+    }
+
+    /// Fetch the previous transactions of spell inputs from this chain backend
+    pub fn with_chain(mut self, chain: Arc<dyn ChainBackend>) -> Self {
+        self.chain = Some(chain);
+        self
+    }
+
+    /// The backend spells are proved with
+    pub fn prover(&self) -> &dyn ProverBackend {
+        self.prover.as_ref()
+    }
+
+    /// Network spells are proved for
+    pub fn network(&self) -> Network {
+        self.network
+    }
 
-        Self { api_url, mock_mode }
+    /// `chain` of prove requests for this network
+    pub fn chain(&self) -> &'static str {
+        network::name(self.network)
     }
 
-    /// Build a spell from template with variable substitution
+    /// Build a spell from template with variable substitution, failing with
+    /// every placeholder left unresolved and every key the spell format does
+    /// not know
     pub fn build_spell(
         &self,
         template: &str,
@@ -103,9 +420,25 @@ impl CharmsService {
             spell = spell.replace(&format!("${{{}}}", key), value);
         }
 
+        lint_built_spell(&spell)?;
         Ok(spell)
     }
 
+    /// Merge spells built from several apps' templates into one spell proved
+    /// in a single request. App keys are namespaced per part (`$ORDER` in
+    /// part `SWAP` becomes `$SWAP_ORDER`).
+    pub fn compose_spells(&self, parts: &[(&str, &str)]) -> Result<String> {
+        let parts = parts
+            .iter()
+            .map(|(namespace, spell_yaml)| {
+                Spell::parse(spell_yaml)
+                    .with_context(|| format!("Spell {} does not parse", namespace))
+                    .map(|spell| (*namespace, spell))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(serde_yaml::to_string(&Spell::compose(&parts)?)?)
+    }
+
     /// Build create-order spell with all variables
     pub fn build_create_order_spell(
         &self,
@@ -191,119 +524,364 @@ impl CharmsService {
         self.build_spell(template, &vars)
     }
 
-    /// Prove a spell - calls Charms Prover API
-    pub async fn prove_spell(
+    /// Build partial-fill spell: the taker takes part of the order (or the
+    /// rest of a partly filled one) and the order output carries what is left
+    pub fn build_partial_fill_spell(
         &self,
-        request: SpellProveRequest,
-    ) -> Result<Vec<ProvedTransaction>> {
-        if self.mock_mode {
-            tracing::info!("Mock mode: returning simulated transaction");
-            // Return mock transactions for development
-            return Ok(vec![
-                ProvedTransaction {
-                    hex: self.generate_mock_tx_hex(),
-                    txid: format!("mock_{}", uuid::Uuid::new_v4()),
-                },
-            ]);
+        template: &str,
+        data: &PartialFillSpellData,
+        order_data: &OrderSpellData,
+        app_id: &str,
+        app_vk: &str,
+    ) -> Result<String> {
+        if data.fill_amount == 0 || data.fill_amount > data.current_remaining {
+            return Err(anyhow!(
+                "Fill of {} does not fit the {} still open",
+                data.fill_amount,
+                data.current_remaining
+            ));
         }
+        let new_remaining = data.current_remaining - data.fill_amount;
 
-        tracing::info!("Calling Charms Prover API at {}", self.api_url);
-        
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(120)) // ZK proofs take time
-            .build()?;
+        let mut vars = order_state_vars(order_data, app_id, app_vk);
+        vars.insert("order_utxo".to_string(), data.order_utxo.clone());
+        vars.insert("taker_utxo".to_string(), data.taker_utxo.clone());
+        vars.insert("taker_pubkey".to_string(), data.taker_pubkey.clone());
+        vars.insert("fill_amount".to_string(), data.fill_amount.to_string());
+        vars.insert("fill_want_amount".to_string(), data.fill_want_amount.to_string());
+        vars.insert("current_filled".to_string(), data.current_filled.to_string());
+        vars.insert("current_remaining".to_string(), data.current_remaining.to_string());
+        vars.insert("new_filled".to_string(), (data.current_filled + data.fill_amount).to_string());
+        vars.insert("new_remaining".to_string(), new_remaining.to_string());
+        // The swap app marks the order Filled once nothing is left
+        vars.insert("new_status".to_string(), if new_remaining == 0 { "1" } else { "0" }.to_string());
+        vars.insert("addr_escrow".to_string(), order_data.escrow_address.clone());
+        vars.insert("addr_maker".to_string(), order_data.maker_address.clone());
+        vars.insert("addr_taker".to_string(), data.taker_address.clone());
+
+        self.build_spell(template, &vars)
+    }
+
+    /// Build cancel-order spell, returning the order's remaining offer to
+    /// the maker
+    pub fn build_cancel_order_spell(
+        &self,
+        template: &str,
+        data: &CancelSpellData,
+        order_data: &OrderSpellData,
+        app_id: &str,
+        app_vk: &str,
+    ) -> Result<String> {
+        let mut vars = order_state_vars(order_data, app_id, app_vk);
+        vars.insert("order_utxo".to_string(), data.order_utxo.clone());
+        vars.insert("maker_signature".to_string(), data.maker_signature.clone());
+        // Partly filled orders stay Open on-chain
+        vars.insert("current_status".to_string(), "0".to_string());
+        vars.insert("filled_amount".to_string(), data.filled_amount.clone());
+        vars.insert("remaining_amount".to_string(), data.remaining_amount.clone());
+        vars.insert("addr_maker".to_string(), order_data.maker_address.clone());
+
+        self.build_spell(template, &vars)
+    }
+
+    /// Build create-escrow spell
+    pub fn build_create_escrow_spell(
+        &self,
+        template: &str,
+        data: &EscrowSpellData,
+        app_vk: &str,
+        current_height: u64,
+    ) -> Result<String> {
+        let mut vars = escrow_vars(data, app_vk);
+        vars.insert("current_height".to_string(), current_height.to_string());
         
-        let response = client
-            .post(&self.api_url)
-            .json(&request)
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error = response.text().await?;
-            anyhow::bail!("Prover API error ({}): {}", status, error);
+        // UTXOs and addresses
+        vars.insert("in_utxo_0".to_string(), data.escrow_utxo.clone());
+        vars.insert("addr_escrow".to_string(), data.escrow_address.clone());
+
+        self.build_spell(template, &vars)
+    }
+
+    /// Build release-escrow spell (escrowed tokens go to the recipient)
+    pub fn build_release_escrow_spell(
+        &self,
+        template: &str,
+        data: &EscrowSpellData,
+        app_vk: &str,
+        recipient_address: &str,
+        witness: &EscrowWitness,
+    ) -> Result<String> {
+        let mut vars = escrow_vars(data, app_vk);
+        vars.insert("escrow_utxo".to_string(), data.escrow_utxo.clone());
+        vars.insert("addr_recipient".to_string(), recipient_address.to_string());
+        vars.insert("preimage".to_string(), yaml_optional(&witness.preimage));
+        vars.insert("signature".to_string(), witness.signature.clone());
+        vars.insert("signer_pubkey".to_string(), yaml_optional(&witness.signer_pubkey));
+
+        self.build_spell(template, &vars)
+    }
+
+    /// Build refund-escrow spell (escrowed tokens return to the depositor)
+    pub fn build_refund_escrow_spell(
+        &self,
+        template: &str,
+        data: &EscrowSpellData,
+        app_vk: &str,
+        depositor_address: &str,
+        witness: &EscrowWitness,
+    ) -> Result<String> {
+        let mut vars = escrow_vars(data, app_vk);
+        vars.insert("escrow_utxo".to_string(), data.escrow_utxo.clone());
+        vars.insert("addr_depositor".to_string(), depositor_address.to_string());
+        // Free-form text, quoted so it stays a YAML string
+        vars.insert("reason".to_string(), format!("{:?}", witness.reason.clone().unwrap_or_default()));
+        vars.insert("signature".to_string(), witness.signature.clone());
+
+        self.build_spell(template, &vars)
+    }
+
+    /// Build release-milestone spell (part of the escrow goes to the recipient,
+    /// the remainder stays locked)
+    pub fn build_release_milestone_spell(
+        &self,
+        template: &str,
+        data: &EscrowSpellData,
+        app_vk: &str,
+        recipient_address: &str,
+        milestone_amount: u64,
+        witness: &EscrowWitness,
+    ) -> Result<String> {
+        if milestone_amount == 0 || milestone_amount >= data.amount {
+            anyhow::bail!("Milestone amount must be less than the held amount");
         }
 
-        let txs: Vec<ProvedTransaction> = response.json().await?;
-        tracing::info!("Received {} transactions from prover", txs.len());
-        Ok(txs)
+        let mut vars = escrow_vars(data, app_vk);
+        vars.insert("escrow_utxo".to_string(), data.escrow_utxo.clone());
+        vars.insert("addr_escrow".to_string(), data.escrow_address.clone());
+        vars.insert("addr_recipient".to_string(), recipient_address.to_string());
+        vars.insert("milestone_amount".to_string(), milestone_amount.to_string());
+        vars.insert("remaining_amount".to_string(), (data.amount - milestone_amount).to_string());
+        vars.insert("signature".to_string(), witness.signature.clone());
+        vars.insert(
+            "signer_pubkey".to_string(),
+            witness.signer_pubkey.clone().unwrap_or_default(),
+        );
+
+        self.build_spell(template, &vars)
     }
 
-    /// Generate a mock transaction hex for testing
-    fn generate_mock_tx_hex(&self) -> String {
-        // This is a valid-looking but fake transaction structure
-        // Version (4 bytes) + Input count (1 byte) + ... simplified
-        "0200000001".to_string() + 
-        &"00".repeat(32) +  // Prev txid
-        "00000000" +        // Prev vout
-        "00" +              // Script length
-        "ffffffff" +        // Sequence
-        "01" +              // Output count
-        "0000000000000000" + // Value
-        "00" +              // Script length
-        "00000000"          // Locktime
+    /// Build dispute-escrow spell (a party moves the escrow into the disputed
+    /// state, keeping its tokens locked for the arbiter)
+    pub fn build_dispute_escrow_spell(
+        &self,
+        template: &str,
+        data: &EscrowSpellData,
+        app_vk: &str,
+        evidence_hash: Option<&str>,
+        witness: &EscrowWitness,
+    ) -> Result<String> {
+        let mut vars = escrow_vars(data, app_vk);
+        vars.insert("escrow_utxo".to_string(), data.escrow_utxo.clone());
+        vars.insert("addr_escrow".to_string(), data.escrow_address.clone());
+        // Free-form text, quoted so it stays a YAML string
+        vars.insert("reason".to_string(), format!("{:?}", witness.reason.clone().unwrap_or_default()));
+        vars.insert("evidence_hash".to_string(), yaml_optional(&evidence_hash.map(str::to_string)));
+        vars.insert(
+            "initiator_pubkey".to_string(),
+            witness.signer_pubkey.clone().unwrap_or_default(),
+        );
+
+        self.build_spell(template, &vars)
     }
 
-    /// Validate a spell locally before proving
-    pub fn validate_spell(&self, spell_yaml: &str) -> Result<()> {
-        // Parse YAML
-        let spell: serde_yaml::Value = serde_yaml::from_str(spell_yaml)?;
-        
-        // Check version
-        if let Some(version) = spell.get("version") {
-            let v = version.as_u64().unwrap_or(0);
-            if v != 8 {
-                anyhow::bail!("Invalid spell version: expected 8, got {}", v);
+    /// Build resolve-dispute spell (arbiter pays the disputed escrow to the winner)
+    pub fn build_resolve_dispute_spell(
+        &self,
+        template: &str,
+        data: &EscrowSpellData,
+        app_vk: &str,
+        winner_address: &str,
+        witness: &EscrowWitness,
+    ) -> Result<String> {
+        let mut vars = escrow_vars(data, app_vk);
+        vars.insert("escrow_utxo".to_string(), data.escrow_utxo.clone());
+        vars.insert("addr_winner".to_string(), winner_address.to_string());
+        vars.insert("arbiter_signature".to_string(), witness.signature.clone());
+
+        self.build_spell(template, &vars)
+    }
+
+    /// Build the spell settling an intent (create and fill in a single
+    /// transaction): the maker's leg moving the offered tokens to the taker
+    /// composed with the taker's leg moving the wanted tokens to the maker,
+    /// both built from the transfer-token template
+    pub fn build_settle_intent_spell(
+        &self,
+        transfer_template: &str,
+        data: &FillSpellData,
+        order_data: &OrderSpellData,
+    ) -> Result<String> {
+        let leg = |token_id: &str, from_utxo: &str, amount: &str, to: &str| {
+            let vars = BTreeMap::from([
+                ("token_id".to_string(), token_id.to_string()),
+                ("token_vk".to_string(), order_data.offer_token_vk.clone()), // Assuming same VK
+                ("from_utxo".to_string(), from_utxo.to_string()),
+                ("amount".to_string(), amount.to_string()),
+                ("addr_to".to_string(), to.to_string()),
+            ]);
+            self.build_spell(transfer_template, &vars)
+        };
+
+        let maker = leg(&order_data.offer_token_id, &data.order_utxo, &data.offer_amount, &data.taker_address)?;
+        let taker = leg(&order_data.want_token_id, &data.taker_utxo, &data.want_amount, &data.maker_address)?;
+        self.compose_spells(&[("MAKER", &maker), ("TAKER", &taker)])
+    }
+
+    /// Prove a spell with the configured backend, attaching the previous
+    /// transactions of its inputs that the caller did not supply and
+    /// reporting each stage (and a heartbeat while proving) to `progress`.
+    /// Fails when the prover returns no transactions.
+    pub async fn prove_spell_with_progress(
+        &self,
+        mut request: SpellProveRequest,
+        progress: ProgressFn<'_>,
+    ) -> Result<Vec<ProvedTransaction>> {
+        let reporter = ProgressReporter::new(progress);
+        if request.chain != self.chain() {
+            anyhow::bail!("Prove request is for {}, but this server proves for {}", request.chain, self.chain());
+        }
+        network::check_address(&request.change_address, self.network).context("Invalid change address")?;
+        // Spells that do not parse are left for the prover to reject
+        let spell = Spell::parse(&request.spell).ok();
+        if let Some(spell) = &spell {
+            spell.check_addresses(self.network)?;
+        }
+
+        // Placeholder proofs need neither app binaries nor input transactions
+        if self.prover.proves_spells() {
+            request.check_binaries()?;
+            if request.replaces.is_none() {
+                self.check_funding_utxo(&request.funding_utxo, request.funding_utxo_value).await?;
             }
-        } else {
-            anyhow::bail!("Spell missing version field");
+            reporter.report(ProveStage::Queued, None, 1);
+            self.attach_prev_txs(&mut request).await?;
         }
-        
-        // Check required fields
-        if spell.get("apps").is_none() {
-            anyhow::bail!("Spell missing 'apps' field");
+
+        let txs = self.prover.prove(&request, &reporter).await?;
+        if txs.is_empty() {
+            return Err(ProverError::InvalidResponse("no transactions returned".to_string()).into());
         }
-        if spell.get("ins").is_none() {
-            anyhow::bail!("Spell missing 'ins' field");
+        // Placeholder proofs carry no spell to check
+        if let (Some(spell), true) = (&spell, self.prover.proves_spells()) {
+            verify_proved_txs(spell, &txs, self.network)
+                .map_err(|e| ProverError::InvalidResponse(format!("transactions do not match the spell: {}", e)))?;
         }
-        if spell.get("outs").is_none() {
-            anyhow::bail!("Spell missing 'outs' field");
+        Ok(txs)
+    }
+
+    /// Check the funding UTXO is unspent, worth `value` sats and confirmed
+    /// before spending minutes proving with it. Placeholder proofs and
+    /// services without a chain backend skip the check.
+    pub async fn check_funding_utxo(&self, outpoint: &OutPoint, value: u64) -> Result<()> {
+        match &self.chain {
+            Some(chain) if self.prover.proves_spells() => {
+                verify_funding_utxo(chain.as_ref(), outpoint, value, self.funding_min_conf).await
+            }
+            _ => Ok(()),
         }
-        
+    }
+
+    /// Fetch the raw transactions behind the spell's inputs and references
+    async fn attach_prev_txs(&self, request: &mut SpellProveRequest) -> Result<()> {
+        let Some(chain) = &self.chain else {
+            return Ok(());
+        };
+
+        let missing: Vec<String> = request.missing_prev_txids()?.iter().map(Txid::to_string).collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        let hexes = chain
+            .raw_transactions(&missing)
+            .await
+            .with_context(|| format!("Failed to fetch previous transactions {}", missing.join(", ")))?;
+        request.prev_txs.extend(hexes);
         Ok(())
     }
 
-    /// Check if service is in mock mode
-    pub fn is_mock_mode(&self) -> bool {
-        self.mock_mode
+    /// Validate a spell locally before proving
+    pub fn validate_spell(&self, spell_yaml: &str) -> Result<()> {
+        Spell::parse(spell_yaml)?.validate()
     }
 }
 
-/// Information about a charm on a UTXO
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CharmInfo {
-    pub app_id: String,
-    pub app_vk: String,
-    pub tag: String,
-    pub data: serde_json::Value,
+/// Render an optional spell value, using YAML `null` when absent
+fn yaml_optional(value: &Option<String>) -> String {
+    value.clone().unwrap_or_else(|| "null".to_string())
 }
 
-impl Default for CharmsService {
-    fn default() -> Self {
-        Self::new()
-    }
+/// Template variables describing the swap app and an order's on-chain
+/// state, for spells spending the order
+fn order_state_vars(order_data: &OrderSpellData, app_id: &str, app_vk: &str) -> BTreeMap<String, String> {
+    let mut vars = BTreeMap::new();
+
+    // App configuration
+    vars.insert("app_id".to_string(), app_id.to_string());
+    vars.insert("app_vk".to_string(), app_vk.to_string());
+    vars.insert("offer_token_id".to_string(), order_data.offer_token_id.clone());
+    vars.insert("offer_token_vk".to_string(), order_data.offer_token_vk.clone());
+    vars.insert("want_token_id".to_string(), order_data.want_token_id.clone());
+    vars.insert("want_token_vk".to_string(), order_data.offer_token_vk.clone()); // Assuming same VK
+
+    // Order state, as the create spell wrote it
+    vars.insert("maker_pubkey".to_string(), order_data.maker_pubkey.clone());
+    vars.insert("offer_amount".to_string(), order_data.offer_amount.clone());
+    vars.insert("want_amount".to_string(), order_data.want_amount.clone());
+    vars.insert("dest_chain".to_string(), order_data.dest_chain.to_string());
+    vars.insert("dest_address".to_string(), order_data.dest_address.clone());
+    vars.insert("expiry_height".to_string(), order_data.expiry_height.to_string());
+    vars.insert("allow_partial".to_string(), order_data.allow_partial.to_string());
+    vars.insert("min_fill_amount".to_string(), "0".to_string());
+    vars.insert("created_at".to_string(), "0".to_string());
+
+    vars
+}
+
+/// Template variables describing the escrow app and its on-chain state
+fn escrow_vars(data: &EscrowSpellData, app_vk: &str) -> BTreeMap<String, String> {
+    let mut vars = BTreeMap::new();
+
+    // App configuration (escrow identity is derived from the funding UTXO)
+    vars.insert("escrow_app_id".to_string(), data.escrow_id.clone());
+    vars.insert("escrow_app_vk".to_string(), app_vk.to_string());
+    vars.insert("token_id".to_string(), data.token_id.clone());
+    vars.insert("token_vk".to_string(), data.token_vk.clone());
+
+    // Escrow state
+    vars.insert("escrow_id".to_string(), data.escrow_id.clone());
+    vars.insert("depositor_pubkey".to_string(), data.depositor_pubkey.clone());
+    vars.insert("recipient_pubkey".to_string(), data.recipient_pubkey.clone());
+    vars.insert("arbiter_pubkey".to_string(), yaml_optional(&data.arbiter_pubkey));
+    vars.insert("escrow_type".to_string(), data.escrow_type.to_string());
+    vars.insert("amount".to_string(), data.amount.to_string());
+    vars.insert("release_hash".to_string(), yaml_optional(&data.release_hash));
+    vars.insert("expiry_height".to_string(), data.expiry_height.to_string());
+    vars.insert("created_at".to_string(), data.created_at.to_string());
+    vars.insert("order_id".to_string(), yaml_optional(&data.order_id));
+
+    vars
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::services::prover::MockProver;
 
     #[test]
     fn test_build_spell() {
-        let service = CharmsService::new();
+        let service = CharmsService::new(Arc::new(MockProver), Network::Testnet4);
         
-        let template = "version: 8\naddress: ${addr}\namount: ${amount}";
+        let template = "version: 8\napps: {}\nouts:\n  - address: ${addr}\n    amount: ${amount}";
         let mut vars = BTreeMap::new();
         vars.insert("addr".to_string(), "tb1q...".to_string());
         vars.insert("amount".to_string(), "1000".to_string());
@@ -313,16 +891,317 @@ mod tests {
         assert!(result.contains("1000"));
     }
 
+    #[test]
+    fn test_cache_key_ignores_spell_formatting() {
+        let request = |spell: &str, fee_rate: f64| SpellProveRequest {
+            spell: spell.to_string(),
+            binaries: BTreeMap::from([("vk".to_string(), vec![1, 2, 3])]),
+            prev_txs: vec![],
+            funding_utxo: "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa:0".parse().unwrap(),
+            funding_utxo_value: 10000,
+            change_address: "tb1q".to_string(),
+            fee_rate,
+            chain: "testnet4".to_string(),
+            replaces: None,
+        };
+
+        let key = request("version: 8\napps:\n  $A: n/x/y\n", 2.0).cache_key();
+        assert_eq!(key, request("apps: {$A: n/x/y}\n# comment\nversion: 8", 2.0).cache_key());
+        assert_ne!(key, request("version: 8\napps:\n  $A: n/x/y\n", 3.0).cache_key());
+        assert_ne!(key, request("version: 8\napps:\n  $A: n/x/z\n", 2.0).cache_key());
+        assert_eq!(key.len(), 64);
+
+        // Binaries go to the prover as base64
+        let json = serde_json::to_value(request("version: 8\n", 2.0)).unwrap();
+        assert_eq!(json["binaries"]["vk"], "AQID");
+    }
+
+    #[test]
+    fn test_unavailable_errors_trigger_fallback() {
+        assert!(ProverError::Transient("timeout".to_string()).is_unavailable());
+        assert!(ProverError::CircuitOpen { retry_in_secs: 5 }.is_unavailable());
+        assert!(!ProverError::CircuitOpen { retry_in_secs: 5 }.is_transient());
+        assert!(!ProverError::Rejected { status: 400, body: String::new() }.is_unavailable());
+        assert!(!ProverError::InvalidResponse("garbage".to_string()).is_unavailable());
+    }
+
+    #[test]
+    fn test_rejections_are_classified() {
+        let code = |status, body: &str| ProverError::rejected(status, body.to_string()).code();
+        assert_eq!(code(400, "Insufficient funds: funding UTXO value 1000 < 1500"), "insufficient_funding");
+        assert_eq!(code(400, "app contract not satisfied: n/abc/def"), "contract_check_failed");
+        assert_eq!(code(422, "spell: invalid type: string, expected u32"), "invalid_spell");
+        assert_eq!(code(401, "missing API key"), "prover_error");
+        assert!(ProverError::Timeout("120s".to_string()).is_transient());
+        assert!(ProverError::Timeout("120s".to_string()).is_unavailable());
+    }
+
+    #[test]
+    fn test_missing_prev_txids_skips_supplied_transactions() {
+        use bitcoin::absolute::LockTime;
+        use bitcoin::consensus::encode::serialize_hex;
+        use bitcoin::transaction::Version;
+
+        let supplied = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![],
+            output: vec![],
+        };
+        let other = "bb".repeat(32);
+        let reference = "cc".repeat(32);
+        let spell = format!(
+            "version: 8\napps:\n  $A: n/x/y\nins:\n  - utxo_id: {}:0\n  - utxo_id: {other}:0\n  - utxo_id: {other}:1\n\
+             refs:\n  - utxo_id: {reference}:2\nouts: []\n",
+            supplied.compute_txid()
+        );
+
+        let request = SpellProveRequest {
+            spell,
+            binaries: BTreeMap::new(),
+            prev_txs: vec![serialize_hex(&supplied)],
+            funding_utxo: format!("{other}:0").parse().unwrap(),
+            funding_utxo_value: 10000,
+            change_address: "tb1q".to_string(),
+            fee_rate: 2.0,
+            chain: "bitcoin".to_string(),
+            replaces: None,
+        };
+        let missing: Vec<String> = request
+            .missing_prev_txids()
+            .unwrap()
+            .iter()
+            .map(|txid| txid.to_string())
+            .collect();
+        assert_eq!(missing, vec![other, reference]);
+    }
+
+    fn order_data() -> OrderSpellData {
+        OrderSpellData {
+            maker_address: "tb1qmaker".to_string(),
+            maker_pubkey: "02aa".to_string(),
+            offer_token_id: "toad".to_string(),
+            offer_token_vk: "vk".to_string(),
+            offer_amount: "1000".to_string(),
+            want_token_id: "btc".to_string(),
+            want_amount: "500".to_string(),
+            expiry_height: 0,
+            allow_partial: false,
+            funding_utxo: "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa:0".to_string(),
+            escrow_address: "".to_string(),
+            dest_chain: 0,
+            dest_address: "tb1qmaker".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_build_settle_intent_spell() {
+        let service = CharmsService::new(Arc::new(MockProver), Network::Testnet4);
+
+        let order_data = order_data();
+        let fill_data = FillSpellData {
+            order_utxo: "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa:0".to_string(),
+            taker_utxo: "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb:1".to_string(),
+            taker_pubkey: "03bb".to_string(),
+            taker_address: "tb1qtaker".to_string(),
+            maker_address: "tb1qmaker".to_string(),
+            offer_amount: "1000".to_string(),
+            want_amount: "500".to_string(),
+        };
+
+        let template = include_str!("../../../apps/swap-app/spells/transfer-token.yaml");
+        let spell = service.build_settle_intent_spell(template, &fill_data, &order_data).unwrap();
+        assert!(!spell.contains("${"));
+        assert!(service.validate_spell(&spell).is_ok());
+
+        // One spell: the maker's input and output, then the taker's
+        let spell = Spell::parse(&spell).unwrap();
+        assert_eq!(spell.apps.keys().collect::<Vec<_>>(), vec!["$MAKER_TOKEN", "$TAKER_TOKEN"]);
+        let ins: Vec<&str> = spell.ins.iter().map(|input| input.utxo_id.as_str()).collect();
+        assert_eq!(ins, vec![fill_data.order_utxo.as_str(), fill_data.taker_utxo.as_str()]);
+        let outs: Vec<&str> = spell.outs.iter().filter_map(|output| output.address.as_deref()).collect();
+        assert_eq!(outs, vec!["tb1qtaker", "tb1qmaker"]);
+        assert_eq!(spell.outs[0].charms["$MAKER_TOKEN"], 1000);
+        assert_eq!(spell.outs[1].charms["$TAKER_TOKEN"], 500);
+    }
+
+    #[test]
+    fn test_build_cancel_order_spell() {
+        let service = CharmsService::new(Arc::new(MockProver), Network::Testnet4);
+
+        let data = CancelSpellData {
+            order_utxo: "dddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddd:0".to_string(),
+            maker_signature: "sig".to_string(),
+            filled_amount: "400".to_string(),
+            remaining_amount: "600".to_string(),
+        };
+        let template = include_str!("../../../apps/swap-app/spells/cancel-order.yaml");
+        let spell = service
+            .build_cancel_order_spell(template, &data, &order_data(), "swap", "vk")
+            .unwrap();
+        assert!(!spell.contains("${"));
+        assert!(service.validate_spell(&spell).is_ok());
+        // The remaining offer goes back to the maker
+        assert!(spell.contains("filled_amount: 400"));
+        assert_eq!(spell.matches("$OFFER: 600").count(), 2);
+    }
+
+    #[test]
+    fn test_build_partial_fill_spell() {
+        let service = CharmsService::new(Arc::new(MockProver), Network::Testnet4);
+        let template = include_str!("../../../apps/swap-app/spells/partial-fill.yaml");
+        let mut data = PartialFillSpellData {
+            order_utxo: "dddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddd:0".to_string(),
+            taker_utxo: "eeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee:1".to_string(),
+            taker_pubkey: "02bb".to_string(),
+            taker_address: "tb1qtaker".to_string(),
+            fill_amount: 300,
+            fill_want_amount: 3000,
+            current_filled: 400,
+            current_remaining: 600,
+        };
+
+        let spell = service
+            .build_partial_fill_spell(template, &data, &order_data(), "swap", "vk")
+            .unwrap();
+        assert!(!spell.contains("${"));
+        assert!(service.validate_spell(&spell).is_ok());
+        assert!(spell.contains("filled_amount: 700"));
+        assert!(spell.contains("$OFFER: 300"));
+        assert!(spell.contains("status: 0  # 1 (Filled)"));
+
+        // Taking all that is left marks the order filled
+        data.fill_amount = 600;
+        let spell = service
+            .build_partial_fill_spell(template, &data, &order_data(), "swap", "vk")
+            .unwrap();
+        assert!(spell.contains("filled_amount: 1000"));
+        assert!(spell.contains("status: 1  # 1 (Filled)"));
+
+        data.fill_amount = 601;
+        assert!(service.build_partial_fill_spell(template, &data, &order_data(), "swap", "vk").is_err());
+    }
+
+    #[test]
+    fn test_build_release_escrow_spell() {
+        let service = CharmsService::new(Arc::new(MockProver), Network::Testnet4);
+
+        let data = EscrowSpellData {
+            escrow_id: "ee".to_string(),
+            depositor_pubkey: "02aa".to_string(),
+            recipient_pubkey: "03bb".to_string(),
+            arbiter_pubkey: None,
+            escrow_type: 0,
+            token_id: "toad".to_string(),
+            token_vk: "vk".to_string(),
+            amount: 1000,
+            release_hash: None,
+            expiry_height: 900000,
+            created_at: 850000,
+            order_id: None,
+            escrow_utxo: "cccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccc:0".to_string(),
+            escrow_address: "".to_string(),
+        };
+        let witness = EscrowWitness {
+            signature: "sig".to_string(),
+            signer_pubkey: Some("02aa".to_string()),
+            ..Default::default()
+        };
+
+        let template = include_str!("../../../apps/escrow-app/spells/release-escrow.yaml");
+        let spell = service
+            .build_release_escrow_spell(template, &data, "vk", "tb1qrecipient", &witness)
+            .unwrap();
+        assert!(!spell.contains("${"));
+        assert!(service.validate_spell(&spell).is_ok());
+    }
+
+    #[test]
+    fn test_build_dispute_escrow_spell() {
+        let service = CharmsService::new(Arc::new(MockProver), Network::Testnet4);
+
+        let data = EscrowSpellData {
+            escrow_id: "ee".to_string(),
+            depositor_pubkey: "02aa".to_string(),
+            recipient_pubkey: "03bb".to_string(),
+            arbiter_pubkey: Some("02cc".to_string()),
+            escrow_type: 1,
+            token_id: "toad".to_string(),
+            token_vk: "vk".to_string(),
+            amount: 1000,
+            release_hash: None,
+            expiry_height: 900000,
+            created_at: 850000,
+            order_id: None,
+            escrow_utxo: "dddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddd:0".to_string(),
+            escrow_address: "tb1qescrow".to_string(),
+        };
+        let witness = EscrowWitness {
+            signature: "sig".to_string(),
+            signer_pubkey: Some("03bb".to_string()),
+            reason: Some("Goods not delivered".to_string()),
+            ..Default::default()
+        };
+
+        let template = include_str!("../../../apps/escrow-app/spells/dispute-escrow.yaml");
+        let spell = service
+            .build_dispute_escrow_spell(template, &data, "vk", None, &witness)
+            .unwrap();
+        assert!(!spell.contains("${"));
+        assert!(spell.contains("Goods not delivered"));
+        assert!(service.validate_spell(&spell).is_ok());
+    }
+
+    #[test]
+    fn test_build_release_milestone_spell() {
+        let service = CharmsService::new(Arc::new(MockProver), Network::Testnet4);
+
+        let data = EscrowSpellData {
+            escrow_id: "ee".to_string(),
+            depositor_pubkey: "02aa".to_string(),
+            recipient_pubkey: "03bb".to_string(),
+            arbiter_pubkey: None,
+            escrow_type: 0,
+            token_id: "toad".to_string(),
+            token_vk: "vk".to_string(),
+            amount: 1000,
+            release_hash: None,
+            expiry_height: 900000,
+            created_at: 850000,
+            order_id: None,
+            escrow_utxo: "cccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccc:0".to_string(),
+            escrow_address: "tb1qescrow".to_string(),
+        };
+        let witness = EscrowWitness {
+            signature: "sig".to_string(),
+            signer_pubkey: Some("02aa".to_string()),
+            ..Default::default()
+        };
+
+        let template = include_str!("../../../apps/escrow-app/spells/release-milestone.yaml");
+        let spell = service
+            .build_release_milestone_spell(template, &data, "vk", "tb1qrecipient", 400, &witness)
+            .unwrap();
+        assert!(!spell.contains("${"));
+        assert!(spell.contains("held_amount: 600"));
+        assert!(service.validate_spell(&spell).is_ok());
+
+        // The full remaining amount must go through a regular release
+        assert!(service
+            .build_release_milestone_spell(template, &data, "vk", "tb1qrecipient", 1000, &witness)
+            .is_err());
+    }
+
     #[test]
     fn test_validate_spell() {
-        let service = CharmsService::new();
+        let service = CharmsService::new(Arc::new(MockProver), Network::Testnet4);
         
         let valid_spell = r#"
 version: 8
 apps:
   $TOKEN: t/abc/def
 ins:
-  - utxo_id: test
+  - utxo_id: 4444444444444444444444444444444444444444444444444444444444444444:0
 outs:
   - address: test
 "#;
@@ -332,7 +1211,7 @@ outs:
 
     #[test]
     fn test_validate_spell_invalid() {
-        let service = CharmsService::new();
+        let service = CharmsService::new(Arc::new(MockProver), Network::Testnet4);
         
         let invalid_spell = "version: 7\napps: {}";
         assert!(service.validate_spell(invalid_spell).is_err());
@@ -0,0 +1,137 @@
+//! Cross-chain swap coordinator
+//!
+//! Tracks the two legs of a cross-chain swap (the Bitcoin/Charms leg on the
+//! source chain and the counterpart leg on the destination chain) through the
+//! lock → reveal → claim / refund lifecycle, and derives a combined status.
+
+use serde::{Deserialize, Serialize};
+
+/// Which side of the swap a leg belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SwapLeg {
+    Source,
+    Dest,
+}
+
+/// State of a single swap leg
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LegState {
+    /// Funds not yet locked
+    Pending,
+    /// Funds locked under the hashlock/timelock
+    Locked,
+    /// Preimage revealed on this chain
+    Revealed,
+    /// Counterparty claimed the locked funds
+    Claimed,
+    /// Timelock expired and funds returned
+    Refunded,
+}
+
+/// Event reported against a leg
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LegEvent {
+    Lock,
+    Reveal,
+    Claim,
+    Refund,
+}
+
+/// Combined status of both legs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapPhase {
+    /// Neither leg locked
+    AwaitingLocks,
+    /// One leg locked, waiting for the counterparty
+    PartiallyLocked,
+    /// Both legs locked, waiting for the preimage
+    Locked,
+    /// Preimage revealed, claims in progress
+    Claiming,
+    /// Both legs claimed
+    Completed,
+    /// At least one leg refunded with no claims
+    Refunded,
+    /// One leg claimed while the other was refunded
+    Inconsistent,
+}
+
+impl LegState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LegState::Pending => "pending",
+            LegState::Locked => "locked",
+            LegState::Revealed => "revealed",
+            LegState::Claimed => "claimed",
+            LegState::Refunded => "refunded",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "pending" => Some(LegState::Pending),
+            "locked" => Some(LegState::Locked),
+            "revealed" => Some(LegState::Revealed),
+            "claimed" => Some(LegState::Claimed),
+            "refunded" => Some(LegState::Refunded),
+            _ => None,
+        }
+    }
+
+    /// Apply an event, returning the next state if the transition is allowed
+    pub fn apply(self, event: LegEvent) -> Option<LegState> {
+        match (self, event) {
+            (LegState::Pending, LegEvent::Lock) => Some(LegState::Locked),
+            (LegState::Locked, LegEvent::Reveal) => Some(LegState::Revealed),
+            // A claim reveals the preimage as a side effect
+            (LegState::Locked, LegEvent::Claim) => Some(LegState::Claimed),
+            (LegState::Revealed, LegEvent::Claim) => Some(LegState::Claimed),
+            (LegState::Locked, LegEvent::Refund) => Some(LegState::Refunded),
+            _ => None,
+        }
+    }
+}
+
+/// Derive the combined swap phase from both leg states
+pub fn combined_phase(source: LegState, dest: LegState) -> SwapPhase {
+    use LegState::*;
+
+    match (source, dest) {
+        (Claimed, Claimed) => SwapPhase::Completed,
+        (Claimed, Refunded) | (Refunded, Claimed) => SwapPhase::Inconsistent,
+        (Refunded, _) | (_, Refunded) => SwapPhase::Refunded,
+        (Revealed, _) | (_, Revealed) | (Claimed, _) | (_, Claimed) => SwapPhase::Claiming,
+        (Locked, Locked) => SwapPhase::Locked,
+        (Pending, Pending) => SwapPhase::AwaitingLocks,
+        _ => SwapPhase::PartiallyLocked,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leg_transitions() {
+        assert_eq!(LegState::Pending.apply(LegEvent::Lock), Some(LegState::Locked));
+        assert_eq!(LegState::Locked.apply(LegEvent::Reveal), Some(LegState::Revealed));
+        assert_eq!(LegState::Revealed.apply(LegEvent::Claim), Some(LegState::Claimed));
+        assert_eq!(LegState::Pending.apply(LegEvent::Claim), None);
+        assert_eq!(LegState::Claimed.apply(LegEvent::Refund), None);
+    }
+
+    #[test]
+    fn test_combined_phase() {
+        assert_eq!(combined_phase(LegState::Pending, LegState::Pending), SwapPhase::AwaitingLocks);
+        assert_eq!(combined_phase(LegState::Locked, LegState::Pending), SwapPhase::PartiallyLocked);
+        assert_eq!(combined_phase(LegState::Locked, LegState::Locked), SwapPhase::Locked);
+        assert_eq!(combined_phase(LegState::Locked, LegState::Revealed), SwapPhase::Claiming);
+        assert_eq!(combined_phase(LegState::Claimed, LegState::Claimed), SwapPhase::Completed);
+        assert_eq!(combined_phase(LegState::Refunded, LegState::Locked), SwapPhase::Refunded);
+        assert_eq!(combined_phase(LegState::Claimed, LegState::Refunded), SwapPhase::Inconsistent);
+    }
+}
@@ -0,0 +1,230 @@
+//! Taproot script trees for escrow outputs
+//!
+//! The escrow contract enforces release, hashlock and timeout rules when a
+//! spell is proved, but a transaction spending the escrow output directly
+//! never goes through the prover. The output itself is therefore locked to a
+//! script tree whose leaves encode the same rules, with the BIP-341 NUMS
+//! point as internal key so there is no key-path spend:
+//!
+//! - Cooperative: `<H(id)> OP_DROP <depositor> OP_CHECKSIGVERIFY <recipient> OP_CHECKSIG`
+//! - Arbitrated (2-of-3 only): `<arbiter> OP_CHECKSIGVERIFY <party> OP_CHECKSIG`,
+//!   once for the recipient and once for the depositor
+//! - Hashlock (when a release hash is set): `OP_SHA256 <hash> OP_EQUALVERIFY <recipient> OP_CHECKSIG`
+//! - Refund: `<expiry_height> OP_CHECKLOCKTIMEVERIFY OP_DROP <depositor> OP_CHECKSIG`
+//!
+//! The commitment to the escrow id in the cooperative leaf keeps every
+//! escrow at its own address, like the order addresses in `addresses`.
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use bitcoin::absolute::LockTime;
+use bitcoin::opcodes::all::{
+    OP_CHECKSIG, OP_CHECKSIGVERIFY, OP_CLTV, OP_DROP, OP_EQUALVERIFY, OP_SHA256,
+};
+use bitcoin::script::Builder;
+use bitcoin::secp256k1::{Secp256k1, XOnlyPublicKey};
+use bitcoin::taproot::{LeafVersion, TaprootBuilder};
+use bitcoin::{Address, Network, ScriptBuf};
+use serde::Serialize;
+
+use crate::services::addresses::{commitment, NUMS_INTERNAL_KEY};
+
+/// Spending condition a leaf encodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EscrowBranch {
+    /// Depositor and recipient agree
+    Cooperative,
+    /// Arbiter resolves a dispute for the recipient
+    ArbiterToRecipient,
+    /// Arbiter resolves a dispute for the depositor
+    ArbiterToDepositor,
+    /// Recipient reveals the release preimage
+    Hashlock,
+    /// Depositor reclaims the funds once the expiry height is reached
+    Refund,
+}
+
+/// One leaf of the tree with the control block proving it
+#[derive(Debug, Clone, Serialize)]
+pub struct EscrowLeaf {
+    pub branch: EscrowBranch,
+    /// Tapscript (hex)
+    pub script: String,
+    /// Control block (hex) for spending through this leaf
+    pub control_block: String,
+}
+
+/// An escrow's taproot output and its leaves
+#[derive(Debug, Clone, Serialize)]
+pub struct EscrowScriptTree {
+    pub address: String,
+    pub internal_key: String,
+    pub merkle_root: String,
+    pub leaves: Vec<EscrowLeaf>,
+}
+
+/// Keys of the escrow parties
+#[derive(Debug, Clone, Copy)]
+pub struct EscrowKeys {
+    pub depositor: XOnlyPublicKey,
+    pub recipient: XOnlyPublicKey,
+    pub arbiter: Option<XOnlyPublicKey>,
+}
+
+/// Build the script tree for an escrow. `release_hash` is the hex SHA-256
+/// the recipient must reveal a preimage of; `expiry_height` must be a block
+/// height, as the refund branch checks it with `OP_CHECKLOCKTIMEVERIFY`.
+pub fn escrow_script_tree(
+    escrow_id: &str,
+    keys: &EscrowKeys,
+    release_hash: Option<&str>,
+    expiry_height: u64,
+    network: Network,
+) -> Result<EscrowScriptTree> {
+    let expiry = u32::try_from(expiry_height)
+        .ok()
+        .and_then(|height| LockTime::from_height(height).ok())
+        .ok_or_else(|| anyhow!("Expiry {} is not a block height", expiry_height))?;
+
+    let mut leaves = vec![(EscrowBranch::Cooperative, cooperative_leaf(escrow_id, keys))];
+    if let Some(arbiter) = keys.arbiter {
+        leaves.push((EscrowBranch::ArbiterToRecipient, two_key_leaf(arbiter, keys.recipient)));
+        leaves.push((EscrowBranch::ArbiterToDepositor, two_key_leaf(arbiter, keys.depositor)));
+    }
+    if let Some(hash) = release_hash {
+        leaves.push((EscrowBranch::Hashlock, hashlock_leaf(hash, keys.recipient)?));
+    }
+    leaves.push((EscrowBranch::Refund, refund_leaf(expiry, keys.depositor)));
+
+    let secp = Secp256k1::verification_only();
+    let internal = XOnlyPublicKey::from_str(NUMS_INTERNAL_KEY)?;
+    let spend_info = TaprootBuilder::with_huffman_tree(leaves.iter().map(|(_, script)| (1, script.clone())))?
+        .finalize(&secp, internal)
+        .map_err(|_| anyhow!("Incomplete taproot tree"))?;
+    let merkle_root = spend_info
+        .merkle_root()
+        .context("Taproot tree has no leaves")?;
+
+    let leaves = leaves
+        .into_iter()
+        .map(|(branch, script)| {
+            let control_block = spend_info
+                .control_block(&(script.clone(), LeafVersion::TapScript))
+                .context("Leaf missing from taproot tree")?;
+            Ok(EscrowLeaf {
+                branch,
+                script: script.to_hex_string(),
+                control_block: hex::encode(control_block.serialize()),
+            })
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(EscrowScriptTree {
+        address: Address::p2tr_tweaked(spend_info.output_key(), network).to_string(),
+        internal_key: internal.to_string(),
+        merkle_root: merkle_root.to_string(),
+        leaves,
+    })
+}
+
+fn cooperative_leaf(escrow_id: &str, keys: &EscrowKeys) -> ScriptBuf {
+    Builder::new()
+        .push_slice(commitment("escrow", escrow_id))
+        .push_opcode(OP_DROP)
+        .push_x_only_key(&keys.depositor)
+        .push_opcode(OP_CHECKSIGVERIFY)
+        .push_x_only_key(&keys.recipient)
+        .push_opcode(OP_CHECKSIG)
+        .into_script()
+}
+
+fn two_key_leaf(first: XOnlyPublicKey, second: XOnlyPublicKey) -> ScriptBuf {
+    Builder::new()
+        .push_x_only_key(&first)
+        .push_opcode(OP_CHECKSIGVERIFY)
+        .push_x_only_key(&second)
+        .push_opcode(OP_CHECKSIG)
+        .into_script()
+}
+
+fn hashlock_leaf(release_hash: &str, recipient: XOnlyPublicKey) -> Result<ScriptBuf> {
+    let hash: [u8; 32] = hex::decode(release_hash.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .context("Release hash must be a hex SHA-256")?;
+    Ok(Builder::new()
+        .push_opcode(OP_SHA256)
+        .push_slice(hash)
+        .push_opcode(OP_EQUALVERIFY)
+        .push_x_only_key(&recipient)
+        .push_opcode(OP_CHECKSIG)
+        .into_script())
+}
+
+fn refund_leaf(expiry: LockTime, depositor: XOnlyPublicKey) -> ScriptBuf {
+    Builder::new()
+        .push_lock_time(expiry)
+        .push_opcode(OP_CLTV)
+        .push_opcode(OP_DROP)
+        .push_x_only_key(&depositor)
+        .push_opcode(OP_CHECKSIG)
+        .into_script()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::secp256k1::{PublicKey, SecretKey};
+    use bitcoin::taproot::ControlBlock;
+    use sha2::{Digest, Sha256};
+
+    fn key(byte: u8) -> XOnlyPublicKey {
+        let secp = Secp256k1::new();
+        let secret = SecretKey::from_slice(&[byte; 32]).unwrap();
+        PublicKey::from_secret_key(&secp, &secret).x_only_public_key().0
+    }
+
+    #[test]
+    fn test_escrow_script_tree_branches() {
+        let keys = EscrowKeys { depositor: key(1), recipient: key(2), arbiter: None };
+        let hash = hex::encode(Sha256::digest(b"secret"));
+
+        let plain = escrow_script_tree("escrow-1", &keys, None, 900_000, Network::Testnet4).unwrap();
+        let locked = escrow_script_tree("escrow-1", &keys, Some(&hash), 900_000, Network::Testnet4).unwrap();
+        let other = escrow_script_tree("escrow-2", &keys, None, 900_000, Network::Testnet4).unwrap();
+        assert_eq!(plain.leaves.len(), 2);
+        assert_eq!(locked.leaves.len(), 3);
+        assert!(plain.address.starts_with("tb1p"));
+        assert_ne!(plain.address, locked.address);
+        assert_ne!(plain.address, other.address);
+        assert_eq!(plain.internal_key, NUMS_INTERNAL_KEY);
+
+        // The refund leaf checks the expiry height and each control block
+        // proves its leaf against the output key
+        let refund = locked.leaves.iter().find(|leaf| leaf.branch == EscrowBranch::Refund).unwrap();
+        let script = ScriptBuf::from_hex(&refund.script).unwrap();
+        assert!(script.to_asm_string().contains("OP_CLTV"));
+        let output_key = Address::from_str(&locked.address)
+            .unwrap()
+            .assume_checked()
+            .script_pubkey()
+            .as_bytes()[2..]
+            .to_vec();
+        let output_key = XOnlyPublicKey::from_slice(&output_key).unwrap();
+        for leaf in &locked.leaves {
+            let control_block = ControlBlock::decode(&hex::decode(&leaf.control_block).unwrap()).unwrap();
+            let script = ScriptBuf::from_hex(&leaf.script).unwrap();
+            assert!(control_block.verify_taproot_commitment(&Secp256k1::verification_only(), output_key, &script));
+        }
+
+        let arbitrated = EscrowKeys { arbiter: Some(key(3)), ..keys };
+        let tree = escrow_script_tree("escrow-1", &arbitrated, None, 900_000, Network::Testnet4).unwrap();
+        assert!(tree.leaves.iter().any(|leaf| leaf.branch == EscrowBranch::ArbiterToDepositor));
+
+        // Timestamps and malformed hashes are rejected
+        assert!(escrow_script_tree("escrow-1", &keys, None, 600_000_000, Network::Testnet4).is_err());
+        assert!(escrow_script_tree("escrow-1", &keys, Some("abcd"), 900_000, Network::Testnet4).is_err());
+    }
+}
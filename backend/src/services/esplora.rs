@@ -0,0 +1,365 @@
+//! Esplora HTTP chain backend
+//!
+//! Talks to an Esplora-compatible API (Blockstream's esplora, mempool.space)
+//! instead of a Bitcoin Core node. Address UTXOs, transactions, blocks, fee
+//! estimates and broadcasting all map onto its REST endpoints.
+
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use axum::async_trait;
+use bitcoin::consensus::encode::deserialize;
+use bitcoin::{Address, Block, Network, OutPoint, Txid};
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+use crate::services::bitcoin::{ScriptPubKeyInfo, TxOutInfo, UnspentOutput};
+use crate::services::chain::{BroadcastRejection, ChainBackend, ChainKind, TxStatus};
+use crate::services::network;
+
+/// Sats per BTC, for the BTC amounts node-shaped UTXOs carry
+const SATS_PER_BTC: f64 = 100_000_000.0;
+
+/// Esplora API client
+pub struct EsploraClient {
+    url: String,
+    network: Network,
+    client: reqwest::Client,
+}
+
+/// `status` of an Esplora transaction or UTXO
+#[derive(Debug, Deserialize)]
+struct EsploraStatus {
+    confirmed: bool,
+    #[serde(default)]
+    block_height: Option<u64>,
+    #[serde(default)]
+    block_hash: Option<String>,
+}
+
+/// Entry of `/address/:address/utxo`
+#[derive(Debug, Deserialize)]
+struct EsploraUtxo {
+    txid: String,
+    vout: u32,
+    status: EsploraStatus,
+    value: u64,
+}
+
+/// Output of `/tx/:txid`
+#[derive(Debug, Deserialize)]
+struct EsploraTxOut {
+    scriptpubkey: String,
+    #[serde(default)]
+    scriptpubkey_address: Option<String>,
+    value: u64,
+}
+
+/// `/tx/:txid`, the parts we read
+#[derive(Debug, Deserialize)]
+struct EsploraTx {
+    vout: Vec<EsploraTxOut>,
+    status: EsploraStatus,
+}
+
+/// `/tx/:txid/outspend/:vout`
+#[derive(Debug, Deserialize)]
+struct EsploraOutspend {
+    spent: bool,
+    /// Spending transaction
+    #[serde(default)]
+    txid: Option<String>,
+}
+
+impl EsploraClient {
+    /// Client for the API at `url` (without a trailing slash) on `network`
+    pub fn new(url: &str, network: Network) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(20))
+            .build()
+            .expect("Failed to build Esplora HTTP client");
+
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            network,
+            client,
+        }
+    }
+
+    /// Client for `ESPLORA_URL`, defaulting to mempool.space for `network`
+    pub fn from_env(network: Network) -> Self {
+        let url = std::env::var("ESPLORA_URL")
+            .ok()
+            .filter(|url| !url.is_empty())
+            .unwrap_or_else(|| network::mempool_api_url(network).to_string());
+        Self::new(&url, network)
+    }
+
+    /// GET a path; `None` on 404
+    async fn get(&self, path: &str) -> Result<Option<reqwest::Response>> {
+        let response = self
+            .client
+            .get(format!("{}{}", self.url, path))
+            .send()
+            .await
+            .with_context(|| format!("Esplora request {} failed", path))?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = response
+            .error_for_status()
+            .with_context(|| format!("Esplora request {} failed", path))?;
+        Ok(Some(response))
+    }
+
+    async fn get_text(&self, path: &str) -> Result<String> {
+        let response = self.get(path).await?.with_context(|| format!("Esplora has no {}", path))?;
+        Ok(response.text().await?.trim().to_string())
+    }
+
+    async fn get_json<T: for<'de> Deserialize<'de>>(&self, path: &str) -> Result<Option<T>> {
+        match self.get(path).await? {
+            Some(response) => Ok(Some(response.json().await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Node-shaped UTXO of an Esplora one at `address`
+    fn unspent_output(&self, address: &str, utxo: EsploraUtxo, tip: u64) -> UnspentOutput {
+        let script_pub_key = Address::from_str(address)
+            .ok()
+            .and_then(|address| address.require_network(self.network).ok())
+            .map(|address| address.script_pubkey().to_hex_string())
+            .unwrap_or_default();
+
+        UnspentOutput {
+            confirmations: confirmations(&utxo.status, tip),
+            txid: utxo.txid,
+            vout: utxo.vout,
+            address: address.to_string(),
+            script_pub_key,
+            amount: utxo.value as f64 / SATS_PER_BTC,
+            spendable: true,
+        }
+    }
+}
+
+/// Confirmations of something mined per `status`, with the best block at `tip`
+fn confirmations(status: &EsploraStatus, tip: u64) -> u32 {
+    match (status.confirmed, status.block_height) {
+        (true, Some(height)) => (tip + 1).saturating_sub(height) as u32,
+        _ => 0,
+    }
+}
+
+/// Node reject reason in an Esplora broadcast error, which wraps the node's
+/// `sendrawtransaction RPC error: {"code":-26,"message":"..."}`
+fn reject_reason(body: &str) -> String {
+    body.find('{')
+        .and_then(|start| serde_json::from_str::<serde_json::Value>(&body[start..]).ok())
+        .and_then(|error| error["message"].as_str().map(str::to_string))
+        .unwrap_or_else(|| body.trim().to_string())
+}
+
+/// Rate of the slowest estimate still within `conf_target` blocks; Esplora
+/// keys its estimates by target
+fn rate_for_target(estimates: &BTreeMap<String, f64>, conf_target: u16) -> Option<f64> {
+    estimates
+        .iter()
+        .filter_map(|(target, rate)| Some((target.parse::<u16>().ok()?, *rate)))
+        .filter(|(target, _)| *target <= conf_target.max(1))
+        .max_by_key(|(target, _)| *target)
+        .map(|(_, rate)| rate)
+}
+
+#[async_trait]
+impl ChainBackend for EsploraClient {
+    fn kind(&self) -> ChainKind {
+        ChainKind::Esplora
+    }
+
+    fn network(&self) -> Network {
+        self.network
+    }
+
+    async fn tip_height(&self) -> Result<u64> {
+        let height = self.get_text("/blocks/tip/height").await?;
+        height.parse().with_context(|| format!("Invalid tip height {:?}", height))
+    }
+
+    async fn list_unspent(&self, addresses: &[String], min_conf: u32) -> Result<Vec<UnspentOutput>> {
+        if addresses.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let tip = self.tip_height().await?;
+        let mut unspent = Vec::new();
+        for address in addresses {
+            let utxos: Vec<EsploraUtxo> = self
+                .get_json(&format!("/address/{}/utxo", address))
+                .await?
+                .unwrap_or_default();
+            unspent.extend(
+                utxos
+                    .into_iter()
+                    .map(|utxo| self.unspent_output(address, utxo, tip))
+                    .filter(|utxo| utxo.confirmations >= min_conf),
+            );
+        }
+        Ok(unspent)
+    }
+
+    async fn tx_out(&self, txid: &str, vout: u32) -> Result<Option<TxOutInfo>> {
+        let Some(outspend) = self
+            .get_json::<EsploraOutspend>(&format!("/tx/{}/outspend/{}", txid, vout))
+            .await?
+        else {
+            return Ok(None);
+        };
+        if outspend.spent {
+            return Ok(None);
+        }
+        let Some(mut tx) = self.get_json::<EsploraTx>(&format!("/tx/{}", txid)).await? else {
+            return Ok(None);
+        };
+        if (vout as usize) >= tx.vout.len() {
+            return Ok(None);
+        }
+
+        let tip = self.tip_height().await?;
+        let out = tx.vout.swap_remove(vout as usize);
+        Ok(Some(TxOutInfo {
+            confirmations: confirmations(&tx.status, tip),
+            value: out.value as f64 / SATS_PER_BTC,
+            script_pub_key: ScriptPubKeyInfo {
+                hex: out.scriptpubkey,
+                address: out.scriptpubkey_address,
+            },
+        }))
+    }
+
+    async fn broadcast(&self, tx_hex: &str) -> Result<String> {
+        let response = self
+            .client
+            .post(format!("{}/tx", self.url))
+            .body(tx_hex.trim().to_string())
+            .send()
+            .await
+            .context("Esplora broadcast failed")?;
+        let status = response.status();
+        let body = response.text().await?;
+        if status == StatusCode::BAD_REQUEST {
+            return Err(BroadcastRejection::new(&reject_reason(&body)).into());
+        }
+        if !status.is_success() {
+            anyhow::bail!("Esplora rejected the transaction ({}): {}", status, body.trim());
+        }
+        Ok(body.trim().to_string())
+    }
+
+    async fn raw_transaction(&self, txid: &str) -> Result<String> {
+        self.get_text(&format!("/tx/{}/hex", txid)).await
+    }
+
+    async fn tx_status(&self, txid: &str) -> Result<Option<TxStatus>> {
+        let Some(status) = self.get_json::<EsploraStatus>(&format!("/tx/{}/status", txid)).await? else {
+            return Ok(None);
+        };
+        let tip = match status.confirmed {
+            true => self.tip_height().await?,
+            false => 0,
+        };
+        Ok(Some(TxStatus {
+            confirmations: confirmations(&status, tip),
+            block_hash: status.block_hash,
+            block_height: status.block_height,
+        }))
+    }
+
+    async fn merkle_block(&self, txid: &str, _block_hash: &str) -> Result<String> {
+        self.get_text(&format!("/tx/{}/merkleblock-proof", txid)).await
+    }
+
+    async fn spenders(&self, outpoints: &[OutPoint]) -> Result<HashMap<OutPoint, Txid>> {
+        let mut spenders = HashMap::new();
+        for outpoint in outpoints {
+            let path = format!("/tx/{}/outspend/{}", outpoint.txid, outpoint.vout);
+            let Some(outspend) = self.get_json::<EsploraOutspend>(&path).await? else {
+                continue;
+            };
+            if let Some(txid) = outspend.txid.filter(|_| outspend.spent) {
+                spenders.insert(*outpoint, Txid::from_str(&txid)?);
+            }
+        }
+        Ok(spenders)
+    }
+
+    async fn fee_rate(&self, conf_target: u16) -> Result<Option<f64>> {
+        let estimates: BTreeMap<String, f64> = self.get_json("/fee-estimates").await?.unwrap_or_default();
+        Ok(rate_for_target(&estimates, conf_target))
+    }
+
+    async fn block_hash(&self, height: u64) -> Result<String> {
+        self.get_text(&format!("/block-height/{}", height)).await
+    }
+
+    async fn block(&self, hash: &str) -> Result<Block> {
+        let path = format!("/block/{}/raw", hash);
+        let response = self.get(&path).await?.with_context(|| format!("Esplora has no block {}", hash))?;
+        let bytes = response.bytes().await?;
+        Ok(deserialize(&bytes)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_esplora_conversions() {
+        let estimates = BTreeMap::from([
+            ("1".to_string(), 20.5),
+            ("6".to_string(), 8.0),
+            ("144".to_string(), 1.5),
+        ]);
+        assert_eq!(rate_for_target(&estimates, 1), Some(20.5));
+        // No 3-block estimate, so the faster 1-block one is used
+        assert_eq!(rate_for_target(&estimates, 3), Some(20.5));
+        assert_eq!(rate_for_target(&estimates, 10), Some(8.0));
+        assert_eq!(rate_for_target(&estimates, 1008), Some(1.5));
+        assert_eq!(rate_for_target(&BTreeMap::new(), 6), None);
+
+        let error = r#"sendrawtransaction RPC error: {"code":-26,"message":"min relay fee not met, 100 < 141"}"#;
+        assert_eq!(reject_reason(error), "min relay fee not met, 100 < 141");
+        assert_eq!(reject_reason(" bad request \n"), "bad request");
+
+        let mined = EsploraStatus {
+            confirmed: true,
+            block_height: Some(100),
+            block_hash: None,
+        };
+        assert_eq!(confirmations(&mined, 100), 1);
+        assert_eq!(confirmations(&mined, 105), 6);
+        let pending = EsploraStatus {
+            confirmed: false,
+            block_height: None,
+            block_hash: None,
+        };
+        assert_eq!(confirmations(&pending, 105), 0);
+
+        let client = EsploraClient::new("https://mempool.space/testnet4/api/", Network::Testnet4);
+        assert_eq!(client.url, "https://mempool.space/testnet4/api");
+        let address = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+        let utxo = EsploraUtxo {
+            txid: "aa".repeat(32),
+            vout: 1,
+            status: mined,
+            value: 150_000,
+        };
+        let unspent = client.unspent_output(address, utxo, 101);
+        assert_eq!((unspent.amount, unspent.confirmations), (0.0015, 2));
+        assert_eq!(unspent.script_pub_key, "0014751e76e8199196d454941c45d1b3a323f1433bd6");
+    }
+}
@@ -0,0 +1,66 @@
+//! Event bus for state-change notifications
+//!
+//! Events are fanned out over a broadcast channel to WebSocket subscribers
+//! and to the webhook dispatcher, which delivers them to the webhooks users
+//! register through the API from the job queue (`services::webhooks`).
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+/// Number of events buffered for slow subscribers before they start lagging
+const CHANNEL_CAPACITY: usize = 256;
+
+/// A state-change notification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    /// Dotted event name, e.g. `escrow.expired`
+    pub kind: String,
+    /// ID of the order/escrow the event is about
+    pub subject_id: String,
+    pub data: serde_json::Value,
+    pub timestamp: String,
+}
+
+impl Event {
+    pub fn new(kind: impl Into<String>, subject_id: impl Into<String>, data: serde_json::Value) -> Self {
+        Self {
+            kind: kind.into(),
+            subject_id: subject_id.into(),
+            data,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+/// Publishes events to WebSocket subscribers and the webhook dispatcher
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+}
+
+impl EventBus {
+    /// Create a new event bus
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self { sender }
+    }
+
+    /// Subscribe to all future events
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+
+    /// Publish an event
+    pub fn publish(&self, event: Event) {
+        tracing::debug!("Event {} for {}", event.kind, event.subject_id);
+
+        // No subscribers is not an error
+        let _ = self.sender.send(event);
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
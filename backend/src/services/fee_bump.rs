@@ -0,0 +1,267 @@
+//! Fee bumping for transactions stuck in the mempool
+//!
+//! Works out what an unconfirmed transaction actually pays, from the outputs
+//! it spends, and the rate a replacement has to pay: at least the current
+//! estimate, and enough over the original for nodes to accept it as a
+//! replace-by-fee. When the transaction cannot be replaced, e.g. because
+//! another party already signed it, a child spending one of its outputs can
+//! pay for both instead (CPFP).
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use anyhow::{bail, Context, Result};
+use bitcoin::absolute::LockTime;
+use bitcoin::consensus::encode::deserialize_hex;
+use bitcoin::transaction::Version;
+use bitcoin::{Amount, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness};
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::services::chain::ChainBackend;
+use crate::services::consolidation::{input_vbytes, output_vbytes, TX_OVERHEAD_VBYTES};
+use crate::services::fees::fee_for;
+
+/// sat/vB a replacement must add over the original (Core's default
+/// `-incrementalrelayfee`)
+pub const INCREMENTAL_RELAY_FEE: f64 = 1.0;
+
+/// Highest fee rate a bump may ask for, in sat/vB
+pub const MAX_BUMP_FEE_RATE: f64 = 1000.0;
+
+/// Fee a transaction pays
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct PaidFee {
+    pub fee_sats: u64,
+    pub vbytes: u64,
+    /// sat/vB
+    pub fee_rate: f64,
+}
+
+/// Why a transaction's fee cannot be bumped
+#[derive(Debug, Error)]
+pub enum BumpError {
+    #[error("Transaction {0} is neither mined nor in the mempool")]
+    NotInMempool(String),
+    #[error("Transaction {0} is already confirmed")]
+    Confirmed(String),
+    #[error("Transaction {txid} already pays {paid:.2} sat/vB, at or above {target:.2} sat/vB")]
+    AlreadyPays { txid: String, paid: f64, target: f64 },
+    #[error(transparent)]
+    Chain(#[from] anyhow::Error),
+}
+
+/// An unconfirmed transaction, the outputs it spends and what it pays
+#[derive(Debug, Clone)]
+pub struct StuckTransaction {
+    pub tx: Transaction,
+    pub prevouts: Vec<TxOut>,
+    pub paid: PaidFee,
+}
+
+impl StuckTransaction {
+    /// Value of the output `outpoint` if the transaction spends it
+    pub fn spent_value(&self, outpoint: &OutPoint) -> Option<u64> {
+        self.tx
+            .input
+            .iter()
+            .zip(&self.prevouts)
+            .find(|(input, _)| input.previous_output == *outpoint)
+            .map(|(_, prevout)| prevout.value.to_sat())
+    }
+}
+
+/// CPFP child transaction and what the package pays
+#[derive(Debug, Clone)]
+pub struct CpfpChild {
+    pub tx: Transaction,
+    pub input_value: u64,
+    pub output_value: u64,
+    pub fee: u64,
+    /// Estimated size once signed
+    pub vbytes: u64,
+    /// sat/vB of parent and child together
+    pub package_fee_rate: f64,
+}
+
+/// Load `txid` if it is waiting in the mempool
+pub async fn load_stuck(chain: &dyn ChainBackend, txid: &str) -> Result<StuckTransaction, BumpError> {
+    match chain.tx_status(txid).await? {
+        None => return Err(BumpError::NotInMempool(txid.to_string())),
+        Some(status) if status.is_confirmed() => return Err(BumpError::Confirmed(txid.to_string())),
+        Some(_) => {}
+    }
+
+    let hex = chain.raw_transaction(txid).await?;
+    let tx: Transaction = deserialize_hex(&hex).with_context(|| format!("Transaction {} does not decode", txid))?;
+    let prevouts = fetch_prevouts(chain, &tx).await?;
+    let paid = paid_fee(&tx, &prevouts)
+        .with_context(|| format!("Transaction {} spends less than it pays out", txid))?;
+    Ok(StuckTransaction { tx, prevouts, paid })
+}
+
+/// Fee of `tx`, whose inputs spend `prevouts` in order; `None` when its
+/// outputs are worth more than its inputs
+pub fn paid_fee(tx: &Transaction, prevouts: &[TxOut]) -> Option<PaidFee> {
+    let input_value: u64 = prevouts.iter().map(|out| out.value.to_sat()).sum();
+    let output_value: u64 = tx.output.iter().map(|out| out.value.to_sat()).sum();
+    let fee_sats = input_value.checked_sub(output_value)?;
+    let vbytes = tx.vsize() as u64;
+    Some(PaidFee {
+        fee_sats,
+        vbytes,
+        fee_rate: fee_sats as f64 / vbytes as f64,
+    })
+}
+
+/// Rate a replacement for a transaction paying `paid` sat/vB should use to
+/// reach `floor`; `None` when it already pays at least `floor`
+pub fn replacement_rate(paid: f64, floor: f64) -> Option<f64> {
+    if paid >= floor {
+        return None;
+    }
+    Some(floor.max(paid + INCREMENTAL_RELAY_FEE))
+}
+
+/// Build a child spending output `vout` of `parent` (worth `value`, locked
+/// by `script_pubkey`) to `destination`, paying enough that parent and child
+/// together reach `fee_rate`
+pub fn build_cpfp_child(
+    parent: &StuckTransaction,
+    vout: u32,
+    value: u64,
+    script_pubkey: &ScriptBuf,
+    destination: ScriptBuf,
+    fee_rate: f64,
+) -> Result<CpfpChild> {
+    let vbytes = (TX_OVERHEAD_VBYTES + input_vbytes(script_pubkey) + output_vbytes(&destination)).ceil() as u64;
+    let package_vbytes = parent.paid.vbytes + vbytes;
+    let fee = fee_for(fee_rate, package_vbytes).saturating_sub(parent.paid.fee_sats);
+    if fee == 0 {
+        bail!("The parent already pays {:.2} sat/vB", parent.paid.fee_rate);
+    }
+    let output_value = value.saturating_sub(fee);
+    if output_value < destination.minimal_non_dust().to_sat() {
+        bail!("An output worth {} sats cannot cover a {} sat fee", value, fee);
+    }
+
+    let tx = Transaction {
+        version: Version::TWO,
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::new(parent.tx.compute_txid(), vout),
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: Amount::from_sat(output_value),
+            script_pubkey: destination,
+        }],
+    };
+
+    Ok(CpfpChild {
+        tx,
+        input_value: value,
+        output_value,
+        fee,
+        vbytes,
+        package_fee_rate: (parent.paid.fee_sats + fee) as f64 / package_vbytes as f64,
+    })
+}
+
+/// Outputs spent by `tx`'s inputs, in input order
+pub async fn fetch_prevouts(chain: &dyn ChainBackend, tx: &Transaction) -> Result<Vec<TxOut>> {
+    let mut parents: HashMap<Txid, Transaction> = HashMap::new();
+    let mut prevouts = Vec::with_capacity(tx.input.len());
+    for input in &tx.input {
+        let outpoint = input.previous_output;
+        let parent = match parents.entry(outpoint.txid) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let hex = chain.raw_transaction(&outpoint.txid.to_string()).await?;
+                let parent: Transaction = deserialize_hex(&hex)
+                    .with_context(|| format!("Transaction {} does not decode", outpoint.txid))?;
+                entry.insert(parent)
+            }
+        };
+        let out = parent
+            .output
+            .get(outpoint.vout as usize)
+            .with_context(|| format!("Transaction {} has no output {}", outpoint.txid, outpoint.vout))?;
+        prevouts.push(out.clone());
+    }
+    Ok(prevouts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::hashes::Hash;
+    use bitcoin::WPubkeyHash;
+
+    fn txout(sats: u64) -> TxOut {
+        TxOut {
+            value: Amount::from_sat(sats),
+            script_pubkey: ScriptBuf::new_p2wpkh(&WPubkeyHash::all_zeros()),
+        }
+    }
+
+    #[test]
+    fn test_paid_fee_and_replacement_rate() {
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                witness: Witness::from_slice(&[[0u8; 72].as_slice(), &[2u8; 33]]),
+            }],
+            output: vec![txout(40_000)],
+        };
+        let vbytes = tx.vsize() as u64;
+
+        let paid = paid_fee(&tx, &[txout(40_000 + 2 * vbytes)]).unwrap();
+        assert_eq!((paid.fee_sats, paid.vbytes, paid.fee_rate), (2 * vbytes, vbytes, 2.0));
+        assert_eq!(paid_fee(&tx, &[txout(39_000)]), None);
+
+        assert_eq!(replacement_rate(2.0, 10.0), Some(10.0));
+        // Barely under the floor still has to add the incremental relay fee
+        assert_eq!(replacement_rate(9.5, 10.0), Some(10.5));
+        assert_eq!(replacement_rate(12.0, 10.0), None);
+    }
+
+    #[test]
+    fn test_build_cpfp_child() {
+        // 200 vB parent paying 1 sat/vB, with a 20000 sat change output
+        let parent = StuckTransaction {
+            tx: Transaction {
+                version: Version::TWO,
+                lock_time: LockTime::ZERO,
+                input: vec![],
+                output: vec![txout(5_000), txout(20_000)],
+            },
+            prevouts: vec![],
+            paid: PaidFee {
+                fee_sats: 200,
+                vbytes: 200,
+                fee_rate: 1.0,
+            },
+        };
+        let script = ScriptBuf::new_p2wpkh(&WPubkeyHash::all_zeros());
+
+        let child = build_cpfp_child(&parent, 1, 20_000, &script, script.clone(), 10.0).unwrap();
+        // 10.5 + 68 + 31 vbytes
+        assert_eq!(child.vbytes, 110);
+        // 10 sat/vB over 310 vB, less what the parent already pays
+        assert_eq!(child.fee, 3100 - 200);
+        assert_eq!(child.output_value, 20_000 - 2900);
+        assert_eq!(child.package_fee_rate, 10.0);
+        assert_eq!(child.tx.input[0].previous_output, OutPoint::new(parent.tx.compute_txid(), 1));
+
+        assert!(build_cpfp_child(&parent, 1, 20_000, &script, script.clone(), 0.5).is_err());
+        // Fee would eat the whole output
+        assert!(build_cpfp_child(&parent, 0, 3_000, &script, script.clone(), 10.0).is_err());
+    }
+}
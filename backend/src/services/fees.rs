@@ -0,0 +1,416 @@
+//! Fee estimation service
+//!
+//! Provides target-confirmation fee tiers from the chain backend (Bitcoin
+//! Core's `estimatesmartfee` or Esplora's `/fee-estimates`) or the
+//! mempool.space API, with a short-lived cache and a static fallback.
+//!
+//! The estimates also guard broadcasts: a transaction paying more than
+//! `MAX_FEE_RATE_MULTIPLE` times the fast tier is refused before it is
+//! relayed, as a fat-fingered fee cannot be taken back.
+
+use anyhow::Result;
+use bitcoin::consensus::encode::deserialize_hex;
+use bitcoin::{Network, Transaction, Txid};
+use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+use super::chain::{BroadcastRejection, ChainBackend, ChainKind};
+use super::network;
+
+/// How long a fee snapshot is reused before re-querying the source
+const CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Confirmation targets (in blocks) for each tier
+const FAST_TARGET: u16 = 1;
+const NORMAL_TARGET: u16 = 6;
+const ECONOMY_TARGET: u16 = 144;
+
+/// Typical virtual size of a charms commit transaction (funding input,
+/// commit output and change)
+pub const COMMIT_TX_VBYTES: u64 = 150;
+/// Typical virtual sizes of spell transactions, proof witness included
+pub const CREATE_ORDER_SPELL_VBYTES: u64 = 450;
+pub const FILL_ORDER_SPELL_VBYTES: u64 = 600;
+
+/// Spell a user can be quoted for before starting it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SpellOperation {
+    CreateOrder,
+    FillOrder,
+    PartialFill,
+    CancelOrder,
+    SettleIntent,
+    CreateEscrow,
+    ReleaseEscrow,
+    RefundEscrow,
+    DisputeEscrow,
+    ResolveDispute,
+    ReleaseMilestone,
+}
+
+impl SpellOperation {
+    /// Typical virtual size of the operation's spell transaction
+    pub fn spell_vbytes(&self) -> u64 {
+        match self {
+            SpellOperation::CreateOrder => CREATE_ORDER_SPELL_VBYTES,
+            SpellOperation::FillOrder => FILL_ORDER_SPELL_VBYTES,
+            // Fill plus the remainder order output
+            SpellOperation::PartialFill => 700,
+            SpellOperation::CancelOrder => 400,
+            SpellOperation::SettleIntent => 650,
+            SpellOperation::CreateEscrow => 450,
+            SpellOperation::ReleaseEscrow | SpellOperation::RefundEscrow => 500,
+            SpellOperation::DisputeEscrow => 450,
+            SpellOperation::ResolveDispute => 550,
+            SpellOperation::ReleaseMilestone => 550,
+        }
+    }
+}
+
+/// Fee priority tier
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeeTier {
+    Fast,
+    Normal,
+    Economy,
+}
+
+impl FeeTier {
+    /// Confirmation target in blocks for this tier
+    pub fn target_blocks(&self) -> u16 {
+        match self {
+            FeeTier::Fast => FAST_TARGET,
+            FeeTier::Normal => NORMAL_TARGET,
+            FeeTier::Economy => ECONOMY_TARGET,
+        }
+    }
+
+    /// Slowest tier that still confirms within `blocks`
+    pub fn for_target(blocks: u16) -> Self {
+        if blocks < NORMAL_TARGET {
+            FeeTier::Fast
+        } else if blocks < ECONOMY_TARGET {
+            FeeTier::Normal
+        } else {
+            FeeTier::Economy
+        }
+    }
+}
+
+/// Where fee estimates are sourced from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeeSource {
+    Node,
+    Esplora,
+    Mempool,
+    Fallback,
+}
+
+/// Fee rates in sat/vB for each tier
+#[derive(Debug, Clone, Serialize)]
+pub struct FeeEstimates {
+    pub fast: f64,
+    pub normal: f64,
+    pub economy: f64,
+    pub source: FeeSource,
+    pub updated_at: String,
+}
+
+impl FeeEstimates {
+    /// Fee rate for a given tier
+    pub fn rate(&self, tier: FeeTier) -> f64 {
+        match tier {
+            FeeTier::Fast => self.fast,
+            FeeTier::Normal => self.normal,
+            FeeTier::Economy => self.economy,
+        }
+    }
+}
+
+/// mempool.space `/v1/fees/recommended` response
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecommendedFees {
+    fastest_fee: f64,
+    half_hour_fee: f64,
+    hour_fee: f64,
+    economy_fee: f64,
+}
+
+/// Fee estimator with caching
+pub struct FeeEstimator {
+    source: FeeSource,
+    mempool_api_url: String,
+    fallback_rate: f64,
+    /// Highest fee rate relayed, as a multiple of the fast tier
+    max_fee_rate_multiple: f64,
+    /// sat/vB always allowed, however low the estimates are
+    max_fee_rate_floor: f64,
+    cache: RwLock<Option<(Instant, FeeEstimates)>>,
+}
+
+impl FeeEstimator {
+    /// Create a new fee estimator from environment, defaulting to the
+    /// mempool API for `network`
+    pub fn new(network: Network) -> Self {
+        let source = match std::env::var("FEE_ESTIMATOR_SOURCE").as_deref() {
+            Ok("mempool") => FeeSource::Mempool,
+            _ => FeeSource::Node,
+        };
+        let mempool_api_url = std::env::var("MEMPOOL_API_URL")
+            .unwrap_or_else(|_| network::mempool_api_url(network).to_string());
+        let fallback_rate = std::env::var("FALLBACK_FEE_RATE")
+            .ok()
+            .and_then(|r| r.parse().ok())
+            .unwrap_or(10.0);
+        let max_fee_rate_multiple = std::env::var("MAX_FEE_RATE_MULTIPLE")
+            .ok()
+            .and_then(|r| r.parse().ok())
+            .unwrap_or(10.0);
+        let max_fee_rate_floor = std::env::var("MAX_FEE_RATE_FLOOR")
+            .ok()
+            .and_then(|r| r.parse().ok())
+            .unwrap_or(50.0);
+
+        Self {
+            source,
+            mempool_api_url,
+            fallback_rate,
+            max_fee_rate_multiple,
+            max_fee_rate_floor,
+            cache: RwLock::new(None),
+        }
+    }
+
+    /// Get current fee estimates for all tiers
+    pub async fn estimate(&self, chain: &dyn ChainBackend) -> FeeEstimates {
+        if let Some((fetched_at, estimates)) = self.cache.read().await.as_ref() {
+            if fetched_at.elapsed() < CACHE_TTL {
+                return estimates.clone();
+            }
+        }
+
+        let fetched = match self.source {
+            FeeSource::Mempool => self.fetch_from_mempool().await,
+            _ => self.fetch_from_chain(chain).await,
+        };
+
+        let estimates = match fetched {
+            Ok(estimates) => estimates,
+            Err(e) => {
+                tracing::warn!("Fee estimation failed, using fallback rate: {}", e);
+                return self.fallback();
+            }
+        };
+
+        *self.cache.write().await = Some((Instant::now(), estimates.clone()));
+        estimates
+    }
+
+    /// Get the fee rate (sat/vB) for a single tier
+    pub async fn fee_rate(&self, chain: &dyn ChainBackend, tier: FeeTier) -> f64 {
+        self.estimate(chain).await.rate(tier)
+    }
+
+    /// Fee rate (sat/vB) for an arbitrary confirmation target. The chain
+    /// backend is asked directly; otherwise the matching tier is used.
+    pub async fn rate_for_target(&self, chain: &dyn ChainBackend, target: u16) -> (f64, FeeSource) {
+        if self.source == FeeSource::Node {
+            match chain.fee_rate(target).await {
+                Ok(Some(rate)) => return (rate, chain_source(chain)),
+                Ok(None) => {}
+                Err(e) => tracing::debug!("Fee estimate for {} blocks failed: {}", target, e),
+            }
+        }
+
+        let estimates = self.estimate(chain).await;
+        (estimates.rate(FeeTier::for_target(target)), estimates.source)
+    }
+
+    /// Relay a signed transaction unless its fee rate is out of line with
+    /// the estimates
+    pub async fn broadcast(&self, chain: &dyn ChainBackend, tx_hex: &str) -> Result<String> {
+        self.check_fee_rate(chain, tx_hex).await?;
+        chain.broadcast(tx_hex).await
+    }
+
+    /// Highest fee rate (sat/vB) relayed while the fast tier is `fast`
+    fn max_fee_rate(&self, fast: f64) -> f64 {
+        (fast * self.max_fee_rate_multiple).max(self.max_fee_rate_floor)
+    }
+
+    /// Refuse a transaction paying more than `max_fee_rate`. One whose
+    /// spent outputs cannot be looked up is left to the chain backend.
+    async fn check_fee_rate(&self, chain: &dyn ChainBackend, tx_hex: &str) -> Result<()> {
+        let Ok(tx) = deserialize_hex::<Transaction>(tx_hex.trim()) else {
+            return Ok(());
+        };
+        let fee = match implied_fee(chain, &tx).await {
+            Ok(fee) => fee,
+            Err(e) => {
+                tracing::debug!("Cannot check the fee of {}: {:#}", tx.compute_txid(), e);
+                return Ok(());
+            }
+        };
+
+        let rate = fee as f64 / tx.vsize() as f64;
+        let max_rate = self.max_fee_rate(self.estimate(chain).await.fast);
+        if rate > max_rate {
+            return Err(BroadcastRejection::fee_too_high(rate, max_rate).into());
+        }
+        Ok(())
+    }
+
+    /// Ask the chain backend for each tier
+    async fn fetch_from_chain(&self, chain: &dyn ChainBackend) -> Result<FeeEstimates> {
+        let fast = chain.fee_rate(FAST_TARGET).await?;
+        let normal = chain.fee_rate(NORMAL_TARGET).await?;
+        let economy = chain.fee_rate(ECONOMY_TARGET).await?;
+
+        let sat_vb = |fee_rate: Option<f64>| {
+            fee_rate.ok_or_else(|| anyhow::anyhow!("Chain backend has insufficient data for fee estimation"))
+        };
+
+        Ok(FeeEstimates {
+            fast: sat_vb(fast)?,
+            normal: sat_vb(normal)?,
+            economy: sat_vb(economy)?,
+            source: chain_source(chain),
+            updated_at: chrono::Utc::now().to_rfc3339(),
+        })
+    }
+
+    /// Query the mempool.space recommended fees endpoint
+    async fn fetch_from_mempool(&self) -> Result<FeeEstimates> {
+        let url = format!("{}/v1/fees/recommended", self.mempool_api_url);
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()?;
+
+        let fees: RecommendedFees = client.get(&url).send().await?.error_for_status()?.json().await?;
+
+        Ok(FeeEstimates {
+            fast: fees.fastest_fee,
+            normal: fees.half_hour_fee.max(fees.hour_fee),
+            economy: fees.economy_fee,
+            source: FeeSource::Mempool,
+            updated_at: chrono::Utc::now().to_rfc3339(),
+        })
+    }
+
+    /// Static estimates used when no source is reachable
+    fn fallback(&self) -> FeeEstimates {
+        FeeEstimates {
+            fast: self.fallback_rate,
+            normal: self.fallback_rate,
+            economy: self.fallback_rate,
+            source: FeeSource::Fallback,
+            updated_at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+/// Fee source naming the chain backend
+fn chain_source(chain: &dyn ChainBackend) -> FeeSource {
+    match chain.kind() {
+        ChainKind::Node => FeeSource::Node,
+        ChainKind::Esplora => FeeSource::Esplora,
+        ChainKind::Mock => FeeSource::Fallback,
+    }
+}
+
+/// Sats `tx` pays: the value of the outputs it spends less the value of its
+/// outputs
+async fn implied_fee(chain: &dyn ChainBackend, tx: &Transaction) -> Result<u64> {
+    let txids: Vec<String> = tx
+        .input
+        .iter()
+        .map(|input| input.previous_output.txid.to_string())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let prev_txs = chain
+        .raw_transactions(&txids)
+        .await?
+        .iter()
+        .map(|hex| deserialize_hex::<Transaction>(hex).map(|prev| (prev.compute_txid(), prev)))
+        .collect::<Result<HashMap<Txid, Transaction>, _>>()?;
+
+    let mut spent = 0u64;
+    for input in &tx.input {
+        let outpoint = input.previous_output;
+        let prev_out = prev_txs
+            .get(&outpoint.txid)
+            .and_then(|prev| prev.output.get(outpoint.vout as usize))
+            .ok_or_else(|| anyhow::anyhow!("Spent output {} not found", outpoint))?;
+        spent += prev_out.value.to_sat();
+    }
+    let created: u64 = tx.output.iter().map(|out| out.value.to_sat()).sum();
+    spent
+        .checked_sub(created)
+        .ok_or_else(|| anyhow::anyhow!("Outputs are worth more than the spent outputs"))
+}
+
+/// Convert a BTC/kvB rate (as returned by Core) to sat/vB
+pub fn btc_per_kvb_to_sat_per_vb(rate: f64) -> f64 {
+    // 1 BTC/kvB = 100_000_000 sat / 1000 vB
+    (rate * 100_000.0 * 100.0).round() / 100.0
+}
+
+/// Convert a sat/vB rate to BTC/kvB, as Core takes it
+pub fn sat_per_vb_to_btc_per_kvb(rate: f64) -> f64 {
+    rate / 100_000.0
+}
+
+/// Fee in sats for a transaction of `vbytes` at `rate` sat/vB, rounded up
+pub fn fee_for(rate: f64, vbytes: u64) -> u64 {
+    (rate * vbytes as f64).ceil() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_btc_per_kvb_conversion() {
+        assert_eq!(btc_per_kvb_to_sat_per_vb(0.0001), 10.0);
+        assert_eq!(btc_per_kvb_to_sat_per_vb(0.00001234), 1.23);
+        assert_eq!(sat_per_vb_to_btc_per_kvb(1000.0), 0.01);
+    }
+
+    #[test]
+    fn test_max_fee_rate() {
+        let mut fees = FeeEstimator::new(Network::Regtest);
+        fees.max_fee_rate_multiple = 10.0;
+        fees.max_fee_rate_floor = 50.0;
+        assert_eq!(fees.max_fee_rate(20.0), 200.0);
+        // Quiet mempools still allow a modest bump
+        assert_eq!(fees.max_fee_rate(1.0), 50.0);
+
+        let rejection = BroadcastRejection::fee_too_high(512.3, 200.0);
+        assert_eq!(rejection.code, "fee_too_high");
+        assert_eq!(BroadcastRejection::new("max-fee-exceeded").code, "fee_too_high");
+    }
+
+    #[test]
+    fn test_tier_targets() {
+        assert_eq!(FeeTier::Fast.target_blocks(), 1);
+        assert!(FeeTier::Normal.target_blocks() < FeeTier::Economy.target_blocks());
+    }
+
+    #[test]
+    fn test_tier_for_target_and_fee() {
+        assert_eq!(FeeTier::for_target(1), FeeTier::Fast);
+        assert_eq!(FeeTier::for_target(3), FeeTier::Fast);
+        assert_eq!(FeeTier::for_target(6), FeeTier::Normal);
+        assert_eq!(FeeTier::for_target(1008), FeeTier::Economy);
+
+        assert_eq!(fee_for(2.5, 151), 378);
+        assert_eq!(fee_for(10.0, COMMIT_TX_VBYTES), 1500);
+    }
+}
@@ -0,0 +1,181 @@
+//! Fill accounting for orders
+//!
+//! Works out what a taker's fill takes from an order and leaves behind,
+//! with the same rules the swap app enforces on chain: only live orders are
+//! filled, a fill of less than what is left needs `allow_partial`, and no
+//! fill takes more than what is left. The taker pays the order's want/offer
+//! price, rounded up so the maker never receives less than their price.
+
+use thiserror::Error;
+
+use crate::db::OrderRecord;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum FillError {
+    #[error("Order is {0} and cannot be filled")]
+    NotFillable(String),
+    #[error("Order offers nothing to fill")]
+    EmptyOrder,
+    #[error("Fill amount must be positive")]
+    NotPositive,
+    #[error("Order does not allow partial fills")]
+    PartialNotAllowed,
+    #[error("Fill of {amount} does not fit the {remaining} still open")]
+    ExceedsRemaining { amount: i64, remaining: i64 },
+}
+
+/// A fill worked out against an order's current state
+#[derive(Debug, Clone, PartialEq)]
+pub struct FillPlan {
+    /// Offer base units the taker receives
+    pub amount: i64,
+    /// Want base units the taker pays the maker
+    pub want_amount: i64,
+    /// Offer base units open before the fill
+    pub remaining: i64,
+    /// The order's filled amount after the fill
+    pub filled_amount: i64,
+    /// Status the order moves to: `filled` or `partiallyfilled`
+    pub status: &'static str,
+    /// Want units per offer unit
+    pub price: f64,
+}
+
+impl FillPlan {
+    /// Whether the fill takes a whole untouched order, which the fill-order
+    /// spell does by consuming it; anything else goes through a partial fill
+    pub fn takes_whole_order(&self) -> bool {
+        self.amount == self.remaining && self.filled_amount == self.amount
+    }
+
+    /// Whether nothing is left open afterwards
+    pub fn completes_order(&self) -> bool {
+        self.status == "filled"
+    }
+}
+
+/// Plan a fill of `amount` offer units of `order`, or of all that is left
+/// when `None`
+pub fn plan_fill(order: &OrderRecord, amount: Option<i64>) -> Result<FillPlan, FillError> {
+    if !matches!(order.status.as_str(), "open" | "partiallyfilled") {
+        return Err(FillError::NotFillable(order.status.clone()));
+    }
+    if order.offer_amount <= 0 {
+        return Err(FillError::EmptyOrder);
+    }
+
+    let remaining = order.offer_amount - order.filled_amount;
+    let amount = amount.unwrap_or(remaining);
+    if amount <= 0 {
+        return Err(FillError::NotPositive);
+    }
+    if amount > remaining {
+        return Err(FillError::ExceedsRemaining { amount, remaining });
+    }
+    if amount < remaining && !order.allow_partial {
+        return Err(FillError::PartialNotAllowed);
+    }
+
+    let filled_amount = order.filled_amount + amount;
+    let offer = order.offer_amount as i128;
+    let want_amount = ((amount as i128 * order.want_amount as i128 + offer - 1) / offer) as i64;
+
+    Ok(FillPlan {
+        amount,
+        want_amount,
+        remaining,
+        filled_amount,
+        status: if filled_amount == order.offer_amount { "filled" } else { "partiallyfilled" },
+        price: order.want_amount as f64 / order.offer_amount as f64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(offer: i64, want: i64, filled: i64, allow_partial: bool, status: &str) -> OrderRecord {
+        let now = chrono::Utc::now();
+        OrderRecord {
+            id: "order-1".to_string(),
+            maker_address: "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx".to_string(),
+            offer_token: "TOAD".to_string(),
+            offer_amount: offer,
+            want_token: "BTC".to_string(),
+            want_amount: want,
+            source_chain: "bitcoin".to_string(),
+            dest_chain: "bitcoin".to_string(),
+            status: status.to_string(),
+            allow_partial,
+            filled_amount: filled,
+            expiry_height: Some(850_000),
+            utxo_id: Some(format!("{}:0", "aa".repeat(32))),
+            tx_id: Some("bb".repeat(32)),
+            created_at: now,
+            updated_at: now,
+            version: 0,
+            archived_at: None,
+            maker_pubkey: None,
+            dest_address: None,
+        }
+    }
+
+    #[test]
+    fn test_full_fill() {
+        let plan = plan_fill(&order(1000, 10_000, 0, false, "open"), None).unwrap();
+        assert_eq!(plan.amount, 1000);
+        assert_eq!(plan.want_amount, 10_000);
+        assert_eq!(plan.filled_amount, 1000);
+        assert_eq!(plan.status, "filled");
+        assert_eq!(plan.price, 10.0);
+        assert!(plan.takes_whole_order());
+        assert!(plan.completes_order());
+
+        // Naming the whole amount is the same fill
+        assert_eq!(plan_fill(&order(1000, 10_000, 0, false, "open"), Some(1000)).unwrap(), plan);
+    }
+
+    #[test]
+    fn test_partial_fills() {
+        let open = order(1000, 10_000, 0, true, "open");
+        let plan = plan_fill(&open, Some(300)).unwrap();
+        assert_eq!((plan.amount, plan.want_amount, plan.remaining), (300, 3000, 1000));
+        assert_eq!(plan.filled_amount, 300);
+        assert_eq!(plan.status, "partiallyfilled");
+        assert!(!plan.takes_whole_order());
+
+        // Taking the rest of a partly filled order completes it, but is not a
+        // whole-order fill
+        let partly = order(1000, 10_000, 300, true, "partiallyfilled");
+        let rest = plan_fill(&partly, None).unwrap();
+        assert_eq!((rest.amount, rest.filled_amount, rest.status), (700, 1000, "filled"));
+        assert!(rest.completes_order());
+        assert!(!rest.takes_whole_order());
+
+        // The maker is never paid less than their price
+        let odd = plan_fill(&order(3, 10, 0, true, "open"), Some(1)).unwrap();
+        assert_eq!(odd.want_amount, 4);
+    }
+
+    #[test]
+    fn test_rejected_fills() {
+        let strict = order(1000, 10_000, 0, false, "open");
+        assert_eq!(plan_fill(&strict, Some(500)), Err(FillError::PartialNotAllowed));
+
+        let partly = order(1000, 10_000, 900, true, "partiallyfilled");
+        assert_eq!(
+            plan_fill(&partly, Some(101)),
+            Err(FillError::ExceedsRemaining { amount: 101, remaining: 100 })
+        );
+        assert_eq!(plan_fill(&partly, Some(0)), Err(FillError::NotPositive));
+        assert_eq!(plan_fill(&partly, Some(-5)), Err(FillError::NotPositive));
+
+        for status in ["filled", "cancelled", "expired", "pendingsignature", "conflicting"] {
+            assert_eq!(
+                plan_fill(&order(1000, 10_000, 0, true, status), None),
+                Err(FillError::NotFillable(status.to_string()))
+            );
+        }
+        assert_eq!(plan_fill(&order(0, 10_000, 0, true, "open"), None), Err(FillError::EmptyOrder));
+    }
+}
@@ -0,0 +1,111 @@
+//! Checks on funding UTXOs before proving
+//!
+//! Proving takes minutes, and a funding UTXO that does not exist, is already
+//! spent, is worth something other than the client claims or is not yet
+//! confirmed only shows up as a prover rejection at the end of it. The UTXO
+//! is looked up with `gettxout` (or Esplora's equivalent) first, so such
+//! requests fail straight away.
+
+use anyhow::{Context, Result};
+use bitcoin::OutPoint;
+use thiserror::Error;
+
+use crate::services::bitcoin::TxOutInfo;
+use crate::services::chain::ChainBackend;
+
+/// Why a funding UTXO cannot pay for a spell
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum FundingMismatch {
+    #[error("Funding UTXO {0} does not exist or is already spent")]
+    Unspendable(OutPoint),
+    #[error("Funding UTXO {outpoint} holds {actual} sats, not the {claimed} claimed")]
+    ValueMismatch { outpoint: OutPoint, actual: u64, claimed: u64 },
+    #[error("Funding UTXO {outpoint} has {confirmations} confirmations; {required} are required")]
+    Unconfirmed { outpoint: OutPoint, confirmations: u32, required: u32 },
+}
+
+impl FundingMismatch {
+    /// Stable code clients can branch on
+    pub fn code(&self) -> &'static str {
+        match self {
+            FundingMismatch::Unspendable(_) => "funding_utxo_unspendable",
+            FundingMismatch::ValueMismatch { .. } => "funding_value_mismatch",
+            FundingMismatch::Unconfirmed { .. } => "funding_utxo_unconfirmed",
+        }
+    }
+}
+
+/// Look `outpoint` up and check it is unspent, worth `claimed` sats and has
+/// at least `min_conf` confirmations. Mismatches fail with `FundingMismatch`.
+pub async fn verify_funding_utxo(
+    chain: &dyn ChainBackend,
+    outpoint: &OutPoint,
+    claimed: u64,
+    min_conf: u32,
+) -> Result<()> {
+    let tx_out = chain
+        .tx_out(&outpoint.txid.to_string(), outpoint.vout)
+        .await
+        .with_context(|| format!("Failed to look up funding UTXO {}", outpoint))?;
+    check_funding_output(outpoint, tx_out.as_ref(), claimed, min_conf)?;
+    Ok(())
+}
+
+fn check_funding_output(
+    outpoint: &OutPoint,
+    tx_out: Option<&TxOutInfo>,
+    claimed: u64,
+    min_conf: u32,
+) -> Result<(), FundingMismatch> {
+    let tx_out = tx_out.ok_or(FundingMismatch::Unspendable(*outpoint))?;
+
+    let actual = (tx_out.value * 100_000_000.0).round() as u64;
+    if actual != claimed {
+        return Err(FundingMismatch::ValueMismatch {
+            outpoint: *outpoint,
+            actual,
+            claimed,
+        });
+    }
+    if tx_out.confirmations < min_conf {
+        return Err(FundingMismatch::Unconfirmed {
+            outpoint: *outpoint,
+            confirmations: tx_out.confirmations,
+            required: min_conf,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::bitcoin::ScriptPubKeyInfo;
+
+    #[test]
+    fn test_check_funding_output() {
+        let outpoint: OutPoint = format!("{}:1", "ab".repeat(32)).parse().unwrap();
+        let tx_out = TxOutInfo {
+            confirmations: 2,
+            value: 0.00012345,
+            script_pub_key: ScriptPubKeyInfo {
+                hex: String::new(),
+                address: None,
+            },
+        };
+
+        assert_eq!(check_funding_output(&outpoint, Some(&tx_out), 12_345, 1), Ok(()));
+        assert_eq!(
+            check_funding_output(&outpoint, Some(&tx_out), 10_000, 1).unwrap_err().code(),
+            "funding_value_mismatch"
+        );
+        assert_eq!(
+            check_funding_output(&outpoint, Some(&tx_out), 12_345, 3).unwrap_err().code(),
+            "funding_utxo_unconfirmed"
+        );
+        assert_eq!(
+            check_funding_output(&outpoint, None, 12_345, 0).unwrap_err().code(),
+            "funding_utxo_unspendable"
+        );
+    }
+}
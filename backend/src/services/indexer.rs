@@ -0,0 +1,514 @@
+//! Chain indexer for charms
+//!
+//! Follows the chain backend block by block, decodes the spell in every
+//! transaction and keeps `charm_utxos` holding exactly the charms on unspent
+//! outputs: outputs a spell gives charms are added, and any spend of a charm
+//! output (spell or not) removes it. Wallet balances, order discovery and
+//! detecting fills made outside this backend read from that table instead of
+//! trusting our own records.
+//!
+//! The hash of every indexed block is kept for `INDEXER_REORG_DEPTH` blocks
+//! (default 100). When the best chain no longer contains the last indexed
+//! block, the indexer rolls the index back to the fork point and re-indexes
+//! the new branch. Orders and escrows whose transactions were in the
+//! orphaned blocks get an `order.reorged` / `escrow.reorged` event saying
+//! whether the transaction is mined again, back in the mempool or dropped;
+//! orders whose creating transaction was dropped need signing again.
+//! Deposits made by those transactions are marked unconfirmed, so the
+//! deposit monitor reports them again once they re-confirm.
+//!
+//! Configured with `INDEXER_ENABLED` (default `true`),
+//! `INDEXER_INTERVAL_SECS`, `INDEXER_BATCH_BLOCKS` and
+//! `INDEXER_START_HEIGHT`; without a start height a fresh database starts
+//! indexing from the current tip.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use bitcoin::{Address, Block, Network, Transaction};
+
+use crate::db::{self, CharmUtxoRecord, DbPool};
+use crate::services::chain::{ChainBackend, TxStatus};
+use crate::services::events::{Event, EventBus};
+use crate::services::zmq::{self, ChainEvents};
+use crate::services::spell_decode::{decode_spell_tx, DecodedSpell};
+
+/// Name the charm indexer's progress is stored under
+pub const INDEXER: &str = "charms";
+
+/// Charms a block spent and created; a charm created and spent within the
+/// same block is in neither
+#[derive(Debug, Default)]
+pub struct BlockCharms {
+    /// Outpoints spent by the block's transactions (`txid:vout`); only those
+    /// holding charms matter
+    pub spent: Vec<String>,
+    pub created: Vec<CharmUtxoRecord>,
+}
+
+impl BlockCharms {
+    /// Charm changes of `block`, mined at `height`
+    pub fn from_block(block: &Block, height: u64, network: Network) -> Self {
+        let mut changes = Self::default();
+        for tx in &block.txdata {
+            let spell = match decode_spell_tx(tx) {
+                Ok(spell) => spell,
+                Err(e) => {
+                    tracing::debug!("Skipping undecodable spell in {}: {}", tx.compute_txid(), e);
+                    None
+                }
+            };
+            changes.apply_tx(tx, spell.as_ref(), height, network);
+        }
+        changes
+    }
+
+    /// Add one transaction, in block order
+    fn apply_tx(&mut self, tx: &Transaction, spell: Option<&DecodedSpell>, height: u64, network: Network) {
+        if !tx.is_coinbase() {
+            for input in &tx.input {
+                let outpoint = input.previous_output.to_string();
+                let before = self.created.len();
+                self.created.retain(|charm| charm.utxo_id != outpoint);
+                if self.created.len() == before {
+                    self.spent.push(outpoint);
+                }
+            }
+        }
+
+        let Some(spell) = spell else {
+            return;
+        };
+        let now = chrono::Utc::now();
+        for out in &spell.outs {
+            let Some(output) = tx.output.get(out.index as usize) else {
+                continue;
+            };
+            let address = Address::from_script(&output.script_pubkey, network)
+                .ok()
+                .map(|address| address.to_string());
+
+            for (app, data) in &out.charms {
+                let Some((tag, app_id, vk)) = split_app(app) else {
+                    continue;
+                };
+                self.created.push(CharmUtxoRecord {
+                    utxo_id: format!("{}:{}", spell.txid, out.index),
+                    app: app.clone(),
+                    tag: tag.to_string(),
+                    app_id: app_id.to_string(),
+                    vk: vk.to_string(),
+                    address: address.clone(),
+                    value: output.value.to_sat() as i64,
+                    data: data.to_string(),
+                    block_height: height as i64,
+                    spent_height: None,
+                    created_at: now,
+                });
+            }
+        }
+    }
+
+    /// Number of charms created per app, for logging
+    fn created_by_app(&self) -> BTreeMap<&str, usize> {
+        let mut counts = BTreeMap::new();
+        for charm in &self.created {
+            *counts.entry(charm.app.as_str()).or_default() += 1;
+        }
+        counts
+    }
+}
+
+/// Tag, identity and verification key of a `tag/identity/vk` app
+fn split_app(app: &str) -> Option<(&str, &str, &str)> {
+    let mut parts = app.splitn(3, '/');
+    Some((parts.next()?, parts.next()?, parts.next()?))
+}
+
+/// Where a transaction from an orphaned block ended up
+fn reorged_status(status: Option<&TxStatus>) -> &'static str {
+    match status {
+        Some(status) if status.is_confirmed() => "confirmed",
+        Some(_) => "mempool",
+        None => "dropped",
+    }
+}
+
+/// Where each of `txids` ended up after a reorg, asking the chain in one
+/// batch; `unknown` for those the chain cannot be asked about
+async fn reorged_statuses(chain: &dyn ChainBackend, txids: &[String]) -> HashMap<String, &'static str> {
+    let statuses = match chain.tx_statuses(txids).await {
+        Ok(statuses) => statuses,
+        Err(e) => {
+            tracing::debug!("Could not look up reorged transactions: {}", e);
+            return txids.iter().map(|txid| (txid.clone(), "unknown")).collect();
+        }
+    };
+    txids
+        .iter()
+        .zip(statuses)
+        .map(|(txid, status)| {
+            let status = match status {
+                Ok(status) => reorged_status(status.as_ref()),
+                Err(e) => {
+                    tracing::debug!("Could not look up reorged transaction {}: {}", txid, e);
+                    "unknown"
+                }
+            };
+            (txid.clone(), status)
+        })
+        .collect()
+}
+
+/// Indexer settings, from the environment
+struct IndexerConfig {
+    start_height: Option<u64>,
+    batch_blocks: u64,
+    reorg_depth: u64,
+}
+
+/// Follow the chain in the background, indexing new blocks as they arrive
+pub fn spawn_indexer(chain: Arc<dyn ChainBackend>, db: DbPool, events: EventBus, chain_events: ChainEvents) {
+    if std::env::var("INDEXER_ENABLED").map(|v| v == "false").unwrap_or(false) {
+        tracing::info!("Charm indexer disabled");
+        return;
+    }
+    let env_u64 = |name: &str| std::env::var(name).ok().and_then(|s| s.parse::<u64>().ok());
+    let interval_secs = env_u64("INDEXER_INTERVAL_SECS").unwrap_or(30);
+    let config = IndexerConfig {
+        start_height: env_u64("INDEXER_START_HEIGHT"),
+        batch_blocks: env_u64("INDEXER_BATCH_BLOCKS").unwrap_or(100).max(1),
+        reorg_depth: env_u64("INDEXER_REORG_DEPTH").unwrap_or(100).max(1),
+    };
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        let mut blocks = chain_events.subscribe();
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = zmq::next_event(&mut blocks, zmq::is_block) => {}
+            }
+
+            if let Err(e) = catch_up(chain.as_ref(), &db, &events, &config).await {
+                tracing::debug!("Charm indexer could not catch up: {:#}", e);
+            }
+        }
+    });
+}
+
+/// Index up to `batch_blocks` blocks past the last one indexed, first
+/// rolling back any indexed blocks a reorg replaced
+async fn catch_up(chain: &dyn ChainBackend, db: &DbPool, events: &EventBus, config: &IndexerConfig) -> Result<()> {
+    let tip = chain.tip_height().await?;
+    let next = match db::get_indexer_height(db, INDEXER).await? {
+        Some(height) => match find_fork(chain, db, height as u64, tip, config.reorg_depth).await? {
+            Some(fork) => {
+                roll_back(chain, db, events, fork).await?;
+                fork
+            }
+            None => height as u64 + 1,
+        },
+        None => config.start_height.unwrap_or(tip),
+    };
+
+    for height in next..=tip.min(next + config.batch_blocks - 1) {
+        let hash = chain.block_hash(height).await?;
+        let block = chain.block(&hash).await?;
+        let changes = BlockCharms::from_block(&block, height, chain.network());
+
+        let prune_below = height.saturating_sub(config.reorg_depth) as i64;
+        db::apply_charm_block(db, INDEXER, height as i64, &hash, &changes.spent, &changes.created, prune_below)
+            .await?;
+        let txids: Vec<String> = block.txdata.iter().map(|tx| tx.compute_txid().to_string()).collect();
+        confirm_transactions(db, &txids, height).await?;
+        if !changes.created.is_empty() {
+            tracing::info!("Indexed block {} ({}): {:?}", height, hash, changes.created_by_app());
+        }
+    }
+    Ok(())
+}
+
+/// Mark the order transactions among `txids`, mined at `height`, confirmed
+async fn confirm_transactions(db: &DbPool, txids: &[String], height: u64) -> Result<()> {
+    let reason = format!("mined at height {}", height);
+    for (id, txid, status) in db::get_transaction_statuses(db, txids).await? {
+        if status != "confirmed" {
+            let by = db::Transition::new("indexer", &reason).with_txid(&txid);
+            db::transition_transaction(db, &id, "confirmed", &by).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Lowest indexed height, at or below `last`, whose block is no longer in the
+/// best chain; `None` when the last indexed block still is
+async fn find_fork(chain: &dyn ChainBackend, db: &DbPool, last: u64, tip: u64, depth: u64) -> Result<Option<u64>> {
+    let mut fork = None;
+    for height in (last.saturating_sub(depth)..=last).rev() {
+        let Some(stored) = db::get_indexed_block_hash(db, INDEXER, height as i64).await? else {
+            break;
+        };
+        if height <= tip && chain.block_hash(height).await? == stored {
+            break;
+        }
+        fork = Some(height);
+    }
+    Ok(fork)
+}
+
+/// Undo the index from `fork` up and reconcile what the orphaned blocks held
+async fn roll_back(chain: &dyn ChainBackend, db: &DbPool, events: &EventBus, fork: u64) -> Result<()> {
+    let hashes = db::rollback_charm_blocks(db, INDEXER, fork as i64).await?;
+    tracing::warn!("Reorg at height {}: rolled back {} indexed block(s)", fork, hashes.len());
+
+    // Nodes keep stale blocks, but a pruned or restarted one, or Esplora, may
+    // not; what cannot be fetched is left for the watchers to notice
+    let mut txids = Vec::new();
+    for hash in &hashes {
+        match chain.block(hash).await {
+            Ok(block) => txids.extend(
+                block
+                    .txdata
+                    .iter()
+                    .filter(|tx| !tx.is_coinbase())
+                    .map(|tx| tx.compute_txid().to_string()),
+            ),
+            Err(e) => tracing::warn!("Could not fetch orphaned block {}: {}", hash, e),
+        }
+    }
+    reconcile(chain, db, events, &txids).await
+}
+
+/// Re-check orders, escrows and deposits whose transactions were orphaned,
+/// publishing where each transaction ended up
+async fn reconcile(chain: &dyn ChainBackend, db: &DbPool, events: &EventBus, txids: &[String]) -> Result<()> {
+    if txids.is_empty() {
+        return Ok(());
+    }
+
+    let unconfirmed = db::unconfirm_address_deposits(db, txids).await?;
+    if unconfirmed > 0 {
+        tracing::info!("Reorg unconfirmed {} address deposit(s)", unconfirmed);
+    }
+    let transactions = db::get_transaction_statuses(db, txids).await?;
+
+    let orders = db::get_orders_by_tx_ids(db, txids).await?;
+    let spell_txs = db::get_spell_transactions(db, txids).await?;
+    let mut affected: Vec<String> = orders
+        .iter()
+        .filter_map(|order| order.tx_id.clone())
+        .chain(spell_txs.iter().map(|spell_tx| spell_tx.txid.clone()))
+        .chain(transactions.iter().map(|(_, txid, _)| txid.clone()))
+        .collect();
+    affected.sort();
+    affected.dedup();
+    let statuses = reorged_statuses(chain, &affected).await;
+
+    // Confirmed again once the new branch is indexed, if it is
+    for (id, txid, status) in &transactions {
+        let new_status = match statuses.get(txid).copied() {
+            Some("dropped") => "dropped",
+            _ => "broadcast",
+        };
+        if status != new_status {
+            let by = db::Transition::new("indexer", "transaction orphaned in a reorg").with_txid(txid);
+            db::transition_transaction(db, id, new_status, &by).await?;
+        }
+    }
+
+    let mut reported = HashSet::new();
+    for order in orders {
+        let Some(txid) = order.tx_id.clone() else {
+            continue;
+        };
+        let status = statuses.get(&txid).copied().unwrap_or("unknown");
+        if status == "dropped" && order.status == "open" {
+            let by = db::Transition::new("indexer", "order transaction dropped in a reorg").with_txid(&txid);
+            let change = db::OrderChange::status("pendingsignature");
+            if let Err(e) = db::transition_order(db, &order.id, order.version, change, &by).await {
+                if e.downcast_ref::<db::StaleOrder>().is_none() {
+                    return Err(e);
+                }
+                tracing::info!("Not reopening reorged order: {}", e);
+            }
+        }
+        events.publish(Event::new(
+            "order.reorged",
+            order.id.clone(),
+            serde_json::json!({ "txid": txid, "status": status }),
+        ));
+        reported.insert(order.id);
+    }
+
+    for spell_tx in spell_txs {
+        let status = statuses.get(&spell_tx.txid).copied().unwrap_or("unknown");
+        let data = serde_json::json!({ "txid": spell_tx.txid, "action": spell_tx.action, "status": status });
+        if let Some(escrow_id) = spell_tx.escrow_id {
+            events.publish(Event::new("escrow.reorged", escrow_id, data));
+        } else if let Some(order_id) = spell_tx.order_id.filter(|id| !reported.contains(id)) {
+            events.publish(Event::new("order.reorged", order_id, data));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::spell_decode::DecodedOutput;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::transaction::Version;
+    use bitcoin::{Amount, OutPoint, ScriptBuf, Sequence, TxIn, TxOut, Witness};
+    use std::str::FromStr;
+
+    fn tx(spends: &[&str], outputs: usize) -> Transaction {
+        let address = Address::from_str("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx")
+            .unwrap()
+            .assume_checked();
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: spends
+                .iter()
+                .map(|outpoint| TxIn {
+                    previous_output: OutPoint::from_str(outpoint).unwrap(),
+                    script_sig: ScriptBuf::new(),
+                    sequence: Sequence::MAX,
+                    witness: Witness::new(),
+                })
+                .collect(),
+            output: (0..outputs)
+                .map(|_| TxOut {
+                    value: Amount::from_sat(1000),
+                    script_pubkey: address.script_pubkey(),
+                })
+                .collect(),
+        }
+    }
+
+    fn spell(tx: &Transaction, outs: Vec<(u32, &str, serde_json::Value)>) -> DecodedSpell {
+        DecodedSpell {
+            txid: tx.compute_txid().to_string(),
+            version: 8,
+            apps: vec![],
+            public_inputs: BTreeMap::new(),
+            ins: vec![],
+            refs: vec![],
+            outs: outs
+                .into_iter()
+                .map(|(index, app, data)| DecodedOutput {
+                    index,
+                    charms: BTreeMap::from([(app.to_string(), data)]),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_block_charms_track_creation_and_spends() {
+        let token = format!("t/{}/{}", "11".repeat(32), "22".repeat(32));
+        let earlier = format!("{}:0", "aa".repeat(32));
+
+        // Mints a token to output 1 while spending an older charm
+        let mint = tx(&[&earlier], 2);
+        let mint_spell = spell(&mint, vec![(1, &token, serde_json::json!(500))]);
+        // Later in the block, moves the minted token on
+        let minted = format!("{}:1", mint.compute_txid());
+        let transfer = tx(&[&minted], 1);
+        let transfer_spell = spell(&transfer, vec![(0, &token, serde_json::json!(500))]);
+
+        let mut changes = BlockCharms::default();
+        changes.apply_tx(&mint, Some(&mint_spell), 100, Network::Testnet4);
+        assert_eq!(changes.created.len(), 1);
+        assert_eq!(changes.created[0].utxo_id, minted);
+        changes.apply_tx(&transfer, Some(&transfer_spell), 100, Network::Testnet4);
+
+        // The minted charm never outlived the block, so only the older one is
+        // spent and only the transfer output is live
+        assert_eq!(changes.spent, vec![earlier]);
+        assert_eq!(changes.created.len(), 1);
+        let live = &changes.created[0];
+        assert_eq!(live.utxo_id, format!("{}:0", transfer.compute_txid()));
+        assert_eq!((live.tag.as_str(), live.app_id.as_str()), ("t", "11".repeat(32).as_str()));
+        assert_eq!(live.address.as_deref(), Some("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx"));
+        assert_eq!((live.value, live.data.as_str(), live.block_height), (1000, "500", 100));
+    }
+
+    #[test]
+    fn test_reorged_status() {
+        let status = |confirmations| TxStatus {
+            confirmations,
+            block_hash: None,
+            block_height: None,
+        };
+        assert_eq!(reorged_status(Some(&status(2))), "confirmed");
+        assert_eq!(reorged_status(Some(&status(0))), "mempool");
+        assert_eq!(reorged_status(None), "dropped");
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_mined_transactions_confirm() {
+        let db = db::testing::fresh_db().await;
+
+        let now = chrono::Utc::now();
+        let order = db::OrderRecord {
+            id: "order-1".to_string(),
+            maker_address: "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx".to_string(),
+            offer_token: "TOAD".to_string(),
+            offer_amount: 1000,
+            want_token: "BTC".to_string(),
+            want_amount: 10_000,
+            source_chain: "bitcoin".to_string(),
+            dest_chain: "bitcoin".to_string(),
+            status: "open".to_string(),
+            allow_partial: false,
+            filled_amount: 0,
+            expiry_height: None,
+            utxo_id: None,
+            tx_id: None,
+            created_at: now,
+            updated_at: now,
+            version: 0,
+            archived_at: None,
+            maker_pubkey: None,
+            dest_address: None,
+        };
+        db::insert_order(&db, &order).await.unwrap();
+        let txid = tx(&[], 1).compute_txid().to_string();
+        db::insert_transaction(
+            &db,
+            &db::TransactionRecord {
+                id: "tx-1".to_string(),
+                order_id: order.id.clone(),
+                tx_type: "fill".to_string(),
+                tx_hex: None,
+                txid: Some(txid.clone()),
+                status: "broadcast".to_string(),
+                signed_at: Some(now),
+                broadcast_at: Some(now),
+                confirmed_at: None,
+                created_at: now,
+            },
+        )
+        .await
+        .unwrap();
+
+        // Confirmed once, however often the block is seen
+        confirm_transactions(&db, &[txid.clone(), "cc".repeat(32)], 101).await.unwrap();
+        let mined = [txid.clone()];
+        confirm_transactions(&db, &mined, 101).await.unwrap();
+        let statuses = db::get_transaction_statuses(&db, &mined).await.unwrap();
+        assert_eq!(statuses, vec![("tx-1".to_string(), txid.clone(), "confirmed".to_string())]);
+
+        let events = db::get_state_events(&db, db::Subject::Transaction, "tx-1").await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].old_status.as_deref(), Some("broadcast"));
+        assert_eq!(events[0].new_status, "confirmed");
+        assert_eq!(events[0].reason, "mined at height 101");
+        assert_eq!(events[0].txid.as_deref(), Some(txid.as_str()));
+    }
+}
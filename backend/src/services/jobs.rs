@@ -0,0 +1,253 @@
+//! Durable background jobs
+//!
+//! Jobs live in the `jobs` table, so queued work survives restarts and is
+//! shared by every backend instance on the database. A worker claims a due
+//! job of its type under a lease (`JOB_LEASE_SECS`, default 300), renews the
+//! lease while the job runs, then completes it or fails it back to the queue
+//! with exponential backoff (`JOB_MAX_ATTEMPTS`, `JOB_RETRY_BASE_MS` and
+//! `JOB_RETRY_MAX_MS`). A job whose worker died is claimed again once its
+//! lease lapses. Recurring sweeps requeue themselves under a unique key, so
+//! each runs on one instance per interval however many are up.
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::db::{self, DbPool, JobRecord, NewJob};
+use crate::services::retry::RetryPolicy;
+
+/// Handle on the job queue for one backend process
+pub struct JobQueue {
+    db: DbPool,
+    /// Identifies this process's claims
+    worker_id: String,
+    lease_secs: i64,
+    poll: Duration,
+    retry: RetryPolicy,
+}
+
+impl JobQueue {
+    /// Queue settings from `JOB_LEASE_SECS`, `JOB_POLL_MS` (default 1000) and
+    /// the `JOB_` retry policy
+    pub fn from_env(db: DbPool) -> Self {
+        let lease_secs = std::env::var("JOB_LEASE_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(300i64)
+            .max(1);
+        let poll_ms = std::env::var("JOB_POLL_MS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(1000);
+
+        Self {
+            db,
+            worker_id: format!("{}-{}", std::process::id(), uuid::Uuid::new_v4()),
+            lease_secs,
+            poll: Duration::from_millis(poll_ms),
+            retry: RetryPolicy::from_env(
+                "JOB",
+                RetryPolicy {
+                    max_attempts: 5,
+                    base_delay: Duration::from_secs(5),
+                    max_delay: Duration::from_secs(3600),
+                },
+            ),
+        }
+    }
+
+    /// A job of `job_type` due now, with the queue's attempt limit
+    pub fn new_job<'a>(&self, job_type: &'a str, payload: &impl Serialize) -> Result<NewJob<'a>> {
+        Ok(NewJob {
+            job_type,
+            payload: serde_json::to_string(payload)?,
+            run_at: chrono::Utc::now(),
+            max_attempts: self.retry.max_attempts as i32,
+            unique_key: None,
+        })
+    }
+
+    /// Queue a job of `job_type` to run now
+    pub async fn enqueue(&self, job_type: &str, payload: &impl Serialize) -> Result<Option<i64>> {
+        db::enqueue_job(&self.db, &self.new_job(job_type, payload)?).await
+    }
+
+    /// Spawn `workers` workers running jobs of `job_type` through `handler`
+    pub fn spawn_workers<F, Fut>(self: &Arc<Self>, job_type: &'static str, workers: usize, handler: F)
+    where
+        F: Fn(JobRecord) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send,
+    {
+        let handler = Arc::new(handler);
+        for _ in 0..workers {
+            let queue = self.clone();
+            let handler = handler.clone();
+            tokio::spawn(async move {
+                loop {
+                    match db::claim_job(&queue.db, job_type, &queue.worker_id, queue.lease_secs).await {
+                        Ok(Some(job)) => queue.run(job, handler.as_ref()).await,
+                        Ok(None) => tokio::time::sleep(queue.poll).await,
+                        Err(e) => {
+                            tracing::debug!("Job worker could not claim a {} job: {}", job_type, e);
+                            tokio::time::sleep(queue.poll).await;
+                        }
+                    }
+                }
+            });
+        }
+    }
+
+    /// Run `handler` as a `job_type` job every `every`, on whichever instance
+    /// claims it first
+    pub fn spawn_recurring<F, Fut>(self: &Arc<Self>, job_type: &'static str, every: Duration, handler: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send,
+    {
+        let queue = self.clone();
+        let every = chrono::Duration::from_std(every).unwrap_or(chrono::Duration::hours(1));
+        tokio::spawn(async move {
+            queue.schedule(job_type, chrono::Utc::now()).await;
+        });
+
+        let queue = self.clone();
+        self.spawn_workers(job_type, 1, move |_job| {
+            let queue = queue.clone();
+            let run = handler();
+            async move {
+                let outcome = run.await;
+                // A failed run queued for retry keeps the key; this is a no-op then
+                queue.schedule(job_type, chrono::Utc::now() + every).await;
+                outcome
+            }
+        });
+    }
+
+    /// Queue the next run of a recurring job unless one is already queued
+    async fn schedule(&self, job_type: &str, run_at: chrono::DateTime<chrono::Utc>) {
+        let job = NewJob {
+            job_type,
+            payload: "{}".to_string(),
+            run_at,
+            max_attempts: self.retry.max_attempts as i32,
+            unique_key: Some(job_type),
+        };
+        if let Err(e) = db::enqueue_job(&self.db, &job).await {
+            tracing::warn!("Failed to schedule {} job: {}", job_type, e);
+        }
+    }
+
+    /// Run a claimed job, renewing its lease until the handler returns
+    async fn run<F, Fut>(&self, job: JobRecord, handler: &F)
+    where
+        F: Fn(JobRecord) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        // Claimed again after leases lapsed more often than it may be tried
+        if job.attempts > job.max_attempts {
+            let error = format!("Gave up after {} attempts", job.max_attempts);
+            self.finish(&job, Err(anyhow::anyhow!(error))).await;
+            return;
+        }
+
+        let id = job.id;
+        let claimed = job.clone();
+        let renew_every = Duration::from_secs((self.lease_secs as u64 / 3).max(1));
+        let outcome = {
+            let run = handler(job);
+            tokio::pin!(run);
+            let mut renew = tokio::time::interval(renew_every);
+            renew.tick().await;
+            loop {
+                tokio::select! {
+                    outcome = &mut run => break outcome,
+                    _ = renew.tick() => {
+                        match db::extend_job_lease(&self.db, id, &self.worker_id, self.lease_secs).await {
+                            Ok(true) => {}
+                            Ok(false) => tracing::warn!("Lost the lease on job {}", id),
+                            Err(e) => tracing::debug!("Failed to renew the lease on job {}: {}", id, e),
+                        }
+                    }
+                }
+            }
+        };
+        self.finish(&claimed, outcome).await;
+    }
+
+    async fn finish(&self, job: &JobRecord, outcome: Result<()>) {
+        let recorded = match &outcome {
+            Ok(()) => db::complete_job(&self.db, job.id, &self.worker_id).await,
+            Err(e) => {
+                let retry_at = retry_at(&self.retry, job, chrono::Utc::now());
+                match retry_at {
+                    Some(at) => tracing::warn!("{} job {} failed, retrying at {}: {:#}", job.job_type, job.id, at, e),
+                    None => tracing::error!("{} job {} failed for good: {:#}", job.job_type, job.id, e),
+                }
+                db::fail_job(&self.db, job.id, &self.worker_id, &format!("{:#}", e), retry_at).await
+            }
+        };
+        match recorded {
+            Ok(true) => {}
+            Ok(false) => tracing::warn!("Job {} was claimed by another worker before it finished", job.id),
+            Err(e) => tracing::error!("Failed to record the outcome of job {}: {}", job.id, e),
+        }
+    }
+}
+
+/// When a failed job runs again, or None once it used up its attempts
+fn retry_at(
+    policy: &RetryPolicy,
+    job: &JobRecord,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Option<chrono::DateTime<chrono::Utc>> {
+    if job.attempts >= job.max_attempts {
+        return None;
+    }
+    let delay = policy.delay(job.attempts.max(1) as u32);
+    Some(now + chrono::Duration::from_std(delay).ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(attempts: i32, max_attempts: i32) -> JobRecord {
+        let now = chrono::Utc::now();
+        JobRecord {
+            id: 1,
+            job_type: "prove".to_string(),
+            payload: "{}".to_string(),
+            status: "running".to_string(),
+            run_at: now,
+            attempts,
+            max_attempts,
+            locked_by: Some("worker".to_string()),
+            locked_until: Some(now),
+            last_error: None,
+            unique_key: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn test_retry_at() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_secs(10),
+            max_delay: Duration::from_secs(60),
+        };
+        let now = chrono::Utc::now();
+
+        let first = retry_at(&policy, &job(1, 3), now).unwrap();
+        assert!(first >= now + chrono::Duration::seconds(5) && first <= now + chrono::Duration::seconds(10));
+        let second = retry_at(&policy, &job(2, 3), now).unwrap();
+        assert!(second >= now + chrono::Duration::seconds(10) && second <= now + chrono::Duration::seconds(20));
+
+        assert_eq!(retry_at(&policy, &job(3, 3), now), None);
+        assert_eq!(retry_at(&policy, &job(4, 3), now), None);
+    }
+}
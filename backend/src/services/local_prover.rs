@@ -0,0 +1,181 @@
+//! Local proving through the charms CLI
+//!
+//! Runs `charms spell prove` on the host instead of calling the hosted prover,
+//! for air-gapped deployments and integration tests. Configured with
+//! `CHARMS_BIN` (default `charms`), `LOCAL_PROVE_TIMEOUT_SECS` and
+//! `LOCAL_PROVE_MOCK`, which passes `--mock` to skip real proof generation.
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+use bitcoin::consensus::encode::deserialize_hex;
+use bitcoin::Transaction;
+use tokio::process::Command;
+
+use crate::services::charms::{ProvedTransaction, ProverError, SpellProveRequest};
+
+/// Host charms binary used to prove spells
+#[derive(Debug, Clone)]
+pub struct LocalProver {
+    binary: String,
+    timeout: Duration,
+    mock: bool,
+}
+
+impl LocalProver {
+    pub fn from_env() -> Self {
+        Self {
+            binary: std::env::var("CHARMS_BIN").unwrap_or_else(|_| "charms".to_string()),
+            timeout: Duration::from_secs(
+                std::env::var("LOCAL_PROVE_TIMEOUT_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(600),
+            ),
+            mock: std::env::var("LOCAL_PROVE_MOCK").map(|v| v == "true").unwrap_or(false),
+        }
+    }
+
+    /// Identifier reported by health checks
+    pub fn url(&self) -> String {
+        format!("local:{}", self.binary)
+    }
+
+    /// Version string of the charms binary, confirming it can be run
+    pub async fn version(&self) -> Result<String, ProverError> {
+        let output = Command::new(&self.binary)
+            .arg("--version")
+            .kill_on_drop(true)
+            .output()
+            .await
+            .map_err(|e| ProverError::Local(format!("Failed to run {}: {}", self.binary, e)))?;
+
+        if !output.status.success() {
+            return Err(ProverError::Local(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    pub async fn prove(&self, request: &SpellProveRequest) -> Result<Vec<ProvedTransaction>, ProverError> {
+        let workdir = std::env::temp_dir().join(format!("charms-prove-{}", uuid::Uuid::new_v4()));
+        let result = self.prove_in(&workdir, request).await;
+        if let Err(e) = tokio::fs::remove_dir_all(&workdir).await {
+            tracing::warn!("Failed to remove prove workdir {}: {}", workdir.display(), e);
+        }
+        result
+    }
+
+    async fn prove_in(&self, workdir: &Path, request: &SpellProveRequest) -> Result<Vec<ProvedTransaction>, ProverError> {
+        let io_error = |e: std::io::Error| ProverError::Local(format!("Failed to prepare prove inputs: {}", e));
+
+        tokio::fs::create_dir_all(workdir).await.map_err(io_error)?;
+        let spell_path = workdir.join("spell.yaml");
+        tokio::fs::write(&spell_path, &request.spell).await.map_err(io_error)?;
+
+        let mut app_bins: Vec<PathBuf> = Vec::with_capacity(request.binaries.len());
+        for (vk, binary) in &request.binaries {
+            let path = workdir.join(format!("{}.wasm", vk));
+            tokio::fs::write(&path, binary).await.map_err(io_error)?;
+            app_bins.push(path);
+        }
+
+        let mut command = Command::new(&self.binary);
+        command
+            .args(["spell", "prove", "--spell"])
+            .arg(&spell_path)
+            .args(["--funding-utxo", &request.funding_utxo.to_string()])
+            .args(["--funding-utxo-value", &request.funding_utxo_value.to_string()])
+            .args(["--change-address", &request.change_address])
+            .args(["--fee-rate", &request.fee_rate.to_string()])
+            .args(["--chain", "bitcoin"]);
+        if !request.prev_txs.is_empty() {
+            command.args(["--prev-txs", &request.prev_txs.join(",")]);
+        }
+        if !app_bins.is_empty() {
+            command.arg("--app-bins").args(&app_bins);
+        }
+        if self.mock {
+            command.arg("--mock");
+        }
+
+        tracing::info!("Proving spell locally with {}", self.binary);
+        let output = command
+            .stdin(Stdio::null())
+            .kill_on_drop(true)
+            .output();
+        let output = tokio::time::timeout(self.timeout, output)
+            .await
+            .map_err(|_| ProverError::Timeout(format!("Local prover timed out after {:?}", self.timeout)))?
+            .map_err(|e| ProverError::Local(format!("Failed to run {}: {}", self.binary, e)))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            // Contract and funding failures read like the hosted API's; anything
+            // else is reported as a CLI failure
+            return Err(match ProverError::rejected(0, stderr) {
+                ProverError::Rejected { body, .. } => ProverError::Local(format!(
+                    "{} exited with {}: {}",
+                    self.binary, output.status, body
+                )),
+                classified => classified,
+            });
+        }
+
+        parse_output(&String::from_utf8_lossy(&output.stdout))
+    }
+}
+
+/// Transactions printed by `charms spell prove`: a JSON array of raw hex
+/// transactions, or of `{hex, txid}` objects like the hosted API returns
+fn parse_output(stdout: &str) -> Result<Vec<ProvedTransaction>, ProverError> {
+    let stdout = stdout.trim();
+    if let Ok(txs) = serde_json::from_str::<Vec<ProvedTransaction>>(stdout) {
+        return Ok(txs);
+    }
+
+    let hexes: Vec<String> = serde_json::from_str(stdout)
+        .map_err(|e| ProverError::InvalidResponse(format!("Unexpected local prover output: {}", e)))?;
+
+    hexes
+        .into_iter()
+        .map(|hex| {
+            let tx: Transaction = deserialize_hex(&hex)
+                .map_err(|e| ProverError::InvalidResponse(format!("Invalid transaction from local prover: {}", e)))?;
+            Ok(ProvedTransaction {
+                txid: tx.compute_txid().to_string(),
+                hex,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::consensus::encode::serialize_hex;
+    use bitcoin::transaction::Version;
+    use bitcoin::{TxIn, TxOut};
+
+    #[test]
+    fn test_parse_cli_output() {
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn::default()],
+            output: vec![TxOut::NULL],
+        };
+        let hex = serialize_hex(&tx);
+
+        let parsed = parse_output(&format!("[\"{}\"]\n", hex)).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].txid, tx.compute_txid().to_string());
+
+        let objects = parse_output(r#"[{"hex": "00", "txid": "abc"}]"#).unwrap();
+        assert_eq!(objects[0].txid, "abc");
+
+        assert!(parse_output("proving...").is_err());
+        assert!(parse_output(r#"["zz"]"#).is_err());
+    }
+}
@@ -0,0 +1,161 @@
+//! Mempool monitoring for spends this backend did not make
+//!
+//! Orders and escrows hold UTXOs that only the spells this backend proves
+//! should spend. Each `SpendWatcher` lists the outpoints it cares about with
+//! the transactions it expects to spend them; on every new block or mempool
+//! transaction (and every `MEMPOOL_MONITOR_INTERVAL_SECS`, default 30) the
+//! monitor asks the chain backend who spends them and hands any other
+//! spender back to the watcher: a fill made elsewhere, or a double-spend of
+//! a transaction still waiting to confirm.
+
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use axum::async_trait;
+use bitcoin::{OutPoint, Txid};
+
+use crate::services::chain::ChainBackend;
+use crate::services::zmq::{self, ChainEvents};
+
+/// What an unexpected spend of a watched outpoint means
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExternalSpend {
+    /// An input of a transaction still waiting to confirm was spent by
+    /// another one
+    Conflicting,
+    /// A live output was spent outside this backend
+    ExternallySpent,
+}
+
+/// Outpoint a watcher cares about
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchedOutpoint {
+    pub outpoint: OutPoint,
+    /// Order or escrow holding it
+    pub subject_id: String,
+    /// Transactions this backend proved that may spend it
+    pub expected: HashSet<Txid>,
+    pub kind: ExternalSpend,
+}
+
+impl WatchedOutpoint {
+    /// Watch `outpoint` (`txid:vout`); `None` for placeholder ids such as mock
+    /// transactions, which no chain knows
+    pub fn new(outpoint: &str, subject_id: &str, expected: &[String], kind: ExternalSpend) -> Option<Self> {
+        Some(Self {
+            outpoint: OutPoint::from_str(outpoint).ok()?,
+            subject_id: subject_id.to_string(),
+            expected: expected.iter().filter_map(|txid| Txid::from_str(txid).ok()).collect(),
+            kind,
+        })
+    }
+}
+
+/// Source of watched outpoints, told when one is spent unexpectedly
+#[async_trait]
+pub trait SpendWatcher: Send + Sync {
+    async fn watched(&self) -> Result<Vec<WatchedOutpoint>>;
+
+    async fn spent_externally(&self, watched: &WatchedOutpoint, txid: &Txid) -> Result<()>;
+}
+
+/// Watched outpoints spent by a transaction not expected to spend them
+pub fn external_spends<'a>(
+    watched: &'a [WatchedOutpoint],
+    spenders: &HashMap<OutPoint, Txid>,
+) -> Vec<(&'a WatchedOutpoint, Txid)> {
+    watched
+        .iter()
+        .filter_map(|w| {
+            let txid = spenders.get(&w.outpoint)?;
+            (!w.expected.contains(txid)).then_some((w, *txid))
+        })
+        .collect()
+}
+
+/// Check the watchers' outpoints in the background on every chain event
+pub fn spawn_mempool_monitor(chain: Arc<dyn ChainBackend>, chain_events: ChainEvents, watchers: Vec<Arc<dyn SpendWatcher>>) {
+    let interval_secs = std::env::var("MEMPOOL_MONITOR_INTERVAL_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(30);
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        let mut events = chain_events.subscribe();
+        // A watcher that fails to record a spend hears about it again
+        let mut reported: HashSet<(OutPoint, Txid)> = HashSet::new();
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = zmq::next_event(&mut events, zmq::is_any) => {}
+            }
+
+            for watcher in &watchers {
+                if let Err(e) = check(chain.as_ref(), watcher.as_ref(), &mut reported).await {
+                    tracing::debug!("Mempool monitor check failed: {:#}", e);
+                }
+            }
+        }
+    });
+}
+
+/// Report a watcher's outpoints that something else spends
+async fn check(
+    chain: &dyn ChainBackend,
+    watcher: &dyn SpendWatcher,
+    reported: &mut HashSet<(OutPoint, Txid)>,
+) -> Result<()> {
+    let watched = watcher.watched().await?;
+    if watched.is_empty() {
+        return Ok(());
+    }
+
+    let outpoints: Vec<OutPoint> = watched.iter().map(|w| w.outpoint).collect();
+    let spenders = chain.spenders(&outpoints).await?;
+    for (watched, txid) in external_spends(&watched, &spenders) {
+        if reported.contains(&(watched.outpoint, txid)) {
+            continue;
+        }
+        tracing::warn!(
+            "{} of {} spent by {}, which this backend did not build",
+            watched.outpoint,
+            watched.subject_id,
+            txid
+        );
+        watcher.spent_externally(watched, &txid).await?;
+        reported.insert((watched.outpoint, txid));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_external_spends() {
+        let txid = |byte: &str| byte.repeat(32);
+        let ours = vec![txid("bb")];
+        let funding =
+            WatchedOutpoint::new(&format!("{}:1", txid("aa")), "order-1", &ours, ExternalSpend::Conflicting).unwrap();
+        let offer = WatchedOutpoint::new(&format!("{}:0", ours[0]), "order-2", &[], ExternalSpend::ExternallySpent).unwrap();
+        assert_eq!(WatchedOutpoint::new("mock_broadcast_1:0", "order-3", &[], ExternalSpend::Conflicting), None);
+
+        let watched = vec![funding.clone(), offer.clone()];
+        let theirs = Txid::from_str(&txid("cc")).unwrap();
+
+        // Our own transaction spending the funding UTXO is expected
+        let spenders = HashMap::from([(funding.outpoint, Txid::from_str(&ours[0]).unwrap())]);
+        assert!(external_spends(&watched, &spenders).is_empty());
+
+        let spenders = HashMap::from([(funding.outpoint, theirs), (offer.outpoint, theirs)]);
+        let spends = external_spends(&watched, &spenders);
+        assert_eq!(spends.len(), 2);
+        assert_eq!((spends[0].0.kind, spends[0].1), (ExternalSpend::Conflicting, theirs));
+        assert_eq!(spends[1].0.subject_id, "order-2");
+    }
+}
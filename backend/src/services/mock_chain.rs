@@ -0,0 +1,285 @@
+//! In-process chain for mock mode
+//!
+//! Paired with the mock prover so the whole order and escrow flow runs
+//! without a node: every address is funded with one confirmed faucet output
+//! the first time it is looked up, broadcasts are accepted into a mempool
+//! that never confirms, and their outputs become spendable at once. Nothing
+//! is persisted; a restart starts from an empty chain.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use anyhow::{bail, Result};
+use axum::async_trait;
+use bitcoin::absolute::LockTime;
+use bitcoin::consensus::encode::{deserialize_hex, serialize_hex};
+use bitcoin::hashes::Hash;
+use bitcoin::transaction::Version;
+use bitcoin::{Address, Amount, Block, Network, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness};
+use sha2::{Digest, Sha256};
+
+use crate::services::bitcoin::{ScriptPubKeyInfo, TxOutInfo, UnspentOutput};
+use crate::services::chain::{BroadcastRejection, ChainBackend, ChainKind, TxStatus};
+
+/// Height the mock chain reports as its tip
+const MOCK_TIP_HEIGHT: u64 = 100_000;
+/// Confirmations of faucet outputs
+const FAUCET_CONFIRMATIONS: u32 = 6;
+/// Value of the faucet output each address starts with
+const FAUCET_SATS: u64 = 100_000;
+
+/// Simulated chain for mock mode
+pub struct MockChain {
+    network: Network,
+    state: Mutex<MockState>,
+}
+
+#[derive(Default)]
+struct MockState {
+    /// Faucet and broadcast transactions, with whether they are confirmed
+    txs: HashMap<Txid, (Transaction, bool)>,
+    /// Spent outputs and the broadcast spending each
+    spent: HashMap<OutPoint, Txid>,
+}
+
+impl MockChain {
+    pub fn new(network: Network) -> Self {
+        Self {
+            network,
+            state: Mutex::new(MockState::default()),
+        }
+    }
+
+    fn script_for(&self, address: &str) -> Option<ScriptBuf> {
+        let address = Address::from_str(address).ok()?.require_network(self.network).ok()?;
+        Some(address.script_pubkey())
+    }
+
+    /// Confirmed transaction paying `FAUCET_SATS` to `script`, the same for
+    /// an address every time
+    fn faucet_tx(address: &str, script: ScriptBuf) -> Transaction {
+        let seed = Sha256::digest(format!("mock-faucet:{}", address).as_bytes());
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::new(Txid::from_byte_array(seed.into()), 0),
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::MAX,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(FAUCET_SATS),
+                script_pubkey: script,
+            }],
+        }
+    }
+
+    fn address_of(&self, script: &ScriptBuf) -> Option<String> {
+        Address::from_script(script, self.network).ok().map(|a| a.to_string())
+    }
+}
+
+impl MockState {
+    fn fund(&mut self, address: &str, script: &ScriptBuf) {
+        let tx = MockChain::faucet_tx(address, script.clone());
+        self.txs.entry(tx.compute_txid()).or_insert((tx, true));
+    }
+
+    fn confirmations(confirmed: bool) -> u32 {
+        if confirmed {
+            FAUCET_CONFIRMATIONS
+        } else {
+            0
+        }
+    }
+}
+
+#[async_trait]
+impl ChainBackend for MockChain {
+    fn kind(&self) -> ChainKind {
+        ChainKind::Mock
+    }
+
+    fn network(&self) -> Network {
+        self.network
+    }
+
+    async fn tip_height(&self) -> Result<u64> {
+        Ok(MOCK_TIP_HEIGHT)
+    }
+
+    async fn list_unspent(&self, addresses: &[String], min_conf: u32) -> Result<Vec<UnspentOutput>> {
+        let mut state = self.state.lock().expect("mock chain lock poisoned");
+        let mut unspent = Vec::new();
+        for address in addresses {
+            let Some(script) = self.script_for(address) else {
+                continue;
+            };
+            state.fund(address, &script);
+
+            for (txid, (tx, confirmed)) in &state.txs {
+                for (vout, out) in tx.output.iter().enumerate() {
+                    let outpoint = OutPoint::new(*txid, vout as u32);
+                    let confirmations = MockState::confirmations(*confirmed);
+                    if out.script_pubkey != script || state.spent.contains_key(&outpoint) || confirmations < min_conf {
+                        continue;
+                    }
+                    unspent.push(UnspentOutput {
+                        txid: txid.to_string(),
+                        vout: vout as u32,
+                        address: address.clone(),
+                        script_pub_key: out.script_pubkey.to_hex_string(),
+                        amount: out.value.to_btc(),
+                        confirmations,
+                        spendable: true,
+                    });
+                }
+            }
+        }
+        Ok(unspent)
+    }
+
+    async fn tx_out(&self, txid: &str, vout: u32) -> Result<Option<TxOutInfo>> {
+        let txid = Txid::from_str(txid)?;
+        let state = self.state.lock().expect("mock chain lock poisoned");
+        if state.spent.contains_key(&OutPoint::new(txid, vout)) {
+            return Ok(None);
+        }
+        let Some((tx, confirmed)) = state.txs.get(&txid) else {
+            return Ok(None);
+        };
+        Ok(tx.output.get(vout as usize).map(|out| TxOutInfo {
+            confirmations: MockState::confirmations(*confirmed),
+            value: out.value.to_btc(),
+            script_pub_key: ScriptPubKeyInfo {
+                hex: out.script_pubkey.to_hex_string(),
+                address: self.address_of(&out.script_pubkey),
+            },
+        }))
+    }
+
+    /// Accepts any decodable transaction that spends nothing already spent,
+    /// whether or not its inputs exist
+    async fn broadcast(&self, tx_hex: &str) -> Result<String> {
+        let tx: Transaction = deserialize_hex(tx_hex.trim()).map_err(|e| BroadcastRejection::new(&format!("TX decode failed: {}", e)))?;
+        let txid = tx.compute_txid();
+
+        let mut state = self.state.lock().expect("mock chain lock poisoned");
+        if state.txs.contains_key(&txid) {
+            return Ok(txid.to_string());
+        }
+        if tx.input.iter().any(|input| state.spent.contains_key(&input.previous_output)) {
+            return Err(BroadcastRejection::new("txn-mempool-conflict").into());
+        }
+        for input in &tx.input {
+            state.spent.insert(input.previous_output, txid);
+        }
+        state.txs.insert(txid, (tx, false));
+        tracing::info!("Mock chain: accepted transaction {}", txid);
+        Ok(txid.to_string())
+    }
+
+    async fn raw_transaction(&self, txid: &str) -> Result<String> {
+        let txid = Txid::from_str(txid)?;
+        match self.state.lock().expect("mock chain lock poisoned").txs.get(&txid) {
+            Some((tx, _)) => Ok(serialize_hex(tx)),
+            None => bail!("Transaction {} not found on the mock chain", txid),
+        }
+    }
+
+    async fn tx_status(&self, txid: &str) -> Result<Option<TxStatus>> {
+        let txid = Txid::from_str(txid)?;
+        let state = self.state.lock().expect("mock chain lock poisoned");
+        Ok(state.txs.get(&txid).map(|(_, confirmed)| TxStatus {
+            confirmations: MockState::confirmations(*confirmed),
+            block_hash: None,
+            block_height: None,
+        }))
+    }
+
+    async fn merkle_block(&self, txid: &str, _block_hash: &str) -> Result<String> {
+        bail!("The mock chain has no blocks to prove {} in", txid)
+    }
+
+    async fn spenders(&self, outpoints: &[OutPoint]) -> Result<HashMap<OutPoint, Txid>> {
+        let state = self.state.lock().expect("mock chain lock poisoned");
+        Ok(outpoints
+            .iter()
+            .filter_map(|outpoint| state.spent.get(outpoint).map(|txid| (*outpoint, *txid)))
+            .collect())
+    }
+
+    /// None, so fees come from the estimator's fallback
+    async fn fee_rate(&self, _conf_target: u16) -> Result<Option<f64>> {
+        Ok(None)
+    }
+
+    async fn block_hash(&self, height: u64) -> Result<String> {
+        bail!("The mock chain has no block at height {}", height)
+    }
+
+    async fn block(&self, hash: &str) -> Result<Block> {
+        bail!("The mock chain has no block {}", hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: &str = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+
+    fn spending(outpoint: OutPoint, to: ScriptBuf) -> Transaction {
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: outpoint,
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::MAX,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(90_000),
+                script_pubkey: to,
+            }],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_faucet_and_broadcast() {
+        let chain = MockChain::new(Network::Testnet4);
+        let addresses = vec![ADDRESS.to_string()];
+
+        // Funded once, on first lookup
+        let funded = chain.list_unspent(&addresses, 1).await.unwrap();
+        assert_eq!(funded.len(), 1);
+        assert_eq!(funded[0].amount, 0.001);
+        assert_eq!(chain.list_unspent(&addresses, 1).await.unwrap().len(), 1);
+        let faucet = OutPoint::new(Txid::from_str(&funded[0].txid).unwrap(), 0);
+        let owner = chain.tx_out(&funded[0].txid, 0).await.unwrap().unwrap();
+        assert_eq!(owner.script_pub_key.address.as_deref(), Some(ADDRESS));
+
+        // Spending it moves the funds into an unconfirmed output
+        let script = chain.script_for(ADDRESS).unwrap();
+        let tx = spending(faucet, script.clone());
+        let txid = chain.broadcast(&serialize_hex(&tx)).await.unwrap();
+        assert_eq!(txid, tx.compute_txid().to_string());
+        assert!(chain.tx_out(&funded[0].txid, 0).await.unwrap().is_none());
+        assert!(chain.list_unspent(&addresses, 1).await.unwrap().is_empty());
+        let pending = chain.list_unspent(&addresses, 0).await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].txid, txid);
+        assert_eq!(chain.tx_status(&txid).await.unwrap().unwrap().confirmations, 0);
+        assert_eq!(chain.raw_transaction(&txid).await.unwrap(), serialize_hex(&tx));
+        assert_eq!(chain.spenders(&[faucet]).await.unwrap().get(&faucet), Some(&tx.compute_txid()));
+
+        // Rebroadcasting is a no-op; a second spend of the same output conflicts
+        assert_eq!(chain.broadcast(&serialize_hex(&tx)).await.unwrap(), txid);
+        let conflict = chain.broadcast(&serialize_hex(&spending(faucet, ScriptBuf::new()))).await.unwrap_err();
+        assert_eq!(BroadcastRejection::of(&conflict).unwrap().code, "conflict");
+        assert!(chain.broadcast("not a transaction").await.is_err());
+    }
+}
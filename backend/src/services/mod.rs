@@ -1,7 +1,45 @@
 //! Backend services
 
+pub mod addresses;
+pub mod app_artifacts;
 pub mod bitcoin;
+pub mod broadcast_check;
 pub mod charms;
+pub mod consolidation;
+pub mod chain;
+pub mod chain_cache;
+pub mod chain_tip;
+pub mod coordinator;
+pub mod escrow_script;
+pub mod esplora;
+pub mod events;
+pub mod fee_bump;
+pub mod fees;
+pub mod fills;
+pub mod funding_check;
+pub mod indexer;
+pub mod jobs;
+pub mod local_prover;
+pub mod mempool_monitor;
+pub mod mock_chain;
+pub mod network;
+pub mod prove_check;
+pub mod prover;
+pub mod prover_pool;
+pub mod psbt;
+pub mod rate_limit;
+pub mod retry;
+pub mod rpc_nodes;
+pub mod sealing;
+pub mod sessions;
+pub mod signatures;
+pub mod spell_check;
+pub mod spell_decode;
+pub mod spell_schema;
+pub mod spv;
+pub mod tokens;
+pub mod webhooks;
+pub mod zmq;
 
 pub use bitcoin::BitcoinService;
 pub use charms::CharmsService;
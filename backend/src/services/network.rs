@@ -0,0 +1,173 @@
+//! Bitcoin network the server runs on
+//!
+//! Set once with `BITCOIN_NETWORK` (`mainnet`, `testnet4`, `signet` or
+//! `regtest`; default `testnet4`) and handed to every service that derives
+//! addresses, builds spells or talks to the node. Startup checks that those
+//! services and the node itself agree, so a server pointed at the wrong node
+//! fails before it hands out an address.
+
+use std::str::FromStr;
+
+use anyhow::{bail, Result};
+use bitcoin::{Address, Network};
+use thiserror::Error;
+
+/// Network used when `BITCOIN_NETWORK` is unset
+pub const DEFAULT_NETWORK: Network = Network::Testnet4;
+
+/// Network from `BITCOIN_NETWORK`
+pub fn from_env() -> Result<Network> {
+    match std::env::var("BITCOIN_NETWORK") {
+        Ok(name) if !name.trim().is_empty() => parse(&name),
+        _ => Ok(DEFAULT_NETWORK),
+    }
+}
+
+/// Parse a network name, accepting Bitcoin Core's names as well
+pub fn parse(name: &str) -> Result<Network> {
+    match name.trim().to_lowercase().as_str() {
+        "mainnet" | "main" | "bitcoin" => Ok(Network::Bitcoin),
+        "testnet4" => Ok(Network::Testnet4),
+        "signet" => Ok(Network::Signet),
+        "regtest" => Ok(Network::Regtest),
+        other => bail!("Unknown network {:?}; expected mainnet, testnet4, signet or regtest", other),
+    }
+}
+
+/// Name of a network in API responses and prove requests
+pub fn name(network: Network) -> &'static str {
+    match network {
+        Network::Bitcoin => "mainnet",
+        Network::Testnet4 => "testnet4",
+        Network::Signet => "signet",
+        Network::Regtest => "regtest",
+        _ => "testnet",
+    }
+}
+
+/// Fail unless `address` is a valid address on `network`
+pub fn check_address(address: &str, network: Network) -> Result<Address> {
+    let Ok(address) = Address::from_str(address.trim()) else {
+        bail!("Invalid address: {}", address);
+    };
+    match address.require_network(network) {
+        Ok(address) => Ok(address),
+        Err(_) => bail!("Address is not a {} address", name(network)),
+    }
+}
+
+/// Why an address in a request cannot be used on the server's network
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum AddressError {
+    #[error("{field}: invalid address {address}")]
+    Invalid { field: String, address: String },
+    #[error("{field}: {address} is not a {network} address")]
+    WrongNetwork {
+        field: String,
+        address: String,
+        network: &'static str,
+    },
+}
+
+/// Fail on the first `(field, address)` that is not a valid address on
+/// `network`
+pub fn check_fields(network: Network, fields: &[(&str, &str)]) -> Result<(), AddressError> {
+    for (field, address) in fields {
+        let Ok(parsed) = Address::from_str(address.trim()) else {
+            return Err(AddressError::Invalid {
+                field: field.to_string(),
+                address: address.to_string(),
+            });
+        };
+        if !parsed.is_valid_for_network(network) {
+            return Err(AddressError::WrongNetwork {
+                field: field.to_string(),
+                address: address.to_string(),
+                network: name(network),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Fail unless every component runs on `network`; `components` pairs a
+/// component's name with its network
+pub fn check_agreement(network: Network, components: &[(&str, Network)]) -> Result<()> {
+    let mismatched: Vec<String> = components
+        .iter()
+        .filter(|(_, other)| *other != network)
+        .map(|(component, other)| format!("{} is on {}", component, name(*other)))
+        .collect();
+    if !mismatched.is_empty() {
+        bail!("Configured for {}, but {}", name(network), mismatched.join(", "));
+    }
+    Ok(())
+}
+
+/// Fail unless `chain`, as reported by the node's `getblockchaininfo`, is
+/// `network`
+pub fn check_node_chain(network: Network, chain: &str) -> Result<()> {
+    if chain != network.to_core_arg() {
+        bail!(
+            "Bitcoin node is on {}, but the server is configured for {} (BITCOIN_NETWORK)",
+            chain,
+            name(network)
+        );
+    }
+    Ok(())
+}
+
+/// Default mempool.space-compatible API for fee estimates on `network`
+pub fn mempool_api_url(network: Network) -> &'static str {
+    match network {
+        Network::Bitcoin => "https://mempool.space/api",
+        Network::Signet => "https://mempool.space/signet/api",
+        // A local mempool backend; mempool.space has no regtest
+        Network::Regtest => "http://127.0.0.1:8999/api",
+        _ => "https://mempool.space/testnet4/api",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_network_names_and_checks() {
+        assert_eq!(parse("mainnet").unwrap(), Network::Bitcoin);
+        assert_eq!(parse(" Testnet4 ").unwrap(), Network::Testnet4);
+        assert_eq!(parse("main").unwrap(), Network::Bitcoin);
+        assert!(parse("testnet5").is_err());
+        assert_eq!(name(Network::Regtest), "regtest");
+
+        // Core reports testnet4 as "testnet4" and mainnet as "main"
+        assert!(check_node_chain(Network::Testnet4, "testnet4").is_ok());
+        assert!(check_node_chain(Network::Bitcoin, "main").is_ok());
+        assert!(check_node_chain(Network::Bitcoin, "testnet4").is_err());
+
+        let testnet = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+        assert!(check_address(testnet, Network::Testnet4).is_ok());
+        assert!(check_address(testnet, Network::Bitcoin).is_err());
+        assert!(check_address("not-an-address", Network::Testnet4).is_err());
+
+        let mainnet = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+        assert!(check_fields(Network::Testnet4, &[("maker_address", testnet)]).is_ok());
+        assert_eq!(
+            check_fields(Network::Testnet4, &[("maker_address", testnet), ("change_address", mainnet)]),
+            Err(AddressError::WrongNetwork {
+                field: "change_address".to_string(),
+                address: mainnet.to_string(),
+                network: "testnet4",
+            })
+        );
+        assert!(matches!(
+            check_fields(Network::Bitcoin, &[("taker_address", "nope")]),
+            Err(AddressError::Invalid { .. })
+        ));
+
+        assert!(check_agreement(Network::Signet, &[("prover", Network::Signet)]).is_ok());
+        let err = check_agreement(Network::Signet, &[("node", Network::Signet), ("prover", Network::Regtest)])
+            .unwrap_err();
+        assert!(err.to_string().contains("prover is on regtest"));
+    }
+}
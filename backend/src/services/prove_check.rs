@@ -0,0 +1,284 @@
+//! Checks on transactions the prover returns
+//!
+//! The prover assembles the spell transaction itself, so before its
+//! transactions are handed out for signing they are decoded and held against
+//! the spell that was submitted: the spell transaction has to spend the
+//! spell's inputs, and each output has to pay the spell's address and amount
+//! and carry exactly the charms the spell gives it. A prover bug or a response
+//! tampered with in transit then fails the prove instead of reaching a wallet.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+
+use bitcoin::consensus::encode::deserialize_hex;
+use bitcoin::{Address, Network, OutPoint, Transaction};
+use charms_data::App;
+use thiserror::Error;
+
+use crate::services::charms::ProvedTransaction;
+use crate::services::network;
+use crate::services::spell_decode::decode_spell_tx;
+use crate::services::spell_schema::Spell;
+
+/// How the prover's transactions differ from the submitted spell
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ProvedMismatch {
+    #[error("Transaction {txid} does not decode: {reason}")]
+    Undecodable { txid: String, reason: String },
+    #[error("Transaction returned as {claimed} hashes to {actual}")]
+    TxidMismatch { claimed: String, actual: String },
+    #[error("No returned transaction carries a spell")]
+    NoSpell,
+    #[error("Spell transaction {txid} does not spend {utxo_id}")]
+    MissingInput { txid: String, utxo_id: String },
+    #[error("Spell transaction {txid} has no output {index}")]
+    MissingOutput { txid: String, index: usize },
+    #[error("Output {index} pays {found}, not {expected}")]
+    WrongAddress { index: usize, expected: String, found: String },
+    #[error("Output {index} is {found} sats, not {expected}")]
+    WrongAmount { index: usize, expected: u64, found: u64 },
+    #[error("Output {index} carries {found} of {app}, not {expected}")]
+    WrongCharm {
+        index: usize,
+        app: String,
+        expected: String,
+        found: String,
+    },
+}
+
+/// Check the prover's transactions against the spell they were proved for
+pub fn verify_proved_txs(spell: &Spell, txs: &[ProvedTransaction], network: Network) -> Result<(), ProvedMismatch> {
+    let mut decoded = Vec::with_capacity(txs.len());
+    for proved in txs {
+        let tx: Transaction = deserialize_hex(proved.hex.trim()).map_err(|e| ProvedMismatch::Undecodable {
+            txid: proved.txid.clone(),
+            reason: e.to_string(),
+        })?;
+        let actual = tx.compute_txid().to_string();
+        if actual != proved.txid {
+            return Err(ProvedMismatch::TxidMismatch {
+                claimed: proved.txid.clone(),
+                actual,
+            });
+        }
+        decoded.push(tx);
+    }
+
+    // The spell transaction comes after the commit transaction it spends
+    let mut spell_tx = None;
+    for tx in decoded.iter().rev() {
+        match decode_spell_tx(tx) {
+            Ok(Some(on_chain)) => {
+                spell_tx = Some((tx, on_chain));
+                break;
+            }
+            Ok(None) => {}
+            Err(e) => {
+                return Err(ProvedMismatch::Undecodable {
+                    txid: tx.compute_txid().to_string(),
+                    reason: format!("{:#}", e),
+                })
+            }
+        }
+    }
+    let Some((tx, on_chain)) = spell_tx else {
+        return Err(ProvedMismatch::NoSpell);
+    };
+    let txid = tx.compute_txid().to_string();
+
+    for input in &spell.ins {
+        let spent = OutPoint::from_str(&input.utxo_id)
+            .is_ok_and(|outpoint| tx.input.iter().any(|txin| txin.previous_output == outpoint));
+        if !spent {
+            return Err(ProvedMismatch::MissingInput {
+                txid,
+                utxo_id: input.utxo_id.clone(),
+            });
+        }
+    }
+
+    let no_charms = BTreeMap::new();
+    for (index, output) in spell.outs.iter().enumerate() {
+        let Some(txout) = tx.output.get(index) else {
+            return Err(ProvedMismatch::MissingOutput { txid, index });
+        };
+
+        if let Some(expected) = output.address.as_deref() {
+            // Addresses on the wrong network were rejected before proving
+            if let Ok(address) = network::check_address(expected, network) {
+                if address.script_pubkey() != txout.script_pubkey {
+                    let found = Address::from_script(&txout.script_pubkey, network)
+                        .map_or_else(|_| txout.script_pubkey.to_hex_string(), |found| found.to_string());
+                    return Err(ProvedMismatch::WrongAddress {
+                        index,
+                        expected: expected.to_string(),
+                        found,
+                    });
+                }
+            }
+        }
+        if let Some(expected) = output.amount {
+            let found = txout.value.to_sat();
+            if found != expected {
+                return Err(ProvedMismatch::WrongAmount { index, expected, found });
+            }
+        }
+
+        let expected: BTreeMap<String, serde_json::Value> = output
+            .charms
+            .iter()
+            .filter_map(|(key, value)| {
+                let app = canonical_app(spell.apps.get(key)?);
+                Some((app, serde_json::to_value(value).ok()?))
+            })
+            .collect();
+        let found = on_chain.charms_at(index as u32).unwrap_or(&no_charms);
+        check_charms(index, &expected, found)?;
+    }
+
+    // Charms on outputs the spell does not list, e.g. the change output
+    for out in on_chain.outs.iter().filter(|out| out.index as usize >= spell.outs.len()) {
+        check_charms(out.index as usize, &no_charms, &out.charms)?;
+    }
+
+    Ok(())
+}
+
+/// Fail unless an output carries exactly the `expected` charms
+fn check_charms(
+    index: usize,
+    expected: &BTreeMap<String, serde_json::Value>,
+    found: &BTreeMap<String, serde_json::Value>,
+) -> Result<(), ProvedMismatch> {
+    let apps: BTreeSet<&String> = expected.keys().chain(found.keys()).collect();
+    for app in apps {
+        let (expected, found) = (expected.get(app), found.get(app));
+        if expected != found {
+            let show = |value: Option<&serde_json::Value>| value.map_or_else(|| "nothing".to_string(), |v| v.to_string());
+            return Err(ProvedMismatch::WrongCharm {
+                index,
+                app: app.clone(),
+                expected: show(expected),
+                found: show(found),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// App as the decoder prints it, so differently written spells compare equal
+fn canonical_app(app: &str) -> String {
+    App::from_str(app).map_or_else(|_| app.to_string(), |app| app.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::blockdata::opcodes::all::{OP_ENDIF, OP_IF};
+    use bitcoin::blockdata::opcodes::OP_FALSE;
+    use bitcoin::consensus::encode::serialize_hex;
+    use bitcoin::script::PushBytesBuf;
+    use bitcoin::transaction::Version;
+    use bitcoin::{Amount, ScriptBuf, Sequence, TxIn, TxOut, Witness};
+    use charms_data::Data;
+    use serde::Serialize;
+
+    #[derive(Serialize)]
+    struct OnChainSpell {
+        version: u32,
+        tx: OnChainTransaction,
+        app_public_inputs: BTreeMap<App, Data>,
+    }
+
+    #[derive(Serialize)]
+    struct OnChainTransaction {
+        outs: Vec<BTreeMap<u32, Data>>,
+    }
+
+    const ADDRESS: &str = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+
+    fn input(outpoint: &str, witness: Witness) -> TxIn {
+        TxIn {
+            previous_output: OutPoint::from_str(outpoint).unwrap(),
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::MAX,
+            witness,
+        }
+    }
+
+    /// Spell transaction spending `funding`, paying `value` to ADDRESS with
+    /// `amount` of `token` on output 0
+    fn spell_tx(funding: &str, token: &App, amount: u64, value: u64) -> ProvedTransaction {
+        let spell = OnChainSpell {
+            version: 8,
+            tx: OnChainTransaction {
+                outs: vec![BTreeMap::from([(0, Data::from(&amount))])],
+            },
+            app_public_inputs: BTreeMap::from([(token.clone(), Data::empty())]),
+        };
+        let data = charms_data::util::write(&(spell, vec![7u8; 64])).unwrap();
+        let envelope = ScriptBuf::builder()
+            .push_opcode(OP_FALSE)
+            .push_opcode(OP_IF)
+            .push_slice(b"spell")
+            .push_slice(PushBytesBuf::try_from(data).unwrap())
+            .push_opcode(OP_ENDIF)
+            .into_script();
+
+        let address = Address::from_str(ADDRESS).unwrap().assume_checked();
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![
+                input(funding, Witness::new()),
+                input(
+                    &format!("{}:0", "bb".repeat(32)),
+                    Witness::from_slice(&[envelope.as_bytes(), &[0xc0; 33]]),
+                ),
+            ],
+            output: vec![TxOut {
+                value: Amount::from_sat(value),
+                script_pubkey: address.script_pubkey(),
+            }],
+        };
+        ProvedTransaction {
+            txid: tx.compute_txid().to_string(),
+            hex: serialize_hex(&tx),
+        }
+    }
+
+    #[test]
+    fn test_verify_proved_txs() {
+        let token = App::from_str(&format!("t/{}/{}", "11".repeat(32), "22".repeat(32))).unwrap();
+        let funding = format!("{}:1", "aa".repeat(32));
+        let spell = Spell::parse(&format!(
+            "version: 8\napps:\n  $TOKEN: {}\nins:\n  - utxo_id: {}\nouts:\n  - address: {}\n    amount: 1000\n    charms:\n      $TOKEN: 500\n",
+            token, funding, ADDRESS
+        ))
+        .unwrap();
+        let check = |tx: ProvedTransaction| verify_proved_txs(&spell, &[tx], Network::Testnet4);
+
+        assert_eq!(check(spell_tx(&funding, &token, 500, 1000)), Ok(()));
+
+        // The prover minted more than the spell asked for
+        assert!(matches!(
+            check(spell_tx(&funding, &token, 600, 1000)),
+            Err(ProvedMismatch::WrongCharm { index: 0, .. })
+        ));
+        assert_eq!(
+            check(spell_tx(&funding, &token, 500, 900)),
+            Err(ProvedMismatch::WrongAmount { index: 0, expected: 1000, found: 900 })
+        );
+        let other_funding = format!("{}:2", "aa".repeat(32));
+        assert!(matches!(
+            check(spell_tx(&other_funding, &token, 500, 1000)),
+            Err(ProvedMismatch::MissingInput { .. })
+        ));
+
+        // A txid that does not belong to the hex
+        let mut relabeled = spell_tx(&funding, &token, 500, 1000);
+        relabeled.txid = "cc".repeat(32);
+        assert!(matches!(check(relabeled), Err(ProvedMismatch::TxidMismatch { .. })));
+    }
+}
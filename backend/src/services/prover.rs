@@ -0,0 +1,434 @@
+//! Prover backends
+//!
+//! `CharmsService` checks and completes a prove request, then hands it to the
+//! `ProverBackend` it was built with: the hosted prover API, the charms CLI on
+//! this host, or a deterministic mock for development and tests. The backend
+//! is chosen once at startup from `MOCK_MODE` and `PROVER_BACKEND` and shared
+//! by every handler.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use axum::async_trait;
+use bitcoin::Transaction;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::services::broadcast_check::{verify_signed_spell_tx, BroadcastMismatch};
+use crate::services::charms::{
+    with_heartbeat, ProgressReporter, ProveStage, ProvedTransaction, ProverError, SpellProveRequest,
+};
+use crate::services::local_prover::LocalProver;
+use crate::services::prover_pool::{EndpointStats, ProverPool};
+use crate::services::retry::RetryPolicy;
+
+/// Kind of backend spells are proved with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProverKind {
+    /// Hosted prover API endpoints
+    Real,
+    /// `charms spell prove` run on this host
+    Local,
+    /// Placeholder transactions that are never broadcast
+    Mock,
+}
+
+/// Proves spells for `CharmsService`
+#[async_trait]
+pub trait ProverBackend: Send + Sync {
+    fn kind(&self) -> ProverKind;
+
+    /// Prove a request whose app binaries and previous transactions are in
+    /// place, reporting progress as it goes
+    async fn prove(
+        &self,
+        request: &SpellProveRequest,
+        reporter: &ProgressReporter<'_>,
+    ) -> Result<Vec<ProvedTransaction>>;
+
+    /// Hosted endpoints with their running statistics, healthiest first
+    fn endpoints(&self) -> Vec<(String, EndpointStats)> {
+        Vec::new()
+    }
+
+    /// The charms CLI this backend proves with, or falls back to
+    fn local(&self) -> Option<&LocalProver> {
+        None
+    }
+
+    /// Whether the transactions it returns carry real spell proofs. Only
+    /// these need app binaries and the transactions the spell spends, and
+    /// are checked against the spell.
+    fn proves_spells(&self) -> bool {
+        true
+    }
+
+    /// Key a proof of `request` is cached under
+    fn cache_key(&self, request: &SpellProveRequest) -> String {
+        request.cache_key()
+    }
+
+    /// Check a signed transaction against the transactions proved for its
+    /// order, escrow or job before it is relayed
+    fn check_signed(
+        &self,
+        signed_hex: &str,
+        proved_hexes: &[String],
+        expected_spend: Option<&str>,
+    ) -> std::result::Result<Transaction, BroadcastMismatch> {
+        verify_signed_spell_tx(signed_hex, proved_hexes, expected_spend)
+    }
+}
+
+/// Backend selected by `MOCK_MODE` (default `true`) and `PROVER_BACKEND`
+/// (`hosted`, `local` or `mock`)
+pub fn from_env() -> Arc<dyn ProverBackend> {
+    if std::env::var("MOCK_MODE").map(|v| v == "true").unwrap_or(true) {
+        return Arc::new(MockProver);
+    }
+
+    match std::env::var("PROVER_BACKEND").as_deref() {
+        Ok("local") => Arc::new(LocalProver::from_env()),
+        Ok("mock") => Arc::new(MockProver),
+        Ok(other) if other != "hosted" => {
+            tracing::warn!("Unknown PROVER_BACKEND {:?}, using the hosted prover", other);
+            Arc::new(RealProver::from_env())
+        }
+        _ => Arc::new(RealProver::from_env()),
+    }
+}
+
+/// Hosted prover API endpoints from `CHARMS_PROVE_API_URL`, with failover,
+/// retries and backoff
+pub struct RealProver {
+    provers: ProverPool,
+    /// Local charms CLI tried when every hosted endpoint is unavailable
+    /// (`PROVER_LOCAL_FALLBACK=true`)
+    local_fallback: Option<LocalProver>,
+    client: reqwest::Client,
+    retry: RetryPolicy,
+}
+
+impl RealProver {
+    pub fn from_env() -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(120)) // ZK proofs take time
+            .build()
+            .expect("Failed to build prover HTTP client");
+
+        let retry = RetryPolicy::from_env(
+            "PROVER",
+            RetryPolicy {
+                max_attempts: 3,
+                base_delay: Duration::from_secs(2),
+                max_delay: Duration::from_secs(30),
+            },
+        );
+
+        let local_fallback = std::env::var("PROVER_LOCAL_FALLBACK")
+            .map(|v| v == "true")
+            .unwrap_or(false)
+            .then(LocalProver::from_env);
+
+        Self {
+            provers: ProverPool::from_env(),
+            local_fallback,
+            client,
+            retry,
+        }
+    }
+
+    /// Try endpoints healthiest first, failing over on transient errors and
+    /// retrying the whole pool with backoff
+    async fn prove_hosted(
+        &self,
+        request: &SpellProveRequest,
+        reporter: &ProgressReporter<'_>,
+    ) -> Result<Vec<ProvedTransaction>> {
+        let mut attempt = 1;
+        loop {
+            let mut last_error = None;
+            for endpoint in self.provers.ranked() {
+                if !endpoint.available() {
+                    continue;
+                }
+
+                let started = Instant::now();
+                let sent = with_heartbeat(self.send_prove_request(&endpoint.url, request), || {
+                    reporter.report(ProveStage::Proving, Some(&endpoint.url), attempt)
+                });
+                match sent.await {
+                    Ok(txs) => {
+                        endpoint.record_success(started.elapsed());
+                        reporter.report(ProveStage::Finalizing, Some(&endpoint.url), attempt);
+                        tracing::info!("Received {} transactions from prover {}", txs.len(), endpoint.url);
+                        return Ok(txs);
+                    }
+                    Err(e) if !e.is_transient() => {
+                        endpoint.record_success(started.elapsed());
+                        return Err(e.into());
+                    }
+                    Err(e) => {
+                        tracing::warn!("Prover {} failed: {}", endpoint.url, e);
+                        endpoint.record_failure(started.elapsed(), &e.to_string());
+                        last_error = Some(e);
+                    }
+                }
+            }
+
+            let Some(error) = last_error else {
+                return Err(ProverError::CircuitOpen {
+                    retry_in_secs: self.provers.next_retry_in().as_secs().max(1),
+                }
+                .into());
+            };
+            if attempt >= self.retry.max_attempts {
+                return Err(error.into());
+            }
+
+            let delay = self.retry.delay(attempt);
+            tracing::warn!(
+                "Prove attempt {}/{} failed on every endpoint; retrying in {:?}",
+                attempt,
+                self.retry.max_attempts,
+                delay
+            );
+            reporter.report(ProveStage::Queued, None, attempt + 1);
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    async fn send_prove_request(
+        &self,
+        url: &str,
+        request: &SpellProveRequest,
+    ) -> std::result::Result<Vec<ProvedTransaction>, ProverError> {
+        tracing::info!("Calling Charms Prover API at {}", url);
+
+        let response = self
+            .client
+            .post(url)
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| {
+                if e.is_builder() {
+                    ProverError::InvalidResponse(e.to_string())
+                } else if e.is_timeout() {
+                    ProverError::Timeout(e.to_string())
+                } else {
+                    ProverError::Transient(e.to_string())
+                }
+            })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                ProverError::Transient(format!("{}: {}", status, body))
+            } else {
+                ProverError::rejected(status.as_u16(), body)
+            });
+        }
+
+        response.json().await.map_err(|e| {
+            if e.is_timeout() {
+                ProverError::Timeout(e.to_string())
+            } else {
+                ProverError::InvalidResponse(e.to_string())
+            }
+        })
+    }
+}
+
+#[async_trait]
+impl ProverBackend for RealProver {
+    fn kind(&self) -> ProverKind {
+        ProverKind::Real
+    }
+
+    async fn prove(
+        &self,
+        request: &SpellProveRequest,
+        reporter: &ProgressReporter<'_>,
+    ) -> Result<Vec<ProvedTransaction>> {
+        let result = self.prove_hosted(request, reporter).await;
+        let unavailable = result
+            .as_ref()
+            .err()
+            .and_then(|e| e.downcast_ref::<ProverError>())
+            .is_some_and(ProverError::is_unavailable);
+
+        match &self.local_fallback {
+            Some(local) if unavailable => {
+                tracing::warn!(
+                    "Hosted provers unavailable ({}); proving with {}",
+                    result.as_ref().err().map(|e| e.to_string()).unwrap_or_default(),
+                    local.url()
+                );
+                prove_local(local, request, reporter).await
+            }
+            _ => result,
+        }
+    }
+
+    fn endpoints(&self) -> Vec<(String, EndpointStats)> {
+        self.provers
+            .ranked()
+            .into_iter()
+            .map(|endpoint| (endpoint.url.clone(), endpoint.stats()))
+            .collect()
+    }
+
+    fn local(&self) -> Option<&LocalProver> {
+        self.local_fallback.as_ref()
+    }
+}
+
+#[async_trait]
+impl ProverBackend for LocalProver {
+    fn kind(&self) -> ProverKind {
+        ProverKind::Local
+    }
+
+    async fn prove(
+        &self,
+        request: &SpellProveRequest,
+        reporter: &ProgressReporter<'_>,
+    ) -> Result<Vec<ProvedTransaction>> {
+        prove_local(self, request, reporter).await
+    }
+
+    fn local(&self) -> Option<&LocalProver> {
+        Some(self)
+    }
+}
+
+/// Prove with the charms CLI on this host
+async fn prove_local(
+    local: &LocalProver,
+    request: &SpellProveRequest,
+    reporter: &ProgressReporter<'_>,
+) -> Result<Vec<ProvedTransaction>> {
+    let url = local.url();
+    let txs = with_heartbeat(local.prove(request), || {
+        reporter.report(ProveStage::Proving, Some(&url), 1)
+    })
+    .await?;
+    reporter.report(ProveStage::Finalizing, Some(&url), 1);
+    tracing::info!("Local prover returned {} transactions", txs.len());
+    Ok(txs)
+}
+
+/// Placeholder proofs for development and tests. The same spell always
+/// yields the same transaction, which only the mock chain accepts.
+pub struct MockProver;
+
+impl MockProver {
+    /// Transaction spending an output named after the spell hash, so it is
+    /// distinct per spell but carries no spell itself
+    fn mock_tx(spell_hash: &[u8]) -> ProvedTransaction {
+        let hex = "0200000001".to_string() + // Version, input count
+            &hex::encode(spell_hash) +       // Prev txid
+            "00000000" +                     // Prev vout
+            "00" +                           // Script length
+            "ffffffff" +                     // Sequence
+            "01" +                           // Output count
+            "0000000000000000" +             // Value
+            "00" +                           // Script length
+            "00000000";                      // Locktime
+
+        ProvedTransaction {
+            hex,
+            txid: format!("mock_{}", hex::encode(&spell_hash[..8])),
+        }
+    }
+}
+
+#[async_trait]
+impl ProverBackend for MockProver {
+    fn kind(&self) -> ProverKind {
+        ProverKind::Mock
+    }
+
+    async fn prove(
+        &self,
+        request: &SpellProveRequest,
+        reporter: &ProgressReporter<'_>,
+    ) -> Result<Vec<ProvedTransaction>> {
+        reporter.report(ProveStage::Proving, Some("mock"), 1);
+        let spell_hash = Sha256::digest(request.spell.as_bytes());
+        reporter.report(ProveStage::Finalizing, Some("mock"), 1);
+        tracing::info!("Mock prover: returning a placeholder transaction");
+        Ok(vec![Self::mock_tx(&spell_hash)])
+    }
+
+    fn proves_spells(&self) -> bool {
+        false
+    }
+
+    /// Placeholder proofs are cached apart from real ones for the same spell
+    fn cache_key(&self, request: &SpellProveRequest) -> String {
+        format!("mock:{}", request.cache_key())
+    }
+
+    /// Placeholder transactions spend an output named after the spell, never
+    /// the UTXO the action is about, so only the proved txid is checked
+    fn check_signed(
+        &self,
+        signed_hex: &str,
+        proved_hexes: &[String],
+        _expected_spend: Option<&str>,
+    ) -> std::result::Result<Transaction, BroadcastMismatch> {
+        verify_signed_spell_tx(signed_hex, proved_hexes, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::charms::CharmsService;
+    use bitcoin::consensus::encode::deserialize_hex;
+    use bitcoin::Transaction;
+    use std::collections::BTreeMap;
+
+    fn request(spell: &str) -> SpellProveRequest {
+        SpellProveRequest {
+            spell: spell.to_string(),
+            binaries: BTreeMap::new(),
+            prev_txs: vec![],
+            funding_utxo: format!("{}:0", "aa".repeat(32)).parse().unwrap(),
+            funding_utxo_value: 10000,
+            change_address: "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx".to_string(),
+            fee_rate: 2.0,
+            chain: "testnet4".to_string(),
+            replaces: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mock_prover_is_deterministic() {
+        // Mock proving needs no app binaries, even for spells that use apps
+        let spell = format!(
+            "version: 8\napps:\n  $ORDER: n/{}/{}\nins: []\nouts: []\n",
+            "11".repeat(32),
+            "22".repeat(32)
+        );
+        let service = CharmsService::new(Arc::new(MockProver), bitcoin::Network::Testnet4);
+        assert_eq!(service.prover().kind(), ProverKind::Mock);
+
+        let first = service.prove_spell_with_progress(request(&spell), &|_| {}).await.unwrap();
+        let again = service.prove_spell_with_progress(request(&spell), &|_| {}).await.unwrap();
+        let other = service.prove_spell_with_progress(request("version: 8\n"), &|_| {}).await.unwrap();
+
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].txid, again[0].txid);
+        assert_eq!(first[0].hex, again[0].hex);
+        assert_ne!(first[0].txid, other[0].txid);
+        assert!(first[0].txid.starts_with("mock_"));
+        assert!(deserialize_hex::<Transaction>(&first[0].hex).is_ok());
+    }
+}
@@ -0,0 +1,199 @@
+//! Prover endpoint pool
+//!
+//! `CHARMS_PROVE_API_URL` may list several prover endpoints separated by
+//! commas. Each endpoint keeps its own circuit breaker and moving averages of
+//! prove latency and error rate; prove requests go to the endpoint with the
+//! lowest expected time to a successful proof and fail over down the ranking.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::services::retry::{CircuitBreaker, CircuitState};
+
+pub const DEFAULT_PROVE_API_URL: &str = "https://v8.charms.dev/spells/prove";
+
+/// Weight of the newest sample in the moving averages
+const EWMA_ALPHA: f64 = 0.2;
+
+/// Running statistics for one endpoint
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EndpointStats {
+    pub requests: u64,
+    pub failures: u64,
+    /// Moving average of prove round-trip time
+    pub avg_latency_ms: Option<u64>,
+    /// Moving average of the failure rate, 0.0 to 1.0
+    pub error_rate: f64,
+    pub last_error: Option<String>,
+    /// "closed", "open" or "half_open"
+    pub circuit: &'static str,
+}
+
+#[derive(Debug)]
+pub struct ProverEndpoint {
+    pub url: String,
+    breaker: CircuitBreaker,
+    stats: Mutex<EndpointStats>,
+}
+
+impl ProverEndpoint {
+    fn new(url: String, breaker: CircuitBreaker) -> Self {
+        Self {
+            url,
+            breaker,
+            stats: Mutex::new(EndpointStats::default()),
+        }
+    }
+
+    /// Whether the endpoint's breaker lets a request through
+    pub fn available(&self) -> bool {
+        self.breaker.acquire().is_ok()
+    }
+
+    /// The endpoint answered; a rejected spell still counts as healthy
+    pub fn record_success(&self, latency: Duration) {
+        self.breaker.record_success();
+        self.record(latency, None);
+    }
+
+    pub fn record_failure(&self, latency: Duration, error: &str) {
+        self.breaker.record_failure();
+        self.record(latency, Some(error));
+    }
+
+    fn record(&self, latency: Duration, error: Option<&str>) {
+        let mut stats = self.stats.lock().unwrap();
+        let latency_ms = latency.as_millis() as f64;
+        let failed = if error.is_some() { 1.0 } else { 0.0 };
+
+        stats.requests += 1;
+        stats.avg_latency_ms = Some(match stats.avg_latency_ms {
+            Some(avg) => (avg as f64 + EWMA_ALPHA * (latency_ms - avg as f64)) as u64,
+            None => latency_ms as u64,
+        });
+        stats.error_rate += EWMA_ALPHA * (failed - stats.error_rate);
+        if let Some(error) = error {
+            stats.failures += 1;
+            stats.last_error = Some(error.to_string());
+        }
+    }
+
+    /// Expected milliseconds until a successful proof: average latency scaled
+    /// by the expected number of tries. Untried endpoints score zero so they
+    /// get measured.
+    fn score(&self) -> f64 {
+        let stats = self.stats.lock().unwrap();
+        let latency = stats.avg_latency_ms.unwrap_or(0) as f64;
+        latency / (1.0 - stats.error_rate).max(0.05)
+    }
+
+    pub fn stats(&self) -> EndpointStats {
+        let mut stats = self.stats.lock().unwrap().clone();
+        stats.circuit = match self.breaker.state() {
+            CircuitState::Closed => "closed",
+            CircuitState::Open { .. } => "open",
+            CircuitState::HalfOpen => "half_open",
+        };
+        stats
+    }
+}
+
+/// The configured prover endpoints
+#[derive(Debug)]
+pub struct ProverPool {
+    endpoints: Vec<ProverEndpoint>,
+}
+
+impl ProverPool {
+    /// Endpoints from `CHARMS_PROVE_API_URL`, each with a breaker configured by
+    /// `PROVER_BREAKER_THRESHOLD` and `PROVER_BREAKER_COOLDOWN_SECS`
+    pub fn from_env() -> Self {
+        let urls = std::env::var("CHARMS_PROVE_API_URL").unwrap_or_else(|_| DEFAULT_PROVE_API_URL.to_string());
+
+        let env_u64 = |name: &str, default: u64| {
+            std::env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+        };
+        let threshold = env_u64("PROVER_BREAKER_THRESHOLD", 5) as u32;
+        let cooldown = Duration::from_secs(env_u64("PROVER_BREAKER_COOLDOWN_SECS", 60));
+
+        Self::new(parse_urls(&urls), threshold, cooldown)
+    }
+
+    fn new(urls: Vec<String>, threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            endpoints: urls
+                .into_iter()
+                .map(|url| ProverEndpoint::new(url, CircuitBreaker::new(threshold, cooldown)))
+                .collect(),
+        }
+    }
+
+    /// Endpoints ordered healthiest first
+    pub fn ranked(&self) -> Vec<&ProverEndpoint> {
+        let mut scored: Vec<_> = self.endpoints.iter().map(|e| (e.score(), e)).collect();
+        scored.sort_by(|a, b| a.0.total_cmp(&b.0));
+        scored.into_iter().map(|(_, endpoint)| endpoint).collect()
+    }
+
+    /// Time until the first open breaker lets a trial through
+    pub fn next_retry_in(&self) -> Duration {
+        self.endpoints
+            .iter()
+            .filter_map(|e| match e.breaker.state() {
+                CircuitState::Open { retry_in } => Some(retry_in),
+                _ => None,
+            })
+            .min()
+            .unwrap_or_default()
+    }
+}
+
+/// Comma-separated URLs, falling back to the public prover when none are given
+fn parse_urls(value: &str) -> Vec<String> {
+    let urls: Vec<String> = value
+        .split(',')
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+        .collect();
+
+    if urls.is_empty() {
+        vec![DEFAULT_PROVE_API_URL.to_string()]
+    } else {
+        urls
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranking_prefers_fast_reliable_endpoints() {
+        assert_eq!(parse_urls(" a , ,b"), vec!["a", "b"]);
+        assert_eq!(parse_urls(""), vec![DEFAULT_PROVE_API_URL]);
+
+        let pool = ProverPool::new(
+            vec!["slow".into(), "flaky".into(), "fast".into()],
+            2,
+            Duration::from_secs(60),
+        );
+        let endpoint = |url: &str| pool.endpoints.iter().find(|e| e.url == url).unwrap();
+
+        endpoint("slow").record_success(Duration::from_secs(40));
+        endpoint("fast").record_success(Duration::from_secs(10));
+        endpoint("flaky").record_success(Duration::from_secs(5));
+        for _ in 0..4 {
+            endpoint("flaky").record_failure(Duration::from_secs(5), "502");
+        }
+
+        let order: Vec<_> = pool.ranked().iter().map(|e| e.url.as_str()).collect();
+        assert_eq!(order, vec!["fast", "flaky", "slow"]);
+
+        let flaky = endpoint("flaky").stats();
+        assert_eq!(flaky.failures, 4);
+        assert_eq!(flaky.circuit, "open");
+        assert!(pool.next_retry_in() > Duration::ZERO);
+    }
+}
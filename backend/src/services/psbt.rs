@@ -0,0 +1,664 @@
+//! PSBT construction for prover-built transactions
+//!
+//! The prover returns raw commit and spell transactions. Wallets and hardware
+//! signers want BIP-174 PSBTs with the spent outputs attached, so each
+//! transaction is converted with its previous outputs looked up from the other
+//! transactions in the batch, caller-supplied `prev_txs`, or the node.
+//! Inputs that already carry a witness (the prover-signed spell input) are
+//! kept as finalized.
+//!
+//! Hardware wallets additionally need the key origin (master fingerprint and
+//! derivation path) of each input they sign, and show the user a summary of
+//! what is being spent and paid instead of a raw hex blob.
+//!
+//! When several parties sign one transaction (maker and taker in a fill),
+//! each returns its own partially signed copy. `finalize_psbts` combines
+//! them, finalizes the inputs it knows how to (P2WPKH, taproot key path, and
+//! taproot script-path leaves with every signature and hashlock preimage
+//! present) and extracts the transaction once every input is final.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use bitcoin::bip32::{DerivationPath, Fingerprint};
+use bitcoin::consensus::encode::deserialize_hex;
+use bitcoin::consensus::encode::serialize_hex;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::opcodes::all::{OP_CHECKSIG, OP_CHECKSIGADD, OP_CHECKSIGVERIFY, OP_SHA256};
+use bitcoin::psbt::{Input, Psbt};
+use bitcoin::script::Instruction;
+use bitcoin::secp256k1::{PublicKey, Secp256k1, XOnlyPublicKey};
+use bitcoin::taproot::TapLeafHash;
+use bitcoin::{Address, Network, Script, ScriptBuf, Transaction, TxOut, Txid, Witness};
+use serde::{Deserialize, Serialize};
+
+use crate::services::chain::ChainBackend;
+
+/// Per-input metadata returned alongside a PSBT
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PsbtInputInfo {
+    pub index: usize,
+    pub outpoint: String,
+    /// Value of the spent output in sats, when it could be resolved
+    pub value: Option<u64>,
+    pub address: Option<String>,
+    /// Already signed (e.g. the prover's spell input); wallets should skip it
+    pub finalized: bool,
+}
+
+/// Output of a PSBT as shown to the signer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PsbtOutputInfo {
+    pub index: usize,
+    pub value: u64,
+    /// Destination address, or `None` for non-standard scripts
+    pub address: Option<String>,
+}
+
+/// A transaction converted to a PSBT
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuiltPsbt {
+    pub txid: String,
+    pub psbt_base64: String,
+    pub inputs: Vec<PsbtInputInfo>,
+    pub outputs: Vec<PsbtOutputInfo>,
+    /// Transaction fee in sats, when every input value is known
+    pub fee: Option<u64>,
+    /// Human-readable lines describing what the transaction spends and pays
+    pub summary: Vec<String>,
+}
+
+/// Where a signer's key comes from in its wallet (BIP-32)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyOrigin {
+    /// Master key fingerprint, 8 hex characters
+    pub master_fingerprint: String,
+    /// Derivation path, e.g. `m/86'/1'/0'/0/0`
+    pub derivation_path: String,
+    /// Public key at that path (hex, compressed or x-only)
+    pub pubkey: String,
+}
+
+impl KeyOrigin {
+    /// Parse the fingerprint, path and key, rejecting malformed origins
+    pub fn parse(&self) -> Result<(Fingerprint, DerivationPath, KeyOriginPubkey)> {
+        let fingerprint = Fingerprint::from_str(&self.master_fingerprint).context("Invalid master fingerprint")?;
+        let path = DerivationPath::from_str(&self.derivation_path).context("Invalid derivation path")?;
+        let bytes = hex::decode(&self.pubkey).context("Public key is not hex")?;
+        let pubkey = match bytes.len() {
+            32 => KeyOriginPubkey::XOnly(XOnlyPublicKey::from_slice(&bytes)?),
+            33 => KeyOriginPubkey::Full(PublicKey::from_slice(&bytes)?),
+            n => bail!("Public key must be 32 or 33 bytes, got {}", n),
+        };
+        Ok((fingerprint, path, pubkey))
+    }
+}
+
+/// Public key of a key origin, as supplied
+#[derive(Debug, Clone, Copy)]
+pub enum KeyOriginPubkey {
+    Full(PublicKey),
+    XOnly(XOnlyPublicKey),
+}
+
+/// Build PSBTs for a batch of raw transactions (in spending order), attaching
+/// the given key origins to the inputs at those indexes
+pub async fn build_psbts(
+    chain: &dyn ChainBackend,
+    tx_hexes: &[String],
+    prev_tx_hexes: &[String],
+    key_origins: &HashMap<usize, KeyOrigin>,
+    network: Network,
+) -> Result<Vec<BuiltPsbt>> {
+    let txs = tx_hexes
+        .iter()
+        .map(|hex| deserialize_hex::<Transaction>(hex).context("Invalid transaction hex"))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut known: HashMap<Txid, Transaction> = HashMap::new();
+    for hex in prev_tx_hexes {
+        let tx: Transaction = deserialize_hex(hex).context("Invalid prev_tx hex")?;
+        known.insert(tx.compute_txid(), tx);
+    }
+    for tx in &txs {
+        known.insert(tx.compute_txid(), tx.clone());
+    }
+
+    // Anything still missing is fetched from the node; failures leave the
+    // input without UTXO metadata rather than failing the whole batch
+    for tx in &txs {
+        for input in &tx.input {
+            let txid = input.previous_output.txid;
+            if known.contains_key(&txid) {
+                continue;
+            }
+            match fetch_transaction(chain, &txid).await {
+                Ok(prev) => {
+                    known.insert(txid, prev);
+                }
+                Err(e) => tracing::warn!("Could not fetch previous transaction {}: {}", txid, e),
+            }
+        }
+    }
+
+    txs.iter().map(|tx| build_psbt(tx, &known, key_origins, network)).collect()
+}
+
+/// Convert one transaction to a PSBT using the known previous transactions
+pub fn build_psbt(
+    tx: &Transaction,
+    known: &HashMap<Txid, Transaction>,
+    key_origins: &HashMap<usize, KeyOrigin>,
+    network: Network,
+) -> Result<BuiltPsbt> {
+    let mut unsigned = tx.clone();
+    for input in &mut unsigned.input {
+        input.script_sig = ScriptBuf::new();
+        input.witness = Witness::new();
+    }
+
+    let mut psbt = Psbt::from_unsigned_tx(unsigned).context("Failed to create PSBT")?;
+    let mut inputs = Vec::with_capacity(tx.input.len());
+    let mut total_in = Some(0u64);
+
+    for (index, input) in tx.input.iter().enumerate() {
+        let outpoint = input.previous_output;
+        let prevout = known
+            .get(&outpoint.txid)
+            .and_then(|prev| prev.output.get(outpoint.vout as usize).map(|out| (prev, out)));
+
+        let psbt_input = &mut psbt.inputs[index];
+        if let Some((prev, out)) = prevout {
+            if out.script_pubkey.is_witness_program() {
+                psbt_input.witness_utxo = Some(out.clone());
+            } else {
+                psbt_input.non_witness_utxo = Some(prev.clone());
+            }
+        }
+
+        let finalized = !input.witness.is_empty() || !input.script_sig.is_empty();
+        if !input.witness.is_empty() {
+            psbt_input.final_script_witness = Some(input.witness.clone());
+        }
+        if !input.script_sig.is_empty() {
+            psbt_input.final_script_sig = Some(input.script_sig.clone());
+        }
+
+        if let (Some(origin), Some((_, out)), false) = (key_origins.get(&index), prevout, finalized) {
+            apply_key_origin(psbt_input, out, origin)?;
+        }
+
+        let value = prevout.map(|(_, out)| out.value.to_sat());
+        total_in = total_in.zip(value).map(|(sum, v)| sum + v);
+
+        inputs.push(PsbtInputInfo {
+            index,
+            outpoint: outpoint.to_string(),
+            value,
+            address: prevout
+                .and_then(|(_, out)| Address::from_script(&out.script_pubkey, network).ok())
+                .map(|a| a.to_string()),
+            finalized,
+        });
+    }
+
+    let outputs: Vec<PsbtOutputInfo> = tx
+        .output
+        .iter()
+        .enumerate()
+        .map(|(index, out)| PsbtOutputInfo {
+            index,
+            value: out.value.to_sat(),
+            address: Address::from_script(&out.script_pubkey, network).ok().map(|a| a.to_string()),
+        })
+        .collect();
+
+    let total_out: u64 = outputs.iter().map(|out| out.value).sum();
+    let fee = total_in.and_then(|sum| sum.checked_sub(total_out));
+
+    Ok(BuiltPsbt {
+        txid: tx.compute_txid().to_string(),
+        psbt_base64: BASE64.encode(psbt.serialize()),
+        summary: summarize(&inputs, &outputs, fee),
+        inputs,
+        outputs,
+        fee,
+    })
+}
+
+/// Record the signer's key origin on an input: key-path taproot inputs get the
+/// internal key and `tap_key_origins`, ECDSA inputs get `bip32_derivation`
+fn apply_key_origin(input: &mut Input, prevout: &TxOut, origin: &KeyOrigin) -> Result<()> {
+    let (fingerprint, path, pubkey) = origin.parse()?;
+
+    if prevout.script_pubkey.is_p2tr() {
+        let xonly = match pubkey {
+            KeyOriginPubkey::Full(key) => key.x_only_public_key().0,
+            KeyOriginPubkey::XOnly(key) => key,
+        };
+        let secp = Secp256k1::verification_only();
+        if prevout.script_pubkey == ScriptBuf::new_p2tr(&secp, xonly, None) {
+            input.tap_internal_key = Some(xonly);
+            input.tap_key_origins.insert(xonly, (vec![], (fingerprint, path)));
+        }
+    } else if let KeyOriginPubkey::Full(key) = pubkey {
+        input.bip32_derivation.insert(key, (fingerprint, path));
+    }
+    Ok(())
+}
+
+/// Readable description of a transaction for the signing device's owner
+fn summarize(inputs: &[PsbtInputInfo], outputs: &[PsbtOutputInfo], fee: Option<u64>) -> Vec<String> {
+    let mut lines = Vec::with_capacity(inputs.len() + outputs.len() + 1);
+
+    for input in inputs {
+        let value = input.value.map_or("an unknown amount".to_string(), |v| format!("{} sats", v));
+        let from = input.address.as_deref().unwrap_or("an unknown script");
+        let signed = if input.finalized { " (already signed)" } else { "" };
+        lines.push(format!("Spend {} from {} ({}){}", value, from, input.outpoint, signed));
+    }
+    for output in outputs {
+        let to = output.address.as_deref().unwrap_or("a non-standard script");
+        lines.push(format!("Pay {} sats to {}", output.value, to));
+    }
+    lines.push(match fee {
+        Some(fee) => format!("Network fee: {} sats", fee),
+        None => "Network fee: unknown (some inputs could not be resolved)".to_string(),
+    });
+
+    lines
+}
+
+/// Co-signed PSBTs merged into one, and the transaction once complete
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FinalizedPsbt {
+    pub txid: String,
+    /// The combined PSBT, with every input that could be finalized final
+    pub psbt_base64: String,
+    /// Every input is final and `tx_hex` is ready to broadcast
+    pub complete: bool,
+    /// Inputs still missing signatures
+    pub unfinalized_inputs: Vec<usize>,
+    pub tx_hex: Option<String>,
+}
+
+/// Combine partially signed copies of one transaction (base64 PSBTs),
+/// finalize what can be and extract the transaction when complete
+pub fn finalize_psbts(psbts_base64: &[String]) -> Result<FinalizedPsbt> {
+    let mut decoded = psbts_base64.iter().enumerate().map(|(index, encoded)| {
+        let bytes = BASE64.decode(encoded.trim()).with_context(|| format!("PSBT {} is not base64", index))?;
+        Psbt::deserialize(&bytes).with_context(|| format!("PSBT {} does not decode", index))
+    });
+    let Some(first) = decoded.next() else {
+        bail!("At least one PSBT is required");
+    };
+    let mut psbt = first?;
+    for (index, other) in decoded.enumerate() {
+        psbt.combine(other?)
+            .with_context(|| format!("PSBT {} is not for the same transaction", index + 1))?;
+    }
+
+    let unfinalized_inputs: Vec<usize> = (0..psbt.inputs.len())
+        .filter(|&index| !finalize_input(&mut psbt.inputs[index]))
+        .collect();
+    let complete = unfinalized_inputs.is_empty();
+    let txid = psbt.unsigned_tx.compute_txid().to_string();
+    let psbt_base64 = BASE64.encode(psbt.serialize());
+    // Fee sanity is left to the broadcast preflight, which also sees the
+    // inputs a PSBT may carry no UTXO for
+    let tx_hex = complete.then(|| serialize_hex(&psbt.extract_tx_unchecked_fee_rate()));
+
+    Ok(FinalizedPsbt {
+        txid,
+        psbt_base64,
+        complete,
+        unfinalized_inputs,
+        tx_hex,
+    })
+}
+
+/// Finalize an input from its signatures, clearing the signing fields as
+/// BIP-174 asks; `false` when it cannot be finalized (yet)
+fn finalize_input(input: &mut Input) -> bool {
+    if input.final_script_witness.is_some() || input.final_script_sig.is_some() {
+        return true;
+    }
+    let Some(script_pubkey) = input.witness_utxo.as_ref().map(|utxo| utxo.script_pubkey.clone()) else {
+        return false;
+    };
+
+    let witness = if script_pubkey.is_p2wpkh() {
+        // One key, one signature
+        input
+            .partial_sigs
+            .iter()
+            .next()
+            .map(|(pubkey, sig)| Witness::p2wpkh(sig, &pubkey.inner))
+    } else if script_pubkey.is_p2tr() {
+        input
+            .tap_key_sig
+            .map(|sig| Witness::p2tr_key_spend(&sig))
+            .or_else(|| script_path_witness(input))
+    } else {
+        None
+    };
+    let Some(witness) = witness else {
+        return false;
+    };
+
+    input.final_script_witness = Some(witness);
+    input.partial_sigs.clear();
+    input.sighash_type = None;
+    input.redeem_script = None;
+    input.witness_script = None;
+    input.bip32_derivation.clear();
+    input.tap_key_sig = None;
+    input.tap_script_sigs.clear();
+    input.sha256_preimages.clear();
+    input.tap_scripts.clear();
+    input.tap_key_origins.clear();
+    input.tap_internal_key = None;
+    input.tap_merkle_root = None;
+    true
+}
+
+/// Witness spending a taproot leaf whose every signature and preimage is
+/// present: those in reverse script order, then the script and control block
+fn script_path_witness(input: &Input) -> Option<Witness> {
+    input.tap_scripts.iter().find_map(|(control_block, (script, leaf_version))| {
+        let leaf_hash = TapLeafHash::from_script(script, *leaf_version);
+        let needed = leaf_inputs(script);
+        if needed.is_empty() {
+            return None;
+        }
+        let items = needed
+            .iter()
+            .rev()
+            .map(|item| match item {
+                LeafInput::Signature(key) => input.tap_script_sigs.get(&(*key, leaf_hash)).map(|sig| sig.to_vec()),
+                LeafInput::Preimage(hash) => input.sha256_preimages.get(hash).cloned(),
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let mut witness = Witness::new();
+        for item in items {
+            witness.push(item);
+        }
+        witness.push(script.as_bytes());
+        witness.push(control_block.serialize());
+        Some(witness)
+    })
+}
+
+/// What a tapscript leaf takes from the witness stack
+enum LeafInput {
+    Signature(XOnlyPublicKey),
+    Preimage(sha256::Hash),
+}
+
+/// Signatures and hashlock preimages a tapscript checks, in script order:
+/// keys pushed before a signature check, and hashes after `OP_SHA256`
+fn leaf_inputs(script: &Script) -> Vec<LeafInput> {
+    let instructions: Vec<Instruction> = script.instructions().filter_map(Result::ok).collect();
+    instructions
+        .windows(2)
+        .filter_map(|pair| match pair {
+            [Instruction::PushBytes(bytes), Instruction::Op(op)]
+                if bytes.len() == 32 && [OP_CHECKSIG, OP_CHECKSIGVERIFY, OP_CHECKSIGADD].contains(op) =>
+            {
+                XOnlyPublicKey::from_slice(bytes.as_bytes()).ok().map(LeafInput::Signature)
+            }
+            [Instruction::Op(op), Instruction::PushBytes(bytes)] if *op == OP_SHA256 => {
+                sha256::Hash::from_slice(bytes.as_bytes()).ok().map(LeafInput::Preimage)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+async fn fetch_transaction(chain: &dyn ChainBackend, txid: &Txid) -> Result<Transaction> {
+    let hex = chain.raw_transaction(&txid.to_string()).await?;
+    Ok(deserialize_hex(&hex)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::transaction::Version;
+    use bitcoin::{Amount, OutPoint, Sequence, TxIn, TxOut, WPubkeyHash};
+
+    fn tx(inputs: Vec<(OutPoint, Witness)>, values: &[u64]) -> Transaction {
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: inputs
+                .into_iter()
+                .map(|(previous_output, witness)| TxIn {
+                    previous_output,
+                    script_sig: ScriptBuf::new(),
+                    sequence: Sequence::MAX,
+                    witness,
+                })
+                .collect(),
+            output: values
+                .iter()
+                .map(|v| TxOut {
+                    value: Amount::from_sat(*v),
+                    script_pubkey: ScriptBuf::new_p2wpkh(&WPubkeyHash::all_zeros()),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_build_psbt_resolves_batch_prevouts() {
+        let funding = tx(vec![(OutPoint::null(), Witness::new())], &[10_000]);
+        let commit = tx(vec![(OutPoint::new(funding.compute_txid(), 0), Witness::new())], &[9_000]);
+        let spell = tx(
+            vec![(OutPoint::new(commit.compute_txid(), 0), Witness::from_slice(&[vec![1u8; 64]]))],
+            &[8_500],
+        );
+
+        let known: HashMap<Txid, Transaction> = [&funding, &commit]
+            .into_iter()
+            .map(|t| (t.compute_txid(), t.clone()))
+            .collect();
+
+        let built = build_psbt(&spell, &known, &HashMap::new(), Network::Testnet4).unwrap();
+        assert_eq!(built.fee, Some(500));
+        assert_eq!(built.summary.last().unwrap(), "Network fee: 500 sats");
+        assert_eq!(built.inputs[0].value, Some(9_000));
+        assert!(built.inputs[0].finalized);
+
+        let psbt = Psbt::deserialize(&BASE64.decode(&built.psbt_base64).unwrap()).unwrap();
+        assert!(psbt.inputs[0].witness_utxo.is_some());
+        assert!(psbt.unsigned_tx.input[0].witness.is_empty());
+
+        // Unknown previous output leaves the fee undetermined
+        let built = build_psbt(&commit, &HashMap::new(), &HashMap::new(), Network::Testnet4).unwrap();
+        assert_eq!(built.fee, None);
+        assert!(!built.inputs[0].finalized);
+    }
+
+    #[test]
+    fn test_build_psbt_attaches_key_origin() {
+        let secp = Secp256k1::new();
+        let secret = bitcoin::secp256k1::SecretKey::from_slice(&[7u8; 32]).unwrap();
+        let pubkey = PublicKey::from_secret_key(&secp, &secret);
+
+        let mut funding = tx(vec![(OutPoint::null(), Witness::new())], &[10_000]);
+        funding.output[0].script_pubkey = ScriptBuf::new_p2wpkh(&bitcoin::CompressedPublicKey(pubkey).wpubkey_hash());
+        let spend = tx(vec![(OutPoint::new(funding.compute_txid(), 0), Witness::new())], &[9_000]);
+
+        let known = HashMap::from([(funding.compute_txid(), funding)]);
+        let origins = HashMap::from([(
+            0,
+            KeyOrigin {
+                master_fingerprint: "d34db33f".to_string(),
+                derivation_path: "m/84'/1'/0'/0/0".to_string(),
+                pubkey: hex::encode(pubkey.serialize()),
+            },
+        )]);
+
+        let built = build_psbt(&spend, &known, &origins, Network::Testnet4).unwrap();
+        let psbt = Psbt::deserialize(&BASE64.decode(&built.psbt_base64).unwrap()).unwrap();
+        let (fingerprint, path) = &psbt.inputs[0].bip32_derivation[&pubkey];
+        assert_eq!(fingerprint.to_string(), "d34db33f");
+        assert_eq!(path.to_string(), "84'/1'/0'/0/0");
+    }
+
+    #[test]
+    fn test_finalize_psbts_combines_cosigners() {
+        use bitcoin::secp256k1::{Keypair, Message};
+        use bitcoin::{ecdsa, taproot, CompressedPublicKey, TapSighashType};
+
+        let secp = Secp256k1::new();
+        let maker = bitcoin::secp256k1::SecretKey::from_slice(&[7u8; 32]).unwrap();
+        let maker_pubkey = CompressedPublicKey(PublicKey::from_secret_key(&secp, &maker));
+        let taker = Keypair::from_seckey_slice(&secp, &[9u8; 32]).unwrap();
+        let taker_key = taker.x_only_public_key().0;
+        let message = Message::from_digest([1u8; 32]);
+
+        let mut funding = tx(vec![(OutPoint::null(), Witness::new())], &[10_000, 20_000]);
+        funding.output[0].script_pubkey = ScriptBuf::new_p2wpkh(&maker_pubkey.wpubkey_hash());
+        funding.output[1].script_pubkey = ScriptBuf::new_p2tr(&secp, taker_key, None);
+        let fill = tx(
+            vec![
+                (OutPoint::new(funding.compute_txid(), 0), Witness::new()),
+                (OutPoint::new(funding.compute_txid(), 1), Witness::new()),
+            ],
+            &[29_000],
+        );
+        let known = HashMap::from([(funding.compute_txid(), funding)]);
+        let built = build_psbt(&fill, &known, &HashMap::new(), Network::Testnet4).unwrap();
+        let unsigned = Psbt::deserialize(&BASE64.decode(&built.psbt_base64).unwrap()).unwrap();
+
+        // Each party signs its own input of its own copy
+        let mut maker_copy = unsigned.clone();
+        maker_copy.inputs[0].partial_sigs.insert(
+            bitcoin::PublicKey::new(maker_pubkey.0),
+            ecdsa::Signature::sighash_all(secp.sign_ecdsa(&message, &maker)),
+        );
+        let mut taker_copy = unsigned.clone();
+        taker_copy.inputs[1].tap_key_sig = Some(taproot::Signature {
+            signature: secp.sign_schnorr_no_aux_rand(&message, &taker),
+            sighash_type: TapSighashType::Default,
+        });
+        let encode = |psbt: &Psbt| BASE64.encode(psbt.serialize());
+
+        let partial = finalize_psbts(&[encode(&maker_copy)]).unwrap();
+        assert!(!partial.complete);
+        assert_eq!(partial.unfinalized_inputs, vec![1]);
+        assert_eq!(partial.tx_hex, None);
+
+        let finalized = finalize_psbts(&[encode(&maker_copy), encode(&taker_copy)]).unwrap();
+        assert!(finalized.complete);
+        assert_eq!(finalized.txid, fill.compute_txid().to_string());
+        let signed: Transaction = deserialize_hex(finalized.tx_hex.as_ref().unwrap()).unwrap();
+        assert_eq!(signed.input[0].witness.len(), 2);
+        assert_eq!(signed.input[1].witness.len(), 1);
+
+        // A copy of some other transaction cannot be combined
+        let unrelated = tx(vec![(OutPoint::null(), Witness::new())], &[1]);
+        let other = build_psbt(&unrelated, &HashMap::new(), &HashMap::new(), Network::Testnet4).unwrap();
+        assert!(finalize_psbts(&[encode(&maker_copy), other.psbt_base64]).is_err());
+        assert!(finalize_psbts(&[]).is_err());
+    }
+
+    #[test]
+    fn test_script_path_needs_every_leaf_key() {
+        use bitcoin::secp256k1::{Keypair, Message};
+        use bitcoin::taproot::{LeafVersion, TaprootBuilder};
+        use bitcoin::{taproot, TapSighashType};
+
+        let secp = Secp256k1::new();
+        let keypair = |byte| Keypair::from_seckey_slice(&secp, &[byte; 32]).unwrap();
+        let (a, b) = (keypair(3), keypair(4));
+        let leaf = bitcoin::script::Builder::new()
+            .push_x_only_key(&a.x_only_public_key().0)
+            .push_opcode(OP_CHECKSIGVERIFY)
+            .push_x_only_key(&b.x_only_public_key().0)
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
+        let internal = keypair(5).x_only_public_key().0;
+        let spend_info = TaprootBuilder::new().add_leaf(0, leaf.clone()).unwrap().finalize(&secp, internal).unwrap();
+        let control_block = spend_info.control_block(&(leaf.clone(), LeafVersion::TapScript)).unwrap();
+        let leaf_hash = TapLeafHash::from_script(&leaf, LeafVersion::TapScript);
+
+        let mut input = Input {
+            witness_utxo: Some(TxOut {
+                value: bitcoin::Amount::from_sat(10_000),
+                script_pubkey: ScriptBuf::new_p2tr_tweaked(spend_info.output_key()),
+            }),
+            ..Default::default()
+        };
+        input.tap_scripts.insert(control_block.clone(), (leaf.clone(), LeafVersion::TapScript));
+        let sign = |keypair: &Keypair| taproot::Signature {
+            signature: secp.sign_schnorr_no_aux_rand(&Message::from_digest([2u8; 32]), keypair),
+            sighash_type: TapSighashType::Default,
+        };
+        input.tap_script_sigs.insert((a.x_only_public_key().0, leaf_hash), sign(&a));
+        assert!(!finalize_input(&mut input.clone()));
+
+        input.tap_script_sigs.insert((b.x_only_public_key().0, leaf_hash), sign(&b));
+        assert!(finalize_input(&mut input));
+        let witness = input.final_script_witness.unwrap();
+        // a's key is checked first, so its signature goes on top of the
+        // stack, after b's
+        assert_eq!(witness.len(), 4);
+        assert_eq!(witness.nth(0).unwrap(), sign(&b).to_vec().as_slice());
+        assert_eq!(witness.nth(2).unwrap(), leaf.as_bytes());
+        assert!(input.tap_scripts.is_empty());
+    }
+
+    #[test]
+    fn test_hashlock_leaf_needs_preimage() {
+        use bitcoin::opcodes::all::OP_EQUALVERIFY;
+        use bitcoin::secp256k1::{Keypair, Message};
+        use bitcoin::taproot::{LeafVersion, TaprootBuilder};
+        use bitcoin::{taproot, TapSighashType};
+
+        let secp = Secp256k1::new();
+        let recipient = Keypair::from_seckey_slice(&secp, &[6; 32]).unwrap();
+        let hash = sha256::Hash::hash(b"secret");
+        let leaf = bitcoin::script::Builder::new()
+            .push_opcode(OP_SHA256)
+            .push_slice(hash.to_byte_array())
+            .push_opcode(OP_EQUALVERIFY)
+            .push_x_only_key(&recipient.x_only_public_key().0)
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
+        let internal = Keypair::from_seckey_slice(&secp, &[7; 32]).unwrap().x_only_public_key().0;
+        let spend_info = TaprootBuilder::new().add_leaf(0, leaf.clone()).unwrap().finalize(&secp, internal).unwrap();
+        let control_block = spend_info.control_block(&(leaf.clone(), LeafVersion::TapScript)).unwrap();
+
+        let mut input = Input {
+            witness_utxo: Some(TxOut {
+                value: bitcoin::Amount::from_sat(10_000),
+                script_pubkey: ScriptBuf::new_p2tr_tweaked(spend_info.output_key()),
+            }),
+            ..Default::default()
+        };
+        input.tap_scripts.insert(control_block, (leaf.clone(), LeafVersion::TapScript));
+        let sig = taproot::Signature {
+            signature: secp.sign_schnorr_no_aux_rand(&Message::from_digest([2u8; 32]), &recipient),
+            sighash_type: TapSighashType::Default,
+        };
+        let leaf_hash = TapLeafHash::from_script(&leaf, LeafVersion::TapScript);
+        input.tap_script_sigs.insert((recipient.x_only_public_key().0, leaf_hash), sig);
+        // The hash is not mistaken for a key, but the preimage is required
+        assert!(!finalize_input(&mut input.clone()));
+
+        input.sha256_preimages.insert(hash, b"secret".to_vec());
+        assert!(finalize_input(&mut input));
+        let witness = input.final_script_witness.unwrap();
+        // OP_SHA256 runs first, so the preimage sits on top of the signature
+        assert_eq!(witness.len(), 4);
+        assert_eq!(witness.nth(0).unwrap(), sig.to_vec().as_slice());
+        assert_eq!(witness.nth(1).unwrap(), b"secret");
+        assert!(input.sha256_preimages.is_empty());
+    }
+}
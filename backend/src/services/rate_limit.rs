@@ -0,0 +1,95 @@
+//! Per-user request rate limits
+//!
+//! Every request made with a wallet session counts against the session's
+//! user, whichever wallet or client sent it: at most `USER_RATE_LIMIT`
+//! requests (default 120; 0 turns the limit off) in each minute-long window.
+//! Counts live in memory, so each instance limits on its own.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Default requests per user per window
+const DEFAULT_USER_RATE_LIMIT: u32 = 120;
+
+/// Length of a rate limit window
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Users tracked before those with expired windows are swept out
+const SWEEP_THRESHOLD: usize = 10_000;
+
+/// Fixed-window request counts, by user ID
+pub struct UserRateLimiter {
+    limit: u32,
+    window: Duration,
+    /// Start of each user's current window and the requests made in it
+    windows: Mutex<HashMap<String, (Instant, u32)>>,
+}
+
+impl UserRateLimiter {
+    /// Limiter allowing `USER_RATE_LIMIT` requests per user per minute
+    pub fn from_env() -> Self {
+        let limit = std::env::var("USER_RATE_LIMIT")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_USER_RATE_LIMIT);
+        Self::new(limit, WINDOW)
+    }
+
+    pub fn new(limit: u32, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Count a request by `user_id`. Over the limit, returns how long until
+    /// the user's window ends.
+    pub fn check(&self, user_id: &str) -> Result<(), Duration> {
+        if self.limit == 0 {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        let mut windows = self.windows.lock().expect("rate limiter lock poisoned");
+        if windows.len() >= SWEEP_THRESHOLD {
+            windows.retain(|_, (started, _)| now.duration_since(*started) < self.window);
+        }
+
+        let (started, count) = windows.entry(user_id.to_string()).or_insert((now, 0));
+        if now.duration_since(*started) >= self.window {
+            *started = now;
+            *count = 0;
+        }
+        if *count >= self.limit {
+            return Err(self.window - now.duration_since(*started));
+        }
+        *count += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits_each_user_per_window() {
+        let limiter = UserRateLimiter::new(2, Duration::from_millis(50));
+        assert!(limiter.check("alice").is_ok());
+        assert!(limiter.check("alice").is_ok());
+        let retry_after = limiter.check("alice").unwrap_err();
+        assert!(retry_after <= Duration::from_millis(50));
+
+        // Another user has their own allowance
+        assert!(limiter.check("bob").is_ok());
+
+        // A new window starts the count again
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(limiter.check("alice").is_ok());
+
+        let unlimited = UserRateLimiter::new(0, WINDOW);
+        assert!((0..1000).all(|_| unlimited.check("alice").is_ok()));
+    }
+}
@@ -0,0 +1,162 @@
+//! Retries and circuit breaking for outbound calls
+//!
+//! `RetryPolicy` spaces attempts with exponential backoff and jitter so many
+//! clients do not retry in lockstep. `CircuitBreaker` stops calling a service
+//! after repeated failures and lets a single trial call through once the
+//! cooldown has passed.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How many times to attempt a call and how long to wait in between
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Read `<PREFIX>_MAX_ATTEMPTS`, `<PREFIX>_RETRY_BASE_MS` and
+    /// `<PREFIX>_RETRY_MAX_MS`, falling back to the given defaults
+    pub fn from_env(prefix: &str, defaults: RetryPolicy) -> Self {
+        let var = |name: &str| -> Option<u64> {
+            std::env::var(format!("{}_{}", prefix, name)).ok().and_then(|v| v.parse().ok())
+        };
+
+        Self {
+            max_attempts: var("MAX_ATTEMPTS").map_or(defaults.max_attempts, |n| n.max(1) as u32),
+            base_delay: var("RETRY_BASE_MS").map_or(defaults.base_delay, Duration::from_millis),
+            max_delay: var("RETRY_MAX_MS").map_or(defaults.max_delay, Duration::from_millis),
+        }
+    }
+
+    /// Delay before the retry following failed attempt `attempt` (1-based):
+    /// the exponential backoff capped at `max_delay`, jittered to 50-100% of it
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16);
+        let backoff = self.base_delay.saturating_mul(1 << exponent).min(self.max_delay);
+        backoff.mul_f64(0.5 + jitter() * 0.5)
+    }
+}
+
+/// Uniform value in [0, 1)
+fn jitter() -> f64 {
+    (uuid::Uuid::new_v4().as_u128() >> 75) as f64 / (1u64 << 53) as f64
+}
+
+/// Circuit breaker state as reported to health checks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    Closed,
+    /// Calls are rejected until the cooldown passes
+    Open { retry_in: Duration },
+    /// Cooldown passed; the next call is a trial
+    HalfOpen,
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+/// Opens after `failure_threshold` consecutive failures and rejects calls for
+/// `cooldown`
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    /// Whether a call may proceed; `Err` holds the time until the next trial.
+    /// Letting a trial through re-arms the cooldown so only one runs at a time.
+    pub fn acquire(&self) -> Result<(), Duration> {
+        let mut state = self.state.lock().unwrap();
+        match state.open_until {
+            Some(until) if until > Instant::now() => Err(until - Instant::now()),
+            Some(_) => {
+                state.open_until = Some(Instant::now() + self.cooldown);
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    pub fn record_success(&self) {
+        *self.state.lock().unwrap() = BreakerState::default();
+    }
+
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= self.failure_threshold {
+            state.open_until = Some(Instant::now() + self.cooldown);
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        let state = self.state.lock().unwrap();
+        match state.open_until {
+            Some(until) if until > Instant::now() => CircuitState::Open {
+                retry_in: until - Instant::now(),
+            },
+            Some(_) => CircuitState::HalfOpen,
+            None => CircuitState::Closed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_is_capped_and_jittered() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1000),
+        };
+
+        for _ in 0..20 {
+            let first = policy.delay(1);
+            assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+            let third = policy.delay(3);
+            assert!(third >= Duration::from_millis(200) && third <= Duration::from_millis(400));
+            assert!(policy.delay(30) <= Duration::from_millis(1000));
+        }
+    }
+
+    #[test]
+    fn test_breaker_opens_and_half_opens() {
+        let breaker = CircuitBreaker::new(2, Duration::from_millis(20));
+        assert!(breaker.acquire().is_ok());
+
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        breaker.record_failure();
+        assert!(matches!(breaker.state(), CircuitState::Open { .. }));
+        assert!(breaker.acquire().is_err());
+
+        std::thread::sleep(Duration::from_millis(25));
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        // One trial goes through; others wait for its outcome
+        assert!(breaker.acquire().is_ok());
+        assert!(breaker.acquire().is_err());
+
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.acquire().is_ok());
+    }
+}
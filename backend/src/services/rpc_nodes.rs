@@ -0,0 +1,145 @@
+//! Bitcoin RPC node pool
+//!
+//! `BITCOIN_RPC_URL` may list several nodes separated by commas. With more
+//! than one, each node's block height is checked every
+//! `BITCOIN_RPC_HEALTH_SECS` (default 30). Calls go to reachable nodes with
+//! the most blocks first and fail over down the ranking when a node cannot be
+//! reached, so restarting one node does not stop broadcasting or
+//! confirmation tracking.
+
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+pub const DEFAULT_RPC_URL: &str = "http://127.0.0.1:48332";
+
+/// What is known about one node
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeHealth {
+    pub url: String,
+    /// Whether the last call or health check reached it
+    pub reachable: bool,
+    /// Height at the last health check
+    pub blocks: Option<u64>,
+    pub failures: u64,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct RpcNode {
+    pub url: String,
+    health: Mutex<NodeHealth>,
+}
+
+impl RpcNode {
+    fn new(url: String) -> Self {
+        Self {
+            health: Mutex::new(NodeHealth {
+                url: url.clone(),
+                reachable: true,
+                blocks: None,
+                failures: 0,
+                last_error: None,
+            }),
+            url,
+        }
+    }
+
+    /// The node answered a call
+    pub fn record_success(&self) {
+        self.health.lock().unwrap().reachable = true;
+    }
+
+    /// The node answered a health check at `blocks`
+    pub fn record_height(&self, blocks: u64) {
+        let mut health = self.health.lock().unwrap();
+        health.reachable = true;
+        health.blocks = Some(blocks);
+    }
+
+    /// The node could not be reached or was not ready
+    pub fn record_failure(&self, error: &str) {
+        let mut health = self.health.lock().unwrap();
+        health.reachable = false;
+        health.failures += 1;
+        health.last_error = Some(error.to_string());
+    }
+
+    pub fn health(&self) -> NodeHealth {
+        self.health.lock().unwrap().clone()
+    }
+}
+
+/// The configured nodes
+#[derive(Debug)]
+pub struct RpcNodes {
+    nodes: Vec<RpcNode>,
+}
+
+impl RpcNodes {
+    /// Nodes from a comma-separated list of URLs, falling back to the local
+    /// testnet4 node when none are given
+    pub fn new(urls: &str) -> Self {
+        let mut urls: Vec<String> = urls
+            .split(',')
+            .map(|url| url.trim().trim_end_matches('/').to_string())
+            .filter(|url| !url.is_empty())
+            .collect();
+        if urls.is_empty() {
+            urls.push(DEFAULT_RPC_URL.to_string());
+        }
+
+        Self {
+            nodes: urls.into_iter().map(RpcNode::new).collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn all(&self) -> &[RpcNode] {
+        &self.nodes
+    }
+
+    /// Reachable nodes first, most synced first, otherwise in configured
+    /// order. Unreachable nodes stay at the end so they are still tried
+    /// when every node is down.
+    pub fn ranked(&self) -> Vec<&RpcNode> {
+        let mut ranked: Vec<(NodeHealth, &RpcNode)> = self.nodes.iter().map(|n| (n.health(), n)).collect();
+        ranked.sort_by_key(|(health, _)| (!health.reachable, std::cmp::Reverse(health.blocks)));
+        ranked.into_iter().map(|(_, node)| node).collect()
+    }
+
+    pub fn health(&self) -> Vec<NodeHealth> {
+        self.nodes.iter().map(RpcNode::health).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranking_prefers_reachable_synced_nodes() {
+        let nodes = RpcNodes::new(" http://a/ , ,http://b,http://c");
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(RpcNodes::new("").all()[0].url, DEFAULT_RPC_URL);
+
+        let order = |nodes: &RpcNodes| nodes.ranked().iter().map(|n| n.url.clone()).collect::<Vec<_>>();
+        assert_eq!(order(&nodes), vec!["http://a", "http://b", "http://c"]);
+
+        nodes.all()[0].record_height(100);
+        nodes.all()[1].record_height(101);
+        nodes.all()[2].record_height(101);
+        assert_eq!(order(&nodes), vec!["http://b", "http://c", "http://a"]);
+
+        nodes.all()[1].record_failure("connection refused");
+        assert_eq!(order(&nodes), vec!["http://c", "http://a", "http://b"]);
+        let b = &nodes.health()[1];
+        assert_eq!((b.reachable, b.failures), (false, 1));
+
+        nodes.all()[1].record_success();
+        assert_eq!(order(&nodes)[0], "http://b");
+    }
+}
@@ -0,0 +1,181 @@
+//! Envelope encryption for sensitive values at rest
+//!
+//! Private spell inputs (signatures, preimages, fill data) kept with prove
+//! jobs and order spells, and the preimages revealed to claim escrows, are
+//! sealed before they are written to the database and opened only when they
+//! are used. The rest of a spell, signed transactions and an escrow's pending
+//! action (only which spell it waits on) hold nothing secret and are stored
+//! as they are.
+//!
+//! Every value is encrypted with its own AES-256-GCM data key, which is
+//! stored alongside it wrapped by a key-encryption key from
+//! `DATA_ENCRYPTION_KEYS` (`<id>:<base64 32-byte key>`, comma-separated).
+//! The first key seals new values; keys after it are kept so values sealed
+//! before a rotation still open. Without keys values are stored as given,
+//! which suits development.
+
+use std::collections::BTreeMap;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+
+/// Prefix of sealed values; anything else was stored before sealing was
+/// enabled and is read as is
+const SEALED_PREFIX: &str = "sealed:v1:";
+
+/// AES-GCM nonce length in bytes
+const NONCE_LEN: usize = 12;
+
+/// Seals and opens values with the configured key-encryption keys
+pub struct Sealer {
+    /// Id of the key new values are sealed with
+    current: Option<String>,
+    keys: BTreeMap<String, Key<Aes256Gcm>>,
+}
+
+impl Sealer {
+    /// Keys from `DATA_ENCRYPTION_KEYS`; a malformed key is an error rather
+    /// than a silent fallback to plaintext
+    pub fn from_env() -> Result<Self> {
+        let Ok(config) = std::env::var("DATA_ENCRYPTION_KEYS") else {
+            return Ok(Self::new(Vec::new()));
+        };
+
+        let keys = config
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (id, key) = entry
+                    .split_once(':')
+                    .with_context(|| format!("Data encryption key {:?} is not <id>:<base64 key>", entry))?;
+                let key: [u8; 32] = BASE64
+                    .decode(key)
+                    .ok()
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .with_context(|| format!("Data encryption key {} is not 32 base64-encoded bytes", id))?;
+                Ok((id.to_string(), key))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::new(keys))
+    }
+
+    /// Sealer with the given keys, the first sealing new values
+    pub fn new(keys: Vec<(String, [u8; 32])>) -> Self {
+        Self {
+            current: keys.first().map(|(id, _)| id.clone()),
+            keys: keys
+                .into_iter()
+                .map(|(id, key)| (id, Key::<Aes256Gcm>::from(key)))
+                .collect(),
+        }
+    }
+
+    /// Whether new values are sealed
+    pub fn is_enabled(&self) -> bool {
+        self.current.is_some()
+    }
+
+    /// Seal `plaintext` for storage. `purpose` names where the value is
+    /// stored and must be given again to open it, so a sealed value cannot
+    /// be moved to another column.
+    pub fn seal(&self, purpose: &str, plaintext: &str) -> Result<String> {
+        let Some(key_id) = &self.current else {
+            return Ok(plaintext.to_string());
+        };
+
+        let data_key = Aes256Gcm::generate_key(OsRng);
+        let wrapped_key = encrypt(&self.keys[key_id], data_key.as_slice(), key_id.as_bytes())?;
+        let ciphertext = encrypt(&data_key, plaintext.as_bytes(), purpose.as_bytes())?;
+        Ok(format!(
+            "{}{}:{}:{}",
+            SEALED_PREFIX,
+            key_id,
+            BASE64.encode(wrapped_key),
+            BASE64.encode(ciphertext)
+        ))
+    }
+
+    /// Open a value stored with `seal`; values stored unsealed are returned
+    /// unchanged
+    pub fn open(&self, purpose: &str, stored: &str) -> Result<String> {
+        let Some(sealed) = stored.strip_prefix(SEALED_PREFIX) else {
+            return Ok(stored.to_string());
+        };
+
+        let mut parts = sealed.splitn(3, ':');
+        let (Some(key_id), Some(wrapped_key), Some(ciphertext)) = (parts.next(), parts.next(), parts.next()) else {
+            bail!("Sealed value is malformed");
+        };
+        let key = self
+            .keys
+            .get(key_id)
+            .with_context(|| format!("Data encryption key {} is not configured", key_id))?;
+
+        let data_key = decrypt(key, &BASE64.decode(wrapped_key)?, key_id.as_bytes())?;
+        let data_key = Key::<Aes256Gcm>::from_exact_iter(data_key).context("Unwrapped data key has the wrong length")?;
+        let plaintext = decrypt(&data_key, &BASE64.decode(ciphertext)?, purpose.as_bytes())?;
+        String::from_utf8(plaintext).context("Sealed value is not UTF-8")
+    }
+
+    /// `seal` for optional columns
+    pub fn seal_optional(&self, purpose: &str, plaintext: Option<&str>) -> Result<Option<String>> {
+        plaintext.map(|value| self.seal(purpose, value)).transpose()
+    }
+
+    /// `open` for optional columns
+    pub fn open_optional(&self, purpose: &str, stored: Option<&str>) -> Result<Option<String>> {
+        stored.map(|value| self.open(purpose, value)).transpose()
+    }
+}
+
+/// Encrypt with a fresh nonce, returned in front of the ciphertext
+fn encrypt(key: &Key<Aes256Gcm>, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+    let nonce = Aes256Gcm::generate_nonce(OsRng);
+    let ciphertext = Aes256Gcm::new(key)
+        .encrypt(&nonce, Payload { msg: plaintext, aad })
+        .map_err(|_| anyhow!("Encryption failed"))?;
+    Ok([nonce.as_slice(), &ciphertext].concat())
+}
+
+fn decrypt(key: &Key<Aes256Gcm>, sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+    if sealed.len() < NONCE_LEN {
+        bail!("Sealed value is truncated");
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    Aes256Gcm::new(key)
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+        .map_err(|_| anyhow!("Sealed value does not decrypt; wrong key or tampered data"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open_across_key_rotation() {
+        let old = Sealer::new(vec![("k1".to_string(), [1; 32])]);
+        let sealed = old.seal("prove_jobs.private_inputs", "preimage: abcd").unwrap();
+        assert!(sealed.starts_with("sealed:v1:k1:"));
+        assert!(!sealed.contains("abcd"));
+        // A fresh data key and nonce every time
+        assert_ne!(sealed, old.seal("prove_jobs.private_inputs", "preimage: abcd").unwrap());
+
+        // After rotating, new values use k2 and old ones still open
+        let rotated = Sealer::new(vec![("k2".to_string(), [2; 32]), ("k1".to_string(), [1; 32])]);
+        assert_eq!(rotated.open("prove_jobs.private_inputs", &sealed).unwrap(), "preimage: abcd");
+        assert!(rotated.seal("x", "y").unwrap().starts_with("sealed:v1:k2:"));
+
+        // Bound to where it is stored, and to the configured keys
+        assert!(rotated.open("escrows.preimage", &sealed).is_err());
+        assert!(Sealer::new(vec![("k2".to_string(), [2; 32])]).open("prove_jobs.private_inputs", &sealed).is_err());
+
+        // Without keys nothing is sealed, and rows stored unsealed still read
+        let disabled = Sealer::new(Vec::new());
+        assert!(!disabled.is_enabled());
+        assert_eq!(disabled.seal("x", "plain").unwrap(), "plain");
+        assert_eq!(rotated.open("x", "plain").unwrap(), "plain");
+    }
+}
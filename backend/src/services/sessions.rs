@@ -0,0 +1,302 @@
+//! Wallet connect sessions
+//!
+//! Connecting is a challenge-response: the server issues a single-use nonce
+//! for an address, the wallet signs the challenge message (BIP-322), and a
+//! verified signature is exchanged for a bearer session token.
+//!
+//! A session can hold several wallets: further addresses are linked by signing
+//! their own challenge, and queries made with the session cover all of them.
+//!
+//! Every verified address is a user in the `users` table; sessions and their
+//! linked wallets live in `sessions` and `session_wallets`, keyed by the
+//! SHA-256 of the token, and outstanding challenges in `connect_challenges`,
+//! so a challenge issued by one instance can be answered on another and none
+//! of it is lost on restart. Key origins stay in memory, as do the per-user
+//! request counts sessions are rate limited by.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::db::{self, ChallengeRecord, DbPool, SessionRecord, SessionWalletRecord};
+use crate::services::psbt::KeyOrigin;
+use crate::services::rate_limit::UserRateLimiter;
+
+/// How long a challenge can be signed before it must be re-issued
+const CHALLENGE_TTL_SECS: i64 = 300;
+
+/// Default cap on outstanding challenges when `MAX_CONNECT_CHALLENGES` is unset
+const DEFAULT_MAX_CHALLENGES: i64 = 10_000;
+
+/// Default session lifetime when `SESSION_TTL_SECS` is unset
+const DEFAULT_SESSION_TTL_SECS: i64 = 86_400;
+
+/// Nonce issued to an address awaiting its signature
+#[derive(Debug, Clone, Serialize)]
+pub struct Challenge {
+    pub address: String,
+    pub nonce: String,
+    /// Exact message the wallet must sign
+    pub message: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl From<ChallengeRecord> for Challenge {
+    fn from(record: ChallengeRecord) -> Self {
+        Self {
+            address: record.address,
+            nonce: record.nonce,
+            message: record.message,
+            expires_at: record.expires_at,
+        }
+    }
+}
+
+/// Authenticated wallet session
+#[derive(Debug, Clone, Serialize)]
+pub struct Session {
+    #[serde(skip_serializing)]
+    pub token: String,
+    /// User who opened the session; per-user data hangs off this
+    pub user_id: String,
+    /// Address the session was opened with
+    pub address: String,
+    /// Every address proven to belong to the user, starting with `address`
+    pub addresses: Vec<String>,
+    /// Public keys declared for the linked wallets, used to find their escrows
+    pub pubkeys: Vec<String>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Session {
+    /// Whether an address is one of the session's linked wallets
+    pub fn owns(&self, address: &str) -> bool {
+        self.addresses.iter().any(|a| a == address)
+    }
+
+    /// Assemble a session from its stored row and linked wallets; the opening
+    /// address always comes first
+    fn from_records(token: &str, record: SessionRecord, wallets: Vec<SessionWalletRecord>) -> Self {
+        let mut addresses = vec![record.address.clone()];
+        let mut pubkeys: Vec<String> = Vec::new();
+        for wallet in wallets {
+            if !addresses.contains(&wallet.address) {
+                addresses.push(wallet.address);
+            }
+            if let Some(pubkey) = wallet.pubkey {
+                if !pubkeys.contains(&pubkey) {
+                    pubkeys.push(pubkey);
+                }
+            }
+        }
+
+        Self {
+            token: token.to_string(),
+            user_id: record.user_id,
+            address: record.address,
+            addresses,
+            pubkeys,
+            expires_at: record.expires_at,
+        }
+    }
+}
+
+/// Sessions and outstanding challenges in the database, with key origins
+/// and rate limits in memory
+#[derive(Clone)]
+pub struct SessionStore {
+    db: DbPool,
+    /// Hardware wallet key origins registered at connect, by address
+    key_origins: Arc<RwLock<HashMap<String, KeyOrigin>>>,
+    session_ttl: Duration,
+    /// Most challenges that may be outstanding at once
+    max_challenges: i64,
+    limiter: Arc<UserRateLimiter>,
+}
+
+impl SessionStore {
+    /// Create a session store on the database, with the session lifetime
+    /// from `SESSION_TTL_SECS`, the challenge cap from `MAX_CONNECT_CHALLENGES`
+    /// and the per-user limit from `USER_RATE_LIMIT`
+    pub fn new(db: DbPool) -> Self {
+        let ttl = std::env::var("SESSION_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_SESSION_TTL_SECS);
+        let max_challenges = std::env::var("MAX_CONNECT_CHALLENGES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MAX_CHALLENGES);
+
+        Self {
+            db,
+            key_origins: Default::default(),
+            session_ttl: Duration::seconds(ttl),
+            max_challenges,
+            limiter: Arc::new(UserRateLimiter::from_env()),
+        }
+    }
+
+    /// Count a request made with one of `session`'s user's sessions. Over
+    /// the limit, returns how long until the user may make another.
+    pub fn check_rate(&self, session: &Session) -> Result<(), std::time::Duration> {
+        self.limiter.check(&session.user_id)
+    }
+
+    /// Issue a fresh challenge for an address, replacing any earlier one.
+    /// Returns None while the cap of outstanding challenges is reached.
+    pub async fn issue_challenge(&self, address: &str) -> Result<Option<Challenge>> {
+        let nonce = Uuid::new_v4().simple().to_string();
+        let challenge = Challenge {
+            address: address.to_string(),
+            message: format!("liquid-nation:connect:{}:{}", address, nonce),
+            nonce,
+            expires_at: Utc::now() + Duration::seconds(CHALLENGE_TTL_SECS),
+        };
+
+        if let Err(e) = db::delete_expired_challenges(&self.db).await {
+            tracing::warn!("Failed to delete expired challenges: {}", e);
+        }
+        let stored = db::upsert_challenge(
+            &self.db,
+            address,
+            &challenge.nonce,
+            &challenge.message,
+            challenge.expires_at,
+            self.max_challenges,
+        )
+        .await?;
+        Ok(stored.then_some(challenge))
+    }
+
+    /// Consume the outstanding challenge for an address, if it has not expired
+    pub async fn take_challenge(&self, address: &str) -> Result<Option<Challenge>> {
+        let Some(record) = db::take_challenge(&self.db, address).await? else {
+            return Ok(None);
+        };
+        let challenge = Challenge::from(record);
+        Ok((challenge.expires_at > Utc::now()).then_some(challenge))
+    }
+
+    /// Open a session for an address whose signature has been verified
+    pub async fn create_session(&self, address: &str) -> Result<Session> {
+        let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+
+        if let Err(e) = db::delete_expired_sessions(&self.db).await {
+            tracing::warn!("Failed to delete expired sessions: {}", e);
+        }
+        db::insert_session(&self.db, &token_hash(&token), address, Utc::now() + self.session_ttl).await?;
+
+        self.session(&token)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Session for {} vanished right after it was opened", address))
+    }
+
+    /// Link a verified address (and optionally its public key) to a live session
+    pub async fn link_address(&self, token: &str, address: &str, pubkey: Option<&str>) -> Result<Option<Session>> {
+        if self.session(token).await?.is_none() {
+            return Ok(None);
+        }
+
+        db::link_session_wallet(&self.db, &token_hash(token), address, pubkey).await?;
+        self.session(token).await
+    }
+
+    /// Remove a linked address; the address the session was opened with stays
+    pub async fn unlink_address(&self, token: &str, address: &str) -> Result<Option<Session>> {
+        let Some(session) = self.session(token).await? else {
+            return Ok(None);
+        };
+
+        if address != session.address {
+            db::remove_session_wallet(&self.db, &token_hash(token), address).await?;
+        }
+        self.session(token).await
+    }
+
+    /// End a session, returning false if it was not live
+    pub async fn end_session(&self, token: &str) -> Result<bool> {
+        db::delete_session(&self.db, &token_hash(token)).await
+    }
+
+    /// Remember the key origin of a connected address so signing payloads
+    /// for it can carry derivation paths
+    pub async fn set_key_origin(&self, address: &str, origin: KeyOrigin) {
+        self.key_origins.write().await.insert(address.to_string(), origin);
+    }
+
+    /// Key origin registered for an address, if any
+    pub async fn key_origin(&self, address: &str) -> Option<KeyOrigin> {
+        self.key_origins.read().await.get(address).cloned()
+    }
+
+    /// Look up a live session by token
+    pub async fn session(&self, token: &str) -> Result<Option<Session>> {
+        let hash = token_hash(token);
+        let Some(record) = db::get_session(&self.db, &hash).await? else {
+            return Ok(None);
+        };
+        let wallets = db::get_session_wallets(&self.db, &hash).await?;
+        Ok(Some(Session::from_records(token, record, wallets)))
+    }
+}
+
+/// Sessions are stored under the SHA-256 of their token, so a database read
+/// does not hand out usable tokens
+fn token_hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wallet(id: i64, address: &str, pubkey: Option<&str>) -> SessionWalletRecord {
+        SessionWalletRecord {
+            id,
+            token_hash: token_hash("token"),
+            address: address.to_string(),
+            pubkey: pubkey.map(str::to_string),
+            linked_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_session_from_records() {
+        let record = SessionRecord {
+            token_hash: token_hash("token"),
+            user_id: "user-1".to_string(),
+            address: "tb1qprimary".to_string(),
+            expires_at: Utc::now() + Duration::hours(1),
+            created_at: Utc::now(),
+        };
+        let wallets = vec![
+            wallet(1, "tb1qprimary", Some("02ab")),
+            wallet(2, "tb1psecond", Some("02ab")),
+            wallet(3, "tb1pthird", None),
+        ];
+
+        let session = Session::from_records("token", record.clone(), wallets);
+        assert_eq!(session.user_id, "user-1");
+        assert_eq!(session.addresses, vec!["tb1qprimary", "tb1psecond", "tb1pthird"]);
+        assert_eq!(session.pubkeys, vec!["02ab"]);
+        assert!(session.owns("tb1psecond"));
+
+        // The opening address leads even without its wallet row
+        let session = Session::from_records("token", record, vec![wallet(2, "tb1psecond", None)]);
+        assert_eq!(session.addresses, vec!["tb1qprimary", "tb1psecond"]);
+        assert!(session.pubkeys.is_empty());
+    }
+
+    #[test]
+    fn test_token_hash() {
+        assert_eq!(token_hash("token").len(), 64);
+        assert_ne!(token_hash("token"), token_hash("other"));
+    }
+}
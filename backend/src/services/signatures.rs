@@ -0,0 +1,256 @@
+//! Signature verification for user-authorized actions
+//!
+//! Actions are authorized by signing the SHA-256 digest of a canonical message.
+//! Compressed (33-byte) public keys are checked as ECDSA (DER or 64-byte compact
+//! signatures); x-only (32-byte) keys are checked as BIP-340 Schnorr.
+//!
+//! Wallet ownership is proven with BIP-322 "simple" message signatures, which
+//! is what browser wallets produce for `signMessage` on segwit and taproot
+//! addresses.
+
+use std::str::FromStr;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use bitcoin::absolute::LockTime;
+use bitcoin::blockdata::opcodes::all::OP_RETURN;
+use bitcoin::blockdata::opcodes::OP_0;
+use bitcoin::hashes::Hash;
+use bitcoin::script::Builder;
+use bitcoin::secp256k1::{ecdsa, schnorr, Message, PublicKey, Secp256k1, XOnlyPublicKey};
+use bitcoin::sighash::{Prevouts, SighashCache};
+use bitcoin::transaction::Version;
+use bitcoin::{
+    Address, Amount, CompressedPublicKey, OutPoint, ScriptBuf, Sequence, Transaction, TxIn,
+    TxOut, Txid, Witness,
+};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+/// BIP-340 tag for the BIP-322 message hash
+const BIP322_TAG: &[u8] = b"BIP0322-signed-message";
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SignatureError {
+    #[error("Public key is not valid hex-encoded secp256k1 key")]
+    InvalidPublicKey,
+    #[error("Signature is malformed")]
+    MalformedSignature,
+    #[error("Signature does not match the public key and message")]
+    Mismatch,
+    #[error("Address is not a valid Bitcoin address")]
+    InvalidAddress,
+    #[error("Only P2WPKH and P2TR addresses support BIP-322 simple signatures")]
+    UnsupportedAddress,
+}
+
+/// Digest that gets signed for a message
+pub fn message_digest(message: &str) -> [u8; 32] {
+    Sha256::digest(message.as_bytes()).into()
+}
+
+/// Verify a hex signature over `message` against a hex public key
+pub fn verify_signature(pubkey_hex: &str, message: &str, signature_hex: &str) -> Result<(), SignatureError> {
+    let pubkey = hex::decode(pubkey_hex).map_err(|_| SignatureError::InvalidPublicKey)?;
+    let signature = hex::decode(signature_hex).map_err(|_| SignatureError::MalformedSignature)?;
+
+    let secp = Secp256k1::verification_only();
+    let msg = Message::from_digest(message_digest(message));
+
+    match pubkey.len() {
+        32 => {
+            let key = XOnlyPublicKey::from_slice(&pubkey).map_err(|_| SignatureError::InvalidPublicKey)?;
+            let sig = schnorr::Signature::from_slice(&signature).map_err(|_| SignatureError::MalformedSignature)?;
+            secp.verify_schnorr(&sig, &msg, &key).map_err(|_| SignatureError::Mismatch)
+        }
+        33 | 65 => {
+            let key = PublicKey::from_slice(&pubkey).map_err(|_| SignatureError::InvalidPublicKey)?;
+            let mut sig = if signature.len() == 64 {
+                ecdsa::Signature::from_compact(&signature)
+            } else {
+                ecdsa::Signature::from_der(&signature)
+            }
+            .map_err(|_| SignatureError::MalformedSignature)?;
+            sig.normalize_s();
+            secp.verify_ecdsa(&msg, &sig, &key).map_err(|_| SignatureError::Mismatch)
+        }
+        _ => Err(SignatureError::InvalidPublicKey),
+    }
+}
+
+/// Canonical hex encoding of a signature by `pubkey_hex`: ECDSA as compact
+/// low-S, Schnorr as lowercase hex. Another encoding of the same signature
+/// has the same canonical form.
+pub fn canonical_signature(pubkey_hex: &str, signature_hex: &str) -> Result<String, SignatureError> {
+    let pubkey = hex::decode(pubkey_hex).map_err(|_| SignatureError::InvalidPublicKey)?;
+    let signature = hex::decode(signature_hex).map_err(|_| SignatureError::MalformedSignature)?;
+
+    match pubkey.len() {
+        32 => schnorr::Signature::from_slice(&signature)
+            .map(|sig| hex::encode(sig.serialize()))
+            .map_err(|_| SignatureError::MalformedSignature),
+        33 | 65 => {
+            let mut sig = if signature.len() == 64 {
+                ecdsa::Signature::from_compact(&signature)
+            } else {
+                ecdsa::Signature::from_der(&signature)
+            }
+            .map_err(|_| SignatureError::MalformedSignature)?;
+            sig.normalize_s();
+            Ok(hex::encode(sig.serialize_compact()))
+        }
+        _ => Err(SignatureError::InvalidPublicKey),
+    }
+}
+
+/// BIP-322 tagged hash of a message
+pub fn bip322_message_hash(message: &str) -> [u8; 32] {
+    let tag = Sha256::digest(BIP322_TAG);
+    let mut engine = Sha256::new();
+    engine.update(tag);
+    engine.update(tag);
+    engine.update(message.as_bytes());
+    engine.finalize().into()
+}
+
+/// Verify a base64 BIP-322 simple signature (a serialized witness) by
+/// `address` over `message`
+pub fn verify_bip322(address: &str, message: &str, signature_b64: &str) -> Result<(), SignatureError> {
+    let address = Address::from_str(address)
+        .map_err(|_| SignatureError::InvalidAddress)?
+        .assume_checked();
+    let script_pubkey = address.script_pubkey();
+
+    let witness_bytes = BASE64.decode(signature_b64).map_err(|_| SignatureError::MalformedSignature)?;
+    let witness: Witness = bitcoin::consensus::deserialize(&witness_bytes)
+        .map_err(|_| SignatureError::MalformedSignature)?;
+
+    let to_sign = bip322_to_sign(&script_pubkey, message, witness.clone());
+    let secp = Secp256k1::verification_only();
+    let mut cache = SighashCache::new(&to_sign);
+
+    if script_pubkey.is_p2wpkh() {
+        if witness.len() != 2 {
+            return Err(SignatureError::MalformedSignature);
+        }
+        let sig = bitcoin::ecdsa::Signature::from_slice(&witness[0])
+            .map_err(|_| SignatureError::MalformedSignature)?;
+        let pubkey = CompressedPublicKey::from_slice(&witness[1])
+            .map_err(|_| SignatureError::InvalidPublicKey)?;
+        if ScriptBuf::new_p2wpkh(&pubkey.wpubkey_hash()) != script_pubkey {
+            return Err(SignatureError::Mismatch);
+        }
+
+        let sighash = cache
+            .p2wpkh_signature_hash(0, &script_pubkey, Amount::ZERO, sig.sighash_type)
+            .map_err(|_| SignatureError::MalformedSignature)?;
+        let msg = Message::from_digest(sighash.to_byte_array());
+        secp.verify_ecdsa(&msg, &sig.signature, &pubkey.0).map_err(|_| SignatureError::Mismatch)
+    } else if script_pubkey.is_p2tr() {
+        if witness.len() != 1 {
+            return Err(SignatureError::MalformedSignature);
+        }
+        let sig = bitcoin::taproot::Signature::from_slice(&witness[0])
+            .map_err(|_| SignatureError::MalformedSignature)?;
+        let output_key = XOnlyPublicKey::from_slice(&script_pubkey.as_bytes()[2..])
+            .map_err(|_| SignatureError::InvalidPublicKey)?;
+
+        let prevout = TxOut { value: Amount::ZERO, script_pubkey };
+        let sighash = cache
+            .taproot_key_spend_signature_hash(0, &Prevouts::All(&[prevout]), sig.sighash_type)
+            .map_err(|_| SignatureError::MalformedSignature)?;
+        let msg = Message::from_digest(sighash.to_byte_array());
+        secp.verify_schnorr(&sig.signature, &msg, &output_key).map_err(|_| SignatureError::Mismatch)
+    } else {
+        Err(SignatureError::UnsupportedAddress)
+    }
+}
+
+/// The virtual `to_sign` transaction whose input carries the signature witness
+fn bip322_to_sign(script_pubkey: &ScriptBuf, message: &str, witness: Witness) -> Transaction {
+    let to_spend = Transaction {
+        version: Version(0),
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint { txid: Txid::all_zeros(), vout: 0xFFFF_FFFF },
+            script_sig: Builder::new()
+                .push_opcode(OP_0)
+                .push_slice(bip322_message_hash(message))
+                .into_script(),
+            sequence: Sequence::ZERO,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut { value: Amount::ZERO, script_pubkey: script_pubkey.clone() }],
+    };
+
+    Transaction {
+        version: Version(0),
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint { txid: to_spend.compute_txid(), vout: 0 },
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ZERO,
+            witness,
+        }],
+        output: vec![TxOut {
+            value: Amount::ZERO,
+            script_pubkey: Builder::new().push_opcode(OP_RETURN).into_script(),
+        }],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::secp256k1::{Keypair, SecretKey};
+
+    #[test]
+    fn test_verify_ecdsa_and_schnorr() {
+        let secp = Secp256k1::new();
+        let secret = SecretKey::from_slice(&[7u8; 32]).unwrap();
+        let msg = Message::from_digest(message_digest("hello"));
+
+        let pubkey = hex::encode(PublicKey::from_secret_key(&secp, &secret).serialize());
+        let sig = hex::encode(secp.sign_ecdsa(&msg, &secret).serialize_der());
+        assert_eq!(verify_signature(&pubkey, "hello", &sig), Ok(()));
+        assert_eq!(verify_signature(&pubkey, "other", &sig), Err(SignatureError::Mismatch));
+
+        let keypair = Keypair::from_secret_key(&secp, &secret);
+        let xonly = hex::encode(keypair.x_only_public_key().0.serialize());
+        let sig = hex::encode(secp.sign_schnorr_no_aux_rand(&msg, &keypair).serialize());
+        assert_eq!(verify_signature(&xonly, "hello", &sig), Ok(()));
+        assert_eq!(verify_signature(&xonly, "hello", "00"), Err(SignatureError::MalformedSignature));
+    }
+
+    #[test]
+    fn test_canonical_signature() {
+        let secp = Secp256k1::new();
+        let secret = SecretKey::from_slice(&[7u8; 32]).unwrap();
+        let msg = Message::from_digest(message_digest("hello"));
+        let pubkey = hex::encode(PublicKey::from_secret_key(&secp, &secret).serialize());
+        let sig = secp.sign_ecdsa(&msg, &secret);
+
+        // DER and compact encodings of one signature are the same signature
+        let der = canonical_signature(&pubkey, &hex::encode(sig.serialize_der())).unwrap();
+        let compact = canonical_signature(&pubkey, &hex::encode(sig.serialize_compact())).unwrap();
+        assert_eq!(der, compact);
+        assert_eq!(canonical_signature(&pubkey, "00"), Err(SignatureError::MalformedSignature));
+    }
+
+    #[test]
+    fn test_verify_bip322_vectors() {
+        // Test vectors from BIP-322
+        assert_eq!(
+            hex::encode(bip322_message_hash("Hello World")),
+            "f0eb03b1a75ac6d9847f55c624a99169b5dccba2a31f5b23bea77ba270de0a7a"
+        );
+
+        let address = "bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l";
+        let signature = "AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=";
+        assert_eq!(verify_bip322(address, "Hello World", signature), Ok(()));
+        assert_eq!(verify_bip322(address, "Hello", signature), Err(SignatureError::Mismatch));
+        assert_eq!(
+            verify_bip322("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2", "Hello World", signature),
+            Err(SignatureError::UnsupportedAddress)
+        );
+    }
+}
@@ -0,0 +1,233 @@
+//! Native spell dry runs
+//!
+//! Converts a built spell into the `Transaction`, `App` and `Data` values the
+//! charms apps receive and runs the swap or escrow `app_contract` directly, so
+//! a spell the contract would reject fails in milliseconds instead of after
+//! minutes of proving. Apps built elsewhere (third-party tokens) are skipped.
+
+use std::collections::BTreeMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Result};
+use charms_data::{App, Charms, Data, Transaction, UtxoId};
+use serde::{Deserialize, Serialize};
+
+use crate::services::spell_schema::{Spell, SpellInput};
+
+/// Contracts this repo builds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Contract {
+    Swap,
+    Escrow,
+}
+
+/// Verification keys identifying the swap and escrow apps in a spell
+#[derive(Debug, Clone)]
+pub struct ContractKeys {
+    pub swap_vk: String,
+    pub escrow_vk: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AppCheckStatus {
+    Passed,
+    Failed,
+    /// Not one of our contracts, so it cannot be run here
+    Skipped,
+}
+
+/// Outcome of one app's contract
+#[derive(Debug, Clone, Serialize)]
+pub struct AppCheck {
+    /// Spell key, e.g. `$ORDER`
+    pub key: String,
+    pub app: String,
+    pub contract: Option<Contract>,
+    pub status: AppCheckStatus,
+    /// Failed contract condition or panic message
+    pub reason: Option<String>,
+}
+
+/// Dry-run result; `passed` is false when any contract that ran failed
+#[derive(Debug, Clone, Serialize)]
+pub struct SpellCheck {
+    pub passed: bool,
+    pub apps: Vec<AppCheck>,
+}
+
+/// Run our contracts against a built spell. `contract` forces which contract
+/// handles apps whose verification key matches ours; it is required when the
+/// swap and escrow keys are the same. Malformed spells are an error.
+pub fn check_spell(spell_yaml: &str, keys: &ContractKeys, contract: Option<Contract>) -> Result<SpellCheck> {
+    let spell = Spell::parse(spell_yaml)?;
+
+    let apps = spell
+        .apps
+        .iter()
+        .map(|(key, app)| {
+            App::from_str(app)
+                .map(|app| (key.clone(), app))
+                .map_err(|e| anyhow!("Invalid app {} ({}): {}", key, app, e))
+        })
+        .collect::<Result<BTreeMap<_, _>>>()?;
+
+    let charms = |values: &BTreeMap<String, serde_yaml::Value>, location: &str| -> Result<Charms> {
+        values
+            .iter()
+            .map(|(key, value)| {
+                let app = apps
+                    .get(key)
+                    .ok_or_else(|| anyhow!("{} references undeclared app {}", location, key))?;
+                Ok((app.clone(), Data::from(value)))
+            })
+            .collect()
+    };
+    let utxos = |inputs: &[SpellInput], kind: &str| -> Result<Vec<(UtxoId, Charms)>> {
+        inputs
+            .iter()
+            .enumerate()
+            .map(|(i, input)| {
+                let location = format!("{}[{}]", kind, i);
+                let utxo_id = UtxoId::from_str(&input.utxo_id)
+                    .map_err(|e| anyhow!("{} has invalid utxo_id {}: {}", location, input.utxo_id, e))?;
+                Ok((utxo_id, charms(&input.charms, &location)?))
+            })
+            .collect()
+    };
+
+    let tx = Transaction {
+        ins: utxos(&spell.ins, "ins")?,
+        refs: utxos(&spell.refs, "refs")?,
+        outs: spell
+            .outs
+            .iter()
+            .enumerate()
+            .map(|(i, out)| charms(&out.charms, &format!("outs[{}]", i)))
+            .collect::<Result<_>>()?,
+        coin_ins: None,
+        coin_outs: None,
+        prev_txs: BTreeMap::new(),
+        app_public_inputs: apps
+            .iter()
+            .map(|(key, app)| (app.clone(), spell.public_inputs.get(key).map(Data::from).unwrap_or_default()))
+            .collect(),
+    };
+
+    let mut checks = Vec::with_capacity(apps.len());
+    for (key, app) in &apps {
+        let resolved = contract_for(key, app, keys, contract)?;
+        let x = tx.app_public_inputs.get(app).cloned().unwrap_or_default();
+        let w = spell.private_inputs.get(key).map(Data::from).unwrap_or_default();
+
+        let outcome = resolved.map(|contract| run_contract(contract, app, &tx, &x, &w));
+        checks.push(AppCheck {
+            key: key.clone(),
+            app: app.to_string(),
+            contract: resolved,
+            status: match &outcome {
+                None => AppCheckStatus::Skipped,
+                Some(Ok(())) => AppCheckStatus::Passed,
+                Some(Err(_)) => AppCheckStatus::Failed,
+            },
+            reason: outcome.and_then(Result::err),
+        });
+    }
+
+    Ok(SpellCheck {
+        passed: checks.iter().all(|c| c.status != AppCheckStatus::Failed),
+        apps: checks,
+    })
+}
+
+fn contract_for(key: &str, app: &App, keys: &ContractKeys, explicit: Option<Contract>) -> Result<Option<Contract>> {
+    let vk = app.vk.to_string();
+    let swap = vk.eq_ignore_ascii_case(&keys.swap_vk);
+    let escrow = vk.eq_ignore_ascii_case(&keys.escrow_vk);
+
+    Ok(match (explicit, swap, escrow) {
+        (_, false, false) => None,
+        (Some(contract), _, _) => Some(contract),
+        (None, true, true) => bail!(
+            "App {} matches both the swap and escrow verification keys; set `contract`",
+            key
+        ),
+        (None, true, false) => Some(Contract::Swap),
+        (None, false, true) => Some(Contract::Escrow),
+    })
+}
+
+/// Run a contract, turning panics (e.g. unwraps on malformed data) into failures
+fn run_contract(contract: Contract, app: &App, tx: &Transaction, x: &Data, w: &Data) -> Result<(), String> {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| match contract {
+        Contract::Swap => liquid_swap_app::check_contract(app, tx, x, w),
+        Contract::Escrow => liquid_escrow_app::check_contract(app, tx, x, w),
+    }));
+
+    result.unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        Err(format!("contract panicked: {}", message))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SWAP_VK: &str = "1111111111111111111111111111111111111111111111111111111111111111";
+    const OTHER_VK: &str = "2222222222222222222222222222222222222222222222222222222222222222";
+    const ESCROW_VK: &str = "5555555555555555555555555555555555555555555555555555555555555555";
+    const ID: &str = "3333333333333333333333333333333333333333333333333333333333333333";
+    const TXID: &str = "4444444444444444444444444444444444444444444444444444444444444444";
+
+    fn keys() -> ContractKeys {
+        ContractKeys {
+            swap_vk: SWAP_VK.to_string(),
+            escrow_vk: ESCROW_VK.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_token_transfer_runs_swap_contract() {
+        let spell = |out_amount: u64| {
+            format!(
+                "version: 8\napps:\n  $T: t/{ID}/{SWAP_VK}\n  $FOREIGN: t/{ID}/{OTHER_VK}\n\
+                 ins:\n  - utxo_id: {TXID}:0\n    charms:\n      $T: 100\n\
+                 outs:\n  - address: tb1q\n    charms:\n      $T: {out_amount}\n      $FOREIGN: 5\n"
+            )
+        };
+
+        let ok = check_spell(&spell(100), &keys(), None).unwrap();
+        assert!(ok.passed);
+        let statuses: Vec<_> = ok.apps.iter().map(|a| (a.key.as_str(), a.status)).collect();
+        assert_eq!(statuses, vec![("$FOREIGN", AppCheckStatus::Skipped), ("$T", AppCheckStatus::Passed)]);
+
+        // Minting tokens is rejected with the failed condition
+        let minted = check_spell(&spell(150), &keys(), None).unwrap();
+        assert!(!minted.passed);
+        let failed = minted.apps.iter().find(|a| a.key == "$T").unwrap();
+        assert_eq!(failed.contract, Some(Contract::Swap));
+        assert_eq!(failed.reason.as_deref(), Some("output_amount.unwrap() <= input_amount.unwrap()"));
+    }
+
+    #[test]
+    fn test_malformed_spells_are_errors() {
+        let undeclared = format!("apps:\n  $T: t/{ID}/{SWAP_VK}\nouts:\n  - charms:\n      $X: 1\n");
+        assert!(check_spell(&undeclared, &keys(), None).is_err());
+        assert!(check_spell("apps:\n  $T: t/liquid-swap/vk\n", &keys(), None).is_err());
+
+        let same_keys = ContractKeys {
+            swap_vk: SWAP_VK.to_string(),
+            escrow_vk: SWAP_VK.to_string(),
+        };
+        let spell = format!("apps:\n  $T: t/{ID}/{SWAP_VK}\n");
+        assert!(check_spell(&spell, &same_keys, None).is_err());
+        assert!(check_spell(&spell, &same_keys, Some(Contract::Escrow)).unwrap().passed);
+    }
+}
@@ -0,0 +1,247 @@
+//! Decoding spells embedded in Bitcoin transactions
+//!
+//! A spell transaction's last input spends the commit output through a
+//! taproot script path. The leaf script carries the spell and its proof in an
+//! envelope (`OP_FALSE OP_IF "spell" <CBOR chunks> OP_ENDIF`), with charms
+//! keyed by the index of their app in the spell's sorted app list.
+
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context, Result};
+use bitcoin::blockdata::opcodes::all::{OP_ENDIF, OP_IF};
+use bitcoin::consensus::encode::deserialize_hex;
+use bitcoin::script::Instruction;
+use bitcoin::{Transaction, TxIn};
+use charms_data::{App, Data, UtxoId};
+use serde::{de::IgnoredAny, Deserialize, Serialize};
+
+/// Marker pushed right after `OP_IF` in a spell envelope
+const SPELL_MARKER: &[u8] = b"spell";
+
+/// Spell as committed on-chain, before the app indexes are resolved
+#[derive(Debug, Deserialize)]
+struct NormalizedSpell {
+    version: u32,
+    tx: NormalizedTransaction,
+    app_public_inputs: BTreeMap<App, Data>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NormalizedTransaction {
+    /// Absent when the inputs are the transaction's own (all but the last)
+    #[serde(default)]
+    ins: Option<Vec<UtxoId>>,
+    #[serde(default)]
+    refs: Option<Vec<UtxoId>>,
+    outs: Vec<BTreeMap<u32, Data>>,
+}
+
+/// A spell read back from a transaction
+#[derive(Debug, Clone, Serialize)]
+pub struct DecodedSpell {
+    pub txid: String,
+    pub version: u32,
+    /// Apps as `tag/identity/vk`
+    pub apps: Vec<String>,
+    pub public_inputs: BTreeMap<String, serde_json::Value>,
+    /// Spent UTXOs carrying charms into the spell
+    pub ins: Vec<String>,
+    pub refs: Vec<String>,
+    pub outs: Vec<DecodedOutput>,
+}
+
+/// Charms an output holds, keyed by app
+#[derive(Debug, Clone, Serialize)]
+pub struct DecodedOutput {
+    pub index: u32,
+    pub charms: BTreeMap<String, serde_json::Value>,
+}
+
+impl DecodedSpell {
+    /// Charms created in output `vout`, if it holds any
+    pub fn charms_at(&self, vout: u32) -> Option<&BTreeMap<String, serde_json::Value>> {
+        self.outs
+            .iter()
+            .find(|out| out.index == vout && !out.charms.is_empty())
+            .map(|out| &out.charms)
+    }
+}
+
+/// Decode the spell in a raw transaction; `None` when it carries no spell
+pub fn decode_spell_hex(tx_hex: &str) -> Result<Option<DecodedSpell>> {
+    let tx: Transaction = deserialize_hex(tx_hex.trim()).context("Transaction hex does not decode")?;
+    decode_spell_tx(&tx)
+}
+
+/// Decode the spell in a transaction; `None` when it carries no spell
+pub fn decode_spell_tx(tx: &Transaction) -> Result<Option<DecodedSpell>> {
+    let Some(spell_input) = tx.input.last() else {
+        return Ok(None);
+    };
+    let Some(data) = envelope_data(spell_input)? else {
+        return Ok(None);
+    };
+
+    let (spell, _proof): (NormalizedSpell, IgnoredAny) =
+        charms_data::util::read(data.as_slice()).context("Spell envelope is not a valid spell")?;
+
+    let apps: Vec<&App> = spell.app_public_inputs.keys().collect();
+    let ins = match spell.tx.ins {
+        Some(ins) => ins.iter().map(UtxoId::to_string).collect(),
+        None => tx.input[..tx.input.len() - 1]
+            .iter()
+            .map(|input| input.previous_output.to_string())
+            .collect(),
+    };
+
+    let mut outs = Vec::with_capacity(spell.tx.outs.len());
+    for (index, charms) in spell.tx.outs.iter().enumerate() {
+        let mut decoded = BTreeMap::new();
+        for (app_index, data) in charms {
+            let app = apps
+                .get(*app_index as usize)
+                .with_context(|| format!("Output {} references app #{} of {}", index, app_index, apps.len()))?;
+            decoded.insert(app.to_string(), data_json(data));
+        }
+        outs.push(DecodedOutput {
+            index: index as u32,
+            charms: decoded,
+        });
+    }
+
+    Ok(Some(DecodedSpell {
+        txid: tx.compute_txid().to_string(),
+        version: spell.version,
+        apps: apps.iter().map(|app| app.to_string()).collect(),
+        public_inputs: spell
+            .app_public_inputs
+            .iter()
+            .filter(|(_, data)| !data.is_empty())
+            .map(|(app, data)| (app.to_string(), data_json(data)))
+            .collect(),
+        ins,
+        refs: spell.tx.refs.unwrap_or_default().iter().map(UtxoId::to_string).collect(),
+        outs,
+    }))
+}
+
+/// Concatenated envelope pushes from the input's tapscript, if it has a
+/// spell envelope
+fn envelope_data(input: &TxIn) -> Result<Option<Vec<u8>>> {
+    let Some(leaf) = input.witness.taproot_leaf_script() else {
+        return Ok(None);
+    };
+
+    let mut instructions = leaf.script.instructions();
+    let opens_envelope = matches!(instructions.next(), Some(Ok(Instruction::PushBytes(b))) if b.is_empty())
+        && matches!(instructions.next(), Some(Ok(Instruction::Op(op))) if op == OP_IF)
+        && matches!(instructions.next(), Some(Ok(Instruction::PushBytes(b))) if b.as_bytes() == SPELL_MARKER);
+    if !opens_envelope {
+        return Ok(None);
+    }
+
+    let mut data = Vec::new();
+    loop {
+        match instructions.next() {
+            Some(Ok(Instruction::PushBytes(bytes))) => data.extend_from_slice(bytes.as_bytes()),
+            Some(Ok(Instruction::Op(op))) if op == OP_ENDIF => return Ok(Some(data)),
+            Some(Err(e)) => bail!("Spell envelope script is malformed: {}", e),
+            _ => bail!("Spell envelope is not closed"),
+        }
+    }
+}
+
+/// Charm data as JSON, falling back to its debug form for CBOR values JSON
+/// cannot hold
+fn data_json(data: &Data) -> serde_json::Value {
+    data.value::<serde_json::Value>()
+        .unwrap_or_else(|_| serde_json::Value::String(format!("{:?}", data)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::blockdata::opcodes::OP_FALSE;
+    use bitcoin::script::PushBytesBuf;
+    use bitcoin::transaction::Version;
+    use bitcoin::{OutPoint, ScriptBuf, Sequence, Witness};
+    use std::str::FromStr;
+
+    #[derive(Serialize)]
+    struct OnChainSpell {
+        version: u32,
+        tx: OnChainTransaction,
+        app_public_inputs: BTreeMap<App, Data>,
+    }
+
+    #[derive(Serialize)]
+    struct OnChainTransaction {
+        outs: Vec<BTreeMap<u32, Data>>,
+    }
+
+    fn input(outpoint: &str, witness: Witness) -> TxIn {
+        TxIn {
+            previous_output: OutPoint::from_str(outpoint).unwrap(),
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::MAX,
+            witness,
+        }
+    }
+
+    #[test]
+    fn test_decode_spell_tx() {
+        let token = App::from_str(&format!("t/{}/{}", "11".repeat(32), "22".repeat(32))).unwrap();
+        let order = App::from_str(&format!("n/{}/{}", "33".repeat(32), "44".repeat(32))).unwrap();
+        let spell = OnChainSpell {
+            version: 8,
+            tx: OnChainTransaction {
+                outs: vec![
+                    BTreeMap::from([(0, Data::from(&serde_json::json!({ "status": 0 }))), (1, Data::from(&500u64))]),
+                    BTreeMap::new(),
+                ],
+            },
+            app_public_inputs: BTreeMap::from([(order.clone(), Data::from(&"create")), (token.clone(), Data::empty())]),
+        };
+        let data = charms_data::util::write(&(spell, vec![7u8; 700])).unwrap();
+
+        let mut envelope = ScriptBuf::builder()
+            .push_opcode(OP_FALSE)
+            .push_opcode(OP_IF)
+            .push_slice(b"spell");
+        for chunk in data.chunks(520) {
+            envelope = envelope.push_slice(PushBytesBuf::try_from(chunk.to_vec()).unwrap());
+        }
+        let envelope = envelope.push_opcode(OP_ENDIF).into_script();
+
+        let funding = format!("{}:1", "aa".repeat(32));
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![
+                input(&funding, Witness::from_slice(&[[1u8; 64]])),
+                input(
+                    &format!("{}:0", "bb".repeat(32)),
+                    Witness::from_slice(&[envelope.as_bytes(), &[0xc0; 33]]),
+                ),
+            ],
+            output: vec![],
+        };
+
+        let decoded = decode_spell_tx(&tx).unwrap().unwrap();
+        assert_eq!(decoded.version, 8);
+        assert_eq!(decoded.ins, vec![funding]);
+        // Apps are sorted, so the order NFT (tag n) is #0
+        assert_eq!(decoded.apps, vec![order.to_string(), token.to_string()]);
+        assert_eq!(decoded.public_inputs.get(&order.to_string()), Some(&serde_json::json!("create")));
+        let charms = decoded.charms_at(0).unwrap();
+        assert_eq!(charms.get(&token.to_string()), Some(&serde_json::json!(500)));
+        assert_eq!(charms.get(&order.to_string()), Some(&serde_json::json!({ "status": 0 })));
+        assert!(decoded.charms_at(1).is_none());
+
+        // A plain payment carries no spell
+        let mut plain = tx.clone();
+        plain.input.truncate(1);
+        assert!(decode_spell_tx(&plain).unwrap().is_none());
+    }
+}
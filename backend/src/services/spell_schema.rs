@@ -0,0 +1,536 @@
+//! Typed charms v8 spell format
+//!
+//! Spells are parsed into these structs before they are proved or dry-run, so
+//! a spell with an undeclared app, a non-numeric token amount or a malformed
+//! UTXO id is rejected up front with every problem listed, rather than by the
+//! prover after minutes of work. Spells built from several apps' templates
+//! are merged here too, with each template's app keys namespaced.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use bitcoin::Network;
+use charms_data::UtxoId;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::services::network;
+
+/// Spell format version this backend builds and accepts
+pub const SPELL_VERSION: u32 = 8;
+
+/// A spell: apps keyed by `$NAME`, and inputs and outputs carrying charms
+/// keyed by those names
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Spell {
+    #[serde(default)]
+    pub version: Option<u32>,
+    pub apps: BTreeMap<String, String>,
+    #[serde(default)]
+    pub public_inputs: BTreeMap<String, serde_yaml::Value>,
+    #[serde(default)]
+    pub private_inputs: BTreeMap<String, serde_yaml::Value>,
+    #[serde(default)]
+    pub ins: Vec<SpellInput>,
+    #[serde(default)]
+    pub refs: Vec<SpellInput>,
+    #[serde(default)]
+    pub outs: Vec<SpellOutput>,
+}
+
+/// A spent (or referenced) UTXO and the charms it holds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpellInput {
+    pub utxo_id: String,
+    #[serde(default)]
+    pub charms: BTreeMap<String, serde_yaml::Value>,
+}
+
+/// A created output and the charms it receives
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpellOutput {
+    #[serde(default)]
+    pub address: Option<String>,
+    /// Output value in sats; the prover picks one when absent
+    #[serde(default)]
+    pub amount: Option<u64>,
+    #[serde(default)]
+    pub charms: BTreeMap<String, serde_yaml::Value>,
+}
+
+impl Spell {
+    /// Parse a spell without checking its contents
+    pub fn parse(spell_yaml: &str) -> Result<Self> {
+        serde_yaml::from_str(spell_yaml).context("Spell does not match the v8 spell format")
+    }
+
+    /// Check the version, app declarations, charm references, token amounts
+    /// and UTXO ids, reporting every problem found
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Vec::new();
+
+        match self.version {
+            Some(SPELL_VERSION) => {}
+            Some(v) => problems.push(format!("Invalid spell version: expected {}, got {}", SPELL_VERSION, v)),
+            None => problems.push("Spell missing version field".to_string()),
+        }
+
+        if self.apps.is_empty() {
+            problems.push("Spell declares no apps".to_string());
+        }
+        for (key, app) in &self.apps {
+            if !key.starts_with('$') {
+                problems.push(format!("App key {} must start with '$'", key));
+            }
+            if app_tag(app).is_none() {
+                problems.push(format!("App {} ({}) is not of the form tag/identity/vk", key, app));
+            }
+        }
+
+        for (kind, inputs) in [("ins", &self.ins), ("refs", &self.refs)] {
+            for (i, input) in inputs.iter().enumerate() {
+                let location = format!("{}[{}]", kind, i);
+                if let Err(e) = UtxoId::from_str(&input.utxo_id) {
+                    problems.push(format!("{} has invalid utxo_id {}: {}", location, input.utxo_id, e));
+                }
+                self.check_charms(&input.charms, &location, &mut problems);
+            }
+        }
+        for (i, output) in self.outs.iter().enumerate() {
+            self.check_charms(&output.charms, &format!("outs[{}]", i), &mut problems);
+        }
+
+        if !problems.is_empty() {
+            bail!("{}", problems.join("; "));
+        }
+        Ok(())
+    }
+
+    /// Fail unless every output address is an address on `network`
+    pub fn check_addresses(&self, network: Network) -> Result<()> {
+        let problems: Vec<String> = self
+            .outs
+            .iter()
+            .enumerate()
+            .filter_map(|(i, output)| {
+                let error = network::check_address(output.address.as_deref()?, network).err()?;
+                Some(format!("outs[{}]: {}", i, error))
+            })
+            .collect();
+        if !problems.is_empty() {
+            bail!("{}", problems.join("; "));
+        }
+        Ok(())
+    }
+
+    /// Verification key of each declared app, keyed by app key (malformed
+    /// app strings are skipped)
+    pub fn app_vks(&self) -> BTreeMap<&str, &str> {
+        self.apps
+            .iter()
+            .filter(|(_, app)| app_tag(app).is_some())
+            .filter_map(|(key, app)| Some((key.as_str(), app.rsplit('/').next()?)))
+            .collect()
+    }
+
+    /// Apps the prover has to run but `binaries` (keyed by verification key)
+    /// has no binary for. Apps whose charms only move from inputs to outputs
+    /// unchanged are checked by the prover without their binary.
+    pub fn apps_missing_binaries<T>(&self, binaries: &BTreeMap<String, T>) -> Vec<String> {
+        self.app_vks()
+            .into_iter()
+            .filter(|(key, vk)| !binaries.contains_key(*vk) && !self.is_simple_transfer(key))
+            .map(|(key, _)| key.to_string())
+            .collect()
+    }
+
+    /// Whether the app takes no inputs and its charms are only moved: token
+    /// amounts are conserved and other charms reappear unchanged
+    fn is_simple_transfer(&self, key: &str) -> bool {
+        if self.public_inputs.contains_key(key) || self.private_inputs.contains_key(key) {
+            return false;
+        }
+
+        let ins: Vec<&serde_yaml::Value> = self.ins.iter().filter_map(|input| input.charms.get(key)).collect();
+        let outs: Vec<&serde_yaml::Value> = self.outs.iter().filter_map(|output| output.charms.get(key)).collect();
+        if ins.is_empty() {
+            // Charms appearing from nothing are a mint
+            return false;
+        }
+
+        if self.apps.get(key).and_then(|app| app_tag(app)) == Some('t') {
+            let total = |values: &[&serde_yaml::Value]| -> Option<u64> {
+                values.iter().try_fold(0u64, |sum, value| sum.checked_add(value.as_u64()?))
+            };
+            return matches!((total(&ins), total(&outs)), (Some(a), Some(b)) if a == b);
+        }
+
+        let mut ins: Vec<String> = ins.into_iter().map(yaml_display).collect();
+        let mut outs: Vec<String> = outs.into_iter().map(yaml_display).collect();
+        ins.sort();
+        outs.sort();
+        ins == outs
+    }
+
+    /// Merge spells into one. Each part's app keys are prefixed with its
+    /// namespace (`$ORDER` in part `SWAP` becomes `$SWAP_ORDER`); parts
+    /// declaring the same app share the key of the first one.
+    pub fn compose(parts: &[(&str, Spell)]) -> Result<Spell> {
+        let mut spell = Spell {
+            version: Some(SPELL_VERSION),
+            apps: BTreeMap::new(),
+            public_inputs: BTreeMap::new(),
+            private_inputs: BTreeMap::new(),
+            ins: Vec::new(),
+            refs: Vec::new(),
+            outs: Vec::new(),
+        };
+        let mut keys_by_app: BTreeMap<&str, String> = BTreeMap::new();
+        let mut spent = HashSet::new();
+
+        for (namespace, part) in parts {
+            if part.version != Some(SPELL_VERSION) {
+                bail!("Spell {} is not a v{} spell", namespace, SPELL_VERSION);
+            }
+
+            let mut renamed = BTreeMap::new();
+            for (key, app) in &part.apps {
+                let composed = keys_by_app
+                    .entry(app.as_str())
+                    .or_insert_with(|| format!("${}_{}", namespace, key.trim_start_matches('$')))
+                    .clone();
+                if let Some(existing) = spell.apps.insert(composed.clone(), app.clone()) {
+                    if existing != *app {
+                        bail!("App key {} is declared as both {} and {}", composed, existing, app);
+                    }
+                }
+                renamed.insert(key.as_str(), composed);
+            }
+            let rename = |charms: &BTreeMap<String, serde_yaml::Value>, location: &str| -> Result<BTreeMap<String, serde_yaml::Value>> {
+                charms
+                    .iter()
+                    .map(|(key, value)| match renamed.get(key.as_str()) {
+                        Some(composed) => Ok((composed.clone(), value.clone())),
+                        None => bail!("Spell {} {} references undeclared app {}", namespace, location, key),
+                    })
+                    .collect()
+            };
+
+            for (inputs, target) in [
+                (&part.public_inputs, &mut spell.public_inputs),
+                (&part.private_inputs, &mut spell.private_inputs),
+            ] {
+                for (key, value) in rename(inputs, "inputs")? {
+                    if target.get(&key).is_some_and(|existing| *existing != value) {
+                        bail!("Spells give app {} conflicting inputs", key);
+                    }
+                    target.insert(key, value);
+                }
+            }
+            for (i, input) in part.ins.iter().enumerate() {
+                if !spent.insert(input.utxo_id.clone()) {
+                    bail!("UTXO {} is spent by more than one spell", input.utxo_id);
+                }
+                spell.ins.push(SpellInput {
+                    utxo_id: input.utxo_id.clone(),
+                    charms: rename(&input.charms, &format!("ins[{}]", i))?,
+                });
+            }
+            for (i, input) in part.refs.iter().enumerate() {
+                if spell.refs.iter().any(|existing| existing.utxo_id == input.utxo_id) {
+                    continue;
+                }
+                spell.refs.push(SpellInput {
+                    utxo_id: input.utxo_id.clone(),
+                    charms: rename(&input.charms, &format!("refs[{}]", i))?,
+                });
+            }
+            for (i, output) in part.outs.iter().enumerate() {
+                spell.outs.push(SpellOutput {
+                    address: output.address.clone(),
+                    amount: output.amount,
+                    charms: rename(&output.charms, &format!("outs[{}]", i))?,
+                });
+            }
+        }
+
+        Ok(spell)
+    }
+
+    fn check_charms(&self, charms: &BTreeMap<String, serde_yaml::Value>, location: &str, problems: &mut Vec<String>) {
+        for (key, value) in charms {
+            let Some(app) = self.apps.get(key) else {
+                problems.push(format!("{} references undeclared app {}", location, key));
+                continue;
+            };
+            // Fungible token charms are a bare amount
+            if app_tag(app) == Some('t') && value.as_u64().is_none() {
+                problems.push(format!(
+                    "{} amount for {} must be a non-negative integer, got {}",
+                    location,
+                    key,
+                    yaml_display(value)
+                ));
+            }
+        }
+    }
+}
+
+/// Take a spell's `private_inputs` out, returning the spell without them and
+/// the inputs as YAML (`None` when it has none), so they can be stored apart
+pub fn split_private_inputs(spell_yaml: &str) -> Result<(String, Option<String>)> {
+    let mut spell: serde_yaml::Value = serde_yaml::from_str(spell_yaml).context("Spell is not valid YAML")?;
+    let private = spell
+        .as_mapping_mut()
+        .and_then(|spell| spell.remove("private_inputs"))
+        .filter(|inputs| !inputs.is_null() && inputs.as_mapping().is_none_or(|inputs| !inputs.is_empty()));
+    match private {
+        Some(private) => Ok((serde_yaml::to_string(&spell)?, Some(serde_yaml::to_string(&private)?))),
+        None => Ok((spell_yaml.to_string(), None)),
+    }
+}
+
+/// Put private inputs taken out with `split_private_inputs` back into a spell
+pub fn join_private_inputs(spell_yaml: &str, private_yaml: &str) -> Result<String> {
+    let mut spell: serde_yaml::Value = serde_yaml::from_str(spell_yaml).context("Spell is not valid YAML")?;
+    let private: serde_yaml::Value = serde_yaml::from_str(private_yaml).context("Private inputs are not valid YAML")?;
+    spell
+        .as_mapping_mut()
+        .context("Spell is not a mapping")?
+        .insert("private_inputs".into(), private);
+    Ok(serde_yaml::to_string(&spell)?)
+}
+
+/// Keys of the spell format, at the top level and in inputs and outputs
+const SPELL_KEYS: &[&str] = &["version", "apps", "public_inputs", "private_inputs", "ins", "refs", "outs"];
+const INPUT_KEYS: &[&str] = &["utxo_id", "charms"];
+const OUTPUT_KEYS: &[&str] = &["address", "amount", "charms"];
+
+/// Problems left in a spell after template substitution
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("{}", self.problems().join("; "))]
+pub struct SpellLintError {
+    /// Names of `${...}` placeholders no variable was given for
+    pub unresolved: Vec<String>,
+    /// Keys outside the spell format, with where they appear
+    pub unknown_keys: Vec<String>,
+}
+
+impl SpellLintError {
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !self.unresolved.is_empty() {
+            problems.push(format!("Unresolved template variables: {}", self.unresolved.join(", ")));
+        }
+        if !self.unknown_keys.is_empty() {
+            problems.push(format!("Unknown spell keys: {}", self.unknown_keys.join(", ")));
+        }
+        problems
+    }
+}
+
+/// Check a spell built from a template before it goes anywhere near the
+/// prover: every `${...}` placeholder must be substituted, and every key must
+/// belong to the spell format (app keys in `public_inputs` and
+/// `private_inputs` must be declared apps)
+pub fn lint_built_spell(spell_yaml: &str) -> std::result::Result<(), SpellLintError> {
+    let error = SpellLintError {
+        unresolved: unresolved_variables(spell_yaml),
+        unknown_keys: serde_yaml::from_str(spell_yaml).map(|value| unknown_keys(&value)).unwrap_or_default(),
+    };
+    if error.unresolved.is_empty() && error.unknown_keys.is_empty() {
+        Ok(())
+    } else {
+        Err(error)
+    }
+}
+
+/// Names of the `${...}` placeholders in `text`, sorted and deduplicated
+fn unresolved_variables(text: &str) -> Vec<String> {
+    let mut names = BTreeSet::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        rest = &rest[start + 2..];
+        match rest.find('}') {
+            Some(end) => {
+                names.insert(rest[..end].to_string());
+                rest = &rest[end + 1..];
+            }
+            None => break,
+        }
+    }
+    names.into_iter().collect()
+}
+
+/// Keys of a parsed spell that are not part of the spell format
+fn unknown_keys(spell: &serde_yaml::Value) -> Vec<String> {
+    let Some(spell) = spell.as_mapping() else {
+        return Vec::new();
+    };
+    let key_name = |key: &serde_yaml::Value| key.as_str().map_or_else(|| yaml_display(key), str::to_string);
+    let mut unknown = Vec::new();
+    let mut check = |map: &serde_yaml::Mapping, allowed: &[&str], location: &str| {
+        for key in map.keys() {
+            let name = key_name(key);
+            if !allowed.contains(&name.as_str()) {
+                unknown.push(if location.is_empty() { name } else { format!("{}.{}", location, name) });
+            }
+        }
+    };
+
+    check(spell, SPELL_KEYS, "");
+    for (field, allowed) in [("ins", INPUT_KEYS), ("refs", INPUT_KEYS), ("outs", OUTPUT_KEYS)] {
+        let entries = spell.get(field).and_then(|v| v.as_sequence()).into_iter().flatten();
+        for (i, entry) in entries.enumerate() {
+            if let Some(entry) = entry.as_mapping() {
+                check(entry, allowed, &format!("{}[{}]", field, i));
+            }
+        }
+    }
+
+    let apps: Vec<String> = spell
+        .get("apps")
+        .and_then(|apps| apps.as_mapping())
+        .map(|apps| apps.keys().map(key_name).collect())
+        .unwrap_or_default();
+    let apps: Vec<&str> = apps.iter().map(String::as_str).collect();
+    for field in ["public_inputs", "private_inputs"] {
+        if let Some(inputs) = spell.get(field).and_then(|v| v.as_mapping()) {
+            check(inputs, &apps, field);
+        }
+    }
+    unknown
+}
+
+/// Tag of an app string `tag/identity/vk`, or `None` when malformed
+fn app_tag(app: &str) -> Option<char> {
+    let mut parts = app.split('/');
+    let (tag, identity, vk) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() || identity.is_empty() || vk.is_empty() {
+        return None;
+    }
+
+    let mut chars = tag.chars();
+    match (chars.next(), chars.next()) {
+        (Some(tag), None) => Some(tag),
+        _ => None,
+    }
+}
+
+fn yaml_display(value: &serde_yaml::Value) -> String {
+    serde_yaml::to_string(value)
+        .map(|s| s.trim_end().to_string())
+        .unwrap_or_else(|_| format!("{:?}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TXID: &str = "4444444444444444444444444444444444444444444444444444444444444444";
+
+    #[test]
+    fn test_validate_reports_every_problem() {
+        let valid = format!(
+            "version: 8\napps:\n  $T: t/abc/def\n  $N: n/abc/def\n\
+             ins:\n  - utxo_id: {TXID}:1\n    charms:\n      $T: 100\n\
+             outs:\n  - address: tb1q\n    charms:\n      $T: 100\n      $N:\n        status: 0\n"
+        );
+        assert!(Spell::parse(&valid).unwrap().validate().is_ok());
+
+        let invalid = "version: 8\napps:\n  $T: t/abc\n  $U: t/abc/def\n\
+             ins:\n  - utxo_id: aa:0\n\
+             outs:\n  - charms:\n      $X: 1\n      $U: \"ten\"\n";
+        let error = Spell::parse(invalid).unwrap().validate().unwrap_err().to_string();
+        assert!(error.contains("App $T (t/abc) is not of the form tag/identity/vk"));
+        assert!(error.contains("ins[0] has invalid utxo_id aa:0"));
+        assert!(error.contains("outs[0] references undeclared app $X"));
+        assert!(error.contains("outs[0] amount for $U must be a non-negative integer, got ten"));
+
+        // Structural mismatches fail to parse at all
+        assert!(Spell::parse("version: 8\napps: []\n").is_err());
+        assert!(Spell::parse("version: 8\napps: {}\nins:\n  - charms: {}\n").is_err());
+    }
+
+    #[test]
+    fn test_compose_namespaces_apps_and_finds_missing_binaries() {
+        let order = Spell::parse(&format!(
+            "version: 8\napps:\n  $ORDER: n/order/swapvk\n  $OFFER: t/toad/tokenvk\n\
+             public_inputs:\n  $ORDER: create\n\
+             ins:\n  - utxo_id: {TXID}:0\n    charms:\n      $OFFER: 100\n\
+             outs:\n  - charms:\n      $ORDER:\n        status: 0\n      $OFFER: 100\n"
+        ))
+        .unwrap();
+        let escrow = Spell::parse(&format!(
+            "version: 8\napps:\n  $ESCROW: n/escrow/escrowvk\n  $TOKEN: t/toad/tokenvk\n\
+             public_inputs:\n  $ESCROW: create\n\
+             ins:\n  - utxo_id: {TXID}:1\n    charms:\n      $TOKEN: 50\n\
+             outs:\n  - charms:\n      $ESCROW:\n        status: 0\n      $TOKEN: 50\n"
+        ))
+        .unwrap();
+
+        let spell = Spell::compose(&[("SWAP", order.clone()), ("ESCROW", escrow.clone())]).unwrap();
+        assert!(spell.validate().is_ok());
+        let keys: Vec<&str> = spell.apps.keys().map(String::as_str).collect();
+        assert_eq!(keys, vec!["$ESCROW_ESCROW", "$SWAP_OFFER", "$SWAP_ORDER"]);
+        assert_eq!(spell.outs[1].charms.get("$SWAP_OFFER").and_then(|v| v.as_u64()), Some(50));
+
+        // The token is only transferred, so just the two contracts need binaries
+        let binaries = BTreeMap::from([("swapvk".to_string(), ())]);
+        assert_eq!(spell.apps_missing_binaries(&binaries), vec!["$ESCROW_ESCROW".to_string()]);
+
+        assert!(Spell::compose(&[("A", order.clone()), ("B", order)]).is_err());
+    }
+
+    #[test]
+    fn test_lint_built_spell_lists_unresolved_variables_and_unknown_keys() {
+        let spell = "version: 8\napps:\n  $N: n/abc/def\npublic_inputs:\n  $N: create\n  $X: oops\n\
+             ins:\n  - utxo_id: ${order_utxo}\n    value: 1\n\
+             outs:\n  - address: ${addr_maker}\n    charms:\n      $N: ${order_utxo}\n\
+             fee: 2\n";
+        let err = lint_built_spell(spell).unwrap_err();
+        assert_eq!(err.unresolved, vec!["addr_maker", "order_utxo"]);
+        assert_eq!(err.unknown_keys, vec!["fee", "ins[0].value", "public_inputs.$X"]);
+        assert!(err.to_string().contains("Unresolved template variables: addr_maker, order_utxo"));
+
+        let valid = format!(
+            "version: 8\napps:\n  $N: n/abc/def\nprivate_inputs:\n  $N: {{ signature: ab }}\n\
+             ins:\n  - utxo_id: {TXID}:0\nouts:\n  - address: tb1q\n    amount: 1000\n"
+        );
+        assert_eq!(lint_built_spell(&valid), Ok(()));
+    }
+
+    #[test]
+    fn test_check_addresses_rejects_other_networks() {
+        let spell = Spell::parse(
+            "version: 8\napps: {}\nouts:\n  - address: tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx\n  - amount: 1000\
+             \n  - address: bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kxv8f3t4\n",
+        )
+        .unwrap();
+
+        let err = spell.check_addresses(Network::Testnet4).unwrap_err().to_string();
+        assert!(err.starts_with("outs[2]: "), "{}", err);
+        assert!(spell.check_addresses(Network::Bitcoin).unwrap_err().to_string().starts_with("outs[0]: "));
+    }
+
+    #[test]
+    fn test_private_inputs_split_and_join() {
+        let spell = format!(
+            "version: 8\napps:\n  $E: n/abc/def\nprivate_inputs:\n  $E:\n    preimage: cafe\n\
+             ins:\n  - utxo_id: {TXID}:0\nouts: []\n"
+        );
+        let (public, private) = split_private_inputs(&spell).unwrap();
+        let private = private.unwrap();
+        assert!(!public.contains("cafe"));
+        assert!(private.contains("preimage: cafe"));
+
+        let joined = Spell::parse(&join_private_inputs(&public, &private).unwrap()).unwrap();
+        assert_eq!(joined.private_inputs["$E"]["preimage"], "cafe");
+        assert_eq!(joined.ins[0].utxo_id, format!("{TXID}:0"));
+
+        // Spells without private inputs are left as they are
+        let plain = "version: 8\napps: {}\nprivate_inputs: {}\n";
+        assert_eq!(split_private_inputs(plain).unwrap(), (plain.to_string(), None));
+    }
+}
@@ -0,0 +1,103 @@
+//! SPV inclusion proofs for confirmed transactions
+//!
+//! The chain backend returns a BIP37 merkle block for a mined transaction:
+//! the block header followed by a partial merkle tree linking the txid to
+//! the header's merkle root. It is checked here before being handed out, so
+//! light clients and counterparties on other chains can verify inclusion
+//! against a header chain of their own instead of trusting this backend.
+
+use anyhow::{bail, Context, Result};
+use bitcoin::consensus::encode::{deserialize_hex, serialize_hex};
+use bitcoin::{BlockHash, MerkleBlock, Txid};
+use serde::Serialize;
+
+/// Proof that a transaction is in a block
+#[derive(Debug, Clone, Serialize)]
+pub struct SpvProof {
+    pub block_hash: String,
+    pub block_height: Option<u64>,
+    /// 80-byte block header (hex)
+    pub header: String,
+    pub merkle_root: String,
+    /// BIP37 merkle block (hex), as `gettxoutproof` returns it
+    pub merkle_block: String,
+    /// Position of the transaction in the block
+    pub tx_index: u32,
+    pub tx_count: u32,
+}
+
+/// Check that `merkle_block_hex` proves `txid` is in block `block_hash`
+pub fn spv_proof(txid: &Txid, block_hash: &BlockHash, block_height: Option<u64>, merkle_block_hex: &str) -> Result<SpvProof> {
+    let merkle_block: MerkleBlock = deserialize_hex(merkle_block_hex.trim()).context("Invalid merkle block")?;
+    if merkle_block.header.block_hash() != *block_hash {
+        bail!("Merkle block is for block {}, not {}", merkle_block.header.block_hash(), block_hash);
+    }
+
+    let mut matches = Vec::new();
+    let mut indexes = Vec::new();
+    merkle_block
+        .extract_matches(&mut matches, &mut indexes)
+        .map_err(|e| anyhow::anyhow!("Merkle proof does not match the header: {:?}", e))?;
+    let tx_index = matches
+        .iter()
+        .position(|matched| matched == txid)
+        .map(|position| indexes[position])
+        .with_context(|| format!("Merkle block does not prove {}", txid))?;
+
+    Ok(SpvProof {
+        block_hash: block_hash.to_string(),
+        block_height,
+        header: serialize_hex(&merkle_block.header),
+        merkle_root: merkle_block.header.merkle_root.to_string(),
+        merkle_block: merkle_block_hex.trim().to_string(),
+        tx_index,
+        tx_count: merkle_block.txn.num_transactions(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::block::{Header, Version as BlockVersion};
+    use bitcoin::hashes::Hash;
+    use bitcoin::transaction::Version;
+    use bitcoin::{Block, CompactTarget, Transaction, TxMerkleNode};
+
+    #[test]
+    fn test_spv_proof_checks_header_and_txid() {
+        let txdata: Vec<Transaction> = (0..5)
+            .map(|n| Transaction {
+                version: Version::TWO,
+                lock_time: LockTime::from_consensus(n),
+                input: vec![],
+                output: vec![],
+            })
+            .collect();
+        let mut block = Block {
+            header: Header {
+                version: BlockVersion::TWO,
+                prev_blockhash: BlockHash::all_zeros(),
+                merkle_root: TxMerkleNode::all_zeros(),
+                time: 0,
+                bits: CompactTarget::from_consensus(0x207fffff),
+                nonce: 0,
+            },
+            txdata,
+        };
+        block.header.merkle_root = block.compute_merkle_root().unwrap();
+        let txid = block.txdata[3].compute_txid();
+        let hash = block.block_hash();
+
+        let proof_hex = serialize_hex(&MerkleBlock::from_block_with_predicate(&block, |t| *t == txid));
+        let proof = spv_proof(&txid, &hash, Some(120), &proof_hex).unwrap();
+        assert_eq!((proof.tx_index, proof.tx_count), (3, 5));
+        assert_eq!(proof.header.len(), 160);
+        assert_eq!(proof.merkle_root, block.header.merkle_root.to_string());
+
+        // Another transaction, or another block, is not proven
+        let other = block.txdata[1].compute_txid();
+        assert!(spv_proof(&other, &hash, None, &proof_hex).is_err());
+        assert!(spv_proof(&txid, &BlockHash::all_zeros(), None, &proof_hex).is_err());
+    }
+}
@@ -0,0 +1,140 @@
+//! Token registry
+//!
+//! Maps charm token app ids to display metadata. Entries are loaded from the
+//! YAML file at `TOKEN_REGISTRY_PATH`, a list of `{app_id, symbol, name, decimals}`.
+//! Tokens missing from the registry fall back to a symbol derived from the app id.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Display metadata for a charm token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenInfo {
+    pub app_id: String,
+    pub symbol: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub decimals: u8,
+}
+
+/// Registry of known tokens keyed by app id
+#[derive(Debug, Clone, Default)]
+pub struct TokenRegistry {
+    tokens: HashMap<String, TokenInfo>,
+}
+
+impl TokenRegistry {
+    /// Load the registry from `TOKEN_REGISTRY_PATH`, empty when unset or unreadable
+    pub fn new() -> Self {
+        let Ok(path) = std::env::var("TOKEN_REGISTRY_PATH") else {
+            return Self::default();
+        };
+
+        match Self::load(&path) {
+            Ok(registry) => {
+                tracing::info!("Loaded {} tokens from {}", registry.tokens.len(), path);
+                registry
+            }
+            Err(e) => {
+                tracing::warn!("Failed to load token registry {}: {:#}", path, e);
+                Self::default()
+            }
+        }
+    }
+
+    fn load(path: &str) -> Result<Self> {
+        let contents = std::fs::read_to_string(path).context("Failed to read registry file")?;
+        Self::from_yaml(&contents)
+    }
+
+    fn from_yaml(contents: &str) -> Result<Self> {
+        let entries: Vec<TokenInfo> = serde_yaml::from_str(contents).context("Invalid registry YAML")?;
+        Ok(Self {
+            tokens: entries.into_iter().map(|t| (t.app_id.clone(), t)).collect(),
+        })
+    }
+
+    /// Metadata for an app id; unregistered tokens get a symbol from the app id
+    pub fn lookup(&self, app_id: &str) -> TokenInfo {
+        self.tokens.get(app_id).cloned().unwrap_or_else(|| TokenInfo {
+            app_id: app_id.to_string(),
+            symbol: fallback_symbol(app_id),
+            name: None,
+            decimals: 0,
+        })
+    }
+
+    /// App ids of registered tokens whose symbol or name contains `fragment`,
+    /// ignoring case
+    pub fn app_ids_matching(&self, fragment: &str) -> Vec<String> {
+        let fragment = fragment.to_lowercase();
+        let mut ids: Vec<String> = self
+            .tokens
+            .values()
+            .filter(|t| {
+                t.symbol.to_lowercase().contains(&fragment)
+                    || t.name.as_deref().is_some_and(|name| name.to_lowercase().contains(&fragment))
+            })
+            .map(|t| t.app_id.clone())
+            .collect();
+        ids.sort();
+        ids
+    }
+}
+
+/// Short uppercase symbol from an app id such as `t/<identity>/<vk>`
+fn fallback_symbol(app_id: &str) -> String {
+    let identity = app_id
+        .split('/')
+        .find(|part| part.len() > 1)
+        .unwrap_or(app_id);
+    identity.chars().take(6).collect::<String>().to_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_lookup() {
+        let registry = TokenRegistry::from_yaml(
+            r#"
+- app_id: t/abc/def
+  symbol: TOAD
+  name: Toad Token
+  decimals: 8
+"#,
+        )
+        .unwrap();
+
+        let toad = registry.lookup("t/abc/def");
+        assert_eq!(toad.symbol, "TOAD");
+        assert_eq!(toad.decimals, 8);
+
+        let unknown = registry.lookup("t/3f8a2b91c0/def");
+        assert_eq!(unknown.symbol, "3F8A2B");
+        assert!(unknown.name.is_none());
+    }
+
+    #[test]
+    fn test_app_ids_matching() {
+        let registry = TokenRegistry::from_yaml(
+            r#"
+- app_id: t/abc/def
+  symbol: TOAD
+  name: Toad Token
+- app_id: t/123/456
+  symbol: FROG
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(registry.app_ids_matching("toa"), vec!["t/abc/def"]);
+        assert_eq!(registry.app_ids_matching("Token"), vec!["t/abc/def"]);
+        assert_eq!(registry.app_ids_matching("FR"), vec!["t/123/456"]);
+        assert!(registry.app_ids_matching("newt").is_empty());
+    }
+}
@@ -0,0 +1,474 @@
+//! Delivery of events to registered webhooks
+//!
+//! The dispatcher listens on the event bus and queues a `webhook` job for
+//! every active subscription whose filters match the event and whose user
+//! holds an address or key of a party to the event's subject (see
+//! `event_owners`). Webhook URLs must resolve to public addresses, both
+//! when registered and on every delivery. Webhook workers
+//! (`WEBHOOK_WORKERS`, default 2) POST the event as JSON with
+//! `X-Webhook-Id` (stable across retries), `X-Webhook-Timestamp` and
+//! `X-Webhook-Signature: sha256=<hex>`, an HMAC-SHA256 of
+//! `<timestamp>.<body>` keyed with the subscription's secret. Every attempt
+//! is logged in `webhook_deliveries`; failed ones are retried by the job
+//! queue with backoff.
+
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::db::{self, DbPool, JobRecord, WebhookDeliveryRecord, WebhookSubscriptionRecord};
+use crate::services::events::{Event, EventBus};
+use crate::services::jobs::JobQueue;
+
+/// Queue job type the webhook workers run
+pub const WEBHOOK_JOB: &str = "webhook";
+
+/// Payload of a queued `webhook` job
+#[derive(Debug, Serialize, Deserialize)]
+struct WebhookJob {
+    subscription_id: String,
+    event_id: String,
+    event: Event,
+}
+
+/// Whether an event kind passes a subscription's filters: exact kinds, or
+/// `prefix.*` patterns; no filters let every event through
+pub fn matches_filters(filters: &[String], kind: &str) -> bool {
+    filters.is_empty()
+        || filters.iter().any(|filter| match filter.strip_suffix(".*") {
+            Some(prefix) => kind.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('.')),
+            None => filter == kind,
+        })
+}
+
+/// A subscription's filters, stored as a JSON array
+pub fn parse_filters(event_filters: &str) -> Vec<String> {
+    serde_json::from_str(event_filters).unwrap_or_default()
+}
+
+/// `sha256=<hex>` HMAC of `<timestamp>.<body>` under `secret`
+pub fn signature(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Whether an address is on the public internet; webhooks never reach
+/// loopback, private, link-local (e.g. cloud metadata at 169.254.169.254)
+/// or other reserved ranges
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || a == 0
+                // Shared address space (carrier-grade NAT)
+                || (a == 100 && (64..128).contains(&b))
+                // Benchmarking
+                || (a == 198 && (b == 18 || b == 19))
+                // Reserved
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(mapped) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(mapped));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                // Unique local
+                || (first & 0xfe00) == 0xfc00
+                // Link-local
+                || (first & 0xffc0) == 0xfe80
+                // Documentation
+                || (first == 0x2001 && ip.segments()[1] == 0x0db8))
+        }
+    }
+}
+
+/// Check that a webhook URL is http(s) and that every address its host
+/// resolves to is public
+pub async fn check_webhook_url(url: &str) -> Result<reqwest::Url> {
+    let parsed = reqwest::Url::parse(url).context("Not a valid URL")?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        bail!("URL must be http(s)");
+    }
+    let host = parsed.host_str().context("URL has no host")?;
+    let port = parsed.port_or_known_default().unwrap_or(80);
+
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let addrs: Vec<IpAddr> = match host.parse::<IpAddr>() {
+        Ok(ip) => vec![ip],
+        Err(_) => tokio::net::lookup_host((host, port))
+            .await
+            .with_context(|| format!("Cannot resolve {}", host))?
+            .map(|addr| addr.ip())
+            .collect(),
+    };
+    if addrs.is_empty() {
+        bail!("{} does not resolve", host);
+    }
+    if let Some(ip) = addrs.iter().find(|ip| !is_public_ip(**ip)) {
+        bail!("{} resolves to non-public address {}", host, ip);
+    }
+    Ok(parsed)
+}
+
+/// Resolves hosts to their public addresses only, so a host re-pointed at an
+/// internal address after the URL was checked still cannot be reached
+struct PublicResolver;
+
+impl reqwest::dns::Resolve for PublicResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| is_public_ip(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} resolves to no public address", name.as_str()).into());
+            }
+            let addrs: reqwest::dns::Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// Addresses and public keys of the parties to an event's subject: the
+/// maker and takers of an order, the parties of an escrow or proposal, or
+/// the watched address and its subscriber. Subjects nobody owns (e.g. a
+/// prove job) have none, and their events are sent to no webhook.
+pub async fn event_owners(db: &DbPool, event: &Event) -> Result<HashSet<String>> {
+    let subject = event.subject_id.as_str();
+    let mut owners = HashSet::new();
+
+    if event.kind.starts_with("address.") {
+        owners.insert(subject.to_string());
+        if let Some(id) = event.data.get("subscription_id").and_then(|id| id.as_str()) {
+            if let Some(subscription) = db::get_address_subscription(db, id).await? {
+                owners.insert(subscription.owner_address);
+            }
+        }
+    } else if let Some(order) = db::get_order_by_id(db, subject).await? {
+        owners.insert(order.maker_address);
+        owners.extend(order.maker_pubkey);
+        owners.extend(db::get_order_fills(db, subject).await?.into_iter().map(|f| f.taker_address));
+    } else if let Some(escrow) = db::get_escrow(db, subject).await? {
+        owners.extend(
+            [
+                Some(escrow.depositor_pubkey),
+                Some(escrow.recipient_pubkey),
+                escrow.arbiter_pubkey,
+                escrow.depositor_address,
+                escrow.recipient_address,
+            ]
+            .into_iter()
+            .flatten(),
+        );
+    } else if let Some(proposal) = db::get_escrow_proposal(db, subject).await? {
+        owners.extend(
+            [Some(proposal.depositor_pubkey), Some(proposal.recipient_pubkey), proposal.arbiter_pubkey]
+                .into_iter()
+                .flatten(),
+        );
+    }
+
+    Ok(owners)
+}
+
+/// Queue a `webhook` job for each matching subscription of every event
+/// published on the bus
+pub fn spawn_webhook_dispatcher(db: DbPool, events: EventBus, jobs: Arc<JobQueue>) {
+    let mut receiver = events.subscribe();
+    tokio::spawn(async move {
+        loop {
+            let event = match receiver.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(missed)) => {
+                    tracing::warn!("Webhook dispatcher missed {} events", missed);
+                    continue;
+                }
+                Err(RecvError::Closed) => return,
+            };
+            if let Err(e) = dispatch(&db, &jobs, event).await {
+                tracing::warn!("Failed to queue webhook deliveries: {:#}", e);
+            }
+        }
+    });
+}
+
+async fn dispatch(db: &DbPool, jobs: &JobQueue, event: Event) -> Result<()> {
+    let subscriptions: Vec<WebhookSubscriptionRecord> = db::get_active_webhook_subscriptions(db)
+        .await?
+        .into_iter()
+        .filter(|s| matches_filters(&parse_filters(&s.event_filters), &event.kind))
+        .collect();
+    if subscriptions.is_empty() {
+        return Ok(());
+    }
+    let owners = event_owners(db, &event).await?;
+    if owners.is_empty() {
+        return Ok(());
+    }
+
+    let mut owned_by_user: HashMap<String, bool> = HashMap::new();
+    for subscription in &subscriptions {
+        let owned = match owned_by_user.get(&subscription.user_id) {
+            Some(owned) => *owned,
+            None => {
+                let identities = db::get_user_identities(db, &subscription.user_id).await?;
+                let owned = identities.iter().any(|id| owners.contains(id));
+                owned_by_user.insert(subscription.user_id.clone(), owned);
+                owned
+            }
+        };
+        if !owned {
+            continue;
+        }
+
+        let job = WebhookJob {
+            subscription_id: subscription.id.clone(),
+            event_id: uuid::Uuid::new_v4().to_string(),
+            event: event.clone(),
+        };
+        jobs.enqueue(WEBHOOK_JOB, &job).await?;
+    }
+    Ok(())
+}
+
+/// Spawn the workers delivering queued webhook jobs
+pub fn spawn_webhook_workers(db: DbPool, jobs: Arc<JobQueue>) {
+    let workers: usize = std::env::var("WEBHOOK_WORKERS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(2);
+    // Redirects are not followed; they could point anywhere
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .redirect(reqwest::redirect::Policy::none())
+        .dns_resolver(Arc::new(PublicResolver))
+        .build()
+        .expect("webhook HTTP client");
+
+    jobs.spawn_workers(WEBHOOK_JOB, workers, move |job| {
+        let db = db.clone();
+        let client = client.clone();
+        async move { deliver(&db, &client, &job).await }
+    });
+}
+
+/// Attempt one delivery and log it; an error sends the job back for a retry
+async fn deliver(db: &DbPool, client: &reqwest::Client, job: &JobRecord) -> Result<()> {
+    let payload: WebhookJob = serde_json::from_str(&job.payload).context("Unreadable webhook job payload")?;
+    // Deleted or paused since the event was queued
+    let Some(subscription) = db::get_webhook_subscription(db, &payload.subscription_id)
+        .await?
+        .filter(|s| s.active)
+    else {
+        return Ok(());
+    };
+
+    let started = Instant::now();
+    let outcome = post(client, &subscription, &payload).await;
+    let (status_code, error) = match &outcome {
+        Ok(status) if status.is_success() => (Some(status.as_u16() as i32), None),
+        Ok(status) => (Some(status.as_u16() as i32), Some(format!("Endpoint answered {}", status))),
+        Err(e) => (None, Some(format!("{:#}", e))),
+    };
+
+    let delivery = WebhookDeliveryRecord {
+        id: 0,
+        subscription_id: subscription.id.clone(),
+        event_id: payload.event_id.clone(),
+        event_kind: payload.event.kind.clone(),
+        subject_id: payload.event.subject_id.clone(),
+        attempt: job.attempts,
+        succeeded: error.is_none(),
+        status_code,
+        error: error.clone(),
+        duration_ms: started.elapsed().as_millis() as i64,
+        created_at: chrono::Utc::now(),
+    };
+    if let Err(e) = db::insert_webhook_delivery(db, &delivery).await {
+        tracing::warn!("Failed to log delivery of {} to webhook {}: {}", payload.event_id, subscription.id, e);
+    }
+
+    if let Some(error) = error {
+        bail!("Webhook {} delivery of {}: {}", subscription.id, payload.event.kind, error);
+    }
+    Ok(())
+}
+
+async fn post(
+    client: &reqwest::Client,
+    subscription: &WebhookSubscriptionRecord,
+    payload: &WebhookJob,
+) -> Result<reqwest::StatusCode> {
+    let url = check_webhook_url(&subscription.url).await?;
+    let body = serde_json::to_string(&payload.event)?;
+    let timestamp = chrono::Utc::now().timestamp();
+
+    let response = client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header("X-Webhook-Id", &payload.event_id)
+        .header("X-Webhook-Timestamp", timestamp.to_string())
+        .header("X-Webhook-Signature", signature(&subscription.secret, timestamp, &body))
+        .body(body)
+        .send()
+        .await?;
+    Ok(response.status())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_filters() {
+        let filters = vec!["order.*".to_string(), "escrow.expired".to_string()];
+        assert!(matches_filters(&filters, "order.filled"));
+        assert!(matches_filters(&filters, "order.externally_spent"));
+        assert!(matches_filters(&filters, "escrow.expired"));
+        assert!(!matches_filters(&filters, "escrow.disputed"));
+        assert!(!matches_filters(&filters, "orders.filled"));
+        assert!(!matches_filters(&filters, "order"));
+
+        assert!(matches_filters(&[], "address.deposit"));
+        assert_eq!(parse_filters(r#"["order.*"]"#), vec!["order.*"]);
+        assert!(parse_filters("not json").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_webhook_urls_must_be_public() {
+        for url in [
+            "http://127.0.0.1/hook",
+            "http://localhost:8080/hook",
+            "http://10.1.2.3/hook",
+            "http://192.168.0.1/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://100.64.0.1/hook",
+            "http://0.0.0.0/hook",
+            "http://[::1]/hook",
+            "http://[fd00::1]/hook",
+            "http://[fe80::1]/hook",
+            "http://[::ffff:127.0.0.1]/hook",
+            "ftp://93.184.216.34/hook",
+            "not a url",
+        ] {
+            assert!(check_webhook_url(url).await.is_err(), "{} accepted", url);
+        }
+
+        assert!(check_webhook_url("https://93.184.216.34/hook").await.is_ok());
+        assert!(check_webhook_url("http://[2606:2800:220:1::1]:8443/hook").await.is_ok());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_dispatch_only_to_subject_owners() {
+        let db = db::testing::fresh_db().await;
+
+        const MAKER: &str = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+        const OTHER: &str = "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7";
+        const LINKED: &str = "tb1q0ht9tyks4vh7p5p904t340cr9nvahy7u3re7zg";
+
+        let now = chrono::Utc::now();
+        let order = db::OrderRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            maker_address: MAKER.to_string(),
+            offer_token: "TOAD".to_string(),
+            offer_amount: 1000,
+            want_token: "BTC".to_string(),
+            want_amount: 10_000,
+            source_chain: "bitcoin".to_string(),
+            dest_chain: "bitcoin".to_string(),
+            status: "open".to_string(),
+            allow_partial: false,
+            filled_amount: 0,
+            expiry_height: None,
+            utxo_id: None,
+            tx_id: None,
+            created_at: now,
+            updated_at: now,
+            version: 0,
+            archived_at: None,
+            maker_pubkey: None,
+            dest_address: None,
+        };
+        db::insert_order(&db, &order).await.unwrap();
+
+        // The maker, a stranger, and a user who linked the maker's wallet to
+        // their session each register a webhook for every event
+        let maker = db::upsert_user(&db, MAKER, None).await.unwrap();
+        let other = db::upsert_user(&db, OTHER, None).await.unwrap();
+        db::insert_session(&db, "linked-session", LINKED, now + chrono::Duration::hours(1)).await.unwrap();
+        db::add_session_wallet(&db, "linked-session", MAKER, None).await.unwrap();
+        let linked = db::upsert_user(&db, LINKED, None).await.unwrap();
+        for user in [&maker, &other, &linked] {
+            let subscription = WebhookSubscriptionRecord {
+                id: user.id.clone(),
+                user_id: user.id.clone(),
+                url: "https://93.184.216.34/hook".to_string(),
+                secret: "key".to_string(),
+                event_filters: "[]".to_string(),
+                active: true,
+                created_at: now,
+                updated_at: now,
+            };
+            db::insert_webhook_subscription(&db, &subscription).await.unwrap();
+        }
+
+        let jobs = JobQueue::from_env(db.clone());
+        let queued_for = || async {
+            let payloads: Vec<String> = sqlx::query_scalar("SELECT payload FROM jobs WHERE job_type = $1")
+                .bind(WEBHOOK_JOB)
+                .fetch_all(&db)
+                .await
+                .unwrap();
+            let mut users: Vec<String> = payloads
+                .iter()
+                .map(|p| serde_json::from_str::<WebhookJob>(p).unwrap().subscription_id)
+                .collect();
+            users.sort();
+            users
+        };
+
+        dispatch(&db, &jobs, Event::new("order.filled", order.id.clone(), serde_json::json!({}))).await.unwrap();
+        let mut expected = vec![maker.id.clone(), linked.id.clone()];
+        expected.sort();
+        assert_eq!(queued_for().await, expected);
+
+        // Nobody owns a prove job, so its events go nowhere
+        dispatch(&db, &jobs, Event::new("prove_job.succeeded", "job-1", serde_json::json!({}))).await.unwrap();
+        assert_eq!(queued_for().await, expected);
+    }
+
+    #[test]
+    fn test_signature() {
+        // Keyed on the secret, over the timestamp and body together
+        let signed = signature("key", 1700000000, r#"{"kind":"order.filled"}"#);
+        assert!(signed.starts_with("sha256="));
+        assert_eq!(signed.len(), "sha256=".len() + 64);
+        assert_eq!(signed, signature("key", 1700000000, r#"{"kind":"order.filled"}"#));
+        assert_ne!(signed, signature("other", 1700000000, r#"{"kind":"order.filled"}"#));
+        assert_ne!(signed, signature("key", 1700000001, r#"{"kind":"order.filled"}"#));
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"key").unwrap();
+        mac.update(br#"1700000000.{"kind":"order.filled"}"#);
+        assert_eq!(signed, format!("sha256={}", hex::encode(mac.finalize().into_bytes())));
+    }
+}
@@ -0,0 +1,185 @@
+//! Real-time block and transaction notifications from Bitcoin Core's ZMQ
+//! interface
+//!
+//! With `ZMQ_RAWBLOCK_URL` and/or `ZMQ_RAWTX_URL` set (the node's
+//! `-zmqpubrawblock` / `-zmqpubrawtx` endpoints), a listener subscribes to
+//! them and announces each new block and mempool transaction on a
+//! `ChainEvents` channel. The indexer, deposit monitor and escrow expiry
+//! monitor wake on those announcements instead of waiting for their next
+//! poll, so confirmations reach order status within seconds. Their polling
+//! loops stay as a fallback for missed notifications and for backends
+//! without ZMQ.
+
+use std::time::Duration;
+
+use bitcoin::block::Header;
+use bitcoin::consensus::encode::deserialize;
+use bitcoin::{BlockHash, Transaction, Txid};
+use tokio::sync::broadcast;
+use zeromq::{Socket, SocketRecv, SubSocket};
+
+/// Announcements buffered for slow listeners; a lagging listener just runs
+/// one catch-up for all it missed
+const CHANNEL_CAPACITY: usize = 64;
+
+/// Delay before reconnecting to an endpoint that dropped
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Something new on the chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainEvent {
+    Block(BlockHash),
+    Transaction(Txid),
+}
+
+/// Fans chain announcements out to the background monitors
+#[derive(Clone)]
+pub struct ChainEvents {
+    sender: broadcast::Sender<ChainEvent>,
+}
+
+impl ChainEvents {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ChainEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn publish(&self, event: ChainEvent) {
+        // No listeners is not an error
+        let _ = self.sender.send(event);
+    }
+}
+
+impl Default for ChainEvents {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Wait for the next announcement `wanted` accepts; never returns when the
+/// channel is closed, leaving the caller's poll interval in charge
+pub async fn next_event(receiver: &mut broadcast::Receiver<ChainEvent>, wanted: fn(&ChainEvent) -> bool) {
+    loop {
+        match receiver.recv().await {
+            Ok(event) if wanted(&event) => return,
+            Ok(_) => {}
+            // Missed some; one wake-up covers them all
+            Err(broadcast::error::RecvError::Lagged(_)) => return,
+            Err(broadcast::error::RecvError::Closed) => std::future::pending().await,
+        }
+    }
+}
+
+pub fn is_block(event: &ChainEvent) -> bool {
+    matches!(event, ChainEvent::Block(_))
+}
+
+pub fn is_any(_: &ChainEvent) -> bool {
+    true
+}
+
+/// Announcement carried by a ZMQ message's topic and body frames
+fn parse_notification(topic: &[u8], body: &[u8]) -> Option<ChainEvent> {
+    match topic {
+        b"rawblock" => {
+            let header: Header = deserialize(body.get(..80)?).ok()?;
+            Some(ChainEvent::Block(header.block_hash()))
+        }
+        b"rawtx" => {
+            let tx: Transaction = deserialize(body).ok()?;
+            Some(ChainEvent::Transaction(tx.compute_txid()))
+        }
+        _ => None,
+    }
+}
+
+/// Subscribe to the node's ZMQ endpoints in the background, if configured
+pub fn spawn_zmq_listener(events: ChainEvents) {
+    let endpoint = |name: &str| std::env::var(name).ok().filter(|url| !url.is_empty());
+    let block_url = endpoint("ZMQ_RAWBLOCK_URL");
+    let tx_url = endpoint("ZMQ_RAWTX_URL");
+    if block_url.is_none() && tx_url.is_none() {
+        tracing::info!("ZMQ notifications not configured; monitors poll the chain");
+        return;
+    }
+
+    // Core may publish both topics on one endpoint
+    let subscriptions: Vec<(String, Vec<&'static str>)> = match (block_url, tx_url) {
+        (Some(block), Some(tx)) if block == tx => vec![(block, vec!["rawblock", "rawtx"])],
+        (block, tx) => block
+            .map(|url| (url, vec!["rawblock"]))
+            .into_iter()
+            .chain(tx.map(|url| (url, vec!["rawtx"])))
+            .collect(),
+    };
+
+    for (url, topics) in subscriptions {
+        let events = events.clone();
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = listen(&url, &topics, &events).await {
+                    tracing::warn!("ZMQ subscription to {} failed: {}", url, e);
+                }
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        });
+    }
+}
+
+/// Relay notifications from one endpoint until the connection fails
+async fn listen(url: &str, topics: &[&str], events: &ChainEvents) -> anyhow::Result<()> {
+    let mut socket = SubSocket::new();
+    socket.connect(url).await?;
+    for topic in topics {
+        socket.subscribe(topic).await?;
+    }
+    tracing::info!("Subscribed to {} on {}", topics.join(", "), url);
+
+    loop {
+        let message = socket.recv().await?;
+        let (Some(topic), Some(body)) = (message.get(0), message.get(1)) else {
+            continue;
+        };
+        match parse_notification(topic, body) {
+            Some(event) => events.publish(event),
+            None => tracing::debug!("Ignoring ZMQ message on {}", String::from_utf8_lossy(topic)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::blockdata::constants::genesis_block;
+    use bitcoin::consensus::encode::serialize;
+    use bitcoin::Network;
+
+    #[tokio::test]
+    async fn test_notifications_wake_listeners() {
+        let genesis = genesis_block(Network::Testnet4);
+        let block = serialize(&genesis);
+        assert_eq!(
+            parse_notification(b"rawblock", &block),
+            Some(ChainEvent::Block(genesis.block_hash()))
+        );
+        let coinbase = &genesis.txdata[0];
+        assert_eq!(
+            parse_notification(b"rawtx", &serialize(coinbase)),
+            Some(ChainEvent::Transaction(coinbase.compute_txid()))
+        );
+        assert_eq!(parse_notification(b"rawblock", &block[..40]), None);
+        assert_eq!(parse_notification(b"hashblock", &block), None);
+
+        // A block-only listener sleeps through transactions
+        let events = ChainEvents::new();
+        let mut receiver = events.subscribe();
+        events.publish(ChainEvent::Transaction(coinbase.compute_txid()));
+        events.publish(ChainEvent::Block(genesis.block_hash()));
+        next_event(&mut receiver, is_block).await;
+        assert!(receiver.is_empty());
+    }
+}
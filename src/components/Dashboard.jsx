@@ -38,7 +38,7 @@ function StatusCell({ percent }) {
 
 function Dashboard({ chainThemes, onNavigate }) {
   const { orders, deleteOrder, cancelAllOrders, loading } = useOrders();
-  const { address: btcAddress } = useWallet();
+  const { address: btcAddress, signMessage } = useWallet();
   const { address: evmAddress } = useEVMWallet();
   
   // Filter for user's orders (orders created by the current user)
@@ -107,7 +107,7 @@ function Dashboard({ chainThemes, onNavigate }) {
   const handleCancelOrder = async (orderId) => {
     if (window.confirm('Are you sure you want to cancel this order?')) {
       try {
-        await deleteOrder(orderId);
+        await deleteOrder(orderId, { makerAddress: btcAddress, signMessage });
         // If we're on a page that becomes empty after deletion, go back a page
         if (paginatedOrders.length === 1 && currentPage > 1) {
           setCurrentPage(currentPage - 1);
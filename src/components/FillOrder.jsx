@@ -51,7 +51,7 @@ const getStatusClass = (status) => {
 
 function FillOrder({ orderId, chainThemes, onNavigate }) {
   const { orders, fillOrder, loading: ordersLoading } = useOrders();
-  const { connected: btcConnected, address: btcAddress, signPsbt } = useWallet() || {};
+  const { connected: btcConnected, address: btcAddress, signPsbt, signMessage } = useWallet() || {};
   const { connected: evmConnected, address: evmAddress } = useEVMWallet() || {};
   
   const [order, setOrder] = useState(null);
@@ -167,17 +167,18 @@ function FillOrder({ orderId, chainThemes, onNavigate }) {
     setError(null);
     
     try {
-      const takerAddress = btcAddress || evmAddress;
-      if (!takerAddress) {
-        throw new Error('Please connect a wallet first');
+      if (!btcAddress) {
+        throw new Error('Please connect a Bitcoin wallet first');
       }
 
-      // Call the fill order API
-      const response = await api.fillOrder(orderId, {
-        taker_address: takerAddress,
-        fill_amount: sendAmount,
-        fill_percent: fillPercent,
+      // The taker signs the fill with the wallet and funds it from one of its UTXOs
+      const id = order._apiOrder?.id || order.id || orderId;
+      const request = await api.prepareFill(id, {
+        takerAddress: btcAddress,
+        fillPercent,
+        signMessage,
       });
+      const response = await api.fillOrder(id, request);
 
       if (response && response.unsigned_txs && response.unsigned_txs.length > 0) {
         // Show signing modal
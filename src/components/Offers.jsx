@@ -37,7 +37,7 @@ function StatusCell({ percent }) {
 
 function Offers({ chainThemes, onNavigate }) {
   const { orders, deleteOrder } = useOrders();
-  const { address: btcAddress } = useWallet();
+  const { address: btcAddress, signMessage } = useWallet();
   const { address: evmAddress } = useEVMWallet();
   const [showCreateMenu, setShowCreateMenu] = useState(false);
   const menuRef = useRef(null);
@@ -119,7 +119,7 @@ function Offers({ chainThemes, onNavigate }) {
     
     if (window.confirm('Are you sure you want to cancel this order?')) {
       try {
-        await deleteOrder(orderId);
+        await deleteOrder(orderId, { makerAddress: btcAddress, signMessage });
       } catch (err) {
         console.error('Failed to cancel order:', err);
         alert('Failed to cancel order. Please try again.');
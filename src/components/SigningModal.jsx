@@ -82,7 +82,7 @@ const SigningModal = ({
         console.log('PSBT hex:', unsignedTx.hex?.substring(0, 50) + '...');
         try {
           // LaserEyes PSBT signing - this will open the wallet
-          const signedPsbt = await signPsbt(unsignedTx.hex, {
+          const signedPsbt = await signPsbt(unsignedTx.psbt?.psbt_base64 || unsignedTx.hex, {
             finalize: true,
             broadcast: false,
           });
@@ -245,6 +245,14 @@ const SigningModal = ({
                   <span className="value">{unsignedTxs[0]?.inputs_to_sign?.length || 1}</span>
                 </div>
                 
+                {unsignedTxs[0]?.psbt?.summary?.length > 0 && (
+                  <ul className="tx-summary">
+                    {unsignedTxs[0].psbt.summary.map((line, i) => (
+                      <li key={i}>{line}</li>
+                    ))}
+                  </ul>
+                )}
+                
                 {unsignedTxs[0]?.hex && (
                   <details className="tx-hex-details">
                     <summary>View Transaction Hex</summary>
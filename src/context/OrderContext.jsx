@@ -57,7 +57,7 @@ const convertUIOrderToApi = (uiOrder) => ({
   allowPartial: uiOrder.partial !== false,
   expiryBlocks: uiOrder.expiryBlocks || 144,
   fundingUtxo: uiOrder.fundingUtxo || '',
-  fundingUtxoValue: uiOrder.fundingUtxoValue || 10000,
+  fundingUtxoValue: uiOrder.fundingUtxoValue,
   destAddress: uiOrder.destAddress || uiOrder.btcWallet || '',
 });
 
@@ -216,6 +216,7 @@ export const OrderProvider = ({ children }) => {
 
   /**
    * Fill an order
+   * @param {Object} fillData - Taker address, fill percent and wallet `signMessage` (see `api.prepareFill`)
    */
   const fillOrder = async (orderId, fillData) => {
     try {
@@ -227,11 +228,8 @@ export const OrderProvider = ({ children }) => {
         throw new Error('Order not found');
       }
 
-      const response = await api.fillOrder(order.id, {
-        takerAddress: fillData.takerAddress,
-        takerUtxo: fillData.takerUtxo,
-        fillAmount: fillData.fillAmount,
-      });
+      const request = await api.prepareFill(order.id, fillData);
+      const response = await api.fillOrder(order.id, request);
 
       // Set signing data for fill
       setSigningData({
@@ -255,8 +253,9 @@ export const OrderProvider = ({ children }) => {
 
   /**
    * Cancel/delete an order
+   * @param {Object} cancelOptions - Maker address and wallet `signMessage` (see `api.prepareCancel`)
    */
-  const deleteOrder = async (orderId) => {
+  const deleteOrder = async (orderId, cancelOptions) => {
     try {
       setLoading(true);
       setError(null);
@@ -265,7 +264,8 @@ export const OrderProvider = ({ children }) => {
       
       if (order?.id) {
         // Call backend cancel
-        const response = await api.cancelOrder(order.id);
+        const cancelData = await api.prepareCancel(order.id, cancelOptions);
+        const response = await api.cancelOrder(order.id, cancelData);
         
         // If there are transactions to sign, open modal
         if (response.unsigned_txs?.length > 0) {
@@ -7,15 +7,45 @@
 
 const API_BASE_URL = import.meta.env.VITE_API_URL || 'http://localhost:3001/api';
 
+// Bearer token from a signed wallet connect, sent with every request
+let sessionToken = null;
+
+/**
+ * Set (or clear with null) the wallet session token used for authenticated requests
+ * @param {string|null} token - Session token from connectWallet
+ */
+export function setSessionToken(token) {
+  sessionToken = token;
+}
+
+// Browser wallet whose signing call shape is requested on signing endpoints
+let walletFormat = null;
+
+/**
+ * Set (or clear with null) the browser wallet signing payloads are shaped for.
+ * Unsigned transactions then carry a `wallet_request` with that wallet's
+ * signing arguments.
+ * @param {'unisat'|'xverse'|null} wallet - Wallet name
+ */
+export function setWalletFormat(wallet) {
+  walletFormat = wallet;
+}
+
 /**
  * Generic API request handler
  */
 async function apiRequest(endpoint, options = {}) {
-  const url = `${API_BASE_URL}${endpoint}`;
+  let url = `${API_BASE_URL}${endpoint}`;
+  if (walletFormat && options.method && options.method !== 'GET') {
+    url += `${url.includes('?') ? '&' : '?'}wallet=${walletFormat}`;
+  }
   
   const defaultHeaders = {
     'Content-Type': 'application/json',
   };
+  if (sessionToken) {
+    defaultHeaders.Authorization = `Bearer ${sessionToken}`;
+  }
 
   const config = {
     ...options,
@@ -29,10 +59,17 @@ async function apiRequest(endpoint, options = {}) {
     const response = await fetch(url, config);
     
     if (!response.ok) {
-      const error = await response.json().catch(() => ({ message: response.statusText }));
-      throw new Error(error.message || `API Error: ${response.status}`);
+      const body = await response.json().catch(() => ({ message: response.statusText }));
+      // `code` tells failures apart, e.g. `insufficient_funding` or `prover_timeout`
+      const error = new Error(body.error || body.message || `API Error: ${response.status}`);
+      error.code = body.code;
+      error.status = response.status;
+      throw error;
     }
     
+    if (response.status === 204) {
+      return null;
+    }
     return await response.json();
   } catch (error) {
     console.error(`API Error (${endpoint}):`, error);
@@ -48,6 +85,30 @@ export async function checkHealth() {
   return apiRequest('/health');
 }
 
+/**
+ * Bitcoin node RPC call counts, failures, retries and latency by method
+ * @returns {Promise<Object>} Stats keyed by RPC method
+ */
+export async function getRpcMetrics() {
+  return apiRequest('/health/rpc');
+}
+
+/**
+ * Configured Bitcoin nodes with their reachability and block height
+ * @returns {Promise<Array>} Nodes in the order RPC calls try them
+ */
+export async function getRpcNodes() {
+  return apiRequest('/health/nodes');
+}
+
+/**
+ * Verification keys of the swap and escrow apps and where each came from
+ * @returns {Promise<Array<{ contract: string, vk: string, source: string }>>}
+ */
+export async function getApps() {
+  return apiRequest('/apps');
+}
+
 // ============================================
 // Order Management
 // ============================================
@@ -86,6 +147,27 @@ export async function getOrder(orderId) {
   return apiRequest(`/orders/${orderId}`);
 }
 
+/**
+ * Spell template versions used to build an order's spells
+ * @param {string} orderId - Order ID
+ * @returns {Promise<Array<{ template_name: string, template_version: number, used_at: string }>>}
+ */
+export async function getOrderSpellTemplates(orderId) {
+  return apiRequest(`/orders/${orderId}/spell-templates`);
+}
+
+/**
+ * Spells built and proved for an order, oldest first (admin)
+ * @param {string} orderId - Order ID
+ * @param {string} adminToken - The server's ADMIN_API_TOKEN
+ * @returns {Promise<Array<{ id: string, spell: string, request_hash: string, status: string, transactions: Array<{ txid: string, hex: string }>, error: string|null, prove_ms: number, created_at: string }>>}
+ */
+export async function getOrderSpells(orderId, adminToken) {
+  return apiRequest(`/orders/${orderId}/spells`, {
+    headers: { 'X-Admin-Token': adminToken },
+  });
+}
+
 /**
  * Create a new swap order
  * @param {Object} orderData - Order creation data
@@ -99,6 +181,7 @@ export async function getOrder(orderId) {
  * @param {boolean} orderData.allowPartial - Allow partial fills
  * @param {number} orderData.expiryBlocks - Expiry in blocks
  * @param {string} orderData.fundingUtxo - UTXO to fund the order
+ * @param {number} orderData.fundingUtxoValue - Value of the funding UTXO in sats
  */
 export async function createOrder(orderData) {
   return apiRequest('/orders', {
@@ -114,6 +197,7 @@ export async function createOrder(orderData) {
       allow_partial: orderData.allowPartial,
       expiry_blocks: orderData.expiryBlocks,
       funding_utxo: orderData.fundingUtxo,
+      funding_utxo_value: orderData.fundingUtxoValue,
     }),
   });
 }
@@ -124,7 +208,10 @@ export async function createOrder(orderData) {
  * @param {Object} fillData - Fill data
  * @param {string} fillData.takerAddress - Taker's address
  * @param {string} fillData.takerUtxo - Taker's UTXO with tokens
+ * @param {number} fillData.takerUtxoValue - Value of the taker's UTXO in sats
  * @param {string} fillData.fillAmount - Amount to fill (for partial)
+ * @param {string} fillData.signature - Taker's signature over
+ *   `liquid-nation:order:fill:<orderId>:<takerAddress>:<amount>:<takerUtxo>`
  */
 export async function fillOrder(orderId, fillData) {
   return apiRequest(`/orders/${orderId}/fill`, {
@@ -132,7 +219,9 @@ export async function fillOrder(orderId, fillData) {
     body: JSON.stringify({
       taker_address: fillData.takerAddress,
       taker_utxo: fillData.takerUtxo,
+      taker_utxo_value: fillData.takerUtxoValue,
       fill_amount: fillData.fillAmount,
+      signature: fillData.signature,
     }),
   });
 }
@@ -148,21 +237,138 @@ export async function partialFillOrder(orderId, fillData) {
     body: JSON.stringify({
       taker_address: fillData.takerAddress,
       taker_utxo: fillData.takerUtxo,
+      taker_utxo_value: fillData.takerUtxoValue,
       fill_amount: fillData.fillAmount,
+      signature: fillData.signature,
     }),
   });
 }
 
+/**
+ * Message a party signs to authorize an order action, as the backend
+ * rebuilds it: `liquid-nation:order:<action>:<orderId>[:<field>...]`
+ * @param {string} action - Action name (`fill`, `cancel`, ...)
+ * @param {string} orderId - Order ID
+ * @param {string[]} [fields] - Action fields, in order
+ */
+export function orderActionMessage(action, orderId, fields = []) {
+  return [`liquid-nation:order:${action}:${orderId}`, ...fields].join(':');
+}
+
+/**
+ * Assemble a signed fill for `fillOrder`: picks the taker's largest free UTXO
+ * and has the wallet sign (BIP-322) the offer units taken
+ * @param {string} orderId - Order ID to fill
+ * @param {Object} options - Fill options
+ * @param {string} options.takerAddress - Taker's Bitcoin address
+ * @param {number} [options.fillPercent] - Share of what is left to take (default 100)
+ * @param {Function} options.signMessage - Wallet signer, `(message, { toSignAddress, protocol })`
+ */
+export async function prepareFill(orderId, { takerAddress, fillPercent = 100, signMessage }) {
+  if (!takerAddress || typeof signMessage !== 'function') {
+    throw new Error('Connect a Bitcoin wallet that can sign messages to fill orders');
+  }
+
+  const [order, utxos] = await Promise.all([
+    getOrder(orderId),
+    getWalletUtxos({ address: takerAddress }),
+  ]);
+  if (!order) {
+    throw new Error('Order not found');
+  }
+  const takerUtxo = (utxos || [])
+    .filter((utxo) => !utxo.reserved_by && !utxo.locked_until)
+    .sort((a, b) => b.value - a.value)[0];
+  if (!takerUtxo) {
+    throw new Error('No free UTXO at the taker address to fill with');
+  }
+
+  // The signature covers the offer base units taken, all that is left for a full fill
+  const remaining = BigInt(order.offer_amount) - BigInt(order.filled_amount || '0');
+  const partial = fillPercent < 100;
+  const amount = partial ? (remaining * BigInt(Math.round(fillPercent))) / 100n : remaining;
+  const outpoint = `${takerUtxo.txid}:${takerUtxo.vout}`;
+  const message = orderActionMessage('fill', orderId, [takerAddress, amount.toString(), outpoint]);
+  const signature = await signMessage(message, { toSignAddress: takerAddress, protocol: 'bip322' });
+
+  return {
+    takerAddress,
+    takerUtxo: outpoint,
+    takerUtxoValue: takerUtxo.value,
+    fillAmount: partial ? amount.toString() : undefined,
+    signature,
+  };
+}
+
+/**
+ * Assemble a signed cancellation for `cancelOrder`: has the wallet sign
+ * (BIP-322) the cancel message and, for orders already on chain, picks the
+ * maker's largest free UTXO to pay the fee
+ * @param {string} orderId - Order ID to cancel
+ * @param {Object} options - Cancel options
+ * @param {string} options.makerAddress - Maker's Bitcoin address
+ * @param {Function} options.signMessage - Wallet signer, `(message, { toSignAddress, protocol })`
+ */
+export async function prepareCancel(orderId, { makerAddress, signMessage }) {
+  if (!makerAddress || typeof signMessage !== 'function') {
+    throw new Error('Connect a Bitcoin wallet that can sign messages to cancel orders');
+  }
+
+  const order = await getOrder(orderId);
+  if (!order) {
+    throw new Error('Order not found');
+  }
+
+  const signature = await signMessage(orderActionMessage('cancel', orderId), {
+    toSignAddress: makerAddress,
+    protocol: 'bip322',
+  });
+
+  // Nothing is locked on chain before the order is signed, so no fee to pay
+  if (order.status === 'pendingsignature') {
+    return { signature };
+  }
+
+  const utxos = await getWalletUtxos({ address: makerAddress });
+  const feeUtxo = (utxos || [])
+    .filter((utxo) => !utxo.reserved_by && !utxo.locked_until)
+    .sort((a, b) => b.value - a.value)[0];
+  if (!feeUtxo) {
+    throw new Error('No free UTXO at the maker address to pay the cancel fee');
+  }
+
+  return {
+    signature,
+    fundingUtxo: `${feeUtxo.txid}:${feeUtxo.vout}`,
+    fundingUtxoValue: feeUtxo.value,
+  };
+}
+
 /**
  * Cancel an order
  * @param {string} orderId - Order ID to cancel
+ * @param {Object} cancelData - Maker's signature over
+ *   `liquid-nation:order:cancel:<orderId>`, plus the UTXO paying the fee for
+ *   orders already on chain
  */
-export async function cancelOrder(orderId) {
+export async function cancelOrder(orderId, cancelData) {
   return apiRequest(`/orders/${orderId}/cancel`, {
     method: 'DELETE',
+    body: JSON.stringify({
+      signature: cancelData.signature,
+      funding_utxo: cancelData.fundingUtxo,
+      funding_utxo_value: cancelData.fundingUtxoValue,
+    }),
   });
 }
 
+/**
+ * List orders made by any wallet linked to the session (requires a session)
+ */
+export async function getMyOrders() {
+  return apiRequest('/orders/mine');
+}
+
 /**
  * Broadcast a signed order transaction
  * @param {string} orderId - Order ID
@@ -170,10 +376,27 @@ export async function cancelOrder(orderId) {
  */
 export async function broadcastOrder(orderId, signedTxHex) {
   return apiRequest(`/orders/${orderId}/broadcast`, {
+    method: 'POST',
+    body: JSON.stringify({ signed_tx_hex: signedTxHex }),
+  });
+}
+
+/**
+ * Rebuild an order's unconfirmed transaction at a higher fee rate: the
+ * maker's own, or a stuck fill's by its taker.
+ * Returns `{ order, replaced_txid, paid, fee_rate, unsigned_txs, signing_instructions }`;
+ * sign the replacement and submit it with broadcastOrder.
+ * @param {string} orderId - Order ID
+ * @param {Object} [options] - Optional parameters
+ * @param {number} [options.feeRate] - sat/vB to reach (defaults to the current estimate for feeTier)
+ * @param {string} [options.feeTier] - 'fast', 'normal' or 'economy' (default 'normal')
+ */
+export async function bumpOrderFee(orderId, options = {}) {
+  return apiRequest(`/orders/${orderId}/bump-fee`, {
     method: 'POST',
     body: JSON.stringify({
-      signed_tx_hex: signedTxHex,
-      order_id: orderId,
+      fee_rate: options.feeRate,
+      fee_tier: options.feeTier,
     }),
   });
 }
@@ -183,35 +406,343 @@ export async function broadcastOrder(orderId, signedTxHex) {
 // ============================================
 
 /**
- * Connect wallet
+ * Connect wallet. Call with just the address to receive a challenge, then
+ * again with the wallet's BIP-322 signature over `challenge.message`; the
+ * returned session token is stored for authenticated requests.
  * @param {Object} walletData - Wallet connection data
  * @param {string} walletData.address - Wallet address
- * @param {string} walletData.signature - Optional signature for verification
- * @param {string} walletData.message - Optional message that was signed
+ * @param {string} [walletData.signature] - BIP-322 signature (base64) over the challenge
+ * @param {string} [walletData.message] - Challenge message that was signed
+ * @param {Object} [walletData.keyOrigin] - Hardware wallet key origin
+ *   ({ master_fingerprint, derivation_path, pubkey }) so signing payloads carry derivation paths
+ * @param {string} [walletData.pubkey] - Public key behind the address, to find its escrows
+ * @param {string} [walletData.pubkeySignature] - BIP-340 signature by pubkey over the challenge message (required with pubkey)
  */
 export async function connectWallet(walletData) {
-  return apiRequest('/wallet/connect', {
+  const response = await apiRequest('/wallet/connect', {
     method: 'POST',
     body: JSON.stringify({
       address: walletData.address,
       signature: walletData.signature,
       message: walletData.message,
+      key_origin: walletData.keyOrigin,
+      pubkey: walletData.pubkey,
+      pubkey_signature: walletData.pubkeySignature,
     }),
   });
+
+  if (response.session_token) {
+    setSessionToken(response.session_token);
+  }
+  return response;
 }
 
 /**
- * Get wallet balance
+ * Get the current session and its linked wallets (requires a session)
  */
-export async function getWalletBalance() {
-  return apiRequest('/wallet/balance');
+export async function getSession() {
+  return apiRequest('/wallet/session');
 }
 
 /**
- * Get wallet UTXOs
+ * Link another wallet to the current session. Request a challenge for the
+ * address with `connectWallet({ address })` first, then pass its signature.
+ * @param {Object} walletData - Same fields as `connectWallet`, signature required
  */
-export async function getWalletUtxos() {
-  return apiRequest('/wallet/utxos');
+export async function linkWallet(walletData) {
+  return apiRequest('/wallet/session/addresses', {
+    method: 'POST',
+    body: JSON.stringify({
+      address: walletData.address,
+      signature: walletData.signature,
+      message: walletData.message,
+      key_origin: walletData.keyOrigin,
+      pubkey: walletData.pubkey,
+      pubkey_signature: walletData.pubkeySignature,
+    }),
+  });
+}
+
+/**
+ * Unlink a wallet from the current session
+ * @param {string} address - Linked address to remove
+ */
+export async function unlinkWallet(address) {
+  return apiRequest(`/wallet/session/addresses/${encodeURIComponent(address)}`, {
+    method: 'DELETE',
+  });
+}
+
+/**
+ * Get wallet balance with per-token charm amounts and NFTs held
+ * @param {string} [address] - Address to report on (defaults to the connected wallet; one of the two is required)
+ */
+export async function getWalletBalance(address) {
+  const query = address ? `?address=${encodeURIComponent(address)}` : '';
+  return apiRequest(`/wallet/balance${query}`);
+}
+
+/**
+ * Get the all-in fee for creating or filling an order (commit + spell transactions)
+ * @param {Object} [options] - Optional parameters
+ * @param {number} [options.target] - Confirmation target in blocks (default 6)
+ * @param {number} [options.vbytes] - Also price a custom transaction of this size
+ */
+export async function getWalletFeeEstimate(options = {}) {
+  const params = new URLSearchParams();
+  if (options.target) params.append('target', options.target);
+  if (options.vbytes) params.append('vbytes', options.vbytes);
+
+  const query = params.toString();
+  return apiRequest(`/wallet/fee-estimate${query ? `?${query}` : ''}`);
+}
+
+/**
+ * Get the session wallets' transaction history with charm effects (requires a session)
+ * @param {Object} [page] - Pagination
+ * @param {number} [page.limit] - Page size (default 50)
+ * @param {number} [page.offset] - Entries to skip
+ */
+export async function getWalletHistory(page = {}) {
+  const params = new URLSearchParams();
+  if (page.limit) params.append('limit', page.limit);
+  if (page.offset) params.append('offset', page.offset);
+
+  const query = params.toString();
+  return apiRequest(`/wallet/history${query ? `?${query}` : ''}`);
+}
+
+/**
+ * Get wallet UTXOs annotated with charms and order reservations
+ * @param {Object} [filters] - Optional filters
+ * @param {string} [filters.address] - Only UTXOs at this address
+ * @param {number} [filters.minConf] - Minimum confirmations
+ */
+export async function getWalletUtxos(filters = {}) {
+  const params = new URLSearchParams();
+  if (filters.address) params.append('address', filters.address);
+  if (filters.minConf !== undefined) params.append('min_conf', filters.minConf);
+
+  const query = params.toString();
+  return apiRequest(`/wallet/utxos${query ? `?${query}` : ''}`);
+}
+
+/**
+ * Build a transaction sweeping the session wallets' small UTXOs into one output
+ * (charm-bearing, reserved and locked UTXOs are skipped)
+ * @param {Object} [options] - Optional parameters
+ * @param {string} [options.destination] - Receiving address (defaults to the connected wallet)
+ * @param {number} [options.feeRate] - sat/vB (defaults to the economy rate)
+ * @param {number} [options.maxUtxoValue] - Only sweep UTXOs below this many sats
+ * @param {number} [options.minConf] - Minimum confirmations (default 1)
+ */
+export async function consolidateUtxos(options = {}) {
+  return apiRequest('/wallet/utxos/consolidate', {
+    method: 'POST',
+    body: JSON.stringify({
+      destination: options.destination,
+      fee_rate: options.feeRate,
+      max_utxo_value: options.maxUtxoValue,
+      min_conf: options.minConf,
+    }),
+  });
+}
+
+/**
+ * Lock a UTXO so order and escrow drafts cannot claim it (requires a session)
+ * @param {string} outpoint - UTXO as txid:vout
+ * @param {number} [ttlSecs] - Lock lifetime in seconds (default 30 minutes)
+ */
+export async function lockUtxo(outpoint, ttlSecs) {
+  return apiRequest(`/wallet/utxos/${encodeURIComponent(outpoint)}/lock`, {
+    method: 'POST',
+    body: JSON.stringify(ttlSecs ? { ttl_secs: ttlSecs } : {}),
+  });
+}
+
+/**
+ * Release a UTXO lock held by the session's wallets
+ * @param {string} outpoint - UTXO as txid:vout
+ */
+export async function unlockUtxo(outpoint) {
+  return apiRequest(`/wallet/utxos/${encodeURIComponent(outpoint)}/lock`, {
+    method: 'DELETE',
+  });
+}
+
+/**
+ * List active UTXO locks held by the session's wallets
+ */
+export async function listUtxoLocks() {
+  return apiRequest('/wallet/utxos/locks');
+}
+
+/**
+ * Build a child-pays-for-parent transaction speeding up an unconfirmed
+ * transaction that cannot be replaced; the child spends one of its outputs
+ * held by the session's wallets (usually the change)
+ * @param {string} txid - Unconfirmed parent transaction
+ * @param {Object} [options] - Optional parameters
+ * @param {number} [options.vout] - Parent output to spend (defaults to the first one the session holds)
+ * @param {string} [options.destination] - Receiving address (defaults to the connected wallet)
+ * @param {number} [options.feeRate] - sat/vB for parent and child together (defaults to the estimate for feeTier)
+ * @param {string} [options.feeTier] - 'fast', 'normal' or 'economy' (default 'normal')
+ */
+export async function buildCpfp(txid, options = {}) {
+  return apiRequest('/wallet/cpfp', {
+    method: 'POST',
+    body: JSON.stringify({
+      txid,
+      vout: options.vout,
+      destination: options.destination,
+      fee_rate: options.feeRate,
+      fee_tier: options.feeTier,
+    }),
+  });
+}
+
+/**
+ * Convert prover transactions into PSBTs for wallet signing
+ * @param {string[]} txs - Raw transaction hex in spending order (commit, spell)
+ * @param {string[]} [prevTxs] - Raw previous transactions the node may not know
+ * @param {Object} [keyOrigins] - Key origins by input index, for hardware signers
+ * @returns {Promise<Object>} PSBTs with per-input metadata, fees and summaries
+ */
+export async function createPsbt(txs, prevTxs = [], keyOrigins = {}) {
+  return apiRequest('/wallet/psbt', {
+    method: 'POST',
+    body: JSON.stringify({ txs, prev_txs: prevTxs, key_origins: keyOrigins }),
+  });
+}
+
+/**
+ * Combine co-signers' partially signed PSBTs and finalize them
+ * @param {string[]} psbts - Base64 PSBTs of the same transaction, one per signer
+ * @returns {Promise<Object>} Combined PSBT, whether it is complete, inputs still
+ *   unsigned and, when complete, the transaction hex to broadcast
+ */
+export async function finalizePsbt(psbts) {
+  return apiRequest('/wallet/psbt/finalize', {
+    method: 'POST',
+    body: JSON.stringify({ psbts }),
+  });
+}
+
+/**
+ * Register an xpub or public descriptor to watch (requires a session)
+ * @param {Object} watchData - Watch-only wallet data
+ * @param {string} [watchData.xpub] - Extended public key (xpub/tpub)
+ * @param {string} [watchData.descriptor] - Output descriptor (instead of xpub)
+ * @param {string} [watchData.label] - Display label
+ * @param {number} [watchData.range] - Last derivation index to track
+ * @param {number} [watchData.birthTime] - Unix time to rescan from
+ */
+export async function watchWallet(watchData) {
+  return apiRequest('/wallet/watch', {
+    method: 'POST',
+    body: JSON.stringify({
+      xpub: watchData.xpub,
+      descriptor: watchData.descriptor,
+      label: watchData.label,
+      range: watchData.range,
+      birth_time: watchData.birthTime,
+    }),
+  });
+}
+
+/**
+ * List watch-only wallets registered by the connected wallet
+ */
+export async function listWatchedWallets() {
+  return apiRequest('/wallet/watch');
+}
+
+/**
+ * Stop watching a wallet
+ * @param {string} walletId - Watched wallet ID
+ */
+export async function unwatchWallet(walletId) {
+  return apiRequest(`/wallet/watch/${walletId}`, {
+    method: 'DELETE',
+  });
+}
+
+/**
+ * Get the balance of a watch-only wallet
+ * @param {string} walletId - Watched wallet ID
+ */
+export async function getWatchedWalletBalance(walletId) {
+  return apiRequest(`/wallet/watch/${walletId}/balance`);
+}
+
+/**
+ * Get the UTXOs of a watch-only wallet
+ * @param {string} walletId - Watched wallet ID
+ */
+export async function getWatchedWalletUtxos(walletId) {
+  return apiRequest(`/wallet/watch/${walletId}/utxos`);
+}
+
+/**
+ * Subscribe to deposits at an address. New funds or charms arriving there are
+ * published as `address.deposit` events (see subscribeToAddressEvents).
+ * @param {string} address - Address to watch
+ * @param {Object} [options] - Optional parameters
+ * @param {string} [options.label] - Display label
+ */
+export async function subscribeAddress(address, options = {}) {
+  return apiRequest('/wallet/subscriptions', {
+    method: 'POST',
+    body: JSON.stringify({
+      address,
+      label: options.label,
+    }),
+  });
+}
+
+/**
+ * List address subscriptions of the connected wallets
+ */
+export async function listAddressSubscriptions() {
+  return apiRequest('/wallet/subscriptions');
+}
+
+/**
+ * Get the deposits seen for an address subscription
+ * @param {string} subscriptionId - Subscription ID
+ */
+export async function getSubscriptionDeposits(subscriptionId) {
+  return apiRequest(`/wallet/subscriptions/${subscriptionId}/deposits`);
+}
+
+/**
+ * Cancel an address subscription
+ * @param {string} subscriptionId - Subscription ID
+ */
+export async function unsubscribeAddress(subscriptionId) {
+  return apiRequest(`/wallet/subscriptions/${subscriptionId}`, {
+    method: 'DELETE',
+  });
+}
+
+/**
+ * Open a WebSocket receiving events about one address (deposits) or order/escrow,
+ * including `prove.queued` / `prove.proving` / `prove.finalizing` while its spell is proved
+ * @param {string} subject - Address, order ID or escrow ID
+ * @param {Function} onEvent - Called with each parsed event
+ * @returns {WebSocket} The socket; call close() to stop
+ */
+export function subscribeToAddressEvents(subject, onEvent) {
+  const wsBase = API_BASE_URL.replace(/^http/, 'ws');
+  const socket = new WebSocket(`${wsBase}/events/ws?subject=${encodeURIComponent(subject)}`);
+  socket.onmessage = (message) => {
+    try {
+      onEvent(JSON.parse(message.data));
+    } catch (err) {
+      console.error('Invalid event message:', err);
+    }
+  };
+  return socket;
 }
 
 /**
@@ -221,20 +752,90 @@ export async function getNewAddress() {
   return apiRequest('/wallet/address');
 }
 
+/**
+ * Derive the taproot address an order's offer is locked at
+ * @param {string} orderId - Order ID
+ * @param {string} makerAddress - Maker's address (used as the key if taproot)
+ * @param {string} [makerPubkey] - Maker's public key (hex)
+ * @returns {Promise<Object>} Address, internal key, merkle root and leaf scripts
+ */
+export async function getOrderEscrowAddress(orderId, makerAddress, makerPubkey) {
+  const params = new URLSearchParams({ order_id: orderId, maker_address: makerAddress });
+  if (makerPubkey) params.append('maker_pubkey', makerPubkey);
+  return apiRequest(`/wallet/escrow-address?${params.toString()}`);
+}
+
 // ============================================
 // Spell / Transaction Operations
 // ============================================
 
 /**
- * Prove a spell and generate transactions
+ * Quote an operation before starting it: expected proving time (from recent
+ * prove jobs), commit + spell transaction sizes and the fee in sats
+ * @param {string} operation - e.g. 'create-order', 'fill-order', 'create-escrow', 'release-escrow'
+ * @param {number} [target] - Confirmation target in blocks (default 6)
+ */
+export async function estimateSpell(operation, target) {
+  const params = new URLSearchParams({ operation });
+  if (target) params.append('target', target);
+  return apiRequest(`/spells/estimate?${params.toString()}`);
+}
+
+/**
+ * Decode the spell embedded in a raw transaction, e.g. before signing it.
+ * Returns `{ txid, version, apps, public_inputs, ins, refs, outs: [{ index, charms }] }`
+ * @param {string} txHex - Raw transaction hex
+ */
+export async function decodeSpell(txHex) {
+  return apiRequest('/spells/decode', {
+    method: 'POST',
+    body: JSON.stringify({ tx_hex: txHex }),
+  });
+}
+
+/**
+ * Dry-run a built spell against the swap/escrow contracts without proving.
+ * Returns `{ passed, apps: [{ key, app, contract, status, reason }] }`, where
+ * `reason` is the contract condition that failed.
+ * @param {string} spellYaml - Built spell YAML
+ * @param {string} [contract] - 'swap' or 'escrow'; needed while both apps share a verification key
+ */
+export async function checkSpell(spellYaml, contract) {
+  return apiRequest('/spells/check', {
+    method: 'POST',
+    body: JSON.stringify({ spell_yaml: spellYaml, contract }),
+  });
+}
+
+/**
+ * Upload an app binary once and reference it by hash in proveSpell's
+ * `binaryRefs`, instead of embedding it in every request. Requires a
+ * connected wallet; uploads count against the user's storage quota.
+ * @param {ArrayBuffer|Blob|Uint8Array} binary - WebAssembly app binary
+ * @returns {Promise<{hash: string, size: number}>}
+ */
+export async function uploadAppBinary(binary) {
+  return apiRequest('/spells/binaries', {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/octet-stream' },
+    body: binary,
+  });
+}
+
+/**
+ * Queue a spell for proving. Returns `{ job_id, status, poll_url, events_url }`
+ * right away; use getProveJob or watchProveJob for the result.
  * @param {Object} spellData - Spell data
  * @param {string} spellData.spellYaml - Spell YAML content
- * @param {string} spellData.appBinary - App binary (base64)
+ * @param {string} [spellData.appBinary] - App binary (base64)
+ * @param {string} [spellData.appVk] - Verification key of the app binary
+ * @param {Object} [spellData.binaryRefs] - Uploaded binaries as `{ [vk]: hash }` (see uploadAppBinary)
  * @param {Array} spellData.prevTxs - Previous transactions
  * @param {string} spellData.fundingUtxo - Funding UTXO
  * @param {number} spellData.fundingUtxoValue - Funding UTXO value in sats
  * @param {string} spellData.changeAddress - Change address
- * @param {number} spellData.feeRate - Fee rate in sat/vB
+ * @param {number} [spellData.feeRate] - Fee rate in sat/vB (defaults to the current estimate for feeTier)
+ * @param {string} [spellData.feeTier] - 'fast', 'normal' or 'economy' (default 'normal')
  */
 export async function proveSpell(spellData) {
   return apiRequest('/spells/prove', {
@@ -242,50 +843,154 @@ export async function proveSpell(spellData) {
     body: JSON.stringify({
       spell_yaml: spellData.spellYaml,
       app_binary: spellData.appBinary,
+      app_vk: spellData.appVk,
+      binary_refs: spellData.binaryRefs,
       prev_txs: spellData.prevTxs,
       funding_utxo: spellData.fundingUtxo,
       funding_utxo_value: spellData.fundingUtxoValue,
       change_address: spellData.changeAddress,
       fee_rate: spellData.feeRate,
+      fee_tier: spellData.feeTier,
     }),
   });
 }
 
 /**
- * Broadcast signed transactions
+ * Get a prove job's status; `transactions` is set once it has succeeded,
+ * `error` and `error_code` (e.g. `contract_check_failed`) once it has failed
+ * @param {string} jobId - Job ID from proveSpell
+ */
+export async function getProveJob(jobId) {
+  return apiRequest(`/spells/jobs/${jobId}`);
+}
+
+/**
+ * Follow a prove job with server-sent events until it succeeds or fails
+ * @param {string} jobId - Job ID from proveSpell
+ * @param {Function} onUpdate - Called with the job on every status change
+ * @param {Function} [onProgress] - Called with `{ stage, prover, attempt, elapsed_secs }`
+ *   while proving (stage is queued, proving or finalizing)
+ * @returns {EventSource} The event source; call close() to stop early
+ */
+export function watchProveJob(jobId, onUpdate, onProgress) {
+  const source = new EventSource(`${API_BASE_URL}/spells/jobs/${jobId}/events`);
+  const handle = (message) => {
+    const job = JSON.parse(message.data);
+    onUpdate(job);
+    if (job.status === 'succeeded' || job.status === 'failed') {
+      source.close();
+    }
+  };
+  ['queued', 'running', 'succeeded', 'failed'].forEach((status) => {
+    source.addEventListener(status, handle);
+  });
+  if (onProgress) {
+    source.addEventListener('progress', (message) => onProgress(JSON.parse(message.data)));
+  }
+  return source;
+}
+
+/**
+ * Broadcast the signed transactions of a prove job; each must be one the job proved
+ * @param {string} jobId - Job ID from proveSpell
  * @param {Array<string>} signedTxs - Array of signed transaction hex strings
  */
-export async function broadcastTransactions(signedTxs) {
+export async function broadcastTransactions(jobId, signedTxs) {
   return apiRequest('/spells/broadcast', {
     method: 'POST',
     body: JSON.stringify({
+      job_id: jobId,
       signed_txs: signedTxs,
     }),
   });
 }
 
 /**
- * Get transaction status
+ * Get transaction status: `{ status, confirmations, block_height, block_hash,
+ * charms_created, charms_consumed, spv_proof }`, where status is mempool or
+ * confirmed; confirmed transactions carry the block header and BIP37 merkle
+ * block proving their inclusion
  * @param {string} txid - Transaction ID
  */
 export async function getTransactionStatus(txid) {
   return apiRequest(`/spells/status/${txid}`);
 }
 
+/**
+ * Decode a transaction the backend's chain source knows: inputs with the
+ * values and charms they spend, outputs with their charms, fee and fee rate
+ * @param {string} txid - Transaction ID
+ */
+export async function getDecodedTransaction(txid) {
+  return apiRequest(`/tx/${txid}/decoded`);
+}
+
+/**
+ * Decode a raw transaction, e.g. to verify it before signing or broadcasting
+ * @param {string} txHex - Raw transaction hex
+ */
+export async function decodeTransaction(txHex) {
+  return apiRequest('/tx/decode', {
+    method: 'POST',
+    body: JSON.stringify({ tx_hex: txHex }),
+  });
+}
+
+// ============================================
+// Indexed Charms
+// ============================================
+
+/**
+ * List live charms indexed from chain. Returns `{ charms: [{ utxo_id, app, tag,
+ * app_id, vk, address, value, data, block_height }], indexed_height, limit, offset }`
+ * @param {Object} [filters] - `{ appId, address, tag, limit, offset }`; tag is 't' or 'n'
+ */
+export async function listCharms(filters = {}) {
+  const params = new URLSearchParams();
+  if (filters.appId) params.append('app_id', filters.appId);
+  if (filters.address) params.append('address', filters.address);
+  if (filters.tag) params.append('tag', filters.tag);
+  if (filters.limit) params.append('limit', filters.limit);
+  if (filters.offset) params.append('offset', filters.offset);
+  return apiRequest(`/charms?${params.toString()}`);
+}
+
+/**
+ * Charms held by one live UTXO: `{ utxo_id, address, value, block_height,
+ * charms: { [app]: data }, indexed_height }`; 404 once spent
+ * @param {string} utxoId - `txid:vout`
+ */
+export async function getCharmUtxo(utxoId) {
+  return apiRequest(`/charms/${encodeURIComponent(utxoId)}`);
+}
+
 // ============================================
 // Escrow Operations
 // ============================================
 
 /**
- * List all escrows with optional filters
+ * List escrows with optional filters, newest first
  * @param {Object} filters - Filter parameters
+ * @param {string} filters.status - Escrow status (e.g. 'active', 'disputed')
+ * @param {string} filters.tokenId - Held token ID
+ * @param {string} filters.depositor - Depositor's public key
+ * @param {string} filters.recipient - Recipient's public key
+ * @param {number} filters.createdAfter - Unix timestamp lower bound
+ * @param {number} filters.createdBefore - Unix timestamp upper bound
+ * @param {number} filters.limit - Page size
+ * @param {string} filters.cursor - `next_cursor` from the previous page
  */
 export async function listEscrows(filters = {}) {
   const params = new URLSearchParams();
   
   if (filters.status) params.append('status', filters.status);
+  if (filters.tokenId) params.append('token_id', filters.tokenId);
   if (filters.depositor) params.append('depositor', filters.depositor);
   if (filters.recipient) params.append('recipient', filters.recipient);
+  if (filters.createdAfter) params.append('created_after', filters.createdAfter);
+  if (filters.createdBefore) params.append('created_before', filters.createdBefore);
+  if (filters.limit) params.append('limit', filters.limit);
+  if (filters.cursor) params.append('cursor', filters.cursor);
   
   const query = params.toString();
   return apiRequest(`/escrows${query ? `?${query}` : ''}`);
@@ -299,6 +1004,15 @@ export async function getEscrow(escrowId) {
   return apiRequest(`/escrows/${escrowId}`);
 }
 
+/**
+ * Get the taproot script tree an escrow output is locked at (cooperative,
+ * arbitrated, hashlock and refund leaves with their control blocks)
+ * @param {string} escrowId - Escrow ID
+ */
+export async function getEscrowScript(escrowId) {
+  return apiRequest(`/escrows/${escrowId}/script`);
+}
+
 /**
  * Create a new escrow
  * @param {Object} escrowData - Escrow creation data
@@ -311,6 +1025,10 @@ export async function getEscrow(escrowId) {
  * @param {string} escrowData.releaseHash - Optional hash for conditional release
  * @param {number} escrowData.expiryHeight - Block height when escrow expires
  * @param {string} escrowData.orderId - Optional associated order ID
+ * @param {string} escrowData.fundingUtxo - UTXO holding the tokens to escrow
+ * @param {number} escrowData.fundingUtxoValue - Value of the funding UTXO in sats
+ * @param {string} escrowData.depositorAddress - Depositor's address for change
+ * @param {Array<{amount: number, description: string}>} [escrowData.milestones] - Optional installments summing to amount
  */
 export async function createEscrow(escrowData) {
   return apiRequest('/escrows', {
@@ -325,6 +1043,138 @@ export async function createEscrow(escrowData) {
       release_hash: escrowData.releaseHash,
       expiry_height: escrowData.expiryHeight,
       order_id: escrowData.orderId,
+      funding_utxo: escrowData.fundingUtxo,
+      funding_utxo_value: escrowData.fundingUtxoValue,
+      depositor_address: escrowData.depositorAddress,
+      milestones: escrowData.milestones,
+    }),
+  });
+}
+
+/**
+ * List escrow templates
+ */
+export async function listEscrowTemplates() {
+  return apiRequest('/escrows/templates');
+}
+
+/**
+ * Create an escrow template
+ * @param {Object} template - Template settings
+ * @param {string} template.name - Display name
+ * @param {string} template.escrowType - 'TwoParty', 'TwoOfTwo', or 'TwoOfThree'
+ * @param {string} template.arbiterPubkey - Optional arbiter's public key
+ * @param {number} template.expiryBlocks - Blocks from creation until expiry
+ * @param {number} template.feeBps - Marketplace fee in basis points
+ * @param {string} template.feeRecipient - Address receiving the fee
+ * @param {number} template.depositorFeeShareBps - Share of the fee paid by the depositor
+ */
+export async function createEscrowTemplate(template) {
+  return apiRequest('/escrows/templates', {
+    method: 'POST',
+    body: JSON.stringify({
+      name: template.name,
+      escrow_type: template.escrowType,
+      arbiter_pubkey: template.arbiterPubkey,
+      expiry_blocks: template.expiryBlocks,
+      fee_bps: template.feeBps,
+      fee_recipient: template.feeRecipient,
+      depositor_fee_share_bps: template.depositorFeeShareBps,
+    }),
+  });
+}
+
+/**
+ * Create an escrow from a template in one call
+ * @param {string} templateId - Template ID
+ * @param {Object} escrowData - Escrow data (parties, token, amount, funding)
+ */
+export async function instantiateEscrowTemplate(templateId, escrowData) {
+  return apiRequest(`/escrows/templates/${templateId}/instantiate`, {
+    method: 'POST',
+    body: JSON.stringify({
+      depositor_pubkey: escrowData.depositorPubkey,
+      recipient_pubkey: escrowData.recipientPubkey,
+      token_id: escrowData.tokenId,
+      amount: escrowData.amount,
+      release_hash: escrowData.releaseHash,
+      order_id: escrowData.orderId,
+      funding_utxo: escrowData.fundingUtxo,
+      funding_utxo_value: escrowData.fundingUtxoValue,
+      depositor_address: escrowData.depositorAddress,
+      milestones: escrowData.milestones,
+    }),
+  });
+}
+
+/**
+ * Propose an escrow; it is only created once the recipient (and arbiter) accept
+ * @param {Object} proposal - Escrow terms (same fields as createEscrow) plus signature
+ * @param {string} proposal.signature - Depositor's signature over the propose payload
+ */
+export async function proposeEscrow(proposal) {
+  return apiRequest('/escrows/proposals', {
+    method: 'POST',
+    body: JSON.stringify({
+      depositor_pubkey: proposal.depositorPubkey,
+      recipient_pubkey: proposal.recipientPubkey,
+      arbiter_pubkey: proposal.arbiterPubkey,
+      escrow_type: proposal.escrowType,
+      token_id: proposal.tokenId,
+      amount: proposal.amount,
+      release_hash: proposal.releaseHash,
+      expiry_height: proposal.expiryHeight,
+      order_id: proposal.orderId,
+      signature: proposal.signature,
+    }),
+  });
+}
+
+/**
+ * List escrow proposals, optionally only those involving a party
+ * @param {string} pubkey - Optional party public key
+ */
+export async function listEscrowProposals(pubkey) {
+  const query = pubkey ? `?pubkey=${encodeURIComponent(pubkey)}` : '';
+  return apiRequest(`/escrows/proposals${query}`);
+}
+
+/**
+ * Get an escrow proposal by ID
+ * @param {string} proposalId - Proposal ID
+ */
+export async function getEscrowProposal(proposalId) {
+  return apiRequest(`/escrows/proposals/${proposalId}`);
+}
+
+/**
+ * Accept an escrow proposal (recipient or arbiter)
+ * @param {string} proposalId - Proposal ID
+ * @param {string} signerPubkey - Accepting party's public key
+ * @param {string} signature - Signature over the accept payload
+ */
+export async function acceptEscrowProposal(proposalId, signerPubkey, signature) {
+  return apiRequest(`/escrows/proposals/${proposalId}/accept`, {
+    method: 'POST',
+    body: JSON.stringify({ signer_pubkey: signerPubkey, signature }),
+  });
+}
+
+/**
+ * Build the creation spell for an accepted proposal
+ * @param {string} proposalId - Proposal ID
+ * @param {Object} fundingData - Funding data
+ * @param {string} fundingData.fundingUtxo - UTXO holding the tokens to escrow
+ * @param {number} fundingData.fundingUtxoValue - Value of the funding UTXO in sats
+ * @param {string} fundingData.depositorAddress - Depositor's address for change
+ */
+export async function createEscrowFromProposal(proposalId, fundingData) {
+  return apiRequest(`/escrows/proposals/${proposalId}/create`, {
+    method: 'POST',
+    body: JSON.stringify({
+      funding_utxo: fundingData.fundingUtxo,
+      funding_utxo_value: fundingData.fundingUtxoValue,
+      depositor_address: fundingData.depositorAddress,
     }),
   });
 }
@@ -336,6 +1186,10 @@ export async function createEscrow(escrowData) {
  * @param {string} releaseData.preimage - Preimage for hash-locked release
  * @param {string} releaseData.signature - Release signature
  * @param {string} releaseData.signerPubkey - Signer's public key
+ * @param {string} releaseData.recipientAddress - Address receiving the tokens
+ * @param {string} releaseData.fundingUtxo - UTXO paying the transaction fee
+ * @param {number} releaseData.fundingUtxoValue - Value of the funding UTXO in sats
+ * @param {string} releaseData.changeAddress - Signer's address for change
  */
 export async function releaseEscrow(escrowId, releaseData) {
   return apiRequest(`/escrows/${escrowId}/release`, {
@@ -344,6 +1198,37 @@ export async function releaseEscrow(escrowId, releaseData) {
       preimage: releaseData.preimage,
       signature: releaseData.signature,
       signer_pubkey: releaseData.signerPubkey,
+      recipient_address: releaseData.recipientAddress,
+      funding_utxo: releaseData.fundingUtxo,
+      funding_utxo_value: releaseData.fundingUtxoValue,
+      change_address: releaseData.changeAddress,
+    }),
+  });
+}
+
+/**
+ * Claim a hash-locked escrow by revealing the preimage
+ * @param {string} escrowId - Escrow ID
+ * @param {Object} claimData - Claim data
+ * @param {string} claimData.preimage - Hex-encoded preimage of the release hash
+ * @param {string} claimData.claimerPubkey - Recipient's public key
+ * @param {string} claimData.signature - Claim signature
+ * @param {string} claimData.recipientAddress - Address receiving the tokens
+ * @param {string} claimData.fundingUtxo - UTXO paying the transaction fee
+ * @param {number} claimData.fundingUtxoValue - Value of the funding UTXO in sats
+ * @param {string} claimData.changeAddress - Claimer's address for change
+ */
+export async function claimEscrow(escrowId, claimData) {
+  return apiRequest(`/escrows/${escrowId}/claim`, {
+    method: 'POST',
+    body: JSON.stringify({
+      preimage: claimData.preimage,
+      claimer_pubkey: claimData.claimerPubkey,
+      signature: claimData.signature,
+      recipient_address: claimData.recipientAddress,
+      funding_utxo: claimData.fundingUtxo,
+      funding_utxo_value: claimData.fundingUtxoValue,
+      change_address: claimData.changeAddress,
     }),
   });
 }
@@ -354,6 +1239,9 @@ export async function releaseEscrow(escrowId, releaseData) {
  * @param {Object} refundData - Refund data
  * @param {string} refundData.reason - Refund reason
  * @param {string} refundData.signature - Refund signature
+ * @param {string} refundData.depositorAddress - Address receiving the tokens
+ * @param {string} refundData.fundingUtxo - UTXO paying the transaction fee
+ * @param {number} refundData.fundingUtxoValue - Value of the funding UTXO in sats
  */
 export async function refundEscrow(escrowId, refundData) {
   return apiRequest(`/escrows/${escrowId}/refund`, {
@@ -361,10 +1249,25 @@ export async function refundEscrow(escrowId, refundData) {
     body: JSON.stringify({
       reason: refundData.reason,
       signature: refundData.signature,
+      depositor_address: refundData.depositorAddress,
+      funding_utxo: refundData.fundingUtxo,
+      funding_utxo_value: refundData.fundingUtxoValue,
     }),
   });
 }
 
+/**
+ * Broadcast a signed escrow transaction
+ * @param {string} escrowId - Escrow ID
+ * @param {string} signedTxHex - Signed transaction hex
+ */
+export async function broadcastEscrow(escrowId, signedTxHex) {
+  return apiRequest(`/escrows/${escrowId}/broadcast`, {
+    method: 'POST',
+    body: JSON.stringify({ signed_tx_hex: signedTxHex }),
+  });
+}
+
 /**
  * Initiate dispute on escrow
  * @param {string} escrowId - Escrow ID
@@ -372,6 +1275,10 @@ export async function refundEscrow(escrowId, refundData) {
  * @param {string} disputeData.reason - Dispute reason
  * @param {string} disputeData.evidenceHash - Optional evidence hash
  * @param {string} disputeData.initiatorPubkey - Initiator's public key
+ * @param {string} disputeData.signature - Initiator's signature over the dispute payload
+ * @param {string} [disputeData.fundingUtxo] - UTXO paying the fee to record the dispute on-chain
+ * @param {number} [disputeData.fundingUtxoValue] - Value of the funding UTXO in sats
+ * @param {string} [disputeData.changeAddress] - Change address for the funding UTXO
  */
 export async function disputeEscrow(escrowId, disputeData) {
   return apiRequest(`/escrows/${escrowId}/dispute`, {
@@ -380,6 +1287,10 @@ export async function disputeEscrow(escrowId, disputeData) {
       reason: disputeData.reason,
       evidence_hash: disputeData.evidenceHash,
       initiator_pubkey: disputeData.initiatorPubkey,
+      signature: disputeData.signature,
+      funding_utxo: disputeData.fundingUtxo,
+      funding_utxo_value: disputeData.fundingUtxoValue,
+      change_address: disputeData.changeAddress,
     }),
   });
 }
@@ -389,7 +1300,11 @@ export async function disputeEscrow(escrowId, disputeData) {
  * @param {string} escrowId - Escrow ID
  * @param {Object} resolveData - Resolution data
  * @param {string} resolveData.winner - 'depositor' or 'recipient'
- * @param {string} resolveData.arbiterSignature - Arbiter's signature
+ * @param {string} resolveData.arbiterSignature - Arbiter's signature over the resolve payload
+ * @param {string} resolveData.winnerAddress - Address receiving the escrowed tokens
+ * @param {string} resolveData.fundingUtxo - Arbiter's UTXO paying the fee
+ * @param {number} [resolveData.fundingUtxoValue] - Value of the funding UTXO in sats
+ * @param {string} resolveData.changeAddress - Arbiter's change address
  */
 export async function resolveDispute(escrowId, resolveData) {
   return apiRequest(`/escrows/${escrowId}/resolve`, {
@@ -397,24 +1312,182 @@ export async function resolveDispute(escrowId, resolveData) {
     body: JSON.stringify({
       winner: resolveData.winner,
       arbiter_signature: resolveData.arbiterSignature,
+      winner_address: resolveData.winnerAddress,
+      funding_utxo: resolveData.fundingUtxo,
+      funding_utxo_value: resolveData.fundingUtxoValue,
+      change_address: resolveData.changeAddress,
+    }),
+  });
+}
+
+/**
+ * Submit dispute evidence (needs a session holding the submitter's key)
+ * @param {string} escrowId - Escrow ID
+ * @param {Object} evidence - Evidence data
+ * @param {string} evidence.submitterPubkey - Submitting party's public key
+ * @param {string} evidence.contentHash - SHA-256 of the document (hex)
+ * @param {string} evidence.storageRef - Object storage reference for the document
+ * @param {string} evidence.contentType - Optional MIME type
+ * @param {string} evidence.description - Optional description
+ */
+export async function submitEvidence(escrowId, evidence) {
+  return apiRequest(`/escrows/${escrowId}/evidence`, {
+    method: 'POST',
+    body: JSON.stringify({
+      submitter_pubkey: evidence.submitterPubkey,
+      content_hash: evidence.contentHash,
+      storage_ref: evidence.storageRef,
+      content_type: evidence.contentType,
+      description: evidence.description,
+    }),
+  });
+}
+
+/**
+ * List dispute evidence visible to the session: all of it for the arbiter,
+ * otherwise what the session's keys submitted
+ * @param {string} escrowId - Escrow ID
+ */
+export async function getEvidence(escrowId) {
+  return apiRequest(`/escrows/${escrowId}/evidence`);
+}
+
+/**
+ * List the milestones of an escrow
+ * @param {string} escrowId - Escrow ID
+ */
+export async function listEscrowMilestones(escrowId) {
+  return apiRequest(`/escrows/${escrowId}/milestones`);
+}
+
+/**
+ * Release one milestone of an escrow (depositor only)
+ * @param {string} escrowId - Escrow ID
+ * @param {number} index - Milestone index
+ * @param {Object} releaseData - Release data
+ * @param {string} releaseData.signature - Depositor's signature over the release_milestone payload
+ * @param {string} releaseData.recipientAddress - Recipient's address
+ * @param {string} releaseData.fundingUtxo - UTXO paying the fee
+ * @param {number} [releaseData.fundingUtxoValue] - Value of the funding UTXO in sats
+ * @param {string} releaseData.changeAddress - Change address
+ */
+export async function releaseEscrowMilestone(escrowId, index, releaseData) {
+  return apiRequest(`/escrows/${escrowId}/milestones/${index}/release`, {
+    method: 'POST',
+    body: JSON.stringify({
+      signature: releaseData.signature,
+      recipient_address: releaseData.recipientAddress,
+      funding_utxo: releaseData.fundingUtxo,
+      funding_utxo_value: releaseData.fundingUtxoValue,
+      change_address: releaseData.changeAddress,
+    }),
+  });
+}
+
+/**
+ * Create an escrow derived from an existing order
+ * @param {string} orderId - Order ID
+ * @param {Object} escrowData - Escrow data
+ * @param {string} escrowData.depositorPubkey - Maker's public key
+ * @param {string} escrowData.recipientPubkey - Recipient's public key
+ * @param {string} escrowData.recipientAddress - Recipient's address
+ * @param {string} escrowData.arbiterPubkey - Optional arbiter's public key
+ * @param {string} escrowData.releaseHash - Hashlock (required for cross-chain orders)
+ * @param {number} escrowData.expiryHeight - Optional override of the order's expiry
+ * @param {string} escrowData.fundingUtxo - Optional funding UTXO (defaults to the order's UTXO)
+ * @param {number} escrowData.fundingUtxoValue - Value of the funding UTXO in sats
+ */
+export async function createOrderEscrow(orderId, escrowData) {
+  return apiRequest(`/orders/${orderId}/escrow`, {
+    method: 'POST',
+    body: JSON.stringify({
+      depositor_pubkey: escrowData.depositorPubkey,
+      recipient_pubkey: escrowData.recipientPubkey,
+      recipient_address: escrowData.recipientAddress,
+      arbiter_pubkey: escrowData.arbiterPubkey,
+      release_hash: escrowData.releaseHash,
+      expiry_height: escrowData.expiryHeight,
+      funding_utxo: escrowData.fundingUtxo,
+      funding_utxo_value: escrowData.fundingUtxoValue,
     }),
   });
 }
 
+/**
+ * Get the escrow created for an order
+ * @param {string} orderId - Order ID
+ */
+export async function getOrderEscrow(orderId) {
+  return apiRequest(`/orders/${orderId}/escrow`);
+}
+
+/**
+ * Query string for one page of an escrow listing
+ * @param {Object} page - Optional page
+ * @param {number} page.limit - Page size
+ * @param {string} page.cursor - `next_cursor` of the previous page
+ */
+function pageQuery(page = {}) {
+  const params = new URLSearchParams();
+  if (page.limit) params.append('limit', page.limit);
+  if (page.cursor) params.append('cursor', page.cursor);
+
+  const queryString = params.toString();
+  return queryString ? `?${queryString}` : '';
+}
+
+/**
+ * List escrows involving any public key linked to the session (requires a session)
+ * @param {Object} page - Optional page: `limit` and `cursor`
+ */
+export async function getMyEscrows(page = {}) {
+  return apiRequest(`/escrows/mine${pageQuery(page)}`);
+}
+
 /**
  * Get escrows by depositor
  * @param {string} pubkey - Depositor's public key
+ * @param {Object} page - Optional page: `limit` and `cursor`
  */
-export async function getEscrowsByDepositor(pubkey) {
-  return apiRequest(`/escrows/by-depositor/${pubkey}`);
+export async function getEscrowsByDepositor(pubkey, page = {}) {
+  return apiRequest(`/escrows/by-depositor/${pubkey}${pageQuery(page)}`);
 }
 
 /**
  * Get escrows by recipient
  * @param {string} pubkey - Recipient's public key
+ * @param {Object} page - Optional page: `limit` and `cursor`
  */
-export async function getEscrowsByRecipient(pubkey) {
-  return apiRequest(`/escrows/by-recipient/${pubkey}`);
+export async function getEscrowsByRecipient(pubkey, page = {}) {
+  return apiRequest(`/escrows/by-recipient/${pubkey}${pageQuery(page)}`);
+}
+
+/**
+ * Get escrows by arbiter
+ * @param {string} pubkey - Arbiter's public key
+ * @param {Object} page - Optional page: `limit` and `cursor`
+ */
+export async function getEscrowsByArbiter(pubkey, page = {}) {
+  return apiRequest(`/escrows/by-arbiter/${pubkey}${pageQuery(page)}`);
+}
+
+/**
+ * List disputes with their evidence, oldest first
+ * @param {Object} filters - Optional filters
+ * @param {string} filters.status - 'open' (default), 'resolved', or 'all'
+ * @param {string} filters.arbiter - Only disputes assigned to this arbiter
+ * @param {number} filters.limit - Page size
+ * @param {string} filters.cursor - `next_cursor` of the previous page
+ */
+export async function listDisputes(filters = {}) {
+  const params = new URLSearchParams();
+  if (filters.status) params.append('status', filters.status);
+  if (filters.arbiter) params.append('arbiter', filters.arbiter);
+  if (filters.limit) params.append('limit', filters.limit);
+  if (filters.cursor) params.append('cursor', filters.cursor);
+
+  const queryString = params.toString();
+  return apiRequest(`/disputes${queryString ? `?${queryString}` : ''}`);
 }
 
 // ============================================
@@ -453,37 +1526,101 @@ export function shortenAddress(address, chars = 6) {
 export default {
   // Health
   checkHealth,
+  getRpcMetrics,
+  getRpcNodes,
+  getApps,
   
   // Orders
   listOrders,
   getOrder,
+  getOrderSpellTemplates,
+  getOrderSpells,
   createOrder,
   fillOrder,
   partialFillOrder,
   cancelOrder,
+  getMyOrders,
   broadcastOrder,
+  bumpOrderFee,
   
   // Wallet
   connectWallet,
+  getSession,
+  linkWallet,
+  unlinkWallet,
+  setSessionToken,
+  setWalletFormat,
+  watchWallet,
+  listWatchedWallets,
+  unwatchWallet,
+  getWatchedWalletBalance,
+  getWatchedWalletUtxos,
+  subscribeAddress,
+  listAddressSubscriptions,
+  getSubscriptionDeposits,
+  unsubscribeAddress,
+  subscribeToAddressEvents,
   getWalletBalance,
+  getWalletFeeEstimate,
+  getWalletHistory,
   getWalletUtxos,
+  consolidateUtxos,
+  lockUtxo,
+  unlockUtxo,
+  listUtxoLocks,
+  buildCpfp,
+  createPsbt,
+  finalizePsbt,
   getNewAddress,
+  getOrderEscrowAddress,
   
   // Spells
+  estimateSpell,
+  decodeSpell,
+  checkSpell,
+  uploadAppBinary,
   proveSpell,
+  getProveJob,
+  watchProveJob,
   broadcastTransactions,
   getTransactionStatus,
+  getDecodedTransaction,
+  decodeTransaction,
+  
+  // Indexed charms
+  listCharms,
+  getCharmUtxo,
   
   // Escrows
   listEscrows,
+  getMyEscrows,
   getEscrow,
+  getEscrowScript,
   createEscrow,
+  listEscrowTemplates,
+  createEscrowTemplate,
+  instantiateEscrowTemplate,
+  proposeEscrow,
+  listEscrowProposals,
+  getEscrowProposal,
+  acceptEscrowProposal,
+  createEscrowFromProposal,
   releaseEscrow,
+  claimEscrow,
   refundEscrow,
+  broadcastEscrow,
   disputeEscrow,
   resolveDispute,
+  submitEvidence,
+  getEvidence,
+  listEscrowMilestones,
+  releaseEscrowMilestone,
+  createOrderEscrow,
+  getOrderEscrow,
   getEscrowsByDepositor,
   getEscrowsByRecipient,
+  getEscrowsByArbiter,
+  listDisputes,
   
   // Utilities
   formatBtc,